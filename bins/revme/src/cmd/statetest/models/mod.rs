@@ -60,6 +60,11 @@ pub struct TxPartIndices {
 pub struct AccountInfo {
     /// Not a balances mapping because it has to remain compatible with the upstream Ethereum tests
     pub balance: U256,
+    /// SabVM extension: the balances of the native tokens other than the base token.
+    ///
+    /// In the post state, only the listed balances are checked.
+    #[serde(default)]
+    pub balances: BTreeMap<U256, U256>,
     pub code: Bytes,
     #[serde(deserialize_with = "deserialize_str_as_u64")]
     pub nonce: u64,
@@ -100,6 +105,10 @@ pub struct TransactionParts {
     #[serde(deserialize_with = "deserialize_maybe_empty")]
     pub to: Option<Address>,
     pub value: Vec<U256>,
+    /// SabVM extension: the native tokens transferred along with the base token value,
+    /// indexed the same way as `value`.
+    #[serde(default)]
+    pub token_transfers: Vec<Vec<TokenTransferItem>>,
     pub max_fee_per_gas: Option<U256>,
     pub max_priority_fee_per_gas: Option<U256>,
 
@@ -120,6 +129,13 @@ pub struct AccessListItem {
 
 pub type AccessList = Vec<AccessListItem>;
 
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TokenTransferItem {
+    pub id: U256,
+    pub amount: U256,
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -148,6 +164,16 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn deserialize_token_balances() -> Result<(), Error> {
+        let json = r#"{"balance":"0x0a","balances":{"0x01":"0x14"},"code":"0x","nonce":"0x00","storage":{}}"#;
+
+        let info: AccountInfo = serde_json::from_str(json)?;
+        assert_eq!(info.balance, U256::from(10));
+        assert_eq!(info.balances.get(&U256::from(1)), Some(&U256::from(20)));
+        Ok(())
+    }
+
     #[test]
     pub fn serialize_b160() -> Result<(), Error> {
         let json = r#"{"_item":"0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba"}"#;
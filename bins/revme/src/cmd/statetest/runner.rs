@@ -10,8 +10,9 @@ use revm::{
     inspector_handle_register,
     inspectors::TracerEip3155,
     primitives::{
-        calc_excess_blob_gas, init_balances, keccak256, Bytecode, Bytes, EVMResultGeneric, Env,
-        ExecutionResult, SpecId, TokenTransfer, TransactTo, B256, BASE_TOKEN_ID, U256,
+        calc_excess_blob_gas, init_balances, keccak256, Address, Bytecode, Bytes, EVMResultGeneric,
+        Env, ExecutionResult, HashMap, SpecId, TokenTransfer, TransactTo, B256, BASE_TOKEN_ID,
+        U256,
     },
     Evm, InMemoryDB, State,
 };
@@ -42,6 +43,13 @@ pub enum TestErrorKind {
     LogsRootMismatch { got: B256, expected: B256 },
     #[error("state root mismatch: got {got}, expected {expected}")]
     StateRootMismatch { got: B256, expected: B256 },
+    #[error("balance mismatch of token {token_id} for {address}: got {got}, expected {expected}")]
+    TokenBalanceMismatch {
+        address: Address,
+        token_id: U256,
+        got: U256,
+        expected: U256,
+    },
     #[error("unknown private key: {0:?}")]
     UnknownPrivateKey(B256),
     #[error("unexpected exception: got {got_exception:?}, expected {expected_exception:?}")]
@@ -234,6 +242,38 @@ fn check_evm_execution<EXT>(
         });
     }
 
+    // SabVM extension: check the balances of the native tokens listed in the post state.
+    // They are not part of the state root, which only commits to the base token balance.
+    let accounts: HashMap<_, _> = evm
+        .context
+        .evm
+        .db
+        .cache
+        .trie_account()
+        .into_iter()
+        .collect();
+    for (address, expected_info) in &test.post_state {
+        for (token_id, expected) in &expected_info.balances {
+            let got = accounts
+                .get(address)
+                .map(|account| account.info.get_balance(*token_id))
+                .unwrap_or_default();
+            if got != *expected {
+                let kind = TestErrorKind::TokenBalanceMismatch {
+                    address: *address,
+                    token_id: *token_id,
+                    got,
+                    expected: *expected,
+                };
+                print_json_output(Some(kind.to_string()));
+                return Err(TestError {
+                    name: test_name.to_string(),
+                    kind,
+                });
+            }
+        }
+    }
+
     print_json_output(None);
 
     Ok(())
@@ -258,9 +298,18 @@ pub fn execute_test_suite(
     for (name, unit) in suite.0 {
         // Create database and insert cache
         let mut cache_state = revm::CacheState::new(false);
+        // The native tokens known to the database: the base token and the ones held in the pre state.
+        let mut db = InMemoryDB::default();
         for (address, info) in unit.pre {
+            let mut balances = init_balances(info.balance);
+            for (token_id, balance) in info.balances {
+                if !db.token_ids.contains(&token_id) {
+                    db.token_ids.push(token_id);
+                }
+                balances.insert(token_id, balance);
+            }
             let acc_info = revm::primitives::AccountInfo {
-                balances: init_balances(info.balance),
+                balances,
                 code_hash: keccak256(&info.code),
                 code: Some(Bytecode::new_raw(info.code)),
                 nonce: info.nonce,
@@ -343,6 +392,16 @@ pub fn execute_test_suite(
                     amount: U256::from(unit.transaction.value[test.indexes.value]),
                 };
                 env.tx.transferred_tokens = vec![token_transfer];
+                if let Some(token_transfers) =
+                    unit.transaction.token_transfers.get(test.indexes.value)
+                {
+                    env.tx
+                        .transferred_tokens
+                        .extend(token_transfers.iter().map(|item| TokenTransfer {
+                            id: item.id,
+                            amount: item.amount,
+                        }));
+                }
 
                 env.tx.access_list = unit
                     .transaction
@@ -376,7 +435,7 @@ pub fn execute_test_suite(
                 let mut state = revm::db::State::builder()
                     .with_cached_prestate(cache)
                     .with_bundle_update()
-                    .with_database(InMemoryDB::default())
+                    .with_database(db.clone())
                     .build();
 
                 let mut evm = Evm::builder()
@@ -447,7 +506,7 @@ pub fn execute_test_suite(
                 let state = revm::db::State::builder()
                     .with_cached_prestate(cache)
                     .with_bundle_update()
-                    .with_database(InMemoryDB::default())
+                    .with_database(db.clone())
                     .build();
 
                 let path = path.display();
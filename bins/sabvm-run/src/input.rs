@@ -0,0 +1,230 @@
+//! The description of a run: the hardfork, the block, the pre-state and the transaction.
+use revm::{
+    primitives::{Address, Bytecode, Bytes, SpecId, TokenTransfer, TransactTo, TxEnv, U256},
+    InMemoryDB,
+};
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+use toml_edit::{DocumentMut, Item, Table};
+
+/// A run, read from JSON or TOML.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Input {
+    /// The hardfork, e.g. `"Cancun"`, the latest one by default.
+    pub spec: Option<String>,
+    #[serde(default)]
+    pub block: BlockInput,
+    /// The accounts, by address.
+    #[serde(default)]
+    pub pre: BTreeMap<Address, AccountInput>,
+    /// Tokens registered without being held by any account.
+    #[serde(default)]
+    pub tokens: Vec<U256>,
+    pub transaction: TransactionInput,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockInput {
+    pub number: Option<U256>,
+    pub timestamp: Option<U256>,
+    pub coinbase: Option<Address>,
+    pub gas_limit: Option<U256>,
+    pub basefee: Option<U256>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountInput {
+    #[serde(default)]
+    pub nonce: u64,
+    #[serde(default)]
+    pub code: Bytes,
+    /// The balances, by token ID.
+    #[serde(default)]
+    pub balances: BTreeMap<U256, U256>,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionInput {
+    pub caller: Address,
+    /// The called account, or `None` to create a contract.
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub data: Bytes,
+    #[serde(default = "default_gas_limit")]
+    pub gas_limit: u64,
+    #[serde(default)]
+    pub gas_price: U256,
+    pub nonce: Option<u64>,
+    #[serde(default)]
+    pub transferred_tokens: Vec<TokenTransfer>,
+    #[serde(default)]
+    pub access_list: Vec<(Address, Vec<U256>)>,
+}
+
+fn default_gas_limit() -> u64 {
+    30_000_000
+}
+
+impl Input {
+    /// Reads the run from JSON.
+    pub fn from_json(input: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(input)
+    }
+
+    /// Reads the run from TOML, whose values are read like their JSON equivalents.
+    pub fn from_toml(input: &str) -> Result<Self, crate::Error> {
+        let document: DocumentMut = input.parse()?;
+        Ok(serde_json::from_value(table_to_json(document.as_table()))?)
+    }
+
+    /// Returns the hardfork to run.
+    pub fn spec_id(&self) -> SpecId {
+        self.spec.as_deref().map_or(SpecId::LATEST, SpecId::from)
+    }
+
+    /// Returns the database holding the pre-state.
+    pub fn db(&self) -> InMemoryDB {
+        let mut db = InMemoryDB::default();
+        for token_id in &self.tokens {
+            db.register_token(*token_id);
+        }
+        for (address, account) in &self.pre {
+            for (token_id, amount) in &account.balances {
+                let Ok(()) = db.fund(*address, *token_id, *amount);
+            }
+            if !account.code.is_empty() {
+                let Ok(()) = db.deploy(*address, Bytecode::new_raw(account.code.clone()));
+            }
+            if account.nonce != 0 {
+                let Ok(()) = db.load_account(*address).map(|db_account| {
+                    db_account.info.nonce = account.nonce;
+                });
+            }
+            for (slot, value) in &account.storage {
+                let Ok(()) = db.insert_account_storage(*address, *slot, *value);
+            }
+        }
+        db
+    }
+
+    /// Returns the transaction.
+    pub fn tx_env(&self) -> TxEnv {
+        let transaction = &self.transaction;
+        TxEnv {
+            caller: transaction.caller,
+            transact_to: match transaction.to {
+                Some(to) => TransactTo::Call(to),
+                None => TransactTo::Create,
+            },
+            data: transaction.data.clone(),
+            gas_limit: transaction.gas_limit,
+            gas_price: transaction.gas_price,
+            nonce: transaction.nonce,
+            transferred_tokens: transaction.transferred_tokens.clone(),
+            access_list: transaction.access_list.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn table_to_json(table: &Table) -> Value {
+    Value::Object(
+        table
+            .iter()
+            .filter_map(|(key, item)| Some((key.to_string(), item_to_json(item)?)))
+            .collect(),
+    )
+}
+
+fn item_to_json(item: &Item) -> Option<Value> {
+    match item {
+        Item::None => None,
+        Item::Value(value) => Some(value_to_json(value)),
+        Item::Table(table) => Some(table_to_json(table)),
+        Item::ArrayOfTables(tables) => {
+            Some(Value::Array(tables.iter().map(table_to_json).collect()))
+        }
+    }
+}
+
+fn value_to_json(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(string) => Value::String(string.value().clone()),
+        toml_edit::Value::Integer(integer) => Value::from(*integer.value()),
+        toml_edit::Value::Float(float) => {
+            Number::from_f64(*float.value()).map_or(Value::Null, Value::Number)
+        }
+        toml_edit::Value::Boolean(boolean) => Value::Bool(*boolean.value()),
+        toml_edit::Value::Datetime(datetime) => Value::String(datetime.value().to_string()),
+        toml_edit::Value::Array(array) => Value::Array(array.iter().map(value_to_json).collect()),
+        toml_edit::Value::InlineTable(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), value_to_json(value)))
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::primitives::{address, BASE_TOKEN_ID};
+
+    #[test]
+    fn reads_toml_like_json() {
+        let toml = r#"
+            [pre."0x00000000000000000000000000000000000000aa"]
+            nonce = 3
+            balances = { "0x0" = 1000, "0x7" = "0x10" }
+
+            [transaction]
+            caller = "0x00000000000000000000000000000000000000aa"
+            transferred_tokens = [{ id = "0x7", amount = "5" }]
+        "#;
+        let json = r#"{
+            "pre": {
+                "0x00000000000000000000000000000000000000aa": {
+                    "nonce": 3,
+                    "balances": { "0x0": 1000, "0x7": "0x10" }
+                }
+            },
+            "transaction": {
+                "caller": "0x00000000000000000000000000000000000000aa",
+                "transferred_tokens": [{ "id": "0x7", "amount": "5" }]
+            }
+        }"#;
+        let caller = address!("00000000000000000000000000000000000000aa");
+        for input in [
+            Input::from_toml(toml).unwrap(),
+            Input::from_json(json).unwrap(),
+        ] {
+            let account = &input.pre[&caller];
+            assert_eq!(account.nonce, 3);
+            assert_eq!(account.balances[&BASE_TOKEN_ID], U256::from(1000));
+            assert_eq!(account.balances[&U256::from(7)], U256::from(16));
+
+            let tx = input.tx_env();
+            assert_eq!(tx.transact_to, TransactTo::Create);
+            assert_eq!(tx.gas_limit, 30_000_000);
+            assert_eq!(
+                tx.transferred_tokens,
+                vec![TokenTransfer {
+                    id: U256::from(7),
+                    amount: U256::from(5),
+                }]
+            );
+
+            let db = input.db();
+            assert_eq!(db.accounts[&caller].info.nonce, 3);
+            assert_eq!(db.token_ids, vec![BASE_TOKEN_ID, U256::from(7)]);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Runs a transaction on a pre-state described in JSON or TOML, and prints its result and its
+//! state changes, e.g. to reproduce a bug report or the behavior of SabVM in CI.
+//!
+//! ```text
+//! sabvm-run [--trace] [--json] <FILE>
+//! ```
+//!
+//! The file is read as TOML if its extension is `.toml`, and as JSON otherwise, or from the
+//! standard input if it is `-`. See `examples/token_transfer.toml`.
+//!
+//! By default a markdown report is printed, with the calls and the token transfers if `--trace`
+//! is set. With `--json`, the result, the balance changes and the state changes are printed as
+//! JSON instead.
+mod input;
+
+use input::Input;
+use revm::{
+    db::{DatabaseRef, InMemoryDB},
+    inspector_handle_register,
+    primitives::{Address, EVMError, ResultAndState, U256},
+    report::{Report, ReportTracer},
+    Evm,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    convert::Infallible,
+    fmt::Write,
+    fs,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+const USAGE: &str = "usage: sabvm-run [--trace] [--json] <FILE>";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{USAGE}")]
+    Usage,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid TOML: {0}")]
+    Toml(#[from] toml_edit::TomlError),
+    #[error("the transaction could not be executed: {0}")]
+    Evm(#[from] EVMError<Infallible>),
+}
+
+/// The options of the command line.
+#[derive(Debug, Default)]
+struct Options {
+    trace: bool,
+    json: bool,
+    path: String,
+}
+
+impl Options {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, Error> {
+        let mut options = Self::default();
+        let mut path = None;
+        for arg in args {
+            match arg.as_str() {
+                "--trace" => options.trace = true,
+                "--json" => options.json = true,
+                _ if arg.starts_with("--") || path.is_some() => return Err(Error::Usage),
+                _ => path = Some(arg),
+            }
+        }
+        options.path = path.ok_or(Error::Usage)?;
+        Ok(options)
+    }
+}
+
+/// A change of the balance of a token held by an account.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct BalanceChange {
+    address: Address,
+    token_id: U256,
+    before: U256,
+    after: U256,
+}
+
+/// Returns the balance changes of the accounts changed by the transaction, sorted by account
+/// and token.
+fn balance_changes(db: &InMemoryDB, result_and_state: &ResultAndState) -> Vec<BalanceChange> {
+    let mut changes = Vec::new();
+    let accounts: BTreeSet<&Address> = result_and_state
+        .state
+        .accounts
+        .iter()
+        .filter(|(_, account)| account.is_touched())
+        .map(|(address, _)| address)
+        .collect();
+    for address in accounts {
+        let Ok(before) = db.basic_ref(*address);
+        let before = before.unwrap_or_default().balances;
+        let after = &result_and_state.state.accounts[address].info.balances;
+        let token_ids: BTreeSet<&U256> = before.keys().chain(after.keys()).collect();
+        for token_id in token_ids {
+            let before = before.get(token_id).copied().unwrap_or_default();
+            let after = after.get(token_id).copied().unwrap_or_default();
+            if before != after {
+                changes.push(BalanceChange {
+                    address: *address,
+                    token_id: *token_id,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// Runs the transaction, returning the output to print.
+fn run(input: &Input, options: &Options) -> Result<String, Error> {
+    let db = input.db();
+    let mut evm = Evm::builder()
+        .with_ref_db(&db)
+        .with_external_context(ReportTracer::new())
+        .with_spec_id(input.spec_id())
+        .modify_block_env(|block| {
+            let block_input = &input.block;
+            block.number = block_input.number.unwrap_or(block.number);
+            block.timestamp = block_input.timestamp.unwrap_or(block.timestamp);
+            block.coinbase = block_input.coinbase.unwrap_or(block.coinbase);
+            block.gas_limit = block_input.gas_limit.unwrap_or(block.gas_limit);
+            block.basefee = block_input.basefee.unwrap_or(block.basefee);
+        })
+        .modify_tx_env(|tx| *tx = input.tx_env())
+        .append_handler_register(inspector_handle_register)
+        .build();
+    let result_and_state = evm.transact()?;
+    let tracer = &evm.context.external;
+    let balance_changes = balance_changes(&db, &result_and_state);
+
+    if options.json {
+        let mut output = serde_json::json!({
+            "result": result_and_state.result,
+            "balance_changes": balance_changes,
+            "state": result_and_state.state,
+        });
+        if options.trace {
+            output["calls"] = tracer
+                .calls()
+                .iter()
+                .map(|call| {
+                    serde_json::json!({
+                        "depth": call.depth,
+                        "kind": call.kind.as_str(),
+                        "caller": call.caller,
+                        "target": call.target,
+                        "values": call.values,
+                        "gas_used": call.gas_used,
+                        "result": call.result.map(|result| format!("{result:?}")),
+                    })
+                })
+                .collect();
+            output["token_flows"] = tracer
+                .token_flows()
+                .iter()
+                .map(|flow| {
+                    serde_json::json!({
+                        "from": flow.from,
+                        "to": flow.to,
+                        "token_id": flow.token_id,
+                        "amount": flow.amount,
+                        "reverted": flow.reverted,
+                    })
+                })
+                .collect();
+        }
+        return Ok(serde_json::to_string_pretty(&output)? + "\n");
+    }
+
+    let mut report = Report::new(&result_and_state);
+    if options.trace {
+        report = report.with_tracer(tracer);
+    }
+    let mut output = report.to_string();
+    if !balance_changes.is_empty() {
+        output.push_str("\n## Balance changes\n\n");
+        output.push_str("| Account | Token | Before | After |\n");
+        output.push_str("|---------|-------|--------|-------|\n");
+        for change in &balance_changes {
+            let _ = writeln!(
+                output,
+                "| {} | {:#x} | {} | {} |",
+                change.address, change.token_id, change.before, change.after
+            );
+        }
+    }
+    Ok(output)
+}
+
+fn main() -> ExitCode {
+    let result = Options::parse(std::env::args().skip(1)).and_then(|options| {
+        let input = if options.path == "-" {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        } else {
+            fs::read_to_string(&options.path)?
+        };
+        let input = if options.path.ends_with(".toml") {
+            Input::from_toml(&input)?
+        } else {
+            Input::from_json(&input)?
+        };
+        run(&input, &options)
+    });
+    match result {
+        Ok(output) => {
+            print!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::primitives::address;
+
+    #[test]
+    fn runs_example() {
+        let input = Input::from_toml(include_str!("../examples/token_transfer.toml")).unwrap();
+        let options = Options::parse(["--trace", "example.toml"].map(String::from)).unwrap();
+        let output = run(&input, &options).unwrap();
+        assert!(output.contains("- Status: success (Stop)\n"));
+        assert!(output.contains("## Calls\n"));
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        assert!(output.contains(&format!("| {contract} | 0x1 | 0x0 | 0x2 |\n")));
+        assert!(output.ends_with(&format!(
+            "| {caller} | 0x7 | 1000 | 600 |\n| {contract} | 0x7 | 0 | 400 |\n"
+        )));
+
+        let options =
+            Options::parse(["--json", "--trace", "example.toml"].map(String::from)).unwrap();
+        let output: serde_json::Value =
+            serde_json::from_str(&run(&input, &options).unwrap()).unwrap();
+        assert_eq!(output["balance_changes"].as_array().unwrap().len(), 2);
+        assert_eq!(output["token_flows"][0]["amount"], "0x190");
+    }
+
+    #[test]
+    fn parses_options() {
+        let parse = |args: &[&str]| Options::parse(args.iter().map(|arg| arg.to_string()));
+        let options = parse(&["--json", "-"]).unwrap();
+        assert!(options.json && !options.trace);
+        assert_eq!(options.path, "-");
+        assert!(matches!(parse(&[]), Err(Error::Usage)));
+        assert!(matches!(parse(&["a", "b"]), Err(Error::Usage)));
+        assert!(matches!(parse(&["--verbose", "a"]), Err(Error::Usage)));
+    }
+}
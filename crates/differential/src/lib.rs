@@ -0,0 +1,4 @@
+//! Differential tests of SabVM against upstream revm, see `tests/differential.rs`.
+//!
+//! The crate is not a member of the workspace: both revm packages are named `revm`, which would
+//! make `cargo test -p revm` ambiguous.
@@ -0,0 +1,407 @@
+//! Differential tests running the same transactions through SabVM and upstream revm, and
+//! asserting identical results and state changes.
+//!
+//! The transactions only transfer the base token, so SabVM has to behave exactly like revm: a
+//! divergence is a change of the Native Tokens leaking into a shared code path, e.g. the gas
+//! accounting or the journaling.
+use revm::{
+    interpreter::opcode::*,
+    primitives::{
+        init_balances, keccak256, AccountInfo, Address, Bytecode, Bytes, Log, SpecId,
+        TokenTransfer, TransactTo, B256, BASE_TOKEN_ID, U256,
+    },
+    Evm, InMemoryDB,
+};
+use revm_upstream as upstream;
+use std::collections::BTreeMap;
+
+const CALLER: Address = Address::with_last_byte(0xaa);
+const CONTRACT: Address = Address::with_last_byte(0xbb);
+const OTHER: Address = Address::with_last_byte(0xcc);
+const LOOPING: Address = Address::with_last_byte(0xdd);
+const COINBASE: Address = Address::with_last_byte(0xee);
+
+const SPECS: [&str; 5] = ["Istanbul", "Berlin", "London", "Shanghai", "Cancun"];
+
+/// An account of the pre-state.
+struct PreAccount {
+    address: Address,
+    balance: U256,
+    nonce: u64,
+    code: Vec<u8>,
+    storage: Vec<(U256, U256)>,
+}
+
+impl PreAccount {
+    fn new(address: Address, balance: u64, code: Vec<u8>) -> Self {
+        Self {
+            address,
+            balance: U256::from(balance),
+            nonce: 0,
+            code,
+            storage: Vec::new(),
+        }
+    }
+}
+
+/// A transaction transferring only the base token, as `value`.
+struct Case {
+    name: &'static str,
+    pre: Vec<PreAccount>,
+    /// The called account, or `None` to create a contract with `data` as initcode.
+    to: Option<Address>,
+    value: U256,
+    data: Vec<u8>,
+    gas_limit: u64,
+}
+
+impl Case {
+    fn call(name: &'static str, pre: Vec<PreAccount>, to: Address) -> Self {
+        Self {
+            name,
+            pre,
+            to: Some(to),
+            value: U256::ZERO,
+            data: Vec::new(),
+            gas_limit: 1_000_000,
+        }
+    }
+}
+
+/// The state changes of an account touched by the transaction.
+#[derive(Debug, PartialEq, Eq)]
+struct AccountOutcome {
+    balance: U256,
+    nonce: u64,
+    code_hash: B256,
+    /// The present value of the changed storage slots.
+    storage: BTreeMap<U256, U256>,
+    is_created: bool,
+    is_selfdestructed: bool,
+}
+
+/// The result and the state changes of a transaction, `None` if the transaction is invalid.
+type Outcome = Option<(
+    String,
+    u64,
+    u64,
+    Bytes,
+    Vec<Log>,
+    BTreeMap<Address, AccountOutcome>,
+)>;
+
+fn run_sabvm(case: &Case, spec: &str) -> Outcome {
+    let mut db = InMemoryDB::default();
+    db.register_token(BASE_TOKEN_ID);
+    for account in &case.pre {
+        db.insert_account_info(
+            account.address,
+            AccountInfo {
+                balances: init_balances(account.balance),
+                nonce: account.nonce,
+                code_hash: keccak256(&account.code),
+                code: Some(Bytecode::new_raw(account.code.clone().into())),
+            },
+        );
+        for (slot, value) in &account.storage {
+            let Ok(()) = db.insert_account_storage(account.address, *slot, *value);
+        }
+    }
+    let mut evm = Evm::builder()
+        .with_db(db)
+        .with_spec_id(SpecId::from(spec))
+        .modify_block_env(|block| {
+            block.coinbase = COINBASE;
+            block.basefee = U256::from(7);
+        })
+        .modify_tx_env(|tx| {
+            tx.caller = CALLER;
+            tx.transact_to = case.to.map_or(TransactTo::Create, TransactTo::Call);
+            tx.data = case.data.clone().into();
+            tx.gas_limit = case.gas_limit;
+            tx.gas_price = U256::from(10);
+            if !case.value.is_zero() {
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: case.value,
+                }];
+            }
+        })
+        .build();
+    let result_and_state = evm.transact().ok()?;
+    let result = result_and_state.result;
+    let status = match &result {
+        revm::primitives::ExecutionResult::Success { reason, .. } => format!("{reason:?}"),
+        revm::primitives::ExecutionResult::Revert { .. } => "Revert".to_string(),
+        revm::primitives::ExecutionResult::Halt { reason, .. } => format!("Halt({reason:?})"),
+    };
+    let gas_refunded = match &result {
+        revm::primitives::ExecutionResult::Success { gas_refunded, .. } => *gas_refunded,
+        _ => 0,
+    };
+    let accounts = result_and_state
+        .state
+        .accounts
+        .into_iter()
+        .filter(|(_, account)| account.is_touched())
+        .map(|(address, account)| {
+            let outcome = AccountOutcome {
+                balance: account.info.get_balance(BASE_TOKEN_ID),
+                nonce: account.info.nonce,
+                code_hash: account.info.code_hash,
+                storage: account
+                    .changed_storage_slots()
+                    .map(|(slot, value)| (*slot, value.present_value))
+                    .collect(),
+                is_created: account.is_created(),
+                is_selfdestructed: account.is_selfdestructed(),
+            };
+            (address, outcome)
+        })
+        .collect();
+    Some((
+        status,
+        result.gas_used(),
+        gas_refunded,
+        result.output().cloned().unwrap_or_default(),
+        result.logs().to_vec(),
+        accounts,
+    ))
+}
+
+fn run_upstream(case: &Case, spec: &str) -> Outcome {
+    use upstream::primitives::{ExecutionResult, TransactTo};
+
+    let mut db = upstream::InMemoryDB::default();
+    for account in &case.pre {
+        db.insert_account_info(
+            account.address,
+            upstream::primitives::AccountInfo {
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash: keccak256(&account.code),
+                code: Some(upstream::primitives::Bytecode::new_raw(
+                    account.code.clone().into(),
+                )),
+            },
+        );
+        for (slot, value) in &account.storage {
+            let Ok(()) = db.insert_account_storage(account.address, *slot, *value);
+        }
+    }
+    let mut evm = upstream::Evm::builder()
+        .with_db(db)
+        .with_spec_id(upstream::primitives::SpecId::from(spec))
+        .modify_block_env(|block| {
+            block.coinbase = COINBASE;
+            block.basefee = U256::from(7);
+        })
+        .modify_tx_env(|tx| {
+            tx.caller = CALLER;
+            tx.transact_to = case.to.map_or(TransactTo::Create, TransactTo::Call);
+            tx.data = case.data.clone().into();
+            tx.gas_limit = case.gas_limit;
+            tx.gas_price = U256::from(10);
+            tx.value = case.value;
+        })
+        .build();
+    let result_and_state = evm.transact().ok()?;
+    let result = result_and_state.result;
+    let status = match &result {
+        ExecutionResult::Success { reason, .. } => format!("{reason:?}"),
+        ExecutionResult::Revert { .. } => "Revert".to_string(),
+        // the halt of `INVALID` was renamed in revm 10
+        ExecutionResult::Halt { reason, .. } => {
+            format!("Halt({reason:?})").replace("InvalidEFOpcode", "InvalidFEOpcode")
+        }
+    };
+    let gas_refunded = match &result {
+        ExecutionResult::Success { gas_refunded, .. } => *gas_refunded,
+        _ => 0,
+    };
+    let accounts = result_and_state
+        .state
+        .into_iter()
+        .filter(|(_, account)| account.is_touched())
+        .map(|(address, account)| {
+            let outcome = AccountOutcome {
+                balance: account.info.balance,
+                nonce: account.info.nonce,
+                code_hash: account.info.code_hash,
+                storage: account
+                    .changed_storage_slots()
+                    .map(|(slot, value)| (*slot, value.present_value))
+                    .collect(),
+                is_created: account.is_created(),
+                is_selfdestructed: account.is_selfdestructed(),
+            };
+            (address, outcome)
+        })
+        .collect();
+    Some((
+        status,
+        result.gas_used(),
+        gas_refunded,
+        result.output().cloned().unwrap_or_default(),
+        result.logs().to_vec(),
+        accounts,
+    ))
+}
+
+/// Returns the code pushing the address, as a `PUSH20`.
+fn push_address(address: Address) -> Vec<u8> {
+    [&[PUSH20], address.as_slice()].concat()
+}
+
+/// Returns the code making a `CALL` to the address with the value and the gas, and storing
+/// its success at the slot.
+fn call_and_store(address: Address, value: u8, gas: u16, slot: u8) -> Vec<u8> {
+    [
+        &[PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH1, value][..],
+        &push_address(address),
+        &[
+            PUSH2,
+            (gas >> 8) as u8,
+            gas as u8,
+            CALL,
+            PUSH1,
+            slot,
+            SSTORE,
+        ],
+    ]
+    .concat()
+}
+
+fn cases() -> Vec<Case> {
+    let caller = || PreAccount::new(CALLER, 1_000_000_000_000, Vec::new());
+    // SSTORE(1, 2), STOP
+    let storing = vec![PUSH1, 2, PUSH1, 1, SSTORE, STOP];
+    // runs out of gas
+    let looping = vec![JUMPDEST, PUSH1, 0, JUMP];
+
+    let mut cases = Vec::new();
+
+    cases.push(Case {
+        value: U256::from(1_000),
+        ..Case::call("value transfer", vec![caller()], OTHER)
+    });
+
+    // clears slot 0 for a refund, writes slot 1, logs and returns 32 bytes
+    let mut contract = PreAccount::new(
+        CONTRACT,
+        0,
+        vec![
+            PUSH1, 0, SLOAD, POP, PUSH1, 0, PUSH1, 0, SSTORE, PUSH1, 7, PUSH1, 1, SSTORE, PUSH1,
+            0xab, PUSH1, 0, MSTORE, PUSH1, 0x42, PUSH1, 32, PUSH1, 0, LOG1, PUSH1, 32, PUSH1, 0,
+            RETURN,
+        ],
+    );
+    contract.storage = vec![(U256::ZERO, U256::from(5))];
+    cases.push(Case::call(
+        "storage, refund and log",
+        vec![caller(), contract],
+        CONTRACT,
+    ));
+
+    cases.push(Case {
+        value: U256::from(3),
+        ..Case::call(
+            "revert",
+            vec![
+                caller(),
+                PreAccount::new(
+                    CONTRACT,
+                    0,
+                    vec![
+                        PUSH1, 1, PUSH1, 0, SSTORE, PUSH1, 0xde, PUSH1, 0, MSTORE8, PUSH1, 1,
+                        PUSH1, 0, REVERT,
+                    ],
+                ),
+            ],
+            CONTRACT,
+        )
+    });
+
+    cases.push(Case::call(
+        "invalid opcode",
+        vec![caller(), PreAccount::new(CONTRACT, 0, vec![INVALID])],
+        CONTRACT,
+    ));
+
+    cases.push(Case {
+        gas_limit: 25_000,
+        ..Case::call(
+            "out of gas",
+            vec![caller(), PreAccount::new(CONTRACT, 0, storing.clone())],
+            CONTRACT,
+        )
+    });
+
+    // stores slot 1, and returns the storing code
+    let initcode = [
+        &[PUSH1, 1, PUSH1, 1, SSTORE, PUSH6][..],
+        &storing,
+        &[PUSH1, 0, MSTORE, PUSH1, 6, PUSH1, 26, RETURN],
+    ]
+    .concat();
+    cases.push(Case {
+        name: "create",
+        pre: vec![caller()],
+        to: None,
+        value: U256::from(9),
+        data: initcode,
+        gas_limit: 1_000_000,
+    });
+
+    // calls a contract with a value, then a looping contract with little gas
+    let calling = [
+        call_and_store(OTHER, 5, 50_000, 0),
+        call_and_store(LOOPING, 0, 1_000, 1),
+    ]
+    .concat();
+    cases.push(Case::call(
+        "nested calls",
+        vec![
+            caller(),
+            PreAccount::new(CONTRACT, 10, calling),
+            PreAccount::new(OTHER, 0, storing),
+            PreAccount::new(LOOPING, 0, looping),
+        ],
+        CONTRACT,
+    ));
+
+    cases.push(Case::call(
+        "selfdestruct",
+        vec![
+            caller(),
+            PreAccount::new(
+                CONTRACT,
+                100,
+                [push_address(OTHER), vec![SELFDESTRUCT]].concat(),
+            ),
+        ],
+        CONTRACT,
+    ));
+
+    cases.push(Case {
+        value: U256::from(1),
+        ..Case::call(
+            "insufficient funds",
+            vec![PreAccount::new(CALLER, 1_000, Vec::new())],
+            OTHER,
+        )
+    });
+
+    cases
+}
+
+#[test]
+fn matches_upstream_revm() {
+    for case in cases() {
+        for spec in SPECS {
+            let sabvm = run_sabvm(&case, spec);
+            let upstream = run_upstream(&case, spec);
+            assert_eq!(sabvm, upstream, "{} diverges in {spec}", case.name);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+/*
+ * C bindings for embedding SabVM, see `src/lib.rs` for the documentation of every function.
+ *
+ * Addresses are 20 bytes, and token IDs, amounts and storage words are 32 bytes, big-endian.
+ * When a function doesn't return SABVM_STATUS_OK, sabvm_last_error describes the error.
+ */
+#ifndef SABVM_H
+#define SABVM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum SabvmStatus {
+    SABVM_STATUS_OK = 0,
+    SABVM_STATUS_NULL_POINTER = 1,
+    SABVM_STATUS_INVALID_TRANSACTION = 2,
+    SABVM_STATUS_DATABASE = 3,
+    SABVM_STATUS_INTERNAL = 4,
+    SABVM_STATUS_PANIC = 5,
+} SabvmStatus;
+
+typedef enum SabvmOutcome {
+    SABVM_OUTCOME_SUCCESS = 0,
+    SABVM_OUTCOME_REVERT = 1,
+    SABVM_OUTCOME_HALT = 2,
+} SabvmOutcome;
+
+typedef struct SabvmTokenTransfer {
+    uint8_t token_id[32];
+    uint8_t amount[32];
+} SabvmTokenTransfer;
+
+typedef struct SabvmTransaction {
+    uint8_t caller[20];
+    uint8_t to[20];
+    bool is_create;
+    const uint8_t *data;
+    size_t data_len;
+    uint64_t gas_limit;
+    uint8_t gas_price[32];
+    const SabvmTokenTransfer *transfers;
+    size_t transfers_len;
+} SabvmTransaction;
+
+typedef struct SabvmExecutionResult {
+    SabvmOutcome outcome;
+    uint64_t gas_used;
+    uint64_t gas_refunded;
+    bool has_created_address;
+    uint8_t created_address[20];
+} SabvmExecutionResult;
+
+typedef struct SabvmEvm SabvmEvm;
+
+SabvmEvm *sabvm_evm_new(void);
+void sabvm_evm_free(SabvmEvm *evm);
+const char *sabvm_last_error(const SabvmEvm *evm);
+
+SabvmStatus sabvm_set_block(SabvmEvm *evm, uint64_t number, uint64_t timestamp,
+                            const uint8_t *coinbase);
+SabvmStatus sabvm_register_token(SabvmEvm *evm, const uint8_t *token_id);
+SabvmStatus sabvm_fund(SabvmEvm *evm, const uint8_t *address, const uint8_t *token_id,
+                       const uint8_t *amount);
+SabvmStatus sabvm_deploy(SabvmEvm *evm, const uint8_t *address, const uint8_t *code,
+                         size_t code_len);
+SabvmStatus sabvm_set_storage(SabvmEvm *evm, const uint8_t *address, const uint8_t *slot,
+                              const uint8_t *value);
+SabvmStatus sabvm_balance(SabvmEvm *evm, const uint8_t *address, const uint8_t *token_id,
+                          uint8_t *amount);
+
+SabvmStatus sabvm_transact(SabvmEvm *evm, const SabvmTransaction *transaction,
+                           SabvmExecutionResult *result);
+SabvmStatus sabvm_transact_commit(SabvmEvm *evm, const SabvmTransaction *transaction,
+                                  SabvmExecutionResult *result);
+SabvmStatus sabvm_output(SabvmEvm *evm, const uint8_t **data, size_t *len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SABVM_H */
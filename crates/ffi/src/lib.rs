@@ -0,0 +1,638 @@
+//! # sabvm-ffi
+//!
+//! C bindings for embedding SabVM in nodes not written in Rust, so that they run the same
+//! Native Token semantics instead of reimplementing them.
+//!
+//! A [SabvmEvm] is an opaque handle owning an EVM and its in-memory state. The state is set up
+//! with [sabvm_fund], [sabvm_deploy] and [sabvm_set_storage], transactions are executed with
+//! [sabvm_transact] or [sabvm_transact_commit], and the balances are queried with
+//! [sabvm_balance].
+//!
+//! Every function returns a [SabvmStatus]. When it is not [SabvmStatus::Ok], [sabvm_last_error]
+//! describes the error. Addresses are 20 bytes, and token IDs, amounts and storage words are 32
+//! bytes, big-endian. A panic is caught and reported as [SabvmStatus::Panic] rather than
+//! unwinding into the caller.
+//!
+//! The declarations for C are in `include/sabvm.h`.
+#![warn(unused_crate_dependencies)]
+
+use core::{ffi::c_char, slice};
+use revm::{
+    primitives::{
+        Address, Bytecode, Bytes, EVMError, ExecutionResult, Output, ResultAndState, TokenTransfer,
+        TransactTo, U256,
+    },
+    DatabaseCommit, DatabaseRef, Evm, InMemoryDB,
+};
+use std::{
+    ffi::CString,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+/// The status returned by every function.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SabvmStatus {
+    Ok = 0,
+    /// A required pointer was null.
+    NullPointer = 1,
+    /// The transaction or the block is invalid, so the transaction was not executed.
+    InvalidTransaction = 2,
+    /// The state could not be read.
+    Database = 3,
+    /// The execution failed for another reason, e.g. a balance overflow.
+    Internal = 4,
+    /// The function panicked.
+    Panic = 5,
+}
+
+/// The outcome of an executed transaction.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SabvmOutcome {
+    Success = 0,
+    Revert = 1,
+    Halt = 2,
+}
+
+/// A token transferred by a transaction.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SabvmTokenTransfer {
+    pub token_id: [u8; 32],
+    pub amount: [u8; 32],
+}
+
+/// A transaction to execute.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SabvmTransaction {
+    pub caller: [u8; 20],
+    /// The called account, ignored if `is_create` is set.
+    pub to: [u8; 20],
+    pub is_create: bool,
+    /// The calldata, or the initcode of a creation. May be null if `data_len` is zero.
+    pub data: *const u8,
+    pub data_len: usize,
+    pub gas_limit: u64,
+    pub gas_price: [u8; 32],
+    /// The transferred tokens. May be null if `transfers_len` is zero.
+    pub transfers: *const SabvmTokenTransfer,
+    pub transfers_len: usize,
+}
+
+/// The result of an executed transaction. Its output is read with [sabvm_output].
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SabvmExecutionResult {
+    pub outcome: SabvmOutcome,
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    /// Whether a contract was created, at `created_address`.
+    pub has_created_address: bool,
+    pub created_address: [u8; 20],
+}
+
+/// An EVM and its in-memory state.
+pub struct SabvmEvm {
+    evm: Evm<'static, (), InMemoryDB>,
+    /// The output of the last transaction.
+    output: Bytes,
+    /// The description of the last error, empty if there was none.
+    last_error: CString,
+}
+
+/// An error reported to the caller.
+struct Error {
+    status: SabvmStatus,
+    message: String,
+}
+
+impl Error {
+    fn null_pointer(name: &str) -> Self {
+        Self {
+            status: SabvmStatus::NullPointer,
+            message: format!("{name} is null"),
+        }
+    }
+}
+
+impl<DBError: core::fmt::Display> From<EVMError<DBError>> for Error {
+    fn from(error: EVMError<DBError>) -> Self {
+        let status = match error {
+            EVMError::Transaction(_) | EVMError::Header(_) => SabvmStatus::InvalidTransaction,
+            EVMError::Database(_) => SabvmStatus::Database,
+            EVMError::Custom(_) => SabvmStatus::Internal,
+        };
+        Self {
+            status,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Runs the function on the EVM, catching its panics and recording its error.
+fn with_evm(evm: *mut SabvmEvm, f: impl FnOnce(&mut SabvmEvm) -> Result<(), Error>) -> SabvmStatus {
+    // SAFETY: the caller passes a handle returned by `sabvm_evm_new`, or null.
+    let Some(evm) = (unsafe { evm.as_mut() }) else {
+        return SabvmStatus::NullPointer;
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| f(evm))).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(Error {
+            status: SabvmStatus::Panic,
+            message: format!("panicked: {message}"),
+        })
+    });
+    match result {
+        Ok(()) => {
+            evm.last_error = CString::default();
+            SabvmStatus::Ok
+        }
+        Err(error) => {
+            evm.last_error = CString::new(error.message.replace('\0', "")).unwrap_or_default();
+            error.status
+        }
+    }
+}
+
+/// Returns the `len` elements at the pointer, which may be null if `len` is zero.
+///
+/// # Safety
+///
+/// The pointer must be valid for reads of `len` elements.
+unsafe fn elements<'a, T>(ptr: *const T, len: usize, name: &str) -> Result<&'a [T], Error> {
+    if len == 0 {
+        Ok(&[])
+    } else if ptr.is_null() {
+        Err(Error::null_pointer(name))
+    } else {
+        Ok(slice::from_raw_parts(ptr, len))
+    }
+}
+
+/// Reads the `N` bytes at the pointer.
+///
+/// # Safety
+///
+/// The pointer must be null or valid for reads of `N` bytes.
+unsafe fn read_bytes<const N: usize>(ptr: *const u8, name: &str) -> Result<[u8; N], Error> {
+    elements(ptr, N, name).map(|bytes| bytes.try_into().unwrap())
+}
+
+unsafe fn read_address(ptr: *const u8, name: &str) -> Result<Address, Error> {
+    read_bytes::<20>(ptr, name).map(Address::new)
+}
+
+unsafe fn read_word(ptr: *const u8, name: &str) -> Result<U256, Error> {
+    read_bytes::<32>(ptr, name).map(U256::from_be_bytes)
+}
+
+/// Creates an EVM with an empty state and the default environment.
+///
+/// The handle is freed with [sabvm_evm_free].
+#[no_mangle]
+pub extern "C" fn sabvm_evm_new() -> *mut SabvmEvm {
+    Box::into_raw(Box::new(SabvmEvm {
+        evm: Evm::builder().with_db(InMemoryDB::default()).build(),
+        output: Bytes::new(),
+        last_error: CString::default(),
+    }))
+}
+
+/// Frees the EVM.
+///
+/// # Safety
+///
+/// `evm` must be null or a handle returned by [sabvm_evm_new] that was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_evm_free(evm: *mut SabvmEvm) {
+    if !evm.is_null() {
+        drop(Box::from_raw(evm));
+    }
+}
+
+/// Returns the description of the last error, as a NUL-terminated string that is empty if the
+/// last call succeeded. The string is valid until the next call with the handle.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_last_error(evm: *const SabvmEvm) -> *const c_char {
+    match evm.as_ref() {
+        Some(evm) => evm.last_error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Sets the number, the timestamp and the beneficiary of the block of the next transactions.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, and `coinbase` must be null or point to 20 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_set_block(
+    evm: *mut SabvmEvm,
+    number: u64,
+    timestamp: u64,
+    coinbase: *const u8,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        let coinbase = read_address(coinbase, "coinbase")?;
+        let block = evm.evm.block_mut();
+        block.number = U256::from(number);
+        block.timestamp = U256::from(timestamp);
+        block.coinbase = coinbase;
+        Ok(())
+    })
+}
+
+/// Registers the token, so that it can be transferred.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, and `token_id` must be null or point to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_register_token(
+    evm: *mut SabvmEvm,
+    token_id: *const u8,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        let token_id = read_word(token_id, "token_id")?;
+        evm.evm.db_mut().register_token(token_id);
+        Ok(())
+    })
+}
+
+/// Adds the amount of the token to the balance of the account, registering the token.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, `address` must be null or point to 20 bytes, and
+/// `token_id` and `amount` must be null or point to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_fund(
+    evm: *mut SabvmEvm,
+    address: *const u8,
+    token_id: *const u8,
+    amount: *const u8,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        let address = read_address(address, "address")?;
+        let token_id = read_word(token_id, "token_id")?;
+        let amount = read_word(amount, "amount")?;
+        let Ok(()) = evm.evm.db_mut().fund(address, token_id, amount);
+        Ok(())
+    })
+}
+
+/// Sets the code of the account.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, `address` must be null or point to 20 bytes, and `code`
+/// must point to `code_len` bytes, or be null if `code_len` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_deploy(
+    evm: *mut SabvmEvm,
+    address: *const u8,
+    code: *const u8,
+    code_len: usize,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        let address = read_address(address, "address")?;
+        let code = elements(code, code_len, "code")?;
+        let bytecode = Bytecode::new_raw(Bytes::copy_from_slice(code));
+        let Ok(()) = evm.evm.db_mut().deploy(address, bytecode);
+        Ok(())
+    })
+}
+
+/// Sets the storage slot of the account.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, `address` must be null or point to 20 bytes, and `slot`
+/// and `value` must be null or point to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_set_storage(
+    evm: *mut SabvmEvm,
+    address: *const u8,
+    slot: *const u8,
+    value: *const u8,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        let address = read_address(address, "address")?;
+        let slot = read_word(slot, "slot")?;
+        let value = read_word(value, "value")?;
+        let Ok(()) = evm
+            .evm
+            .db_mut()
+            .insert_account_storage(address, slot, value);
+        Ok(())
+    })
+}
+
+/// Writes the balance of the token held by the account to `amount`, 32 bytes.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, `address` must be null or point to 20 bytes,
+/// `token_id` must be null or point to 32 bytes, and `amount` must be null or valid for writes
+/// of 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_balance(
+    evm: *mut SabvmEvm,
+    address: *const u8,
+    token_id: *const u8,
+    amount: *mut u8,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        let address = read_address(address, "address")?;
+        let token_id = read_word(token_id, "token_id")?;
+        if amount.is_null() {
+            return Err(Error::null_pointer("amount"));
+        }
+        let Ok(info) = evm.evm.db().basic_ref(address);
+        let balance = info.map_or(U256::ZERO, |info| info.get_balance(token_id));
+        slice::from_raw_parts_mut(amount, 32).copy_from_slice(&balance.to_be_bytes::<32>());
+        Ok(())
+    })
+}
+
+/// Executes the transaction without committing its state changes, writing its result to
+/// `result`.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, `transaction` must be null or point to a transaction
+/// whose pointers are valid, and `result` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_transact(
+    evm: *mut SabvmEvm,
+    transaction: *const SabvmTransaction,
+    result: *mut SabvmExecutionResult,
+) -> SabvmStatus {
+    with_evm(evm, |evm| transact(evm, transaction, result).map(|_| ()))
+}
+
+/// Executes the transaction and commits its state changes, writing its result to `result`.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, `transaction` must be null or point to a transaction
+/// whose pointers are valid, and `result` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_transact_commit(
+    evm: *mut SabvmEvm,
+    transaction: *const SabvmTransaction,
+    result: *mut SabvmExecutionResult,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        let ResultAndState { state, .. } = transact(evm, transaction, result)?;
+        evm.evm.db_mut().commit(state);
+        Ok(())
+    })
+}
+
+/// Executes the transaction, writing its result.
+///
+/// # Safety
+///
+/// See [sabvm_transact].
+unsafe fn transact(
+    evm: &mut SabvmEvm,
+    transaction: *const SabvmTransaction,
+    result: *mut SabvmExecutionResult,
+) -> Result<ResultAndState, Error> {
+    let transaction = transaction
+        .as_ref()
+        .ok_or_else(|| Error::null_pointer("transaction"))?;
+    if result.is_null() {
+        return Err(Error::null_pointer("result"));
+    }
+    let data = elements(transaction.data, transaction.data_len, "data")?;
+    let transfers = elements(
+        transaction.transfers,
+        transaction.transfers_len,
+        "transfers",
+    )?;
+
+    let tx = evm.evm.tx_mut();
+    tx.caller = Address::new(transaction.caller);
+    tx.transact_to = if transaction.is_create {
+        TransactTo::Create
+    } else {
+        TransactTo::Call(Address::new(transaction.to))
+    };
+    tx.data = Bytes::copy_from_slice(data);
+    tx.gas_limit = transaction.gas_limit;
+    tx.gas_price = U256::from_be_bytes(transaction.gas_price);
+    tx.transferred_tokens = transfers
+        .iter()
+        .map(|transfer| TokenTransfer {
+            id: U256::from_be_bytes(transfer.token_id),
+            amount: U256::from_be_bytes(transfer.amount),
+        })
+        .collect();
+
+    evm.output = Bytes::new();
+    let result_and_state = evm.evm.transact()?;
+    let execution_result = &result_and_state.result;
+    let (outcome, gas_refunded) = match execution_result {
+        ExecutionResult::Success { gas_refunded, .. } => (SabvmOutcome::Success, *gas_refunded),
+        ExecutionResult::Revert { .. } => (SabvmOutcome::Revert, 0),
+        ExecutionResult::Halt { .. } => (SabvmOutcome::Halt, 0),
+    };
+    let created_address = match execution_result {
+        ExecutionResult::Success {
+            output: Output::Create(_, address),
+            ..
+        } => *address,
+        _ => None,
+    };
+    if let Some(output) = execution_result.output() {
+        evm.output = output.clone();
+    }
+    result.write(SabvmExecutionResult {
+        outcome,
+        gas_used: execution_result.gas_used(),
+        gas_refunded,
+        has_created_address: created_address.is_some(),
+        created_address: created_address.unwrap_or_default().into_array(),
+    });
+    Ok(result_and_state)
+}
+
+/// Writes the location and the length of the output of the last transaction, its return or revert
+/// data, to `data` and `len`. The output is valid until the next transaction.
+///
+/// # Safety
+///
+/// `evm` must be null or a valid handle, and `data` and `len` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sabvm_output(
+    evm: *mut SabvmEvm,
+    data: *mut *const u8,
+    len: *mut usize,
+) -> SabvmStatus {
+    with_evm(evm, |evm| {
+        if data.is_null() || len.is_null() {
+            return Err(Error::null_pointer("data or len"));
+        }
+        data.write(evm.output.as_ptr());
+        len.write(evm.output.len());
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::{
+        interpreter::opcode,
+        primitives::{address, BASE_TOKEN_ID},
+    };
+    use std::ffi::CStr;
+
+    const CALLER: Address = address!("00000000000000000000000000000000000000aa");
+    const CONTRACT: Address = address!("00000000000000000000000000000000000000bb");
+
+    fn word(value: U256) -> [u8; 32] {
+        value.to_be_bytes()
+    }
+
+    fn transaction(transfers: &[SabvmTokenTransfer]) -> SabvmTransaction {
+        SabvmTransaction {
+            caller: CALLER.into_array(),
+            to: CONTRACT.into_array(),
+            is_create: false,
+            data: ptr::null(),
+            data_len: 0,
+            gas_limit: 100_000,
+            gas_price: [0; 32],
+            transfers: transfers.as_ptr(),
+            transfers_len: transfers.len(),
+        }
+    }
+
+    unsafe fn balance(evm: *mut SabvmEvm, address: Address, token_id: U256) -> U256 {
+        let mut amount = [0; 32];
+        let status = sabvm_balance(
+            evm,
+            address.as_ptr(),
+            word(token_id).as_ptr(),
+            amount.as_mut_ptr(),
+        );
+        assert_eq!(status, SabvmStatus::Ok);
+        U256::from_be_bytes(amount)
+    }
+
+    #[test]
+    fn transacts_through_c_abi() {
+        unsafe {
+            let evm = sabvm_evm_new();
+            let token_id = U256::from(7);
+            for token_id in [BASE_TOKEN_ID, token_id] {
+                let status = sabvm_fund(
+                    evm,
+                    CALLER.as_ptr(),
+                    word(token_id).as_ptr(),
+                    word(U256::from(1_000)).as_ptr(),
+                );
+                assert_eq!(status, SabvmStatus::Ok);
+            }
+            // returns the 32 bytes of memory after MSTORE(0, 42)
+            let code = [
+                opcode::PUSH1,
+                42,
+                opcode::PUSH1,
+                0,
+                opcode::MSTORE,
+                opcode::PUSH1,
+                32,
+                opcode::PUSH1,
+                0,
+                opcode::RETURN,
+            ];
+            let status = sabvm_deploy(evm, CONTRACT.as_ptr(), code.as_ptr(), code.len());
+            assert_eq!(status, SabvmStatus::Ok);
+
+            let transfers = [SabvmTokenTransfer {
+                token_id: word(token_id),
+                amount: word(U256::from(400)),
+            }];
+            let mut result = SabvmExecutionResult {
+                outcome: SabvmOutcome::Halt,
+                gas_used: 0,
+                gas_refunded: 0,
+                has_created_address: false,
+                created_address: [0; 20],
+            };
+            // a dry run doesn't change the state
+            let status = sabvm_transact(evm, &transaction(&transfers), &mut result);
+            assert_eq!(status, SabvmStatus::Ok);
+            assert_eq!(result.outcome, SabvmOutcome::Success);
+            assert_eq!(balance(evm, CONTRACT, token_id), U256::ZERO);
+
+            let status = sabvm_transact_commit(evm, &transaction(&transfers), &mut result);
+            assert_eq!(status, SabvmStatus::Ok);
+            assert_eq!(result.outcome, SabvmOutcome::Success);
+            assert!(result.gas_used > 0);
+            assert!(!result.has_created_address);
+            assert_eq!(balance(evm, CALLER, token_id), U256::from(600));
+            assert_eq!(balance(evm, CONTRACT, token_id), U256::from(400));
+
+            let (mut data, mut len) = (ptr::null(), 0);
+            assert_eq!(sabvm_output(evm, &mut data, &mut len), SabvmStatus::Ok);
+            assert_eq!(slice::from_raw_parts(data, len), &word(U256::from(42)));
+            assert!(CStr::from_ptr(sabvm_last_error(evm)).is_empty());
+
+            sabvm_evm_free(evm);
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        unsafe {
+            let evm = sabvm_evm_new();
+            let mut result = SabvmExecutionResult {
+                outcome: SabvmOutcome::Success,
+                gas_used: 0,
+                gas_refunded: 0,
+                has_created_address: false,
+                created_address: [0; 20],
+            };
+
+            // the caller can't pay for the transferred tokens
+            let transfers = [SabvmTokenTransfer {
+                token_id: word(BASE_TOKEN_ID),
+                amount: word(U256::from(1)),
+            }];
+            let status = sabvm_transact_commit(evm, &transaction(&transfers), &mut result);
+            assert_eq!(status, SabvmStatus::InvalidTransaction);
+            assert!(!CStr::from_ptr(sabvm_last_error(evm)).is_empty());
+
+            let status = sabvm_transact(evm, ptr::null(), &mut result);
+            assert_eq!(status, SabvmStatus::NullPointer);
+            assert_eq!(
+                CStr::from_ptr(sabvm_last_error(evm)).to_str(),
+                Ok("transaction is null")
+            );
+
+            assert_eq!(
+                sabvm_register_token(ptr::null_mut(), ptr::null()),
+                SabvmStatus::NullPointer
+            );
+            assert!(sabvm_last_error(ptr::null()).is_null());
+
+            sabvm_evm_free(evm);
+        }
+    }
+}
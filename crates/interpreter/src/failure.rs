@@ -0,0 +1,268 @@
+//! Typed failures of the token transfers.
+//!
+//! A failed transfer surfaces as an [InstructionResult] inside the interpreter and as a
+//! [HaltReason] in the execution result. [TransferFailure] is the single mapping between the
+//! two, so that matching on it keeps working whatever those variants are named.
+use crate::{primitives::HaltReason, InstructionResult};
+use core::fmt;
+
+/// Why a transfer of tokens failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferFailure {
+    /// The sender holds less than the transferred amount.
+    OutOfFunds,
+    /// The balance of the recipient would overflow.
+    OverflowPayment,
+}
+
+impl TransferFailure {
+    /// Returns the result of the instruction or the frame whose transfer failed.
+    #[inline]
+    pub const fn instruction_result(self) -> InstructionResult {
+        match self {
+            Self::OutOfFunds => InstructionResult::OutOfFunds,
+            Self::OverflowPayment => InstructionResult::OverflowPayment,
+        }
+    }
+
+    /// Returns the reason the transaction halted when its transfer failed.
+    #[inline]
+    pub const fn halt_reason(self) -> HaltReason {
+        match self {
+            Self::OutOfFunds => HaltReason::OutOfFunds,
+            Self::OverflowPayment => HaltReason::OverflowPayment,
+        }
+    }
+
+    /// Returns the transfer failure the instruction result stands for, if any.
+    #[inline]
+    pub const fn from_instruction_result(result: InstructionResult) -> Option<Self> {
+        match result {
+            InstructionResult::OutOfFunds => Some(Self::OutOfFunds),
+            InstructionResult::OverflowPayment => Some(Self::OverflowPayment),
+            _ => None,
+        }
+    }
+
+    /// Returns the transfer failure the halt reason stands for, if any.
+    #[inline]
+    pub const fn from_halt_reason(reason: HaltReason) -> Option<Self> {
+        match reason {
+            HaltReason::OutOfFunds => Some(Self::OutOfFunds),
+            HaltReason::OverflowPayment => Some(Self::OverflowPayment),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TransferFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfFunds => f.write_str("insufficient balance for transfer"),
+            Self::OverflowPayment => f.write_str("balance overflow on transfer"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransferFailure {}
+
+impl From<TransferFailure> for InstructionResult {
+    #[inline]
+    fn from(failure: TransferFailure) -> Self {
+        failure.instruction_result()
+    }
+}
+
+impl From<TransferFailure> for HaltReason {
+    #[inline]
+    fn from(failure: TransferFailure) -> Self {
+        failure.halt_reason()
+    }
+}
+
+impl TryFrom<InstructionResult> for TransferFailure {
+    type Error = InstructionResult;
+
+    #[inline]
+    fn try_from(result: InstructionResult) -> Result<Self, Self::Error> {
+        Self::from_instruction_result(result).ok_or(result)
+    }
+}
+
+impl TryFrom<HaltReason> for TransferFailure {
+    type Error = HaltReason;
+
+    #[inline]
+    fn try_from(reason: HaltReason) -> Result<Self, Self::Error> {
+        Self::from_halt_reason(reason).ok_or(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitives::OutOfGasError, SuccessOrHalt};
+
+    /// Every halt reason. [halt_reason_index] fails to compile when a variant is added, and then
+    /// has to be added here too.
+    fn halt_reasons() -> Vec<HaltReason> {
+        #[cfg_attr(not(feature = "optimism"), allow(unused_mut))]
+        let mut reasons = vec![
+            HaltReason::OutOfGas(OutOfGasError::Basic),
+            HaltReason::OutOfGas(OutOfGasError::MemoryLimit),
+            HaltReason::OutOfGas(OutOfGasError::Memory),
+            HaltReason::OutOfGas(OutOfGasError::Precompile),
+            HaltReason::OutOfGas(OutOfGasError::InvalidOperand),
+            HaltReason::OpcodeNotFound,
+            HaltReason::InvalidFEOpcode,
+            HaltReason::InvalidJump,
+            HaltReason::NotActivated,
+            HaltReason::StackUnderflow,
+            HaltReason::StackOverflow,
+            HaltReason::OutOfOffset,
+            HaltReason::CreateCollision,
+            HaltReason::PrecompileError,
+            HaltReason::NonceOverflow,
+            HaltReason::CreateContractSizeLimit,
+            HaltReason::CreateContractStartingWithEF,
+            HaltReason::CreateInitCodeSizeLimit,
+            HaltReason::OverflowPayment,
+            HaltReason::StateChangeDuringStaticCall,
+            HaltReason::CallNotAllowedInsideStatic,
+            HaltReason::OutOfFunds,
+            HaltReason::CallTooDeep,
+            HaltReason::UnauthorizedCaller,
+            HaltReason::TokenTransferToPrecompile,
+            HaltReason::LegacyValueCallToMntOnlyContract,
+            HaltReason::JournalLimitExceeded,
+            HaltReason::DebuggerHalt,
+            HaltReason::Interrupted,
+        ];
+        #[cfg(feature = "optimism")]
+        reasons.push(HaltReason::FailedDeposit);
+        for (index, reason) in reasons.iter().enumerate() {
+            assert_eq!(halt_reason_index(*reason), index, "{reason:?}");
+        }
+        reasons
+    }
+
+    fn halt_reason_index(reason: HaltReason) -> usize {
+        match reason {
+            HaltReason::OutOfGas(error) => match error {
+                OutOfGasError::Basic => 0,
+                OutOfGasError::MemoryLimit => 1,
+                OutOfGasError::Memory => 2,
+                OutOfGasError::Precompile => 3,
+                OutOfGasError::InvalidOperand => 4,
+            },
+            HaltReason::OpcodeNotFound => 5,
+            HaltReason::InvalidFEOpcode => 6,
+            HaltReason::InvalidJump => 7,
+            HaltReason::NotActivated => 8,
+            HaltReason::StackUnderflow => 9,
+            HaltReason::StackOverflow => 10,
+            HaltReason::OutOfOffset => 11,
+            HaltReason::CreateCollision => 12,
+            HaltReason::PrecompileError => 13,
+            HaltReason::NonceOverflow => 14,
+            HaltReason::CreateContractSizeLimit => 15,
+            HaltReason::CreateContractStartingWithEF => 16,
+            HaltReason::CreateInitCodeSizeLimit => 17,
+            HaltReason::OverflowPayment => 18,
+            HaltReason::StateChangeDuringStaticCall => 19,
+            HaltReason::CallNotAllowedInsideStatic => 20,
+            HaltReason::OutOfFunds => 21,
+            HaltReason::CallTooDeep => 22,
+            HaltReason::UnauthorizedCaller => 23,
+            HaltReason::TokenTransferToPrecompile => 24,
+            HaltReason::LegacyValueCallToMntOnlyContract => 25,
+            HaltReason::JournalLimitExceeded => 26,
+            HaltReason::DebuggerHalt => 27,
+            HaltReason::Interrupted => 28,
+            #[cfg(feature = "optimism")]
+            HaltReason::FailedDeposit => 29,
+        }
+    }
+
+    #[cfg(feature = "optimism")]
+    fn is_failed_deposit(reason: HaltReason) -> bool {
+        reason == HaltReason::FailedDeposit
+    }
+
+    #[cfg(not(feature = "optimism"))]
+    fn is_failed_deposit(_reason: HaltReason) -> bool {
+        false
+    }
+
+    #[test]
+    fn halt_reasons_round_trip() {
+        for reason in halt_reasons() {
+            let result = InstructionResult::from(reason);
+            let halt = SuccessOrHalt::from(result);
+            if is_failed_deposit(reason) {
+                // deposits fail outside the interpreter
+                assert_eq!(halt, SuccessOrHalt::FatalExternalError);
+            } else {
+                assert_eq!(halt, SuccessOrHalt::Halt(reason), "{reason:?}");
+            }
+        }
+    }
+
+    /// The only instruction results without a halt reason of their own.
+    #[test]
+    fn lossy_results_share_a_halt_reason() {
+        for (result, reason) in [
+            (
+                InstructionResult::ReturnContractInNotInitEOF,
+                HaltReason::OpcodeNotFound,
+            ),
+            (
+                InstructionResult::EOFOpcodeDisabledInLegacy,
+                HaltReason::OpcodeNotFound,
+            ),
+        ] {
+            assert_eq!(SuccessOrHalt::from(result), SuccessOrHalt::Halt(reason));
+        }
+    }
+
+    #[test]
+    fn transfer_failures_map_both_ways() {
+        for failure in [
+            TransferFailure::OutOfFunds,
+            TransferFailure::OverflowPayment,
+        ] {
+            let result = InstructionResult::from(failure);
+            let reason = HaltReason::from(failure);
+            assert_eq!(SuccessOrHalt::from(result), SuccessOrHalt::Halt(reason));
+            assert_eq!(InstructionResult::from(reason), result);
+            assert_eq!(TransferFailure::try_from(result), Ok(failure));
+            assert_eq!(TransferFailure::try_from(reason), Ok(failure));
+        }
+        // a failed transfer reverts the frame, but halts the transaction
+        assert!(InstructionResult::from(TransferFailure::OutOfFunds).is_revert());
+        assert!(InstructionResult::from(TransferFailure::OverflowPayment).is_error());
+
+        for reason in halt_reasons() {
+            if let Ok(failure) = TransferFailure::try_from(reason) {
+                assert_eq!(failure.halt_reason(), reason);
+            }
+        }
+        assert_eq!(
+            TransferFailure::try_from(InstructionResult::OutOfGas),
+            Err(InstructionResult::OutOfGas)
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn renamed_variants_have_aliases() {
+        assert_eq!(InstructionResult::OutOfFund, InstructionResult::OutOfFunds);
+        assert_eq!(HaltReason::OutOfFund, HaltReason::OutOfFunds);
+        assert!(matches!(
+            InstructionResult::from(TransferFailure::OutOfFunds),
+            InstructionResult::OutOfFund
+        ));
+    }
+}
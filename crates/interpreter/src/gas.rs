@@ -107,8 +107,18 @@ impl Gas {
     /// Related to EIP-3529: Reduction in refunds
     #[inline]
     pub fn set_final_refund(&mut self, is_london: bool) {
-        let max_refund_quotient = if is_london { 5 } else { 2 };
-        self.refunded = (self.refunded() as u64).min(self.spent() / max_refund_quotient) as i64;
+        self.set_final_refund_with_quotient(if is_london { 5 } else { 2 });
+    }
+
+    /// Set a refund value for final refund, limited to the given part of the gas spent.
+    ///
+    /// A quotient of `0` removes the refund.
+    #[inline]
+    pub fn set_final_refund_with_quotient(&mut self, max_refund_quotient: u64) {
+        self.refunded = match self.spent().checked_div(max_refund_quotient) {
+            Some(max_refund) => (self.refunded() as u64).min(max_refund) as i64,
+            None => 0,
+        };
     }
 
     /// Set a refund value. This overrides the current refund value.
@@ -1,9 +1,9 @@
-use revm_primitives::TokenTransfer;
+use revm_primitives::{TokenTransfer, PER_EMPTY_ACCOUNT_COST};
 
 use super::constants::*;
 use crate::{
     num_words,
-    primitives::{Address, SpecId, U256},
+    primitives::{Address, CallStipend, SpecId, MEMORY_GAS_QUOTIENT, U256},
     SelfDestructResult,
 };
 use std::vec::Vec;
@@ -329,6 +329,13 @@ pub const fn call_cost(
     gas
 }
 
+/// The gas given to the callee of a multi-token call transferring nonzero amounts of
+/// `transferred_tokens` tokens, on top of the forwarded gas.
+#[inline]
+pub const fn call_stipend(stipend: CallStipend, transferred_tokens: usize) -> u64 {
+    stipend.count(transferred_tokens) * CALL_STIPEND
+}
+
 /// Berlin warm and cold storage access cost for account access.
 #[inline]
 pub const fn warm_cold_cost(is_cold: bool) -> u64 {
@@ -348,9 +355,19 @@ pub const fn memory_gas_for_len(len: usize) -> u64 {
 /// Memory expansion cost calculation for a given number of words.
 #[inline]
 pub const fn memory_gas(num_words: u64) -> u64 {
+    memory_gas_with_quotient(num_words, MEMORY_GAS_QUOTIENT)
+}
+
+/// Memory expansion cost calculation for a given number of words, with `quotient` as the divisor
+/// of the quadratic term. A quotient of `0` is treated as `1`.
+///
+/// See [`CfgEnv::memory_gas_quotient`](crate::primitives::CfgEnv::memory_gas_quotient).
+#[inline]
+pub const fn memory_gas_with_quotient(num_words: u64, quotient: u64) -> u64 {
+    let quotient = if quotient == 0 { 1 } else { quotient };
     MEMORY
         .saturating_mul(num_words)
-        .saturating_add(num_words.saturating_mul(num_words) / 512)
+        .saturating_add(num_words.saturating_mul(num_words) / quotient)
 }
 
 /// Initial gas that is deducted for transaction to be included.
@@ -360,54 +377,106 @@ pub fn validate_initial_tx_gas(
     input: &[u8],
     is_create: bool,
     access_list: &[(Address, Vec<U256>)],
-    _transferred_tokens: &[TokenTransfer],
+    token_access_list: &[(Address, Vec<U256>)],
+    transferred_tokens: &[TokenTransfer],
+    authorization_list_num: u64,
 ) -> u64 {
     let mut initial_gas = 0;
+
+    // base stipend
+    initial_gas += 21000;
+    initial_gas += calldata_cost(spec_id, input);
+    initial_gas += access_list_cost(spec_id, access_list, token_access_list);
+    if is_create {
+        initial_gas += creation_cost(spec_id, input);
+    }
+    initial_gas += authorization_list_cost(spec_id, authorization_list_num);
+
+    // gas cost of transferring the Native Tokens
+    initial_gas += transferred_tokens_cost(transferred_tokens);
+
+    initial_gas
+}
+
+/// Returns the part of the initial gas charged for the input data of the transaction.
+#[inline]
+pub fn calldata_cost(spec_id: SpecId, input: &[u8]) -> u64 {
     let zero_data_len = input.iter().filter(|v| **v == 0).count() as u64;
     let non_zero_data_len = input.len() as u64 - zero_data_len;
 
     // initdate stipend
-    initial_gas += zero_data_len * TRANSACTION_ZERO_DATA;
-    // EIP-2028: Transaction data gas cost reduction
-    initial_gas += non_zero_data_len
-        * if spec_id.is_enabled_in(SpecId::ISTANBUL) {
-            16
-        } else {
-            68
-        };
+    zero_data_len * TRANSACTION_ZERO_DATA
+        // EIP-2028: Transaction data gas cost reduction
+        + non_zero_data_len
+            * if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+                16
+            } else {
+                68
+            }
+}
 
-    // get number of access list account and storages.
-    if spec_id.is_enabled_in(SpecId::BERLIN) {
-        let accessed_slots = access_list
-            .iter()
-            .fold(0, |slot_count, (_, slots)| slot_count + slots.len() as u64);
-        initial_gas += access_list.len() as u64 * ACCESS_LIST_ADDRESS;
-        initial_gas += accessed_slots * ACCESS_LIST_STORAGE_KEY;
+/// Returns the part of the initial gas charged for the access list and the token access list of
+/// the transaction.
+#[inline]
+pub fn access_list_cost(
+    spec_id: SpecId,
+    access_list: &[(Address, Vec<U256>)],
+    token_access_list: &[(Address, Vec<U256>)],
+) -> u64 {
+    if !spec_id.is_enabled_in(SpecId::BERLIN) {
+        return 0;
     }
+    // get number of access list account and storages.
+    let accessed_slots = access_list
+        .iter()
+        .fold(0, |slot_count, (_, slots)| slot_count + slots.len() as u64);
+    let mut initial_gas = access_list.len() as u64 * ACCESS_LIST_ADDRESS;
+    initial_gas += accessed_slots * ACCESS_LIST_STORAGE_KEY;
+
+    // the token access list is charged like the access list
+    let accessed_balances = token_access_list
+        .iter()
+        .fold(0, |balance_count, (_, token_ids)| {
+            balance_count + token_ids.len() as u64
+        });
+    initial_gas += token_access_list.len() as u64 * ACCESS_LIST_ADDRESS;
+    initial_gas += accessed_balances * ACCESS_LIST_TOKEN_BALANCE;
+    initial_gas
+}
 
-    // base stipend
-    initial_gas += if is_create {
-        if spec_id.is_enabled_in(SpecId::HOMESTEAD) {
-            // EIP-2: Homestead Hard-fork Changes
-            53000
-        } else {
-            21000
-        }
-    } else {
-        21000
-    };
-
+/// Returns the part of the initial gas charged for creating a contract with the init code,
+/// on top of the base stipend.
+#[inline]
+pub fn creation_cost(spec_id: SpecId, init_code: &[u8]) -> u64 {
+    let mut initial_gas = 0;
+    // EIP-2: Homestead Hard-fork Changes
+    if spec_id.is_enabled_in(SpecId::HOMESTEAD) {
+        initial_gas += 53000 - 21000;
+    }
     // EIP-3860: Limit and meter initcode
     // Init code stipend for bytecode analysis
-    if spec_id.is_enabled_in(SpecId::SHANGHAI) && is_create {
-        initial_gas += initcode_cost(input.len() as u64)
+    if spec_id.is_enabled_in(SpecId::SHANGHAI) {
+        initial_gas += initcode_cost(init_code.len() as u64)
     }
+    initial_gas
+}
 
-    //DEV: The following code leads to SabVM failing a part of Ethereum Tests. To remain compatible with them for as long as possible, only uncomment it right before the Genesis launch.
-    // gas cost of transferring the Native Tokens
-    // if !transferred_tokens.is_empty() {
-    //     initial_gas += transferred_tokens.len() as u64 * TRANSFERRED_TOKEN;
-    // }
+/// Returns the part of the initial gas charged for the authorizations of a set-code transaction.
+#[inline]
+pub fn authorization_list_cost(spec_id: SpecId, authorization_list_num: u64) -> u64 {
+    // EIP-7702: Set EOA account code
+    if spec_id.is_enabled_in(SpecId::PRAGUE) {
+        authorization_list_num * PER_EMPTY_ACCOUNT_COST
+    } else {
+        0
+    }
+}
 
-    initial_gas
+/// Returns the part of the initial gas charged for the Native Tokens transferred by the
+/// transaction.
+#[inline]
+pub fn transferred_tokens_cost(_transferred_tokens: &[TokenTransfer]) -> u64 {
+    //DEV: The following code leads to SabVM failing a part of Ethereum Tests. To remain compatible with them for as long as possible, only uncomment it right before the Genesis launch.
+    // transferred_tokens.len() as u64 * TRANSFERRED_TOKEN
+    0
 }
@@ -56,3 +56,6 @@ pub const CALL_STIPEND: u64 = 2300;
 pub const BURN_TOKENS: u64 = 10000;
 pub const MINT_TOKENS: u64 = 10000;
 pub const TRANSFERRED_TOKEN: u64 = 50;
+/// The gas cost of a token balance in the token access list, like a storage key in the access
+/// list.
+pub const ACCESS_LIST_TOKEN_BALANCE: u64 = ACCESS_LIST_STORAGE_KEY;
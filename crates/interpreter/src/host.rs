@@ -1,8 +1,11 @@
-use crate::primitives::{Address, Bytecode, Env, Log, B256, U256};
+use crate::{
+    primitives::{Address, Bytecode, Env, Log, B256, U256},
+    TransferFailure,
+};
+use std::vec::Vec;
 
 mod dummy;
 pub use dummy::DummyHost;
-use revm_primitives::BASE_TOKEN_ID;
 
 /// EVM context host.
 pub trait Host {
@@ -23,6 +26,15 @@ pub trait Host {
     /// Get code of `address` and if the account is cold.
     fn code(&mut self, address: Address) -> Option<(Bytecode, bool)>;
 
+    /// Get whether `address` is a contract, i.e. whether its account has code.
+    ///
+    /// This classifies the account of a frame, e.g. the immediate caller of a call, rather than the
+    /// sender of the transaction. The account of a contract being created has no code until its
+    /// constructor returns, and an EOA that delegates its code with EIP-7702 is a contract.
+    fn is_contract(&mut self, address: Address) -> Option<bool> {
+        self.code(address).map(|(code, _)| !code.is_empty())
+    }
+
     /// Get code hash of `address` and if the account is cold.
     fn code_hash(&mut self, address: Address) -> Option<(B256, bool)>;
 
@@ -44,14 +56,36 @@ pub trait Host {
     fn log(&mut self, log: Log);
 
     /// Mark `address` to be deleted, with funds transferred to `target`.
-    fn selfdestruct(&mut self, address: Address, target: Address) -> Option<SelfDestructResult>;
-
-    /// Get token balance of address and if account is cold loaded.
+    ///
+    /// Returns why the funds couldn't be transferred, in which case nothing is changed.
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<Result<SelfDestructResult, TransferFailure>>;
+
+    /// Get token balance of address and if the balance is cold accessed.
+    ///
+    /// The balance of the base token is cold when the account is.
     fn balance(&mut self, token_id: U256, address: Address) -> Option<(U256, bool)>;
 
     /// Get the base token balance of `address` and if the account is cold.
     fn base_balance(&mut self, address: Address) -> Option<(U256, bool)> {
-        self.balance(BASE_TOKEN_ID, address)
+        let base_token_id = self.env().cfg.base_token_id;
+        self.balance(base_token_id, address)
+    }
+
+    /// Get the IDs of all the registered Native Tokens.
+    ///
+    /// These are not the tokens sent with a call, see [Interpreter::incoming_token_values].
+    ///
+    /// [Interpreter::incoming_token_values]: crate::Interpreter::incoming_token_values
+    fn registered_token_ids(&mut self) -> Option<Vec<U256>>;
+
+    /// Get the IDs of the registered Native Tokens.
+    #[deprecated = "use `registered_token_ids` instead"]
+    fn token_ids(&mut self) -> Option<Vec<U256>> {
+        self.registered_token_ids()
     }
 
     /// Burn a Native Token.
@@ -1,7 +1,7 @@
 use crate::primitives::{hash_map::Entry, Bytecode, HashMap, U256};
 use crate::{
     primitives::{Address, Env, Log, B256, KECCAK_EMPTY},
-    Host, SStoreResult, SelfDestructResult,
+    Host, SStoreResult, SelfDestructResult, TransferFailure,
 };
 use std::vec::Vec;
 
@@ -113,7 +113,11 @@ impl Host for DummyHost {
     }
 
     #[inline]
-    fn selfdestruct(&mut self, _address: Address, _target: Address) -> Option<SelfDestructResult> {
+    fn selfdestruct(
+        &mut self,
+        _address: Address,
+        _target: Address,
+    ) -> Option<Result<SelfDestructResult, TransferFailure>> {
         panic!("Selfdestruct is not supported for this host")
     }
 
@@ -122,6 +126,11 @@ impl Host for DummyHost {
         Some((U256::ZERO, false))
     }
 
+    #[inline]
+    fn registered_token_ids(&mut self) -> Option<Vec<U256>> {
+        Some(Vec::new())
+    }
+
     #[inline]
     fn burn(
         &mut self,
@@ -16,6 +16,10 @@ pub enum InstructionResult {
     Revert = 0x10, // revert opcode
     CallTooDeep,
     OutOfFunds,
+    /// Tokens transferred to a precompile, where they would be stranded.
+    TokenTransferToPrecompile,
+    /// A `CALL` with a value to a contract that only accepts Native Token call values.
+    LegacyValueCallToMntOnlyContract,
 
     // Actions
     CallOrCreate = 0x20,
@@ -54,6 +58,19 @@ pub enum InstructionResult {
     /// EOF function stack overflow
     EOFFunctionStackOverflow,
     UnauthorizedCaller,
+    /// Execution was halted by a debugger.
+    DebuggerHalt,
+    /// Execution was interrupted by a watchdog.
+    Interrupted,
+    /// The journal of the frame or of the transaction exceeded its configured number of entries.
+    JournalLimitExceeded,
+}
+
+#[allow(non_upper_case_globals)]
+impl InstructionResult {
+    /// Former name of [InstructionResult::OutOfFunds].
+    #[deprecated = "use `InstructionResult::OutOfFunds`, or match on `TransferFailure`"]
+    pub const OutOfFund: Self = Self::OutOfFunds;
 }
 
 impl From<SuccessReason> for InstructionResult {
@@ -97,6 +114,11 @@ impl From<HaltReason> for InstructionResult {
             #[cfg(feature = "optimism")]
             HaltReason::FailedDeposit => Self::FatalExternalError,
             HaltReason::UnauthorizedCaller => Self::UnauthorizedCaller,
+            HaltReason::TokenTransferToPrecompile => Self::TokenTransferToPrecompile,
+            HaltReason::LegacyValueCallToMntOnlyContract => Self::LegacyValueCallToMntOnlyContract,
+            HaltReason::DebuggerHalt => Self::DebuggerHalt,
+            HaltReason::Interrupted => Self::Interrupted,
+            HaltReason::JournalLimitExceeded => Self::JournalLimitExceeded,
         }
     }
 }
@@ -115,7 +137,11 @@ macro_rules! return_ok {
 #[macro_export]
 macro_rules! return_revert {
     () => {
-        InstructionResult::Revert | InstructionResult::CallTooDeep | InstructionResult::OutOfFunds
+        InstructionResult::Revert
+            | InstructionResult::CallTooDeep
+            | InstructionResult::OutOfFunds
+            | InstructionResult::TokenTransferToPrecompile
+            | InstructionResult::LegacyValueCallToMntOnlyContract
     };
 }
 
@@ -147,6 +173,9 @@ macro_rules! return_error {
             | InstructionResult::ReturnContractInNotInitEOF
             | InstructionResult::EOFOpcodeDisabledInLegacy
             | InstructionResult::EOFFunctionStackOverflow
+            | InstructionResult::DebuggerHalt
+            | InstructionResult::Interrupted
+            | InstructionResult::JournalLimitExceeded
     };
 }
 
@@ -261,10 +290,12 @@ impl From<InstructionResult> for SuccessOrHalt {
             InstructionResult::OverflowPayment => Self::Halt(HaltReason::OverflowPayment), // Check for first call is done separately.
             InstructionResult::PrecompileError => Self::Halt(HaltReason::PrecompileError),
             InstructionResult::NonceOverflow => Self::Halt(HaltReason::NonceOverflow),
-            InstructionResult::CreateContractSizeLimit
-            | InstructionResult::CreateContractStartingWithEF => {
+            InstructionResult::CreateContractSizeLimit => {
                 Self::Halt(HaltReason::CreateContractSizeLimit)
             }
+            InstructionResult::CreateContractStartingWithEF => {
+                Self::Halt(HaltReason::CreateContractStartingWithEF)
+            }
             InstructionResult::CreateInitCodeSizeLimit => {
                 Self::Halt(HaltReason::CreateInitCodeSizeLimit)
             }
@@ -275,6 +306,15 @@ impl From<InstructionResult> for SuccessOrHalt {
                 panic!("Unexpected EOF internal Return Contract")
             }
             InstructionResult::UnauthorizedCaller => Self::Halt(HaltReason::UnauthorizedCaller),
+            InstructionResult::TokenTransferToPrecompile => {
+                Self::Halt(HaltReason::TokenTransferToPrecompile)
+            }
+            InstructionResult::LegacyValueCallToMntOnlyContract => {
+                Self::Halt(HaltReason::LegacyValueCallToMntOnlyContract)
+            }
+            InstructionResult::DebuggerHalt => Self::Halt(HaltReason::DebuggerHalt),
+            InstructionResult::Interrupted => Self::Halt(HaltReason::Interrupted),
+            InstructionResult::JournalLimitExceeded => Self::Halt(HaltReason::JournalLimitExceeded),
         }
     }
 }
@@ -344,6 +384,9 @@ mod tests {
             InstructionResult::CreateContractStartingWithEF,
             InstructionResult::CreateInitCodeSizeLimit,
             InstructionResult::FatalExternalError,
+            InstructionResult::DebuggerHalt,
+            InstructionResult::Interrupted,
+            InstructionResult::JournalLimitExceeded,
         ];
 
         for result in error_results {
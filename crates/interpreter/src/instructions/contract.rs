@@ -3,7 +3,7 @@ mod call_helpers;
 pub use call_helpers::{
     calc_call_gas, get_memory_input_and_out_ranges, resize_memory_and_return_range,
 };
-use revm_primitives::{keccak256, BerlinSpec, TokenTransfer, BASE_TOKEN_ID};
+use revm_primitives::{keccak256, smallvec::smallvec, BerlinSpec, TokenTransfer, TokenTransfers};
 
 use crate::{
     gas::{self, cost_per_word, EOF_CREATE_GAS, KECCAK256WORD},
@@ -15,8 +15,6 @@ use crate::{
 };
 use core::{cmp::max, ops::Range};
 use std::boxed::Box;
-use std::vec;
-use std::vec::Vec;
 
 /// Resize memory and return memory range if successful.
 /// Return `None` if there is not enough gas. And if `len`
@@ -224,13 +222,12 @@ pub fn extcall<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interpreter, host
             gas_limit,
             target_address,
             caller: interpreter.contract.target_address,
+            caller_bytecode_address: interpreter.contract.bytecode_address,
             bytecode_address: target_address,
-            values: CallValues::Transfer(vec![
-                (TokenTransfer {
-                    id: BASE_TOKEN_ID,
-                    amount: value,
-                }),
-            ]),
+            values: CallValues::Transfer(smallvec![TokenTransfer {
+                id: host.env().cfg.base_token_id,
+                amount: value,
+            },]),
             scheme: CallScheme::Call,
             is_static: interpreter.is_static,
             is_eof: true,
@@ -261,8 +258,9 @@ pub fn extdelegatecall<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interpret
             gas_limit,
             target_address,
             caller: interpreter.contract.target_address,
+            caller_bytecode_address: interpreter.contract.bytecode_address,
             bytecode_address: target_address,
-            values: CallValues::Apparent(interpreter.contract.call_values.clone()),
+            values: interpreter.contract.delegated_call_values(),
             // TODO(EOF) should be EofDelegateCall?
             scheme: CallScheme::DelegateCall,
             is_static: interpreter.is_static,
@@ -293,8 +291,9 @@ pub fn extstaticcall<H: Host + ?Sized>(interpreter: &mut Interpreter, host: &mut
             gas_limit,
             target_address,
             caller: interpreter.contract.target_address,
+            caller_bytecode_address: interpreter.contract.bytecode_address,
             bytecode_address: target_address,
-            values: CallValues::Transfer(Vec::new()),
+            values: CallValues::Transfer(TokenTransfers::new()),
             scheme: CallScheme::Call,
             is_static: interpreter.is_static,
             is_eof: true,
@@ -419,13 +418,12 @@ pub fn call<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interpreter, host: &
             gas_limit,
             target_address: to,
             caller: interpreter.contract.target_address,
+            caller_bytecode_address: interpreter.contract.bytecode_address,
             bytecode_address: to,
-            values: CallValues::Transfer(vec![
-                (TokenTransfer {
-                    id: BASE_TOKEN_ID,
-                    amount: value,
-                }),
-            ]),
+            values: CallValues::Transfer(smallvec![TokenTransfer {
+                id: host.env().cfg.base_token_id,
+                amount: value,
+            },]),
             scheme: CallScheme::Call,
             is_static: interpreter.is_static,
             is_eof: false,
@@ -475,13 +473,12 @@ pub fn call_code<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interpreter, ho
             gas_limit,
             target_address: interpreter.contract.target_address,
             caller: interpreter.contract.target_address,
+            caller_bytecode_address: interpreter.contract.bytecode_address,
             bytecode_address: to,
-            values: CallValues::Transfer(vec![
-                (TokenTransfer {
-                    id: BASE_TOKEN_ID,
-                    amount: value,
-                }),
-            ]),
+            values: CallValues::Transfer(smallvec![TokenTransfer {
+                id: host.env().cfg.base_token_id,
+                amount: value,
+            },]),
             scheme: CallScheme::CallCode,
             is_static: interpreter.is_static,
             is_eof: false,
@@ -521,8 +518,9 @@ pub fn delegate_call<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interpreter
             gas_limit,
             target_address: interpreter.contract.target_address,
             caller: interpreter.contract.caller,
+            caller_bytecode_address: interpreter.contract.bytecode_address,
             bytecode_address: to,
-            values: CallValues::Apparent(interpreter.contract.call_values.clone()),
+            values: interpreter.contract.delegated_call_values(),
             scheme: CallScheme::DelegateCall,
             is_static: interpreter.is_static,
             is_eof: false,
@@ -562,8 +560,9 @@ pub fn static_call<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interpreter,
             gas_limit,
             target_address: to,
             caller: interpreter.contract.target_address,
+            caller_bytecode_address: interpreter.contract.bytecode_address,
             bytecode_address: to,
-            values: CallValues::Transfer(Vec::new()),
+            values: CallValues::Transfer(TokenTransfers::new()),
             scheme: CallScheme::StaticCall,
             is_static: true,
             is_eof: false,
@@ -214,6 +214,13 @@ pub fn selfdestruct<H: Host + ?Sized, SPEC: Spec>(interpreter: &mut Interpreter,
         interpreter.instruction_result = InstructionResult::FatalExternalError;
         return;
     };
+    let res = match res {
+        Ok(res) => res,
+        Err(failure) => {
+            interpreter.instruction_result = failure.into();
+            return;
+        }
+    };
 
     // EIP-3529: Reduction in refunds
     if !SPEC::enabled(LONDON) && !res.previously_destroyed {
@@ -1,6 +1,6 @@
 use crate::{
     gas,
-    primitives::{Spec, B256, BASE_TOKEN_ID, KECCAK_EMPTY, U256},
+    primitives::{Spec, B256, KECCAK_EMPTY, U256},
     Host, InstructionResult, Interpreter,
 };
 use core::ptr;
@@ -86,17 +86,10 @@ pub fn calldatasize<H: Host + ?Sized>(interpreter: &mut Interpreter, _host: &mut
     push!(interpreter, U256::from(interpreter.contract.input.len()));
 }
 
-pub fn callvalue<H: Host + ?Sized>(interpreter: &mut Interpreter, _host: &mut H) {
+pub fn callvalue<H: Host + ?Sized>(interpreter: &mut Interpreter, host: &mut H) {
     gas!(interpreter, gas::BASE);
-    push!(
-        interpreter,
-        interpreter
-            .contract
-            .call_values
-            .iter()
-            .find(|tt| tt.id == BASE_TOKEN_ID)
-            .map_or(U256::ZERO, |tt| tt.amount)
-    );
+    let base_token_id = host.env().cfg.base_token_id;
+    push!(interpreter, interpreter.incoming_token_value(base_token_id));
 }
 
 pub fn calldatacopy<H: Host + ?Sized>(interpreter: &mut Interpreter, _host: &mut H) {
@@ -10,12 +10,12 @@ pub use shared_memory::{num_words, SharedMemory, EMPTY_SHARED_MEMORY};
 pub use stack::{Stack, STACK_LIMIT};
 
 use crate::{
-    gas, primitives::Bytes, push, push_b256, return_ok, return_revert, CallOutcome, CallValues,
+    primitives::Bytes, push, push_b256, return_ok, return_revert, CallOutcome, CallValues,
     CreateOutcome, EOFCreateOutcome, FunctionStack, Gas, Host, InstructionResult,
     InterpreterAction,
 };
 use core::cmp::min;
-use revm_primitives::{Address, Bytecode, Eof, U256};
+use revm_primitives::{Address, Bytecode, Eof, TokenTransfer, U256};
 use std::borrow::ToOwned;
 
 /// EVM bytecode interpreter.
@@ -80,6 +80,8 @@ pub struct CallInfo {
     pub target_address: Address,
     pub call_values: CallValues,
     pub input_data: Bytes,
+    /// The gas used before the call, charged before its gas is forwarded.
+    pub gas_used: u64,
 }
 
 /// The result of an interpreter operation.
@@ -133,7 +135,7 @@ impl Interpreter {
     /// Test related helper
     #[cfg(test)]
     pub fn new_bytecode(bytecode: Bytecode) -> Self {
-        use revm_primitives::{TokenTransfer, BASE_TOKEN_ID};
+        use revm_primitives::{smallvec::smallvec, TokenTransfer, BASE_TOKEN_ID};
 
         Self::new(
             Contract::new(
@@ -142,12 +144,10 @@ impl Interpreter {
                 None,
                 crate::primitives::Address::default(),
                 crate::primitives::Address::default(),
-                vec![
-                    (TokenTransfer {
-                        id: BASE_TOKEN_ID,
-                        amount: U256::ZERO,
-                    }),
-                ],
+                smallvec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::ZERO,
+                },],
             ),
             0,
             false,
@@ -324,6 +324,25 @@ impl Interpreter {
         &self.contract
     }
 
+    /// Returns the Native Tokens sent with the call of this frame, without zero amounts.
+    ///
+    /// A `DELEGATECALL` frame has the values of the frame that made the delegatecall. These are
+    /// not the registered tokens, see [Host::registered_token_ids].
+    #[inline]
+    pub fn incoming_token_values(&self) -> &[TokenTransfer] {
+        &self.contract.call_values
+    }
+
+    /// Returns the amount of the token sent with the call of this frame, see
+    /// [Self::incoming_token_values].
+    #[inline]
+    pub fn incoming_token_value(&self, token_id: U256) -> U256 {
+        self.incoming_token_values()
+            .iter()
+            .find(|transfer| transfer.id == token_id)
+            .map_or(U256::ZERO, |transfer| transfer.amount)
+    }
+
     /// Returns a reference to the interpreter's gas state.
     #[inline]
     pub fn gas(&self) -> &Gas {
@@ -435,7 +454,7 @@ impl InterpreterResult {
 #[must_use]
 pub fn resize_memory(memory: &mut SharedMemory, gas: &mut Gas, new_size: usize) -> bool {
     let new_words = num_words(new_size as u64);
-    let new_cost = gas::memory_gas(new_words);
+    let new_cost = memory.expansion_cost(new_words);
     let current_cost = memory.current_expansion_cost();
     let cost = new_cost - current_cost;
     let success = gas.record_cost(cost);
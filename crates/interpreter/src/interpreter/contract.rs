@@ -1,9 +1,8 @@
 use super::analysis::to_analysed;
 use crate::{
-    primitives::{Address, Bytecode, Bytes, Env, TokenTransfer, TransactTo, B256},
-    CallInputs,
+    primitives::{Address, Bytecode, Bytes, Env, TokenTransfers, TransactTo, B256, U256},
+    CallInputs, CallValues,
 };
-use std::vec::Vec;
 
 /// EVM contract information.
 #[derive(Clone, Debug, Default)]
@@ -18,10 +17,17 @@ pub struct Contract {
     pub hash: Option<B256>,
     /// Target address of the account. Storage of this address is going to be modified.
     pub target_address: Address,
+    /// Address of the account whose bytecode is executed. It differs from
+    /// [Self::target_address] when the code runs through a `DELEGATECALL` or a `CALLCODE`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bytecode_address: Address,
     /// Caller of the EVM.
     pub caller: Address,
-    /// Values sent to the contract from transaction or from CALL opcodes.
-    pub call_values: Vec<TokenTransfer>,
+    /// Values sent to the contract from transaction or from CALL opcodes, without zero amounts.
+    ///
+    /// A `DELEGATECALL` frame has the call values of the frame that made the delegatecall, see
+    /// [`CallValues`].
+    pub call_values: TokenTransfers,
 }
 
 impl Contract {
@@ -33,7 +39,7 @@ impl Contract {
         hash: Option<B256>,
         target_address: Address,
         caller: Address,
-        call_values: Vec<TokenTransfer>,
+        call_values: TokenTransfers,
     ) -> Self {
         let bytecode = to_analysed(bytecode);
 
@@ -42,6 +48,7 @@ impl Contract {
             bytecode,
             hash,
             target_address,
+            bytecode_address: target_address,
             caller,
             call_values,
         }
@@ -60,7 +67,12 @@ impl Contract {
             hash,
             contract_address,
             env.tx.caller,
-            env.tx.transferred_tokens.clone(),
+            env.tx
+                .transferred_tokens
+                .iter()
+                .filter(|transfer| transfer.amount != U256::ZERO)
+                .cloned()
+                .collect(),
         )
     }
 
@@ -72,14 +84,23 @@ impl Contract {
         hash: Option<B256>,
         call_context: &CallInputs,
     ) -> Self {
-        Self::new(
-            input,
-            bytecode,
-            hash,
-            call_context.target_address,
-            call_context.caller,
-            call_context.call_values(),
-        )
+        Self {
+            bytecode_address: call_context.bytecode_address,
+            ..Self::new(
+                input,
+                bytecode,
+                hash,
+                call_context.target_address,
+                call_context.caller,
+                call_context.values.frame_values().cloned().collect(),
+            )
+        }
+    }
+
+    /// Returns the values of a delegatecall made by this contract, which are its own call values.
+    #[inline]
+    pub fn delegated_call_values(&self) -> CallValues {
+        CallValues::Apparent(self.call_values.clone())
     }
 
     /// Returns whether the given position is a valid jump destination.
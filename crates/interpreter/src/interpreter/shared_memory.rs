@@ -1,5 +1,5 @@
 use core::{cmp::min, fmt, ops::Range};
-use revm_primitives::{B256, U256};
+use revm_primitives::{B256, MEMORY_GAS_QUOTIENT, U256};
 use std::vec::Vec;
 
 /// A sequential memory shared between calls, which uses
@@ -19,6 +19,9 @@ pub struct SharedMemory {
     /// Memory limit. See [`CfgEnv`](revm_primitives::CfgEnv).
     #[cfg(feature = "memory_limit")]
     memory_limit: u64,
+    /// Divisor of the quadratic term of the memory expansion cost. See
+    /// [`CfgEnv`](revm_primitives::CfgEnv).
+    memory_gas_quotient: u64,
 }
 
 /// Empty shared memory.
@@ -30,6 +33,7 @@ pub const EMPTY_SHARED_MEMORY: SharedMemory = SharedMemory {
     last_checkpoint: 0,
     #[cfg(feature = "memory_limit")]
     memory_limit: u64::MAX,
+    memory_gas_quotient: MEMORY_GAS_QUOTIENT,
 };
 
 impl fmt::Debug for SharedMemory {
@@ -69,6 +73,7 @@ impl SharedMemory {
             last_checkpoint: 0,
             #[cfg(feature = "memory_limit")]
             memory_limit: u64::MAX,
+            memory_gas_quotient: MEMORY_GAS_QUOTIENT,
         }
     }
 
@@ -85,6 +90,33 @@ impl SharedMemory {
         }
     }
 
+    /// Sets the `memory_limit` upper bound for allocation size.
+    #[cfg(feature = "memory_limit")]
+    #[inline]
+    pub fn set_memory_limit(&mut self, memory_limit: u64) {
+        self.memory_limit = memory_limit;
+    }
+
+    /// Sets the divisor of the quadratic term of the memory expansion cost.
+    #[inline]
+    pub fn set_memory_gas_quotient(&mut self, memory_gas_quotient: u64) {
+        self.memory_gas_quotient = memory_gas_quotient;
+    }
+
+    /// Returns the divisor of the quadratic term of the memory expansion cost.
+    #[inline]
+    pub fn memory_gas_quotient(&self) -> u64 {
+        self.memory_gas_quotient
+    }
+
+    /// Clears all the memory contexts, keeping the allocated capacity for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.checkpoints.clear();
+        self.last_checkpoint = 0;
+    }
+
     /// Returns `true` if the `new_size` for the current context memory will
     /// make the shared buffer length exceed the `memory_limit`.
     #[cfg(feature = "memory_limit")]
@@ -126,7 +158,13 @@ impl SharedMemory {
     /// Returns the gas cost for the current memory expansion.
     #[inline]
     pub fn current_expansion_cost(&self) -> u64 {
-        crate::gas::memory_gas_for_len(self.len())
+        self.expansion_cost(num_words(self.len() as u64))
+    }
+
+    /// Returns the gas cost for a memory expansion to `num_words` words.
+    #[inline]
+    pub fn expansion_cost(&self, num_words: u64) -> u64 {
+        crate::gas::memory_gas_with_quotient(num_words, self.memory_gas_quotient)
     }
 
     /// Resizes the memory in-place so that `len` is equal to `new_len`.
@@ -332,6 +370,25 @@ mod tests {
         assert_eq!(num_words(u64::MAX), u64::MAX / 32);
     }
 
+    #[test]
+    fn expansion_cost_uses_the_quotient() {
+        let mut shared_memory = SharedMemory::new();
+        assert_eq!(
+            shared_memory.expansion_cost(1024),
+            crate::gas::memory_gas(1024)
+        );
+        assert_eq!(shared_memory.expansion_cost(1024), 3 * 1024 + 2048);
+
+        shared_memory.set_memory_gas_quotient(64);
+        assert_eq!(shared_memory.expansion_cost(1024), 3 * 1024 + 16384);
+        shared_memory.set_memory_gas_quotient(0);
+        assert_eq!(shared_memory.expansion_cost(1024), 3 * 1024 + 1024 * 1024);
+
+        shared_memory.new_context();
+        shared_memory.resize(64);
+        assert_eq!(shared_memory.current_expansion_cost(), 3 * 2 + 4);
+    }
+
     #[test]
     fn new_free_context() {
         let mut shared_memory = SharedMemory::new();
@@ -379,6 +436,23 @@ mod tests {
         assert_eq!(shared_memory.len(), 0);
     }
 
+    #[test]
+    fn clear_keeps_capacity() {
+        let mut shared_memory = SharedMemory::new();
+        shared_memory.new_context();
+        shared_memory.resize(64);
+        shared_memory.new_context();
+        shared_memory.resize(32);
+        let capacity = shared_memory.buffer.capacity();
+
+        shared_memory.clear();
+        assert_eq!(shared_memory.buffer.len(), 0);
+        assert_eq!(shared_memory.buffer.capacity(), capacity);
+        assert_eq!(shared_memory.checkpoints.len(), 0);
+        assert_eq!(shared_memory.last_checkpoint, 0);
+        assert_eq!(shared_memory.len(), 0);
+    }
+
     #[test]
     fn resize() {
         let mut shared_memory = SharedMemory::new();
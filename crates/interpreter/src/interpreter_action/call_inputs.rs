@@ -1,7 +1,9 @@
-use crate::primitives::{Address, Bytes, TokenTransfer, TransactTo, TxEnv, BASE_TOKEN_ID, U256};
+use crate::primitives::{
+    nonzero_token_transfers, smallvec::smallvec, Address, Bytes, CallRequest, CallRequestError,
+    TokenTransfer, TokenTransfers, TransactTo, TxEnv, BASE_TOKEN_ID, U256,
+};
 use core::ops::Range;
 use std::boxed::Box;
-use std::vec;
 use std::vec::Vec;
 
 /// Inputs for a call.
@@ -28,6 +30,12 @@ pub struct CallInputs {
     ///
     /// Previously `context.caller`.
     pub caller: Address,
+    /// The account address of the bytecode executed by the caller.
+    ///
+    /// It differs from the address of the caller's storage when the caller runs delegated code,
+    /// e.g. a library called with `DELEGATECALL` by a proxy.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub caller_bytecode_address: Address,
     /// Call values.
     ///
     /// NOTE: These values may not necessarily be transferred from caller to callee, see [`CallValues`].
@@ -58,7 +66,8 @@ impl CallInputs {
             target_address,
             bytecode_address: target_address,
             caller: tx_env.caller,
-            values: CallValues::Transfer(tx_env.transferred_tokens.clone()),
+            caller_bytecode_address: tx_env.caller,
+            values: CallValues::Transfer(tx_env.transferred_tokens.as_slice().into()),
             scheme: CallScheme::Call,
             is_static: false,
             is_eof: false,
@@ -73,26 +82,42 @@ impl CallInputs {
         Self::new(tx_env, gas_limit).map(Box::new)
     }
 
+    /// Creates new call inputs from a JSON-RPC call request, whose `value` is an amount of the
+    /// base token `base_token_id`.
+    ///
+    /// Returns `Ok(None)` if the request is a contract creation.
+    pub fn from_call_request(
+        request: CallRequest,
+        base_token_id: U256,
+    ) -> Result<Option<Self>, CallRequestError> {
+        let tx_env = request.try_into_tx_env(base_token_id)?;
+        Ok(Self::new(&tx_env, tx_env.gas_limit))
+    }
+
     /// Returns `true` if the call will transfer a non-zero value.
     #[inline]
     pub fn transfers_value(&self) -> bool {
-        self.values.transfer().iter().any(|x| x.amount > U256::ZERO)
+        nonzero_token_transfers(self.values.transferred())
+            .next()
+            .is_some()
     }
 
     /// Returns the transfer value.
     ///
     /// This is the value that is transferred from caller to callee, see [`CallValues`].
     #[inline]
+    #[deprecated = "use `values.transferred()` instead"]
     pub fn transfer_value(&self) -> Vec<TokenTransfer> {
-        self.values.transfer()
+        self.values.transferred().to_vec()
     }
 
     /// Returns the **apparent** call value.
     ///
     /// This value is not actually transferred, see [`CallValues`].
     #[inline]
+    #[deprecated = "use `values.apparent_values()` instead"]
     pub fn apparent_value(&self) -> Vec<TokenTransfer> {
-        self.values.apparent()
+        self.values.apparent_values().to_vec()
     }
 
     /// Returns the address of the transfer source account.
@@ -115,8 +140,9 @@ impl CallInputs {
     ///
     /// NOTE: this values may not necessarily be transferred from caller to callee, see [`CallValues`].
     #[inline]
+    #[deprecated = "use `values.as_slice()` instead"]
     pub fn call_values(&self) -> Vec<TokenTransfer> {
-        self.values.get()
+        self.values.as_slice().to_vec()
     }
 }
 
@@ -135,47 +161,101 @@ pub enum CallScheme {
 }
 
 /// Call values.
+///
+/// The call values of a frame, as read by `CALLVALUE` and by the `getCallValues` function of the
+/// Native Tokens Precompile, are the tokens transferred by the call that created the frame, with
+/// zero amounts left out. A `DELEGATECALL` frame transfers nothing and sees the call values of the
+/// frame that made the delegatecall, so they are the same along a chain of delegatecalls.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CallValues {
     /// Concrete value, transferred from caller to callee at the end of the transaction.
-    Transfer(Vec<TokenTransfer>),
+    Transfer(TokenTransfers),
     /// Apparent value, that is **not** actually transferred.
     ///
     /// Set when in a `DELEGATECALL` call type, and used by the `CALLVALUE` opcode.
-    Apparent(Vec<TokenTransfer>),
+    Apparent(TokenTransfers),
 }
 
 impl Default for CallValues {
     #[inline]
     fn default() -> Self {
-        CallValues::Transfer(vec![
-            (TokenTransfer {
-                id: BASE_TOKEN_ID,
-                amount: U256::ZERO,
-            }),
-        ])
+        CallValues::Transfer(smallvec![TokenTransfer {
+            id: BASE_TOKEN_ID,
+            amount: U256::ZERO,
+        },])
     }
 }
 
 impl CallValues {
-    /// Returns the call value, regardless of the type.
+    /// Returns the call values, regardless of the type.
     #[inline]
-    pub fn get(&self) -> Vec<TokenTransfer> {
+    pub fn as_slice(&self) -> &[TokenTransfer] {
         match self {
-            Self::Transfer(values) | Self::Apparent(values) => values.clone(),
+            Self::Transfer(values) | Self::Apparent(values) => values,
         }
     }
 
-    /// Returns the transferred value, if any.
+    /// Consumes `self` and returns the call values, regardless of the type.
     #[inline]
-    pub fn transfer(&self) -> Vec<TokenTransfer> {
+    pub fn into_inner(self) -> TokenTransfers {
         match self {
-            Self::Transfer(values) => values.clone(),
-            Self::Apparent(_) => Vec::new(),
+            Self::Transfer(values) | Self::Apparent(values) => values,
         }
     }
 
+    /// Returns the transferred values, or an empty slice if the values are apparent.
+    #[inline]
+    pub fn transferred(&self) -> &[TokenTransfer] {
+        match self {
+            Self::Transfer(values) => values,
+            Self::Apparent(_) => &[],
+        }
+    }
+
+    /// Returns the apparent values, or an empty slice if the values are transferred.
+    #[inline]
+    pub fn apparent_values(&self) -> &[TokenTransfer] {
+        match self {
+            Self::Transfer(_) => &[],
+            Self::Apparent(values) => values,
+        }
+    }
+
+    /// Returns the call values of the called frame: the values with a non-zero amount,
+    /// regardless of the type.
+    #[inline]
+    pub fn frame_values(&self) -> impl Iterator<Item = &TokenTransfer> {
+        self.as_slice()
+            .iter()
+            .filter(|value| value.amount != U256::ZERO)
+    }
+
+    /// Returns the amount of the given token, regardless of the type.
+    ///
+    /// Returns zero if the token is not part of the call values.
+    #[inline]
+    pub fn amount_of(&self, token_id: U256) -> U256 {
+        self.as_slice()
+            .iter()
+            .find(|tt| tt.id == token_id)
+            .map_or(U256::ZERO, |tt| tt.amount)
+    }
+
+    /// Returns the call value, regardless of the type.
+    #[inline]
+    #[deprecated = "use `as_slice` or `into_inner` instead"]
+    pub fn get(&self) -> Vec<TokenTransfer> {
+        self.as_slice().to_vec()
+    }
+
+    /// Returns the transferred value, if any.
+    #[inline]
+    #[deprecated = "use `transferred` instead"]
+    pub fn transfer(&self) -> Vec<TokenTransfer> {
+        self.transferred().to_vec()
+    }
+
     /// Returns whether the call value will be transferred.
     #[inline]
     pub const fn is_transfer(&self) -> bool {
@@ -184,11 +264,9 @@ impl CallValues {
 
     /// Returns the apparent value, if any.
     #[inline]
+    #[deprecated = "use `apparent_values` instead"]
     pub fn apparent(&self) -> Vec<TokenTransfer> {
-        match self {
-            Self::Transfer(_) => Vec::new(),
-            Self::Apparent(values) => values.clone(),
-        }
+        self.apparent_values().to_vec()
     }
 
     /// Returns whether the call value is apparent, and not actually transferred.
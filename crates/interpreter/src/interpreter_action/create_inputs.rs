@@ -19,8 +19,9 @@ pub struct CreateInputs {
 }
 
 impl CreateInputs {
-    /// Creates new create inputs.
-    pub fn new(tx_env: &TxEnv, gas_limit: u64) -> Option<Self> {
+    /// Creates new create inputs, whose value is the amount of the base token transferred by the
+    /// transaction.
+    pub fn new(tx_env: &TxEnv, gas_limit: u64, base_token_id: U256) -> Option<Self> {
         let TransactTo::Create = tx_env.transact_to else {
             return None;
         };
@@ -28,15 +29,15 @@ impl CreateInputs {
         Some(CreateInputs {
             caller: tx_env.caller,
             scheme: CreateScheme::Create,
-            value: tx_env.get_base_transfer_value(), //TODO: pass all of the transferred tokens when contract deployment is permissionless
+            value: tx_env.transferred_amount(base_token_id), //TODO: pass all of the transferred tokens when contract deployment is permissionless
             init_code: tx_env.data.clone(),
             gas_limit,
         })
     }
 
     /// Returns boxed create inputs.
-    pub fn new_boxed(tx_env: &TxEnv, gas_limit: u64) -> Option<Box<Self>> {
-        Self::new(tx_env, gas_limit).map(Box::new)
+    pub fn new_boxed(tx_env: &TxEnv, gas_limit: u64, base_token_id: U256) -> Option<Box<Self>> {
+        Self::new(tx_env, gas_limit, base_token_id).map(Box::new)
     }
 
     /// Returns the address that this create call will create.
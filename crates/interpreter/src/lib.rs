@@ -17,6 +17,7 @@ use serde_json as _;
 #[cfg(test)]
 use walkdir as _;
 
+pub mod failure;
 mod function_stack;
 pub mod gas;
 mod host;
@@ -27,6 +28,7 @@ pub mod interpreter_action;
 pub mod opcode;
 
 // Reexport primary types.
+pub use failure::TransferFailure;
 pub use function_stack::{FunctionReturnFrame, FunctionStack};
 pub use gas::Gas;
 pub use host::{DummyHost, Host, LoadAccountResult, SStoreResult, SelfDestructResult};
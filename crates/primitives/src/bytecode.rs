@@ -4,7 +4,16 @@ pub mod legacy;
 pub use eof::Eof;
 pub use legacy::{JumpTable, LegacyAnalyzedBytecode};
 
-use crate::{keccak256, Bytes, B256, KECCAK_EMPTY};
+use crate::{keccak256, Address, Bytes, B256, KECCAK_EMPTY};
+use std::vec::Vec;
+
+/// The prefix of the code of an account that delegates its code to another account, followed
+/// by the address of that account.
+///
+/// Incorporated as part of the Prague upgrade via [EIP-7702].
+///
+/// [EIP-7702]: https://eips.ethereum.org/EIPS/eip-7702
+pub const EIP7702_MAGIC_BYTES: [u8; 3] = [0xef, 0x01, 0x00];
 
 /// State of the [`Bytecode`] analysis.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -66,6 +75,31 @@ impl Bytecode {
         matches!(self, Self::Eof(_))
     }
 
+    /// Creates the delegation designation of an account whose code is the code of `address`.
+    #[inline]
+    pub fn new_eip7702(address: Address) -> Self {
+        let mut bytes = Vec::with_capacity(EIP7702_MAGIC_BYTES.len() + Address::len_bytes());
+        bytes.extend_from_slice(&EIP7702_MAGIC_BYTES);
+        bytes.extend_from_slice(address.as_slice());
+        Self::new_raw(bytes.into())
+    }
+
+    /// Returns the address the code is delegated to if the bytecode is a delegation
+    /// designation.
+    #[inline]
+    pub fn eip7702_address(&self) -> Option<Address> {
+        self.original_byte_slice()
+            .strip_prefix(&EIP7702_MAGIC_BYTES)
+            .filter(|address| address.len() == Address::len_bytes())
+            .map(Address::from_slice)
+    }
+
+    /// Return true if bytecode is a delegation designation.
+    #[inline]
+    pub fn is_eip7702(&self) -> bool {
+        self.eip7702_address().is_some()
+    }
+
     /// Creates a new raw [`Bytecode`].
     #[inline]
     pub fn new_raw(bytecode: Bytes) -> Self {
@@ -26,11 +26,23 @@ pub const BLOCKHASH_SERVE_WINDOW: usize = 8192;
 /// This is named `HISTORY_STORAGE_ADDRESS` in the EIP.
 pub const BLOCKHASH_STORAGE_ADDRESS: Address = address!("25a219378dad9b3503c8268c9ca836a52427a4fb");
 
+/// The system account recording the block emissions: its storage slot zero holds the number of
+/// the last block whose [emissions](crate::BlockEnv::emissions) were applied, plus one.
+///
+/// The emissions are minted with this account as the minter.
+pub const EMISSIONS_ADDRESS: Address = address!("00000000000000000000000000000000000e1551");
+
 /// EIP-3860: Limit and meter initcode
 ///
 /// Limit of maximum initcode size is `2 * MAX_CODE_SIZE`.
 pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
 
+/// The divisor of the squared number of memory words in the memory expansion cost,
+/// `3 * words + words² / 512`.
+///
+/// The default of [`CfgEnv::memory_gas_quotient`](crate::CfgEnv::memory_gas_quotient).
+pub const MEMORY_GAS_QUOTIENT: u64 = 512;
+
 /// The address of precompile 3, which is handled specially in a few places.
 pub const PRECOMPILE3: Address =
     Address::new([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3]);
@@ -61,5 +73,6 @@ pub const BLOB_GASPRICE_UPDATE_FRACTION: u64 = 3338477;
 /// First version of the blob.
 pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
 
-// ID of base token
+/// ID of the base token, unless the chain configures another one in
+/// [CfgEnv::base_token_id](crate::CfgEnv::base_token_id).
 pub const BASE_TOKEN_ID: U256 = U256::ZERO;
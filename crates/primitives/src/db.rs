@@ -1,6 +1,6 @@
 use crate::{AccountInfo, Address, Bytecode, EvmState, B256, U256};
 use auto_impl::auto_impl;
-use std::vec::Vec;
+use std::{boxed::Box, vec::Vec};
 
 pub mod components;
 pub use components::{
@@ -37,6 +37,22 @@ pub trait Database {
 pub trait DatabaseCommit {
     /// Commit changes to the database.
     fn commit(&mut self, changes: EvmState);
+
+    /// Commit changes to the database chunk by chunk, e.g. the chunks of [EvmState::into_chunks].
+    ///
+    /// Every chunk is committed and dropped before the next one is requested, so a database
+    /// converting the changes into its own representation doesn't hold both representations of
+    /// all the changes at once.
+    #[auto_impl(keep_default_for(&mut, Box))]
+    fn commit_chunks<I>(&mut self, chunks: I)
+    where
+        Self: Sized,
+        I: IntoIterator<Item = EvmState>,
+    {
+        for chunk in chunks {
+            self.commit(chunk);
+        }
+    }
 }
 
 /// EVM database interface.
@@ -69,6 +85,56 @@ pub trait DatabaseRef {
     fn get_token_ids_ref(&self) -> Result<Vec<U256>, Self::Error>;
 }
 
+/// An iterator over the entries of a [`DatabaseIter`].
+pub type DatabaseEntries<'a, T> = Box<dyn Iterator<Item = T> + 'a>;
+
+/// EVM database iteration interface.
+///
+/// Lists the accounts of the database and the storage of an account, which [`DatabaseRef`]
+/// can only look up one key at a time. It is needed to export the state, to generate witnesses,
+/// or to clear the storage of a selfdestructed account. Databases that cannot be listed, such
+/// as one reading from a remote node, don't implement it.
+pub trait DatabaseIter: DatabaseRef {
+    /// Returns the existing accounts, in no particular order.
+    fn accounts_iter(&self) -> Result<DatabaseEntries<'_, (Address, AccountInfo)>, Self::Error>;
+
+    /// Returns the nonzero storage slots of the account, in no particular order.
+    fn storage_iter(
+        &self,
+        address: Address,
+    ) -> Result<DatabaseEntries<'_, (U256, U256)>, Self::Error>;
+}
+
+impl<T: DatabaseIter + ?Sized> DatabaseIter for &T {
+    #[inline]
+    fn accounts_iter(&self) -> Result<DatabaseEntries<'_, (Address, AccountInfo)>, Self::Error> {
+        (**self).accounts_iter()
+    }
+
+    #[inline]
+    fn storage_iter(
+        &self,
+        address: Address,
+    ) -> Result<DatabaseEntries<'_, (U256, U256)>, Self::Error> {
+        (**self).storage_iter(address)
+    }
+}
+
+impl<T: DatabaseIter + ?Sized> DatabaseIter for &mut T {
+    #[inline]
+    fn accounts_iter(&self) -> Result<DatabaseEntries<'_, (Address, AccountInfo)>, Self::Error> {
+        (**self).accounts_iter()
+    }
+
+    #[inline]
+    fn storage_iter(
+        &self,
+        address: Address,
+    ) -> Result<DatabaseEntries<'_, (U256, U256)>, Self::Error> {
+        (**self).storage_iter(address)
+    }
+}
+
 /// Wraps a [`DatabaseRef`] to provide a [`Database`] implementation.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct WrapDatabaseRef<T: DatabaseRef>(pub T);
@@ -120,6 +186,40 @@ impl<T: DatabaseRef> Database for WrapDatabaseRef<T> {
     }
 }
 
+impl<T: DatabaseRef> DatabaseRef for WrapDatabaseRef<T> {
+    type Error = T::Error;
+
+    #[inline]
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.0.basic_ref(address)
+    }
+
+    #[inline]
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.0.code_by_hash_ref(code_hash)
+    }
+
+    #[inline]
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.0.storage_ref(address, index)
+    }
+
+    #[inline]
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        self.0.block_hash_ref(number)
+    }
+
+    #[inline]
+    fn is_token_id_valid_ref(&self, token_id: U256) -> Result<bool, Self::Error> {
+        self.0.is_token_id_valid_ref(token_id)
+    }
+
+    #[inline]
+    fn get_token_ids_ref(&self) -> Result<Vec<U256>, Self::Error> {
+        self.0.get_token_ids_ref()
+    }
+}
+
 impl<T: DatabaseRef + DatabaseCommit> DatabaseCommit for WrapDatabaseRef<T> {
     #[inline]
     fn commit(&mut self, changes: EvmState) {
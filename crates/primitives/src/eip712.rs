@@ -0,0 +1,349 @@
+//! [EIP-712] hashing of the typed structures signed for SabVM.
+//!
+//! A signer approves a structure by signing its [`signing_hash`], which binds the
+//! [struct hash](Eip712Struct::struct_hash) to the [domain](Eip712Domain) of the contract or chain
+//! that checks the signature, so that a signature can't be replayed elsewhere.
+//!
+//! [EIP-712]: https://eips.ethereum.org/EIPS/eip-712
+
+use crate::{b256, keccak256, Address, B256, U256};
+use std::{string::String, vec::Vec};
+
+/// The type hash of [`Eip712Domain`]: `keccak256("EIP712Domain(string name,string version,uint256
+/// chainId,address verifyingContract)")`.
+pub const EIP712_DOMAIN_TYPEHASH: B256 =
+    b256!("8b73c3c69bb8fe3d512ecc4cf759cc79239f7b179b0ffacaa9a75d522b39400f");
+
+/// The prefix of the message hashed into the [`signing_hash`].
+pub const EIP712_PREFIX: [u8; 2] = [0x19, 0x01];
+
+/// The domain that a typed structure is signed for.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Eip712Domain {
+    /// The name of the signing domain, e.g. of the contract or the protocol.
+    pub name: String,
+    /// The version of the signing domain. Signatures are valid for a single version.
+    pub version: String,
+    /// The chain ID the signatures are valid on.
+    pub chain_id: u64,
+    /// The address of the contract that checks the signatures.
+    pub verifying_contract: Address,
+}
+
+impl Eip712Domain {
+    /// Creates a new domain.
+    pub fn new(
+        name: impl Into<String>,
+        version: impl Into<String>,
+        chain_id: u64,
+        verifying_contract: Address,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            chain_id,
+            verifying_contract,
+        }
+    }
+
+    /// Returns the domain separator, the struct hash of the domain.
+    pub fn separator(&self) -> B256 {
+        let mut encoder = Eip712Encoder::new(EIP712_DOMAIN_TYPEHASH);
+        encoder
+            .string(&self.name)
+            .string(&self.version)
+            .uint(U256::from(self.chain_id))
+            .address(self.verifying_contract);
+        encoder.finish()
+    }
+
+    /// Returns the hash that the signer of `value` signs in this domain.
+    pub fn signing_hash<T: Eip712Struct>(&self, value: &T) -> B256 {
+        signing_hash(self.separator(), value.struct_hash())
+    }
+}
+
+/// A structure that can be signed as [EIP-712] typed data.
+///
+/// [EIP-712]: https://eips.ethereum.org/EIPS/eip-712
+pub trait Eip712Struct {
+    /// The encoded type, e.g. `Mail(address from,address to,string contents)`, followed by the
+    /// types of the referenced structures sorted by name.
+    const ENCODED_TYPE: &'static str;
+
+    /// Encodes the members of the structure, in the order of [`Self::ENCODED_TYPE`].
+    fn encode_data(&self, encoder: &mut Eip712Encoder);
+
+    /// Returns the type hash, the Keccak-256 hash of [`Self::ENCODED_TYPE`].
+    fn type_hash() -> B256 {
+        keccak256(Self::ENCODED_TYPE)
+    }
+
+    /// Returns the struct hash, `keccak256(type_hash ++ encode_data)`.
+    fn struct_hash(&self) -> B256 {
+        let mut encoder = Eip712Encoder::new(Self::type_hash());
+        self.encode_data(&mut encoder);
+        encoder.finish()
+    }
+}
+
+/// Returns the hash that is signed for the struct hash in the domain:
+/// `keccak256("\x19\x01" ++ domain_separator ++ struct_hash)`.
+pub fn signing_hash(domain_separator: B256, struct_hash: B256) -> B256 {
+    let mut out = [0; 66];
+    out[..2].copy_from_slice(&EIP712_PREFIX);
+    out[2..34].copy_from_slice(domain_separator.as_slice());
+    out[34..].copy_from_slice(struct_hash.as_slice());
+    keccak256(out)
+}
+
+/// Encodes the members of a typed structure into 32-byte words.
+#[derive(Clone, Debug)]
+pub struct Eip712Encoder {
+    out: Vec<u8>,
+}
+
+impl Eip712Encoder {
+    /// Creates an encoder of the structure with the type hash.
+    pub fn new(type_hash: B256) -> Self {
+        let mut out = Vec::with_capacity(32 * 8);
+        out.extend_from_slice(type_hash.as_slice());
+        Self { out }
+    }
+
+    /// Encodes a `bytes32` member.
+    pub fn word(&mut self, word: B256) -> &mut Self {
+        self.out.extend_from_slice(word.as_slice());
+        self
+    }
+
+    /// Encodes a `uint256` member.
+    pub fn uint(&mut self, value: U256) -> &mut Self {
+        self.word(value.into())
+    }
+
+    /// Encodes an `address` member.
+    pub fn address(&mut self, address: Address) -> &mut Self {
+        self.word(address.into_word())
+    }
+
+    /// Encodes a `bool` member.
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.uint(U256::from(value))
+    }
+
+    /// Encodes a `bytes` member as its Keccak-256 hash.
+    pub fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.word(keccak256(bytes))
+    }
+
+    /// Encodes a `string` member as the Keccak-256 hash of its UTF-8 bytes.
+    pub fn string(&mut self, string: &str) -> &mut Self {
+        self.bytes(string.as_bytes())
+    }
+
+    /// Encodes a member that is itself a structure, as its struct hash.
+    pub fn struct_member<T: Eip712Struct>(&mut self, value: &T) -> &mut Self {
+        self.word(value.struct_hash())
+    }
+
+    /// Returns the Keccak-256 hash of the encoding.
+    pub fn finish(self) -> B256 {
+        keccak256(self.out)
+    }
+}
+
+/// A permit for `spender` to transfer up to `amount` of the native token `token_id` held by
+/// `owner`, signed by the owner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenPermit {
+    /// The account whose tokens are transferred, and that signs the permit.
+    pub owner: Address,
+    /// The account allowed to transfer the tokens.
+    pub spender: Address,
+    /// The ID of the native token.
+    pub token_id: U256,
+    /// The amount of tokens the spender may transfer.
+    pub amount: U256,
+    /// The nonce of the permit, which is used once.
+    pub nonce: U256,
+    /// The timestamp after which the permit expires.
+    pub deadline: U256,
+}
+
+impl Eip712Struct for TokenPermit {
+    const ENCODED_TYPE: &'static str = "TokenPermit(address owner,address spender,uint256 tokenId,\
+         uint256 amount,uint256 nonce,uint256 deadline)";
+
+    fn encode_data(&self, encoder: &mut Eip712Encoder) {
+        encoder
+            .address(self.owner)
+            .address(self.spender)
+            .uint(self.token_id)
+            .uint(self.amount)
+            .uint(self.nonce)
+            .uint(self.deadline);
+    }
+}
+
+/// The authorization of a gas payer to pay for the gas of a sponsored transaction, see
+/// [`TxEnv::gas_payer`](crate::TxEnv::gas_payer).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GasSponsorship {
+    /// The caller of the sponsored transaction.
+    pub sender: Address,
+    /// The hash signed by the sender, see
+    /// [`SabvmTransaction::signature_hash`](crate::SabvmTransaction::signature_hash), which
+    /// commits to the gas limit and the fees the gas payer pays for.
+    pub transaction_hash: B256,
+}
+
+impl Eip712Struct for GasSponsorship {
+    const ENCODED_TYPE: &'static str = "GasSponsorship(address sender,bytes32 transactionHash)";
+
+    fn encode_data(&self, encoder: &mut Eip712Encoder) {
+        encoder.address(self.sender).word(self.transaction_hash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address;
+
+    struct Person {
+        name: &'static str,
+        wallet: Address,
+    }
+
+    impl Eip712Struct for Person {
+        const ENCODED_TYPE: &'static str = "Person(string name,address wallet)";
+
+        fn encode_data(&self, encoder: &mut Eip712Encoder) {
+            encoder.string(self.name).address(self.wallet);
+        }
+    }
+
+    struct Mail {
+        from: Person,
+        to: Person,
+        contents: &'static str,
+    }
+
+    impl Eip712Struct for Mail {
+        const ENCODED_TYPE: &'static str =
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)";
+
+        fn encode_data(&self, encoder: &mut Eip712Encoder) {
+            encoder
+                .struct_member(&self.from)
+                .struct_member(&self.to)
+                .string(self.contents);
+        }
+    }
+
+    /// The example of the EIP.
+    #[test]
+    fn hashes_the_eip_example() {
+        assert_eq!(
+            keccak256(
+                "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)"
+            ),
+            EIP712_DOMAIN_TYPEHASH
+        );
+        let domain = Eip712Domain::new(
+            "Ether Mail",
+            "1",
+            1,
+            address!("CcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"),
+        );
+        assert_eq!(
+            domain.separator(),
+            b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        let mail = Mail {
+            from: Person {
+                name: "Cow",
+                wallet: address!("CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+            },
+            to: Person {
+                name: "Bob",
+                wallet: address!("bBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+            },
+            contents: "Hello, Bob!",
+        };
+        assert_eq!(
+            Mail::type_hash(),
+            b256!("a0cedeb2dc280ba39b857546d74f5549c3a1d7bdc2dd96bf881f76108e23dac2")
+        );
+        assert_eq!(
+            mail.struct_hash(),
+            b256!("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+        );
+        assert_eq!(
+            domain.signing_hash(&mail),
+            b256!("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn signing_hashes_commit_to_every_field() {
+        let domain = Eip712Domain::new(
+            "SabVM",
+            "1",
+            1,
+            address!("00000000000000000000000000000000000000aa"),
+        );
+        let permit = TokenPermit {
+            owner: address!("00000000000000000000000000000000000000bb"),
+            spender: address!("00000000000000000000000000000000000000cc"),
+            token_id: U256::from(1),
+            amount: U256::from(100),
+            nonce: U256::ZERO,
+            deadline: U256::MAX,
+        };
+        assert_eq!(
+            TokenPermit::ENCODED_TYPE,
+            "TokenPermit(address owner,address spender,uint256 tokenId,uint256 amount,\
+             uint256 nonce,uint256 deadline)"
+        );
+
+        let hash = domain.signing_hash(&permit);
+        for changed in [
+            TokenPermit {
+                token_id: U256::from(2),
+                ..permit
+            },
+            TokenPermit {
+                amount: U256::from(101),
+                ..permit
+            },
+            TokenPermit {
+                nonce: U256::from(1),
+                ..permit
+            },
+        ] {
+            assert_ne!(domain.signing_hash(&changed), hash);
+        }
+        let other_chain = Eip712Domain {
+            chain_id: 2,
+            ..domain.clone()
+        };
+        assert_ne!(other_chain.signing_hash(&permit), hash);
+
+        let sponsorship = GasSponsorship {
+            sender: permit.owner,
+            transaction_hash: B256::repeat_byte(1),
+        };
+        assert_ne!(
+            domain.signing_hash(&sponsorship),
+            domain.signing_hash(&GasSponsorship {
+                transaction_hash: B256::repeat_byte(2),
+                ..sponsorship
+            })
+        );
+    }
+}
@@ -1,15 +1,29 @@
+pub mod authorization;
+pub mod call_request;
 pub mod handler_cfg;
+pub mod transaction;
+pub mod wire;
 
+pub use authorization::{
+    Authorization, RecoveredAuthorization, SignedAuthorization, PER_AUTH_BASE_COST,
+    PER_EMPTY_ACCOUNT_COST,
+};
+pub use call_request::{CallRequest, CallRequestError};
 pub use handler_cfg::{CfgEnvWithHandlerCfg, EnvWithHandlerCfg, HandlerCfg};
 use hashbrown::HashSet;
+pub use transaction::SabvmTransaction;
+pub use wire::{TxDecodeError, TxEncodeError, MULTI_TOKEN_TX_TYPE, UNSIGNED_MULTI_TOKEN_TX_TYPE};
 
 use crate::{
-    calc_blob_gasprice, Account, Address, Bytes, InvalidHeader, InvalidTransaction, Spec, SpecId,
-    B256, BASE_TOKEN_ID, GAS_PER_BLOB, KECCAK_EMPTY, MAX_BLOB_NUMBER_PER_BLOCK, MAX_INITCODE_SIZE,
-    U256, VERSIONED_HASH_VERSION_KZG,
+    calc_blob_gasprice, spec_to_generic, Account, AccountInfo, Address, Bytecode, Bytes,
+    InvalidEnv, InvalidHeader, InvalidTransaction, Spec, SpecId, B256, BASE_TOKEN_ID, GAS_PER_BLOB,
+    KECCAK_EMPTY, MAX_BLOB_NUMBER_PER_BLOCK, MAX_INITCODE_SIZE, MEMORY_GAS_QUOTIENT, U256,
+    VERSIONED_HASH_VERSION_KZG,
 };
 use core::cmp::{min, Ordering};
 use core::hash::Hash;
+use core::ops::ControlFlow;
+use smallvec::SmallVec;
 use std::boxed::Box;
 use std::vec::Vec;
 
@@ -73,18 +87,72 @@ impl Env {
         })
     }
 
+    /// Validates the block and transaction environment for the given spec.
+    ///
+    /// Unlike [`Env::validate_block_env`] and [`Env::validate_tx`], which stop at the first
+    /// problem, this returns every problem found, in the order they are checked in. An empty
+    /// result means that the environment is valid.
+    pub fn validate(&self, spec_id: SpecId) -> Vec<InvalidEnv> {
+        let mut problems = Vec::new();
+        spec_to_generic!(spec_id, {
+            let _ = self.check_block_env::<SPEC>(&mut |e| {
+                problems.push(InvalidEnv::Header(e));
+                ControlFlow::Continue(())
+            });
+            let _ = self.check_tx::<SPEC>(&mut |e| {
+                problems.push(InvalidEnv::Transaction(e));
+                ControlFlow::Continue(())
+            });
+        });
+        problems
+    }
+
+    /// Validates the transaction against the caller account and, for sponsored transactions,
+    /// the gas payer account for the given spec, returning every problem found.
+    ///
+    /// See [`Env::validate_tx_against_state`] and [`Env::validate_gas_payer_against_state`],
+    /// which stop at the first problem.
+    pub fn validate_against_state(
+        &self,
+        spec_id: SpecId,
+        caller: &Account,
+        gas_payer: Option<&Account>,
+    ) -> Vec<InvalidTransaction> {
+        let mut problems = Vec::new();
+        let mut report = |e| {
+            problems.push(e);
+            ControlFlow::Continue(())
+        };
+        spec_to_generic!(spec_id, {
+            let _ = self.check_tx_against_state::<SPEC>(&mut caller.clone(), &mut report);
+            if let Some(gas_payer) = gas_payer {
+                let _ =
+                    self.check_gas_payer_against_state::<SPEC>(&mut gas_payer.clone(), &mut report);
+            }
+        });
+        problems
+    }
+
     /// Validate the block environment.
     #[inline]
     pub fn validate_block_env<SPEC: Spec>(&self) -> Result<(), InvalidHeader> {
+        first_error(|report| self.check_block_env::<SPEC>(report))
+    }
+
+    /// Reports the problems with the block environment, stopping when `report` breaks.
+    fn check_block_env<SPEC: Spec>(
+        &self,
+        report: &mut dyn FnMut(InvalidHeader) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
         // `prevrandao` is required for the merge
         if SPEC::enabled(SpecId::MERGE) && self.block.prevrandao.is_none() {
-            return Err(InvalidHeader::PrevrandaoNotSet);
+            report(InvalidHeader::PrevrandaoNotSet)?;
         }
         // `excess_blob_gas` is required for Cancun
         if SPEC::enabled(SpecId::CANCUN) && self.block.blob_excess_gas_and_price.is_none() {
-            return Err(InvalidHeader::ExcessBlobGasNotSet);
+            report(InvalidHeader::ExcessBlobGasNotSet)?;
         }
-        Ok(())
+        ControlFlow::Continue(())
     }
 
     /// Validate transaction data that is set inside ENV and return error if something is wrong.
@@ -92,12 +160,20 @@ impl Env {
     /// Return initial spend gas (Gas needed to execute transaction).
     #[inline]
     pub fn validate_tx<SPEC: Spec>(&self) -> Result<(), InvalidTransaction> {
+        first_error(|report| self.check_tx::<SPEC>(report))
+    }
+
+    /// Reports the problems with the transaction, stopping when `report` breaks.
+    fn check_tx<SPEC: Spec>(
+        &self,
+        report: &mut dyn FnMut(InvalidTransaction) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
         // BASEFEE tx check
         if SPEC::enabled(SpecId::LONDON) {
             if let Some(priority_fee) = self.tx.gas_priority_fee {
                 if priority_fee > self.tx.gas_price {
                     // or gas_max_fee for eip1559
-                    return Err(InvalidTransaction::PriorityFeeGreaterThanMaxFee);
+                    report(InvalidTransaction::PriorityFeeGreaterThanMaxFee)?;
                 }
             }
 
@@ -105,7 +181,7 @@ impl Env {
             if !self.cfg.is_base_fee_check_disabled()
                 && self.effective_gas_price() < self.block.basefee
             {
-                return Err(InvalidTransaction::GasPriceLessThanBasefee);
+                report(InvalidTransaction::GasPriceLessThanBasefee)?;
             }
         }
 
@@ -113,7 +189,7 @@ impl Env {
         if !self.cfg.is_block_gas_limit_disabled()
             && U256::from(self.tx.gas_limit) > self.block.gas_limit
         {
-            return Err(InvalidTransaction::CallerGasLimitMoreThanBlock);
+            report(InvalidTransaction::CallerGasLimitMoreThanBlock)?;
         }
 
         // EIP-3860: Limit and meter initcode
@@ -124,20 +200,20 @@ impl Env {
                 .map(|limit| limit.saturating_mul(2))
                 .unwrap_or(MAX_INITCODE_SIZE);
             if self.tx.data.len() > max_initcode_size {
-                return Err(InvalidTransaction::CreateInitCodeSizeLimit);
+                report(InvalidTransaction::CreateInitCodeSizeLimit)?;
             }
         }
 
         // Check if the transaction's chain id is correct
         if let Some(tx_chain_id) = self.tx.chain_id {
             if tx_chain_id != self.cfg.chain_id {
-                return Err(InvalidTransaction::InvalidChainId);
+                report(InvalidTransaction::InvalidChainId)?;
             }
         }
 
         // Check that access list is empty for transactions before BERLIN
         if !SPEC::enabled(SpecId::BERLIN) && !self.tx.access_list.is_empty() {
-            return Err(InvalidTransaction::AccessListNotSupported);
+            report(InvalidTransaction::AccessListNotSupported)?;
         }
 
         // - For CANCUN and later, check that the gas price is not more than the tx max
@@ -146,14 +222,16 @@ impl Env {
             // Presence of max_fee_per_blob_gas means that this is blob transaction.
             if let Some(max) = self.tx.max_fee_per_blob_gas {
                 // ensure that the user was willing to at least pay the current blob gasprice
-                let price = self.block.get_blob_gasprice().expect("already checked");
-                if U256::from(price) > max {
-                    return Err(InvalidTransaction::BlobGasPriceGreaterThanMax);
+                // (a missing blob gasprice is reported by the block environment validation)
+                if let Some(price) = self.block.get_blob_gasprice() {
+                    if U256::from(price) > max {
+                        report(InvalidTransaction::BlobGasPriceGreaterThanMax)?;
+                    }
                 }
 
                 // there must be at least one blob
                 if self.tx.blob_hashes.is_empty() {
-                    return Err(InvalidTransaction::EmptyBlobs);
+                    report(InvalidTransaction::EmptyBlobs)?;
                 }
 
                 // The field `to` deviates slightly from the semantics with the exception
@@ -161,32 +239,45 @@ impl Env {
                 // a 20-byte address. This means that blob transactions cannot
                 // have the form of a create transaction.
                 if self.tx.transact_to.is_create() {
-                    return Err(InvalidTransaction::BlobCreateTransaction);
+                    report(InvalidTransaction::BlobCreateTransaction)?;
                 }
 
                 // all versioned blob hashes must start with VERSIONED_HASH_VERSION_KZG
-                for blob in self.tx.blob_hashes.iter() {
-                    if blob[0] != VERSIONED_HASH_VERSION_KZG {
-                        return Err(InvalidTransaction::BlobVersionNotSupported);
-                    }
+                if self
+                    .tx
+                    .blob_hashes
+                    .iter()
+                    .any(|blob| blob[0] != VERSIONED_HASH_VERSION_KZG)
+                {
+                    report(InvalidTransaction::BlobVersionNotSupported)?;
                 }
 
                 // ensure the total blob gas spent is at most equal to the limit
                 // assert blob_gas_used <= MAX_BLOB_GAS_PER_BLOCK
                 let num_blobs = self.tx.blob_hashes.len();
                 if num_blobs > MAX_BLOB_NUMBER_PER_BLOCK as usize {
-                    return Err(InvalidTransaction::TooManyBlobs {
+                    report(InvalidTransaction::TooManyBlobs {
                         have: num_blobs,
                         max: MAX_BLOB_NUMBER_PER_BLOCK as usize,
-                    });
+                    })?;
                 }
             }
         } else {
             if !self.tx.blob_hashes.is_empty() {
-                return Err(InvalidTransaction::BlobVersionedHashesNotSupported);
+                report(InvalidTransaction::BlobVersionedHashesNotSupported)?;
             }
             if self.tx.max_fee_per_blob_gas.is_some() {
-                return Err(InvalidTransaction::MaxFeePerBlobGasNotSupported);
+                report(InvalidTransaction::MaxFeePerBlobGasNotSupported)?;
+            }
+        }
+
+        // EIP-7702: Set EOA account code
+        if !self.tx.authorization_list.is_empty() {
+            if !SPEC::enabled(SpecId::PRAGUE) {
+                report(InvalidTransaction::AuthorizationListNotSupported)?;
+            }
+            if self.tx.transact_to.is_create() || !self.tx.blob_hashes.is_empty() {
+                report(InvalidTransaction::AuthorizationListInvalidFields)?;
             }
         }
 
@@ -197,11 +288,17 @@ impl Env {
             let unique_ids: HashSet<&U256> = slice.iter().map(|token| &token.id).collect();
 
             if unique_ids.len() != slice.len() {
-                return Err(InvalidTransaction::TokenIdsNotUnique);
+                report(InvalidTransaction::TokenIdsNotUnique)?;
             }
         }
 
-        Ok(())
+        // Every token is tipped at most once
+        let unique_tip_ids: HashSet<&U256> = self.tx.tip_tokens.iter().map(|tip| &tip.id).collect();
+        if unique_tip_ids.len() != self.tx.tip_tokens.len() {
+            report(InvalidTransaction::TokenIdsNotUnique)?;
+        }
+
+        ControlFlow::Continue(())
     }
 
     /// Validate transaction against state.
@@ -210,11 +307,27 @@ impl Env {
         &self,
         account: &mut Account,
     ) -> Result<(), InvalidTransaction> {
+        first_error(|report| self.check_tx_against_state::<SPEC>(account, report))
+    }
+
+    /// Reports the problems with the transaction given the caller account, stopping when
+    /// `report` breaks.
+    fn check_tx_against_state<SPEC: Spec>(
+        &self,
+        account: &mut Account,
+        report: &mut dyn FnMut(InvalidTransaction) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
         // EIP-3607: Reject transactions from senders with deployed code
         // This EIP is introduced after london but there was no collision in past
-        // so we can leave it enabled always
-        if !self.cfg.is_eip3607_disabled() && account.info.code_hash != KECCAK_EMPTY {
-            return Err(InvalidTransaction::RejectCallerWithCode);
+        // so we can leave it enabled always.
+        // EIP-7702: EOAs whose code is a delegation designation can still send transactions.
+        // Impersonated callers send transactions without a signature, even if they have code.
+        if !self.cfg.is_eip3607_disabled()
+            && !self.cfg.is_impersonated(self.tx.caller)
+            && account.info.code_hash != KECCAK_EMPTY
+            && !account.info.code.as_ref().is_some_and(Bytecode::is_eip7702)
+        {
+            report(InvalidTransaction::RejectCallerWithCode)?;
         }
 
         // Check that the transaction's nonce is correct
@@ -222,70 +335,185 @@ impl Env {
             let state = account.info.nonce;
             match tx.cmp(&state) {
                 Ordering::Greater => {
-                    return Err(InvalidTransaction::NonceTooHigh { tx, state });
+                    report(InvalidTransaction::NonceTooHigh { tx, state })?;
                 }
                 Ordering::Less => {
-                    return Err(InvalidTransaction::NonceTooLow { tx, state });
+                    report(InvalidTransaction::NonceTooLow { tx, state })?;
                 }
                 _ => {}
             }
         }
 
-        let mut required_base_balance = U256::from(self.tx.gas_limit)
-            .checked_mul(self.tx.gas_price)
-            .and_then(|gas_cost| gas_cost.checked_add(self.tx.get_base_transfer_value()))
-            .ok_or(InvalidTransaction::OverflowPaymentInTransaction)?;
-
-        if SPEC::enabled(SpecId::CANCUN) {
-            // if the tx is not a blob tx, this will be None, so we add zero
-            let data_fee = self.calc_max_data_fee().unwrap_or_default();
-            required_base_balance = required_base_balance
-                .checked_add(U256::from(data_fee))
-                .ok_or(InvalidTransaction::OverflowPaymentInTransaction)?;
-        }
-
-        // Check if the account has enough base balance for gas_limit*gas_price and value transfer.
+        // The gas of sponsored transactions is paid by the gas payer
+        let gas_cost = if self.tx.is_sponsored() {
+            Some(U256::ZERO)
+        } else {
+            self.max_gas_cost::<SPEC>()
+        };
+        let base_token_id = self.cfg.base_token_id;
+        let required_base_balance = gas_cost.and_then(|gas_cost| {
+            gas_cost
+                .checked_add(self.tx.transferred_amount(base_token_id))?
+                .checked_add(self.tx.tip_amount(base_token_id))
+        });
+
+        // Check if the account has enough base balance for gas_limit*gas_price, value transfer
+        // and tip.
         // Transfer will be done inside `*_inner` functions.
-        let base_token_balance = account.info.get_base_balance();
-        if required_base_balance > base_token_balance {
-            if self.cfg.is_balance_check_disabled() {
-                // Add transaction cost to balance to ensure execution doesn't fail.
-                account.info.set_base_balance(required_base_balance);
-            } else {
-                return Err(InvalidTransaction::LackOfFundForMaxFee {
-                    fee: Box::new(required_base_balance),
-                    balance: Box::new(base_token_balance),
-                });
+        let base_token_balance = account.info.get_balance(base_token_id);
+        match required_base_balance {
+            None => report(InvalidTransaction::OverflowPaymentInTransaction)?,
+            Some(required_base_balance) if required_base_balance > base_token_balance => {
+                if self.cfg.is_balance_check_disabled() {
+                    // Add transaction cost to balance to ensure execution doesn't fail.
+                    account
+                        .info
+                        .set_balance(base_token_id, required_base_balance);
+                } else {
+                    report(InvalidTransaction::LackOfFundForMaxFee {
+                        fee: Box::new(required_base_balance),
+                        balance: Box::new(base_token_balance),
+                    })?;
+                }
             }
+            Some(_) => {}
         }
 
-        // If other native tokens are being transferred in the tx, then, for each of the tokens,
-        // check that the account has a balance big enough to cover the transfer amount
-        if !self.tx.transferred_tokens.is_empty() {
-            for transferred_token in self
+        // If other native tokens are being transferred or tipped in the tx, then, for each of the
+        // tokens, check that the account has a balance big enough to cover the amounts
+        let mut checked_token_ids = Vec::new();
+        for token in self.tx.transferred_tokens.iter().chain(&self.tx.tip_tokens) {
+            let token_id = token.id;
+            if token_id == base_token_id || checked_token_ids.contains(&token_id) {
+                continue;
+            }
+            checked_token_ids.push(token_id);
+
+            let Some(required_balance) = self
                 .tx
-                .transferred_tokens
-                .iter()
-                .filter(|token| token.id != BASE_TOKEN_ID)
-            {
-                let (token_id, transfer_amount) = (transferred_token.id, transferred_token.amount);
-                let token_balance = account.info.get_balance(token_id);
-                if token_balance >= transfer_amount {
-                    continue;
+                .transferred_amount(token_id)
+                .checked_add(self.tx.tip_amount(token_id))
+            else {
+                report(InvalidTransaction::OverflowPaymentInTransaction)?;
+                continue;
+            };
+            let token_balance = account.info.get_balance(token_id);
+            if token_balance >= required_balance {
+                continue;
+            }
+
+            report(InvalidTransaction::NotEnoughTokenBalanceForTransfer {
+                token_id: Box::new(token_id),
+                required_balance: Box::new(required_balance),
+                actual_balance: Box::new(token_balance),
+            })?;
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Validate the gas payer of a sponsored transaction against state.
+    ///
+    /// Does nothing if the caller pays for the gas, which is checked by
+    /// [`Env::validate_tx_against_state`].
+    #[inline]
+    pub fn validate_gas_payer_against_state<SPEC: Spec>(
+        &self,
+        payer: &mut Account,
+    ) -> Result<(), InvalidTransaction> {
+        first_error(|report| self.check_gas_payer_against_state::<SPEC>(payer, report))
+    }
+
+    /// Reports the problems with the gas payer account, stopping when `report` breaks.
+    fn check_gas_payer_against_state<SPEC: Spec>(
+        &self,
+        payer: &mut Account,
+        report: &mut dyn FnMut(InvalidTransaction) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        if !self.tx.is_sponsored() {
+            return ControlFlow::Continue(());
+        }
+
+        let balance = payer.info.get_balance(self.cfg.base_token_id);
+        match self.max_gas_cost::<SPEC>() {
+            None => report(InvalidTransaction::OverflowPaymentInTransaction)?,
+            Some(fee) if fee > balance => {
+                if self.cfg.is_balance_check_disabled() {
+                    // Add transaction cost to balance to ensure execution doesn't fail.
+                    payer.info.set_balance(self.cfg.base_token_id, fee);
+                } else {
+                    report(InvalidTransaction::GasPayerLackOfFundForMaxFee {
+                        fee: Box::new(fee),
+                        balance: Box::new(balance),
+                    })?;
                 }
+            }
+            Some(_) => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Validate the tips of the transaction against the beneficiary of the block, whose balances
+    /// have to be able to receive them.
+    #[inline]
+    pub fn validate_tips_against_beneficiary(
+        &self,
+        beneficiary: &AccountInfo,
+    ) -> Result<(), InvalidTransaction> {
+        first_error(|report| self.check_tips_against_beneficiary(beneficiary, report))
+    }
 
-                return Err(InvalidTransaction::NotEnoughTokenBalanceForTransfer {
-                    token_id: Box::new(token_id),
-                    required_balance: Box::new(transfer_amount),
-                    actual_balance: Box::new(token_balance),
-                });
+    /// Reports the tips that would overflow the balances of the beneficiary, stopping when
+    /// `report` breaks.
+    fn check_tips_against_beneficiary(
+        &self,
+        beneficiary: &AccountInfo,
+        report: &mut dyn FnMut(InvalidTransaction) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        // the tips the beneficiary pays to itself are deducted before they are paid back
+        if self.block.coinbase == self.tx.caller {
+            return ControlFlow::Continue(());
+        }
+        for tip in nonzero_token_transfers(&self.tx.tip_tokens) {
+            if beneficiary
+                .get_balance(tip.id)
+                .checked_add(tip.amount)
+                .is_none()
+            {
+                report(InvalidTransaction::TipOverflow {
+                    token_id: Box::new(tip.id),
+                })?;
             }
         }
+        ControlFlow::Continue(())
+    }
 
-        Ok(())
+    /// Returns the cost of the gas of the transaction at the maximum gas price, including the
+    /// maximum data fee, or `None` on overflow.
+    fn max_gas_cost<SPEC: Spec>(&self) -> Option<U256> {
+        let gas_cost = U256::from(self.tx.gas_limit).checked_mul(self.tx.gas_price)?;
+        if SPEC::enabled(SpecId::CANCUN) {
+            // if the tx is not a blob tx, this will be None, so we add zero
+            let data_fee = self.calc_max_data_fee().unwrap_or_default();
+            gas_cost.checked_add(data_fee)
+        } else {
+            Some(gas_cost)
+        }
     }
 }
 
+/// Runs a check that reports problems to a callback, returning the first problem.
+fn first_error<E>(
+    check: impl FnOnce(&mut dyn FnMut(E) -> ControlFlow<()>) -> ControlFlow<()>,
+) -> Result<(), E> {
+    let mut error = None;
+    let _ = check(&mut |e| {
+        error = Some(e);
+        ControlFlow::Break(())
+    });
+    error.map_or(Ok(()), Err)
+}
+
 /// EVM configuration.
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -340,6 +568,98 @@ pub struct CfgEnv {
     /// By default, it is set to `false`.
     #[cfg(feature = "optional_beneficiary_reward")]
     pub disable_beneficiary_reward: bool,
+    /// Prunes the zero balances of the accounts, e.g. the ones drained by transfers, from the
+    /// state returned by the EVM, so that they are not persisted.
+    /// By default, it is set to `false` and the state holds the zero balances, like the balances
+    /// of the database it was loaded from.
+    pub prune_zero_balances: bool,
+    /// Allows tokens to be transferred to precompiles, whose balances can't be spent.
+    /// By default, it is set to `false` and transactions transferring tokens to a precompile are
+    /// invalid, while calls doing so fail with [TokenTransferToPrecompile].
+    ///
+    /// [TokenTransferToPrecompile]: crate::HaltReason::TokenTransferToPrecompile
+    pub allow_token_transfers_to_precompiles: bool,
+    /// The address whose received tokens are burned, so that a plain transfer to it removes them
+    /// from the supply.
+    /// By default, it is set to `None` and no address burns the tokens it receives.
+    pub burn_address: Option<Address>,
+    /// The ID of the base token of the chain, which pays for the gas and is the value of the
+    /// `CALL`, `CREATE` and `SELFDESTRUCT` opcodes.
+    /// By default, it is set to [BASE_TOKEN_ID].
+    pub base_token_id: U256,
+    /// The contracts that only expect to receive Native Tokens as the call values of the Native
+    /// Tokens precompile, e.g. contracts ported from an ERC-20 that read the call values with
+    /// `getCallValues`.
+    ///
+    /// A `CALL` made by a contract with a nonzero value transfers the value as a single base
+    /// token transfer, as on Ethereum. With [Self::reject_legacy_value_calls], such calls to
+    /// these contracts fail instead.
+    pub mnt_only_contracts: HashSet<Address>,
+    /// Rejects the `CALL`s made by contracts with a nonzero value to [Self::mnt_only_contracts],
+    /// which fail with [LegacyValueCallToMntOnlyContract] without transferring the value.
+    /// By default, it is set to `false`. Transactions are not affected, as they transfer their
+    /// tokens explicitly.
+    ///
+    /// [LegacyValueCallToMntOnlyContract]: crate::HaltReason::LegacyValueCallToMntOnlyContract
+    pub reject_legacy_value_calls: bool,
+    /// The maximum number of journal entries a transaction can hold, e.g. its loaded accounts,
+    /// storage changes and token transfers.
+    ///
+    /// The limit is checked when a call or create starts and when it returns. A frame returning
+    /// with the journal over the limit fails with [JournalLimitExceeded], which reverts its
+    /// changes and spends its gas, and so do the frames started with the journal over the limit.
+    /// It bounds the memory of the journal when the gas limits are too high to do so.
+    /// By default, it is set to `None` and the journal isn't limited.
+    ///
+    /// [JournalLimitExceeded]: crate::HaltReason::JournalLimitExceeded
+    pub journal_entries_limit: Option<usize>,
+    /// The maximum number of journal entries a single frame can add, including the entries of
+    /// its successful subcalls. It is checked like [Self::journal_entries_limit], when the frame
+    /// returns.
+    /// By default, it is set to `None` and the frames aren't limited.
+    pub frame_journal_entries_limit: Option<usize>,
+    /// Rejects the `mint` and `burn` calls of the Native Tokens precompile made by delegated
+    /// code, e.g. a library that a proxy called with `DELEGATECALL`.
+    ///
+    /// The tokens are minted and burned by the contract whose storage the calling code runs on,
+    /// which is the proxy rather than the library. With this flag, only the contract's own code
+    /// can mint and burn its tokens.
+    /// By default, it is set to `false`.
+    pub reject_delegated_mint_burn: bool,
+    /// The quotient of the gas spent that caps the gas refund of a transaction, e.g. `5` for a
+    /// refund of at most a fifth of the gas spent. A quotient of `0` disables the refunds.
+    /// By default, it is set to `None` and the default quotient of the spec is used.
+    pub max_refund_quotient: Option<u64>,
+    /// Protects the addresses reserved for the Sablier precompiles, `0x7060…`, where no
+    /// precompile is registered yet, so that no balance can be squatted on the address of a
+    /// future precompile.
+    ///
+    /// Transactions calling such an address are invalid, and calls transferring tokens to it fail
+    /// with [TokenTransferToPrecompile]. Contracts are never created in this range.
+    /// By default, it is set to `false`.
+    ///
+    /// [TokenTransferToPrecompile]: crate::HaltReason::TokenTransferToPrecompile
+    pub protect_reserved_addresses: bool,
+    /// The divisor of the quadratic term of the memory expansion cost, `3 * words + words² /
+    /// quotient`. A lower quotient makes large memories more expensive, which bounds the memory a
+    /// transaction can pay for. A quotient of `0` is treated as `1`.
+    /// By default, it is set to [MEMORY_GAS_QUOTIENT], as on Ethereum.
+    pub memory_gas_quotient: u64,
+    /// The call stipends given to the callee of a multi-token call, transferring several tokens
+    /// or a token other than the base token, on top of the gas forwarded by the caller, see
+    /// [CallStipend]. Calls transferring the base token alone, like a `CALL` with value, always
+    /// give the stipend of Ethereum.
+    /// By default, it is set to [CallStipend::Once]: a single stipend whatever the number of
+    /// transferred tokens.
+    pub call_stipend: CallStipend,
+    /// The callers whose transactions are executed without a signature, as if they had signed
+    /// them, like the impersonated accounts of a development node, so that a simulator can
+    /// execute transactions as any address, including a contract.
+    ///
+    /// The transactions of an impersonated caller with code aren't rejected by EIP-3607. The
+    /// caller's nonce is checked as usual, unless the transaction has none.
+    /// By default, it is set to [Impersonation::Disabled], for consensus use.
+    pub impersonation: Impersonation,
 }
 
 impl CfgEnv {
@@ -348,6 +668,70 @@ impl CfgEnv {
         self
     }
 
+    /// Sets the token in which the gas is paid and the rewards are credited, see
+    /// [Self::base_token_id].
+    pub fn with_base_token_id(mut self, base_token_id: U256) -> Self {
+        self.base_token_id = base_token_id;
+        self
+    }
+
+    /// Caps the gas refund of a transaction to the given quotient of the gas spent, see
+    /// [Self::max_refund_quotient].
+    pub fn with_max_refund_quotient(mut self, max_refund_quotient: u64) -> Self {
+        self.max_refund_quotient = Some(max_refund_quotient);
+        self
+    }
+
+    /// Sets the divisor of the quadratic memory expansion cost, see [Self::memory_gas_quotient].
+    pub fn with_memory_gas_quotient(mut self, memory_gas_quotient: u64) -> Self {
+        self.memory_gas_quotient = memory_gas_quotient;
+        self
+    }
+
+    /// Sets the callers whose transactions are executed without a signature, see
+    /// [Self::impersonation].
+    pub fn with_impersonation(mut self, impersonation: Impersonation) -> Self {
+        self.impersonation = impersonation;
+        self
+    }
+
+    /// Sets the stipends given to the callees of multi-token calls, see [Self::call_stipend].
+    pub fn with_call_stipend(mut self, call_stipend: CallStipend) -> Self {
+        self.call_stipend = call_stipend;
+        self
+    }
+
+    /// Sets the hard limit in bytes beyond which the memory can't be resized, see
+    /// [Self::memory_limit].
+    #[cfg(feature = "memory_limit")]
+    pub fn with_memory_limit(mut self, memory_limit: u64) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    /// Returns the quotient of the gas spent that caps the gas refund of a transaction in the
+    /// spec, which is `0` if the refunds are disabled.
+    ///
+    /// Without a configured [Self::max_refund_quotient], it is the default quotient of the spec,
+    /// see [SpecId::max_refund_quotient].
+    pub fn refund_quotient(&self, spec_id: SpecId) -> u64 {
+        if self.is_gas_refund_disabled() {
+            return 0;
+        }
+        self.max_refund_quotient
+            .unwrap_or_else(|| spec_id.max_refund_quotient())
+    }
+
+    /// Returns `true` if the transactions of the caller are executed without a signature, see
+    /// [Self::impersonation].
+    pub fn is_impersonated(&self, caller: Address) -> bool {
+        match &self.impersonation {
+            Impersonation::Disabled => false,
+            Impersonation::Accounts(accounts) => accounts.contains(&caller),
+            Impersonation::All => true,
+        }
+    }
+
     #[cfg(feature = "optional_eip3607")]
     pub fn is_eip3607_disabled(&self) -> bool {
         self.disable_eip3607
@@ -431,6 +815,20 @@ impl Default for CfgEnv {
             disable_base_fee: false,
             #[cfg(feature = "optional_beneficiary_reward")]
             disable_beneficiary_reward: false,
+            prune_zero_balances: false,
+            allow_token_transfers_to_precompiles: false,
+            burn_address: None,
+            base_token_id: BASE_TOKEN_ID,
+            mnt_only_contracts: HashSet::default(),
+            reject_legacy_value_calls: false,
+            journal_entries_limit: None,
+            frame_journal_entries_limit: None,
+            reject_delegated_mint_burn: false,
+            max_refund_quotient: None,
+            protect_reserved_addresses: false,
+            memory_gas_quotient: MEMORY_GAS_QUOTIENT,
+            call_stipend: CallStipend::default(),
+            impersonation: Impersonation::default(),
         }
     }
 }
@@ -476,6 +874,13 @@ pub struct BlockEnv {
     pub blob_excess_gas_and_price: Option<BlobExcessGasAndPrice>,
     /// The list of tokens transferred in the transaction.
     pub transferred_tokens: Vec<TokenTransfer>,
+    /// The Native Tokens minted by the protocol at the start of the block, e.g. the rewards of
+    /// the validators.
+    ///
+    /// They are applied before the first transaction of the block, see
+    /// [EMISSIONS_ADDRESS](crate::EMISSIONS_ADDRESS).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub emissions: Vec<TokenEmission>,
 }
 
 impl BlockEnv {
@@ -527,11 +932,20 @@ impl Default for BlockEnv {
             prevrandao: Some(B256::ZERO),
             blob_excess_gas_and_price: Some(BlobExcessGasAndPrice::new(0)),
             transferred_tokens: Vec::new(),
+            emissions: Vec::new(),
         }
     }
 }
 
 /// The transaction environment.
+///
+/// With the `serde` feature, the transaction is (de)serialized as an object keyed by the field
+/// names, with `U256` quantities as hex strings. The destination is either `"Create"` or
+/// `{"Call": address}`, and the transferred tokens are a list of `{"id", "amount"}` objects.
+/// `transferred_tokens` may be omitted, in which case no tokens are transferred.
+///
+/// Over the wire, transactions transferring native tokens are encoded as typed transactions of
+/// type [`UNSIGNED_MULTI_TOKEN_TX_TYPE`], see [`wire`].
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TxEnv {
@@ -592,7 +1006,41 @@ pub struct TxEnv {
     pub optimism: OptimismFields,
 
     /// The list of tokens transferred in the transaction.
+    #[cfg_attr(feature = "serde", serde(default))]
     pub transferred_tokens: Vec<TokenTransfer>,
+
+    /// A list of addresses and the IDs of the Native Tokens whose balances the transaction
+    /// plans to access.
+    ///
+    /// Like the [access list](Self::access_list), the accounts and the balances start warm and
+    /// are charged in the initial gas. As the balances read by the transaction are declared
+    /// upfront, conflicts between transactions can be detected without executing them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub token_access_list: Vec<(Address, Vec<U256>)>,
+
+    /// The account that pays for the gas of the transaction instead of the caller.
+    ///
+    /// The payer's authorization, e.g. its signature over the transaction, is checked outside
+    /// of the EVM. If set to `None`, the caller pays for the gas.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub gas_payer: Option<Address>,
+
+    /// The tokens tipped by the caller to the beneficiary of the block, on top of the priority
+    /// fee, e.g. to pay the block producer in other tokens than the base token.
+    ///
+    /// Like the gas, the tips are deducted from the caller before the transaction runs, and are
+    /// paid whether it succeeds or not. The caller pays them even if the transaction is
+    /// sponsored.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tip_tokens: Vec<TokenTransfer>,
+
+    /// The authorizations that set the code of their authorities before the transaction runs.
+    ///
+    /// Incorporated as part of the Prague upgrade via [EIP-7702].
+    ///
+    /// [EIP-7702]: https://eips.ethereum.org/EIPS/eip-7702
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub authorization_list: Vec<RecoveredAuthorization>,
 }
 
 pub enum TxType {
@@ -617,20 +1065,38 @@ impl TxEnv {
         *self = Self::default();
     }
 
+    /// Returns the account that pays for the gas of the transaction: the
+    /// [`gas_payer`](Self::gas_payer) if set, the caller otherwise.
+    #[inline]
+    pub fn fee_payer(&self) -> Address {
+        self.gas_payer.unwrap_or(self.caller)
+    }
+
+    /// Returns `true` if the gas of the transaction is paid by an account other than the caller.
+    #[inline]
+    pub fn is_sponsored(&self) -> bool {
+        self.fee_payer() != self.caller
+    }
+
+    /// Returns the amount of [BASE_TOKEN_ID] transferred by the transaction.
     pub fn get_base_transfer_value(&self) -> U256 {
-        if self.transferred_tokens.is_empty() {
-            return Default::default();
-        }
+        self.transferred_amount(BASE_TOKEN_ID)
+    }
 
-        if let Some(token) = self
-            .transferred_tokens
+    /// Returns the amount of the token transferred by the transaction.
+    pub fn transferred_amount(&self, token_id: U256) -> U256 {
+        self.transferred_tokens
             .iter()
-            .find(|token| token.id == BASE_TOKEN_ID)
-        {
-            return token.amount;
-        }
+            .find(|token| token.id == token_id)
+            .map_or(U256::ZERO, |token| token.amount)
+    }
 
-        Default::default()
+    /// Returns the amount of the token tipped to the beneficiary by the transaction.
+    pub fn tip_amount(&self, token_id: U256) -> U256 {
+        self.tip_tokens
+            .iter()
+            .find(|token| token.id == token_id)
+            .map_or(U256::ZERO, |token| token.amount)
     }
 }
 
@@ -651,6 +1117,10 @@ impl Default for TxEnv {
             #[cfg(feature = "optimism")]
             optimism: OptimismFields::default(),
             transferred_tokens: Vec::new(),
+            token_access_list: Vec::new(),
+            gas_payer: None,
+            tip_tokens: Vec::new(),
+            authorization_list: Vec::new(),
         }
     }
 }
@@ -773,10 +1243,74 @@ pub enum AnalysisKind {
     Analyse,
 }
 
+/// How many call stipends the callee of a multi-token call is given, on top of the gas forwarded
+/// by the caller.
+///
+/// On Ethereum, a `CALL` transferring ether gives its callee a stipend of 2300 gas, paid by the
+/// value transfer cost, so that a contract receiving ether can at least log it. A call made by
+/// the Native Tokens precompile can transfer many tokens at once, for a single value transfer
+/// cost. The rule only applies to such multi-token calls: a call transferring the base token
+/// alone gives a single stipend, as on Ethereum.
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CallStipend {
+    /// A single stipend if any token is transferred, whatever the number of tokens.
+    #[default]
+    Once,
+    /// A stipend for every token transferred. The stipends beyond the first one are paid by the
+    /// caller, like the forwarded gas.
+    PerToken,
+    /// No stipend, the callee only has the forwarded gas.
+    Disabled,
+}
+
+impl CallStipend {
+    /// Returns the number of stipends given to the callee of a call transferring nonzero amounts
+    /// of `transferred_tokens` tokens.
+    pub const fn count(self, transferred_tokens: usize) -> u64 {
+        match self {
+            _ if transferred_tokens == 0 => 0,
+            Self::Once => 1,
+            Self::PerToken => transferred_tokens as u64,
+            Self::Disabled => 0,
+        }
+    }
+}
+
+/// The callers whose transactions are executed without a signature, see
+/// [CfgEnv::impersonation].
+#[derive(Clone, Default, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Impersonation {
+    /// Every transaction is expected to be signed by its caller.
+    #[default]
+    Disabled,
+    /// The accounts are impersonated.
+    Accounts(HashSet<Address>),
+    /// Every caller is impersonated, like the auto-impersonation of a development node.
+    All,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn call_stipends_are_counted_by_the_rule() {
+        for stipend in [
+            CallStipend::Once,
+            CallStipend::PerToken,
+            CallStipend::Disabled,
+        ] {
+            assert_eq!(stipend.count(0), 0);
+        }
+        assert_eq!(CallStipend::Once.count(1), 1);
+        assert_eq!(CallStipend::Once.count(5), 1);
+        assert_eq!(CallStipend::PerToken.count(1), 1);
+        assert_eq!(CallStipend::PerToken.count(5), 5);
+        assert_eq!(CallStipend::Disabled.count(5), 0);
+    }
+
     #[test]
     fn test_validate_tx_chain_id() {
         let mut env = Env::default();
@@ -797,6 +1331,114 @@ mod tests {
             Err(InvalidTransaction::AccessListNotSupported)
         );
     }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let mut env = Env::default();
+        env.block.prevrandao = None;
+        env.block.blob_excess_gas_and_price = None;
+        env.tx.chain_id = Some(1);
+        env.cfg.chain_id = 2;
+        env.tx.gas_priority_fee = Some(U256::from(2));
+        env.tx.gas_price = U256::from(1);
+        env.tx.transferred_tokens = vec![
+            TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(1),
+            };
+            2
+        ];
+        assert_eq!(
+            env.validate(SpecId::CANCUN),
+            vec![
+                InvalidEnv::Header(InvalidHeader::PrevrandaoNotSet),
+                InvalidEnv::Header(InvalidHeader::ExcessBlobGasNotSet),
+                InvalidEnv::Transaction(InvalidTransaction::PriorityFeeGreaterThanMaxFee),
+                InvalidEnv::Transaction(InvalidTransaction::InvalidChainId),
+                InvalidEnv::Transaction(InvalidTransaction::TokenIdsNotUnique),
+            ]
+        );
+        assert_eq!(
+            env.validate_tx::<crate::CancunSpec>(),
+            Err(InvalidTransaction::PriorityFeeGreaterThanMaxFee)
+        );
+        assert!(Env::default().validate(SpecId::FRONTIER).is_empty());
+    }
+
+    #[test]
+    fn test_validate_against_state_reports_every_problem() {
+        let mut env = Env::default();
+        env.tx.nonce = Some(1);
+        env.tx.gas_limit = 10;
+        env.tx.gas_price = U256::from(1);
+        env.tx.transferred_tokens = vec![TokenTransfer {
+            id: U256::from(1),
+            amount: U256::from(1),
+        }];
+        let account = Account::from(crate::AccountInfo::default());
+        assert_eq!(
+            env.validate_against_state(SpecId::CANCUN, &account, None),
+            vec![
+                InvalidTransaction::NonceTooHigh { tx: 1, state: 0 },
+                InvalidTransaction::LackOfFundForMaxFee {
+                    fee: Box::new(U256::from(10)),
+                    balance: Box::new(U256::ZERO),
+                },
+                InvalidTransaction::NotEnoughTokenBalanceForTransfer {
+                    token_id: Box::new(U256::from(1)),
+                    required_balance: Box::new(U256::from(1)),
+                    actual_balance: Box::new(U256::ZERO),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_tip_tokens() {
+        let tip = |id: u64, amount: u64| TokenTransfer {
+            id: U256::from(id),
+            amount: U256::from(amount),
+        };
+        let mut env = Env::default();
+        env.tx.gas_limit = 10;
+        env.tx.gas_price = U256::from(1);
+        env.tx.transferred_tokens = vec![tip(7, 5)];
+        env.tx.tip_tokens = vec![tip(7, 3), tip(7, 3)];
+        assert_eq!(
+            env.validate_tx::<crate::CancunSpec>(),
+            Err(InvalidTransaction::TokenIdsNotUnique)
+        );
+
+        // the tips are added to the transferred amounts and to the gas cost
+        env.tx.tip_tokens = vec![tip(7, 3), tip(0, 2)];
+        assert_eq!(env.validate_tx::<crate::CancunSpec>(), Ok(()));
+        let account = Account::from(crate::AccountInfo {
+            balances: crate::HashMap::from([
+                (BASE_TOKEN_ID, U256::from(11)),
+                (U256::from(7), U256::from(7)),
+            ]),
+            ..Default::default()
+        });
+        assert_eq!(
+            env.validate_against_state(SpecId::CANCUN, &account, None),
+            vec![
+                InvalidTransaction::LackOfFundForMaxFee {
+                    fee: Box::new(U256::from(12)),
+                    balance: Box::new(U256::from(11)),
+                },
+                InvalidTransaction::NotEnoughTokenBalanceForTransfer {
+                    token_id: Box::new(U256::from(7)),
+                    required_balance: Box::new(U256::from(8)),
+                    actual_balance: Box::new(U256::from(7)),
+                },
+            ]
+        );
+
+        env.tx.tip_tokens = vec![tip(7, 2), tip(0, 1)];
+        assert!(env
+            .validate_against_state(SpecId::CANCUN, &account, None)
+            .is_empty());
+    }
 }
 
 /// The information about a token transfer.
@@ -807,6 +1449,40 @@ pub struct TokenTransfer {
     pub amount: U256,
 }
 
+/// An amount of a Native Token minted by the protocol to a recipient, see
+/// [BlockEnv::emissions].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenEmission {
+    pub recipient: Address,
+    pub token_id: U256,
+    pub amount: U256,
+}
+
+/// The token transfers of a call, stored inline when there is at most one, as in most calls.
+pub type TokenTransfers = SmallVec<[TokenTransfer; 1]>;
+
+impl TokenTransfer {
+    /// Returns `true` if the transfer moves no tokens.
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.amount.is_zero()
+    }
+}
+
+/// Returns the transfers of a nonzero amount, which are the only ones applied.
+///
+/// The transfers of a zero amount are skipped wherever tokens are transferred: by the
+/// transaction, by calls and creations, and by the precompiles. They change no balance, aren't
+/// journaled and don't warm the balances, so transferring only zero amounts just touches the
+/// recipient, like a `CALL` with no value.
+#[inline]
+pub fn nonzero_token_transfers(
+    transfers: &[TokenTransfer],
+) -> impl Iterator<Item = &TokenTransfer> + '_ {
+    transfers.iter().filter(|transfer| !transfer.is_zero())
+}
+
 impl Ord for TokenTransfer {
     fn cmp(&self, other: &Self) -> Ordering {
         self.amount.cmp(&other.amount)
@@ -0,0 +1,143 @@
+//! [EIP-7702] set-code authorizations.
+//!
+//! An authorization lets an EOA designate a contract whose code runs whenever the EOA is called,
+//! so that e.g. a sponsored transaction can claim tokens through the code of an EOA.
+//!
+//! [EIP-7702]: https://eips.ethereum.org/EIPS/eip-7702
+
+use crate::{keccak256, Address, B256};
+use alloy_primitives::Signature;
+use alloy_rlp::{length_of_length, BufMut, Encodable, Header};
+use std::vec::Vec;
+
+/// The prefix of the message signed by an authority, which separates it from transactions.
+pub const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// The gas charged for every authorization in the transaction's intrinsic gas.
+pub const PER_EMPTY_ACCOUNT_COST: u64 = 25000;
+
+/// The gas charged for an authorization of an authority that already exists; the rest of
+/// [`PER_EMPTY_ACCOUNT_COST`] is refunded.
+pub const PER_AUTH_BASE_COST: u64 = 12500;
+
+/// An unsigned authorization to set the code of the signer to a delegation to `address`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Authorization {
+    /// The chain ID the authorization is valid on, or `0` for every chain.
+    pub chain_id: u64,
+    /// The address of the code the authority delegates to. The zero address clears the
+    /// delegation.
+    pub address: Address,
+    /// The nonce the authority's account must have.
+    pub nonce: u64,
+}
+
+impl Authorization {
+    /// Returns the hash that the authority signs: the Keccak-256 hash of [`AUTHORIZATION_MAGIC`]
+    /// followed by `rlp([chain_id, address, nonce])`.
+    pub fn signature_hash(&self) -> B256 {
+        let mut out = Vec::with_capacity(1 + self.length());
+        out.put_u8(AUTHORIZATION_MAGIC);
+        self.encode(&mut out);
+        keccak256(out)
+    }
+
+    fn fields_length(&self) -> usize {
+        self.chain_id.length() + self.address.length() + self.nonce.length()
+    }
+}
+
+impl Encodable for Authorization {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.fields_length(),
+        }
+        .encode(out);
+        self.chain_id.encode(out);
+        self.address.encode(out);
+        self.nonce.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.fields_length();
+        payload_length + length_of_length(payload_length)
+    }
+}
+
+/// An [`Authorization`] signed by its authority.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedAuthorization {
+    /// The signed authorization.
+    pub inner: Authorization,
+    /// The signature of the authority.
+    pub signature: Signature,
+}
+
+impl SignedAuthorization {
+    /// Recovers the authority from the signature.
+    #[cfg(feature = "k256")]
+    pub fn recover_authority(&self) -> Result<Address, alloy_primitives::SignatureError> {
+        self.signature
+            .recover_address_from_prehash(&self.inner.signature_hash())
+    }
+
+    /// Recovers the authority, keeping the authorization in the transaction if the signature is
+    /// invalid: such authorizations are skipped when the transaction is executed.
+    #[cfg(feature = "k256")]
+    pub fn into_recovered(self) -> RecoveredAuthorization {
+        let authority = self.recover_authority().ok();
+        RecoveredAuthorization::new_unchecked(self.inner, authority)
+    }
+}
+
+/// An [`Authorization`] with its recovered authority, as carried by [`TxEnv`](crate::TxEnv).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecoveredAuthorization {
+    /// The authorization.
+    pub inner: Authorization,
+    /// The authority, or `None` if it could not be recovered from the signature.
+    pub authority: Option<Address>,
+}
+
+impl RecoveredAuthorization {
+    /// Creates a recovered authorization without checking that `authority` signed it.
+    pub fn new_unchecked(inner: Authorization, authority: Option<Address>) -> Self {
+        Self { inner, authority }
+    }
+}
+
+#[cfg(all(test, feature = "k256"))]
+mod tests {
+    use super::*;
+    use crate::address;
+    use k256::ecdsa::SigningKey;
+
+    #[test]
+    fn recovers_authority() {
+        let key = SigningKey::from_slice(&[0x22; 32]).unwrap();
+        let inner = Authorization {
+            chain_id: 1,
+            address: address!("00000000000000000000000000000000000000bb"),
+            nonce: 4,
+        };
+        let signature = key
+            .sign_prehash_recoverable(inner.signature_hash().as_slice())
+            .unwrap()
+            .into();
+        let signed = SignedAuthorization { inner, signature };
+        assert_eq!(
+            signed.clone().into_recovered().authority,
+            Some(Address::from_private_key(&key))
+        );
+
+        let mut tampered = signed;
+        tampered.inner.nonce += 1;
+        assert_ne!(
+            tampered.into_recovered().authority,
+            Some(Address::from_private_key(&key))
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! JSON-RPC call requests, as taken by `eth_call` and `eth_estimateGas`.
+
+use super::{TokenTransfer, TransactTo, TxEnv};
+use crate::{Address, Bytes, U256};
+use alloy_primitives::U64;
+use core::fmt;
+use std::vec::Vec;
+
+/// A JSON-RPC call request.
+///
+/// Besides the standard fields, the request can transfer native tokens through
+/// `tokenTransfers`, a list of `[id, amount]` tuples of hex strings. `{"id", "amount"}`
+/// objects are accepted as well. The base token may be transferred either through `value`
+/// or through `tokenTransfers`, but not both.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CallRequest {
+    /// The sender of the call.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub from: Option<Address>,
+    /// The destination of the call, or `None` for a contract creation.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub to: Option<Address>,
+    /// The gas limit of the call.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub gas: Option<U64>,
+    /// The legacy gas price.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub gas_price: Option<U256>,
+    /// The maximum fee per gas.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub max_fee_per_gas: Option<U256>,
+    /// The priority fee per gas.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// The amount of the base token transferred with the call.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub value: Option<U256>,
+    /// The data of the call, also accepted as `data`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, alias = "data", skip_serializing_if = "Option::is_none")
+    )]
+    pub input: Option<Bytes>,
+    /// The nonce of the call.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub nonce: Option<U64>,
+    /// The chain ID of the call.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub chain_id: Option<U64>,
+    /// The native tokens transferred with the call.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            with = "token_transfer_tuples",
+            skip_serializing_if = "Vec::is_empty"
+        )
+    )]
+    pub token_transfers: Vec<TokenTransfer>,
+}
+
+/// Errors that can occur when converting a [`CallRequest`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallRequestError {
+    /// Both `gasPrice` and `maxFeePerGas` are set.
+    ConflictingFeeFields,
+    /// The base token is transferred both through `value` and through `tokenTransfers`.
+    DuplicateBaseToken,
+    /// A token transfer is not a pair of hex-encoded quantities.
+    InvalidTokenTransfer,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CallRequestError {}
+
+impl fmt::Display for CallRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConflictingFeeFields => {
+                write!(f, "both gasPrice and maxFeePerGas specified")
+            }
+            Self::DuplicateBaseToken => {
+                write!(
+                    f,
+                    "base token transferred through both value and tokenTransfers"
+                )
+            }
+            Self::InvalidTokenTransfer => write!(f, "invalid token transfer"),
+        }
+    }
+}
+
+impl TokenTransfer {
+    /// Parses a token transfer from a `(id, amount)` tuple of `0x`-prefixed hex quantities.
+    pub fn from_hex_tuple(id: &str, amount: &str) -> Result<Self, CallRequestError> {
+        Ok(Self {
+            id: parse_hex_quantity(id)?,
+            amount: parse_hex_quantity(amount)?,
+        })
+    }
+}
+
+fn parse_hex_quantity(s: &str) -> Result<U256, CallRequestError> {
+    let digits = s
+        .strip_prefix("0x")
+        .filter(|digits| !digits.is_empty())
+        .ok_or(CallRequestError::InvalidTokenTransfer)?;
+    U256::from_str_radix(digits, 16).map_err(|_| CallRequestError::InvalidTokenTransfer)
+}
+
+impl CallRequest {
+    /// Converts the request into a [`TxEnv`], leaving the fields the request does not set to
+    /// their defaults. The `value`, if any, is an amount of the base token `base_token_id`, and
+    /// is transferred first.
+    pub fn try_into_tx_env(self, base_token_id: U256) -> Result<TxEnv, CallRequestError> {
+        let request = self;
+        if request.gas_price.is_some() && request.max_fee_per_gas.is_some() {
+            return Err(CallRequestError::ConflictingFeeFields);
+        }
+        if request.value.is_some()
+            && request
+                .token_transfers
+                .iter()
+                .any(|transfer| transfer.id == base_token_id)
+        {
+            return Err(CallRequestError::DuplicateBaseToken);
+        }
+
+        let mut transferred_tokens = Vec::with_capacity(request.token_transfers.len() + 1);
+        if let Some(amount) = request.value {
+            transferred_tokens.push(TokenTransfer {
+                id: base_token_id,
+                amount,
+            });
+        }
+        transferred_tokens.extend(request.token_transfers);
+
+        let default = TxEnv::default();
+        Ok(TxEnv {
+            caller: request.from.unwrap_or(default.caller),
+            gas_limit: request.gas.map_or(default.gas_limit, |gas| gas.to()),
+            gas_price: request
+                .gas_price
+                .or(request.max_fee_per_gas)
+                .unwrap_or(default.gas_price),
+            transact_to: request.to.map_or(TransactTo::Create, TransactTo::Call),
+            data: request.input.unwrap_or_default(),
+            nonce: request.nonce.map(|nonce| nonce.to()),
+            chain_id: request.chain_id.map(|chain_id| chain_id.to()),
+            gas_priority_fee: request.max_priority_fee_per_gas,
+            transferred_tokens,
+            ..default
+        })
+    }
+
+    /// Converts the transaction into a request. The transfer of the base token `base_token_id`
+    /// is moved to `value`.
+    pub fn from_tx_env(tx: &TxEnv, base_token_id: U256) -> Self {
+        let (gas_price, max_fee_per_gas) = match tx.gas_priority_fee {
+            Some(_) => (None, Some(tx.gas_price)),
+            None => (Some(tx.gas_price), None),
+        };
+        Self {
+            from: Some(tx.caller),
+            to: match tx.transact_to {
+                TransactTo::Call(address) => Some(address),
+                TransactTo::Create => None,
+            },
+            gas: Some(U64::from(tx.gas_limit)),
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas: tx.gas_priority_fee,
+            value: tx
+                .transferred_tokens
+                .iter()
+                .find(|transfer| transfer.id == base_token_id)
+                .map(|transfer| transfer.amount),
+            input: Some(tx.data.clone()),
+            nonce: tx.nonce.map(U64::from),
+            chain_id: tx.chain_id.map(U64::from),
+            token_transfers: tx
+                .transferred_tokens
+                .iter()
+                .filter(|transfer| transfer.id != base_token_id)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// (De)serializes token transfers as `[id, amount]` tuples, also accepting
+/// `{"id", "amount"}` objects.
+#[cfg(feature = "serde")]
+mod token_transfer_tuples {
+    use super::TokenTransfer;
+    use crate::U256;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::vec::Vec;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TokenTransferRepr {
+        Tuple(U256, U256),
+        Object { id: U256, amount: U256 },
+    }
+
+    pub(super) fn serialize<S: Serializer>(
+        transfers: &[TokenTransfer],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            transfers
+                .iter()
+                .map(|transfer| (transfer.id, transfer.amount)),
+        )
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<TokenTransfer>, D::Error> {
+        Ok(Vec::<TokenTransferRepr>::deserialize(deserializer)?
+            .into_iter()
+            .map(|repr| match repr {
+                TokenTransferRepr::Tuple(id, amount) | TokenTransferRepr::Object { id, amount } => {
+                    TokenTransfer { id, amount }
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{address, bytes, BASE_TOKEN_ID};
+
+    #[test]
+    fn parses_hex_tuples() {
+        assert_eq!(
+            TokenTransfer::from_hex_tuple("0x1", "0x64"),
+            Ok(TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(100),
+            })
+        );
+        for (id, amount) in [("1", "0x64"), ("0x", "0x64"), ("0x1", "0xzz")] {
+            assert_eq!(
+                TokenTransfer::from_hex_tuple(id, amount),
+                Err(CallRequestError::InvalidTokenTransfer)
+            );
+        }
+    }
+
+    #[test]
+    fn converts_to_tx_env() {
+        let request = CallRequest {
+            from: Some(address!("00000000000000000000000000000000000000aa")),
+            to: Some(address!("00000000000000000000000000000000000000bb")),
+            gas: Some(U64::from(21_000)),
+            max_fee_per_gas: Some(U256::from(10)),
+            max_priority_fee_per_gas: Some(U256::from(1)),
+            value: Some(U256::from(5)),
+            input: Some(bytes!("01")),
+            token_transfers: vec![TokenTransfer {
+                id: U256::from(7),
+                amount: U256::from(100),
+            }],
+            ..Default::default()
+        };
+        let tx = request.clone().try_into_tx_env(BASE_TOKEN_ID).unwrap();
+        assert_eq!(tx.caller, request.from.unwrap());
+        assert_eq!(tx.transact_to, TransactTo::Call(request.to.unwrap()));
+        assert_eq!(tx.gas_limit, 21_000);
+        assert_eq!(tx.gas_price, U256::from(10));
+        assert_eq!(tx.gas_priority_fee, Some(U256::from(1)));
+        assert_eq!(
+            tx.transferred_tokens,
+            vec![
+                TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(5),
+                },
+                TokenTransfer {
+                    id: U256::from(7),
+                    amount: U256::from(100),
+                },
+            ]
+        );
+
+        assert_eq!(CallRequest::from_tx_env(&tx, BASE_TOKEN_ID), request);
+    }
+
+    #[test]
+    fn value_is_an_amount_of_the_base_token() {
+        let base_token_id = U256::from(7);
+        let request = CallRequest {
+            value: Some(U256::from(5)),
+            token_transfers: vec![TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(100),
+            }],
+            ..Default::default()
+        };
+        let tx = request.clone().try_into_tx_env(base_token_id).unwrap();
+        assert_eq!(
+            tx.transferred_tokens,
+            vec![
+                TokenTransfer {
+                    id: base_token_id,
+                    amount: U256::from(5),
+                },
+                TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(100),
+                },
+            ]
+        );
+        let converted = CallRequest::from_tx_env(&tx, base_token_id);
+        assert_eq!(converted.value, request.value);
+        assert_eq!(converted.token_transfers, request.token_transfers);
+
+        let request = CallRequest {
+            token_transfers: vec![TokenTransfer {
+                id: base_token_id,
+                amount: U256::from(1),
+            }],
+            ..request
+        };
+        assert_eq!(
+            request.try_into_tx_env(base_token_id),
+            Err(CallRequestError::DuplicateBaseToken)
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_fields() {
+        let request = CallRequest {
+            gas_price: Some(U256::from(1)),
+            max_fee_per_gas: Some(U256::from(1)),
+            ..Default::default()
+        };
+        assert_eq!(
+            request.try_into_tx_env(BASE_TOKEN_ID),
+            Err(CallRequestError::ConflictingFeeFields)
+        );
+
+        let request = CallRequest {
+            value: Some(U256::from(1)),
+            token_transfers: vec![TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(1),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            request.try_into_tx_env(BASE_TOKEN_ID),
+            Err(CallRequestError::DuplicateBaseToken)
+        );
+    }
+
+    #[test]
+    fn missing_to_is_create() {
+        let tx = CallRequest::default()
+            .try_into_tx_env(BASE_TOKEN_ID)
+            .unwrap();
+        assert_eq!(tx.transact_to, TransactTo::Create);
+        assert!(tx.transferred_tokens.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializes_rpc_request() {
+        let request: CallRequest = serde_json::from_str(
+            r#"{
+                "from": "0x00000000000000000000000000000000000000aa",
+                "to": "0x00000000000000000000000000000000000000bb",
+                "data": "0x01",
+                "value": "0x5",
+                "tokenTransfers": [["0x7", "0x64"], { "id": "0x8", "amount": "0x1" }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(request.input, Some(bytes!("01")));
+        assert_eq!(request.value, Some(U256::from(5)));
+        assert_eq!(
+            request.token_transfers,
+            vec![
+                TokenTransfer {
+                    id: U256::from(7),
+                    amount: U256::from(100),
+                },
+                TokenTransfer {
+                    id: U256::from(8),
+                    amount: U256::from(1),
+                },
+            ]
+        );
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json["tokenTransfers"],
+            serde_json::json!([["0x7", "0x64"], ["0x8", "0x1"]])
+        );
+        assert_eq!(
+            serde_json::from_value::<CallRequest>(json).unwrap(),
+            request
+        );
+    }
+}
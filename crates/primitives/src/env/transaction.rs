@@ -0,0 +1,294 @@
+//! Signed SabVM transactions.
+
+use super::{
+    wire::{
+        access_list_length, decode_access_list, decode_tx_type, encode_access_list,
+        ensure_consumed, TxDecodeError, MULTI_TOKEN_TX_TYPE,
+    },
+    TokenTransfer, TransactTo, TxEnv,
+};
+use crate::{keccak256, Address, Bytes, B256, U256};
+use alloy_primitives::{Parity, Signature};
+use alloy_rlp::{length_of_length, list_length, BufMut, Decodable, Encodable, Header};
+use std::vec::Vec;
+
+/// A signed multi-token transaction, the [EIP-2718] envelope of type [`MULTI_TOKEN_TX_TYPE`]:
+///
+/// ```text
+/// MULTI_TOKEN_TX_TYPE || rlp([
+///     chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, data,
+///     access_list, transferred_tokens, y_parity, r, s
+/// ])
+/// ```
+///
+/// The signature is over the [`signature_hash`](Self::signature_hash), which commits to every
+/// field except the signature itself.
+///
+/// [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SabvmTransaction {
+    /// The chain ID of the transaction.
+    pub chain_id: u64,
+    /// The nonce of the transaction.
+    pub nonce: u64,
+    /// The priority fee per gas.
+    pub max_priority_fee_per_gas: U256,
+    /// The maximum fee per gas.
+    pub max_fee_per_gas: U256,
+    /// The gas limit of the transaction.
+    pub gas_limit: u64,
+    /// The destination of the transaction.
+    pub to: TransactTo,
+    /// The data of the transaction.
+    pub data: Bytes,
+    /// The list of tokens transferred in the transaction.
+    pub transferred_tokens: Vec<TokenTransfer>,
+    /// A list of addresses and storage keys that the transaction plans to access.
+    pub access_list: Vec<(Address, Vec<U256>)>,
+    /// The signature of the transaction.
+    pub signature: Signature,
+}
+
+impl SabvmTransaction {
+    /// Returns the hash that the sender signs: the Keccak-256 hash of the transaction type
+    /// followed by the RLP list of the unsigned fields.
+    pub fn signature_hash(&self) -> B256 {
+        let payload_length = self.fields_length();
+        let mut out = Vec::with_capacity(1 + payload_length + length_of_length(payload_length));
+        out.put_u8(MULTI_TOKEN_TX_TYPE);
+        Header {
+            list: true,
+            payload_length,
+        }
+        .encode(&mut out);
+        self.encode_fields(&mut out);
+        keccak256(out)
+    }
+
+    /// Returns the hash of the signed transaction, which identifies it.
+    pub fn hash(&self) -> B256 {
+        keccak256(self.encode_2718())
+    }
+
+    /// Returns the [EIP-2718] encoding of the signed transaction.
+    ///
+    /// [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+    pub fn encode_2718(&self) -> Bytes {
+        let payload_length = self.fields_length() + self.signature.rlp_vrs_len();
+        let mut out = Vec::with_capacity(1 + payload_length + length_of_length(payload_length));
+        out.put_u8(MULTI_TOKEN_TX_TYPE);
+        Header {
+            list: true,
+            payload_length,
+        }
+        .encode(&mut out);
+        self.encode_fields(&mut out);
+        self.signature.write_rlp_vrs(&mut out);
+        out.into()
+    }
+
+    /// Decodes a signed transaction from its [EIP-2718] encoding, advancing the buffer past it.
+    ///
+    /// [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+    pub fn decode_2718(buf: &mut &[u8]) -> Result<Self, TxDecodeError> {
+        decode_tx_type(buf, MULTI_TOKEN_TX_TYPE)?;
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let chain_id = u64::decode(&mut payload)?;
+        let nonce = u64::decode(&mut payload)?;
+        let max_priority_fee_per_gas = U256::decode(&mut payload)?;
+        let max_fee_per_gas = U256::decode(&mut payload)?;
+        let gas_limit = u64::decode(&mut payload)?;
+        let to = TransactTo::decode(&mut payload)?;
+        let data = Bytes::decode(&mut payload)?;
+        let access_list = decode_access_list(&mut payload)?;
+        let transferred_tokens = Vec::<TokenTransfer>::decode(&mut payload)?;
+        let y_parity = bool::decode(&mut payload)?;
+        let r = U256::decode(&mut payload)?;
+        let s = U256::decode(&mut payload)?;
+        ensure_consumed(payload)?;
+
+        let signature = Signature::from_rs_and_parity(r, s, Parity::Parity(y_parity))
+            .map_err(|_| TxDecodeError::InvalidSignature)?;
+
+        Ok(Self {
+            chain_id,
+            nonce,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            gas_limit,
+            to,
+            data,
+            transferred_tokens,
+            access_list,
+            signature,
+        })
+    }
+
+    /// Recovers the sender of the transaction from its signature.
+    ///
+    /// Signatures whose `s` value is greater than `secp256k1n / 2` are rejected, as in [EIP-2], so
+    /// that a signed transaction has a single valid encoding.
+    ///
+    /// [EIP-2]: https://eips.ethereum.org/EIPS/eip-2
+    #[cfg(feature = "k256")]
+    pub fn recover_signer(&self) -> Result<Address, alloy_primitives::SignatureError> {
+        if self.signature.normalize_s().is_some() {
+            return Err(alloy_primitives::SignatureError::FromBytes(
+                "signature s value is greater than secp256k1n / 2",
+            ));
+        }
+        self.signature
+            .recover_address_from_prehash(&self.signature_hash())
+    }
+
+    /// Converts the transaction into a [`TxEnv`] sent by `caller`, which is expected to be the
+    /// [recovered signer](Self::recover_signer).
+    pub fn into_tx_env(self, caller: Address) -> TxEnv {
+        TxEnv {
+            caller,
+            gas_limit: self.gas_limit,
+            gas_price: self.max_fee_per_gas,
+            transact_to: self.to,
+            data: self.data,
+            nonce: Some(self.nonce),
+            chain_id: Some(self.chain_id),
+            access_list: self.access_list,
+            gas_priority_fee: Some(self.max_priority_fee_per_gas),
+            transferred_tokens: self.transferred_tokens,
+            ..Default::default()
+        }
+    }
+
+    /// Recovers the sender of the transaction and converts it into a [`TxEnv`].
+    #[cfg(feature = "k256")]
+    pub fn try_into_tx_env(self) -> Result<TxEnv, alloy_primitives::SignatureError> {
+        let caller = self.recover_signer()?;
+        Ok(self.into_tx_env(caller))
+    }
+
+    /// Returns the payload length of the unsigned fields.
+    fn fields_length(&self) -> usize {
+        self.chain_id.length()
+            + self.nonce.length()
+            + self.max_priority_fee_per_gas.length()
+            + self.max_fee_per_gas.length()
+            + self.gas_limit.length()
+            + self.to.length()
+            + self.data.length()
+            + access_list_length(&self.access_list)
+            + list_length(&self.transferred_tokens)
+    }
+
+    /// Encodes the unsigned fields, without a list header.
+    fn encode_fields(&self, out: &mut dyn BufMut) {
+        self.chain_id.encode(out);
+        self.nonce.encode(out);
+        self.max_priority_fee_per_gas.encode(out);
+        self.max_fee_per_gas.encode(out);
+        self.gas_limit.encode(out);
+        self.to.encode(out);
+        self.data.encode(out);
+        encode_access_list(&self.access_list, out);
+        alloy_rlp::encode_list::<_, TokenTransfer>(&self.transferred_tokens, out);
+    }
+}
+
+#[cfg(all(test, feature = "k256"))]
+mod tests {
+    use super::*;
+    use crate::{address, bytes, BASE_TOKEN_ID};
+    use k256::ecdsa::SigningKey;
+
+    fn unsigned_tx() -> SabvmTransaction {
+        SabvmTransaction {
+            chain_id: 1,
+            nonce: 3,
+            max_priority_fee_per_gas: U256::from(2),
+            max_fee_per_gas: U256::from(10),
+            gas_limit: 50_000,
+            to: TransactTo::Call(address!("00000000000000000000000000000000000000bb")),
+            data: bytes!("c0ffee"),
+            transferred_tokens: vec![
+                TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(1),
+                },
+                TokenTransfer {
+                    id: U256::from(7),
+                    amount: U256::from(100),
+                },
+            ],
+            access_list: vec![(
+                address!("00000000000000000000000000000000000000cc"),
+                vec![U256::from(1)],
+            )],
+            signature: Signature::test_signature(),
+        }
+    }
+
+    fn sign(mut tx: SabvmTransaction, key: &SigningKey) -> SabvmTransaction {
+        let hash = tx.signature_hash();
+        tx.signature = key
+            .sign_prehash_recoverable(hash.as_slice())
+            .unwrap()
+            .into();
+        tx
+    }
+
+    #[test]
+    fn roundtrip() {
+        let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+        let tx = sign(unsigned_tx(), &key);
+        let encoded = tx.encode_2718();
+        assert_eq!(encoded[0], MULTI_TOKEN_TX_TYPE);
+
+        let mut buf = encoded.as_ref();
+        assert_eq!(SabvmTransaction::decode_2718(&mut buf), Ok(tx.clone()));
+        assert!(buf.is_empty());
+        assert_eq!(tx.hash(), keccak256(&encoded));
+    }
+
+    #[test]
+    fn recovers_signer() {
+        let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+        let signer = Address::from_private_key(&key);
+        let tx = sign(unsigned_tx(), &key);
+        assert_eq!(tx.recover_signer().unwrap(), signer);
+
+        let tx_env = tx.clone().try_into_tx_env().unwrap();
+        assert_eq!(tx_env.caller, signer);
+        assert_eq!(tx_env.nonce, Some(tx.nonce));
+        assert_eq!(tx_env.gas_price, tx.max_fee_per_gas);
+        assert_eq!(tx_env.gas_priority_fee, Some(tx.max_priority_fee_per_gas));
+        assert_eq!(tx_env.transferred_tokens, tx.transferred_tokens);
+    }
+
+    #[test]
+    fn signature_hash_commits_to_transferred_tokens() {
+        let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+        let signer = Address::from_private_key(&key);
+        let mut tx = sign(unsigned_tx(), &key);
+        tx.transferred_tokens[1].amount = U256::from(101);
+        assert_ne!(tx.recover_signer().ok(), Some(signer));
+    }
+
+    #[test]
+    fn rejects_high_s_signatures() {
+        let key = SigningKey::from_slice(&[0x11; 32]).unwrap();
+        let mut tx = sign(unsigned_tx(), &key);
+        let secp256k1n = U256::from_str_radix(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            16,
+        )
+        .unwrap();
+        // (r, n - s) with the opposite parity is the other valid ECDSA signature of the hash
+        tx.signature = Signature::from_rs_and_parity(
+            tx.signature.r(),
+            secp256k1n - tx.signature.s(),
+            tx.signature.v().inverted(),
+        )
+        .unwrap();
+        assert!(tx.recover_signer().is_err());
+        assert!(tx.try_into_tx_env().is_err());
+    }
+}
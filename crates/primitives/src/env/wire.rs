@@ -0,0 +1,538 @@
+//! Wire encoding of multi-token transactions.
+//!
+//! An unsigned multi-token transaction is an [EIP-2718] typed transaction of type
+//! [`UNSIGNED_MULTI_TOKEN_TX_TYPE`]:
+//!
+//! ```text
+//! UNSIGNED_MULTI_TOKEN_TX_TYPE || rlp([
+//!     chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, data,
+//!     access_list, transferred_tokens, caller
+//! ])
+//! ```
+//!
+//! - `to` is the empty string for contract creations.
+//! - `access_list` follows [EIP-2930]: `[[address, [storage_key, ...]], ...]`, with 32-byte keys.
+//! - `transferred_tokens` is `[[id, amount], ...]`. It replaces the `value` field of the other
+//!   transaction types, the base token being transferred with id [`BASE_TOKEN_ID`](crate::BASE_TOKEN_ID).
+//! - `caller` is the sender. [`TxEnv`] holds the recovered sender instead of a signature, so it is
+//!   part of the payload.
+//!
+//! Signed transactions, as shipped between nodes, are
+//! [`SabvmTransaction`](super::SabvmTransaction)s of type [`MULTI_TOKEN_TX_TYPE`]. Their encoding
+//! replaces `caller` with the `y_parity, r, s` signature fields. The two formats have distinct
+//! types, so that an unsigned transaction is never mistaken for a signed one.
+//!
+//! [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+//! [EIP-2930]: https://eips.ethereum.org/EIPS/eip-2930
+
+use super::{TokenTransfer, TransactTo, TxEnv};
+use crate::{Address, Bytes, B256, U256};
+use alloy_rlp::{length_of_length, list_length, BufMut, Decodable, Encodable, Header};
+use core::fmt;
+use std::vec::Vec;
+
+/// The [EIP-2718] transaction type of signed multi-token transactions.
+///
+/// [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+pub const MULTI_TOKEN_TX_TYPE: u8 = 0x4d;
+
+/// The [EIP-2718] transaction type of unsigned multi-token transactions, which carry their sender
+/// instead of a signature.
+///
+/// [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+pub const UNSIGNED_MULTI_TOKEN_TX_TYPE: u8 = 0x4e;
+
+/// Errors that can occur when encoding a [`TxEnv`] as a multi-token transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TxEncodeError {
+    /// The transaction has no chain ID.
+    MissingChainId,
+    /// The transaction has no nonce.
+    MissingNonce,
+    /// The transaction carries blobs, which multi-token transactions do not support.
+    BlobsNotSupported,
+    /// The transaction has a gas payer, which is authorized outside of the transaction encoding.
+    GasPayerNotSupported,
+    /// The transaction carries an authorization list, which multi-token transactions do not
+    /// support.
+    AuthorizationListNotSupported,
+    /// The transaction carries a token access list, which multi-token transactions do not
+    /// support.
+    TokenAccessListNotSupported,
+    /// The transaction tips tokens, which multi-token transactions do not support.
+    TipTokensNotSupported,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TxEncodeError {}
+
+impl fmt::Display for TxEncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingChainId => write!(f, "multi-token transactions require a chain ID"),
+            Self::MissingNonce => write!(f, "multi-token transactions require a nonce"),
+            Self::BlobsNotSupported => {
+                write!(f, "multi-token transactions do not support blobs")
+            }
+            Self::GasPayerNotSupported => {
+                write!(f, "multi-token transactions do not encode a gas payer")
+            }
+            Self::AuthorizationListNotSupported => {
+                write!(
+                    f,
+                    "multi-token transactions do not support authorization lists"
+                )
+            }
+            Self::TokenAccessListNotSupported => {
+                write!(
+                    f,
+                    "multi-token transactions do not support token access lists"
+                )
+            }
+            Self::TipTokensNotSupported => {
+                write!(f, "multi-token transactions do not support token tips")
+            }
+        }
+    }
+}
+
+/// Errors that can occur when decoding a multi-token transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxDecodeError {
+    /// The input is empty.
+    EmptyInput,
+    /// The transaction type is not the type of the decoded format.
+    UnexpectedType(u8),
+    /// The payload is not valid RLP.
+    Rlp(alloy_rlp::Error),
+    /// The signature is not a valid ECDSA signature.
+    InvalidSignature,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TxDecodeError {}
+
+impl fmt::Display for TxDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyInput => write!(f, "empty transaction input"),
+            Self::UnexpectedType(ty) => write!(f, "unexpected transaction type {ty:#04x}"),
+            Self::Rlp(err) => write!(f, "invalid RLP payload: {err}"),
+            Self::InvalidSignature => write!(f, "invalid transaction signature"),
+        }
+    }
+}
+
+impl From<alloy_rlp::Error> for TxDecodeError {
+    fn from(err: alloy_rlp::Error) -> Self {
+        Self::Rlp(err)
+    }
+}
+
+impl Encodable for TokenTransfer {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.id.length() + self.amount.length(),
+        }
+        .encode(out);
+        self.id.encode(out);
+        self.amount.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.id.length() + self.amount.length();
+        payload_length + length_of_length(payload_length)
+    }
+}
+
+impl Decodable for TokenTransfer {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let transfer = Self {
+            id: U256::decode(&mut payload)?,
+            amount: U256::decode(&mut payload)?,
+        };
+        ensure_consumed(payload)?;
+        Ok(transfer)
+    }
+}
+
+impl Encodable for TransactTo {
+    fn encode(&self, out: &mut dyn BufMut) {
+        match self {
+            Self::Call(address) => address.encode(out),
+            Self::Create => out.put_u8(alloy_rlp::EMPTY_STRING_CODE),
+        }
+    }
+
+    fn length(&self) -> usize {
+        match self {
+            Self::Call(address) => address.length(),
+            Self::Create => 1,
+        }
+    }
+}
+
+impl Decodable for TransactTo {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        if buf.first() == Some(&alloy_rlp::EMPTY_STRING_CODE) {
+            *buf = &buf[1..];
+            return Ok(Self::Create);
+        }
+        Address::decode(buf).map(Self::Call)
+    }
+}
+
+impl TxEnv {
+    /// Returns the [EIP-2718] encoding of the transaction as an unsigned multi-token transaction.
+    ///
+    /// See the [module documentation](crate::env::wire) for the layout.
+    ///
+    /// A missing [`gas_priority_fee`](Self::gas_priority_fee) is encoded as the gas price,
+    /// which results in the same effective gas price.
+    ///
+    /// [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+    pub fn encode_multi_token(&self) -> Result<Bytes, TxEncodeError> {
+        let chain_id = self.chain_id.ok_or(TxEncodeError::MissingChainId)?;
+        let nonce = self.nonce.ok_or(TxEncodeError::MissingNonce)?;
+        if !self.blob_hashes.is_empty() || self.max_fee_per_blob_gas.is_some() {
+            return Err(TxEncodeError::BlobsNotSupported);
+        }
+        if self.gas_payer.is_some() {
+            return Err(TxEncodeError::GasPayerNotSupported);
+        }
+        if !self.authorization_list.is_empty() {
+            return Err(TxEncodeError::AuthorizationListNotSupported);
+        }
+        if !self.token_access_list.is_empty() {
+            return Err(TxEncodeError::TokenAccessListNotSupported);
+        }
+        if !self.tip_tokens.is_empty() {
+            return Err(TxEncodeError::TipTokensNotSupported);
+        }
+        let priority_fee = self.gas_priority_fee.unwrap_or(self.gas_price);
+
+        let payload_length = chain_id.length()
+            + nonce.length()
+            + priority_fee.length()
+            + self.gas_price.length()
+            + self.gas_limit.length()
+            + self.transact_to.length()
+            + self.data.length()
+            + access_list_length(&self.access_list)
+            + list_length(&self.transferred_tokens)
+            + self.caller.length();
+
+        let mut out = Vec::with_capacity(1 + payload_length + length_of_length(payload_length));
+        out.put_u8(UNSIGNED_MULTI_TOKEN_TX_TYPE);
+        Header {
+            list: true,
+            payload_length,
+        }
+        .encode(&mut out);
+        chain_id.encode(&mut out);
+        nonce.encode(&mut out);
+        priority_fee.encode(&mut out);
+        self.gas_price.encode(&mut out);
+        self.gas_limit.encode(&mut out);
+        self.transact_to.encode(&mut out);
+        self.data.encode(&mut out);
+        encode_access_list(&self.access_list, &mut out);
+        alloy_rlp::encode_list::<_, TokenTransfer>(&self.transferred_tokens, &mut out);
+        self.caller.encode(&mut out);
+
+        Ok(out.into())
+    }
+
+    /// Decodes an unsigned multi-token transaction from its [EIP-2718] encoding, advancing the
+    /// buffer past it.
+    ///
+    /// Fields that are not part of the encoding are left to their defaults.
+    ///
+    /// [EIP-2718]: https://eips.ethereum.org/EIPS/eip-2718
+    pub fn decode_multi_token(buf: &mut &[u8]) -> Result<Self, TxDecodeError> {
+        decode_tx_type(buf, UNSIGNED_MULTI_TOKEN_TX_TYPE)?;
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let chain_id = u64::decode(&mut payload)?;
+        let nonce = u64::decode(&mut payload)?;
+        let gas_priority_fee = U256::decode(&mut payload)?;
+        let gas_price = U256::decode(&mut payload)?;
+        let gas_limit = u64::decode(&mut payload)?;
+        let transact_to = TransactTo::decode(&mut payload)?;
+        let data = Bytes::decode(&mut payload)?;
+        let access_list = decode_access_list(&mut payload)?;
+        let transferred_tokens = Vec::<TokenTransfer>::decode(&mut payload)?;
+        let caller = Address::decode(&mut payload)?;
+        ensure_consumed(payload)?;
+
+        Ok(Self {
+            caller,
+            gas_limit,
+            gas_price,
+            transact_to,
+            data,
+            nonce: Some(nonce),
+            chain_id: Some(chain_id),
+            access_list,
+            gas_priority_fee: Some(gas_priority_fee),
+            transferred_tokens,
+            ..Default::default()
+        })
+    }
+}
+
+/// Strips the expected transaction type byte from the buffer.
+pub(super) fn decode_tx_type(buf: &mut &[u8], expected: u8) -> Result<(), TxDecodeError> {
+    let (&ty, rest) = buf.split_first().ok_or(TxDecodeError::EmptyInput)?;
+    if ty != expected {
+        return Err(TxDecodeError::UnexpectedType(ty));
+    }
+    *buf = rest;
+    Ok(())
+}
+
+/// Returns the encoded length of an [EIP-2930] access list.
+///
+/// [EIP-2930]: https://eips.ethereum.org/EIPS/eip-2930
+pub(super) fn access_list_length(access_list: &[(Address, Vec<U256>)]) -> usize {
+    let payload_length = access_list_payload_length(access_list);
+    payload_length + length_of_length(payload_length)
+}
+
+/// Encodes an [EIP-2930] access list, with the storage keys as 32-byte strings.
+///
+/// [EIP-2930]: https://eips.ethereum.org/EIPS/eip-2930
+pub(super) fn encode_access_list(access_list: &[(Address, Vec<U256>)], out: &mut dyn BufMut) {
+    Header {
+        list: true,
+        payload_length: access_list_payload_length(access_list),
+    }
+    .encode(out);
+    for (address, keys) in access_list {
+        Header {
+            list: true,
+            payload_length: access_list_item_payload_length(keys),
+        }
+        .encode(out);
+        address.encode(out);
+        Header {
+            list: true,
+            payload_length: keys.len() * B256::ZERO.length(),
+        }
+        .encode(out);
+        for key in keys {
+            B256::from(*key).encode(out);
+        }
+    }
+}
+
+/// Decodes an [EIP-2930] access list.
+///
+/// [EIP-2930]: https://eips.ethereum.org/EIPS/eip-2930
+pub(super) fn decode_access_list(buf: &mut &[u8]) -> alloy_rlp::Result<Vec<(Address, Vec<U256>)>> {
+    let mut payload = Header::decode_bytes(buf, true)?;
+    let mut access_list = Vec::new();
+    while !payload.is_empty() {
+        let mut item = Header::decode_bytes(&mut payload, true)?;
+        let address = Address::decode(&mut item)?;
+        let keys = Vec::<B256>::decode(&mut item)?;
+        ensure_consumed(item)?;
+        access_list.push((address, keys.into_iter().map(|key| key.into()).collect()));
+    }
+    Ok(access_list)
+}
+
+fn access_list_payload_length(access_list: &[(Address, Vec<U256>)]) -> usize {
+    access_list
+        .iter()
+        .map(|(_, keys)| {
+            let item_payload_length = access_list_item_payload_length(keys);
+            item_payload_length + length_of_length(item_payload_length)
+        })
+        .sum()
+}
+
+/// Returns the payload length of an access list item holding the given storage keys.
+fn access_list_item_payload_length(keys: &[U256]) -> usize {
+    let keys_payload_length = keys.len() * B256::ZERO.length();
+    Address::ZERO.length() + keys_payload_length + length_of_length(keys_payload_length)
+}
+
+pub(super) fn ensure_consumed(payload: &[u8]) -> alloy_rlp::Result<()> {
+    if payload.is_empty() {
+        Ok(())
+    } else {
+        Err(alloy_rlp::Error::Custom("unexpected trailing data in list"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{address, bytes, RecoveredAuthorization, BASE_TOKEN_ID};
+
+    fn multi_token_tx() -> TxEnv {
+        TxEnv {
+            caller: address!("00000000000000000000000000000000000000aa"),
+            gas_limit: 100_000,
+            gas_price: U256::from(10),
+            transact_to: TransactTo::Call(address!("00000000000000000000000000000000000000bb")),
+            data: bytes!("deadbeef"),
+            nonce: Some(7),
+            chain_id: Some(1),
+            access_list: vec![
+                (
+                    address!("00000000000000000000000000000000000000cc"),
+                    vec![U256::from(1), U256::MAX],
+                ),
+                (address!("00000000000000000000000000000000000000dd"), vec![]),
+            ],
+            gas_priority_fee: Some(U256::from(2)),
+            transferred_tokens: vec![
+                TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(1000),
+                },
+                TokenTransfer {
+                    id: U256::MAX,
+                    amount: U256::ZERO,
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn multi_token_tx_roundtrip() {
+        let tx = multi_token_tx();
+        let encoded = tx.encode_multi_token().unwrap();
+        assert_eq!(encoded[0], UNSIGNED_MULTI_TOKEN_TX_TYPE);
+
+        let mut buf = encoded.as_ref();
+        assert_eq!(TxEnv::decode_multi_token(&mut buf), Ok(tx));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn multi_token_tx_roundtrip_create() {
+        let tx = TxEnv {
+            transact_to: TransactTo::Create,
+            access_list: vec![],
+            transferred_tokens: vec![],
+            ..multi_token_tx()
+        };
+        let encoded = tx.encode_multi_token().unwrap();
+        assert_eq!(TxEnv::decode_multi_token(&mut encoded.as_ref()), Ok(tx));
+    }
+
+    #[test]
+    fn token_transfer_encoding() {
+        let transfer = TokenTransfer {
+            id: U256::from(1),
+            amount: U256::from(0x0400),
+        };
+        let encoded = alloy_rlp::encode(&transfer);
+        assert_eq!(encoded, [0xc4, 0x01, 0x82, 0x04, 0x00]);
+        assert_eq!(encoded.len(), transfer.length());
+        assert_eq!(TokenTransfer::decode(&mut encoded.as_slice()), Ok(transfer));
+    }
+
+    #[test]
+    fn missing_priority_fee_is_gas_price() {
+        let tx = TxEnv {
+            gas_priority_fee: None,
+            ..multi_token_tx()
+        };
+        let decoded = TxEnv::decode_multi_token(&mut tx.encode_multi_token().unwrap().as_ref());
+        assert_eq!(decoded.unwrap().gas_priority_fee, Some(tx.gas_price));
+    }
+
+    #[test]
+    fn encode_errors() {
+        let tx = TxEnv {
+            nonce: None,
+            ..multi_token_tx()
+        };
+        assert_eq!(tx.encode_multi_token(), Err(TxEncodeError::MissingNonce));
+
+        let tx = TxEnv {
+            chain_id: None,
+            ..multi_token_tx()
+        };
+        assert_eq!(tx.encode_multi_token(), Err(TxEncodeError::MissingChainId));
+
+        let tx = TxEnv {
+            blob_hashes: vec![B256::ZERO],
+            ..multi_token_tx()
+        };
+        assert_eq!(
+            tx.encode_multi_token(),
+            Err(TxEncodeError::BlobsNotSupported)
+        );
+
+        let tx = TxEnv {
+            gas_payer: Some(address!("00000000000000000000000000000000000000ee")),
+            ..multi_token_tx()
+        };
+        assert_eq!(
+            tx.encode_multi_token(),
+            Err(TxEncodeError::GasPayerNotSupported)
+        );
+
+        let tx = TxEnv {
+            authorization_list: vec![RecoveredAuthorization::new_unchecked(
+                Default::default(),
+                None,
+            )],
+            ..multi_token_tx()
+        };
+        assert_eq!(
+            tx.encode_multi_token(),
+            Err(TxEncodeError::AuthorizationListNotSupported)
+        );
+
+        let tx = TxEnv {
+            token_access_list: vec![(Address::ZERO, vec![U256::from(1)])],
+            ..multi_token_tx()
+        };
+        assert_eq!(
+            tx.encode_multi_token(),
+            Err(TxEncodeError::TokenAccessListNotSupported)
+        );
+
+        let tx = TxEnv {
+            tip_tokens: vec![TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(1),
+            }],
+            ..multi_token_tx()
+        };
+        assert_eq!(
+            tx.encode_multi_token(),
+            Err(TxEncodeError::TipTokensNotSupported)
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            TxEnv::decode_multi_token(&mut [].as_slice()),
+            Err(TxDecodeError::EmptyInput)
+        );
+        assert_eq!(
+            TxEnv::decode_multi_token(&mut [0x02, 0xc0].as_slice()),
+            Err(TxDecodeError::UnexpectedType(0x02))
+        );
+        assert_eq!(
+            TxEnv::decode_multi_token(&mut [MULTI_TOKEN_TX_TYPE, 0xc0].as_slice()),
+            Err(TxDecodeError::UnexpectedType(MULTI_TOKEN_TX_TYPE))
+        );
+
+        let encoded = multi_token_tx().encode_multi_token().unwrap();
+        assert!(matches!(
+            TxEnv::decode_multi_token(&mut &encoded[..encoded.len() - 1]),
+            Err(TxDecodeError::Rlp(_))
+        ));
+    }
+}
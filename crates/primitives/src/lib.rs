@@ -10,12 +10,14 @@ extern crate alloc as std;
 mod bytecode;
 mod constants;
 pub mod db;
+pub mod eip712;
 pub mod env;
 
 #[cfg(feature = "c-kzg")]
 pub mod kzg;
 pub mod precompile;
 pub mod result;
+pub mod revert;
 pub mod specification;
 pub mod state;
 pub mod utilities;
@@ -26,7 +28,9 @@ pub use alloy_primitives::{
 pub use bitvec;
 pub use bytecode::*;
 pub use constants::*;
+pub use eip712::*;
 pub use env::*;
+pub use smallvec;
 
 cfg_if::cfg_if! {
     if #[cfg(all(not(feature = "hashbrown"), feature = "std"))] {
@@ -41,6 +45,7 @@ cfg_if::cfg_if! {
 pub use kzg::{EnvKzgSettings, KzgSettings};
 pub use precompile::*;
 pub use result::*;
+pub use revert::*;
 pub use specification::*;
 pub use state::*;
 pub use utilities::*;
@@ -21,6 +21,8 @@ pub struct PrimitiveCallInfo {
     pub target_address: Address,
     pub token_transfers: Vec<TokenTransfer>,
     pub input_data: Bytes,
+    /// The gas used by the precompile, charged before the gas of the call is forwarded.
+    pub gas_used: u64,
 }
 
 /// Contains the information about the result of a precompile operation.
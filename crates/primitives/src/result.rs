@@ -1,4 +1,4 @@
-use crate::{Address, Bytes, EvmState, Log, U256};
+use crate::{Address, Bytes, CustomError, EvmState, Log, RevertReason, TokenTransfer, U256};
 use core::fmt;
 use std::{boxed::Box, string::String, vec::Vec};
 
@@ -28,6 +28,17 @@ pub enum ExecutionResult {
         gas_refunded: u64,
         logs: Vec<Log>,
         output: Output,
+        /// How the gas was spent, if the handler that produced the result reports it.
+        #[cfg_attr(feature = "serde", serde(default))]
+        gas_breakdown: Option<GasBreakdown>,
+        /// The token transfers made by the transaction, if the handler that produced the result
+        /// reports them.
+        #[cfg_attr(feature = "serde", serde(default))]
+        token_transfers: Option<TokenTransferSummary>,
+        /// The tokens minted and burned by the transaction, if the handler that produced the
+        /// result reports them.
+        #[cfg_attr(feature = "serde", serde(default))]
+        supply_changes: Option<TokenSupplyChanges>,
     },
     /// Reverted by `REVERT` opcode that doesn't spend all gas.
     Revert { gas_used: u64, output: Bytes },
@@ -47,11 +58,33 @@ impl ExecutionResult {
         matches!(self, Self::Success { .. })
     }
 
+    /// Returns true if execution result is a Revert.
+    pub fn is_revert(&self) -> bool {
+        matches!(self, Self::Revert { .. })
+    }
+
     /// Returns true if execution result is a Halt.
     pub fn is_halt(&self) -> bool {
         matches!(self, Self::Halt { .. })
     }
 
+    /// Returns the kind of the result.
+    pub fn kind(&self) -> ExecutionResultKind {
+        match self {
+            Self::Success { .. } => ExecutionResultKind::Success,
+            Self::Revert { .. } => ExecutionResultKind::Revert,
+            Self::Halt { .. } => ExecutionResultKind::Halt,
+        }
+    }
+
+    /// Returns the reason of the halt, if the execution halted.
+    pub fn halt_reason(&self) -> Option<HaltReason> {
+        match self {
+            Self::Halt { reason, .. } => Some(*reason),
+            _ => None,
+        }
+    }
+
     /// Returns the output data of the execution.
     ///
     /// Returns `None` if the execution was halted.
@@ -74,6 +107,23 @@ impl ExecutionResult {
         }
     }
 
+    /// Returns the reason of the revert, decoded from the output, if the execution reverted.
+    ///
+    /// `Error(string)` and `Panic(uint256)` are decoded, while custom errors are returned as
+    /// [RevertReason::Unknown], see [Self::revert_reason_with].
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        self.revert_reason_with(&[])
+    }
+
+    /// Returns the reason of the revert like [Self::revert_reason], also decoding the given
+    /// custom errors.
+    pub fn revert_reason_with(&self, custom_errors: &[CustomError]) -> Option<RevertReason> {
+        match self {
+            Self::Revert { output, .. } => Some(RevertReason::decode(output, custom_errors)),
+            _ => None,
+        }
+    }
+
     /// Returns the logs if execution is successful, or an empty list otherwise.
     pub fn logs(&self) -> &[Log] {
         match self {
@@ -90,6 +140,34 @@ impl ExecutionResult {
         }
     }
 
+    /// Returns the gas breakdown if execution is successful and the handler reported it.
+    pub fn gas_breakdown(&self) -> Option<&GasBreakdown> {
+        match self {
+            Self::Success { gas_breakdown, .. } => gas_breakdown.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the summary of the token transfers if execution is successful and the handler
+    /// reported it.
+    pub fn token_transfers(&self) -> Option<&TokenTransferSummary> {
+        match self {
+            Self::Success {
+                token_transfers, ..
+            } => token_transfers.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the tokens minted and burned if execution is successful and the handler reported
+    /// them.
+    pub fn supply_changes(&self) -> Option<&TokenSupplyChanges> {
+        match self {
+            Self::Success { supply_changes, .. } => supply_changes.as_ref(),
+            _ => None,
+        }
+    }
+
     /// Returns the gas used.
     pub fn gas_used(&self) -> u64 {
         match *self {
@@ -100,6 +178,280 @@ impl ExecutionResult {
     }
 }
 
+/// Kind of an [ExecutionResult], without its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExecutionResultKind {
+    Success,
+    Revert,
+    Halt,
+}
+
+impl ExecutionResultKind {
+    /// Returns the name of the kind, e.g. `success`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Revert => "revert",
+            Self::Halt => "halt",
+        }
+    }
+}
+
+/// Summary of the token transfers made by a successful transaction, including the transfers
+/// of its calls but not of the calls that reverted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenTransferSummary {
+    /// Number of transfers of a nonzero amount.
+    pub count: u64,
+    /// Amount transferred of every token, ordered by token ID.
+    pub totals: Vec<TokenTransfer>,
+}
+
+impl TokenTransferSummary {
+    /// Adds a transfer to the summary. Transfers of a zero amount are ignored.
+    pub fn record(&mut self, id: U256, amount: U256) {
+        if amount.is_zero() {
+            return;
+        }
+        self.count += 1;
+        add_to_totals(&mut self.totals, id, amount);
+    }
+
+    /// Returns the amount transferred of the token.
+    pub fn total(&self, id: U256) -> U256 {
+        total_of(&self.totals, id)
+    }
+}
+
+/// Adds the amount to the total of the token in the totals ordered by token ID.
+fn add_to_totals(totals: &mut Vec<TokenTransfer>, id: U256, amount: U256) {
+    match totals.binary_search_by(|total| total.id.cmp(&id)) {
+        Ok(index) => {
+            let total = &mut totals[index].amount;
+            *total = total.saturating_add(amount);
+        }
+        Err(index) => totals.insert(index, TokenTransfer { id, amount }),
+    }
+}
+
+/// Returns the total of the token in the totals.
+fn total_of(totals: &[TokenTransfer], id: U256) -> U256 {
+    totals
+        .iter()
+        .find(|total| total.id == id)
+        .map_or(U256::ZERO, |total| total.amount)
+}
+
+/// Amounts of the tokens minted and burned, by a transaction or by a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenSupplyChanges {
+    /// Amount minted of every token, ordered by token ID.
+    pub minted: Vec<TokenTransfer>,
+    /// Amount burned of every token, ordered by token ID.
+    pub burned: Vec<TokenTransfer>,
+}
+
+impl TokenSupplyChanges {
+    /// Adds a mint of the token. Mints of a zero amount are ignored.
+    pub fn record_mint(&mut self, id: U256, amount: U256) {
+        if !amount.is_zero() {
+            add_to_totals(&mut self.minted, id, amount);
+        }
+    }
+
+    /// Adds a burn of the token. Burns of a zero amount are ignored.
+    pub fn record_burn(&mut self, id: U256, amount: U256) {
+        if !amount.is_zero() {
+            add_to_totals(&mut self.burned, id, amount);
+        }
+    }
+
+    /// Adds the mints and burns of the other changes, e.g. of a transaction to those of its block.
+    pub fn extend(&mut self, other: &Self) {
+        for mint in &other.minted {
+            self.record_mint(mint.id, mint.amount);
+        }
+        for burn in &other.burned {
+            self.record_burn(burn.id, burn.amount);
+        }
+    }
+
+    /// Returns the amount minted of the token.
+    pub fn minted(&self, id: U256) -> U256 {
+        total_of(&self.minted, id)
+    }
+
+    /// Returns the amount burned of the token.
+    pub fn burned(&self, id: U256) -> U256 {
+        total_of(&self.burned, id)
+    }
+
+    /// Returns whether no token was minted nor burned.
+    pub fn is_empty(&self) -> bool {
+        self.minted.is_empty() && self.burned.is_empty()
+    }
+}
+
+/// Result of the execution of the transactions of a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockExecutionResult {
+    /// Results of the transactions, in the order they were executed.
+    pub results: Vec<ExecutionResult>,
+    /// Gas used by all the transactions.
+    pub gas_used: u64,
+    /// Tokens minted and burned by all the transactions, so that the emission schedules can be
+    /// enforced and the supply of every token tracked block by block.
+    pub supply_changes: TokenSupplyChanges,
+}
+
+impl BlockExecutionResult {
+    /// Adds the result of the next transaction of the block.
+    pub fn push(&mut self, result: ExecutionResult) {
+        self.gas_used += result.gas_used();
+        if let Some(supply_changes) = result.supply_changes() {
+            self.supply_changes.extend(supply_changes);
+        }
+        self.results.push(result);
+    }
+}
+
+/// Result of a simulated transaction, whose changes are not committed, with the balance changes
+/// it would make, e.g. to preview them in a wallet without tracing the transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Simulation {
+    /// Result of the transaction and the state it would leave.
+    pub result_and_state: ResultAndState,
+    /// Balance changes of the accounts, including the fee paid by the caller and received by
+    /// the beneficiary, ordered by account and token ID.
+    pub balance_deltas: Vec<BalanceDelta>,
+    /// IDs of the tokens that would be minted for the first time, in the order they would be.
+    pub minted_token_ids: Vec<U256>,
+}
+
+impl Simulation {
+    /// Returns the balance change of the token held by the account, if it would change.
+    pub fn balance_delta(&self, address: Address, token_id: U256) -> Option<&BalanceDelta> {
+        self.balance_deltas
+            .iter()
+            .find(|delta| delta.address == address && delta.token_id == token_id)
+    }
+}
+
+/// Change of the balance of a token held by an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BalanceDelta {
+    pub address: Address,
+    pub token_id: U256,
+    /// Balance before the transaction.
+    pub before: U256,
+    /// Balance after the transaction.
+    pub after: U256,
+}
+
+impl BalanceDelta {
+    /// Returns the amount received by the account, zero if its balance decreased.
+    pub fn received(&self) -> U256 {
+        self.after.saturating_sub(self.before)
+    }
+
+    /// Returns the amount spent by the account, zero if its balance increased.
+    pub fn spent(&self) -> U256 {
+        self.before.saturating_sub(self.after)
+    }
+}
+
+/// Outcome of a transaction, with the data of all the kinds of [ExecutionResult] in one type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TxOutcome {
+    pub kind: ExecutionResultKind,
+    pub gas_used: u64,
+    /// Gas refunded, zero unless successful.
+    pub gas_refunded: u64,
+    /// Logs, empty unless successful.
+    pub logs: Vec<Log>,
+    /// Output data, empty if halted.
+    pub output: Bytes,
+    /// Address of the created contract, if a contract was created.
+    pub created_address: Option<Address>,
+    /// Reason of the halt, if halted.
+    pub halt_reason: Option<HaltReason>,
+    /// Token transfers, empty unless successful or if the handler didn't report them.
+    pub token_transfers: TokenTransferSummary,
+}
+
+impl From<ExecutionResult> for TxOutcome {
+    fn from(result: ExecutionResult) -> Self {
+        let kind = result.kind();
+        let gas_used = result.gas_used();
+        let halt_reason = result.halt_reason();
+        match result {
+            ExecutionResult::Success {
+                gas_refunded,
+                logs,
+                output,
+                token_transfers,
+                ..
+            } => Self {
+                kind,
+                gas_used,
+                gas_refunded,
+                logs,
+                created_address: output.address().copied(),
+                output: output.into_data(),
+                halt_reason,
+                token_transfers: token_transfers.unwrap_or_default(),
+            },
+            ExecutionResult::Revert { output, .. } => Self {
+                kind,
+                gas_used,
+                gas_refunded: 0,
+                logs: Vec::new(),
+                output,
+                created_address: None,
+                halt_reason,
+                token_transfers: TokenTransferSummary::default(),
+            },
+            ExecutionResult::Halt { .. } => Self {
+                kind,
+                gas_used,
+                gas_refunded: 0,
+                logs: Vec::new(),
+                output: Bytes::new(),
+                created_address: None,
+                halt_reason,
+                token_transfers: TokenTransferSummary::default(),
+            },
+        }
+    }
+}
+
+/// Breakdown of the gas spent by a successful transaction.
+///
+/// The gas used by the transaction is `intrinsic + execution - refunded`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GasBreakdown {
+    /// Gas charged before execution, for the calldata, the access list, the authorizations and
+    /// the transferred tokens.
+    pub intrinsic: u64,
+    /// Gas spent by the execution of the transaction.
+    pub execution: u64,
+    /// Gas refunded to the transaction, after the refund cap.
+    pub refunded: u64,
+    /// Whether the refund was limited by the refund cap of EIP-3529 (or of the hardforks before
+    /// London).
+    pub refund_capped: bool,
+    /// Part of the intrinsic gas charged for the transferred tokens.
+    pub token_transfer_surcharge: u64,
+}
+
 /// Output of a transaction execution.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -212,6 +564,11 @@ pub enum InvalidTransaction {
         fee: Box<U256>,
         balance: Box<U256>,
     },
+    /// The gas payer of a sponsored transaction doesn't have enough base token to cover gas_limit*gas_price.
+    GasPayerLackOfFundForMaxFee {
+        fee: Box<U256>,
+        balance: Box<U256>,
+    },
     /// Overflow payment in transaction.
     OverflowPaymentInTransaction,
     /// Nonce overflows in transaction.
@@ -250,6 +607,10 @@ pub enum InvalidTransaction {
     BlobVersionNotSupported,
     /// EOF crate should have `to` address
     EofCrateShouldHaveToAddress,
+    /// `authorization_list` is not supported for blocks before the Prague hardfork.
+    AuthorizationListNotSupported,
+    /// Set-code transaction can't be a create transaction or carry blobs.
+    AuthorizationListInvalidFields,
     /// System transactions are not supported post-regolith hardfork.
     ///
     /// Before the Regolith hardfork, there was a special field in the `Deposit` transaction
@@ -294,6 +655,14 @@ pub enum InvalidTransaction {
     },
     /// Token IDs in transaction are not unique
     TokenIdsNotUnique,
+    /// Transaction transfers tokens to a precompile, where they would be stranded.
+    TokenTransferToPrecompile,
+    /// Transaction calls an address reserved for the precompiles, where no precompile is.
+    CallToReservedAddress,
+    /// A tip of the transaction would overflow the balance of the beneficiary.
+    TipOverflow {
+        token_id: Box<U256>,
+    },
 }
 
 #[cfg(feature = "std")]
@@ -350,6 +719,10 @@ impl fmt::Display for InvalidTransaction {
             }
             Self::BlobVersionNotSupported => write!(f, "blob version not supported"),
             Self::EofCrateShouldHaveToAddress => write!(f, "EOF crate should have `to` address"),
+            Self::AuthorizationListNotSupported => write!(f, "authorization list not supported"),
+            Self::AuthorizationListInvalidFields => {
+                write!(f, "authorization list tx has invalid fields")
+            }
             #[cfg(feature = "optimism")]
             Self::DepositSystemTxPostRegolith => {
                 write!(
@@ -370,6 +743,9 @@ impl fmt::Display for InvalidTransaction {
             Self::LackOfFundForMaxFee { fee, balance } => {
                 write!(f, "lack of funds ({balance}) for max fee ({fee})")
             }
+            Self::GasPayerLackOfFundForMaxFee { fee, balance } => {
+                write!(f, "lack of gas payer funds ({balance}) for max fee ({fee})")
+            }
             Self::NotEnoughTokenBalanceForTransfer {
                 token_id,
                 required_balance,
@@ -378,6 +754,18 @@ impl fmt::Display for InvalidTransaction {
                 write!(f, "The account balance {actual_balance} of token id {token_id} is not enough to cover the required {required_balance}")
             }
             Self::TokenIdsNotUnique => write!(f, "The ids of the submitted tokens are not unique"),
+            Self::TokenTransferToPrecompile => {
+                write!(f, "The transaction transfers tokens to a precompile")
+            }
+            Self::CallToReservedAddress => {
+                write!(f, "The transaction calls a reserved precompile address")
+            }
+            Self::TipOverflow { token_id } => {
+                write!(
+                    f,
+                    "The tip of token id {token_id} overflows the beneficiary balance"
+                )
+            }
         }
     }
 }
@@ -390,6 +778,8 @@ pub enum InvalidHeader {
     PrevrandaoNotSet,
     /// `excess_blob_gas` is not set for Cancun and above.
     ExcessBlobGasNotSet,
+    /// An emission of the block would overflow the balance of its recipient.
+    EmissionOverflow,
 }
 
 #[cfg(feature = "std")]
@@ -400,6 +790,50 @@ impl fmt::Display for InvalidHeader {
         match self {
             Self::PrevrandaoNotSet => write!(f, "`prevrandao` not set"),
             Self::ExcessBlobGasNotSet => write!(f, "`excess_blob_gas` not set"),
+            Self::EmissionOverflow => write!(f, "block emission overflows a balance"),
+        }
+    }
+}
+
+/// A problem with the [`crate::env::Env`], as reported by [`crate::env::Env::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidEnv {
+    /// The block environment is invalid.
+    Header(InvalidHeader),
+    /// The transaction is invalid.
+    Transaction(InvalidTransaction),
+}
+
+impl From<InvalidHeader> for InvalidEnv {
+    fn from(value: InvalidHeader) -> Self {
+        Self::Header(value)
+    }
+}
+
+impl From<InvalidTransaction> for InvalidEnv {
+    fn from(value: InvalidTransaction) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl<DBError> From<InvalidEnv> for EVMError<DBError> {
+    fn from(value: InvalidEnv) -> Self {
+        match value {
+            InvalidEnv::Header(e) => Self::Header(e),
+            InvalidEnv::Transaction(e) => Self::Transaction(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidEnv {}
+
+impl fmt::Display for InvalidEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Header(e) => write!(f, "header validation error: {e}"),
+            Self::Transaction(e) => write!(f, "transaction validation error: {e}"),
         }
     }
 }
@@ -449,6 +883,27 @@ pub enum HaltReason {
 
     /* Sablier errors */
     UnauthorizedCaller,
+    /// A call transferred tokens to a precompile, where they would be stranded.
+    TokenTransferToPrecompile,
+    /// A contract `CALL`ed a contract that only accepts Native Token call values with a nonzero
+    /// value, see [CfgEnv::reject_legacy_value_calls](crate::CfgEnv::reject_legacy_value_calls).
+    LegacyValueCallToMntOnlyContract,
+    /// The journal of a frame or of the transaction exceeded its number of entries, see
+    /// [CfgEnv::journal_entries_limit](crate::CfgEnv::journal_entries_limit).
+    JournalLimitExceeded,
+
+    /* Internal Halt that can be only found inside Inspector */
+    /// Execution was halted by a debugger.
+    DebuggerHalt,
+    /// Execution was interrupted by a watchdog, e.g. when it ran out of time.
+    Interrupted,
+}
+
+#[allow(non_upper_case_globals)]
+impl HaltReason {
+    /// Former name of [HaltReason::OutOfFunds].
+    #[deprecated = "use `HaltReason::OutOfFunds`"]
+    pub const OutOfFund: Self = Self::OutOfFunds;
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -0,0 +1,178 @@
+//! Decoding of the reasons of reverts from the output of the reverted executions.
+use crate::{hex, Bytes, U256};
+use core::fmt;
+use std::string::String;
+
+/// The selector of `Error(string)`, the error of `revert("reason")` and `require(false, "reason")`.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// The selector of `Panic(uint256)`, the error of failed assertions, arithmetic overflows and
+/// other checks inserted by the Solidity compiler.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// A custom error, e.g. `error Unauthorized(address caller)`, identified by its selector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CustomError {
+    /// The first four bytes of the hash of the signature.
+    pub selector: [u8; 4],
+    /// The signature of the error, e.g. `Unauthorized(address)`.
+    pub signature: &'static str,
+}
+
+/// The reason of a revert, decoded from the output of the reverted execution.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RevertReason {
+    /// `Error(string)`, with its message.
+    Error(String),
+    /// `Panic(uint256)`, with its code.
+    Panic(U256),
+    /// One of the custom errors the output was decoded with, with its ABI-encoded arguments.
+    Custom { error: CustomError, args: Bytes },
+    /// An output that isn't a known error, e.g. the output of a bare `revert()` or an unknown
+    /// custom error.
+    Unknown(Bytes),
+}
+
+impl RevertReason {
+    /// Decodes the output of a reverted execution, recognizing the standard errors and the given
+    /// custom errors.
+    pub fn decode(output: &Bytes, custom_errors: &[CustomError]) -> Self {
+        Self::decode_known(output, custom_errors).unwrap_or_else(|| Self::Unknown(output.clone()))
+    }
+
+    fn decode_known(output: &Bytes, custom_errors: &[CustomError]) -> Option<Self> {
+        let selector: [u8; 4] = output.get(..4)?.try_into().ok()?;
+        let args = &output[4..];
+        match selector {
+            ERROR_SELECTOR => decode_string(args).map(Self::Error),
+            PANIC_SELECTOR => (args.len() == 32).then(|| Self::Panic(U256::from_be_slice(args))),
+            _ => custom_errors
+                .iter()
+                .find(|error| error.selector == selector)
+                .map(|error| Self::Custom {
+                    error: *error,
+                    args: output.slice(4..),
+                }),
+        }
+    }
+
+    /// Returns the description of the code of a `Panic(uint256)`, if the code is known.
+    pub fn panic_description(code: U256) -> Option<&'static str> {
+        let description = match u8::try_from(code).ok()? {
+            0x00 => "generic compiler panic",
+            0x01 => "assertion failed",
+            0x11 => "arithmetic overflow or underflow",
+            0x12 => "division or modulo by zero",
+            0x21 => "invalid enum value",
+            0x22 => "invalid storage byte array encoding",
+            0x31 => "pop on an empty array",
+            0x32 => "array index out of bounds",
+            0x41 => "out of memory",
+            0x51 => "call to a zero-initialized internal function",
+            _ => return None,
+        };
+        Some(description)
+    }
+}
+
+/// Decodes the ABI encoding of a single `string`.
+fn decode_string(args: &[u8]) -> Option<String> {
+    let word = |offset: usize| -> Option<usize> {
+        let word = args.get(offset..offset.checked_add(32)?)?;
+        usize::try_from(U256::from_be_slice(word)).ok()
+    };
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = args.get(start..start.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error(message) => write!(f, "{message}"),
+            Self::Panic(code) => match Self::panic_description(*code) {
+                Some(description) => write!(f, "panic: {description} ({code:#04x})"),
+                None => write!(f, "panic: unknown code {code:#x}"),
+            },
+            Self::Custom { error, .. } => write!(f, "{}", error.signature),
+            Self::Unknown(output) if output.is_empty() => write!(f, "reverted without a reason"),
+            Self::Unknown(output) => {
+                write!(f, "unknown revert reason {}", hex::encode_prefixed(output))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bytes, ExecutionResult};
+
+    #[test]
+    fn decodes_standard_and_custom_errors() {
+        let revert = |output: Bytes| ExecutionResult::Revert {
+            gas_used: 0,
+            output,
+        };
+
+        // revert("Not enough tokens")
+        let error = revert(bytes!(
+            "08c379a0"
+            "0000000000000000000000000000000000000000000000000000000000000020"
+            "0000000000000000000000000000000000000000000000000000000000000011"
+            "4e6f7420656e6f75676820746f6b656e73000000000000000000000000000000"
+        ));
+        assert_eq!(
+            error.revert_reason(),
+            Some(RevertReason::Error(String::from("Not enough tokens")))
+        );
+
+        // arithmetic overflow
+        let panic = revert(bytes!(
+            "4e487b71"
+            "0000000000000000000000000000000000000000000000000000000000000011"
+        ));
+        let reason = panic.revert_reason().unwrap();
+        assert_eq!(reason, RevertReason::Panic(U256::from(0x11)));
+        assert_eq!(
+            reason.to_string(),
+            "panic: arithmetic overflow or underflow (0x11)"
+        );
+
+        // error Unauthorized(address)
+        let unauthorized = CustomError {
+            selector: [0x8e, 0x4a, 0x23, 0xd6],
+            signature: "Unauthorized(address)",
+        };
+        let custom = revert(bytes!(
+            "8e4a23d6"
+            "00000000000000000000000000000000000000000000000000000000000000aa"
+        ));
+        assert_eq!(
+            custom.revert_reason_with(&[unauthorized]),
+            Some(RevertReason::Custom {
+                error: unauthorized,
+                args: custom.output().unwrap().slice(4..),
+            })
+        );
+        assert_eq!(
+            custom.revert_reason(),
+            Some(RevertReason::Unknown(custom.output().unwrap().clone()))
+        );
+
+        // truncated errors aren't decoded
+        let truncated = revert(
+            bytes!("08c379a0" "0000000000000000000000000000000000000000000000000000000000000020"),
+        );
+        assert!(matches!(
+            truncated.revert_reason(),
+            Some(RevertReason::Unknown(_))
+        ));
+        assert_eq!(
+            revert(Bytes::new()).revert_reason().unwrap().to_string(),
+            "reverted without a reason"
+        );
+    }
+}
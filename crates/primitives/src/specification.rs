@@ -87,6 +87,20 @@ impl SpecId {
     pub const fn enabled(our: SpecId, other: SpecId) -> bool {
         our as u8 >= other as u8
     }
+
+    /// Returns the default quotient of the gas spent that caps the gas refund of a transaction.
+    ///
+    /// The refund is at most a half of the gas spent, and a fifth since London ([EIP-3529]).
+    ///
+    /// [EIP-3529]: https://eips.ethereum.org/EIPS/eip-3529
+    #[inline]
+    pub const fn max_refund_quotient(self) -> u64 {
+        if self.is_enabled_in(Self::LONDON) {
+            5
+        } else {
+            2
+        }
+    }
 }
 
 impl From<&str> for SpecId {
@@ -1,7 +1,10 @@
-use crate::{Address, Bytecode, HashMap, B256, BASE_TOKEN_ID, KECCAK_EMPTY, U256};
+use crate::{keccak256, Address, Bytecode, HashMap, B256, BASE_TOKEN_ID, KECCAK_EMPTY, U256};
 use bitflags::bitflags;
-use core::hash::{Hash, Hasher};
-use std::vec::Vec;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+use std::vec::{self, Vec};
 
 /// EVM State contains a mapping from addresses to accounts, as well as the collection of supported Native Tokens.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -13,6 +16,132 @@ pub struct EvmState {
     pub token_ids: Vec<U256>,
 }
 
+impl EvmState {
+    /// Returns the sum of the balances of the given token over all the accounts.
+    pub fn total_balance(&self, token_id: U256) -> U256 {
+        self.accounts.values().fold(U256::ZERO, |total, account| {
+            total.wrapping_add(account.info.get_balance(token_id))
+        })
+    }
+
+    /// Removes the zero balances of all the accounts.
+    pub fn prune_zero_balances(&mut self) {
+        self.accounts
+            .values_mut()
+            .for_each(|account| account.info.prune_zero_balances());
+    }
+
+    /// Returns a canonical fingerprint of the state.
+    ///
+    /// The fingerprint does not depend on the iteration order of the underlying maps, and
+    /// zero balances and zero storage values are treated the same as missing ones. The order
+    /// of the token ids is significant.
+    pub fn fingerprint(&self) -> B256 {
+        let mut data = Vec::new();
+        for token_id in &self.token_ids {
+            data.extend_from_slice(&token_id.to_be_bytes::<32>());
+        }
+
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_unstable_by_key(|(address, _)| *address);
+        for (address, account) in accounts {
+            data.extend_from_slice(address.as_slice());
+            data.push(account.status.bits());
+            data.extend_from_slice(&account.info.nonce.to_be_bytes());
+            data.extend_from_slice(account.info.code_hash.as_slice());
+
+            let mut balances: Vec<_> = account.info.iter_nonzero_balances().collect();
+            balances.sort_unstable();
+            for (token_id, balance) in balances {
+                data.extend_from_slice(&token_id.to_be_bytes::<32>());
+                data.extend_from_slice(&balance.to_be_bytes::<32>());
+            }
+
+            let mut storage: Vec<_> = account
+                .storage
+                .iter()
+                .filter(|(_, slot)| !slot.present_value.is_zero())
+                .collect();
+            storage.sort_unstable_by_key(|(key, _)| *key);
+            for (key, slot) in storage {
+                data.extend_from_slice(&key.to_be_bytes::<32>());
+                data.extend_from_slice(&slot.present_value.to_be_bytes::<32>());
+            }
+        }
+        keccak256(data)
+    }
+
+    /// Returns an iterator draining the accounts in chunks of at most `chunk_size` accounts,
+    /// ordered by address.
+    ///
+    /// Every chunk holds the token ids, so that it can be committed on its own. A state without
+    /// accounts is drained in a single chunk holding only the token ids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn into_chunks(self, chunk_size: usize) -> StateChunks {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        let mut addresses: Vec<Address> = self.accounts.keys().copied().collect();
+        addresses.sort_unstable();
+        StateChunks {
+            accounts: self.accounts,
+            addresses: addresses.into_iter(),
+            token_ids: self.token_ids,
+            chunk_size,
+            drained: false,
+        }
+    }
+}
+
+/// An iterator draining the accounts of an [EvmState] in chunks, see [EvmState::into_chunks].
+///
+/// The accounts are moved out of the state as the chunks are made, so a consumer that releases
+/// every chunk before requesting the next one never holds two copies of the whole state.
+#[derive(Debug)]
+pub struct StateChunks {
+    accounts: HashMap<Address, Account>,
+    addresses: vec::IntoIter<Address>,
+    token_ids: Vec<U256>,
+    chunk_size: usize,
+    drained: bool,
+}
+
+impl StateChunks {
+    /// Returns the number of accounts that haven't been drained yet.
+    pub fn remaining_accounts(&self) -> usize {
+        self.addresses.len()
+    }
+}
+
+impl Iterator for StateChunks {
+    type Item = EvmState;
+
+    fn next(&mut self) -> Option<EvmState> {
+        if self.drained && self.addresses.len() == 0 {
+            return None;
+        }
+        self.drained = true;
+
+        let accounts = self
+            .addresses
+            .by_ref()
+            .take(self.chunk_size)
+            .map(|address| {
+                let account = self
+                    .accounts
+                    .remove(&address)
+                    .expect("address of the state");
+                (address, account)
+            })
+            .collect();
+        Some(EvmState {
+            accounts,
+            token_ids: self.token_ids.clone(),
+        })
+    }
+}
+
 /// Structure used for EIP-1153 transient storage.
 pub type TransientStorage = HashMap<(Address, U256), U256>;
 
@@ -185,6 +314,28 @@ impl EvmStorageSlot {
     }
 }
 
+/// Error of the checked balance mutations of [AccountInfo].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BalanceError {
+    /// The balance of the token would exceed `U256::MAX`.
+    Overflow { token_id: U256 },
+    /// The balance of the token would drop below zero.
+    Underflow { token_id: U256 },
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BalanceError {}
+
+impl fmt::Display for BalanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow { token_id } => write!(f, "balance of token {token_id} overflows"),
+            Self::Underflow { token_id } => write!(f, "balance of token {token_id} underflows"),
+        }
+    }
+}
+
 /// The token balances of an account, as a mapping from token ids to token amounts owned by the address.
 pub type TokenBalances = HashMap<U256, U256>;
 
@@ -266,22 +417,11 @@ impl AccountInfo {
     ///
     /// An account is empty if the following conditions are met.
     /// - code hash is zero or set to the Keccak256 hash of the empty string `""`
-    /// - the balances of the Account haven't been set
+    /// - the balances of all the tokens are zero, whichever token is the base token of the chain
     /// - nonce is zero
     pub fn is_empty(&self) -> bool {
         let code_empty = self.is_empty_code_hash() || self.code_hash == B256::ZERO;
-        code_empty
-            && (self.balances.len() == 0
-                || self.only_has_base_balance() && self.is_base_balance_zero())
-            && self.nonce == 0
-    }
-
-    fn only_has_base_balance(&self) -> bool {
-        self.balances.len() == 1 && self.balances.contains_key(&BASE_TOKEN_ID)
-    }
-
-    fn is_base_balance_zero(&self) -> bool {
-        self.get_base_balance() == U256::ZERO
+        code_empty && self.balances.values().all(U256::is_zero) && self.nonce == 0
     }
 
     /// Returns `true` if the account is not empty.
@@ -306,11 +446,23 @@ impl AccountInfo {
         self.code_hash == KECCAK_EMPTY
     }
 
+    /// Decreases the token balance of the account, returning the new balance.
+    ///
+    /// The balance is left unchanged if it is lower than `value`.
+    pub fn decrease_balance(&mut self, token_id: U256, value: U256) -> Result<U256, BalanceError> {
+        let balance = self
+            .get_balance(token_id)
+            .checked_sub(value)
+            .ok_or(BalanceError::Underflow { token_id })?;
+        self.balances.insert(token_id, balance);
+        Ok(balance)
+    }
+
     /// Decreases the token balance of the account, wrapping around `0` on underflow.
-    pub fn decrease_balance(&mut self, token_id: U256, balance: U256) -> Option<U256> {
+    pub fn wrapping_decrease_balance(&mut self, token_id: U256, value: U256) -> Option<U256> {
         let current_balance = self.get_balance(token_id);
         self.balances
-            .insert(token_id, current_balance.wrapping_sub(balance))
+            .insert(token_id, current_balance.wrapping_sub(value))
     }
 
     /// Decreases the token balance of the account, saturating at zero.
@@ -320,9 +472,16 @@ impl AccountInfo {
             .insert(token_id, current_balance.saturating_sub(balance))
     }
 
+    /// Decreases the base token balance of the account, returning the new balance.
+    ///
+    /// The balance is left unchanged if it is lower than `value`.
+    pub fn decrease_base_balance(&mut self, value: U256) -> Result<U256, BalanceError> {
+        self.decrease_balance(BASE_TOKEN_ID, value)
+    }
+
     /// Decreases the base token balance of the account, wrapping around `0` on underflow.
-    pub fn decrease_base_balance(&mut self, balance: U256) -> Option<U256> {
-        self.decrease_balance(BASE_TOKEN_ID, balance)
+    pub fn wrapping_decrease_base_balance(&mut self, value: U256) -> Option<U256> {
+        self.wrapping_decrease_balance(BASE_TOKEN_ID, value)
     }
 
     /// Decreases the base token balance of the account, saturating at zero.
@@ -340,8 +499,20 @@ impl AccountInfo {
         self.get_balance(BASE_TOKEN_ID)
     }
 
+    /// Increases the `token_id` balance of the account, returning the new balance.
+    ///
+    /// The balance is left unchanged if it would exceed `U256::MAX`.
+    pub fn increase_balance(&mut self, token_id: U256, value: U256) -> Result<U256, BalanceError> {
+        let balance = self
+            .get_balance(token_id)
+            .checked_add(value)
+            .ok_or(BalanceError::Overflow { token_id })?;
+        self.balances.insert(token_id, balance);
+        Ok(balance)
+    }
+
     /// Increases the `token_id` balance of the account, wrapping around `U256::MAX` on overflow.
-    pub fn increase_balance(&mut self, token_id: U256, value: U256) -> Option<U256> {
+    pub fn wrapping_increase_balance(&mut self, token_id: U256, value: U256) -> Option<U256> {
         let current_balance = self.get_balance(token_id);
         self.balances
             .insert(token_id, current_balance.wrapping_add(value))
@@ -354,16 +525,36 @@ impl AccountInfo {
             .insert(token_id, current_balance.saturating_add(value))
     }
 
-    /// Increases the base token balance of the account, wrapping around `U256::MAX` on overflow.
-    pub fn increase_base_balance(&mut self, value: U256) -> Option<U256> {
+    /// Increases the base token balance of the account, returning the new balance.
+    ///
+    /// The balance is left unchanged if it would exceed `U256::MAX`.
+    pub fn increase_base_balance(&mut self, value: U256) -> Result<U256, BalanceError> {
         self.increase_balance(BASE_TOKEN_ID, value)
     }
 
+    /// Increases the base token balance of the account, wrapping around `U256::MAX` on overflow.
+    pub fn wrapping_increase_base_balance(&mut self, value: U256) -> Option<U256> {
+        self.wrapping_increase_balance(BASE_TOKEN_ID, value)
+    }
+
     /// Increases the base token balance of the account, saturating at `U256::MAX`.
     pub fn increase_base_balance_saturating(&mut self, value: U256) -> Option<U256> {
         self.increase_balance_saturating(BASE_TOKEN_ID, value)
     }
 
+    /// Returns an iterator over the token ids and amounts of the non-zero balances.
+    pub fn iter_nonzero_balances(&self) -> impl Iterator<Item = (U256, U256)> + '_ {
+        self.balances
+            .iter()
+            .filter(|(_, balance)| !balance.is_zero())
+            .map(|(token_id, balance)| (*token_id, *balance))
+    }
+
+    /// Removes the zero balances, e.g. the ones drained by transfers.
+    pub fn prune_zero_balances(&mut self) {
+        self.balances.retain(|_, balance| !balance.is_zero());
+    }
+
     pub fn set_balance(&mut self, token_id: U256, balance: U256) -> Option<U256> {
         self.balances.insert(token_id, balance)
     }
@@ -389,7 +580,79 @@ impl From<TokenBalances> for AccountInfo {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Account, KECCAK_EMPTY, U256};
+    use crate::{
+        Account, AccountInfo, Address, BalanceError, EvmState, EvmStorageSlot, BASE_TOKEN_ID,
+        KECCAK_EMPTY, U256,
+    };
+    use std::vec::Vec;
+
+    #[test]
+    fn drains_accounts_in_chunks() {
+        let mut state = EvmState {
+            token_ids: vec![BASE_TOKEN_ID, U256::from(1)],
+            ..Default::default()
+        };
+        for byte in [5, 1, 4, 2, 3] {
+            state
+                .accounts
+                .insert(Address::with_last_byte(byte), Account::default());
+        }
+
+        let mut chunks = state.clone().into_chunks(2);
+        let mut addresses = Vec::new();
+        for expected_len in [2, 2, 1] {
+            let chunk = chunks.next().unwrap();
+            assert_eq!(chunk.accounts.len(), expected_len);
+            assert_eq!(chunk.token_ids, state.token_ids);
+            let mut chunk_addresses: Vec<_> = chunk.accounts.into_keys().collect();
+            chunk_addresses.sort_unstable();
+            addresses.extend(chunk_addresses);
+        }
+        assert_eq!(chunks.remaining_accounts(), 0);
+        assert!(chunks.next().is_none());
+        // the chunks follow the order of the addresses
+        assert_eq!(
+            addresses,
+            (1..=5).map(Address::with_last_byte).collect::<Vec<_>>()
+        );
+
+        // the token ids of a state without accounts are still drained
+        let empty = EvmState {
+            token_ids: state.token_ids.clone(),
+            ..Default::default()
+        };
+        let chunks: Vec<_> = empty.clone().into_chunks(2).collect();
+        assert_eq!(chunks, vec![empty]);
+    }
+
+    #[test]
+    fn state_fingerprint_is_canonical() {
+        let mut state = EvmState::default();
+        let fingerprint = state.fingerprint();
+
+        // zero balances and zero storage values are the same as missing ones
+        let mut account = Account::default();
+        account.info.set_balance(U256::from(1), U256::ZERO);
+        account
+            .storage
+            .insert(U256::from(1), EvmStorageSlot::new(U256::ZERO));
+        state.accounts.insert(Address::ZERO, account.clone());
+        let mut other = EvmState::default();
+        other.accounts.insert(Address::ZERO, Account::default());
+        assert_eq!(state.fingerprint(), other.fingerprint());
+        assert_ne!(state.fingerprint(), fingerprint);
+
+        account.info.set_balance(U256::from(1), U256::from(2));
+        state.accounts.insert(Address::ZERO, account);
+        assert_ne!(state.fingerprint(), other.fingerprint());
+        assert_eq!(state.total_balance(U256::from(1)), U256::from(2));
+
+        // the order of the token ids is significant
+        other.token_ids = vec![U256::from(1), U256::from(2)];
+        let fingerprint = other.fingerprint();
+        other.token_ids.reverse();
+        assert_ne!(other.fingerprint(), fingerprint);
+    }
 
     #[test]
     fn account_is_empty_balance() {
@@ -398,6 +661,13 @@ mod tests {
 
         account.info.set_base_balance(U256::from(1));
         assert!(!account.is_empty());
+
+        // the balances of the other tokens count too, as any of them can be the base token
+        account.info.set_base_balance(U256::ZERO);
+        account.info.set_balance(U256::from(1), U256::ZERO);
+        assert!(account.is_empty());
+        account.info.set_balance(U256::from(1), U256::from(1));
+        assert!(!account.is_empty());
     }
 
     #[test]
@@ -446,4 +716,54 @@ mod tests {
         assert!(account.is_touched());
         assert!(!account.is_selfdestructed());
     }
+
+    #[test]
+    fn checked_balance_mutations() {
+        let token_id = U256::from(1);
+        let mut info = AccountInfo::default();
+        assert_eq!(
+            info.increase_balance(token_id, U256::from(5)),
+            Ok(U256::from(5))
+        );
+        assert_eq!(
+            info.decrease_balance(token_id, U256::from(2)),
+            Ok(U256::from(3))
+        );
+
+        // failed mutations leave the balance unchanged
+        assert_eq!(
+            info.decrease_balance(token_id, U256::from(4)),
+            Err(BalanceError::Underflow { token_id })
+        );
+        assert_eq!(info.get_balance(token_id), U256::from(3));
+        assert_eq!(
+            info.increase_balance(token_id, U256::MAX),
+            Err(BalanceError::Overflow { token_id })
+        );
+        assert_eq!(info.get_balance(token_id), U256::from(3));
+
+        assert_eq!(
+            info.decrease_base_balance(U256::from(1)),
+            Err(BalanceError::Underflow {
+                token_id: BASE_TOKEN_ID
+            })
+        );
+        info.wrapping_decrease_base_balance(U256::from(1));
+        assert_eq!(info.get_base_balance(), U256::MAX);
+    }
+
+    #[test]
+    fn prunes_zero_balances() {
+        let mut info = AccountInfo::default();
+        info.set_balance(U256::from(1), U256::from(2));
+        info.set_balance(U256::from(3), U256::ZERO);
+        assert_eq!(
+            info.iter_nonzero_balances().collect::<Vec<_>>(),
+            vec![(U256::from(1), U256::from(2))]
+        );
+
+        info.prune_zero_balances();
+        assert_eq!(info.balances.len(), 1);
+        assert_eq!(info.get_balance(U256::from(1)), U256::from(2));
+    }
 }
@@ -1,6 +1,6 @@
 use crate::{
-    b256, TokenBalances, B256, BASE_TOKEN_ID, BLOB_GASPRICE_UPDATE_FRACTION, MIN_BLOB_GASPRICE,
-    TARGET_BLOB_GAS_PER_BLOCK,
+    b256, SpecId, TokenBalances, B256, BASE_TOKEN_ID, BLOB_GASPRICE_UPDATE_FRACTION,
+    MIN_BLOB_GASPRICE, TARGET_BLOB_GAS_PER_BLOCK,
 };
 pub use alloy_primitives::keccak256;
 use alloy_primitives::{Address, U256};
@@ -69,6 +69,9 @@ pub fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> u128 {
 }
 
 /// Returns the token ID by hashing the address and sub ID.
+///
+/// This is the [legacy](TokenIdDerivation::Legacy) derivation, whose preimage isn't
+/// domain-separated from the other hashes of an address and a word, e.g. of a storage slot.
 pub fn token_id_address(address: Address, sub_id: U256) -> U256 {
     let first = &address[..];
     let second_bytes = B256::from(sub_id);
@@ -76,10 +79,58 @@ pub fn token_id_address(address: Address, sub_id: U256) -> U256 {
     keccak256([first, second].concat()).into()
 }
 
+/// The prefix of the preimage of the token IDs derived with [TokenIdDerivation::V1].
+pub const TOKEN_ID_DOMAIN_V1: &[u8] = b"SabVM/TokenID/v1";
+
+/// How the ID of a token is derived from the contract minting it and its sub ID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenIdDerivation {
+    /// `keccak256(minter ++ sub_id)`, see [token_id_address]. Used before Prague.
+    Legacy,
+    /// `keccak256(TOKEN_ID_DOMAIN_V1 ++ minter ++ sub_id)`. Used since Prague, except for the
+    /// tokens already registered with their legacy ID, which keep it.
+    V1,
+}
+
+impl TokenIdDerivation {
+    /// All the derivations, from the oldest.
+    pub const ALL: [Self; 2] = [Self::Legacy, Self::V1];
+
+    /// Returns the derivation of the IDs of the tokens first minted under the spec.
+    #[inline]
+    pub const fn for_spec(spec_id: SpecId) -> Self {
+        if spec_id.is_enabled_in(SpecId::PRAGUE) {
+            Self::V1
+        } else {
+            Self::Legacy
+        }
+    }
+
+    /// Returns the ID of the token minted by the contract with the sub ID.
+    pub fn token_id(self, minter: Address, sub_id: U256) -> U256 {
+        match self {
+            Self::Legacy => token_id_address(minter, sub_id),
+            Self::V1 => {
+                keccak256([TOKEN_ID_DOMAIN_V1, &minter[..], &B256::from(sub_id)[..]].concat())
+                    .into()
+            }
+        }
+    }
+
+    /// Returns the derivation of the token ID from the claimed minter and sub ID, or `None` if
+    /// the token isn't theirs.
+    pub fn verify(token_id: U256, minter: Address, sub_id: U256) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|derivation| derivation.token_id(minter, sub_id) == token_id)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::GAS_PER_BLOB;
+    use crate::{address, GAS_PER_BLOB};
 
     // https://github.com/ethereum/go-ethereum/blob/28857080d732857030eda80c69b9ba2c8926f221/consensus/misc/eip4844/eip4844_test.go#L27
     #[test]
@@ -184,6 +235,51 @@ mod tests {
             assert_eq!(actual, expected, "test: {t:?}");
         }
     }
+    #[test]
+    fn token_id_derivations() {
+        let minter = address!("00000000000000000000000000000000000000aa");
+        let sub_id = U256::from(3);
+        let legacy = token_id_address(minter, sub_id);
+        let v1 = TokenIdDerivation::V1.token_id(minter, sub_id);
+        assert_eq!(TokenIdDerivation::Legacy.token_id(minter, sub_id), legacy);
+        assert_ne!(v1, legacy);
+        assert_eq!(
+            v1,
+            U256::from_be_bytes(
+                keccak256(
+                    [
+                        b"SabVM/TokenID/v1".as_slice(),
+                        &[0; 19],
+                        &[0xaa],
+                        &[0; 31],
+                        &[3]
+                    ]
+                    .concat()
+                )
+                .0
+            )
+        );
+
+        assert_eq!(
+            TokenIdDerivation::for_spec(SpecId::CANCUN),
+            TokenIdDerivation::Legacy
+        );
+        assert_eq!(
+            TokenIdDerivation::for_spec(SpecId::PRAGUE),
+            TokenIdDerivation::V1
+        );
+
+        assert_eq!(
+            TokenIdDerivation::verify(legacy, minter, sub_id),
+            Some(TokenIdDerivation::Legacy)
+        );
+        assert_eq!(
+            TokenIdDerivation::verify(v1, minter, sub_id),
+            Some(TokenIdDerivation::V1)
+        );
+        assert_eq!(TokenIdDerivation::verify(v1, minter, U256::from(4)), None);
+        assert_eq!(TokenIdDerivation::verify(v1, Address::ZERO, sub_id), None);
+    }
 }
 
 #[cfg(feature = "std")]
@@ -0,0 +1,312 @@
+//! # sabvm-python
+//!
+//! Python bindings of SabVM, exposing an [Evm](PyEvm) with an in-memory state to set up, run
+//! transactions on and query, and the traces of its transactions.
+//!
+//! Token IDs, amounts and storage words are Python integers, converted through their 32
+//! big-endian bytes, and addresses are hex strings or 20 bytes.
+use core::{convert::Infallible, str::FromStr};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyDict, PyLong},
+};
+use revm::{
+    inspector_handle_register,
+    primitives::{
+        Address, Bytecode, Bytes, EVMError, ExecutionResult, Output, SpecId, TokenTransfer,
+        TransactTo, BASE_TOKEN_ID, U256,
+    },
+    report::{Report, ReportTracer},
+    DatabaseCommit, DatabaseRef, Evm, InMemoryDB,
+};
+
+create_exception!(
+    sabvm,
+    EvmError,
+    PyException,
+    "The transaction could not be executed."
+);
+
+fn evm_error(error: EVMError<Infallible>) -> PyErr {
+    EvmError::new_err(error.to_string())
+}
+
+/// A 256-bit word, converted from and to a Python integer.
+struct Word(U256);
+
+impl<'py> FromPyObject<'py> for Word {
+    fn extract_bound(value: &Bound<'py, PyAny>) -> PyResult<Self> {
+        // raises `OverflowError` for negative integers and integers of more than 256 bits
+        let bytes = value.call_method1("to_bytes", (32, "big"))?;
+        Ok(Self(U256::from_be_slice(
+            bytes.downcast::<PyBytes>()?.as_bytes(),
+        )))
+    }
+}
+
+impl IntoPy<PyObject> for Word {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let bytes = PyBytes::new_bound(py, &self.0.to_be_bytes::<32>());
+        py.get_type_bound::<PyLong>()
+            .call_method1("from_bytes", (bytes, "big"))
+            .expect("int.from_bytes accepts bytes")
+            .unbind()
+    }
+}
+
+/// An address, converted from a hex string or 20 bytes.
+struct PyAddress(Address);
+
+impl<'py> FromPyObject<'py> for PyAddress {
+    fn extract_bound(value: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let address = match value.downcast::<PyBytes>() {
+            Ok(bytes) => Address::try_from(bytes.as_bytes()).ok(),
+            Err(_) => Address::from_str(&value.extract::<String>()?).ok(),
+        };
+        address
+            .map(Self)
+            .ok_or_else(|| PyValueError::new_err("an address is 20 bytes, or 40 hex digits"))
+    }
+}
+
+/// An EVM and its in-memory state. The calls and the token transfers of its transactions are
+/// traced.
+#[pyclass(name = "Evm", unsendable)]
+struct PyEvm {
+    evm: Evm<'static, ReportTracer, InMemoryDB>,
+}
+
+#[pymethods]
+impl PyEvm {
+    /// Creates an EVM with an empty state, running the hardfork named by `spec`, e.g.
+    /// `"Cancun"`, or the latest one.
+    #[new]
+    #[pyo3(signature = (spec = None))]
+    fn new(spec: Option<&str>) -> Self {
+        let mut evm = Evm::builder()
+            .with_db(InMemoryDB::default())
+            .with_external_context(ReportTracer::new())
+            .append_handler_register(inspector_handle_register)
+            .build();
+        if let Some(spec) = spec {
+            evm.modify_spec_id(SpecId::from(spec));
+        }
+        Self { evm }
+    }
+
+    /// Sets the number, the timestamp and the beneficiary of the block of the next
+    /// transactions.
+    #[pyo3(signature = (number = None, timestamp = None, coinbase = None))]
+    fn set_block(
+        &mut self,
+        number: Option<u64>,
+        timestamp: Option<u64>,
+        coinbase: Option<PyAddress>,
+    ) {
+        let block = self.evm.block_mut();
+        if let Some(number) = number {
+            block.number = U256::from(number);
+        }
+        if let Some(timestamp) = timestamp {
+            block.timestamp = U256::from(timestamp);
+        }
+        if let Some(PyAddress(coinbase)) = coinbase {
+            block.coinbase = coinbase;
+        }
+    }
+
+    /// Registers the token, so that it can be transferred.
+    fn register_token(&mut self, token_id: Word) {
+        self.evm.db_mut().register_token(token_id.0);
+    }
+
+    /// Adds the amount of the token to the balance of the account, registering the token.
+    fn fund(&mut self, address: PyAddress, token_id: Word, amount: Word) {
+        let Ok(()) = self.evm.db_mut().fund(address.0, token_id.0, amount.0);
+    }
+
+    /// Sets the code of the account.
+    fn deploy(&mut self, address: PyAddress, code: Vec<u8>) {
+        let bytecode = Bytecode::new_raw(Bytes::from(code));
+        let Ok(()) = self.evm.db_mut().deploy(address.0, bytecode);
+    }
+
+    /// Sets the storage slot of the account.
+    fn set_storage(&mut self, address: PyAddress, slot: Word, value: Word) {
+        let Ok(()) = self
+            .evm
+            .db_mut()
+            .insert_account_storage(address.0, slot.0, value.0);
+    }
+
+    /// Returns the balance of the token held by the account, of the base token by default.
+    #[pyo3(signature = (address, token_id = Word(BASE_TOKEN_ID)))]
+    fn balance(&self, address: PyAddress, token_id: Word) -> Word {
+        let Ok(info) = self.evm.db().basic_ref(address.0);
+        Word(info.map_or(U256::ZERO, |info| info.get_balance(token_id.0)))
+    }
+
+    /// Returns the storage slot of the account.
+    fn storage(&self, address: PyAddress, slot: Word) -> Word {
+        let Ok(value) = self.evm.db().storage_ref(address.0, slot.0);
+        Word(value)
+    }
+
+    /// Executes a transaction, committing its state changes unless `commit` is false, and
+    /// returns its result as a dictionary:
+    ///
+    /// - `status`: `"success"`, `"revert"` or `"halt"`
+    /// - `reason`: why the execution stopped or halted, `None` if it reverted
+    /// - `gas_used`, `gas_refunded`
+    /// - `output`: the returned or revert data, as bytes
+    /// - `created_address`: the address of the created contract, or `None`
+    /// - `logs`: the logs, with their `address`, `topics` and `data`
+    /// - `calls`: the calls, with their `depth`, `kind`, `caller`, `target`, transferred
+    ///   `values` as `(token_id, amount)` pairs, `gas_used` and `result`
+    /// - `token_flows`: the token transfers, with their `from`, `to`, `token_id`, `amount`
+    ///   and whether they were `reverted`
+    /// - `report`: a markdown report of the transaction
+    ///
+    /// Raises `EvmError` if the transaction is invalid.
+    #[pyo3(signature = (
+        caller,
+        to = None,
+        data = Vec::new(),
+        gas_limit = 30_000_000,
+        gas_price = Word(U256::ZERO),
+        transfers = Vec::new(),
+        commit = true,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn transact(
+        &mut self,
+        py: Python<'_>,
+        caller: PyAddress,
+        to: Option<PyAddress>,
+        data: Vec<u8>,
+        gas_limit: u64,
+        gas_price: Word,
+        transfers: Vec<(Word, Word)>,
+        commit: bool,
+    ) -> PyResult<PyObject> {
+        let tx = self.evm.tx_mut();
+        tx.caller = caller.0;
+        tx.transact_to = match to {
+            Some(PyAddress(to)) => TransactTo::Call(to),
+            None => TransactTo::Create,
+        };
+        tx.data = Bytes::from(data);
+        tx.gas_limit = gas_limit;
+        tx.gas_price = gas_price.0;
+        tx.transferred_tokens = transfers
+            .into_iter()
+            .map(|(token_id, amount)| TokenTransfer {
+                id: token_id.0,
+                amount: amount.0,
+            })
+            .collect();
+
+        let result_and_state = self.evm.transact().map_err(evm_error)?;
+        let tracer = &self.evm.context.external;
+        let result = &result_and_state.result;
+        let dict = PyDict::new_bound(py);
+        let (status, reason, gas_refunded) = match result {
+            ExecutionResult::Success {
+                reason,
+                gas_refunded,
+                ..
+            } => ("success", Some(format!("{reason:?}")), *gas_refunded),
+            ExecutionResult::Revert { .. } => ("revert", None, 0),
+            ExecutionResult::Halt { reason, .. } => ("halt", Some(format!("{reason:?}")), 0),
+        };
+        dict.set_item("status", status)?;
+        dict.set_item("reason", reason)?;
+        dict.set_item("gas_used", result.gas_used())?;
+        dict.set_item("gas_refunded", gas_refunded)?;
+        let output = result.output().cloned().unwrap_or_default();
+        dict.set_item("output", PyBytes::new_bound(py, &output))?;
+        let created_address = match result {
+            ExecutionResult::Success {
+                output: Output::Create(_, address),
+                ..
+            } => address.map(|address| address.to_string()),
+            _ => None,
+        };
+        dict.set_item("created_address", created_address)?;
+
+        let logs = result
+            .logs()
+            .iter()
+            .map(|log| {
+                let entry = PyDict::new_bound(py);
+                entry.set_item("address", log.address.to_string())?;
+                let topics: Vec<String> = log
+                    .data
+                    .topics()
+                    .iter()
+                    .map(|topic| topic.to_string())
+                    .collect();
+                entry.set_item("topics", topics)?;
+                entry.set_item("data", PyBytes::new_bound(py, &log.data.data))?;
+                Ok(entry.into_py(py))
+            })
+            .collect::<PyResult<Vec<PyObject>>>()?;
+        dict.set_item("logs", logs)?;
+
+        let calls = tracer
+            .calls()
+            .iter()
+            .map(|call| {
+                let entry = PyDict::new_bound(py);
+                entry.set_item("depth", call.depth)?;
+                entry.set_item("kind", call.kind.as_str())?;
+                entry.set_item("caller", call.caller.to_string())?;
+                entry.set_item("target", call.target.map(|target| target.to_string()))?;
+                let values: Vec<(PyObject, PyObject)> = call
+                    .values
+                    .iter()
+                    .map(|value| (Word(value.id).into_py(py), Word(value.amount).into_py(py)))
+                    .collect();
+                entry.set_item("values", values)?;
+                entry.set_item("gas_used", call.gas_used)?;
+                entry.set_item("result", call.result.map(|result| format!("{result:?}")))?;
+                Ok(entry.into_py(py))
+            })
+            .collect::<PyResult<Vec<PyObject>>>()?;
+        dict.set_item("calls", calls)?;
+
+        let token_flows = tracer
+            .token_flows()
+            .iter()
+            .map(|flow| {
+                let entry = PyDict::new_bound(py);
+                entry.set_item("from", flow.from.to_string())?;
+                entry.set_item("to", flow.to.to_string())?;
+                entry.set_item("token_id", Word(flow.token_id).into_py(py))?;
+                entry.set_item("amount", Word(flow.amount).into_py(py))?;
+                entry.set_item("reverted", flow.reverted)?;
+                Ok(entry.into_py(py))
+            })
+            .collect::<PyResult<Vec<PyObject>>>()?;
+        dict.set_item("token_flows", token_flows)?;
+
+        let report = Report::new(&result_and_state).with_tracer(tracer);
+        dict.set_item("report", report.to_string())?;
+
+        if commit {
+            self.evm.db_mut().commit(result_and_state.state);
+        }
+        Ok(dict.into_py(py))
+    }
+}
+
+/// Python bindings of SabVM.
+#[pymodule]
+fn sabvm(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEvm>()?;
+    m.add("EvmError", m.py().get_type_bound::<EvmError>())?;
+    m.add("BASE_TOKEN_ID", Word(BASE_TOKEN_ID).into_py(m.py()))?;
+    Ok(())
+}
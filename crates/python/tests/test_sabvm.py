@@ -0,0 +1,47 @@
+import pytest
+import sabvm
+
+CALLER = "0x00000000000000000000000000000000000000aa"
+CONTRACT = "0x00000000000000000000000000000000000000bb"
+TOKEN = 7
+
+# SSTORE(1, 2), STOP
+STORING_CODE = bytes([0x60, 0x02, 0x60, 0x01, 0x55, 0x00])
+
+
+def funded_evm():
+    evm = sabvm.Evm()
+    evm.fund(CALLER, sabvm.BASE_TOKEN_ID, 10**18)
+    evm.fund(CALLER, TOKEN, 1_000)
+    evm.deploy(CONTRACT, STORING_CODE)
+    return evm
+
+
+def test_transact_and_trace():
+    evm = funded_evm()
+    result = evm.transact(CALLER, to=CONTRACT, transfers=[(TOKEN, 400)])
+    assert result["status"] == "success"
+    assert evm.balance(CALLER, TOKEN) == 600
+    assert evm.balance(CONTRACT, TOKEN) == 400
+    assert evm.storage(CONTRACT, 1) == 2
+
+    [call] = result["calls"]
+    assert call["kind"] == "CALL"
+    assert call["values"] == [(TOKEN, 400)]
+    [flow] = result["token_flows"]
+    assert (flow["token_id"], flow["amount"], flow["reverted"]) == (TOKEN, 400, False)
+    assert "## Storage writes" in result["report"]
+
+
+def test_dry_run_keeps_state():
+    evm = funded_evm()
+    result = evm.transact(CALLER, to=CONTRACT, transfers=[(TOKEN, 400)], commit=False)
+    assert result["status"] == "success"
+    assert evm.balance(CONTRACT, TOKEN) == 0
+    assert evm.storage(CONTRACT, 1) == 0
+
+
+def test_invalid_transaction_raises():
+    evm = funded_evm()
+    with pytest.raises(sabvm.EvmError):
+        evm.transact(CALLER, to=CONTRACT, transfers=[(TOKEN, 2_000)])
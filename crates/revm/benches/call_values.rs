@@ -0,0 +1,100 @@
+//! Benchmarks of calls transferring a single token, the most common kind of value transfer.
+//!
+//! Run with `cargo bench --bench call_values`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use revm::{
+    db::{CacheDB, EmptyDB},
+    interpreter::opcode,
+    primitives::{address, init_balances, AccountInfo, Address, Bytecode, TransactTo, U256},
+    Evm,
+};
+use std::time::Duration;
+
+const CALLER: Address = address!("00000000000000000000000000000000000000aa");
+const PROXY: Address = address!("00000000000000000000000000000000000000bb");
+const CALLEE: Address = address!("00000000000000000000000000000000000000cc");
+
+/// Numbers of calls made by the proxy in a transaction.
+const CALL_COUNTS: [usize; 3] = [1, 16, 256];
+
+/// A contract making `calls` calls to the callee, each transferring one unit of the base token.
+fn proxy_bytecode(calls: usize) -> Bytecode {
+    let mut code = Vec::new();
+    for _ in 0..calls {
+        // CALL(GAS, CALLEE, 1, 0, 0, 0, 0), discarding the success flag
+        code.extend_from_slice(&[
+            opcode::PUSH1,
+            0,
+            opcode::PUSH1,
+            0,
+            opcode::PUSH1,
+            0,
+            opcode::PUSH1,
+            0,
+            opcode::PUSH1,
+            1,
+            opcode::PUSH20,
+        ]);
+        code.extend_from_slice(CALLEE.as_slice());
+        code.extend_from_slice(&[opcode::GAS, opcode::CALL, opcode::POP]);
+    }
+    code.push(opcode::STOP);
+    Bytecode::new_raw(code.into())
+}
+
+fn db_with_proxy(calls: usize) -> CacheDB<EmptyDB> {
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(
+        CALLER,
+        AccountInfo {
+            balances: init_balances(U256::from(u64::MAX)),
+            ..Default::default()
+        },
+    );
+    let proxy = proxy_bytecode(calls);
+    db.insert_account_info(
+        PROXY,
+        AccountInfo {
+            balances: init_balances(U256::from(u64::MAX)),
+            code_hash: proxy.hash_slow(),
+            code: Some(proxy),
+            ..Default::default()
+        },
+    );
+    // A contract that only stops.
+    let stop = Bytecode::new_raw([opcode::STOP].to_vec().into());
+    db.insert_account_info(
+        CALLEE,
+        AccountInfo {
+            code_hash: stop.hash_slow(),
+            code: Some(stop),
+            ..Default::default()
+        },
+    );
+    db
+}
+
+fn single_token_calls(c: &mut Criterion) {
+    let mut g = c.benchmark_group("single_token_calls");
+    g.warm_up_time(Duration::from_secs(1));
+    for calls in CALL_COUNTS {
+        let mut evm = Evm::builder()
+            .with_db(db_with_proxy(calls))
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TransactTo::Call(PROXY);
+                tx.gas_limit = 30_000_000;
+            })
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        g.bench_with_input(BenchmarkId::from_parameter(calls), &calls, |b, _| {
+            b.iter(|| evm.transact().unwrap())
+        });
+    }
+    g.finish();
+}
+
+criterion_group!(benches, single_token_calls);
+criterion_main!(benches);
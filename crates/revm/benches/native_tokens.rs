@@ -0,0 +1,272 @@
+//! Benchmarks of the Sablier-specific native token paths.
+//!
+//! Run with `cargo bench --bench native_tokens`.
+//!
+//! For CI, save a snapshot on the base branch and compare the changes against it:
+//!
+//! ```text
+//! cargo bench --bench native_tokens -- --save-baseline main
+//! cargo bench --bench native_tokens -- --baseline main
+//! ```
+//!
+//! Line-oriented output, suitable for diffing or for uploading to a benchmark tracker,
+//! can be obtained with `-- --output-format bencher`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use revm::{
+    db::{CacheDB, EmptyDB},
+    primitives::{
+        address, bytes, init_balances, token_id_address, AccountInfo, Address, Bytecode, Bytes,
+        TokenTransfer, TransactTo, BASE_TOKEN_ID, U256,
+    },
+    sablier::native_tokens::{
+        ADDRESS as NATIVE_TOKENS_ADDRESS, BURN_SELECTOR, MINT_SELECTOR,
+        TRANSFER_MULTIPLE_AND_CALL_SELECTOR, TRANSFER_SELECTOR,
+    },
+    Evm, JournaledState,
+};
+use revm_interpreter::primitives::{HashSet, SpecId};
+use std::time::Duration;
+
+const CALLER: Address = address!("0000000000000000000000000000000000000001");
+const PROXY: Address = address!("0000000000000000000000000000000000000002");
+const CALLEE: Address = address!("0000000000000000000000000000000000000003");
+const RECIPIENT: Address = address!("0000000000000000000000000000000000000004");
+
+/// Token counts used by the benchmarks that scale with the number of transferred tokens.
+const TOKEN_COUNTS: [usize; 3] = [1, 4, 16];
+
+/// Checkpoint depths used by the journal revert benchmark.
+const CHECKPOINT_DEPTHS: [usize; 3] = [16, 128, 1024];
+
+/// A contract that copies its calldata to memory and `DELEGATECALL`s the native tokens precompile with it,
+/// reverting if the precompile fails.
+fn proxy_bytecode() -> Bytecode {
+    // CALLDATACOPY(0, 0, CALLDATASIZE), then push the DELEGATECALL arguments and the precompile address
+    let mut code = bytes!("3660006000376000600036600073").to_vec();
+    code.extend_from_slice(NATIVE_TOKENS_ADDRESS.as_slice());
+    // GAS DELEGATECALL, then jump to STOP on success or REVERT(0, 0) otherwise
+    code.extend_from_slice(&[
+        0x5a, 0xf4, 0x60, 0x2c, 0x57, 0x60, 0x00, 0x60, 0x00, 0xfd, 0x5b, 0x00,
+    ]);
+    Bytecode::new_raw(code.into())
+}
+
+/// Returns a database where the caller and the proxy hold `token_count` tokens, besides the base token.
+fn db_with_tokens(token_count: usize) -> (CacheDB<EmptyDB>, Vec<U256>) {
+    let token_ids: Vec<U256> = (1..=token_count).map(U256::from).collect();
+
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.token_ids.extend(token_ids.iter().copied());
+
+    let mut balances = init_balances(U256::from(u64::MAX));
+    for token_id in &token_ids {
+        balances.insert(*token_id, U256::from(u64::MAX));
+    }
+    db.insert_account_info(
+        CALLER,
+        AccountInfo {
+            balances: balances.clone(),
+            ..Default::default()
+        },
+    );
+
+    let proxy = proxy_bytecode();
+    db.insert_account_info(
+        PROXY,
+        AccountInfo {
+            balances,
+            code_hash: proxy.hash_slow(),
+            code: Some(proxy),
+            ..Default::default()
+        },
+    );
+
+    // A contract that only stops.
+    let stop = Bytecode::new_raw(bytes!("00"));
+    db.insert_account_info(
+        CALLEE,
+        AccountInfo {
+            code_hash: stop.hash_slow(),
+            code: Some(stop),
+            ..Default::default()
+        },
+    );
+
+    (db, token_ids)
+}
+
+/// ABI-encodes the precompile input, with every argument taking a full word.
+fn encode(selector: u32, words: &[U256]) -> Bytes {
+    let mut input = selector.to_be_bytes().to_vec();
+    for word in words {
+        input.extend_from_slice(&word.to_be_bytes::<32>());
+    }
+    input.into()
+}
+
+fn address_word(address: Address) -> U256 {
+    U256::from_be_slice(address.as_slice())
+}
+
+fn tx_multi_token_transfer(c: &mut Criterion) {
+    let mut g = c.benchmark_group("tx_multi_token_transfer");
+    g.warm_up_time(Duration::from_secs(1));
+    for token_count in TOKEN_COUNTS {
+        let (db, token_ids) = db_with_tokens(token_count);
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TransactTo::Call(CALLEE);
+                tx.transferred_tokens = token_ids
+                    .iter()
+                    .map(|id| TokenTransfer {
+                        id: *id,
+                        amount: U256::from(10),
+                    })
+                    .collect();
+            })
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+        g.bench_with_input(
+            BenchmarkId::from_parameter(token_count),
+            &token_count,
+            |b, _| b.iter(|| evm.transact().unwrap()),
+        );
+    }
+    g.finish();
+}
+
+fn precompile(c: &mut Criterion) {
+    let sub_id = U256::from(1);
+    let minted_token_id = token_id_address(PROXY, sub_id);
+    let inputs = [
+        (
+            "transfer",
+            encode(
+                TRANSFER_SELECTOR,
+                &[address_word(RECIPIENT), U256::from(1), U256::from(10)],
+            ),
+        ),
+        (
+            "mint",
+            encode(
+                MINT_SELECTOR,
+                &[sub_id, address_word(PROXY), U256::from(10)],
+            ),
+        ),
+        (
+            "burn",
+            encode(
+                BURN_SELECTOR,
+                &[sub_id, address_word(PROXY), U256::from(10)],
+            ),
+        ),
+    ];
+
+    let mut g = c.benchmark_group("precompile");
+    g.warm_up_time(Duration::from_secs(1));
+    for (name, input) in inputs {
+        let (mut db, _) = db_with_tokens(1);
+        // Give the proxy a balance of the minted token, so that it can be burned.
+        db.token_ids.push(minted_token_id);
+        let mut proxy = db.accounts.get(&PROXY).unwrap().info.clone();
+        proxy.set_balance(minted_token_id, U256::from(u64::MAX));
+        db.insert_account_info(PROXY, proxy);
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TransactTo::Call(PROXY);
+                tx.data = input;
+            })
+            .build();
+        assert!(evm.transact().unwrap().result.is_success(), "{name}");
+        g.bench_function(name, |b| b.iter(|| evm.transact().unwrap()));
+    }
+    g.finish();
+}
+
+fn transfer_multiple_and_call(c: &mut Criterion) {
+    let mut g = c.benchmark_group("transfer_multiple_and_call");
+    g.warm_up_time(Duration::from_secs(1));
+    for token_count in TOKEN_COUNTS {
+        let (db, token_ids) = db_with_tokens(token_count);
+
+        // recipient, token ids offset, amounts offset, calldata offset
+        let len = U256::from(token_count);
+        let mut words = vec![
+            address_word(CALLEE),
+            U256::from(128),
+            U256::from(160 + 32 * token_count),
+            U256::from(192 + 64 * token_count),
+        ];
+        words.push(len);
+        words.extend(token_ids.iter().copied());
+        words.push(len);
+        words.extend(token_ids.iter().map(|_| U256::from(10)));
+        // the calldata is a single word holding a right-aligned function selector
+        words.push(U256::from(32));
+        words.push(U256::from(0x12345678));
+        let input = encode(TRANSFER_MULTIPLE_AND_CALL_SELECTOR, &words);
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TransactTo::Call(PROXY);
+                tx.data = input;
+            })
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+        g.bench_with_input(
+            BenchmarkId::from_parameter(token_count),
+            &token_count,
+            |b, _| b.iter(|| evm.transact().unwrap()),
+        );
+    }
+    g.finish();
+}
+
+fn journal_checkpoint_revert(c: &mut Criterion) {
+    let mut g = c.benchmark_group("journal_checkpoint_revert");
+    g.warm_up_time(Duration::from_secs(1));
+    for depth in CHECKPOINT_DEPTHS {
+        let (mut db, token_ids) = db_with_tokens(4);
+        let transfers: Vec<TokenTransfer> = core::iter::once(BASE_TOKEN_ID)
+            .chain(token_ids)
+            .map(|id| TokenTransfer {
+                id,
+                amount: U256::from(1),
+            })
+            .collect();
+        g.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, &depth| {
+            b.iter(|| {
+                let mut journal = JournaledState::new(SpecId::CANCUN, HashSet::new());
+                let mut checkpoints = Vec::with_capacity(depth);
+                for _ in 0..depth {
+                    checkpoints.push(journal.checkpoint());
+                    journal
+                        .transfer(&CALLER, &RECIPIENT, &transfers, &mut db)
+                        .unwrap();
+                }
+                while let Some(checkpoint) = checkpoints.pop() {
+                    journal.checkpoint_revert(checkpoint);
+                }
+                journal
+            })
+        });
+    }
+    g.finish();
+}
+
+criterion_group!(
+    benches,
+    tx_multi_token_transfer,
+    precompile,
+    transfer_multiple_and_call,
+    journal_checkpoint_revert,
+);
+criterion_main!(benches);
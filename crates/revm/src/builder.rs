@@ -1,6 +1,6 @@
 use crate::{
     db::{Database, DatabaseRef, EmptyDB, WrapDatabaseRef},
-    handler::register,
+    handler::{register, ChainHandler},
     primitives::{
         BlockEnv, CfgEnv, CfgEnvWithHandlerCfg, Env, EnvWithHandlerCfg, HandlerCfg, SpecId, TxEnv,
     },
@@ -9,6 +9,19 @@ use crate::{
 use core::marker::PhantomData;
 use std::boxed::Box;
 
+#[cfg(feature = "c-kzg")]
+use crate::primitives::EnvKzgSettings;
+#[cfg(feature = "std")]
+use crate::sablier::SablierPrecompileSet;
+
+#[cfg(feature = "journal-log")]
+use crate::JournalLog;
+#[cfg(feature = "journal-log")]
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
 /// Evm Builder allows building or modifying EVM.
 /// Note that some of the methods that changes underlying structures
 /// will reset the registered handler to default mainnet.
@@ -159,8 +172,13 @@ impl<'a, EXT, DB: Database> EvmBuilder<'a, SetGenericStage, EXT, DB> {
     ///
     /// If `optimism-default-handler` feature is enabled this is not needed.
     #[cfg(feature = "optimism")]
-    pub fn optimism(mut self) -> EvmBuilder<'a, HandlerStage, EXT, DB> {
-        self.handler = Handler::optimism_with_spec(self.handler.cfg.spec_id);
+    pub fn optimism(self) -> EvmBuilder<'a, HandlerStage, EXT, DB> {
+        self.with_chain_handler::<crate::optimism::OptimismHandler>()
+    }
+
+    /// Sets the handler of the chain with the current spec.
+    pub fn with_chain_handler<C: ChainHandler>(mut self) -> EvmBuilder<'a, HandlerStage, EXT, DB> {
+        self.handler = Handler::chain_with_spec::<C>(self.handler.cfg.spec_id);
         EvmBuilder {
             context: self.context,
             handler: self.handler,
@@ -341,6 +359,38 @@ impl<'a, BuilderStage, EXT, DB: Database> EvmBuilder<'a, BuilderStage, EXT, DB>
         }
     }
 
+    /// Installs the Sablier precompiles of the set in place of the mainnet ones.
+    ///
+    /// When called, EvmBuilder will transition from SetGenericStage to HandlerStage.
+    #[cfg(feature = "std")]
+    pub fn with_sablier_precompiles(
+        self,
+        precompile_set: SablierPrecompileSet,
+    ) -> EvmBuilder<'a, HandlerStage, EXT, DB> {
+        self.append_handler_register_box(Box::new(move |handler| precompile_set.register(handler)))
+    }
+
+    /// Appends the journal of every executed transaction to the log.
+    ///
+    /// When called, EvmBuilder will transition from SetGenericStage to HandlerStage.
+    #[cfg(feature = "journal-log")]
+    pub fn with_journal_log<W: Write + Send + 'static>(
+        self,
+        log: Arc<Mutex<JournalLog<W>>>,
+    ) -> EvmBuilder<'a, HandlerStage, EXT, DB> {
+        self.append_handler_register_box(Box::new(move |handler| {
+            JournalLog::register(log.clone(), handler)
+        }))
+    }
+
+    /// Emits the metrics of the executed transactions, see [crate::telemetry].
+    ///
+    /// When called, EvmBuilder will transition from SetGenericStage to HandlerStage.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(self) -> EvmBuilder<'a, HandlerStage, EXT, DB> {
+        self.append_handler_register(crate::register_metrics)
+    }
+
     /// Sets specification Id , that will mark the version of EVM.
     /// It represent the hard fork of ethereum.
     ///
@@ -412,6 +462,13 @@ impl<'a, BuilderStage, EXT, DB: Database> EvmBuilder<'a, BuilderStage, EXT, DB>
         self
     }
 
+    /// Sets the trusted setup the KZG point evaluation precompile verifies proofs with.
+    #[cfg(feature = "c-kzg")]
+    pub fn with_kzg_settings(mut self, kzg_settings: EnvKzgSettings) -> Self {
+        self.context.evm.env.cfg.kzg_settings = kzg_settings;
+        self
+    }
+
     /// Clears Environment of EVM.
     pub fn with_clear_env(mut self) -> Self {
         self.context.evm.env.clear();
@@ -648,4 +705,36 @@ mod test {
 
         evm.transact().unwrap();
     }
+
+    #[test]
+    fn build_with_sablier_precompiles() {
+        use crate::sablier::{
+            native_tokens::{self, NativeTokensSelectors},
+            u64_to_prefixed_address, SablierPrecompile, SablierPrecompileSet,
+        };
+
+        let v2_address = u64_to_prefixed_address(2);
+        let set = SablierPrecompileSet::mainnet()
+            .with_precompile(
+                SpecId::PRAGUE,
+                v2_address,
+                SablierPrecompile::NativeTokens(NativeTokensSelectors::V1),
+            )
+            .without_precompile(SpecId::PRAGUE, native_tokens::ADDRESS);
+
+        let evm = Evm::builder()
+            .with_empty_db()
+            .with_spec_id(SpecId::CANCUN)
+            .with_sablier_precompiles(set)
+            .build();
+        let precompiles = evm.handler.pre_execution().load_precompiles();
+        assert!(precompiles.contains_key(&native_tokens::ADDRESS));
+        assert!(!precompiles.contains_key(&v2_address));
+
+        // the set is kept when the hardfork changes
+        let evm = evm.modify().with_spec_id(SpecId::PRAGUE).build();
+        let precompiles = evm.handler.pre_execution().load_precompiles();
+        assert!(!precompiles.contains_key(&native_tokens::ADDRESS));
+        assert!(precompiles.contains_key(&v2_address));
+    }
 }
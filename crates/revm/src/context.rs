@@ -12,10 +12,13 @@ use revm_interpreter::as_usize_saturated;
 
 use crate::{
     db::{Database, EmptyDB},
-    interpreter::{Host, LoadAccountResult, SStoreResult, SelfDestructResult},
-    primitives::{Address, Bytecode, Env, HandlerCfg, Log, B256, BLOCK_HASH_HISTORY, U256},
+    interpreter::{Host, LoadAccountResult, SStoreResult, SelfDestructResult, TransferFailure},
+    primitives::{
+        Address, Bytecode, Env, HandlerCfg, Log, SpecId::PRAGUE, B256, BLOCKHASH_SERVE_WINDOW,
+        BLOCKHASH_STORAGE_ADDRESS, BLOCK_HASH_HISTORY, U256,
+    },
 };
-use std::boxed::Box;
+use std::{boxed::Box, vec::Vec};
 
 /// Main Context structure that contains both EvmContext and External context.
 pub struct Context<EXT, DB: Database> {
@@ -97,13 +100,15 @@ where
     }
 }
 
-impl<EXT, DB: Database> Host for Context<EXT, DB> {
+/// The inner context is a host too, so that code given only the inner context, e.g. the handlers
+/// of the Native Tokens Precompile, can operate on it through a `dyn Host`.
+impl<DB: Database> Host for InnerEvmContext<DB> {
     fn env(&self) -> &Env {
-        &self.evm.env
+        &self.env
     }
 
     fn env_mut(&mut self) -> &mut Env {
-        &mut self.evm.env
+        &mut self.env
     }
 
     fn block_hash(&mut self, number: U256) -> Option<B256> {
@@ -121,9 +126,21 @@ impl<EXT, DB: Database> Host for Context<EXT, DB> {
 
         if diff <= BLOCK_HASH_HISTORY {
             return self
-                .evm
                 .block_hash(number)
-                .map_err(|e| self.evm.error = Err(e))
+                .map_err(|e| self.error = Err(e))
+                .ok();
+        }
+
+        // EIP-2935: older hashes are served from the history storage contract, which is warm
+        // loaded in `load_accounts`.
+        if diff <= BLOCKHASH_SERVE_WINDOW && self.journaled_state.spec.is_enabled_in(PRAGUE) {
+            return self
+                .sload(
+                    BLOCKHASH_STORAGE_ADDRESS,
+                    number % U256::from(BLOCKHASH_SERVE_WINDOW),
+                )
+                .map(|(value, _)| B256::from(value.to_be_bytes()))
+                .map_err(|e| self.error = Err(e))
                 .ok();
         }
 
@@ -131,83 +148,77 @@ impl<EXT, DB: Database> Host for Context<EXT, DB> {
     }
 
     fn load_account(&mut self, address: Address) -> Option<LoadAccountResult> {
-        self.evm
-            .load_account_exist(address)
-            .map_err(|e| self.evm.error = Err(e))
+        self.load_account_exist(address)
+            .map_err(|e| self.error = Err(e))
             .ok()
     }
 
     fn code(&mut self, address: Address) -> Option<(Bytecode, bool)> {
-        self.evm
-            .code(address)
-            .map_err(|e| self.evm.error = Err(e))
-            .ok()
+        self.code(address).map_err(|e| self.error = Err(e)).ok()
     }
 
     fn code_hash(&mut self, address: Address) -> Option<(B256, bool)> {
-        self.evm
-            .code_hash(address)
-            .map_err(|e| self.evm.error = Err(e))
+        self.code_hash(address)
+            .map_err(|e| self.error = Err(e))
             .ok()
     }
 
     fn sload(&mut self, address: Address, index: U256) -> Option<(U256, bool)> {
-        self.evm
-            .sload(address, index)
-            .map_err(|e| self.evm.error = Err(e))
+        self.sload(address, index)
+            .map_err(|e| self.error = Err(e))
             .ok()
     }
 
     fn sstore(&mut self, address: Address, index: U256, value: U256) -> Option<SStoreResult> {
-        self.evm
-            .sstore(address, index, value)
-            .map_err(|e| self.evm.error = Err(e))
+        self.sstore(address, index, value)
+            .map_err(|e| self.error = Err(e))
             .ok()
     }
 
     fn tload(&mut self, address: Address, index: U256) -> U256 {
-        self.evm.tload(address, index)
+        self.tload(address, index)
     }
 
     fn tstore(&mut self, address: Address, index: U256, value: U256) {
-        self.evm.tstore(address, index, value)
+        self.tstore(address, index, value)
     }
 
     fn log(&mut self, log: Log) {
-        self.evm.journaled_state.log(log);
+        self.journaled_state.log(log);
     }
 
-    fn selfdestruct(&mut self, address: Address, target: Address) -> Option<SelfDestructResult> {
-        self.evm
-            .inner
-            .journaled_state
-            .selfdestruct(address, target, &mut self.evm.inner.db)
-            .map_err(|e| self.evm.error = Err(e))
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<Result<SelfDestructResult, TransferFailure>> {
+        self.selfdestruct(address, target)
+            .map_err(|e| self.error = Err(e))
             .ok()
     }
 
     fn balance(&mut self, token_id: U256, address: Address) -> Option<(U256, bool)> {
-        self.evm
-            .balance(token_id, address)
-            .map_err(|e| self.evm.error = Err(e))
+        self.balance(token_id, address)
+            .map_err(|e| self.error = Err(e))
             .ok()
     }
 
     fn base_balance(&mut self, address: Address) -> Option<(U256, bool)> {
-        self.evm
-            .base_balance(address)
-            .map_err(|e| self.evm.error = Err(e))
+        self.base_balance(address)
+            .map_err(|e| self.error = Err(e))
+            .ok()
+    }
+
+    fn registered_token_ids(&mut self) -> Option<Vec<U256>> {
+        self.registered_token_ids()
+            .map(<[U256]>::to_vec)
+            .map_err(|e| self.error = Err(e))
             .ok()
     }
 
     fn burn(&mut self, burner: Address, sub_id: U256, token_holder: Address, amount: U256) -> bool {
-        self.evm.inner.journaled_state.burn(
-            burner,
-            sub_id,
-            token_holder,
-            amount,
-            &mut self.evm.inner.db,
-        )
+        self.journaled_state
+            .burn(burner, sub_id, token_holder, amount, &mut self.db)
     }
     fn mint(&mut self, minter: Address, recipient: Address, sub_id: U256, amount: U256) -> bool {
         // TODO: also return the generated Token Id from this function?
@@ -216,12 +227,81 @@ impl<EXT, DB: Database> Host for Context<EXT, DB> {
             return false;
         }
 
-        self.evm.inner.journaled_state.mint(
-            minter,
-            recipient,
-            sub_id,
-            amount,
-            &mut self.evm.inner.db,
-        )
+        self.journaled_state
+            .mint(minter, recipient, sub_id, amount, &mut self.db)
+    }
+}
+
+impl<EXT, DB: Database> Host for Context<EXT, DB> {
+    fn env(&self) -> &Env {
+        Host::env(&self.evm.inner)
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        self.evm.inner.env_mut()
+    }
+
+    fn load_account(&mut self, address: Address) -> Option<LoadAccountResult> {
+        Host::load_account(&mut self.evm.inner, address)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Option<B256> {
+        Host::block_hash(&mut self.evm.inner, number)
+    }
+
+    fn code(&mut self, address: Address) -> Option<(Bytecode, bool)> {
+        Host::code(&mut self.evm.inner, address)
+    }
+
+    fn code_hash(&mut self, address: Address) -> Option<(B256, bool)> {
+        Host::code_hash(&mut self.evm.inner, address)
+    }
+
+    fn sload(&mut self, address: Address, index: U256) -> Option<(U256, bool)> {
+        Host::sload(&mut self.evm.inner, address, index)
+    }
+
+    fn sstore(&mut self, address: Address, index: U256, value: U256) -> Option<SStoreResult> {
+        Host::sstore(&mut self.evm.inner, address, index, value)
+    }
+
+    fn tload(&mut self, address: Address, index: U256) -> U256 {
+        Host::tload(&mut self.evm.inner, address, index)
+    }
+
+    fn tstore(&mut self, address: Address, index: U256, value: U256) {
+        Host::tstore(&mut self.evm.inner, address, index, value)
+    }
+
+    fn log(&mut self, log: Log) {
+        self.evm.inner.log(log)
+    }
+
+    fn selfdestruct(
+        &mut self,
+        address: Address,
+        target: Address,
+    ) -> Option<Result<SelfDestructResult, TransferFailure>> {
+        Host::selfdestruct(&mut self.evm.inner, address, target)
+    }
+
+    fn balance(&mut self, token_id: U256, address: Address) -> Option<(U256, bool)> {
+        Host::balance(&mut self.evm.inner, token_id, address)
+    }
+
+    fn base_balance(&mut self, address: Address) -> Option<(U256, bool)> {
+        Host::base_balance(&mut self.evm.inner, address)
+    }
+
+    fn registered_token_ids(&mut self) -> Option<Vec<U256>> {
+        Host::registered_token_ids(&mut self.evm.inner)
+    }
+
+    fn burn(&mut self, burner: Address, sub_id: U256, token_holder: Address, amount: U256) -> bool {
+        self.evm.inner.burn(burner, sub_id, token_holder, amount)
+    }
+
+    fn mint(&mut self, minter: Address, recipient: Address, sub_id: U256, amount: U256) -> bool {
+        self.evm.inner.mint(minter, recipient, sub_id, amount)
     }
 }
@@ -10,6 +10,9 @@ use std::{boxed::Box, sync::Arc};
 
 use super::InnerEvmContext;
 
+#[cfg(feature = "std")]
+use crate::sablier::native_tokens::NativeTokenCall;
+
 /// Precompile and its handlers.
 pub enum ContextPrecompile<DB: Database> {
     /// Ordinary precompiles
@@ -44,6 +47,22 @@ impl<DB: Database> ContextPrecompiles<DB> {
         self.inner.keys()
     }
 
+    /// Returns whether there is a precompile at the address.
+    #[inline]
+    pub fn contains(&self, address: &Address) -> bool {
+        self.inner.contains_key(address)
+    }
+
+    /// Returns whether the precompile at the address keeps the tokens transferred to it, see
+    /// [ContextStatefulPrecompileMut::accepts_token_transfers].
+    #[inline]
+    pub fn accepts_token_transfers(&self, address: &Address) -> bool {
+        matches!(
+            self.inner.get(address),
+            Some(ContextPrecompile::ContextStatefulMut(p)) if p.accepts_token_transfers()
+        )
+    }
+
     /// Extends the precompiles with the given precompiles.
     ///
     /// Other precompiles with overwrite existing precompiles.
@@ -75,6 +94,19 @@ impl<DB: Database> ContextPrecompiles<DB> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<DB: Database> ContextPrecompiles<DB> {
+    /// Returns the native token operation of the call if it is made to a precompile that
+    /// operates on native tokens.
+    #[inline]
+    pub fn native_token_call(&self, inputs: &CallInputs) -> Option<NativeTokenCall> {
+        match self.inner.get(&inputs.bytecode_address)? {
+            ContextPrecompile::ContextStatefulMut(p) => p.native_token_call(inputs),
+            _ => None,
+        }
+    }
+}
+
 impl<DB: Database> Default for ContextPrecompiles<DB> {
     fn default() -> Self {
         Self {
@@ -117,6 +149,23 @@ pub trait ContextStatefulPrecompileMut<DB: Database>: DynClone + Send + Sync {
         gas_price: u64,
         evmctx: &mut InnerEvmContext<DB>,
     ) -> PrecompileResult;
+
+    /// Returns whether the precompile keeps the tokens transferred to it, e.g. as the deposit of
+    /// a stream, so that transferring tokens to it isn't rejected.
+    ///
+    /// Returns `false` by default, for precompiles whose balances would be stranded.
+    fn accepts_token_transfers(&self) -> bool {
+        false
+    }
+
+    /// Decodes the call as a native token operation, so that inspectors can show it.
+    ///
+    /// Returns `None` by default, for precompiles that don't operate on native tokens.
+    #[cfg(feature = "std")]
+    fn native_token_call(&self, inputs: &CallInputs) -> Option<NativeTokenCall> {
+        let _ = inputs;
+        None
+    }
 }
 
 dyn_clone::clone_trait_object!(<DB> ContextStatefulPrecompileMut<DB>);
@@ -2,14 +2,16 @@ use super::inner_evm_context::InnerEvmContext;
 use crate::{
     db::Database,
     interpreter::{
+        gas,
         interpreter::{CallInfo, ResultOrNewCall as InterpreterResultOrNewCallInfo},
-        return_ok, CallInputs, CallValues, Contract, Gas, InstructionResult, Interpreter,
-        InterpreterResult,
+        return_ok, CallInputs, CallScheme, CallValues, Contract, Gas, InstructionResult,
+        Interpreter, InterpreterResult, SharedMemory, EMPTY_SHARED_MEMORY,
     },
     primitives::{
-        Bytes, EVMError, Env, HashSet, ResultOrNewCall as PrecompileResultOrNewCallInfo, U256,
+        nonzero_token_transfers, Address, Bytes, EVMError, Env, HashSet, InvalidTransaction,
+        ResultOrNewCall as PrecompileResultOrNewCallInfo, TokenTransfer, TransactTo,
     },
-    ContextPrecompiles, FrameOrResult, CALL_STACK_LIMIT,
+    sablier, ContextPrecompiles, FrameOrResult, FramePool, CALL_STACK_LIMIT,
 };
 
 use core::{
@@ -24,6 +26,12 @@ pub struct EvmContext<DB: Database> {
     pub inner: InnerEvmContext<DB>,
     /// Precompiles that are available for evm.
     pub precompiles: ContextPrecompiles<DB>,
+    /// Shared memory that is rented to the call loop and returned after each transaction,
+    /// so its allocation is reused across transactions.
+    pub shared_memory: SharedMemory,
+    /// Pool of call stack, token transfer and analysed bytecode allocations, reused across
+    /// transactions.
+    pub frame_pool: FramePool,
 }
 
 impl<DB: Database + Clone> Clone for EvmContext<DB>
@@ -34,6 +42,8 @@ where
         Self {
             inner: self.inner.clone(),
             precompiles: ContextPrecompiles::default(),
+            shared_memory: SharedMemory::new(),
+            frame_pool: FramePool::default(),
         }
     }
 }
@@ -71,6 +81,8 @@ impl<DB: Database> EvmContext<DB> {
         Self {
             inner: InnerEvmContext::new(db),
             precompiles: ContextPrecompiles::default(),
+            shared_memory: SharedMemory::new(),
+            frame_pool: FramePool::default(),
         }
     }
 
@@ -80,6 +92,8 @@ impl<DB: Database> EvmContext<DB> {
         Self {
             inner: InnerEvmContext::new_with_env(db, env),
             precompiles: ContextPrecompiles::default(),
+            shared_memory: SharedMemory::new(),
+            frame_pool: FramePool::default(),
         }
     }
 
@@ -91,9 +105,30 @@ impl<DB: Database> EvmContext<DB> {
         EvmContext {
             inner: self.inner.with_db(db),
             precompiles: ContextPrecompiles::default(),
+            shared_memory: SharedMemory::new(),
+            frame_pool: FramePool::default(),
         }
     }
 
+    /// Takes the pooled shared memory, cleared and ready for a new transaction.
+    ///
+    /// The memory should be handed back with [`EvmContext::return_shared_memory`].
+    #[inline]
+    pub fn take_shared_memory(&mut self) -> SharedMemory {
+        let mut shared_memory = core::mem::replace(&mut self.shared_memory, EMPTY_SHARED_MEMORY);
+        shared_memory.clear();
+        #[cfg(feature = "memory_limit")]
+        shared_memory.set_memory_limit(self.inner.env.cfg.memory_limit);
+        shared_memory.set_memory_gas_quotient(self.inner.env.cfg.memory_gas_quotient);
+        shared_memory
+    }
+
+    /// Returns the shared memory to the pool so its allocation can be reused.
+    #[inline]
+    pub fn return_shared_memory(&mut self, shared_memory: SharedMemory) {
+        self.shared_memory = shared_memory;
+    }
+
     /// Sets precompiles
     #[inline]
     pub fn set_precompiles(&mut self, precompiles: ContextPrecompiles<DB>) {
@@ -103,6 +138,71 @@ impl<DB: Database> EvmContext<DB> {
         self.precompiles = precompiles;
     }
 
+    /// Returns whether transferring the values to the address is rejected, because the address
+    /// is a precompile whose balances would be stranded, i.e. one that doesn't
+    /// [accept token transfers](ContextPrecompiles::accepts_token_transfers), or a
+    /// [protected reserved address](Self::is_protected_reserved_address).
+    ///
+    /// See [CfgEnv::allow_token_transfers_to_precompiles](crate::primitives::CfgEnv::allow_token_transfers_to_precompiles).
+    #[inline]
+    pub fn rejects_token_transfer_to(&self, address: &Address, values: &[TokenTransfer]) -> bool {
+        if nonzero_token_transfers(values).next().is_none() {
+            return false;
+        }
+        if self.precompiles.contains_key(address) {
+            !self.env.cfg.allow_token_transfers_to_precompiles
+                && !self.precompiles.accepts_token_transfers(address)
+        } else {
+            self.is_protected_reserved_address(address)
+        }
+    }
+
+    /// Validates the destination of the transaction against the loaded precompiles, which can't
+    /// receive the tokens of the transaction, and the
+    /// [protected reserved addresses](Self::is_protected_reserved_address), which can't be called.
+    #[inline]
+    pub fn validate_tx_destination(&self) -> Result<(), InvalidTransaction> {
+        let tx = &self.env.tx;
+        if let TransactTo::Call(target) = tx.transact_to {
+            if self.is_protected_reserved_address(&target) {
+                return Err(InvalidTransaction::CallToReservedAddress);
+            }
+            if self.rejects_token_transfer_to(&target, &tx.transferred_tokens) {
+                return Err(InvalidTransaction::TokenTransferToPrecompile);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the address is [reserved](sablier::is_reserved_address) for the Sablier
+    /// precompiles while no precompile is registered there, and protected from being called by
+    /// transactions and from receiving tokens.
+    ///
+    /// See [CfgEnv::protect_reserved_addresses](crate::primitives::CfgEnv::protect_reserved_addresses).
+    #[inline]
+    pub fn is_protected_reserved_address(&self, address: &Address) -> bool {
+        self.env.cfg.protect_reserved_addresses
+            && sablier::is_reserved_address(address)
+            && !self.precompiles.contains_key(address)
+    }
+
+    /// Returns whether the call is rejected, because it is a `CALL` made by a contract with a
+    /// nonzero value to a contract that only accepts Native Token call values.
+    ///
+    /// The calls made by the Native Tokens precompile and the call of the transaction transfer
+    /// their tokens explicitly, and are not rejected.
+    ///
+    /// See [CfgEnv::reject_legacy_value_calls](crate::primitives::CfgEnv::reject_legacy_value_calls).
+    #[inline]
+    pub fn rejects_legacy_value_call(&self, inputs: &CallInputs) -> bool {
+        let cfg = &self.env.cfg;
+        cfg.reject_legacy_value_calls
+            && self.journaled_state.depth() > 0
+            && inputs.scheme == CallScheme::Call
+            && inputs.transfers_value()
+            && cfg.mnt_only_contracts.contains(&inputs.target_address)
+    }
+
     /// Call precompile contract
     #[inline]
     fn call_precompile(
@@ -126,7 +226,10 @@ impl<DB: Database> EvmContext<DB> {
                     return Some(InterpreterResultOrNewCallInfo::NewCall(CallInfo {
                         target_address: primitive_call_info.target_address,
                         input_data: primitive_call_info.input_data,
-                        call_values: CallValues::Transfer(primitive_call_info.token_transfers),
+                        call_values: CallValues::Transfer(
+                            primitive_call_info.token_transfers.into(),
+                        ),
+                        gas_used: primitive_call_info.gas_used,
                     }));
                 }
                 PrecompileResultOrNewCallInfo::Result(interpreter_result) => {
@@ -177,91 +280,201 @@ impl<DB: Database> EvmContext<DB> {
             return return_result(InstructionResult::CallTooDeep);
         }
 
-        let (account, _) = self
-            .inner
-            .journaled_state
-            .load_code(inputs.bytecode_address, &mut self.inner.db)?;
-        let code_hash = account.info.code_hash();
-        let bytecode = account.info.code.clone().unwrap_or_default();
+        if self.exceeds_journal_entries_limit() {
+            return return_result(InstructionResult::JournalLimitExceeded);
+        }
+
+        let (bytecode, code_hash, mut delegate_access_cost) =
+            self.inner.executable_code(inputs.bytecode_address)?;
+        let bytecode = self.frame_pool.analysed_code(code_hash, bytecode);
+        // Calls pay for the access of an EIP-7702 delegate like an account access, while
+        // transactions access the delegate of their target for free.
+        if self.journaled_state.depth() == 0 {
+            delegate_access_cost = 0;
+        }
+
+        if self.rejects_token_transfer_to(&inputs.target_address, inputs.values.transferred()) {
+            return return_result(InstructionResult::TokenTransferToPrecompile);
+        }
+
+        if self.rejects_legacy_value_call(inputs) {
+            return return_result(InstructionResult::LegacyValueCallToMntOnlyContract);
+        }
 
         // Create subroutine checkpoint
         let checkpoint = self.journaled_state.checkpoint();
 
-        // Touch address. For "EIP-158 State Clear", this will erase empty accounts.
-        match &inputs.values {
-            // if transfer value is zero, do the touch.
-            CallValues::Transfer(values)
-                if values.is_empty() || values.iter().all(|tt| tt.amount == U256::ZERO) =>
+        // Transfer value from caller to called account. The called account is touched even if no
+        // value is transferred, for "EIP-158 State Clear", which will erase empty accounts.
+        if let CallValues::Transfer(values) = &inputs.values {
+            if let Some(result) =
+                self.inner
+                    .transfer(&inputs.caller, &inputs.target_address, values)?
             {
-                self.load_account(inputs.target_address)?;
-                self.journaled_state.touch(&inputs.target_address);
-            }
-            CallValues::Transfer(values) => {
-                // Transfer value from caller to called account
-                if let Some(result) = self.inner.journaled_state.transfer(
-                    &inputs.caller,
-                    &inputs.target_address,
-                    values,
-                    &mut self.inner.db,
-                )? {
-                    self.journaled_state.checkpoint_revert(checkpoint);
-                    return return_result(result);
-                }
+                self.journaled_state.checkpoint_revert(checkpoint);
+                return return_result(result.into());
             }
-            _ => {}
-        };
+        }
 
         if let Some(result_or_call_info) = self.call_precompile(inputs, gas) {
             match result_or_call_info {
                 InterpreterResultOrNewCallInfo::NewCall(call_info) => {
-                    // Compose the new Call Frame to process
-                    let (account, _) = self
-                        .inner
-                        .journaled_state
-                        .load_code(call_info.target_address, &mut self.inner.db)?;
-                    let code_hash = account.info.code_hash();
-                    let bytecode = account.info.code.clone().unwrap_or_default();
+                    // The spawned call is checked and charged like a `CALL` made by the
+                    // precompile frame, which is one level deeper than the caller.
+                    if self.journaled_state.depth() > CALL_STACK_LIMIT {
+                        self.journaled_state.checkpoint_revert(checkpoint);
+                        return return_result(InstructionResult::CallTooDeep);
+                    }
 
+                    let transferred_tokens =
+                        nonzero_token_transfers(call_info.call_values.transferred()).count();
+                    let transfers_value = transferred_tokens > 0;
+                    if inputs.is_static && transfers_value {
+                        self.journaled_state.checkpoint_revert(checkpoint);
+                        return return_result(InstructionResult::CallNotAllowedInsideStatic);
+                    }
+
+                    if self.rejects_token_transfer_to(
+                        &call_info.target_address,
+                        call_info.call_values.transferred(),
+                    ) {
+                        self.journaled_state.checkpoint_revert(checkpoint);
+                        return return_result(InstructionResult::TokenTransferToPrecompile);
+                    }
+
+                    let mut gas = gas;
+                    if !gas.record_cost(call_info.gas_used) {
+                        self.journaled_state.checkpoint_revert(checkpoint);
+                        return Ok(FrameOrResult::new_call_result(
+                            InterpreterResult {
+                                result: InstructionResult::PrecompileOOG,
+                                gas: Gas::new_spent(inputs.gas_limit),
+                                output: Bytes::new(),
+                            },
+                            inputs.return_memory_offset.clone(),
+                        ));
+                    }
+                    if transfers_value && !gas.record_cost(gas::CALLVALUE) {
+                        self.journaled_state.checkpoint_revert(checkpoint);
+                        return Ok(FrameOrResult::new_call_result(
+                            InterpreterResult {
+                                result: InstructionResult::OutOfGas,
+                                gas: Gas::new_spent(inputs.gas_limit),
+                                output: Bytes::new(),
+                            },
+                            inputs.return_memory_offset.clone(),
+                        ));
+                    }
+
+                    // The access of an EIP-7702 delegate is charged like an account access.
+                    let (bytecode, code_hash, delegate_access_cost) =
+                        self.inner.executable_code(call_info.target_address)?;
+                    let bytecode = self.frame_pool.analysed_code(code_hash, bytecode);
+                    if !gas.record_cost(delegate_access_cost) {
+                        self.journaled_state.checkpoint_revert(checkpoint);
+                        return Ok(FrameOrResult::new_call_result(
+                            InterpreterResult {
+                                result: InstructionResult::OutOfGas,
+                                gas: Gas::new_spent(inputs.gas_limit),
+                                output: Bytes::new(),
+                            },
+                            inputs.return_memory_offset.clone(),
+                        ));
+                    }
+
+                    // A transfer of the base token alone gives the stipend of a `CALL`, while the
+                    // stipends of multi-token transfers follow the configured rule. The stipends
+                    // beyond the first one are paid by the precompile frame.
+                    let base_token_id = self.env.cfg.base_token_id;
+                    let stipend = if transferred_tokens == 1
+                        && nonzero_token_transfers(call_info.call_values.transferred())
+                            .all(|transfer| transfer.id == base_token_id)
+                    {
+                        gas::CALL_STIPEND
+                    } else {
+                        gas::call_stipend(self.env.cfg.call_stipend, transferred_tokens)
+                    };
+                    if !gas.record_cost(stipend.saturating_sub(gas::CALL_STIPEND)) {
+                        self.journaled_state.checkpoint_revert(checkpoint);
+                        return Ok(FrameOrResult::new_call_result(
+                            InterpreterResult {
+                                result: InstructionResult::OutOfGas,
+                                gas: Gas::new_spent(inputs.gas_limit),
+                                output: Bytes::new(),
+                            },
+                            inputs.return_memory_offset.clone(),
+                        ));
+                    }
+
+                    // EIP-150: forward all but one 64th of the remaining gas, plus the stipends
+                    // if tokens are transferred.
+                    let forwarded_gas = gas.remaining() - gas.remaining() / 64;
+                    let retained_gas = gas.remaining() - forwarded_gas;
+                    let child_gas_limit = forwarded_gas + stipend;
+
+                    // Compose the new Call Frame to process
                     let call_inputs = CallInputs {
                         input: call_info.input_data.clone(),
-                        gas_limit: gas.limit(),
+                        gas_limit: child_gas_limit,
                         bytecode_address: call_info.target_address,
                         target_address: call_info.target_address,
-                        caller: inputs.caller,
+                        // The precompile runs in the context of `inputs.target_address`, which
+                        // makes the call and pays for the transfer.
+                        caller: inputs.target_address,
+                        caller_bytecode_address: inputs.bytecode_address,
                         values: call_info.call_values,
                         scheme: revm_interpreter::CallScheme::Call,
                         is_eof: false,
                         is_static: inputs.is_static,
-                        return_memory_offset: 0..0,
+                        // The spawned call returns in place of the precompile, so its output
+                        // is written where the caller expects the precompile's output.
+                        return_memory_offset: inputs.return_memory_offset.clone(),
                     };
 
                     // Transfer value from caller to called account
-                    if let Some(result) = self.inner.journaled_state.transfer(
+                    if let Some(result) = self.inner.transfer(
                         &call_inputs.caller,
                         &call_inputs.target_address,
-                        &call_inputs.values.get(),
-                        &mut self.inner.db,
+                        call_inputs.values.transferred(),
                     )? {
                         self.journaled_state.checkpoint_revert(checkpoint);
-                        return return_result(result);
+                        return return_result(result.into());
                     }
 
-                    let contract = Contract::new_with_context(
+                    let contract = Contract::new(
                         call_info.input_data.clone(),
                         bytecode,
                         Some(code_hash),
-                        &call_inputs,
+                        call_inputs.target_address,
+                        call_inputs.caller,
+                        self.frame_pool
+                            .token_transfers_from(call_inputs.values.frame_values()),
                     );
 
+                    // The interpreter shares the gas limit of the precompile frame, so that the
+                    // retained gas can be given back without exceeding it.
+                    let mut interpreter =
+                        Interpreter::new(contract, inputs.gas_limit, call_inputs.is_static);
+                    let charged = interpreter
+                        .gas
+                        .record_cost(inputs.gas_limit - call_inputs.gas_limit);
+                    debug_assert!(charged, "spawned call gas exceeds the precompile gas");
+
                     // Create interpreter, execute the call and push new CallStackFrame.
-                    Ok(FrameOrResult::new_call_frame(
-                        call_inputs.return_memory_offset.clone(),
+                    Ok(FrameOrResult::new_spawned_call_frame(
+                        Box::new(call_inputs),
+                        retained_gas,
                         checkpoint,
-                        Interpreter::new(contract, call_inputs.gas_limit, call_inputs.is_static),
+                        interpreter,
                     ))
                 }
 
-                InterpreterResultOrNewCallInfo::Result(result) => {
+                InterpreterResultOrNewCallInfo::Result(mut result) => {
+                    if matches!(result.result, return_ok!())
+                        && self.frame_exceeds_journal_limits(checkpoint)
+                    {
+                        result.result = InstructionResult::JournalLimitExceeded;
+                    }
                     if matches!(result.result, return_ok!()) {
                         self.journaled_state.checkpoint_commit();
                     } else {
@@ -273,18 +486,51 @@ impl<DB: Database> EvmContext<DB> {
                     ))
                 }
             }
-        } else if !bytecode.is_empty() {
-            let contract =
-                Contract::new_with_context(inputs.input.clone(), bytecode, Some(code_hash), inputs);
+        } else {
+            let mut gas = gas;
+            if !gas.record_cost(delegate_access_cost) {
+                self.journaled_state.checkpoint_revert(checkpoint);
+                return Ok(FrameOrResult::new_call_result(
+                    InterpreterResult {
+                        result: InstructionResult::OutOfGas,
+                        gas: Gas::new_spent(inputs.gas_limit),
+                        output: Bytes::new(),
+                    },
+                    inputs.return_memory_offset.clone(),
+                ));
+            }
+            if bytecode.is_empty() {
+                self.journaled_state.checkpoint_commit();
+                return Ok(FrameOrResult::new_call_result(
+                    InterpreterResult {
+                        result: InstructionResult::Stop,
+                        gas,
+                        output: Bytes::new(),
+                    },
+                    inputs.return_memory_offset.clone(),
+                ));
+            }
+
+            let contract = Contract {
+                bytecode_address: inputs.bytecode_address,
+                ..Contract::new(
+                    inputs.input.clone(),
+                    bytecode,
+                    Some(code_hash),
+                    inputs.target_address,
+                    inputs.caller,
+                    self.frame_pool
+                        .token_transfers_from(inputs.values.frame_values()),
+                )
+            };
             // Create interpreter and executes call and push new CallStackFrame.
+            let mut interpreter = Interpreter::new(contract, gas.limit(), inputs.is_static);
+            interpreter.gas = gas;
             Ok(FrameOrResult::new_call_frame(
                 inputs.return_memory_offset.clone(),
                 checkpoint,
-                Interpreter::new(contract, gas.limit(), inputs.is_static),
+                interpreter,
             ))
-        } else {
-            self.journaled_state.checkpoint_commit();
-            return_result(InstructionResult::Stop)
         }
     }
 }
@@ -296,7 +542,7 @@ pub(crate) mod test_utils {
     use crate::{
         db::{CacheDB, EmptyDB},
         journaled_state::JournaledState,
-        primitives::{address, Address, SpecId, TokenBalances, B256},
+        primitives::{address, Address, SpecId, TokenBalances, TokenTransfers, B256},
     };
 
     /// Mock caller address.
@@ -310,7 +556,8 @@ pub(crate) mod test_utils {
             bytecode_address: to,
             target_address: to,
             caller: MOCK_CALLER,
-            values: CallValues::Transfer(Vec::new()),
+            caller_bytecode_address: MOCK_CALLER,
+            values: CallValues::Transfer(TokenTransfers::new()),
             scheme: revm_interpreter::CallScheme::Call,
             is_eof: false,
             is_static: false,
@@ -349,10 +596,13 @@ pub(crate) mod test_utils {
                 journaled_state: JournaledState::new(SpecId::CANCUN, HashSet::new()),
                 db,
                 error: Ok(()),
+                intrinsic_gas: 0,
                 #[cfg(feature = "optimism")]
                 l1_block_info: None,
             },
             precompiles: ContextPrecompiles::default(),
+            shared_memory: SharedMemory::new(),
+            frame_pool: FramePool::default(),
         }
     }
 
@@ -364,10 +614,13 @@ pub(crate) mod test_utils {
                 journaled_state: JournaledState::new(SpecId::CANCUN, HashSet::new()),
                 db,
                 error: Ok(()),
+                intrinsic_gas: 0,
                 #[cfg(feature = "optimism")]
                 l1_block_info: None,
             },
             precompiles: ContextPrecompiles::default(),
+            shared_memory: SharedMemory::new(),
+            frame_pool: FramePool::default(),
         }
     }
 }
@@ -378,9 +631,11 @@ mod tests {
     use crate::{
         db::{CacheDB, EmptyDB},
         primitives::{
-            address, utilities::init_balances, AccountInfo, Bytecode, TokenTransfer, BASE_TOKEN_ID,
+            address, smallvec::smallvec, utilities::init_balances, AccountInfo, Address, Bytecode,
+            CallStipend, Precompile, PrecompileResult, PrimitiveCallInfo, TokenBalances,
+            TokenTransfer, TokenTransfers, BASE_TOKEN_ID, U256,
         },
-        Frame, JournalEntry,
+        ContextPrecompile, Frame, JournalEntry,
     };
     use std::boxed::Box;
     use test_utils::*;
@@ -415,12 +670,10 @@ mod tests {
         let mut evm_context = test_utils::create_empty_evm_context(Box::new(env), db);
         let contract = address!("dead10000000000000000000000000000001dead");
         let mut call_inputs = test_utils::create_mock_call_inputs(contract);
-        call_inputs.values = CallValues::Transfer(vec![
-            (TokenTransfer {
-                id: BASE_TOKEN_ID,
-                amount: U256::from(1),
-            }),
-        ]);
+        call_inputs.values = CallValues::Transfer(smallvec![TokenTransfer {
+            id: BASE_TOKEN_ID,
+            amount: U256::from(1),
+        }]);
         let res = evm_context.make_call_frame(&call_inputs);
         let Ok(FrameOrResult::Result(result)) = res else {
             panic!("Expected FrameOrResult::Result");
@@ -474,4 +727,275 @@ mod tests {
         };
         assert_eq!(call_frame.return_memory_range, 0..0,);
     }
+
+    const SPAWNING_PRECOMPILE: Address = address!("00000000000000000000000000000000000000ee");
+    const SPAWNED_CALLEE: Address = address!("dead10000000000000000000000000000001dead");
+
+    /// The gas used by the [spawning_precompile] before its call.
+    const SPAWNING_GAS_COST: u64 = 100;
+
+    /// Precompile that calls [SPAWNED_CALLEE], transferring one base token.
+    fn spawning_precompile(_input: &Bytes, _gas_limit: u64) -> PrecompileResult {
+        Ok(PrecompileResultOrNewCallInfo::Call(PrimitiveCallInfo {
+            target_address: SPAWNED_CALLEE,
+            token_transfers: vec![TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(1),
+            }],
+            input_data: Bytes::new(),
+            gas_used: SPAWNING_GAS_COST,
+        }))
+    }
+
+    /// Precompile that calls [SPAWNED_CALLEE], transferring three tokens.
+    fn multi_token_spawning_precompile(_input: &Bytes, _gas_limit: u64) -> PrecompileResult {
+        Ok(PrecompileResultOrNewCallInfo::Call(PrimitiveCallInfo {
+            target_address: SPAWNED_CALLEE,
+            token_transfers: (1..=3)
+                .map(|id| TokenTransfer {
+                    id: U256::from(id),
+                    amount: U256::from(1),
+                })
+                .collect(),
+            input_data: Bytes::new(),
+            gas_used: SPAWNING_GAS_COST,
+        }))
+    }
+
+    fn create_spawning_context() -> EvmContext<CacheDB<EmptyDB>> {
+        create_spawning_context_with_balances(init_balances(U256::from(3_000_000_000_u128)))
+    }
+
+    /// Creates a context where the mock caller holds the balances.
+    fn create_spawning_context_with_balances(
+        balances: TokenBalances,
+    ) -> EvmContext<CacheDB<EmptyDB>> {
+        let mut cdb = CacheDB::new(EmptyDB::default());
+        let by = Bytecode::new_raw(Bytes::from(vec![0x60, 0x00, 0x60, 0x00]));
+        cdb.insert_account_info(
+            SPAWNED_CALLEE,
+            AccountInfo {
+                code_hash: by.clone().hash_slow(),
+                code: Some(by),
+                ..Default::default()
+            },
+        );
+        let mut context = create_cache_db_evm_context_with_balances(Box::default(), cdb, balances);
+        context.precompiles.extend([(
+            SPAWNING_PRECOMPILE,
+            ContextPrecompile::Ordinary(Precompile::Standard(spawning_precompile)),
+        )]);
+        context
+    }
+
+    /// Creates `CallInputs` of the mock caller delegatecalling [SPAWNING_PRECOMPILE].
+    fn spawning_call_inputs() -> CallInputs {
+        CallInputs {
+            bytecode_address: SPAWNING_PRECOMPILE,
+            target_address: test_utils::MOCK_CALLER,
+            caller: address!("00000000000000000000000000000000000000aa"),
+            values: CallValues::Apparent(TokenTransfers::new()),
+            scheme: revm_interpreter::CallScheme::DelegateCall,
+            ..test_utils::create_mock_call_inputs(SPAWNING_PRECOMPILE)
+        }
+    }
+
+    fn spawned_call_result(
+        context: &mut EvmContext<CacheDB<EmptyDB>>,
+        inputs: &CallInputs,
+    ) -> InterpreterResult {
+        let Ok(FrameOrResult::Result(result)) = context.make_call_frame(inputs) else {
+            panic!("Expected FrameOrResult::Result");
+        };
+        assert_eq!(context.journaled_state.depth, 0);
+        result.into_interpreter_result()
+    }
+
+    #[test]
+    fn test_make_call_frame_spawned_call_gas() {
+        let mut context = create_spawning_context();
+        let mut call_inputs = spawning_call_inputs();
+        call_inputs.gas_limit = 100_000;
+        call_inputs.return_memory_offset = 32..64;
+        let res = context.make_call_frame(&call_inputs);
+        let Ok(FrameOrResult::Frame(Frame::Call(call_frame))) = res else {
+            panic!("Expected FrameOrResult::Frame(Frame::Call(..))");
+        };
+
+        // The gas of the precompile and 9000 for the transfer, then 63/64 of the rest plus the
+        // stipend go to the callee.
+        let remaining = 100_000 - SPAWNING_GAS_COST - gas::CALLVALUE;
+        assert_eq!(call_frame.retained_gas, remaining / 64);
+        let interpreter = &call_frame.frame_data.interpreter;
+        assert_eq!(
+            interpreter.gas.remaining(),
+            remaining - remaining / 64 + gas::CALL_STIPEND
+        );
+        assert_eq!(interpreter.contract.target_address, SPAWNED_CALLEE);
+        assert_eq!(interpreter.contract.caller, test_utils::MOCK_CALLER);
+        assert_eq!(call_frame.return_memory_range, 32..64);
+    }
+
+    #[test]
+    fn test_make_call_frame_spawned_call_stipends() {
+        let callee_gas = |call_stipend: CallStipend, gas_limit: u64| {
+            let balances = (1..=3).map(|id| (U256::from(id), U256::from(1))).collect();
+            let mut context = create_spawning_context_with_balances(balances);
+            context.env.cfg.call_stipend = call_stipend;
+            context.precompiles.extend([(
+                SPAWNING_PRECOMPILE,
+                ContextPrecompile::Ordinary(Precompile::Standard(multi_token_spawning_precompile)),
+            )]);
+            let mut call_inputs = spawning_call_inputs();
+            call_inputs.gas_limit = gas_limit;
+            match context.make_call_frame(&call_inputs).unwrap() {
+                FrameOrResult::Frame(Frame::Call(call_frame)) => {
+                    Ok(call_frame.frame_data.interpreter.gas.remaining())
+                }
+                FrameOrResult::Result(result) => Err(result.into_interpreter_result().result),
+                _ => panic!("Expected a call frame or a result"),
+            }
+        };
+        let remaining = 100_000 - SPAWNING_GAS_COST - gas::CALLVALUE;
+        let forwarded = |remaining: u64| remaining - remaining / 64;
+
+        // a single stipend for the three tokens
+        assert_eq!(
+            callee_gas(CallStipend::Once, 100_000),
+            Ok(forwarded(remaining) + gas::CALL_STIPEND)
+        );
+        // a stipend per token, two of which are paid by the caller
+        let paid = 2 * gas::CALL_STIPEND;
+        assert_eq!(
+            callee_gas(CallStipend::PerToken, 100_000),
+            Ok(forwarded(remaining - paid) + 3 * gas::CALL_STIPEND)
+        );
+        assert_eq!(
+            callee_gas(
+                CallStipend::PerToken,
+                SPAWNING_GAS_COST + gas::CALLVALUE + paid - 1
+            ),
+            Err(InstructionResult::OutOfGas)
+        );
+        assert_eq!(
+            callee_gas(CallStipend::Disabled, 100_000),
+            Ok(forwarded(remaining))
+        );
+
+        // while a transfer of the base token alone always gives the stipend of a `CALL`
+        let mut context = create_spawning_context();
+        context.env.cfg.call_stipend = CallStipend::Disabled;
+        let mut call_inputs = spawning_call_inputs();
+        call_inputs.gas_limit = 100_000;
+        let Ok(FrameOrResult::Frame(Frame::Call(call_frame))) =
+            context.make_call_frame(&call_inputs)
+        else {
+            panic!("Expected FrameOrResult::Frame(Frame::Call(..))");
+        };
+        assert_eq!(
+            call_frame.frame_data.interpreter.gas.remaining(),
+            forwarded(remaining) + gas::CALL_STIPEND
+        );
+    }
+
+    #[test]
+    fn test_make_call_frame_spawned_call_out_of_gas() {
+        let mut context = create_spawning_context();
+        let mut call_inputs = spawning_call_inputs();
+        call_inputs.gas_limit = SPAWNING_GAS_COST + gas::CALLVALUE - 1;
+        let result = spawned_call_result(&mut context, &call_inputs);
+        assert_eq!(result.result, InstructionResult::OutOfGas);
+        assert_eq!(result.gas.remaining(), 0);
+    }
+
+    #[test]
+    fn test_make_call_frame_spawned_call_precompile_out_of_gas() {
+        let mut context = create_spawning_context();
+        let mut call_inputs = spawning_call_inputs();
+        call_inputs.gas_limit = SPAWNING_GAS_COST - 1;
+        let result = spawned_call_result(&mut context, &call_inputs);
+        assert_eq!(result.result, InstructionResult::PrecompileOOG);
+        assert_eq!(result.gas.remaining(), 0);
+    }
+
+    #[test]
+    fn test_make_call_frame_spawned_call_static() {
+        let mut context = create_spawning_context();
+        let mut call_inputs = spawning_call_inputs();
+        call_inputs.gas_limit = 100_000;
+        call_inputs.is_static = true;
+        let result = spawned_call_result(&mut context, &call_inputs);
+        assert_eq!(result.result, InstructionResult::CallNotAllowedInsideStatic);
+    }
+
+    #[test]
+    fn test_make_call_frame_spawned_call_too_deep() {
+        let mut context = create_spawning_context();
+        context.journaled_state.depth = CALL_STACK_LIMIT as usize;
+        let mut call_inputs = spawning_call_inputs();
+        call_inputs.gas_limit = 100_000;
+        let Ok(FrameOrResult::Result(result)) = context.make_call_frame(&call_inputs) else {
+            panic!("Expected FrameOrResult::Result");
+        };
+        assert_eq!(
+            result.interpreter_result().result,
+            InstructionResult::CallTooDeep
+        );
+        assert_eq!(context.journaled_state.depth, CALL_STACK_LIMIT as usize);
+    }
+
+    #[test]
+    fn test_make_call_frame_charges_delegate_access() {
+        let code = Bytecode::new_raw(Bytes::from(vec![0x60, 0x00, 0x60, 0x00]));
+        let delegate = address!("dead10000000000000000000000000000001dead");
+        let authority = address!("dead20000000000000000000000000000002dead");
+        let mut cdb = CacheDB::new(EmptyDB::default());
+        cdb.insert_account_info(
+            delegate,
+            AccountInfo {
+                code_hash: code.hash_slow(),
+                code: Some(code),
+                ..Default::default()
+            },
+        );
+        let delegation = Bytecode::new_eip7702(delegate);
+        cdb.insert_account_info(
+            authority,
+            AccountInfo {
+                code_hash: delegation.hash_slow(),
+                code: Some(delegation),
+                ..Default::default()
+            },
+        );
+        let mut context = create_cache_db_evm_context(Box::default(), cdb);
+        let spent_gas = |context: &mut EvmContext<_>, depth, gas_limit| {
+            context.journaled_state.depth = depth;
+            let call_inputs = CallInputs {
+                gas_limit,
+                ..test_utils::create_mock_call_inputs(authority)
+            };
+            match context.make_call_frame(&call_inputs).unwrap() {
+                FrameOrResult::Frame(Frame::Call(frame)) => {
+                    frame.frame_data.interpreter.gas.spent()
+                }
+                FrameOrResult::Result(result) => {
+                    assert_eq!(
+                        result.interpreter_result().result,
+                        InstructionResult::OutOfGas
+                    );
+                    result.gas().spent()
+                }
+                _ => panic!("Expected a call frame or result"),
+            }
+        };
+
+        // the transaction accesses the delegate of its target for free
+        assert_eq!(spent_gas(&mut context, 0, 3_000), 0);
+        // while calls pay for a cold then a warm access
+        context.journaled_state.state.accounts.remove(&delegate);
+        assert_eq!(spent_gas(&mut context, 1, 2_000), 2_000);
+        context.journaled_state.state.accounts.remove(&delegate);
+        assert_eq!(spent_gas(&mut context, 1, 3_000), 2_600);
+        assert_eq!(spent_gas(&mut context, 1, 3_000), 100);
+    }
 }
@@ -3,18 +3,20 @@ use crate::{
     interpreter::{
         analysis::to_analysed, gas, return_ok, Contract, CreateInputs, EOFCreateInput, Gas,
         InstructionResult, Interpreter, InterpreterResult, LoadAccountResult, SStoreResult,
-        SelfDestructResult, MAX_CODE_SIZE,
+        SelfDestructResult, TransferFailure, MAX_CODE_SIZE,
     },
     journaled_state::JournaledState,
     primitives::{
-        keccak256, Account, Address, AnalysisKind, Bytecode, Bytes, CreateScheme, EVMError, Env,
-        Eof, HashSet, Spec,
+        keccak256,
+        smallvec::smallvec,
+        Account, Address, AnalysisKind, Bytecode, Bytes, CreateScheme, EVMError, Env, Eof, HashSet,
+        Spec,
         SpecId::{self, *},
-        TokenTransfer, B256, BASE_TOKEN_ID, U256,
+        TokenTransfer, B256, U256,
     },
-    FrameOrResult, JournalCheckpoint, CALL_STACK_LIMIT,
+    sablier, FrameOrResult, JournalCheckpoint, CALL_STACK_LIMIT,
 };
-use std::{boxed::Box, vec};
+use std::boxed::Box;
 
 /// EVM contexts contains data that EVM needs for execution.
 #[derive(Debug)]
@@ -28,6 +30,9 @@ pub struct InnerEvmContext<DB: Database> {
     pub db: DB,
     /// Error that happened during execution.
     pub error: Result<(), EVMError<DB::Error>>,
+    /// The intrinsic gas of the transaction being executed, as priced by the
+    /// [IntrinsicGasHandler](crate::handler::IntrinsicGasHandler).
+    pub intrinsic_gas: u64,
     /// Used as temporary value holder to store L1 block info.
     #[cfg(feature = "optimism")]
     pub l1_block_info: Option<crate::optimism::L1BlockInfo>,
@@ -43,6 +48,7 @@ where
             journaled_state: self.journaled_state.clone(),
             db: self.db.clone(),
             error: self.error.clone(),
+            intrinsic_gas: self.intrinsic_gas,
             #[cfg(feature = "optimism")]
             l1_block_info: self.l1_block_info.clone(),
         }
@@ -56,6 +62,7 @@ impl<DB: Database> InnerEvmContext<DB> {
             journaled_state: JournaledState::new(SpecId::LATEST, HashSet::new()),
             db,
             error: Ok(()),
+            intrinsic_gas: 0,
             #[cfg(feature = "optimism")]
             l1_block_info: None,
         }
@@ -69,6 +76,7 @@ impl<DB: Database> InnerEvmContext<DB> {
             journaled_state: JournaledState::new(SpecId::LATEST, HashSet::new()),
             db,
             error: Ok(()),
+            intrinsic_gas: 0,
             #[cfg(feature = "optimism")]
             l1_block_info: None,
         }
@@ -84,6 +92,7 @@ impl<DB: Database> InnerEvmContext<DB> {
             journaled_state: self.journaled_state,
             db,
             error: Ok(()),
+            intrinsic_gas: self.intrinsic_gas,
             #[cfg(feature = "optimism")]
             l1_block_info: self.l1_block_info,
         }
@@ -100,11 +109,14 @@ impl<DB: Database> InnerEvmContext<DB> {
     /// Loading of accounts/storages is needed to make them warm.
     #[inline]
     pub fn load_access_list(&mut self) -> Result<(), EVMError<DB::Error>> {
-        for (address, slots) in self.env.tx.access_list.iter() {
-            self.journaled_state
-                .initial_account_load(*address, slots, &mut self.db)?;
-        }
-        Ok(())
+        let access_list = self
+            .env
+            .tx
+            .access_list
+            .iter()
+            .map(|(address, slots)| (*address, slots.as_slice()));
+        self.journaled_state
+            .warm_accounts(access_list, &mut self.db)
     }
 
     /// Return environment.
@@ -152,15 +164,31 @@ impl<DB: Database> InnerEvmContext<DB> {
             .load_account_exist(address, &mut self.db)
     }
 
-    /// Return the base token balance and the is_cold flag of the account.
+    /// Return the token balance of the account and whether it was cold accessed, see
+    /// [JournaledState::token_balance].
     pub fn balance(
         &mut self,
         token_id: U256,
         address: Address,
     ) -> Result<(U256, bool), EVMError<DB::Error>> {
         self.journaled_state
-            .load_account(address, &mut self.db)
-            .map(|(acc, is_cold)| (acc.info.get_balance(token_id), is_cold))
+            .token_balance(address, token_id, &mut self.db)
+    }
+
+    /// Returns the IDs of the registered Native Tokens, loading them if they aren't loaded yet.
+    ///
+    /// The tokens minted by the transaction so far are included.
+    #[inline]
+    pub fn registered_token_ids(&mut self) -> Result<&[U256], EVMError<DB::Error>> {
+        self.journaled_state.load_native_token_ids(&mut self.db)?;
+        Ok(&self.journaled_state.state.token_ids)
+    }
+
+    /// Returns the IDs of the registered Native Tokens.
+    #[inline]
+    #[deprecated = "use `registered_token_ids` instead"]
+    pub fn token_ids(&mut self) -> Result<&[U256], EVMError<DB::Error>> {
+        self.registered_token_ids()
     }
 
     /// Return account code and if address is cold loaded.
@@ -171,6 +199,35 @@ impl<DB: Database> InnerEvmContext<DB> {
             .map(|(a, is_cold)| (a.info.code.clone().unwrap(), is_cold))
     }
 
+    /// Return the code that runs when the address is called, its hash and the gas cost of
+    /// accessing the delegate.
+    ///
+    /// The code of an EOA that delegates its code with [EIP-7702] is the code of the delegate,
+    /// whose access costs as much as the access of an account. The cost is zero when the code is
+    /// not delegated. Delegations are not followed any further.
+    ///
+    /// [EIP-7702]: https://eips.ethereum.org/EIPS/eip-7702
+    #[inline]
+    pub fn executable_code(
+        &mut self,
+        address: Address,
+    ) -> Result<(Bytecode, B256, u64), EVMError<DB::Error>> {
+        let (account, _) = self.journaled_state.load_code(address, &mut self.db)?;
+        let code = account.info.code.clone().unwrap_or_default();
+        let code_hash = account.info.code_hash();
+        match code.eip7702_address() {
+            Some(delegate) => {
+                let (account, is_cold) = self.journaled_state.load_code(delegate, &mut self.db)?;
+                Ok((
+                    account.info.code.clone().unwrap_or_default(),
+                    account.info.code_hash(),
+                    gas::warm_cold_cost(is_cold),
+                ))
+            }
+            None => Ok((code, code_hash, 0)),
+        }
+    }
+
     /// Get code hash of address.
     #[inline]
     pub fn code_hash(&mut self, address: Address) -> Result<(B256, bool), EVMError<DB::Error>> {
@@ -216,15 +273,48 @@ impl<DB: Database> InnerEvmContext<DB> {
         self.journaled_state.tstore(address, index, value)
     }
 
-    /// Selfdestructs the account.
+    /// Selfdestructs the account, returning why the balances couldn't be moved to the target,
+    /// if they couldn't.
+    ///
+    /// The balances sent to the [burn address](crate::primitives::CfgEnv::burn_address) are
+    /// burned.
     #[inline]
     pub fn selfdestruct(
         &mut self,
         address: Address,
         target: Address,
-    ) -> Result<SelfDestructResult, EVMError<DB::Error>> {
-        self.journaled_state
-            .selfdestruct(address, target, &mut self.db)
+    ) -> Result<Result<SelfDestructResult, TransferFailure>, EVMError<DB::Error>> {
+        let (result, transferred) =
+            match self
+                .journaled_state
+                .selfdestruct(address, target, &mut self.db)?
+            {
+                Ok(selfdestructed) => selfdestructed,
+                Err(failure) => return Ok(Err(failure)),
+            };
+        if self.env.cfg.burn_address == Some(target) {
+            self.journaled_state.burn_balances(target, &transferred);
+        }
+        Ok(Ok(result))
+    }
+
+    /// Transfers the tokens, returning why the transfer failed, if it did.
+    ///
+    /// The tokens sent to the [burn address](crate::primitives::CfgEnv::burn_address) are burned.
+    #[inline]
+    pub fn transfer(
+        &mut self,
+        from: &Address,
+        to: &Address,
+        transfers: &[TokenTransfer],
+    ) -> Result<Option<TransferFailure>, EVMError<DB::Error>> {
+        let result = self
+            .journaled_state
+            .transfer(from, to, transfers, &mut self.db)?;
+        if result.is_none() && self.env.cfg.burn_address == Some(*to) {
+            self.journaled_state.burn_balances(*to, transfers);
+        }
+        Ok(result)
     }
 
     /// Make create frame.
@@ -251,12 +341,16 @@ impl<DB: Database> InnerEvmContext<DB> {
             return return_error(InstructionResult::CallTooDeep);
         }
 
+        if self.exceeds_journal_entries_limit() {
+            return return_error(InstructionResult::JournalLimitExceeded);
+        }
+
         // Fetch balance of caller.
         let (caller_balance, _) = self.base_balance(inputs.caller)?;
 
         // Check if caller has enough balance to send to the created contract.
         if caller_balance < inputs.value {
-            return return_error(InstructionResult::OutOfFunds);
+            return return_error(TransferFailure::OutOfFunds.into());
         }
 
         // Increase nonce of caller and check if it overflows
@@ -289,12 +383,10 @@ impl<DB: Database> InnerEvmContext<DB> {
             None,
             inputs.created_address,
             inputs.caller,
-            vec![
-                (TokenTransfer {
-                    id: BASE_TOKEN_ID,
-                    amount: inputs.value,
-                }),
-            ],
+            smallvec![TokenTransfer {
+                id: self.env.cfg.base_token_id,
+                amount: inputs.value,
+            },],
         );
 
         let mut interpreter = Interpreter::new(contract, inputs.gas_limit, false);
@@ -323,6 +415,11 @@ impl<DB: Database> InnerEvmContext<DB> {
         // behaviour will be same as if it failed on return.
         //
         // Bytes of RETURN will drained in `insert_eofcreate_outcome`.
+        if interpreter_result.result == InstructionResult::ReturnContract
+            && self.frame_exceeds_journal_limits(journal_checkpoint)
+        {
+            interpreter_result.result = InstructionResult::JournalLimitExceeded;
+        }
         if interpreter_result.result != InstructionResult::ReturnContract {
             self.journaled_state.checkpoint_revert(journal_checkpoint);
             return;
@@ -366,12 +463,16 @@ impl<DB: Database> InnerEvmContext<DB> {
             return return_error(InstructionResult::CallTooDeep);
         }
 
+        if self.exceeds_journal_entries_limit() {
+            return return_error(InstructionResult::JournalLimitExceeded);
+        }
+
         // Fetch balance of caller.
         let (caller_balance, _) = self.base_balance(inputs.caller)?;
 
         // Check if caller has enough balance to send to the created contract.
         if caller_balance < inputs.value {
-            return return_error(InstructionResult::OutOfFunds);
+            return return_error(TransferFailure::OutOfFunds.into());
         }
 
         // Increase nonce of caller and check if it overflows
@@ -385,10 +486,10 @@ impl<DB: Database> InnerEvmContext<DB> {
         // Create address
         let mut init_code_hash = B256::ZERO;
         let created_address = match inputs.scheme {
-            CreateScheme::Create => inputs.caller.create(old_nonce),
+            CreateScheme::Create => sablier::create_address(inputs.caller, old_nonce),
             CreateScheme::Create2 { salt } => {
                 init_code_hash = keccak256(&inputs.init_code);
-                inputs.caller.create2(salt.to_be_bytes(), init_code_hash)
+                sablier::create2_address(inputs.caller, salt, init_code_hash)
             }
         };
 
@@ -418,12 +519,10 @@ impl<DB: Database> InnerEvmContext<DB> {
             Some(init_code_hash),
             created_address,
             inputs.caller,
-            vec![
-                (TokenTransfer {
-                    id: BASE_TOKEN_ID,
-                    amount: inputs.value,
-                }),
-            ],
+            smallvec![TokenTransfer {
+                id: self.env.cfg.base_token_id,
+                amount: inputs.value,
+            },],
         );
 
         Ok(FrameOrResult::new_create_frame(
@@ -433,13 +532,40 @@ impl<DB: Database> InnerEvmContext<DB> {
         ))
     }
 
+    /// Returns whether the journal holds more entries than the transaction is allowed.
+    ///
+    /// See [CfgEnv::journal_entries_limit](crate::primitives::CfgEnv::journal_entries_limit).
+    #[inline]
+    pub fn exceeds_journal_entries_limit(&self) -> bool {
+        self.env
+            .cfg
+            .journal_entries_limit
+            .is_some_and(|limit| self.journaled_state.entries() > limit)
+    }
+
+    /// Returns whether the journal exceeds its limits with the entries added by the frame of the
+    /// checkpoint, which then fails with [InstructionResult::JournalLimitExceeded].
+    #[inline]
+    pub fn frame_exceeds_journal_limits(&self, journal_checkpoint: JournalCheckpoint) -> bool {
+        self.env
+            .cfg
+            .frame_journal_entries_limit
+            .is_some_and(|limit| self.journaled_state.entries_since(journal_checkpoint) > limit)
+            || self.exceeds_journal_entries_limit()
+    }
+
     /// Handles call return.
     #[inline]
     pub fn call_return(
         &mut self,
-        interpreter_result: &InterpreterResult,
+        interpreter_result: &mut InterpreterResult,
         journal_checkpoint: JournalCheckpoint,
     ) {
+        if matches!(interpreter_result.result, return_ok!())
+            && self.frame_exceeds_journal_limits(journal_checkpoint)
+        {
+            interpreter_result.result = InstructionResult::JournalLimitExceeded;
+        }
         // revert changes or not.
         if matches!(interpreter_result.result, return_ok!()) {
             self.journaled_state.checkpoint_commit();
@@ -456,6 +582,11 @@ impl<DB: Database> InnerEvmContext<DB> {
         address: Address,
         journal_checkpoint: JournalCheckpoint,
     ) {
+        if matches!(interpreter_result.result, return_ok!())
+            && self.frame_exceeds_journal_limits(journal_checkpoint)
+        {
+            interpreter_result.result = InstructionResult::JournalLimitExceeded;
+        }
         // if return is not ok revert and return.
         if !matches!(interpreter_result.result, return_ok!()) {
             self.journaled_state.checkpoint_revert(journal_checkpoint);
@@ -519,11 +650,12 @@ impl<DB: Database> InnerEvmContext<DB> {
         interpreter_result.result = InstructionResult::Return;
     }
 
-    /// Return account balance and is_cold flag.
+    /// Return the balance of the base token of the chain and is_cold flag.
     #[inline]
     pub fn base_balance(&mut self, address: Address) -> Result<(U256, bool), EVMError<DB::Error>> {
+        let base_token_id = self.env.cfg.base_token_id;
         self.journaled_state
             .load_account(address, &mut self.db)
-            .map(|(acc, is_cold)| (acc.info.get_base_balance(), is_cold))
+            .map(|(acc, is_cold)| (acc.info.get_balance(base_token_id), is_cold))
     }
 }
@@ -2,21 +2,45 @@
 
 //#[cfg(feature = "alloydb")]
 //pub mod alloydb;
+#[cfg(feature = "state-commitment")]
+pub mod commitment;
 pub mod emptydb;
 #[cfg(feature = "ethersdb")]
 pub mod ethersdb;
+pub mod genesis;
 pub mod in_memory_db;
+pub mod overlay;
+#[cfg(feature = "std")]
+pub mod prefetch;
+pub mod recording;
+#[cfg(feature = "slot-balances")]
+pub mod slot_balances;
+pub mod snapshot;
 pub mod states;
+#[cfg(feature = "std")]
+pub mod sync_cache_db;
 
 pub use crate::primitives::db::*;
 //#[cfg(feature = "alloydb")]
 //pub use alloydb::AlloyDB;
+#[cfg(feature = "state-commitment")]
+pub use commitment::{state_root, BalanceProof, ProofError, EMPTY_ROOT_HASH};
 pub use emptydb::{EmptyDB, EmptyDBTyped};
 #[cfg(feature = "ethersdb")]
 pub use ethersdb::EthersDB;
+pub use genesis::{BaseToken, Genesis, GenesisAccount, GenesisBuilder, GenesisError};
 pub use in_memory_db::*;
+pub use overlay::OverlayDB;
+#[cfg(feature = "std")]
+pub use prefetch::PrefetchDB;
+pub use recording::{DbRead, RecordingDB, ReplayDB, ReplayError};
+#[cfg(feature = "slot-balances")]
+pub use slot_balances::{SlotBalancesDB, BALANCES_ADDRESS};
+pub use snapshot::{SnapshotError, StateSnapshot};
 pub use states::{
     AccountRevert, AccountStatus, BundleAccount, BundleState, CacheState, DBBox,
     OriginalValuesKnown, PlainAccount, RevertToSlot, State, StateBuilder, StateDBBox,
     StorageWithOriginalValues, TransitionAccount, TransitionState,
 };
+#[cfg(feature = "std")]
+pub use sync_cache_db::SyncCacheDB;
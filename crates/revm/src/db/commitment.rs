@@ -0,0 +1,602 @@
+//! Reference implementation of the SabVM state root.
+//!
+//! The state is committed to with Merkle Patricia Tries, as in Ethereum, except that the balance
+//! of an account is replaced by the root of the trie of its Native Token balances:
+//!
+//! ```text
+//! state trie:   keccak256(address)  => rlp([nonce, tokens_root, storage_root, code_hash])
+//! tokens trie:  keccak256(token_id) => rlp(balance)
+//! storage trie: keccak256(slot)     => rlp(value)
+//! ```
+//!
+//! Token IDs and storage slots are hashed as 32-byte big-endian words. Zero balances and storage
+//! values are left out, so an account without balances has the [EMPTY_ROOT_HASH] as tokens root.
+//!
+//! A [BalanceProof] proves the balance of a token held by an account to anyone who knows the state
+//! root, e.g. a bridge proving the balances of the pre-state of an execution to another chain.
+use super::{PlainAccount, StateSnapshot};
+use crate::primitives::{b256, keccak256, AccountInfo, Address, Bytes, HashMap, B256, U256};
+use alloy_rlp::{length_of_length, BufMut, Decodable, Encodable, Header};
+use core::fmt;
+use std::vec::Vec;
+
+/// The root of an empty trie, `keccak256(rlp(""))`.
+pub const EMPTY_ROOT_HASH: B256 =
+    b256!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+
+/// Returns the state root of the accounts.
+pub fn state_root<'a>(accounts: impl IntoIterator<Item = (&'a Address, &'a PlainAccount)>) -> B256 {
+    secure_trie_root(accounts.into_iter().map(|(address, account)| {
+        (
+            keccak256(address),
+            account_leaf(&account.info, storage_root(&account.storage)),
+        )
+    }))
+}
+
+/// Returns the root of the trie of the Native Token balances.
+pub fn tokens_root(balances: &HashMap<U256, U256>) -> B256 {
+    word_trie_root(balances)
+}
+
+/// Returns the root of the storage trie.
+pub fn storage_root(storage: &HashMap<U256, U256>) -> B256 {
+    word_trie_root(storage)
+}
+
+/// Returns the value of the account in the state trie,
+/// `rlp([nonce, tokens_root, storage_root, code_hash])`.
+pub fn account_leaf(info: &AccountInfo, storage_root: B256) -> Vec<u8> {
+    let tokens_root = tokens_root(&info.balances);
+    let payload_length = info.nonce.length()
+        + tokens_root.length()
+        + storage_root.length()
+        + info.code_hash.length();
+    let mut out = Vec::with_capacity(payload_length + length_of_length(payload_length));
+    Header {
+        list: true,
+        payload_length,
+    }
+    .encode(&mut out);
+    info.nonce.encode(&mut out);
+    tokens_root.encode(&mut out);
+    storage_root.encode(&mut out);
+    info.code_hash.encode(&mut out);
+    out
+}
+
+impl StateSnapshot {
+    /// Returns the [state root](state_root) of the snapshot.
+    pub fn state_root(&self) -> B256 {
+        state_root(&self.accounts)
+    }
+
+    /// Returns the proof of the balance of the token held by the account, against the
+    /// [state root](Self::state_root) of the snapshot.
+    ///
+    /// The balance is zero if the account doesn't exist or doesn't hold the token, which is
+    /// proven as well.
+    pub fn balance_proof(&self, address: Address, token_id: U256) -> BalanceProof {
+        let account_key = keccak256(address);
+        let account_proof = secure_trie_proof(
+            self.accounts.iter().map(|(address, account)| {
+                (
+                    keccak256(address),
+                    account_leaf(&account.info, storage_root(&account.storage)),
+                )
+            }),
+            account_key,
+        );
+        let (balance, balance_proof) = match self.accounts.get(&address) {
+            Some(account) => (
+                account.info.get_balance(token_id),
+                word_trie_proof(&account.info.balances, token_id),
+            ),
+            None => (U256::ZERO, Vec::new()),
+        };
+        BalanceProof {
+            address,
+            token_id,
+            balance,
+            account_proof,
+            balance_proof,
+        }
+    }
+}
+
+/// A Merkle proof of the balance of a token held by an account, see
+/// [StateSnapshot::balance_proof].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BalanceProof {
+    /// The account holding the balance.
+    pub address: Address,
+    /// The ID of the token.
+    pub token_id: U256,
+    /// The claimed balance.
+    pub balance: U256,
+    /// The nodes of the state trie on the path to the account, from the root. The nodes small
+    /// enough to be embedded in their parent are not repeated.
+    pub account_proof: Vec<Bytes>,
+    /// The nodes of the tokens trie of the account on the path to the balance, from the root.
+    /// It is empty if the account doesn't exist.
+    pub balance_proof: Vec<Bytes>,
+}
+
+/// Why a [BalanceProof] is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofError {
+    /// A node on the path is not in the proof.
+    MissingNode,
+    /// A node doesn't hash to the hash its parent references it by.
+    HashMismatch,
+    /// A node, or the account in the leaf of the state trie, is not validly encoded.
+    InvalidNode,
+    /// The proven balance is not the claimed one.
+    BalanceMismatch,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProofError {}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingNode => "a node of the proof is missing",
+            Self::HashMismatch => "a node of the proof doesn't match its hash",
+            Self::InvalidNode => "a node of the proof is invalid",
+            Self::BalanceMismatch => "the proven balance is not the claimed one",
+        })
+    }
+}
+
+impl BalanceProof {
+    /// Verifies that the account holds the claimed balance in the state with the given root.
+    pub fn verify(&self, state_root: B256) -> Result<(), ProofError> {
+        let leaf = verify_trie_proof(state_root, keccak256(self.address), &self.account_proof)?;
+        let balance = match leaf {
+            Some(leaf) => {
+                let tokens_root = decode_tokens_root(&leaf).ok_or(ProofError::InvalidNode)?;
+                let key = keccak256(self.token_id.to_be_bytes::<32>());
+                match verify_trie_proof(tokens_root, key, &self.balance_proof)? {
+                    Some(value) => {
+                        U256::decode(&mut value.as_slice()).map_err(|_| ProofError::InvalidNode)?
+                    }
+                    None => U256::ZERO,
+                }
+            }
+            None => U256::ZERO,
+        };
+        if balance == self.balance {
+            Ok(())
+        } else {
+            Err(ProofError::BalanceMismatch)
+        }
+    }
+}
+
+/// Returns the tokens root of the account in its leaf of the state trie.
+fn decode_tokens_root(mut leaf: &[u8]) -> Option<B256> {
+    let header = Header::decode(&mut leaf).ok()?;
+    if !header.list || header.payload_length != leaf.len() {
+        return None;
+    }
+    u64::decode(&mut leaf).ok()?;
+    B256::decode(&mut leaf).ok()
+}
+
+/// Returns the root of the trie of the non-zero words, keyed by the hash of their keys.
+fn word_trie_root(words: &HashMap<U256, U256>) -> B256 {
+    secure_trie_root(word_entries(words))
+}
+
+/// Returns the proof of the word at the key in the trie of the non-zero words.
+fn word_trie_proof(words: &HashMap<U256, U256>, key: U256) -> Vec<Bytes> {
+    secure_trie_proof(word_entries(words), keccak256(key.to_be_bytes::<32>()))
+}
+
+fn word_entries(words: &HashMap<U256, U256>) -> impl Iterator<Item = (B256, Vec<u8>)> + '_ {
+    words
+        .iter()
+        .filter(|(_, value)| **value != U256::ZERO)
+        .map(|(key, value)| (keccak256(key.to_be_bytes::<32>()), alloy_rlp::encode(value)))
+}
+
+/// Returns the nodes on the path to the key in the trie of the entries, from the root.
+fn secure_trie_proof(entries: impl Iterator<Item = (B256, Vec<u8>)>, key: B256) -> Vec<Bytes> {
+    let mut entries: Vec<_> = entries
+        .map(|(key, value)| (to_nibbles(key.as_slice()), value))
+        .collect();
+    let mut proof = Vec::new();
+    if !entries.is_empty() {
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        collect_proof(&entries, 0, &to_nibbles(key.as_slice()), &mut proof);
+    }
+    proof
+}
+
+/// Pushes the node holding the sorted entries, and its descendants on the path to the key, to
+/// the proof. Like in [encode_node], the keys share the nibbles before `depth`.
+fn collect_proof(entries: &[(Vec<u8>, Vec<u8>)], depth: usize, key: &[u8], proof: &mut Vec<Bytes>) {
+    let node = encode_node(entries, depth);
+    // nodes shorter than a hash are embedded in their parent, but the root is always hashed.
+    if depth == 0 || node.len() >= 32 {
+        proof.push(node.into());
+    }
+    if entries.len() == 1 {
+        return;
+    }
+
+    let (first, last) = (&entries[0].0, &entries[entries.len() - 1].0);
+    let shared = first[depth..]
+        .iter()
+        .zip(&last[depth..])
+        .take_while(|(a, b)| a == b)
+        .count();
+    if shared > 0 {
+        if key.get(depth..depth + shared) == Some(&first[depth..depth + shared]) {
+            collect_proof(entries, depth + shared, key, proof);
+        }
+        return;
+    }
+
+    let Some(nibble) = key.get(depth) else {
+        return;
+    };
+    let children: Vec<_> = entries
+        .iter()
+        .filter(|(entry_key, _)| entry_key.get(depth) == Some(nibble))
+        .cloned()
+        .collect();
+    if !children.is_empty() {
+        collect_proof(&children, depth + 1, key, proof);
+    }
+}
+
+/// Walks the proof from the root to the key, returning the value at the key, or `None` if the
+/// proof shows that the key is not in the trie.
+fn verify_trie_proof(
+    root: B256,
+    key: B256,
+    proof: &[Bytes],
+) -> Result<Option<Vec<u8>>, ProofError> {
+    if root == EMPTY_ROOT_HASH && proof.is_empty() {
+        return Ok(None);
+    }
+    let key = to_nibbles(key.as_slice());
+    let mut nodes = proof.iter();
+    let mut depth = 0;
+    // the reference to the next node: its hash, or the node itself if it is embedded.
+    let mut reference = NodeReference::Hash(root);
+    loop {
+        let node: &[u8] = match reference {
+            NodeReference::Hash(hash) => {
+                let node = nodes.next().ok_or(ProofError::MissingNode)?;
+                if keccak256(node) != hash {
+                    return Err(ProofError::HashMismatch);
+                }
+                node
+            }
+            NodeReference::Embedded(node) => node,
+        };
+        let items = decode_node(node).ok_or(ProofError::InvalidNode)?;
+        match items.as_slice() {
+            [path, value] => {
+                let (nibbles, leaf) = decode_hex_prefix(string_payload(path)?)?;
+                if !key[depth..].starts_with(&nibbles) {
+                    return Ok(None);
+                }
+                depth += nibbles.len();
+                if leaf {
+                    if depth != key.len() {
+                        return Ok(None);
+                    }
+                    return string_payload(value).map(|value| Some(value.to_vec()));
+                }
+                reference = NodeReference::decode(value)?.ok_or(ProofError::InvalidNode)?;
+            }
+            [children @ .., value] if children.len() == 16 => {
+                let Some(nibble) = key.get(depth) else {
+                    let value = string_payload(value)?;
+                    return Ok((!value.is_empty()).then(|| value.to_vec()));
+                };
+                match NodeReference::decode(children[*nibble as usize])? {
+                    Some(child) => reference = child,
+                    None => return Ok(None),
+                }
+                depth += 1;
+            }
+            _ => return Err(ProofError::InvalidNode),
+        }
+    }
+}
+
+/// The reference to a node from its parent, see [reference].
+enum NodeReference<'a> {
+    Hash(B256),
+    Embedded(&'a [u8]),
+}
+
+impl<'a> NodeReference<'a> {
+    /// Decodes the item of the parent node referencing a node, which is `None` if it is empty.
+    fn decode(item: &'a [u8]) -> Result<Option<Self>, ProofError> {
+        if is_list(item) {
+            return Ok(Some(Self::Embedded(item)));
+        }
+        match string_payload(item)? {
+            [] => Ok(None),
+            hash if hash.len() == 32 => Ok(Some(Self::Hash(B256::from_slice(hash)))),
+            _ => Err(ProofError::InvalidNode),
+        }
+    }
+}
+
+/// Returns the encoded items of the encoded node, which is a list.
+fn decode_node(mut node: &[u8]) -> Option<Vec<&[u8]>> {
+    let header = Header::decode(&mut node).ok()?;
+    if !header.list || header.payload_length != node.len() {
+        return None;
+    }
+    let mut items = Vec::with_capacity(17);
+    while !node.is_empty() {
+        let mut rest = node;
+        let header = Header::decode(&mut rest).ok()?;
+        let length = node.len() - rest.len() + header.payload_length;
+        if length > node.len() {
+            return None;
+        }
+        let (item, others) = node.split_at(length);
+        items.push(item);
+        node = others;
+    }
+    Some(items)
+}
+
+fn is_list(item: &[u8]) -> bool {
+    item.first()
+        .is_some_and(|byte| *byte >= alloy_rlp::EMPTY_LIST_CODE)
+}
+
+/// Returns the payload of the encoded string.
+fn string_payload(mut item: &[u8]) -> Result<&[u8], ProofError> {
+    let header = Header::decode(&mut item).map_err(|_| ProofError::InvalidNode)?;
+    if header.list || header.payload_length != item.len() {
+        return Err(ProofError::InvalidNode);
+    }
+    Ok(item)
+}
+
+/// Decodes the nibbles of a leaf or extension node, and whether it is a leaf, see [hex_prefix].
+fn decode_hex_prefix(path: &[u8]) -> Result<(Vec<u8>, bool), ProofError> {
+    let (flags, rest) = path.split_first().ok_or(ProofError::InvalidNode)?;
+    let leaf = match flags >> 4 {
+        0 | 1 => false,
+        2 | 3 => true,
+        _ => return Err(ProofError::InvalidNode),
+    };
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if flags & 0x10 != 0 {
+        nibbles.push(flags & 0x0f);
+    }
+    nibbles.extend(to_nibbles(rest));
+    Ok((nibbles, leaf))
+}
+
+fn secure_trie_root(entries: impl Iterator<Item = (B256, Vec<u8>)>) -> B256 {
+    trie_root(entries.map(|(key, value)| (key.to_vec(), value)))
+}
+
+/// Returns the root of the Merkle Patricia Trie of the entries.
+fn trie_root(entries: impl Iterator<Item = (Vec<u8>, Vec<u8>)>) -> B256 {
+    let mut entries: Vec<_> = entries
+        .map(|(key, value)| (to_nibbles(&key), value))
+        .collect();
+    if entries.is_empty() {
+        return EMPTY_ROOT_HASH;
+    }
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    keccak256(encode_node(&entries, 0))
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+/// Returns the encoding of the node holding the sorted entries, whose keys share the nibbles
+/// before `depth`.
+fn encode_node(entries: &[(Vec<u8>, Vec<u8>)], depth: usize) -> Vec<u8> {
+    if let [(key, value)] = entries {
+        return encode_list(&[
+            encode_bytes(&hex_prefix(&key[depth..], true)),
+            encode_bytes(value),
+        ]);
+    }
+
+    // the keys are sorted, so the prefix shared by all of them is the one of the first and last.
+    let (first, last) = (&entries[0].0, &entries[entries.len() - 1].0);
+    let shared = first[depth..]
+        .iter()
+        .zip(&last[depth..])
+        .take_while(|(a, b)| a == b)
+        .count();
+    if shared > 0 {
+        let child = encode_node(entries, depth + shared);
+        return encode_list(&[
+            encode_bytes(&hex_prefix(&first[depth..depth + shared], false)),
+            reference(child),
+        ]);
+    }
+
+    // a key ending at the branch is its value, and sorts first.
+    let (value, mut rest) = match entries.split_first() {
+        Some(((key, value), rest)) if key.len() == depth => (encode_bytes(value), rest),
+        _ => (encode_bytes(&[]), entries),
+    };
+    let mut items = Vec::with_capacity(17);
+    for nibble in 0..16 {
+        let count = rest
+            .iter()
+            .take_while(|(key, _)| key[depth] == nibble)
+            .count();
+        let (children, others) = rest.split_at(count);
+        items.push(if children.is_empty() {
+            encode_bytes(&[])
+        } else {
+            reference(encode_node(children, depth + 1))
+        });
+        rest = others;
+    }
+    items.push(value);
+    encode_list(&items)
+}
+
+/// Returns the reference to a node from its parent: the node itself if its encoding is shorter
+/// than a hash, its hash otherwise.
+fn reference(node: Vec<u8>) -> Vec<u8> {
+    if node.len() < 32 {
+        node
+    } else {
+        encode_bytes(keccak256(node).as_slice())
+    }
+}
+
+/// Returns the hex-prefix encoding of the nibbles of a leaf or extension node.
+fn hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 0x20 } else { 0 };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(flag | 0x10 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag);
+        nibbles
+    };
+    out.extend(rest.chunks(2).map(|pair| pair[0] << 4 | pair[1]));
+    out
+}
+
+fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    alloy_rlp::encode(bytes)
+}
+
+/// Returns the encoding of the list of the encoded items.
+fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload_length = items.iter().map(Vec::len).sum();
+    let mut out = Vec::with_capacity(payload_length + length_of_length(payload_length));
+    Header {
+        list: true,
+        payload_length,
+    }
+    .encode(&mut out);
+    for item in items {
+        out.put_slice(item);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{address, init_balances, BASE_TOKEN_ID};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn computes_the_root_of_a_trie() {
+        assert_eq!(trie_root(core::iter::empty()), EMPTY_ROOT_HASH);
+        // the example of the Ethereum wiki
+        let entries = [
+            ("doe", "reindeer"),
+            ("dog", "puppy"),
+            ("dogglesworth", "cat"),
+        ];
+        assert_eq!(
+            trie_root(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            ),
+            b256!("8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3")
+        );
+    }
+
+    #[test]
+    fn commits_to_token_balances() {
+        let holder = address!("00000000000000000000000000000000000000aa");
+        let account = |balances| PlainAccount {
+            info: AccountInfo {
+                balances,
+                ..Default::default()
+            },
+            storage: HashMap::new(),
+        };
+        let root = |account| state_root(&BTreeMap::from([(holder, account)]));
+
+        let mut balances = init_balances(U256::from(1_000));
+        let base_only = root(account(balances.clone()));
+        balances.insert(U256::from(1), U256::ZERO);
+        assert_eq!(root(account(balances.clone())), base_only);
+        balances.insert(U256::from(1), U256::from(7));
+        assert_ne!(root(account(balances)), base_only);
+
+        assert_eq!(state_root(&BTreeMap::new()), EMPTY_ROOT_HASH);
+        assert_eq!(tokens_root(&HashMap::new()), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn proves_token_balances() {
+        let mut snapshot = StateSnapshot::default();
+        for i in 1..=40u8 {
+            let mut balances = init_balances(U256::from(i));
+            for token_id in 1..u64::from(i % 5) {
+                balances.insert(U256::from(token_id), U256::from(i) * U256::from(token_id));
+            }
+            let info = AccountInfo {
+                balances,
+                nonce: u64::from(i),
+                ..Default::default()
+            };
+            snapshot.accounts.insert(
+                Address::with_last_byte(i),
+                PlainAccount {
+                    info,
+                    storage: HashMap::new(),
+                },
+            );
+        }
+        let root = snapshot.state_root();
+
+        for i in 1..=40u8 {
+            for token_id in 0..5u64 {
+                let proof =
+                    snapshot.balance_proof(Address::with_last_byte(i), U256::from(token_id));
+                assert_eq!(proof.verify(root), Ok(()), "{i} {token_id}");
+            }
+        }
+        let proof = snapshot.balance_proof(Address::with_last_byte(9), U256::from(3));
+        assert_eq!(proof.balance, U256::from(27));
+        // accounts without the token and missing accounts hold none
+        let proof = snapshot.balance_proof(Address::with_last_byte(10), U256::from(1));
+        assert_eq!((proof.balance, proof.verify(root)), (U256::ZERO, Ok(())));
+        let missing = snapshot.balance_proof(Address::with_last_byte(41), BASE_TOKEN_ID);
+        assert!(missing.balance_proof.is_empty());
+        assert_eq!(missing.verify(root), Ok(()));
+
+        let mut forged = snapshot.balance_proof(Address::with_last_byte(9), U256::from(3));
+        forged.balance += U256::from(1);
+        assert_eq!(forged.verify(root), Err(ProofError::BalanceMismatch));
+        let mut forged = missing;
+        forged.balance = U256::from(1);
+        assert_eq!(forged.verify(root), Err(ProofError::BalanceMismatch));
+        let mut forged = proof.clone();
+        forged.account_proof.pop();
+        assert_eq!(forged.verify(root), Err(ProofError::MissingNode));
+        let mut forged = proof.clone();
+        let mut node = forged.balance_proof[0].to_vec();
+        *node.last_mut().unwrap() ^= 1;
+        forged.balance_proof[0] = node.into();
+        assert_eq!(forged.verify(root), Err(ProofError::HashMismatch));
+        assert_eq!(proof.verify(EMPTY_ROOT_HASH), Err(ProofError::HashMismatch));
+    }
+}
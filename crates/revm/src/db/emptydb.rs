@@ -1,9 +1,9 @@
 use core::{convert::Infallible, fmt, marker::PhantomData};
 use revm_interpreter::primitives::{
-    db::{Database, DatabaseRef},
+    db::{Database, DatabaseEntries, DatabaseIter, DatabaseRef},
     keccak256, AccountInfo, Address, Bytecode, B256, U256,
 };
-use std::{string::ToString, vec::Vec};
+use std::{boxed::Box, string::ToString, vec::Vec};
 
 /// An empty database that always returns default values when queried.
 pub type EmptyDB = EmptyDBTyped<Infallible>;
@@ -126,6 +126,21 @@ impl<E> DatabaseRef for EmptyDBTyped<E> {
     }
 }
 
+impl<E> DatabaseIter for EmptyDBTyped<E> {
+    #[inline]
+    fn accounts_iter(&self) -> Result<DatabaseEntries<'_, (Address, AccountInfo)>, Self::Error> {
+        Ok(Box::new(core::iter::empty()))
+    }
+
+    #[inline]
+    fn storage_iter(
+        &self,
+        _address: Address,
+    ) -> Result<DatabaseEntries<'_, (U256, U256)>, Self::Error> {
+        Ok(Box::new(core::iter::empty()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,536 @@
+//! Declarative genesis state of a network, with its Native Token allocations.
+//!
+//! The JSON format is the `alloc` of a geth genesis file, extended with the base token of the
+//! network, the balances of the other Native Tokens and the token IDs supported at genesis:
+//!
+//! ```json
+//! {
+//!   "metadata": { "network": "devnet" },
+//!   "baseToken": { "id": "0x0", "name": "Ether", "symbol": "ETH", "decimals": 18 },
+//!   "tokenIds": ["0x1"],
+//!   "alloc": {
+//!     "0x00000000000000000000000000000000000000aa": {
+//!       "balance": "0x3e8",
+//!       "tokens": { "0x1": "0x64" }
+//!     }
+//!   }
+//! }
+//! ```
+use super::{DatabaseCommit, InMemoryDB};
+use crate::primitives::{
+    token_id_address, Account, AccountInfo, Address, Bytecode, Bytes, EvmState, EvmStorageSlot,
+    HashMap, BASE_TOKEN_ID, U256,
+};
+use core::fmt;
+use std::{collections::BTreeMap, string::String, vec, vec::Vec};
+
+/// The base token of a network, which pays for the gas and is the value of the calls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BaseToken {
+    /// The token ID, which the EVM of the network is configured with in
+    /// [CfgEnv::base_token_id](crate::primitives::CfgEnv::base_token_id).
+    pub id: U256,
+    /// The display name of the token, e.g. `Ether`.
+    pub name: String,
+    /// The display symbol of the token, e.g. `ETH`.
+    pub symbol: String,
+    /// The number of decimals the amounts are displayed with.
+    pub decimals: u8,
+}
+
+impl Default for BaseToken {
+    fn default() -> Self {
+        Self {
+            id: BASE_TOKEN_ID,
+            name: "Ether".into(),
+            symbol: "ETH".into(),
+            decimals: 18,
+        }
+    }
+}
+
+impl BaseToken {
+    /// Creates a base token whose ID is the one of the token minted by the system address with
+    /// sub ID zero, so that networks with distinct system addresses have distinct base tokens.
+    pub fn derived(
+        system_address: Address,
+        name: impl Into<String>,
+        symbol: impl Into<String>,
+        decimals: u8,
+    ) -> Self {
+        Self {
+            id: token_id_address(system_address, U256::ZERO),
+            name: name.into(),
+            symbol: symbol.into(),
+            decimals,
+        }
+    }
+}
+
+/// An account allocated at genesis.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct GenesisAccount {
+    /// The nonce of the account.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub nonce: u64,
+    /// The balance of the base token of the network.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub balance: U256,
+    /// The balances of the other Native Tokens, by token ID.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub tokens: BTreeMap<U256, U256>,
+    /// The code of the account, if it is a contract.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub code: Option<Bytes>,
+    /// The storage of the account.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+impl GenesisAccount {
+    /// Returns the balance of the token, given the base token of the network.
+    pub fn get_balance(&self, base_token_id: U256, token_id: U256) -> U256 {
+        if token_id == base_token_id {
+            self.balance
+        } else {
+            self.tokens.get(&token_id).copied().unwrap_or_default()
+        }
+    }
+
+    /// Sets the balance of the token, given the base token of the network.
+    pub fn set_balance(&mut self, base_token_id: U256, token_id: U256, balance: U256) {
+        if token_id == base_token_id {
+            self.balance = balance;
+        } else {
+            self.tokens.insert(token_id, balance);
+        }
+    }
+
+    /// Returns the info of the account, without the zero balances, given the base token of the
+    /// network.
+    pub fn info(&self, base_token_id: U256) -> AccountInfo {
+        let mut balances = HashMap::new();
+        if self.balance != U256::ZERO {
+            balances.insert(base_token_id, self.balance);
+        }
+        balances.extend(
+            self.tokens
+                .iter()
+                .filter(|(_, balance)| **balance != U256::ZERO)
+                .map(|(token_id, balance)| (*token_id, *balance)),
+        );
+        let mut info = AccountInfo {
+            balances,
+            nonce: self.nonce,
+            ..Default::default()
+        };
+        if let Some(code) = &self.code {
+            let code = Bytecode::new_raw(code.clone());
+            info.code_hash = code.hash_slow();
+            info.code = Some(code);
+        }
+        info
+    }
+}
+
+/// The genesis state of a network.
+///
+/// Built with [GenesisBuilder], or read from JSON with [Genesis::from_json].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct Genesis {
+    /// Free-form metadata of the network, e.g. its name.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub metadata: BTreeMap<String, String>,
+    /// The base token of the network, the [default](BaseToken::default) one if not set.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub base_token: Option<BaseToken>,
+    /// The Native Tokens supported at genesis, besides the base token.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub token_ids: Vec<U256>,
+    /// The accounts allocated at genesis.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub alloc: BTreeMap<Address, GenesisAccount>,
+}
+
+impl Genesis {
+    /// Returns the ID of the base token of the network.
+    pub fn base_token_id(&self) -> U256 {
+        self.base_token
+            .as_ref()
+            .map_or(BASE_TOKEN_ID, |base_token| base_token.id)
+    }
+
+    /// Returns the supported token IDs: the base token followed by the other tokens.
+    pub fn supported_token_ids(&self) -> Vec<U256> {
+        let mut token_ids = vec![self.base_token_id()];
+        for token_id in &self.token_ids {
+            if !token_ids.contains(token_id) {
+                token_ids.push(*token_id);
+            }
+        }
+        token_ids
+    }
+
+    /// Checks that every allocated token is supported.
+    pub fn validate(&self) -> Result<(), GenesisError> {
+        let token_ids = self.supported_token_ids();
+        for (address, account) in &self.alloc {
+            for (token_id, balance) in &account.tokens {
+                if *balance != U256::ZERO && !token_ids.contains(token_id) {
+                    return Err(GenesisError::UnsupportedToken {
+                        address: *address,
+                        token_id: *token_id,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the genesis state, with every account marked as created so that it can be
+    /// committed to any [DatabaseCommit].
+    pub fn to_state(&self) -> Result<EvmState, GenesisError> {
+        self.validate()?;
+        let base_token_id = self.base_token_id();
+        let accounts = self
+            .alloc
+            .iter()
+            .map(|(address, genesis_account)| {
+                let mut account = Account::from(genesis_account.info(base_token_id));
+                account.storage = genesis_account
+                    .storage
+                    .iter()
+                    .map(|(slot, value)| (*slot, EvmStorageSlot::new_changed(U256::ZERO, *value)))
+                    .collect();
+                account.mark_created();
+                account.mark_touch();
+                (*address, account)
+            })
+            .collect();
+        Ok(EvmState {
+            accounts,
+            token_ids: self.supported_token_ids(),
+        })
+    }
+
+    /// Returns an in-memory database holding the genesis state.
+    pub fn to_db(&self) -> Result<InMemoryDB, GenesisError> {
+        let mut db = InMemoryDB::default();
+        db.commit(self.to_state()?);
+        Ok(db)
+    }
+
+    /// Reads the genesis from JSON.
+    #[cfg(feature = "serde-json")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Writes the genesis as pretty-printed JSON.
+    #[cfg(feature = "serde-json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("genesis is serializable")
+    }
+}
+
+/// Errors of an invalid [Genesis].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GenesisError {
+    /// A token that isn't supported at genesis is allocated to an account.
+    UnsupportedToken { address: Address, token_id: U256 },
+}
+
+impl fmt::Display for GenesisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedToken { address, token_id } => {
+                write!(f, "unsupported token {token_id} allocated to {address}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GenesisError {}
+
+/// Builder of a [Genesis].
+#[derive(Clone, Debug, Default)]
+pub struct GenesisBuilder {
+    genesis: Genesis,
+}
+
+impl GenesisBuilder {
+    /// Creates a builder of an empty genesis.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a builder that extends the genesis.
+    pub fn from_genesis(genesis: Genesis) -> Self {
+        Self { genesis }
+    }
+
+    /// Sets the metadata entry.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.genesis.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the base token of the network.
+    ///
+    /// The balances of the base token must be set after it.
+    pub fn with_base_token(mut self, base_token: BaseToken) -> Self {
+        self.genesis.base_token = Some(base_token);
+        self
+    }
+
+    /// Supports the token at genesis.
+    pub fn with_token_id(mut self, token_id: U256) -> Self {
+        if token_id != self.genesis.base_token_id() && !self.genesis.token_ids.contains(&token_id) {
+            self.genesis.token_ids.push(token_id);
+        }
+        self
+    }
+
+    /// Allocates the account, replacing any earlier allocation at the address.
+    pub fn with_account(mut self, address: Address, account: GenesisAccount) -> Self {
+        self.genesis.alloc.insert(address, account);
+        self
+    }
+
+    /// Sets the balance of the token of the account.
+    pub fn with_balance(mut self, address: Address, token_id: U256, balance: U256) -> Self {
+        let base_token_id = self.genesis.base_token_id();
+        self.account(address)
+            .set_balance(base_token_id, token_id, balance);
+        self
+    }
+
+    /// Sets the nonce of the account.
+    pub fn with_nonce(mut self, address: Address, nonce: u64) -> Self {
+        self.account(address).nonce = nonce;
+        self
+    }
+
+    /// Sets the code of the account.
+    pub fn with_code(mut self, address: Address, code: Bytes) -> Self {
+        self.account(address).code = Some(code);
+        self
+    }
+
+    /// Sets the storage slot of the account.
+    pub fn with_storage(mut self, address: Address, slot: U256, value: U256) -> Self {
+        self.account(address).storage.insert(slot, value);
+        self
+    }
+
+    /// Returns the genesis, checking that every allocated token is supported.
+    pub fn build(self) -> Result<Genesis, GenesisError> {
+        self.genesis.validate()?;
+        Ok(self.genesis)
+    }
+
+    /// Returns an in-memory database holding the genesis state.
+    pub fn build_db(self) -> Result<InMemoryDB, GenesisError> {
+        self.genesis.to_db()
+    }
+
+    fn account(&mut self, address: Address) -> &mut GenesisAccount {
+        self.genesis.alloc.entry(address).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::DatabaseRef,
+        interpreter::opcode,
+        primitives::{address, TokenTransfer, TransactTo},
+        Evm,
+    };
+
+    const ALICE: Address = address!("00000000000000000000000000000000000000aa");
+    const BOB: Address = address!("00000000000000000000000000000000000000bb");
+    const CONTRACT: Address = address!("00000000000000000000000000000000000000cc");
+
+    fn genesis() -> GenesisBuilder {
+        let token_id = U256::from(1);
+        GenesisBuilder::new()
+            .with_metadata("network", "devnet")
+            .with_token_id(token_id)
+            .with_balance(ALICE, BASE_TOKEN_ID, U256::from(1_000_000))
+            .with_balance(ALICE, token_id, U256::from(100))
+            .with_code(CONTRACT, Bytes::from_static(&[0x00]))
+            .with_storage(CONTRACT, U256::from(1), U256::from(2))
+    }
+
+    #[test]
+    fn builds_db() {
+        let token_id = U256::from(1);
+        let db = genesis().build_db().unwrap();
+        assert_eq!(db.get_token_ids_ref(), Ok(vec![BASE_TOKEN_ID, token_id]));
+        let alice = db.basic_ref(ALICE).unwrap().unwrap();
+        assert_eq!(alice.get_base_balance(), U256::from(1_000_000));
+        assert_eq!(alice.get_balance(token_id), U256::from(100));
+        assert_eq!(db.storage_ref(CONTRACT, U256::from(1)), Ok(U256::from(2)));
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = ALICE;
+                tx.transact_to = TransactTo::Call(BOB);
+                tx.gas_limit = 21_000;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: token_id,
+                    amount: U256::from(40),
+                }];
+            })
+            .build();
+        assert!(evm.transact_commit().unwrap().is_success());
+        let bob = evm.db().basic_ref(BOB).unwrap().unwrap();
+        assert_eq!(bob.get_balance(token_id), U256::from(40));
+    }
+
+    #[test]
+    fn configures_base_token() {
+        let base_token = BaseToken::derived(
+            address!("00000000000000000000000000000000000000c1"),
+            "Sablier",
+            "SAB",
+            18,
+        );
+        let base_token_id = base_token.id;
+        let coinbase = address!("00000000000000000000000000000000000000c2");
+        // returns CALLVALUE and SELFBALANCE
+        let code = [
+            opcode::CALLVALUE,
+            opcode::PUSH1,
+            0,
+            opcode::MSTORE,
+            opcode::SELFBALANCE,
+            opcode::PUSH1,
+            0x20,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x40,
+            opcode::PUSH1,
+            0,
+            opcode::RETURN,
+        ];
+        let genesis = GenesisBuilder::new()
+            .with_base_token(base_token)
+            .with_balance(ALICE, base_token_id, U256::from(1_000_000))
+            .with_code(CONTRACT, Bytes::copy_from_slice(&code))
+            .build()
+            .unwrap();
+        assert_eq!(genesis.alloc[&ALICE].balance, U256::from(1_000_000));
+        assert_eq!(genesis.supported_token_ids(), vec![base_token_id]);
+
+        let mut evm = Evm::builder()
+            .with_db(genesis.to_db().unwrap())
+            .modify_cfg_env(|cfg| cfg.base_token_id = genesis.base_token_id())
+            .modify_block_env(|block| block.coinbase = coinbase)
+            .modify_tx_env(|tx| {
+                tx.caller = ALICE;
+                tx.transact_to = TransactTo::Call(CONTRACT);
+                tx.gas_limit = 100_000;
+                tx.gas_price = U256::from(1);
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: base_token_id,
+                    amount: U256::from(40),
+                }];
+            })
+            .build();
+        let result = evm.transact_commit().unwrap();
+        let gas_used = U256::from(result.gas_used());
+        let output = result.output().unwrap();
+        assert_eq!(U256::from_be_slice(&output[..32]), U256::from(40));
+        assert_eq!(U256::from_be_slice(&output[32..]), U256::from(40));
+
+        // the gas is paid with the base token
+        let alice = evm.db().basic_ref(ALICE).unwrap().unwrap();
+        assert_eq!(
+            alice.get_balance(base_token_id),
+            U256::from(1_000_000 - 40) - gas_used
+        );
+        let coinbase = evm.db().basic_ref(coinbase).unwrap().unwrap();
+        assert_eq!(coinbase.get_balance(base_token_id), gas_used);
+        assert_eq!(coinbase.get_base_balance(), U256::ZERO);
+    }
+
+    #[test]
+    fn rejects_unsupported_tokens() {
+        let error = GenesisBuilder::new()
+            .with_balance(ALICE, U256::from(7), U256::from(1))
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            GenesisError::UnsupportedToken {
+                address: ALICE,
+                token_id: U256::from(7),
+            }
+        );
+    }
+
+    #[cfg(feature = "serde-json")]
+    #[test]
+    fn json_roundtrip() {
+        let genesis = genesis().build().unwrap();
+        assert_eq!(Genesis::from_json(&genesis.to_json()).unwrap(), genesis);
+
+        let json = r#"{
+            "tokenIds": ["0x1"],
+            "alloc": {
+                "0x00000000000000000000000000000000000000aa": {
+                    "balance": "0x3e8",
+                    "tokens": { "0x1": "0x64" }
+                }
+            }
+        }"#;
+        let genesis = Genesis::from_json(json).unwrap();
+        let alice = &genesis.alloc[&ALICE];
+        assert_eq!(
+            alice.get_balance(BASE_TOKEN_ID, BASE_TOKEN_ID),
+            U256::from(1000)
+        );
+        assert_eq!(
+            alice.get_balance(BASE_TOKEN_ID, U256::from(1)),
+            U256::from(100)
+        );
+        assert_eq!(
+            genesis.supported_token_ids(),
+            vec![BASE_TOKEN_ID, U256::from(1)]
+        );
+    }
+}
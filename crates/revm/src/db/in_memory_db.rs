@@ -1,11 +1,11 @@
-use super::{DatabaseCommit, DatabaseRef, EmptyDB};
+use super::{DatabaseCommit, DatabaseEntries, DatabaseIter, DatabaseRef, EmptyDB};
 use crate::primitives::{
     hash_map::Entry, state::EvmState, utilities::init_balances, AccountInfo, Address, Bytecode,
-    HashMap, Log, B256, BASE_TOKEN_ID, KECCAK_EMPTY, U256,
+    HashMap, Log, TokenBalances, B256, BASE_TOKEN_ID, KECCAK_EMPTY, U256,
 };
 use crate::Database;
 use core::convert::Infallible;
-use std::vec::Vec;
+use std::{boxed::Box, vec::Vec};
 
 /// A [Database] implementation that stores all state changes in memory.
 pub type InMemoryDB = CacheDB<EmptyDB>;
@@ -84,6 +84,27 @@ impl<ExtDB> CacheDB<ExtDB> {
         self.insert_contract(&mut info);
         self.accounts.entry(address).or_default().info = info;
     }
+
+    /// Adds the token to the registry of the valid token IDs, if it isn't registered yet.
+    pub fn register_token(&mut self, token_id: U256) {
+        if !self.token_ids.contains(&token_id) {
+            self.token_ids.push(token_id);
+        }
+    }
+}
+
+impl InMemoryDB {
+    /// Creates a database whose accounts hold the given balances, with their tokens registered,
+    /// e.g. as the genesis of a local devnet.
+    pub fn prefunded(alloc: impl IntoIterator<Item = (Address, TokenBalances)>) -> Self {
+        let mut db = Self::default();
+        for (address, balances) in alloc {
+            for (token_id, amount) in balances {
+                db.fund(address, token_id, amount).unwrap();
+            }
+        }
+        db
+    }
 }
 
 impl<ExtDB: DatabaseRef> CacheDB<ExtDB> {
@@ -105,6 +126,45 @@ impl<ExtDB: DatabaseRef> CacheDB<ExtDB> {
         }
     }
 
+    /// Adds the amount of the token to the balance of the account, registering the token, like a
+    /// faucet of a development network.
+    ///
+    /// The account is created if it doesn't exist.
+    pub fn fund(
+        &mut self,
+        address: Address,
+        token_id: U256,
+        amount: U256,
+    ) -> Result<(), ExtDB::Error> {
+        self.register_token(token_id);
+        self.load_existing_account(address)?
+            .info
+            .increase_balance_saturating(token_id, amount);
+        Ok(())
+    }
+
+    /// Sets the code of the account, keeping its balances and storage.
+    ///
+    /// The account is created if it doesn't exist, with a nonce of 1 like the contracts deployed
+    /// since Spurious Dragon (EIP-161).
+    pub fn deploy(&mut self, address: Address, bytecode: Bytecode) -> Result<(), ExtDB::Error> {
+        let mut info = self.load_existing_account(address)?.info.clone();
+        info.code_hash = bytecode.hash_slow();
+        info.code = Some(bytecode);
+        info.nonce = info.nonce.max(1);
+        self.insert_account_info(address, info);
+        Ok(())
+    }
+
+    /// Returns the account like [Self::load_account], creating it if it doesn't exist.
+    fn load_existing_account(&mut self, address: Address) -> Result<&mut DbAccount, ExtDB::Error> {
+        let account = self.load_account(address)?;
+        if matches!(account.account_state, AccountState::NotExisting) {
+            account.account_state = AccountState::None;
+        }
+        Ok(account)
+    }
+
     /// insert account storage without overriding account info
     pub fn insert_account_storage(
         &mut self,
@@ -165,7 +225,10 @@ impl<ExtDB> DatabaseCommit for CacheDB<ExtDB> {
                     .map(|(key, value)| (key, value.present_value())),
             );
         }
-        self.token_ids = changes.token_ids;
+        // the token IDs are only in the changes if they were loaded
+        if !changes.token_ids.is_empty() {
+            self.token_ids = changes.token_ids;
+        }
     }
 }
 
@@ -301,12 +364,52 @@ impl<ExtDB: DatabaseRef> DatabaseRef for CacheDB<ExtDB> {
         }
     }
 
+    // shared readers see the registry of the cache, like the users of `Database`
     fn get_token_ids_ref(&self) -> Result<Vec<U256>, Self::Error> {
-        self.db.get_token_ids_ref()
+        Ok(self.token_ids.clone())
     }
 
     fn is_token_id_valid_ref(&self, token_id: U256) -> Result<bool, Self::Error> {
-        self.db.is_token_id_valid_ref(token_id)
+        Ok(self.token_ids.contains(&token_id))
+    }
+}
+
+/// Lists the cached accounts and storage over those of the wrapped database.
+impl<ExtDB: DatabaseIter> DatabaseIter for CacheDB<ExtDB> {
+    fn accounts_iter(&self) -> Result<DatabaseEntries<'_, (Address, AccountInfo)>, Self::Error> {
+        let cached = self
+            .accounts
+            .iter()
+            .filter_map(|(address, account)| Some((*address, account.info()?)));
+        let uncached = self
+            .db
+            .accounts_iter()?
+            .filter(|(address, _)| !self.accounts.contains_key(address));
+        Ok(Box::new(cached.chain(uncached)))
+    }
+
+    fn storage_iter(
+        &self,
+        address: Address,
+    ) -> Result<DatabaseEntries<'_, (U256, U256)>, Self::Error> {
+        let Some(account) = self.accounts.get(&address) else {
+            return self.db.storage_iter(address);
+        };
+        let cached = account
+            .storage
+            .iter()
+            .map(|(index, value)| (*index, *value))
+            .filter(|(_, value)| *value != U256::ZERO);
+        match account.account_state {
+            AccountState::NotExisting | AccountState::StorageCleared => Ok(Box::new(cached)),
+            AccountState::Touched | AccountState::None => {
+                let uncached = self
+                    .db
+                    .storage_iter(address)?
+                    .filter(|(index, _)| !account.storage.contains_key(index));
+                Ok(Box::new(cached.chain(uncached)))
+            }
+        }
     }
 }
 
@@ -440,8 +543,10 @@ impl Database for BenchmarkDB {
 
 #[cfg(test)]
 mod tests {
-    use super::{CacheDB, EmptyDB};
-    use crate::primitives::{db::Database, AccountInfo, Address, U256};
+    use super::{CacheDB, EmptyDB, InMemoryDB};
+    use crate::primitives::{
+        db::Database, AccountInfo, Address, Bytecode, Bytes, TokenBalances, BASE_TOKEN_ID, U256,
+    };
 
     #[test]
     fn test_insert_account_storage() {
@@ -466,6 +571,33 @@ mod tests {
         assert_eq!(new_state.storage(account, key), Ok(value));
     }
 
+    #[test]
+    fn test_dev_helpers() {
+        let holder = Address::with_last_byte(0xaa);
+        let contract = Address::with_last_byte(0xbb);
+        let token_id = U256::from(7);
+        let mut db = InMemoryDB::prefunded([(
+            holder,
+            TokenBalances::from([(BASE_TOKEN_ID, U256::from(100)), (token_id, U256::from(5))]),
+        )]);
+        assert_eq!(db.token_ids, [BASE_TOKEN_ID, token_id]);
+
+        // funding adds to the balance, and creates the account
+        db.fund(holder, token_id, U256::from(3)).unwrap();
+        db.fund(contract, BASE_TOKEN_ID, U256::from(1)).unwrap();
+        let bytecode = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+        db.deploy(contract, bytecode.clone()).unwrap();
+        db.register_token(token_id);
+        assert_eq!(db.token_ids, [BASE_TOKEN_ID, token_id]);
+
+        let info = db.basic(holder).unwrap().unwrap();
+        assert_eq!(info.get_balance(token_id), U256::from(8));
+        let info = db.basic(contract).unwrap().unwrap();
+        assert_eq!(info.get_balance(BASE_TOKEN_ID), U256::from(1));
+        assert_eq!(info.nonce, 1);
+        assert_eq!(db.code_by_hash(info.code_hash), Ok(bytecode));
+    }
+
     #[test]
     fn test_replace_account_storage() {
         let account = Address::with_last_byte(42);
@@ -0,0 +1,440 @@
+//! A copy-on-write overlay over any [Database], for simulations that are thrown away or applied
+//! later.
+//!
+//! [OverlayDB] keeps the changes committed to it in memory, and reads everything else from the
+//! database it wraps, which is never written to. Overlays nest: an overlay can wrap another
+//! overlay, either by `&mut` to run one simulation at a time on top of it, or with
+//! [OverlayDB::fork] to run several independent ones side by side. This makes trees of what-if
+//! simulations cheap, e.g. N alternative second transactions on top of one first transaction:
+//!
+//! ```text
+//! base <- OverlayDB (first tx) <- fork (second tx, alternative 1)
+//!                              <- fork (second tx, alternative 2)
+//! ```
+//!
+//! The changes of an overlay are returned by [OverlayDB::changes] as an [EvmState], and applied
+//! to the wrapped database by [OverlayDB::flatten].
+use super::{Database, DatabaseCommit, DatabaseRef, WrapDatabaseRef};
+use crate::primitives::{
+    Account, AccountInfo, AccountStatus, Address, Bytecode, EvmState, EvmStorageSlot, HashMap,
+    B256, KECCAK_EMPTY, U256,
+};
+use std::vec::Vec;
+
+/// A write overlay over a database.
+///
+/// Reads return the changes committed to the overlay, and fall through to the wrapped database
+/// for everything else. Reads are not cached, so the overlay only holds the changes.
+#[derive(Clone, Debug, Default)]
+pub struct OverlayDB<DB> {
+    db: DB,
+    accounts: HashMap<Address, OverlayAccount>,
+    contracts: HashMap<B256, Bytecode>,
+    token_ids: Option<Vec<U256>>,
+}
+
+/// The changes of an account.
+#[derive(Clone, Debug, Default)]
+struct OverlayAccount {
+    /// The account info, or `None` if the account was destroyed.
+    info: Option<AccountInfo>,
+    /// Whether the storage of the wrapped database is hidden, because the account was destroyed
+    /// or created in the overlay.
+    storage_cleared: bool,
+    /// The changed storage slots.
+    storage: HashMap<U256, U256>,
+}
+
+impl<DB> OverlayDB<DB> {
+    /// Wraps the database with an empty overlay.
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            accounts: HashMap::new(),
+            contracts: HashMap::new(),
+            token_ids: None,
+        }
+    }
+
+    /// Returns the wrapped database.
+    pub fn inner(&self) -> &DB {
+        &self.db
+    }
+
+    /// Consumes the overlay, discarding its changes and returning the wrapped database.
+    pub fn into_inner(self) -> DB {
+        self.db
+    }
+
+    /// Returns `true` if no changes were committed to the overlay.
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.token_ids.is_none()
+    }
+
+    /// Discards the changes committed to the overlay.
+    pub fn clear(&mut self) {
+        self.accounts.clear();
+        self.contracts.clear();
+        self.token_ids = None;
+    }
+
+    /// Returns a new overlay on top of this one, which can be forked any number of times.
+    ///
+    /// Changes committed to the fork are not visible to this overlay or to the other forks.
+    pub fn fork(&self) -> OverlayDB<WrapDatabaseRef<&Self>>
+    where
+        DB: DatabaseRef,
+    {
+        OverlayDB::new(WrapDatabaseRef(self))
+    }
+
+    /// Returns the account info in the overlay, or `None` if it has to be read from the wrapped
+    /// database.
+    fn overlay_basic(&self, address: Address) -> Option<Option<AccountInfo>> {
+        self.accounts
+            .get(&address)
+            .map(|account| account.info.clone())
+    }
+
+    /// Returns the storage value in the overlay, or `None` if it has to be read from the wrapped
+    /// database.
+    fn overlay_storage(&self, address: Address, index: U256) -> Option<U256> {
+        let account = self.accounts.get(&address)?;
+        match account.storage.get(&index) {
+            Some(value) => Some(*value),
+            None if account.storage_cleared => Some(U256::ZERO),
+            None => None,
+        }
+    }
+
+    /// Returns the changes of the overlay, with the given token IDs.
+    fn changes_with(&self, token_ids: Vec<U256>) -> EvmState {
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|(address, overlay)| {
+                let mut account = Account {
+                    info: overlay.info.clone().unwrap_or_default(),
+                    storage: overlay
+                        .storage
+                        .iter()
+                        .map(|(index, value)| (*index, EvmStorageSlot::new(*value)))
+                        .collect(),
+                    status: AccountStatus::Touched,
+                };
+                if overlay.info.is_none() {
+                    account.mark_selfdestruct();
+                } else if overlay.storage_cleared {
+                    account.mark_created();
+                }
+                (*address, account)
+            })
+            .collect();
+        EvmState {
+            accounts,
+            token_ids,
+        }
+    }
+}
+
+impl<DB: Database> OverlayDB<DB> {
+    /// Returns the changes committed to the overlay, as a changeset that can be committed to the
+    /// wrapped database.
+    ///
+    /// The changeset holds every changed account with its full info, including its token
+    /// balances, the changed storage slots, and the supported token IDs.
+    pub fn changes(&self) -> Result<EvmState, DB::Error> {
+        Ok(self.changes_with(self.get_token_ids()?))
+    }
+
+    /// Commits the changes of the overlay to the wrapped database and returns it.
+    ///
+    /// Flattening a nested overlay applies its changes to the overlay below it.
+    pub fn flatten(mut self) -> Result<DB, DB::Error>
+    where
+        DB: DatabaseCommit,
+    {
+        if !self.is_empty() {
+            let changes = self.changes()?;
+            self.db.commit(changes);
+        }
+        Ok(self.db)
+    }
+}
+
+impl<DB> DatabaseCommit for OverlayDB<DB> {
+    fn commit(&mut self, changes: EvmState) {
+        for (address, mut account) in changes.accounts {
+            if !account.is_touched() {
+                continue;
+            }
+            let overlay = self.accounts.entry(address).or_default();
+            if account.is_selfdestructed() {
+                *overlay = OverlayAccount {
+                    info: None,
+                    storage_cleared: true,
+                    storage: HashMap::new(),
+                };
+                continue;
+            }
+            if account.is_created() {
+                overlay.storage_cleared = true;
+                overlay.storage.clear();
+            }
+            overlay.storage.extend(
+                account
+                    .changed_storage_slots()
+                    .map(|(index, slot)| (*index, slot.present_value())),
+            );
+
+            if let Some(code) = &account.info.code {
+                if !code.is_empty() {
+                    if account.info.code_hash == KECCAK_EMPTY {
+                        account.info.code_hash = code.hash_slow();
+                    }
+                    self.contracts
+                        .entry(account.info.code_hash)
+                        .or_insert_with(|| code.clone());
+                }
+            }
+            if account.info.code_hash == B256::ZERO {
+                account.info.code_hash = KECCAK_EMPTY;
+            }
+            overlay.info = Some(account.info);
+        }
+        // the token IDs are only in the changes if they were loaded
+        if !changes.token_ids.is_empty() {
+            self.token_ids = Some(changes.token_ids);
+        }
+    }
+}
+
+impl<DB: Database> Database for OverlayDB<DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.overlay_basic(address) {
+            Some(info) => Ok(info),
+            None => self.db.basic(address),
+        }
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.db.code_by_hash(code_hash),
+        }
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.overlay_storage(address, index) {
+            Some(value) => Ok(value),
+            None => self.db.storage(address, index),
+        }
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.db.block_hash(number)
+    }
+
+    fn get_token_ids(&self) -> Result<Vec<U256>, Self::Error> {
+        match &self.token_ids {
+            Some(token_ids) => Ok(token_ids.clone()),
+            None => self.db.get_token_ids(),
+        }
+    }
+
+    fn is_token_id_valid(&self, token_id: U256) -> Result<bool, Self::Error> {
+        match &self.token_ids {
+            Some(token_ids) => Ok(token_ids.contains(&token_id)),
+            None => self.db.is_token_id_valid(token_id),
+        }
+    }
+}
+
+impl<DB: DatabaseRef> DatabaseRef for OverlayDB<DB> {
+    type Error = DB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.overlay_basic(address) {
+            Some(info) => Ok(info),
+            None => self.db.basic_ref(address),
+        }
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => self.db.code_by_hash_ref(code_hash),
+        }
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        match self.overlay_storage(address, index) {
+            Some(value) => Ok(value),
+            None => self.db.storage_ref(address, index),
+        }
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        self.db.block_hash_ref(number)
+    }
+
+    fn is_token_id_valid_ref(&self, token_id: U256) -> Result<bool, Self::Error> {
+        match &self.token_ids {
+            Some(token_ids) => Ok(token_ids.contains(&token_id)),
+            None => self.db.is_token_id_valid_ref(token_id),
+        }
+    }
+
+    fn get_token_ids_ref(&self) -> Result<Vec<U256>, Self::Error> {
+        match &self.token_ids {
+            Some(token_ids) => Ok(token_ids.clone()),
+            None => self.db.get_token_ids_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryDB,
+        primitives::{address, TokenTransfer, TransactTo, BASE_TOKEN_ID},
+        Evm,
+    };
+
+    const ALICE: Address = address!("00000000000000000000000000000000000a11ce");
+    const BOB: Address = address!("0000000000000000000000000000000000000b0b");
+    const CAROL: Address = address!("00000000000000000000000000000000000ca201");
+    const TOKEN_ID: U256 = U256::from_limbs([7, 0, 0, 0]);
+
+    fn base_db() -> InMemoryDB {
+        let mut info = AccountInfo::default();
+        info.increase_balance(TOKEN_ID, U256::from(100)).unwrap();
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(ALICE, info);
+        db.token_ids = vec![BASE_TOKEN_ID, TOKEN_ID];
+        db
+    }
+
+    /// Sends `amount` of [TOKEN_ID] from [ALICE] to `to`, committing the changes to the database.
+    fn send<DB: Database + DatabaseCommit>(db: DB, nonce: u64, to: Address, amount: u64)
+    where
+        DB::Error: core::fmt::Debug,
+    {
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = ALICE;
+                tx.transact_to = TransactTo::Call(to);
+                tx.nonce = Some(nonce);
+                tx.gas_price = U256::ZERO;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: TOKEN_ID,
+                    amount: U256::from(amount),
+                }];
+            })
+            .build();
+        assert!(evm.transact_commit().unwrap().is_success());
+    }
+
+    fn balance<DB: DatabaseRef>(db: &DB, address: Address) -> U256
+    where
+        DB::Error: core::fmt::Debug,
+    {
+        db.basic_ref(address)
+            .unwrap()
+            .unwrap_or_default()
+            .get_balance(TOKEN_ID)
+    }
+
+    #[test]
+    fn simulates_alternatives_on_top_of_a_transaction() {
+        let base = base_db();
+        let mut first = OverlayDB::new(WrapDatabaseRef(&base));
+        send(&mut first, 0, BOB, 40);
+        assert_eq!(balance(&first, BOB), U256::from(40));
+        assert_eq!(balance(&base, BOB), U256::ZERO);
+
+        let alternatives: Vec<_> = [(BOB, 10), (CAROL, 20), (CAROL, 60)]
+            .into_iter()
+            .map(|(to, amount)| {
+                let mut second = first.fork();
+                send(&mut second, 1, to, amount);
+                second
+            })
+            .collect();
+        assert_eq!(balance(&alternatives[0], BOB), U256::from(50));
+        assert_eq!(balance(&alternatives[0], CAROL), U256::ZERO);
+        assert_eq!(balance(&alternatives[1], CAROL), U256::from(20));
+        assert_eq!(balance(&alternatives[2], ALICE), U256::ZERO);
+        // the forks don't see each other, and don't change the overlay below them
+        assert_eq!(balance(&first, ALICE), U256::from(60));
+        assert_eq!(balance(&first, CAROL), U256::ZERO);
+        // the sender, the recipient and the beneficiary
+        assert!(alternatives.iter().all(|fork| fork.accounts.len() == 3));
+    }
+
+    #[test]
+    fn flattens_into_the_wrapped_database() {
+        let mut base = base_db();
+        let mut first = OverlayDB::new(WrapDatabaseRef(&base));
+        send(&mut first, 0, BOB, 40);
+
+        // a nested overlay flattens into the overlay below it
+        let mut second = OverlayDB::new(&mut first);
+        send(&mut second, 1, CAROL, 25);
+        let changes = second.changes().unwrap();
+        assert_eq!(changes.accounts.len(), 3);
+        assert_eq!(changes.total_balance(TOKEN_ID), U256::from(60));
+        assert_eq!(changes.token_ids, vec![BASE_TOKEN_ID, TOKEN_ID]);
+        second.flatten().unwrap();
+        assert_eq!(balance(&first, CAROL), U256::from(25));
+
+        let changes = first.changes().unwrap();
+        assert_eq!(changes.total_balance(TOKEN_ID), U256::from(100));
+        base.commit(changes);
+        assert_eq!(balance(&base, ALICE), U256::from(35));
+        assert_eq!(balance(&base, BOB), U256::from(40));
+        assert_eq!(balance(&base, CAROL), U256::from(25));
+        assert_eq!(base.basic_ref(ALICE).unwrap().unwrap().nonce, 2);
+    }
+
+    #[test]
+    fn hides_the_storage_of_destroyed_accounts() {
+        let mut base = base_db();
+        base.insert_account_storage(BOB, U256::from(1), U256::from(2))
+            .unwrap();
+        base.insert_account_storage(BOB, U256::from(3), U256::from(4))
+            .unwrap();
+        let mut overlay = OverlayDB::new(&mut base);
+        assert!(overlay.is_empty());
+
+        let mut account = Account::from(AccountInfo::default());
+        account.mark_touch();
+        account.storage.insert(
+            U256::from(1),
+            EvmStorageSlot::new_changed(U256::from(2), U256::from(5)),
+        );
+        overlay.commit(EvmState {
+            accounts: HashMap::from_iter([(BOB, account.clone())]),
+            token_ids: Vec::new(),
+        });
+        assert_eq!(overlay.storage(BOB, U256::from(1)), Ok(U256::from(5)));
+        assert_eq!(overlay.storage(BOB, U256::from(3)), Ok(U256::from(4)));
+        assert_eq!(overlay.get_token_ids(), Ok(vec![BASE_TOKEN_ID, TOKEN_ID]));
+
+        account.mark_selfdestruct();
+        overlay.commit(EvmState {
+            accounts: HashMap::from_iter([(BOB, account)]),
+            token_ids: Vec::new(),
+        });
+        assert_eq!(overlay.basic(BOB), Ok(None));
+        assert_eq!(overlay.storage(BOB, U256::from(3)), Ok(U256::ZERO));
+
+        overlay.flatten().unwrap();
+        assert_eq!(base.storage_ref(BOB, U256::from(1)), Ok(U256::ZERO));
+        assert_eq!(base.basic_ref(BOB), Ok(None));
+    }
+}
@@ -0,0 +1,323 @@
+use super::{Database, DatabaseRef};
+use crate::primitives::{AccountInfo, Address, Bytecode, HashMap, TransactTo, TxEnv, B256, U256};
+use std::{
+    hash::Hash,
+    panic,
+    sync::RwLock,
+    thread::{self, ScopedJoinHandle},
+    vec::Vec,
+};
+
+/// A thread-safe caching wrapper of a [DatabaseRef] whose cache can be warmed ahead of
+/// execution.
+///
+/// [PrefetchDB::prefetch] loads the state a transaction is going to read on several threads at
+/// once, so that the transaction doesn't have to wait for the wrapped database one read at a
+/// time. The cache is never invalidated, so the wrapped database must not change while it is in
+/// use; changes made by transactions are not written to it.
+#[derive(Debug, Default)]
+pub struct PrefetchDB<DB> {
+    db: DB,
+    accounts: RwLock<HashMap<Address, Option<AccountInfo>>>,
+    contracts: RwLock<HashMap<B256, Bytecode>>,
+    storage: RwLock<HashMap<(Address, U256), U256>>,
+    block_hashes: RwLock<HashMap<U256, B256>>,
+    token_ids: RwLock<Option<Vec<U256>>>,
+    valid_token_ids: RwLock<HashMap<U256, bool>>,
+}
+
+impl<DB> PrefetchDB<DB> {
+    /// Wraps the database with an empty cache.
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            accounts: Default::default(),
+            contracts: Default::default(),
+            storage: Default::default(),
+            block_hashes: Default::default(),
+            token_ids: Default::default(),
+            valid_token_ids: Default::default(),
+        }
+    }
+
+    /// Returns the wrapped database.
+    pub fn inner(&self) -> &DB {
+        &self.db
+    }
+
+    /// Consumes the wrapper, returning the wrapped database.
+    pub fn into_inner(self) -> DB {
+        self.db
+    }
+}
+
+/// Returns the cached value of the key, or loads it and caches it.
+fn cached<K: Eq + Hash, V: Clone, E>(
+    cache: &RwLock<HashMap<K, V>>,
+    key: K,
+    load: impl FnOnce() -> Result<V, E>,
+) -> Result<V, E> {
+    if let Some(value) = cache.read().unwrap().get(&key) {
+        return Ok(value.clone());
+    }
+    let value = load()?;
+    cache.write().unwrap().insert(key, value.clone());
+    Ok(value)
+}
+
+/// Joins the threads, returning the first error.
+fn join_all<E>(handles: Vec<ScopedJoinHandle<'_, Result<(), E>>>) -> Result<(), E> {
+    handles.into_iter().try_for_each(|handle| {
+        handle
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+impl<DB: DatabaseRef + Sync> PrefetchDB<DB>
+where
+    DB::Error: Send,
+{
+    /// Loads the state the transaction is going to read into the cache, on a thread per account.
+    ///
+    /// The accounts of the caller, the callee, the gas payer, the addresses of the access list
+    /// and the authorities of the authorization list and their delegates are loaded together
+    /// with their code and token balances, as are the storage slots of the access list, the
+    /// supported token IDs and the validity of the transferred token IDs.
+    pub fn prefetch(&self, tx: &TxEnv) -> Result<(), DB::Error> {
+        let mut accounts: HashMap<Address, Vec<U256>> = HashMap::new();
+        accounts.entry(tx.caller).or_default();
+        if let TransactTo::Call(address) = tx.transact_to {
+            accounts.entry(address).or_default();
+        }
+        if let Some(gas_payer) = tx.gas_payer {
+            accounts.entry(gas_payer).or_default();
+        }
+        for (address, slots) in &tx.access_list {
+            accounts.entry(*address).or_default().extend(slots);
+        }
+        for authorization in &tx.authorization_list {
+            accounts.entry(authorization.inner.address).or_default();
+            if let Some(authority) = authorization.authority {
+                accounts.entry(authority).or_default();
+            }
+        }
+
+        thread::scope(|scope| {
+            let mut handles = Vec::with_capacity(accounts.len() + 1);
+            handles.push(scope.spawn(|| {
+                self.get_token_ids_ref()?;
+                tx.transferred_tokens
+                    .iter()
+                    .try_for_each(|transfer| self.is_token_id_valid_ref(transfer.id).map(drop))
+            }));
+            for (address, slots) in accounts {
+                handles.push(scope.spawn(move || {
+                    if let Some(info) = self.basic_ref(address)? {
+                        if info.code.is_none() && !info.is_empty_code_hash() {
+                            self.code_by_hash_ref(info.code_hash)?;
+                        }
+                    }
+                    slots
+                        .into_iter()
+                        .try_for_each(|slot| self.storage_ref(address, slot).map(drop))
+                }));
+            }
+            join_all(handles)
+        })
+    }
+}
+
+impl<DB: DatabaseRef> DatabaseRef for PrefetchDB<DB> {
+    type Error = DB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        cached(&self.accounts, address, || self.db.basic_ref(address))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        cached(&self.contracts, code_hash, || {
+            self.db.code_by_hash_ref(code_hash)
+        })
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        cached(&self.storage, (address, index), || {
+            self.db.storage_ref(address, index)
+        })
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        cached(&self.block_hashes, number, || {
+            self.db.block_hash_ref(number)
+        })
+    }
+
+    fn is_token_id_valid_ref(&self, token_id: U256) -> Result<bool, Self::Error> {
+        cached(&self.valid_token_ids, token_id, || {
+            self.db.is_token_id_valid_ref(token_id)
+        })
+    }
+
+    fn get_token_ids_ref(&self) -> Result<Vec<U256>, Self::Error> {
+        if let Some(token_ids) = &*self.token_ids.read().unwrap() {
+            return Ok(token_ids.clone());
+        }
+        let token_ids = self.db.get_token_ids_ref()?;
+        *self.token_ids.write().unwrap() = Some(token_ids.clone());
+        Ok(token_ids)
+    }
+}
+
+impl<DB: DatabaseRef> Database for PrefetchDB<DB> {
+    type Error = DB::Error;
+
+    #[inline]
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    #[inline]
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    #[inline]
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    #[inline]
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+
+    #[inline]
+    fn get_token_ids(&self) -> Result<Vec<U256>, Self::Error> {
+        self.get_token_ids_ref()
+    }
+
+    #[inline]
+    fn is_token_id_valid(&self, token_id: U256) -> Result<bool, Self::Error> {
+        self.is_token_id_valid_ref(token_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryDB,
+        primitives::{address, init_balances, TokenTransfer, BASE_TOKEN_ID},
+        Evm,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CALLER: Address = address!("00000000000000000000000000000000000000aa");
+    const TARGET: Address = address!("00000000000000000000000000000000000000bb");
+
+    /// Counts the reads of accounts and storage slots, and serves the registry of the cache.
+    #[derive(Default)]
+    struct CountingDB {
+        db: InMemoryDB,
+        reads: AtomicUsize,
+    }
+
+    impl DatabaseRef for CountingDB {
+        type Error = <InMemoryDB as DatabaseRef>::Error;
+
+        fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.db.basic_ref(address)
+        }
+
+        fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+            self.db.code_by_hash_ref(code_hash)
+        }
+
+        fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.db.storage_ref(address, index)
+        }
+
+        fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+            self.db.block_hash_ref(number)
+        }
+
+        fn is_token_id_valid_ref(&self, token_id: U256) -> Result<bool, Self::Error> {
+            Ok(self.db.token_ids.contains(&token_id))
+        }
+
+        fn get_token_ids_ref(&self) -> Result<Vec<U256>, Self::Error> {
+            Ok(self.db.token_ids.clone())
+        }
+    }
+
+    fn tx() -> TxEnv {
+        TxEnv {
+            caller: CALLER,
+            transact_to: TransactTo::Call(TARGET),
+            gas_limit: 100_000,
+            access_list: vec![(TARGET, vec![U256::from(1), U256::from(2)])],
+            transferred_tokens: vec![TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(10),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn prefetches_access_list() {
+        let mut inner = CountingDB::default();
+        inner.db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        inner
+            .db
+            .insert_account_storage(TARGET, U256::from(1), U256::from(7))
+            .unwrap();
+        let db = PrefetchDB::new(inner);
+
+        db.prefetch(&tx()).unwrap();
+        // the caller, the target and its two slots
+        assert_eq!(db.inner().reads.load(Ordering::Relaxed), 4);
+
+        assert_eq!(db.storage_ref(TARGET, U256::from(1)), Ok(U256::from(7)));
+        assert_eq!(
+            db.basic_ref(CALLER).unwrap().unwrap().get_base_balance(),
+            U256::from(1_000_000)
+        );
+        assert_eq!(db.inner().reads.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn executes_against_prefetched_state() {
+        let mut inner = CountingDB::default();
+        inner.db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        let db = PrefetchDB::new(inner);
+        let tx = tx();
+        db.prefetch(&tx).unwrap();
+
+        let mut evm = Evm::builder()
+            .with_ref_db(&db)
+            .modify_tx_env(|env| *env = tx)
+            .build();
+        let result = evm.transact().unwrap();
+        assert!(result.result.is_success());
+        assert_eq!(
+            result.state.accounts[&TARGET].info.get_base_balance(),
+            U256::from(10)
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! Recording and replaying of the reads made to a database.
+//!
+//! A [RecordingDB] logs every read made by the EVM together with its response, and a [ReplayDB]
+//! serves the reads from the log. A bug report can include the log of a transaction, which
+//! reproduces it without access to the database it was executed on, token IDs included.
+use super::{Database, DatabaseCommit};
+use crate::primitives::{AccountInfo, Address, Bytecode, EvmState, B256, U256};
+use core::{
+    cell::{Cell, RefCell},
+    fmt,
+};
+use std::vec::Vec;
+
+/// A read made to a database, with its response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DbRead {
+    /// [Database::basic]
+    Basic {
+        address: Address,
+        account: Option<AccountInfo>,
+    },
+    /// [Database::code_by_hash]
+    CodeByHash { code_hash: B256, code: Bytecode },
+    /// [Database::storage]
+    Storage {
+        address: Address,
+        index: U256,
+        value: U256,
+    },
+    /// [Database::block_hash]
+    BlockHash { number: U256, hash: B256 },
+    /// [Database::get_token_ids]
+    TokenIds { token_ids: Vec<U256> },
+    /// [Database::is_token_id_valid]
+    IsTokenIdValid { token_id: U256, valid: bool },
+}
+
+/// A database wrapper that records the reads made to the wrapped database.
+///
+/// Failed reads are not recorded. Changes are committed to the wrapped database.
+#[derive(Debug, Default)]
+pub struct RecordingDB<DB> {
+    db: DB,
+    reads: RefCell<Vec<DbRead>>,
+}
+
+impl<DB> RecordingDB<DB> {
+    /// Wraps the database, with no reads recorded.
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            reads: RefCell::default(),
+        }
+    }
+
+    /// Returns the wrapped database.
+    pub fn inner(&self) -> &DB {
+        &self.db
+    }
+
+    /// Returns the reads recorded so far, in the order they were made, and clears them.
+    pub fn take_reads(&mut self) -> Vec<DbRead> {
+        self.reads.take()
+    }
+
+    /// Consumes the wrapper, returning the wrapped database and the recorded reads.
+    pub fn into_parts(self) -> (DB, Vec<DbRead>) {
+        (self.db, self.reads.into_inner())
+    }
+
+    fn record(&self, read: DbRead) {
+        self.reads.borrow_mut().push(read);
+    }
+}
+
+impl<DB: Database> Database for RecordingDB<DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let account = self.db.basic(address)?;
+        self.record(DbRead::Basic {
+            address,
+            account: account.clone(),
+        });
+        Ok(account)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        let code = self.db.code_by_hash(code_hash)?;
+        self.record(DbRead::CodeByHash {
+            code_hash,
+            code: code.clone(),
+        });
+        Ok(code)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        let value = self.db.storage(address, index)?;
+        self.record(DbRead::Storage {
+            address,
+            index,
+            value,
+        });
+        Ok(value)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        let hash = self.db.block_hash(number)?;
+        self.record(DbRead::BlockHash { number, hash });
+        Ok(hash)
+    }
+
+    fn get_token_ids(&self) -> Result<Vec<U256>, Self::Error> {
+        let token_ids = self.db.get_token_ids()?;
+        self.record(DbRead::TokenIds {
+            token_ids: token_ids.clone(),
+        });
+        Ok(token_ids)
+    }
+
+    fn is_token_id_valid(&self, token_id: U256) -> Result<bool, Self::Error> {
+        let valid = self.db.is_token_id_valid(token_id)?;
+        self.record(DbRead::IsTokenIdValid { token_id, valid });
+        Ok(valid)
+    }
+}
+
+impl<DB: DatabaseCommit> DatabaseCommit for RecordingDB<DB> {
+    fn commit(&mut self, changes: EvmState) {
+        self.db.commit(changes)
+    }
+}
+
+/// Errors of a [ReplayDB].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// All the recorded reads have been served.
+    Exhausted,
+    /// The read doesn't match the recorded read at the index, i.e. the execution diverged from
+    /// the recorded one.
+    Diverged { index: usize },
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReplayError {}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exhausted => write!(f, "all the recorded reads have been served"),
+            Self::Diverged { index } => {
+                write!(f, "the read doesn't match the recorded read {index}")
+            }
+        }
+    }
+}
+
+/// A database that serves the reads recorded by a [RecordingDB], in the order they were made.
+///
+/// The changes committed to it are discarded, as the reads that follow them are recorded too.
+#[derive(Debug, Default)]
+pub struct ReplayDB {
+    reads: Vec<DbRead>,
+    next: Cell<usize>,
+}
+
+impl ReplayDB {
+    /// Creates a database serving the reads.
+    pub fn new(reads: Vec<DbRead>) -> Self {
+        Self {
+            reads,
+            next: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of recorded reads that haven't been served yet.
+    pub fn remaining(&self) -> usize {
+        self.reads.len() - self.next.get()
+    }
+
+    /// Serves the next recorded read, if `respond` answers the query with it.
+    fn serve<T>(&self, respond: impl FnOnce(&DbRead) -> Option<T>) -> Result<T, ReplayError> {
+        let index = self.next.get();
+        let read = self.reads.get(index).ok_or(ReplayError::Exhausted)?;
+        let response = respond(read).ok_or(ReplayError::Diverged { index })?;
+        self.next.set(index + 1);
+        Ok(response)
+    }
+}
+
+impl Database for ReplayDB {
+    type Error = ReplayError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.serve(|read| match read {
+            DbRead::Basic {
+                address: recorded,
+                account,
+            } if *recorded == address => Some(account.clone()),
+            _ => None,
+        })
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.serve(|read| match read {
+            DbRead::CodeByHash {
+                code_hash: recorded,
+                code,
+            } if *recorded == code_hash => Some(code.clone()),
+            _ => None,
+        })
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.serve(|read| match read {
+            DbRead::Storage {
+                address: recorded_address,
+                index: recorded_index,
+                value,
+            } if *recorded_address == address && *recorded_index == index => Some(*value),
+            _ => None,
+        })
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.serve(|read| match read {
+            DbRead::BlockHash {
+                number: recorded,
+                hash,
+            } if *recorded == number => Some(*hash),
+            _ => None,
+        })
+    }
+
+    fn get_token_ids(&self) -> Result<Vec<U256>, Self::Error> {
+        self.serve(|read| match read {
+            DbRead::TokenIds { token_ids } => Some(token_ids.clone()),
+            _ => None,
+        })
+    }
+
+    fn is_token_id_valid(&self, token_id: U256) -> Result<bool, Self::Error> {
+        self.serve(|read| match read {
+            DbRead::IsTokenIdValid {
+                token_id: recorded,
+                valid,
+            } if *recorded == token_id => Some(*valid),
+            _ => None,
+        })
+    }
+}
+
+impl DatabaseCommit for ReplayDB {
+    fn commit(&mut self, _changes: EvmState) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryDB,
+        interpreter::opcode,
+        primitives::{
+            address, init_balances, EVMError, ResultAndState, TokenTransfer, TransactTo, TxEnv,
+            BASE_TOKEN_ID,
+        },
+        Evm,
+    };
+
+    #[test]
+    fn replays_recorded_transaction() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(5);
+        let mut db = InMemoryDB::default();
+        db.token_ids.push(token_id);
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        // loads slot 1 and stores it in slot 2
+        let code = [
+            opcode::PUSH1,
+            1,
+            opcode::SLOAD,
+            opcode::PUSH1,
+            2,
+            opcode::SSTORE,
+            opcode::STOP,
+        ];
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(code.to_vec().into())),
+                ..Default::default()
+            },
+        );
+        db.insert_account_storage(contract, U256::from(1), U256::from(7))
+            .unwrap();
+        let tx = TxEnv {
+            caller,
+            transact_to: TransactTo::Call(contract),
+            gas_limit: 100_000,
+            transferred_tokens: vec![TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(10),
+            }],
+            ..Default::default()
+        };
+
+        let mut evm = Evm::builder()
+            .with_db(RecordingDB::new(db))
+            .with_tx_env(tx.clone())
+            .build();
+        let recorded = evm.transact().unwrap();
+        let (_, reads) = evm.into_context().evm.inner.db.into_parts();
+        assert!(reads.contains(&DbRead::Storage {
+            address: contract,
+            index: U256::from(1),
+            value: U256::from(7),
+        }));
+
+        let replay = |tx: TxEnv| {
+            Evm::builder()
+                .with_db(ReplayDB::new(reads.clone()))
+                .with_tx_env(tx)
+                .build()
+                .transact()
+        };
+        let ResultAndState { result, state } = replay(tx.clone()).unwrap();
+        assert_eq!(result, recorded.result);
+        assert_eq!(state, recorded.state);
+
+        // a transaction reading other accounts can't be replayed
+        let other = TxEnv {
+            caller: address!("00000000000000000000000000000000000000cc"),
+            ..tx
+        };
+        assert!(matches!(
+            replay(other),
+            Err(EVMError::Database(ReplayError::Diverged { .. }))
+        ));
+    }
+}
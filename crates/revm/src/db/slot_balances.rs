@@ -0,0 +1,301 @@
+//! Storage of the Native Token balances as storage slots, for databases that can't extend the
+//! account with its balances.
+//!
+//! A [SlotBalancesDB] keeps the balances of every account in the storage of the
+//! [BALANCES_ADDRESS] system account, laid out like the storage of this Solidity contract:
+//!
+//! ```solidity
+//! contract Balances {
+//!     // slot 0
+//!     mapping(address holder => mapping(uint256 tokenId => uint256 balance)) balances;
+//!     // slot 1, the IDs of the tokens with a nonzero balance, sorted
+//!     mapping(address holder => uint256[] tokenIds) heldTokenIds;
+//! }
+//! ```
+//!
+//! The accounts of the wrapped database have no balances, so the database and the tries of a node
+//! only need to store accounts and storage slots, as in Ethereum. No code runs at the system
+//! account, so its storage can't be written by contracts.
+use super::{Database, DatabaseCommit};
+use crate::primitives::{
+    keccak256, Account, AccountInfo, Address, Bytecode, EvmState, EvmStorage, EvmStorageSlot,
+    HashMap, TokenBalances, B256, U256,
+};
+use std::vec::Vec;
+
+/// The system account whose storage holds the balances.
+pub const BALANCES_ADDRESS: Address = crate::sablier::u64_to_prefixed_address(0xba);
+
+/// The slot of the `balances` mapping.
+const BALANCES_SLOT: U256 = U256::ZERO;
+
+/// The slot of the `heldTokenIds` mapping.
+const HELD_TOKEN_IDS_SLOT: U256 = U256::from_limbs([1, 0, 0, 0]);
+
+/// Returns the slot of the value of the key in the Solidity mapping at the slot.
+fn mapping_slot(key: B256, slot: U256) -> U256 {
+    let mut preimage = [0; 64];
+    preimage[..32].copy_from_slice(key.as_slice());
+    preimage[32..].copy_from_slice(&slot.to_be_bytes::<32>());
+    keccak256(preimage).into()
+}
+
+/// Returns the slot of the balance of the token of the holder, `balances[holder][token_id]`.
+pub fn balance_slot(holder: Address, token_id: U256) -> U256 {
+    let holder_slot = mapping_slot(holder.into_word(), BALANCES_SLOT);
+    mapping_slot(token_id.into(), holder_slot)
+}
+
+/// Returns the slot of the number of tokens held by the holder, `heldTokenIds[holder].length`.
+pub fn held_token_ids_slot(holder: Address) -> U256 {
+    mapping_slot(holder.into_word(), HELD_TOKEN_IDS_SLOT)
+}
+
+/// Returns the slot of the ID of the token held by the holder at the index,
+/// `heldTokenIds[holder][index]`.
+pub fn held_token_id_slot(holder: Address, index: usize) -> U256 {
+    let elements_slot: U256 = keccak256(held_token_ids_slot(holder).to_be_bytes::<32>()).into();
+    elements_slot.wrapping_add(U256::from(index))
+}
+
+/// The balances of an account, as they are stored.
+#[derive(Debug, Default)]
+struct StoredBalances {
+    /// The IDs of the held tokens, in the order they are stored.
+    token_ids: Vec<U256>,
+    balances: TokenBalances,
+}
+
+/// A database wrapper storing the balances of the accounts as storage slots of the
+/// [BALANCES_ADDRESS] system account in the wrapped database.
+///
+/// The balances are read when the account is loaded, and the changed balances are committed as
+/// storage changes of the system account.
+#[derive(Debug, Default)]
+pub struct SlotBalancesDB<DB> {
+    db: DB,
+    /// The stored balances of the loaded accounts, that the committed balances are compared to.
+    loaded: HashMap<Address, StoredBalances>,
+}
+
+impl<DB> SlotBalancesDB<DB> {
+    /// Wraps the database.
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            loaded: HashMap::default(),
+        }
+    }
+
+    /// Returns the wrapped database.
+    pub fn inner(&self) -> &DB {
+        &self.db
+    }
+
+    /// Consumes the wrapper, returning the wrapped database.
+    pub fn into_inner(self) -> DB {
+        self.db
+    }
+}
+
+impl<DB: Database> SlotBalancesDB<DB> {
+    /// Reads the balances of the holder from the storage of the system account.
+    fn read_balances(&mut self, holder: Address) -> Result<StoredBalances, DB::Error> {
+        let count = self
+            .db
+            .storage(BALANCES_ADDRESS, held_token_ids_slot(holder))?;
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        let mut stored = StoredBalances::default();
+        for index in 0..count {
+            let token_id = self
+                .db
+                .storage(BALANCES_ADDRESS, held_token_id_slot(holder, index))?;
+            let balance = self
+                .db
+                .storage(BALANCES_ADDRESS, balance_slot(holder, token_id))?;
+            stored.token_ids.push(token_id);
+            stored.balances.insert(token_id, balance);
+        }
+        Ok(stored)
+    }
+}
+
+impl<DB: Database> Database for SlotBalancesDB<DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let Some(mut info) = self.db.basic(address)? else {
+            self.loaded.insert(address, StoredBalances::default());
+            return Ok(None);
+        };
+        let stored = self.read_balances(address)?;
+        info.balances.clone_from(&stored.balances);
+        self.loaded.insert(address, stored);
+        Ok(Some(info))
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.db.code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.db.storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.db.block_hash(number)
+    }
+
+    fn get_token_ids(&self) -> Result<Vec<U256>, Self::Error> {
+        self.db.get_token_ids()
+    }
+
+    fn is_token_id_valid(&self, token_id: U256) -> Result<bool, Self::Error> {
+        self.db.is_token_id_valid(token_id)
+    }
+}
+
+impl<DB: DatabaseCommit> DatabaseCommit for SlotBalancesDB<DB> {
+    fn commit(&mut self, mut changes: EvmState) {
+        let mut slots = EvmStorage::default();
+        let mut change = |slot: U256, original: U256, present: U256| {
+            if original != present {
+                slots.insert(slot, EvmStorageSlot::new_changed(original, present));
+            }
+        };
+
+        for (address, account) in changes.accounts.iter_mut() {
+            if !account.is_touched() {
+                continue;
+            }
+            let balances = core::mem::take(&mut account.info.balances);
+            let mut token_ids: Vec<U256> = if account.is_selfdestructed() {
+                Vec::new()
+            } else {
+                balances
+                    .iter()
+                    .filter(|(_, balance)| !balance.is_zero())
+                    .map(|(token_id, _)| *token_id)
+                    .collect()
+            };
+            token_ids.sort_unstable();
+
+            let stored = self.loaded.entry(*address).or_default();
+            for token_id in stored.balances.keys().chain(&token_ids) {
+                let original = stored.balances.get(token_id).copied().unwrap_or_default();
+                let present = if token_ids.binary_search(token_id).is_ok() {
+                    balances[token_id]
+                } else {
+                    U256::ZERO
+                };
+                change(balance_slot(*address, *token_id), original, present);
+            }
+            if stored.token_ids != token_ids {
+                change(
+                    held_token_ids_slot(*address),
+                    U256::from(stored.token_ids.len()),
+                    U256::from(token_ids.len()),
+                );
+                for index in 0..stored.token_ids.len().max(token_ids.len()) {
+                    change(
+                        held_token_id_slot(*address, index),
+                        stored.token_ids.get(index).copied().unwrap_or_default(),
+                        token_ids.get(index).copied().unwrap_or_default(),
+                    );
+                }
+            }
+
+            stored.balances = token_ids
+                .iter()
+                .map(|token_id| (*token_id, balances[token_id]))
+                .collect();
+            stored.token_ids = token_ids;
+        }
+
+        if !slots.is_empty() {
+            let system_account = changes
+                .accounts
+                .entry(BALANCES_ADDRESS)
+                .or_insert_with(|| Account::from(AccountInfo::default()));
+            system_account.mark_touch();
+            system_account.storage.extend(slots);
+        }
+        self.db.commit(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryDB,
+        primitives::{address, init_balances, TokenTransfer, TransactTo, BASE_TOKEN_ID},
+        Evm,
+    };
+
+    #[test]
+    fn stores_balances_as_slots() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let receiver = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(7);
+        let mut db = SlotBalancesDB::new(InMemoryDB::default());
+        db.db.insert_account_info(caller, AccountInfo::default());
+        // the caller holds both tokens
+        let mut balances = init_balances(U256::from(1_000_000));
+        balances.insert(token_id, U256::from(50));
+        let mut genesis = EvmState::default();
+        genesis.accounts.insert(caller, {
+            let mut account = Account::from(AccountInfo {
+                balances,
+                ..Default::default()
+            });
+            account.mark_touch();
+            account
+        });
+        db.commit(genesis);
+        db.db.token_ids = vec![BASE_TOKEN_ID, token_id];
+        assert!(db.db.accounts[&caller].info.balances.is_empty());
+        assert_eq!(
+            db.db
+                .storage(BALANCES_ADDRESS, held_token_ids_slot(caller))
+                .unwrap(),
+            U256::from(2)
+        );
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(receiver);
+                tx.gas_limit = 100_000;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: token_id,
+                    amount: U256::from(50),
+                }];
+            })
+            .build();
+        assert!(evm.transact_commit().unwrap().is_success());
+
+        let mut db = evm.into_context().evm.inner.db;
+        let storage = |db: &mut SlotBalancesDB<InMemoryDB>, slot| {
+            db.db.storage(BALANCES_ADDRESS, slot).unwrap()
+        };
+        assert_eq!(
+            storage(&mut db, balance_slot(receiver, token_id)),
+            U256::from(50)
+        );
+        assert_eq!(storage(&mut db, balance_slot(caller, token_id)), U256::ZERO);
+        // the caller no longer holds the token, but still holds the base token
+        assert_eq!(storage(&mut db, held_token_ids_slot(caller)), U256::from(1));
+        assert_eq!(storage(&mut db, held_token_id_slot(caller, 1)), U256::ZERO);
+        assert_eq!(storage(&mut db, held_token_id_slot(receiver, 0)), token_id);
+
+        // the balances are read back from the slots
+        let mut db = SlotBalancesDB::new(db.into_inner());
+        let receiver_info = db.basic(receiver).unwrap().unwrap();
+        assert_eq!(receiver_info.get_balance(token_id), U256::from(50));
+        let caller_info = db.basic(caller).unwrap().unwrap();
+        assert_eq!(caller_info.get_balance(token_id), U256::ZERO);
+        assert!(caller_info.get_balance(BASE_TOKEN_ID) > U256::ZERO);
+    }
+}
@@ -0,0 +1,600 @@
+//! Versioned binary snapshots of the state, with its Native Token registry.
+//!
+//! A snapshot holds the accounts, their storage and token balances, the contract code and the
+//! supported token IDs. It is encoded as:
+//!
+//! ```text
+//! SNAPSHOT_MAGIC || SNAPSHOT_VERSION || rlp([
+//!     [token_id, ...],
+//!     [[address, nonce, code_hash, [[token_id, balance], ...], [[slot, value], ...]], ...],
+//!     [[code_hash, code], ...]
+//! ])
+//! ```
+//!
+//! Accounts, balances, storage slots and contracts are sorted, so that the same state is always
+//! encoded to the same bytes. Zero balances and storage values are left out.
+use super::{BundleState, CacheDB, DatabaseIter, InMemoryDB, PlainAccount};
+use crate::primitives::{
+    AccountInfo, Address, Bytecode, Bytes, Eof, HashMap, B256, KECCAK_EMPTY, U256,
+};
+use alloy_rlp::{length_of_length, list_length, BufMut, Decodable, Encodable, Header};
+use core::fmt;
+use std::{collections::BTreeMap, vec::Vec};
+
+/// The magic bytes a snapshot starts with.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"SABS";
+
+/// The version of the snapshot format written by [StateSnapshot::encode].
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// The magic bytes of EOF bytecode.
+const EOF_MAGIC: [u8; 2] = [0xEF, 0x00];
+
+/// A snapshot of the state and of the supported token IDs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateSnapshot {
+    /// The supported token IDs, in the order they were added.
+    pub token_ids: Vec<U256>,
+    /// The existing accounts. The code of an account is in [contracts](Self::contracts),
+    /// by its code hash.
+    pub accounts: BTreeMap<Address, PlainAccount>,
+    /// The contract code, by code hash.
+    pub contracts: BTreeMap<B256, Bytecode>,
+}
+
+/// Errors that can occur when decoding a [StateSnapshot].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The input doesn't start with [SNAPSHOT_MAGIC].
+    NotASnapshot,
+    /// The snapshot was written in a version of the format that isn't supported.
+    UnsupportedVersion(u8),
+    /// The payload is not valid RLP.
+    Rlp(alloy_rlp::Error),
+    /// The code doesn't hash to its code hash, or is invalid EOF.
+    InvalidCode(B256),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SnapshotError {}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotASnapshot => write!(f, "not a state snapshot"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported snapshot version {version}, expected {SNAPSHOT_VERSION}"
+            ),
+            Self::Rlp(err) => write!(f, "invalid RLP payload: {err}"),
+            Self::InvalidCode(code_hash) => write!(f, "invalid code of code hash {code_hash}"),
+        }
+    }
+}
+
+impl From<alloy_rlp::Error> for SnapshotError {
+    fn from(err: alloy_rlp::Error) -> Self {
+        Self::Rlp(err)
+    }
+}
+
+impl StateSnapshot {
+    /// Takes a snapshot of the accounts cached by the database.
+    ///
+    /// Accounts that are not cached, such as those only in the wrapped database, are not part of
+    /// the snapshot.
+    pub fn from_cache_db<ExtDB>(db: &CacheDB<ExtDB>) -> Self {
+        let mut snapshot = Self {
+            token_ids: db.token_ids.clone(),
+            ..Default::default()
+        };
+        for (address, account) in &db.accounts {
+            if let Some(info) = account.info() {
+                snapshot.insert_account(*address, info, account.storage.clone());
+            }
+        }
+        for code in db.contracts.values() {
+            snapshot.insert_code(code.clone());
+        }
+        snapshot
+    }
+
+    /// Takes a snapshot of the whole state of the database.
+    ///
+    /// Unlike [from_cache_db](Self::from_cache_db), the accounts of the databases wrapped by the
+    /// database are part of the snapshot.
+    pub fn from_db<DB: DatabaseIter>(db: &DB) -> Result<Self, DB::Error> {
+        let mut snapshot = Self {
+            token_ids: db.get_token_ids_ref()?,
+            ..Default::default()
+        };
+        for (address, mut info) in db.accounts_iter()? {
+            if info.code.is_none() && !info.is_empty_code_hash() {
+                info.code = Some(db.code_by_hash_ref(info.code_hash)?);
+            }
+            let storage = db.storage_iter(address)?.collect();
+            snapshot.insert_account(address, info, storage);
+        }
+        Ok(snapshot)
+    }
+
+    /// Takes a snapshot of the present state of the bundle, which supports the token IDs.
+    ///
+    /// The bundle only holds the accounts changed by the executed blocks, so the snapshot is the
+    /// whole state only if the blocks were executed on an empty one.
+    pub fn from_bundle_state(bundle: &BundleState, token_ids: Vec<U256>) -> Self {
+        let mut snapshot = Self {
+            token_ids,
+            ..Default::default()
+        };
+        for (address, account) in &bundle.state {
+            if let Some(info) = &account.info {
+                let storage = account
+                    .storage
+                    .iter()
+                    .map(|(slot, value)| (*slot, value.present_value))
+                    .collect();
+                snapshot.insert_account(*address, info.clone(), storage);
+            }
+        }
+        for code in bundle.contracts.values() {
+            snapshot.insert_code(code.clone());
+        }
+        snapshot
+    }
+
+    fn insert_account(
+        &mut self,
+        address: Address,
+        mut info: AccountInfo,
+        storage: HashMap<U256, U256>,
+    ) {
+        if let Some(code) = info.code.take() {
+            self.insert_code(code);
+        }
+        info.balances.retain(|_, balance| *balance != U256::ZERO);
+        let storage = storage
+            .into_iter()
+            .filter(|(_, value)| *value != U256::ZERO)
+            .collect();
+        self.accounts
+            .insert(address, PlainAccount { info, storage });
+    }
+
+    fn insert_code(&mut self, code: Bytecode) {
+        if !code.is_empty() {
+            self.contracts.insert(code.hash_slow(), code);
+        }
+    }
+
+    /// Returns the encoding of the snapshot.
+    ///
+    /// See the [module documentation](self) for the format.
+    pub fn encode(&self) -> Vec<u8> {
+        let accounts: Vec<_> = self
+            .accounts
+            .iter()
+            .map(|(address, account)| SnapshotAccount::new(*address, account))
+            .collect();
+        let contracts: Vec<_> = self
+            .contracts
+            .iter()
+            .map(|(code_hash, code)| SnapshotContract {
+                code_hash: *code_hash,
+                code: code.original_bytes(),
+            })
+            .collect();
+
+        let payload_length =
+            list_length(&self.token_ids) + list_length(&accounts) + list_length(&contracts);
+        let mut out = Vec::with_capacity(
+            SNAPSHOT_MAGIC.len() + 1 + payload_length + length_of_length(payload_length),
+        );
+        out.extend_from_slice(&SNAPSHOT_MAGIC);
+        out.put_u8(SNAPSHOT_VERSION);
+        Header {
+            list: true,
+            payload_length,
+        }
+        .encode(&mut out);
+        alloy_rlp::encode_list::<_, U256>(&self.token_ids, &mut out);
+        alloy_rlp::encode_list::<_, SnapshotAccount>(&accounts, &mut out);
+        alloy_rlp::encode_list::<_, SnapshotContract>(&contracts, &mut out);
+        out
+    }
+
+    /// Decodes a snapshot written by [StateSnapshot::encode].
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let bytes = bytes
+            .strip_prefix(&SNAPSHOT_MAGIC)
+            .ok_or(SnapshotError::NotASnapshot)?;
+        let (&version, mut buf) = bytes.split_first().ok_or(SnapshotError::NotASnapshot)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut payload = Header::decode_bytes(&mut buf, true)?;
+        ensure_consumed(buf)?;
+        let token_ids = Vec::<U256>::decode(&mut payload)?;
+        let accounts = Vec::<SnapshotAccount>::decode(&mut payload)?;
+        let contracts = Vec::<SnapshotContract>::decode(&mut payload)?;
+        ensure_consumed(payload)?;
+
+        let contracts = contracts
+            .into_iter()
+            .map(|contract| Ok((contract.code_hash, contract.bytecode()?)))
+            .collect::<Result<_, SnapshotError>>()?;
+        let accounts = accounts
+            .into_iter()
+            .map(SnapshotAccount::into_account)
+            .collect();
+        Ok(Self {
+            token_ids,
+            accounts,
+            contracts,
+        })
+    }
+
+    /// Loads the snapshot into an in-memory database.
+    pub fn into_db(self) -> InMemoryDB {
+        let mut db = InMemoryDB {
+            token_ids: self.token_ids,
+            ..Default::default()
+        };
+        for (address, account) in self.accounts {
+            let mut info = account.info;
+            info.code = self.contracts.get(&info.code_hash).cloned();
+            db.insert_account_info(address, info);
+            db.accounts.get_mut(&address).unwrap().storage = account.storage;
+        }
+        db.contracts.extend(self.contracts);
+        db
+    }
+
+    /// Converts the snapshot into a bundle whose present state is the snapshot, e.g. to be
+    /// written to a database as the state of a block.
+    ///
+    /// The token IDs are not part of the bundle.
+    pub fn into_bundle_state(self) -> BundleState {
+        let mut builder = BundleState::builder(0..=0);
+        for (address, account) in self.accounts {
+            let storage = account
+                .storage
+                .into_iter()
+                .map(|(slot, value)| (slot, (U256::ZERO, value)))
+                .collect();
+            builder = builder
+                .state_present_account_info(address, account.info)
+                .state_storage(address, storage);
+        }
+        for (code_hash, code) in self.contracts {
+            builder = builder.contract(code_hash, code);
+        }
+        builder.build()
+    }
+
+    /// Writes the encoding of the snapshot to the writer.
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&self.encode())
+    }
+
+    /// Reads a snapshot from the reader, until its end.
+    ///
+    /// A snapshot that can't be decoded fails with [InvalidData](std::io::ErrorKind::InvalidData).
+    #[cfg(feature = "std")]
+    pub fn read_from<R: std::io::Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::decode(&bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+}
+
+fn ensure_consumed(payload: &[u8]) -> alloy_rlp::Result<()> {
+    if payload.is_empty() {
+        Ok(())
+    } else {
+        Err(alloy_rlp::Error::Custom("unexpected trailing data in list"))
+    }
+}
+
+/// A `[key, value]` pair of a token balance or of a storage slot.
+struct Entry(U256, U256);
+
+impl Entry {
+    fn into_pair(self) -> (U256, U256) {
+        (self.0, self.1)
+    }
+
+    fn payload_length(&self) -> usize {
+        self.0.length() + self.1.length()
+    }
+}
+
+impl Encodable for Entry {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + length_of_length(payload_length)
+    }
+}
+
+impl Decodable for Entry {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let entry = Self(U256::decode(&mut payload)?, U256::decode(&mut payload)?);
+        ensure_consumed(payload)?;
+        Ok(entry)
+    }
+}
+
+/// Returns the non-zero entries of the map, sorted by key.
+fn sorted_entries(map: &HashMap<U256, U256>) -> Vec<Entry> {
+    let mut entries: Vec<_> = map
+        .iter()
+        .filter(|(_, value)| **value != U256::ZERO)
+        .map(|(key, value)| Entry(*key, *value))
+        .collect();
+    entries.sort_unstable_by_key(|entry| entry.0);
+    entries
+}
+
+/// An account, as encoded in a snapshot.
+struct SnapshotAccount {
+    address: Address,
+    nonce: u64,
+    code_hash: B256,
+    balances: Vec<Entry>,
+    storage: Vec<Entry>,
+}
+
+impl SnapshotAccount {
+    fn new(address: Address, account: &PlainAccount) -> Self {
+        Self {
+            address,
+            nonce: account.info.nonce,
+            code_hash: account.info.code_hash,
+            balances: sorted_entries(&account.info.balances),
+            storage: sorted_entries(&account.storage),
+        }
+    }
+
+    fn into_account(self) -> (Address, PlainAccount) {
+        let info = AccountInfo {
+            balances: self.balances.into_iter().map(Entry::into_pair).collect(),
+            nonce: self.nonce,
+            code_hash: self.code_hash,
+            code: None,
+        };
+        let storage = self.storage.into_iter().map(Entry::into_pair).collect();
+        (self.address, PlainAccount { info, storage })
+    }
+
+    fn payload_length(&self) -> usize {
+        self.address.length()
+            + self.nonce.length()
+            + self.code_hash.length()
+            + list_length(&self.balances)
+            + list_length(&self.storage)
+    }
+}
+
+impl Encodable for SnapshotAccount {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+        self.address.encode(out);
+        self.nonce.encode(out);
+        self.code_hash.encode(out);
+        alloy_rlp::encode_list::<_, Entry>(&self.balances, out);
+        alloy_rlp::encode_list::<_, Entry>(&self.storage, out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + length_of_length(payload_length)
+    }
+}
+
+impl Decodable for SnapshotAccount {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let account = Self {
+            address: Address::decode(&mut payload)?,
+            nonce: u64::decode(&mut payload)?,
+            code_hash: B256::decode(&mut payload)?,
+            balances: Vec::<Entry>::decode(&mut payload)?,
+            storage: Vec::<Entry>::decode(&mut payload)?,
+        };
+        ensure_consumed(payload)?;
+        Ok(account)
+    }
+}
+
+/// A contract, as encoded in a snapshot.
+struct SnapshotContract {
+    code_hash: B256,
+    code: Bytes,
+}
+
+impl SnapshotContract {
+    /// Returns the bytecode of the contract, checking it against its code hash.
+    fn bytecode(self) -> Result<Bytecode, SnapshotError> {
+        let code = if self.code.starts_with(&EOF_MAGIC) {
+            Eof::decode(self.code)
+                .map(Bytecode::Eof)
+                .map_err(|_| SnapshotError::InvalidCode(self.code_hash))?
+        } else {
+            Bytecode::new_raw(self.code)
+        };
+        if code.hash_slow() != self.code_hash || self.code_hash == KECCAK_EMPTY {
+            return Err(SnapshotError::InvalidCode(self.code_hash));
+        }
+        Ok(code)
+    }
+
+    fn payload_length(&self) -> usize {
+        self.code_hash.length() + self.code.length()
+    }
+}
+
+impl Encodable for SnapshotContract {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+        self.code_hash.encode(out);
+        self.code.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + length_of_length(payload_length)
+    }
+}
+
+impl Decodable for SnapshotContract {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = Header::decode_bytes(buf, true)?;
+        let contract = Self {
+            code_hash: B256::decode(&mut payload)?,
+            code: Bytes::decode(&mut payload)?,
+        };
+        ensure_consumed(payload)?;
+        Ok(contract)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{address, init_balances, BASE_TOKEN_ID};
+
+    const HOLDER: Address = address!("00000000000000000000000000000000000000aa");
+    const CONTRACT: Address = address!("00000000000000000000000000000000000000bb");
+
+    fn db() -> InMemoryDB {
+        let mut db = InMemoryDB::default();
+        db.token_ids.push(U256::from(1));
+        let mut balances = init_balances(U256::from(1_000));
+        balances.insert(U256::from(1), U256::from(7));
+        db.insert_account_info(
+            HOLDER,
+            AccountInfo {
+                balances,
+                nonce: 3,
+                ..Default::default()
+            },
+        );
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x01, 0x00]));
+        db.insert_account_info(
+            CONTRACT,
+            AccountInfo::new(init_balances(U256::ZERO), 1, code.hash_slow(), code),
+        );
+        db.insert_account_storage(CONTRACT, U256::from(1), U256::from(2))
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn roundtrips_cache_db() {
+        let snapshot = StateSnapshot::from_cache_db(&db());
+        let bytes = snapshot.encode();
+        assert_eq!(&bytes[..4], &SNAPSHOT_MAGIC);
+        assert_eq!(StateSnapshot::decode(&bytes), Ok(snapshot.clone()));
+        // the encoding doesn't depend on the order of the hash maps
+        assert_eq!(StateSnapshot::from_cache_db(&db()).encode(), bytes);
+
+        let loaded = StateSnapshot::decode(&bytes).unwrap().into_db();
+        assert_eq!(loaded.token_ids, vec![BASE_TOKEN_ID, U256::from(1)]);
+        let holder = loaded.accounts[&HOLDER].info.clone();
+        assert_eq!(holder.nonce, 3);
+        assert_eq!(holder.get_balance(U256::from(1)), U256::from(7));
+        let contract = &loaded.accounts[&CONTRACT];
+        assert_eq!(contract.storage[&U256::from(1)], U256::from(2));
+        assert_eq!(
+            loaded.contracts[&contract.info.code_hash].original_byte_slice(),
+            &[0x60, 0x01, 0x00]
+        );
+
+        let bundle = snapshot.clone().into_bundle_state();
+        assert_eq!(
+            StateSnapshot::from_bundle_state(&bundle, snapshot.token_ids.clone()),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn snapshots_wrapped_databases() {
+        let mut db = CacheDB::new(db());
+        let snapshot = StateSnapshot::from_db(&db).unwrap();
+        let wrapped = StateSnapshot::from_cache_db(&db.db);
+        assert_eq!(snapshot.accounts, wrapped.accounts);
+        assert_eq!(snapshot.contracts, wrapped.contracts);
+        // nothing is cached yet
+        assert!(StateSnapshot::from_cache_db(&db).accounts.is_empty());
+
+        let other = address!("00000000000000000000000000000000000000cc");
+        db.insert_account_info(
+            other,
+            AccountInfo {
+                balances: init_balances(U256::from(5)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_storage(HOLDER, U256::from(5), U256::from(6))
+            .unwrap();
+        db.replace_account_storage(
+            CONTRACT,
+            HashMap::from_iter([(U256::from(2), U256::from(3))]),
+        )
+        .unwrap();
+        let snapshot = StateSnapshot::from_db(&db).unwrap();
+        assert_eq!(snapshot.accounts.len(), 3);
+        assert_eq!(snapshot.accounts[&HOLDER].info.nonce, 3);
+        assert_eq!(
+            snapshot.accounts[&HOLDER].storage,
+            HashMap::from_iter([(U256::from(5), U256::from(6))])
+        );
+        assert_eq!(
+            snapshot.accounts[&CONTRACT].storage,
+            HashMap::from_iter([(U256::from(2), U256::from(3))])
+        );
+        assert_eq!(snapshot.contracts.len(), 1);
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let mut bytes = StateSnapshot::from_cache_db(&db()).encode();
+        assert_eq!(
+            StateSnapshot::decode(b"{}"),
+            Err(SnapshotError::NotASnapshot)
+        );
+        bytes[4] = SNAPSHOT_VERSION + 1;
+        assert_eq!(
+            StateSnapshot::decode(&bytes),
+            Err(SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1))
+        );
+        bytes[4] = SNAPSHOT_VERSION;
+        bytes.push(0);
+        assert!(matches!(
+            StateSnapshot::decode(&bytes),
+            Err(SnapshotError::Rlp(_))
+        ));
+    }
+}
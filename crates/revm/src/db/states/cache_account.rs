@@ -239,7 +239,7 @@ impl CacheAccount {
         }
 
         let (_, transition) = self.account_info_change(|info| {
-            info.increase_balance(token_id, U256::from(value));
+            info.increase_balance_saturating(token_id, U256::from(value));
         });
         Some(transition)
     }
@@ -0,0 +1,304 @@
+use super::{
+    in_memory_db::{AccountState, CacheDB, DbAccount},
+    Database, DatabaseCommit, DatabaseRef,
+};
+use crate::primitives::{hash_map::Entry, AccountInfo, Address, Bytecode, EvmState, B256, U256};
+use core::mem;
+use std::{sync::RwLock, vec::Vec};
+
+/// A thread-safe [CacheDB] that can be shared between [Evm](crate::Evm)s running on different
+/// threads.
+///
+/// The cache is behind a [RwLock] and is filled through [DatabaseRef], so a shared reference
+/// (e.g. an [Arc](std::sync::Arc)) can be given to every [Evm](crate::Evm) with
+/// [`EvmBuilder::with_ref_db`](crate::EvmBuilder::with_ref_db). The wrapped database is read
+/// without holding the lock.
+///
+/// Changes are committed with [SyncCacheDB::commit_ref]. The token IDs of committed states are
+/// merged with the cached ones, so a token minted on one thread is not lost when another thread
+/// commits a state that was loaded before the mint.
+#[derive(Debug, Default)]
+pub struct SyncCacheDB<ExtDB> {
+    cache: RwLock<CacheDB<()>>,
+    db: ExtDB,
+}
+
+impl<ExtDB> SyncCacheDB<ExtDB> {
+    /// Wraps the database with an empty cache.
+    pub fn new(db: ExtDB) -> Self {
+        Self {
+            cache: RwLock::new(CacheDB::new(())),
+            db,
+        }
+    }
+
+    /// Returns the wrapped database.
+    pub fn inner(&self) -> &ExtDB {
+        &self.db
+    }
+
+    /// Insert account info but not override storage
+    pub fn insert_account_info(&self, address: Address, info: AccountInfo) {
+        self.cache
+            .write()
+            .unwrap()
+            .insert_account_info(address, info);
+    }
+
+    /// Commits the changes to the cache through a shared reference.
+    pub fn commit_ref(&self, mut changes: EvmState) {
+        let committed_token_ids = mem::take(&mut changes.token_ids);
+
+        let mut cache = self.cache.write().unwrap();
+        let mut token_ids = mem::take(&mut cache.token_ids);
+        cache.commit(changes);
+        for token_id in committed_token_ids {
+            if !token_ids.contains(&token_id) {
+                token_ids.push(token_id);
+            }
+        }
+        cache.token_ids = token_ids;
+    }
+}
+
+impl<ExtDB: DatabaseRef> SyncCacheDB<ExtDB> {
+    /// insert account storage without overriding account info
+    pub fn insert_account_storage(
+        &self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), ExtDB::Error> {
+        self.load_account(address)?;
+        let mut cache = self.cache.write().unwrap();
+        cache
+            .accounts
+            .get_mut(&address)
+            .unwrap()
+            .storage
+            .insert(slot, value);
+        Ok(())
+    }
+
+    /// Caches the account, loading it from the wrapped database if it isn't cached yet.
+    fn load_account(&self, address: Address) -> Result<Option<AccountInfo>, ExtDB::Error> {
+        if let Some(account) = self.cache.read().unwrap().accounts.get(&address) {
+            return Ok(account.info());
+        }
+        let account: DbAccount = self.db.basic_ref(address)?.into();
+        // another thread may have cached or committed the account in the meantime.
+        Ok(self
+            .cache
+            .write()
+            .unwrap()
+            .accounts
+            .entry(address)
+            .or_insert(account)
+            .info())
+    }
+}
+
+impl<ExtDB> DatabaseCommit for SyncCacheDB<ExtDB> {
+    fn commit(&mut self, changes: EvmState) {
+        self.commit_ref(changes)
+    }
+}
+
+impl<ExtDB: DatabaseRef> DatabaseRef for SyncCacheDB<ExtDB> {
+    type Error = ExtDB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.load_account(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if let Some(code) = self.cache.read().unwrap().contracts.get(&code_hash) {
+            return Ok(code.clone());
+        }
+        let code = self.db.code_by_hash_ref(code_hash)?;
+        Ok(self
+            .cache
+            .write()
+            .unwrap()
+            .contracts
+            .entry(code_hash)
+            .or_insert(code)
+            .clone())
+    }
+
+    /// Get the value in an account's storage slot, caching the account if it isn't cached yet.
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if self.load_account(address)?.is_none() {
+            return Ok(U256::ZERO);
+        }
+        {
+            let cache = self.cache.read().unwrap();
+            let account = &cache.accounts[&address];
+            if let Some(value) = account.storage.get(&index) {
+                return Ok(*value);
+            }
+            if matches!(
+                account.account_state,
+                AccountState::StorageCleared | AccountState::NotExisting
+            ) {
+                return Ok(U256::ZERO);
+            }
+        }
+        let value = self.db.storage_ref(address, index)?;
+        let mut cache = self.cache.write().unwrap();
+        let account = cache.accounts.get_mut(&address).unwrap();
+        match account.storage.entry(index) {
+            Entry::Occupied(entry) => Ok(*entry.get()),
+            // the storage may have been cleared by a commit in the meantime.
+            Entry::Vacant(_) if account.account_state.is_storage_cleared() => Ok(U256::ZERO),
+            Entry::Vacant(entry) => Ok(*entry.insert(value)),
+        }
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        if let Some(hash) = self.cache.read().unwrap().block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let hash = self.db.block_hash_ref(number)?;
+        self.cache
+            .write()
+            .unwrap()
+            .block_hashes
+            .insert(number, hash);
+        Ok(hash)
+    }
+
+    fn get_token_ids_ref(&self) -> Result<Vec<U256>, Self::Error> {
+        Ok(self.cache.read().unwrap().token_ids.clone())
+    }
+
+    fn is_token_id_valid_ref(&self, token_id: U256) -> Result<bool, Self::Error> {
+        Ok(self.cache.read().unwrap().token_ids.contains(&token_id))
+    }
+}
+
+impl<ExtDB: DatabaseRef> Database for SyncCacheDB<ExtDB> {
+    type Error = ExtDB::Error;
+
+    #[inline]
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    #[inline]
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    #[inline]
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    #[inline]
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+
+    #[inline]
+    fn get_token_ids(&self) -> Result<Vec<U256>, Self::Error> {
+        self.get_token_ids_ref()
+    }
+
+    #[inline]
+    fn is_token_id_valid(&self, token_id: U256) -> Result<bool, Self::Error> {
+        self.is_token_id_valid_ref(token_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::EmptyDB,
+        primitives::{address, init_balances, TokenTransfer, TransactTo, BASE_TOKEN_ID},
+        Evm,
+    };
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn shared_between_threads() {
+        let senders = [
+            address!("00000000000000000000000000000000000000a1"),
+            address!("00000000000000000000000000000000000000a2"),
+        ];
+        let recipients = [
+            address!("00000000000000000000000000000000000000b1"),
+            address!("00000000000000000000000000000000000000b2"),
+        ];
+        let db = Arc::new(SyncCacheDB::new(EmptyDB::default()));
+        for sender in senders {
+            db.insert_account_info(
+                sender,
+                AccountInfo {
+                    balances: init_balances(U256::from(1_000_000)),
+                    ..Default::default()
+                },
+            );
+        }
+
+        thread::scope(|scope| {
+            for (sender, recipient) in senders.into_iter().zip(recipients) {
+                let db = db.clone();
+                scope.spawn(move || {
+                    let mut evm = Evm::builder()
+                        .with_ref_db(db.clone())
+                        .modify_tx_env(|tx| {
+                            tx.caller = sender;
+                            tx.transact_to = TransactTo::Call(recipient);
+                            tx.gas_limit = 21_000;
+                            tx.transferred_tokens = vec![TokenTransfer {
+                                id: BASE_TOKEN_ID,
+                                amount: U256::from(10),
+                            }];
+                        })
+                        .build();
+                    let result = evm.transact().unwrap();
+                    assert!(result.result.is_success());
+                    db.commit_ref(result.state);
+                });
+            }
+        });
+
+        for recipient in recipients {
+            let info = db.basic_ref(recipient).unwrap().unwrap();
+            assert_eq!(info.get_base_balance(), U256::from(10));
+        }
+    }
+
+    #[test]
+    fn merges_committed_token_ids() {
+        let db = SyncCacheDB::new(EmptyDB::default());
+        db.commit_ref(EvmState {
+            token_ids: vec![BASE_TOKEN_ID, U256::from(1)],
+            ..Default::default()
+        });
+        // a state loaded before the first commit
+        db.commit_ref(EvmState {
+            token_ids: vec![BASE_TOKEN_ID, U256::from(2)],
+            ..Default::default()
+        });
+        assert_eq!(
+            db.get_token_ids_ref().unwrap(),
+            vec![BASE_TOKEN_ID, U256::from(1), U256::from(2)]
+        );
+        assert!(db.is_token_id_valid_ref(U256::from(1)).unwrap());
+        assert!(!db.is_token_id_valid_ref(U256::from(3)).unwrap());
+    }
+
+    #[test]
+    fn caches_storage() {
+        let address = address!("00000000000000000000000000000000000000cc");
+        let db = SyncCacheDB::new(EmptyDB::default());
+        db.insert_account_info(address, AccountInfo::default());
+        db.insert_account_storage(address, U256::from(1), U256::from(2))
+            .unwrap();
+        assert_eq!(db.storage_ref(address, U256::from(1)), Ok(U256::from(2)));
+        assert_eq!(db.storage_ref(address, U256::from(3)), Ok(U256::ZERO));
+    }
+}
@@ -1,17 +1,18 @@
 use crate::{
     builder::{EvmBuilder, HandlerStage, SetGenericStage},
-    db::{Database, DatabaseCommit, EmptyDB},
+    db::{CacheDB, Database, DatabaseCommit, DatabaseRef, EmptyDB},
     handler::Handler,
-    interpreter::{Host, InterpreterAction, SharedMemory},
+    interpreter::{Host, InterpreterAction},
     primitives::{
-        specification::SpecId, BlockEnv, CfgEnv, EVMError, EVMResult, EnvWithHandlerCfg,
-        ExecutionResult, HandlerCfg, ResultAndState, TransactTo, TxEnv,
+        specification::SpecId, Address, BalanceDelta, BlockEnv, BlockExecutionResult, Bytes,
+        CfgEnv, EVMError, EVMResult, EnvWithHandlerCfg, ExecutionResult, HandlerCfg, InvalidEnv,
+        InvalidTransaction, ResultAndState, Simulation, TransactTo, TxEnv, KECCAK_EMPTY, U256,
     },
-    Context, ContextWithHandlerCfg, Frame, FrameOrResult, FrameResult,
+    Context, ContextWithHandlerCfg, Frame, FrameOrResult, FrameResult, FrameStack, InnerEvmContext,
 };
-use core::fmt;
+use core::{fmt, mem};
 use revm_interpreter::{CallInputs, CreateInputs};
-use std::vec::Vec;
+use std::{boxed::Box, format, vec::Vec};
 
 /// EVM call stack limit.
 pub const CALL_STACK_LIMIT: u64 = 1024;
@@ -46,6 +47,42 @@ impl<EXT, DB: Database + DatabaseCommit> Evm<'_, EXT, DB> {
         self.context.evm.db.commit(state);
         Ok(result)
     }
+
+    /// Transact pre-verified transaction and commits its changes to the database in chunks of at
+    /// most `chunk_size` accounts, see [DatabaseCommit::commit_chunks].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn transact_commit_in_chunks(
+        &mut self,
+        chunk_size: usize,
+    ) -> Result<ExecutionResult, EVMError<DB::Error>> {
+        let ResultAndState { result, state } = self.transact()?;
+        self.context
+            .evm
+            .db
+            .commit_chunks(state.into_chunks(chunk_size));
+        Ok(result)
+    }
+
+    /// Executes the transactions of the block in the block environment, committing the changes
+    /// of every transaction before the next one is executed.
+    ///
+    /// The result holds the result of every transaction, and the tokens minted and burned by the
+    /// whole block. An invalid transaction fails the whole block, but the changes of the
+    /// transactions executed before it are already committed.
+    pub fn transact_block(
+        &mut self,
+        txs: impl IntoIterator<Item = TxEnv>,
+    ) -> Result<BlockExecutionResult, EVMError<DB::Error>> {
+        let mut block_result = BlockExecutionResult::default();
+        for tx in txs {
+            *self.tx_mut() = tx;
+            block_result.push(self.transact_commit()?);
+        }
+        Ok(block_result)
+    }
 }
 
 impl<'a> Evm<'a, (), EmptyDB> {
@@ -53,6 +90,55 @@ impl<'a> Evm<'a, (), EmptyDB> {
     pub fn builder() -> EvmBuilder<'a, SetGenericStage, (), EmptyDB> {
         EvmBuilder::default()
     }
+
+    /// Executes the transaction of the environment against a shared reference to the database.
+    ///
+    /// The database is only read through [DatabaseRef]. The changes of the transaction are
+    /// buffered in the journal and returned in the [ResultAndState], and are never written to the
+    /// database, so many simulations can run concurrently on one snapshot of the state.
+    pub fn transact_ref<DB: DatabaseRef>(db: &DB, env: EnvWithHandlerCfg) -> EVMResult<DB::Error> {
+        Evm::builder()
+            .with_ref_db(db)
+            .with_env_with_handler_cfg(env)
+            .build()
+            .transact()
+    }
+
+    /// Executes the setup transactions once, then every candidate transaction independently on
+    /// the state they leave, against a shared reference to the database.
+    ///
+    /// The changes of the setup transactions are kept in a [CacheDB] whether they succeeded or
+    /// not, as if they were included in a block, and an invalid setup transaction fails the whole
+    /// bundle. The candidates are [transacted](Evm::transact_ref) on that cache, so they don't
+    /// see each other's changes, and their results are returned in order. The database is never
+    /// written to.
+    pub fn simulate_bundle<DB: DatabaseRef>(
+        db: &DB,
+        env: EnvWithHandlerCfg,
+        setup: Vec<TxEnv>,
+        candidates: Vec<TxEnv>,
+    ) -> Result<Vec<EVMResult<DB::Error>>, EVMError<DB::Error>> {
+        let mut cache = CacheDB::new(db);
+        cache.token_ids = db.get_token_ids_ref().map_err(EVMError::Database)?;
+        let mut evm = Evm::builder()
+            .with_db(cache)
+            .with_env_with_handler_cfg(env)
+            .build();
+        for tx in setup {
+            *evm.tx_mut() = tx;
+            evm.transact_commit()?;
+        }
+
+        let (cache, env) = evm.into_db_and_env_with_handler_cfg();
+        Ok(candidates
+            .into_iter()
+            .map(|tx| {
+                let mut env = env.clone();
+                env.tx = tx;
+                Evm::transact_ref(&cache, env)
+            })
+            .collect())
+    }
 }
 
 impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
@@ -74,97 +160,140 @@ impl<'a, EXT, DB: Database> Evm<'a, EXT, DB> {
     /// Runs main call loop.
     #[inline]
     pub fn run_the_loop(&mut self, first_frame: Frame) -> Result<FrameResult, EVMError<DB::Error>> {
-        let mut call_stack: Vec<Frame> = Vec::with_capacity(1025);
-        call_stack.push(first_frame);
+        let mut frame_stack = self.start_frames(first_frame);
+        let result = loop {
+            match self.step_frame(&mut frame_stack) {
+                Ok(Some(result)) => break Ok(result),
+                Ok(None) => {}
+                Err(error) => break Err(error),
+            }
+        };
+        self.finish_frames(frame_stack);
+        result
+    }
 
-        #[cfg(feature = "memory_limit")]
-        let mut shared_memory =
-            SharedMemory::new_with_memory_limit(self.context.evm.env.cfg.memory_limit);
-        #[cfg(not(feature = "memory_limit"))]
-        let mut shared_memory = SharedMemory::new();
-
-        shared_memory.new_context();
-
-        // Peek the last stack frame.
-        let mut stack_frame = call_stack.last_mut().unwrap();
-
-        loop {
-            // Execute the frame.
-            let next_action =
-                self.handler
-                    .execute_frame(stack_frame, &mut shared_memory, &mut self.context)?;
-
-            // Take error and break the loop, if any.
-            // This error can be set in the Interpreter when it interacts with the context.
-            self.context.evm.take_error()?;
-
-            let exec = &mut self.handler.execution;
-            let frame_or_result = match next_action {
-                InterpreterAction::Call { inputs } => exec.call(&mut self.context, inputs)?,
-                InterpreterAction::Create { inputs } => exec.create(&mut self.context, inputs)?,
-                InterpreterAction::EOFCreate { inputs } => {
-                    exec.eofcreate(&mut self.context, inputs)?
-                }
-                InterpreterAction::Return { result } => {
-                    // free memory context.
-                    shared_memory.free_context();
-
-                    // pop last frame from the stack and consume it to create FrameResult.
-                    let returned_frame = call_stack
-                        .pop()
-                        .expect("We just returned from Interpreter frame");
-
-                    let ctx = &mut self.context;
-                    FrameOrResult::Result(match returned_frame {
-                        Frame::Call(frame) => {
-                            // return_call
-                            FrameResult::Call(exec.call_return(ctx, frame, result)?)
-                        }
-                        Frame::Create(frame) => {
-                            // return_create
-                            FrameResult::Create(exec.create_return(ctx, frame, result)?)
-                        }
-                        Frame::EOFCreate(frame) => {
-                            // return_eofcreate
-                            FrameResult::EOFCreate(exec.eofcreate_return(ctx, frame, result)?)
-                        }
-                    })
-                }
-                InterpreterAction::None => unreachable!("InterpreterAction::None is not expected"),
-            };
+    /// Starts running the frames of a transaction from its first frame, see [FrameStack].
+    ///
+    /// The shared memory and the call stack are rented from the context, and should be handed
+    /// back with [Self::finish_frames].
+    #[inline]
+    pub fn start_frames(&mut self, first_frame: Frame) -> FrameStack {
+        // Rent the shared memory and the call stack so their allocations are reused across
+        // transactions.
+        let shared_memory = self.context.evm.take_shared_memory();
+        let call_stack = self.context.evm.frame_pool.take_call_stack();
+        let mut frame_stack = FrameStack::new(call_stack, shared_memory);
+        frame_stack.push(first_frame);
+        frame_stack
+    }
 
-            // handle result
-            match frame_or_result {
-                FrameOrResult::Frame(frame) => {
-                    shared_memory.new_context();
-                    call_stack.push(frame);
-                    stack_frame = call_stack.last_mut().unwrap();
-                }
-                FrameOrResult::Result(result) => {
-                    let Some(top_frame) = call_stack.last_mut() else {
-                        // Break the loop if there are no more frames.
-                        return Ok(result);
-                    };
-                    stack_frame = top_frame;
-                    let ctx = &mut self.context;
-                    // Insert result to the top frame.
-                    match result {
-                        FrameResult::Call(outcome) => {
-                            // return_call
-                            exec.insert_call_outcome(ctx, stack_frame, &mut shared_memory, outcome)?
-                        }
-                        FrameResult::Create(outcome) => {
-                            // return_create
-                            exec.insert_create_outcome(ctx, stack_frame, outcome)?
-                        }
-                        FrameResult::EOFCreate(outcome) => {
-                            // return_eofcreate
-                            exec.insert_eofcreate_outcome(ctx, stack_frame, outcome)?
-                        }
+    /// Runs the top frame of the stack until it makes a call or a create, or returns.
+    ///
+    /// The frame of a call or a create is pushed on the stack, and runs at the next step. A
+    /// returned frame is popped from the stack and its result is inserted into the frame below,
+    /// which resumes at the next step. Returns the result of the first frame once it returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stack is empty, i.e. if the first frame already returned.
+    #[inline]
+    pub fn step_frame(
+        &mut self,
+        frame_stack: &mut FrameStack,
+    ) -> Result<Option<FrameResult>, EVMError<DB::Error>> {
+        let FrameStack {
+            frames,
+            shared_memory,
+        } = frame_stack;
+        let stack_frame = frames.last_mut().expect("the frame stack is empty");
+
+        // Execute the frame.
+        let next_action =
+            self.handler
+                .execute_frame(stack_frame, shared_memory, &mut self.context)?;
+
+        // Take error and break the loop, if any.
+        // This error can be set in the Interpreter when it interacts with the context.
+        self.context.evm.take_error()?;
+
+        let exec = &mut self.handler.execution;
+        let frame_or_result = match next_action {
+            InterpreterAction::Call { inputs } => exec.call(&mut self.context, inputs)?,
+            InterpreterAction::Create { inputs } => exec.create(&mut self.context, inputs)?,
+            InterpreterAction::EOFCreate { inputs } => exec.eofcreate(&mut self.context, inputs)?,
+            InterpreterAction::Return { result } => {
+                // free memory context.
+                shared_memory.free_context();
+
+                // pop last frame from the stack and consume it to create FrameResult.
+                let mut returned_frame = frames
+                    .pop()
+                    .expect("We just returned from Interpreter frame");
+                self.context
+                    .evm
+                    .frame_pool
+                    .recycle_frame(&mut returned_frame);
+
+                let ctx = &mut self.context;
+                FrameOrResult::Result(match returned_frame {
+                    Frame::Call(frame) => {
+                        // return_call
+                        FrameResult::Call(exec.call_return(ctx, frame, result)?)
+                    }
+                    Frame::Create(frame) => {
+                        // return_create
+                        FrameResult::Create(exec.create_return(ctx, frame, result)?)
+                    }
+                    Frame::EOFCreate(frame) => {
+                        // return_eofcreate
+                        FrameResult::EOFCreate(exec.eofcreate_return(ctx, frame, result)?)
+                    }
+                })
+            }
+            InterpreterAction::None => unreachable!("InterpreterAction::None is not expected"),
+        };
+
+        // handle result
+        match frame_or_result {
+            FrameOrResult::Frame(frame) => frame_stack.push(frame),
+            FrameOrResult::Result(result) => {
+                let Some(stack_frame) = frames.last_mut() else {
+                    // The first frame returned.
+                    return Ok(Some(result));
+                };
+                let ctx = &mut self.context;
+                // Insert result to the top frame.
+                match result {
+                    FrameResult::Call(outcome) => {
+                        // return_call
+                        exec.insert_call_outcome(ctx, stack_frame, shared_memory, outcome)?
+                    }
+                    FrameResult::Create(outcome) => {
+                        // return_create
+                        exec.insert_create_outcome(ctx, stack_frame, outcome)?
+                    }
+                    FrameResult::EOFCreate(outcome) => {
+                        // return_eofcreate
+                        exec.insert_eofcreate_outcome(ctx, stack_frame, outcome)?
                     }
                 }
             }
         }
+        Ok(None)
+    }
+
+    /// Hands the shared memory and the call stack of the frame stack back to the context.
+    ///
+    /// The frames left on the stack, e.g. after an error, are dropped without returning.
+    #[inline]
+    pub fn finish_frames(&mut self, frame_stack: FrameStack) {
+        let (mut call_stack, shared_memory) = frame_stack.into_parts();
+        // Recycle the token transfers of the frames left over after an error.
+        for mut frame in call_stack.drain(..) {
+            self.context.evm.frame_pool.recycle_frame(&mut frame);
+        }
+        self.context.evm.frame_pool.return_call_stack(call_stack);
+        self.context.evm.return_shared_memory(shared_memory);
     }
 }
 
@@ -185,6 +314,79 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
         output
     }
 
+    /// Validates the environment and the transaction against the state, returning every
+    /// problem found instead of stopping at the first one like
+    /// [`preverify_transaction`](Self::preverify_transaction).
+    ///
+    /// The checks are the mainnet ones, so problems reported by custom validation handlers are
+    /// not included, but the intrinsic gas is priced by the intrinsic gas handles. The state is
+    /// not modified. An empty result means that the transaction can be executed.
+    pub fn preflight(&mut self) -> Result<Vec<InvalidEnv>, EVMError<DB::Error>> {
+        let spec_id = self.spec_id();
+        let InnerEvmContext { env, db, .. } = &mut self.context.evm.inner;
+        let mut problems = env.validate(spec_id);
+
+        let initial_gas_spend = self.handler.intrinsic_gas().total(env);
+        if initial_gas_spend > env.tx.gas_limit {
+            problems.push(InvalidTransaction::CallGasCostMoreThanGasLimit.into());
+        }
+
+        for token in env.tx.transferred_tokens.iter().chain(&env.tx.tip_tokens) {
+            if !db.is_token_id_valid(token.id).map_err(EVMError::Database)? {
+                problems.push(
+                    InvalidTransaction::InvalidTokenId {
+                        token_id: Box::new(token.id),
+                    }
+                    .into(),
+                );
+            }
+        }
+
+        let mut caller = db
+            .basic(env.tx.caller)
+            .map_err(EVMError::Database)?
+            .unwrap_or_default();
+        // the code tells delegating EOAs apart from contracts
+        if caller.code.is_none() && caller.code_hash != KECCAK_EMPTY {
+            caller.code = Some(
+                db.code_by_hash(caller.code_hash)
+                    .map_err(EVMError::Database)?,
+            );
+        }
+        let caller = caller.into();
+        let gas_payer = if env.tx.is_sponsored() {
+            let payer = db
+                .basic(env.tx.fee_payer())
+                .map_err(EVMError::Database)?
+                .unwrap_or_default();
+            Some(payer.into())
+        } else {
+            None
+        };
+        problems.extend(
+            env.validate_against_state(spec_id, &caller, gas_payer.as_ref())
+                .into_iter()
+                .map(InvalidEnv::from),
+        );
+
+        if !env.tx.tip_tokens.is_empty() {
+            let beneficiary = db
+                .basic(env.block.coinbase)
+                .map_err(EVMError::Database)?
+                .unwrap_or_default();
+            if let Err(problem) = env.validate_tips_against_beneficiary(&beneficiary) {
+                problems.push(problem.into());
+            }
+        }
+
+        let precompiles = self.handler.pre_execution().load_precompiles();
+        self.context.evm.set_precompiles(precompiles);
+        if let Err(problem) = self.context.evm.validate_tx_destination() {
+            problems.push(problem.into());
+        }
+        Ok(problems)
+    }
+
     /// Calls clear handle of post execution to clear the state for next execution.
     fn clear(&mut self) {
         self.handler.post_execution().clear(&mut self.context);
@@ -195,27 +397,31 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
     /// This function will not validate the transaction.
     #[inline]
     pub fn transact_preverified(&mut self) -> EVMResult<DB::Error> {
-        let initial_gas_spend = self
-            .handler
-            .validation()
-            .initial_tx_gas(&self.context.evm.env)
-            .inspect_err(|_| {
-                self.clear();
-            })?;
+        let initial_gas_spend = self.initial_tx_gas().inspect_err(|_| {
+            self.clear();
+        })?;
         let output = self.transact_preverified_inner(initial_gas_spend);
         let output = self.handler.post_execution().end(&mut self.context, output);
         self.clear();
         output
     }
 
+    /// Validates the intrinsic gas of the transaction, priced by the intrinsic gas handles.
+    #[inline]
+    fn initial_tx_gas(&self) -> Result<u64, EVMError<DB::Error>> {
+        let env = &self.context.evm.env;
+        let intrinsic_gas = self.handler.intrinsic_gas().total(env);
+        self.handler.validation().initial_tx_gas(env, intrinsic_gas)
+    }
+
     /// Pre verify transaction inner.
     #[inline]
     fn preverify_transaction_inner(&mut self) -> Result<u64, EVMError<DB::Error>> {
         self.handler.validation().env(&self.context.evm.env)?;
-        let initial_gas_spend = self
-            .handler
-            .validation()
-            .initial_tx_gas(&self.context.evm.env)?;
+        let initial_gas_spend = self.initial_tx_gas()?;
+        // the transaction is validated against the precompiles too.
+        let precompiles = self.handler.pre_execution().load_precompiles();
+        self.context.evm.set_precompiles(precompiles);
         self.handler
             .validation()
             .tx_against_state(&mut self.context)?;
@@ -237,6 +443,96 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
         output
     }
 
+    /// Calls the contract with the calldata against the current state and returns its output,
+    /// like `eth_call`.
+    ///
+    /// The call is made from the zero address, without transferring tokens, with the gas limit
+    /// of the block and a zero gas price. It is not validated, and the changes it makes are
+    /// discarded, so the state and the transaction environment are left as they were. A call
+    /// that reverts or halts fails with [EVMError::Custom].
+    pub fn call_view(
+        &mut self,
+        address: Address,
+        calldata: Bytes,
+    ) -> Result<Bytes, EVMError<DB::Error>> {
+        let tx = TxEnv {
+            caller: Address::ZERO,
+            transact_to: TransactTo::Call(address),
+            data: calldata,
+            gas_limit: self.block().gas_limit.saturating_to(),
+            gas_price: U256::ZERO,
+            ..Default::default()
+        };
+        let tx = mem::replace(self.tx_mut(), tx);
+        let result = self.transact_preverified();
+        *self.tx_mut() = tx;
+        match result?.result {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+            ExecutionResult::Revert { output, .. } => Err(EVMError::Custom(format!(
+                "view call reverted with {output}"
+            ))),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(EVMError::Custom(format!("view call halted: {reason:?}")))
+            }
+        }
+    }
+
+    /// Transacts the transaction like [transact](Evm::transact), without committing its
+    /// changes, and returns the balance changes it would make and the tokens it would mint for
+    /// the first time, like a token-aware `eth_call`.
+    ///
+    /// The balances before the transaction are read from the database, which is left as it was.
+    pub fn simulate(&mut self) -> Result<Simulation, EVMError<DB::Error>> {
+        let result_and_state = self.transact()?;
+        let db = &mut self.context.evm.db;
+        let state = &result_and_state.state;
+
+        let mut addresses: Vec<&Address> = state
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.is_touched())
+            .map(|(address, _)| address)
+            .collect();
+        addresses.sort_unstable();
+        let mut balance_deltas = Vec::new();
+        for address in addresses {
+            let before = db
+                .basic(*address)
+                .map_err(EVMError::Database)?
+                .map(|info| info.balances)
+                .unwrap_or_default();
+            let after = &state.accounts[address].info.balances;
+            let mut token_ids: Vec<&U256> = before.keys().chain(after.keys()).collect();
+            token_ids.sort_unstable();
+            token_ids.dedup();
+            for token_id in token_ids {
+                let delta = BalanceDelta {
+                    address: *address,
+                    token_id: *token_id,
+                    before: before.get(token_id).copied().unwrap_or_default(),
+                    after: after.get(token_id).copied().unwrap_or_default(),
+                };
+                if delta.before != delta.after {
+                    balance_deltas.push(delta);
+                }
+            }
+        }
+
+        let known_token_ids = db.get_token_ids().map_err(EVMError::Database)?;
+        let minted_token_ids = state
+            .token_ids
+            .iter()
+            .filter(|token_id| !known_token_ids.contains(token_id))
+            .copied()
+            .collect();
+
+        Ok(Simulation {
+            result_and_state,
+            balance_deltas,
+            minted_token_ids,
+        })
+    }
+
     /// Returns the reference of handler configuration
     #[inline]
     pub fn handler_cfg(&self) -> &HandlerCfg {
@@ -328,6 +624,12 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
         // load access list and beneficiary if needed.
         pre_exec.load_accounts(ctx)?;
 
+        // store the parent block hash in the history contract.
+        pre_exec.apply_blockhash_history(ctx)?;
+
+        // mint the emissions of the block, if this is its first transaction.
+        pre_exec.apply_block_emissions(ctx)?;
+
         // load precompiles
         let precompiles = pre_exec.load_precompiles();
         ctx.evm.set_precompiles(precompiles);
@@ -335,7 +637,11 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
         // deduce caller balance with its limit.
         pre_exec.deduct_caller(ctx)?;
 
+        // set the code of the authorities of set-code transactions.
+        let eip7702_gas_refund = pre_exec.apply_eip7702_auth_list(ctx)? as i64;
+
         let gas_limit = ctx.evm.env.tx.gas_limit - initial_gas_spend;
+        ctx.evm.intrinsic_gas = initial_gas_spend;
 
         let exec = self.handler.execution();
         // call inner handling of call/create
@@ -346,7 +652,8 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
             )?,
             TransactTo::Create => exec.create(
                 ctx,
-                CreateInputs::new_boxed(&ctx.evm.env.tx, gas_limit).unwrap(),
+                CreateInputs::new_boxed(&ctx.evm.env.tx, gas_limit, ctx.evm.env.cfg.base_token_id)
+                    .unwrap(),
             )?,
         };
 
@@ -363,6 +670,14 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
             .execution()
             .last_frame_return(ctx, &mut result)?;
 
+        // the authorizations are refunded whatever the outcome of the transaction, within the
+        // cap on refunds.
+        if eip7702_gas_refund > 0 {
+            let gas = result.gas_mut();
+            gas.record_refund(eip7702_gas_refund);
+            gas.set_final_refund_with_quotient(ctx.evm.env.cfg.refund_quotient(ctx.evm.spec_id()));
+        }
+
         let post_exec = self.handler.post_execution();
         // Reimburse the caller
         post_exec.reimburse_caller(ctx, result.gas())?;
@@ -372,3 +687,1545 @@ impl<EXT, DB: Database> Evm<'_, EXT, DB> {
         post_exec.output(ctx, result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryDB,
+        inspector_handle_register,
+        interpreter::opcode,
+        primitives::{
+            address, init_balances, AccountInfo, Authorization, Bytecode, ExecutionResultKind,
+            HaltReason, HashMap, Impersonation, InvalidHeader, InvalidTransaction, OutOfGasError,
+            RecoveredAuthorization, TokenEmission, TokenSupplyChanges, TokenTransfer,
+            TokenTransferSummary, TxOutcome, B256, BASE_TOKEN_ID, BLOCKHASH_STORAGE_ADDRESS,
+            EMISSIONS_ADDRESS,
+        },
+        Inspector,
+    };
+
+    #[test]
+    fn steps_through_frames() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let proxy = address!("00000000000000000000000000000000000000bb");
+        let callee = address!("00000000000000000000000000000000000000cc");
+        // CALL(GAS, callee, 0, 0, 0, 0, 0)
+        let mut code = [opcode::PUSH1, 0].repeat(5);
+        code.push(opcode::PUSH20);
+        code.extend_from_slice(callee.as_slice());
+        code.extend([opcode::GAS, opcode::CALL, opcode::POP, opcode::STOP]);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(caller, AccountInfo::default());
+        for (address, code) in [(proxy, code), (callee, vec![opcode::STOP])] {
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    code: Some(Bytecode::new_raw(code.into())),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(proxy);
+            })
+            .build();
+
+        let inputs = CallInputs::new_boxed(&evm.context.evm.env.tx, 100_000).unwrap();
+        let Ok(FrameOrResult::Frame(first_frame)) =
+            evm.handler.execution().call(&mut evm.context, inputs)
+        else {
+            panic!("expected the frame of the proxy");
+        };
+        let mut frame_stack = evm.start_frames(first_frame);
+        let mut depths = Vec::new();
+        let result = loop {
+            let result = evm.step_frame(&mut frame_stack).unwrap();
+            depths.push(frame_stack.depth());
+            if let Some(result) = result {
+                break result;
+            }
+        };
+        evm.finish_frames(frame_stack);
+
+        // the proxy calls the callee, the callee returns, then the proxy returns
+        assert_eq!(depths, vec![2, 1, 0]);
+        assert!(result.interpreter_result().is_ok());
+    }
+
+    #[test]
+    fn transact_ref_runs_concurrently_on_shared_db() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+
+        std::thread::scope(|scope| {
+            for recipient in [
+                address!("00000000000000000000000000000000000000b1"),
+                address!("00000000000000000000000000000000000000b2"),
+            ] {
+                let db = &db;
+                scope.spawn(move || {
+                    let mut env = EnvWithHandlerCfg::default();
+                    env.tx.caller = caller;
+                    env.tx.transact_to = TransactTo::Call(recipient);
+                    env.tx.gas_limit = 21_000;
+                    env.tx.transferred_tokens = vec![TokenTransfer {
+                        id: BASE_TOKEN_ID,
+                        amount: U256::from(10),
+                    }];
+
+                    let ResultAndState { result, state } = Evm::transact_ref(db, env).unwrap();
+                    assert!(result.is_success());
+                    assert_eq!(
+                        state.accounts[&recipient].info.get_base_balance(),
+                        U256::from(10)
+                    );
+                });
+            }
+        });
+
+        // the shared database is left untouched
+        assert_eq!(
+            db.accounts[&caller].info.get_base_balance(),
+            U256::from(1_000_000)
+        );
+    }
+
+    #[test]
+    fn simulate_bundle_runs_candidates_on_the_setup_state() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let searcher = address!("00000000000000000000000000000000000000bb");
+        let transfer = |from, to, amount| TxEnv {
+            caller: from,
+            transact_to: TransactTo::Call(to),
+            gas_limit: 21_000,
+            transferred_tokens: vec![TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(amount),
+            }],
+            ..Default::default()
+        };
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+
+        let recipients = [
+            address!("00000000000000000000000000000000000000c1"),
+            address!("00000000000000000000000000000000000000c2"),
+        ];
+        // each candidate spends most of what the setup sent, so they can't both run in sequence
+        let results = Evm::simulate_bundle(
+            &db,
+            EnvWithHandlerCfg::default(),
+            vec![transfer(caller, searcher, 100)],
+            recipients
+                .iter()
+                .map(|recipient| transfer(searcher, *recipient, 60))
+                .collect(),
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 2);
+        for (result, recipient) in results.into_iter().zip(recipients) {
+            let ResultAndState { result, state } = result.unwrap();
+            assert!(result.is_success());
+            assert_eq!(
+                state.accounts[&searcher].info.get_base_balance(),
+                U256::from(40)
+            );
+            assert_eq!(
+                state.accounts[&recipient].info.get_base_balance(),
+                U256::from(60)
+            );
+        }
+        assert!(!db.accounts.contains_key(&searcher));
+    }
+
+    #[test]
+    fn commits_in_chunks_like_a_whole_commit() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        let commit = |chunk_size: Option<usize>| {
+            let mut evm = Evm::builder()
+                .with_db(db.clone())
+                .modify_tx_env(|tx| {
+                    tx.caller = caller;
+                    tx.transact_to =
+                        TransactTo::Call(address!("00000000000000000000000000000000000000bb"));
+                    tx.gas_limit = 21_000;
+                    tx.transferred_tokens = vec![TokenTransfer {
+                        id: BASE_TOKEN_ID,
+                        amount: U256::from(10),
+                    }];
+                })
+                .build();
+            let result = match chunk_size {
+                Some(chunk_size) => evm.transact_commit_in_chunks(chunk_size),
+                None => evm.transact_commit(),
+            };
+            assert!(result.unwrap().is_success());
+            evm.into_context().evm.inner.db
+        };
+
+        let whole = commit(None);
+        let chunked = commit(Some(1));
+        let infos = |db: &InMemoryDB| -> HashMap<Address, AccountInfo> {
+            db.accounts
+                .iter()
+                .map(|(address, account)| (*address, account.info.clone()))
+                .collect()
+        };
+        assert_eq!(infos(&chunked), infos(&whole));
+        assert_eq!(chunked.token_ids, whole.token_ids);
+    }
+
+    #[test]
+    fn prunes_drained_balances() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let token_id = U256::from(1);
+        let mut db = InMemoryDB::default();
+        db.token_ids.push(token_id);
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: HashMap::from([
+                    (BASE_TOKEN_ID, U256::from(21_000)),
+                    (token_id, U256::from(10)),
+                ]),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to =
+                    TransactTo::Call(address!("00000000000000000000000000000000000000bb"));
+                tx.gas_limit = 21_000;
+                tx.gas_price = U256::from(1);
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: token_id,
+                    amount: U256::from(10),
+                }];
+            })
+            .build();
+
+        let ResultAndState { result, state } = evm.transact().unwrap();
+        assert!(result.is_success());
+        assert_eq!(
+            state.accounts[&caller].info.balances,
+            HashMap::from([(BASE_TOKEN_ID, U256::ZERO), (token_id, U256::ZERO)])
+        );
+
+        evm.cfg_mut().prune_zero_balances = true;
+        let ResultAndState { state, .. } = evm.transact().unwrap();
+        assert!(state.accounts[&caller].info.balances.is_empty());
+    }
+
+    #[test]
+    fn call_view_leaves_the_state_unchanged() {
+        let contract = address!("00000000000000000000000000000000000000bb");
+        // stores 42 in slot 0 and returns it, or reverts if called with data
+        let code = [
+            opcode::CALLDATASIZE,
+            opcode::PUSH1,
+            18,
+            opcode::JUMPI,
+            opcode::PUSH1,
+            42,
+            opcode::DUP1,
+            opcode::PUSH1,
+            0,
+            opcode::SSTORE,
+            opcode::PUSH1,
+            0,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            32,
+            opcode::PUSH1,
+            0,
+            opcode::RETURN,
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0,
+            opcode::DUP1,
+            opcode::REVERT,
+        ];
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::copy_from_slice(&code))),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| tx.gas_limit = 21_000)
+            .build();
+
+        let output = evm.call_view(contract, Bytes::new()).unwrap();
+        assert_eq!(U256::from_be_slice(&output), U256::from(42));
+        assert_eq!(evm.db_mut().storage(contract, U256::ZERO), Ok(U256::ZERO));
+        assert_eq!(evm.tx().gas_limit, 21_000);
+        assert!(matches!(
+            evm.call_view(contract, Bytes::from_static(&[1])),
+            Err(EVMError::Custom(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_token_transfers_to_precompiles() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        let identity = address!("0000000000000000000000000000000000000004");
+        // calls the identity precompile with a value of 1 and stores whether the call succeeded
+        let mut code = vec![opcode::PUSH1, 0, opcode::DUP1, opcode::DUP1, opcode::DUP1];
+        code.extend([
+            opcode::PUSH1,
+            1,
+            opcode::PUSH1,
+            4,
+            opcode::GAS,
+            opcode::CALL,
+        ]);
+        code.extend([opcode::PUSH1, 0, opcode::SSTORE, opcode::STOP]);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                balances: init_balances(U256::from(1)),
+                code: Some(Bytecode::new_raw(code.into())),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.gas_limit = 100_000;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(1),
+                }];
+            })
+            .build();
+
+        evm.tx_mut().transact_to = TransactTo::Call(identity);
+        assert_eq!(
+            evm.transact().err(),
+            Some(EVMError::Transaction(
+                InvalidTransaction::TokenTransferToPrecompile
+            ))
+        );
+        assert_eq!(
+            evm.preverify_transaction().err(),
+            Some(EVMError::Transaction(
+                InvalidTransaction::TokenTransferToPrecompile
+            ))
+        );
+        assert_eq!(
+            evm.preflight().unwrap(),
+            vec![InvalidTransaction::TokenTransferToPrecompile.into()]
+        );
+
+        // the call made by the contract fails, and the transaction goes on
+        evm.tx_mut().transact_to = TransactTo::Call(contract);
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(evm.db_mut().storage(contract, U256::ZERO), Ok(U256::ZERO));
+        assert_eq!(
+            evm.db_mut()
+                .basic(identity)
+                .unwrap()
+                .unwrap_or_default()
+                .get_base_balance(),
+            U256::ZERO
+        );
+
+        evm.cfg_mut().allow_token_transfers_to_precompiles = true;
+        evm.tx_mut().transact_to = TransactTo::Call(identity);
+        assert!(evm.transact_commit().unwrap().is_success());
+        evm.tx_mut().transact_to = TransactTo::Call(contract);
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.db_mut().storage(contract, U256::ZERO),
+            Ok(U256::from(1))
+        );
+        assert_eq!(
+            evm.db_mut()
+                .basic(identity)
+                .unwrap()
+                .unwrap()
+                .get_base_balance(),
+            U256::from(2)
+        );
+    }
+
+    #[test]
+    fn protects_reserved_addresses() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        let reserved = crate::sablier::u64_to_prefixed_address(0x100);
+        // calls the reserved address with a value of 1 and stores whether the call succeeded
+        let mut code = vec![opcode::PUSH1, 0, opcode::DUP1, opcode::DUP1, opcode::DUP1];
+        code.extend([opcode::PUSH1, 1, opcode::PUSH20]);
+        code.extend(reserved.as_slice());
+        code.extend([opcode::GAS, opcode::CALL]);
+        code.extend([opcode::PUSH1, 0, opcode::SSTORE, opcode::STOP]);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                balances: init_balances(U256::from(2)),
+                code: Some(Bytecode::new_raw(code.into())),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_cfg_env(|cfg| cfg.protect_reserved_addresses = true)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.gas_limit = 100_000;
+            })
+            .build();
+
+        evm.tx_mut().transact_to = TransactTo::Call(reserved);
+        assert_eq!(
+            evm.transact().err(),
+            Some(EVMError::Transaction(
+                InvalidTransaction::CallToReservedAddress
+            ))
+        );
+        assert_eq!(
+            evm.preverify_transaction().err(),
+            Some(EVMError::Transaction(
+                InvalidTransaction::CallToReservedAddress
+            ))
+        );
+        assert_eq!(
+            evm.preflight().unwrap(),
+            vec![InvalidTransaction::CallToReservedAddress.into()]
+        );
+        // the registered precompiles can be called
+        evm.tx_mut().transact_to = TransactTo::Call(crate::sablier::native_tokens::ADDRESS);
+        assert!(evm.transact().is_ok());
+
+        // the call made by the contract fails, and the transaction goes on
+        evm.tx_mut().transact_to = TransactTo::Call(contract);
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(evm.db_mut().storage(contract, U256::ZERO), Ok(U256::ZERO));
+        assert_eq!(evm.db_mut().basic(reserved), Ok(None));
+
+        evm.cfg_mut().protect_reserved_addresses = false;
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.db_mut().storage(contract, U256::ZERO),
+            Ok(U256::from(1))
+        );
+        evm.tx_mut().transact_to = TransactTo::Call(reserved);
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.db_mut()
+                .basic(reserved)
+                .unwrap()
+                .unwrap()
+                .get_base_balance(),
+            U256::from(1)
+        );
+    }
+
+    #[test]
+    fn rejects_legacy_value_calls_to_mnt_only_contracts() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        let mnt_only = address!("00000000000000000000000000000000000000cc");
+        // calls the MNT-only contract with a value of 1 and stores whether the call succeeded
+        let mut code = vec![opcode::PUSH1, 0, opcode::DUP1, opcode::DUP1, opcode::DUP1];
+        code.extend([opcode::PUSH1, 1, opcode::PUSH20]);
+        code.extend(mnt_only.as_slice());
+        code.extend([opcode::GAS, opcode::CALL]);
+        code.extend([opcode::PUSH1, 0, opcode::SSTORE, opcode::STOP]);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                balances: init_balances(U256::from(2)),
+                code: Some(Bytecode::new_raw(code.into())),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            mnt_only,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from_static(&[opcode::STOP]))),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_cfg_env(|cfg| {
+                cfg.mnt_only_contracts.insert(mnt_only);
+                cfg.reject_legacy_value_calls = true;
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.gas_limit = 100_000;
+            })
+            .build();
+        let base_balance = |evm: &mut Evm<'_, (), InMemoryDB>, address| {
+            evm.db_mut()
+                .basic(address)
+                .unwrap()
+                .unwrap()
+                .get_base_balance()
+        };
+
+        // the transaction transfers its tokens explicitly
+        evm.tx_mut().transact_to = TransactTo::Call(mnt_only);
+        evm.tx_mut().transferred_tokens = vec![TokenTransfer {
+            id: BASE_TOKEN_ID,
+            amount: U256::from(1),
+        }];
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(base_balance(&mut evm, mnt_only), U256::from(1));
+
+        // the call made by the contract fails, and the transaction goes on
+        evm.tx_mut().transact_to = TransactTo::Call(contract);
+        evm.tx_mut().transferred_tokens.clear();
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(evm.db_mut().storage(contract, U256::ZERO), Ok(U256::ZERO));
+        assert_eq!(base_balance(&mut evm, mnt_only), U256::from(1));
+
+        // without the switch, the value is transferred as a base token transfer
+        evm.cfg_mut().reject_legacy_value_calls = false;
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.db_mut().storage(contract, U256::ZERO),
+            Ok(U256::from(1))
+        );
+        assert_eq!(base_balance(&mut evm, mnt_only), U256::from(2));
+    }
+
+    #[test]
+    fn burn_address_burns_received_tokens() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let burn_address = address!("00000000000000000000000000000000000000dd");
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_cfg_env(|cfg| cfg.burn_address = Some(burn_address))
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(burn_address);
+                tx.gas_limit = 21_000;
+                tx.gas_price = U256::ZERO;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(10),
+                }];
+            })
+            .build();
+
+        let ResultAndState { result, state } = evm.transact().unwrap();
+        assert!(result.is_success());
+        assert_eq!(
+            state.accounts[&caller].info.get_base_balance(),
+            U256::from(1_000_000 - 10)
+        );
+        assert_eq!(
+            state.accounts[&burn_address].info.get_base_balance(),
+            U256::ZERO
+        );
+    }
+
+    #[test]
+    fn reports_gas_breakdown() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        // clears slots 1, 2 and 3
+        let mut code = Vec::new();
+        for slot in 1..=3 {
+            code.extend([opcode::PUSH1, 0, opcode::PUSH1, slot, opcode::SSTORE]);
+        }
+        code.push(opcode::STOP);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(code))),
+                ..Default::default()
+            },
+        );
+        for slot in 1..=3 {
+            db.insert_account_storage(contract, U256::from(slot), U256::from(1))
+                .unwrap();
+        }
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(contract);
+                tx.gas_limit = 100_000;
+                tx.data = Bytes::from_static(&[1, 0]);
+            })
+            .build();
+
+        let result = evm.transact().unwrap().result;
+        let breakdown = *result.gas_breakdown().unwrap();
+        assert_eq!(breakdown.intrinsic, 21_000 + 16 + 4);
+        assert_eq!(breakdown.token_transfer_surcharge, 0);
+        // three refunds of 4800 exceed a fifth of the gas spent
+        assert!(breakdown.refund_capped);
+        assert_eq!(
+            breakdown.refunded,
+            (breakdown.intrinsic + breakdown.execution) / 5
+        );
+        assert_eq!(
+            result.gas_used(),
+            breakdown.intrinsic + breakdown.execution - breakdown.refunded
+        );
+    }
+
+    #[test]
+    fn summarizes_outcome_with_token_transfers() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let proxy = address!("00000000000000000000000000000000000000bb");
+        let reverter = address!("00000000000000000000000000000000000000cc");
+        let receiver = address!("00000000000000000000000000000000000000dd");
+        // calls the reverter with 5 and the receiver with 3 of the base token
+        let mut code = Vec::new();
+        for (target, value) in [(reverter, 5), (receiver, 3)] {
+            code.extend([opcode::PUSH1, 0].repeat(4));
+            code.extend([opcode::PUSH1, value, opcode::PUSH20]);
+            code.extend_from_slice(target.as_slice());
+            code.extend([opcode::GAS, opcode::CALL, opcode::POP]);
+        }
+        code.push(opcode::STOP);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            proxy,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(code))),
+                ..Default::default()
+            },
+        );
+        let revert = [opcode::PUSH1, 0, opcode::PUSH1, 0, opcode::REVERT];
+        db.insert_account_info(
+            reverter,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(revert.to_vec()))),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(proxy);
+                tx.gas_limit = 100_000;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(10),
+                }];
+            })
+            .build();
+
+        let result = evm.transact().unwrap().result;
+        assert_eq!(result.kind(), ExecutionResultKind::Success);
+        let outcome = TxOutcome::from(result);
+        // the transfer to the reverter was reverted
+        assert_eq!(outcome.token_transfers.count, 2);
+        assert_eq!(outcome.token_transfers.total(BASE_TOKEN_ID), U256::from(13));
+        assert_eq!(outcome.halt_reason, None);
+
+        evm.tx_mut().transact_to = TransactTo::Call(reverter);
+        let result = evm.transact().unwrap().result;
+        assert!(result.is_revert());
+        let outcome = TxOutcome::from(result);
+        assert_eq!(outcome.kind, ExecutionResultKind::Revert);
+        assert_eq!(outcome.token_transfers, TokenTransferSummary::default());
+
+        evm.tx_mut().gas_limit = 21_001;
+        let result = evm.transact().unwrap().result;
+        assert_eq!(
+            result.halt_reason(),
+            Some(HaltReason::OutOfGas(OutOfGasError::Basic))
+        );
+        assert_eq!(TxOutcome::from(result).kind, ExecutionResultKind::Halt);
+    }
+
+    #[test]
+    fn block_result_sums_supply_changes() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let receiver = address!("00000000000000000000000000000000000000bb");
+        let burn_address = address!("00000000000000000000000000000000000000dd");
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_cfg_env(|cfg| cfg.burn_address = Some(burn_address))
+            .build();
+        let transfer = |to, amount: u64| TxEnv {
+            caller,
+            transact_to: TransactTo::Call(to),
+            gas_limit: 100_000,
+            transferred_tokens: vec![TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(amount),
+            }],
+            ..Default::default()
+        };
+
+        let block_result = evm
+            .transact_block([
+                transfer(burn_address, 10),
+                transfer(receiver, 3),
+                transfer(burn_address, 5),
+            ])
+            .unwrap();
+        assert_eq!(block_result.results.len(), 3);
+        assert_eq!(block_result.gas_used, 3 * 21_000);
+        assert_eq!(
+            block_result.results[1].supply_changes(),
+            Some(&TokenSupplyChanges::default())
+        );
+        assert_eq!(
+            block_result.supply_changes.burned(BASE_TOKEN_ID),
+            U256::from(15)
+        );
+        assert!(block_result.supply_changes.minted.is_empty());
+    }
+
+    #[test]
+    fn simulate_reports_balance_deltas() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let receiver = address!("00000000000000000000000000000000000000bb");
+        let coinbase = address!("00000000000000000000000000000000000000cc");
+        let token_id = U256::from(7);
+        let mut db = InMemoryDB::default();
+        db.fund(caller, BASE_TOKEN_ID, U256::from(1_000_000))
+            .unwrap();
+        db.fund(caller, token_id, U256::from(100)).unwrap();
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_block_env(|block| block.coinbase = coinbase)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(receiver);
+                tx.gas_limit = 100_000;
+                tx.gas_price = U256::from(2);
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: token_id,
+                    amount: U256::from(40),
+                }];
+            })
+            .build();
+
+        let simulation = evm.simulate().unwrap();
+        let gas_used = simulation.result_and_state.result.gas_used();
+        let fee = U256::from(2 * gas_used);
+        let deltas: Vec<_> = simulation
+            .balance_deltas
+            .iter()
+            .map(|delta| (delta.address, delta.token_id, delta.before, delta.after))
+            .collect();
+        assert_eq!(
+            deltas,
+            vec![
+                (
+                    caller,
+                    BASE_TOKEN_ID,
+                    U256::from(1_000_000),
+                    U256::from(1_000_000) - fee
+                ),
+                (caller, token_id, U256::from(100), U256::from(60)),
+                (receiver, token_id, U256::ZERO, U256::from(40)),
+                (coinbase, BASE_TOKEN_ID, U256::ZERO, fee),
+            ]
+        );
+        assert_eq!(
+            simulation.balance_delta(caller, token_id).unwrap().spent(),
+            U256::from(40)
+        );
+        assert!(simulation.minted_token_ids.is_empty());
+        // the database is left as it was
+        assert_eq!(
+            evm.db().accounts[&caller].info.get_balance(token_id),
+            U256::from(100)
+        );
+    }
+
+    #[test]
+    fn pays_tip_tokens_to_the_beneficiary() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let reverter = address!("00000000000000000000000000000000000000bb");
+        let coinbase = address!("00000000000000000000000000000000000000cc");
+        let token_id = U256::from(7);
+        let mut db = InMemoryDB::default();
+        db.fund(caller, BASE_TOKEN_ID, U256::from(1_000_000))
+            .unwrap();
+        db.fund(caller, token_id, U256::from(100)).unwrap();
+        let revert = [opcode::PUSH1, 0, opcode::PUSH1, 0, opcode::REVERT];
+        db.deploy(reverter, Bytecode::new_raw(Bytes::from(revert.to_vec())))
+            .unwrap();
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_block_env(|block| block.coinbase = coinbase)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(reverter);
+                tx.gas_limit = 100_000;
+                tx.tip_tokens = vec![TokenTransfer {
+                    id: token_id,
+                    amount: U256::from(30),
+                }];
+            })
+            .build();
+
+        // the tip is paid even if the transaction reverts
+        let result = evm.transact_commit().unwrap();
+        assert!(result.is_revert());
+        let balance = |evm: &Evm<'_, (), InMemoryDB>, address| {
+            evm.db().accounts[&address].info.get_balance(token_id)
+        };
+        assert_eq!(balance(&evm, caller), U256::from(70));
+        assert_eq!(balance(&evm, coinbase), U256::from(30));
+
+        // the tip is summarized with the transfers
+        evm.tx_mut().transact_to = TransactTo::Call(coinbase);
+        let result = evm.transact_commit().unwrap();
+        let transfers = result.token_transfers().unwrap();
+        assert_eq!(transfers.count, 1);
+        assert_eq!(transfers.total(token_id), U256::from(30));
+        assert_eq!(balance(&evm, coinbase), U256::from(60));
+
+        // the caller can't tip more than it holds
+        evm.tx_mut().tip_tokens[0].amount = U256::from(41);
+        assert_eq!(
+            evm.transact().unwrap_err(),
+            EVMError::Transaction(InvalidTransaction::NotEnoughTokenBalanceForTransfer {
+                token_id: Box::new(token_id),
+                required_balance: Box::new(U256::from(41)),
+                actual_balance: Box::new(U256::from(40)),
+            })
+        );
+
+        // the tip can't overflow the balance of the beneficiary
+        evm.db_mut()
+            .fund(coinbase, token_id, U256::MAX - U256::from(60))
+            .unwrap();
+        evm.tx_mut().tip_tokens[0].amount = U256::from(1);
+        let overflow = InvalidTransaction::TipOverflow {
+            token_id: Box::new(token_id),
+        };
+        assert_eq!(evm.preflight().unwrap(), vec![overflow.clone().into()]);
+        assert_eq!(evm.transact().unwrap_err(), EVMError::Transaction(overflow));
+    }
+
+    #[test]
+    fn halts_frames_exceeding_journal_limits() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let proxy = address!("00000000000000000000000000000000000000bb");
+        let writer = address!("00000000000000000000000000000000000000cc");
+        // writes 1 to slots 1 to 8
+        let mut code = Vec::new();
+        for slot in 1..=8 {
+            code.extend([opcode::PUSH1, 1, opcode::PUSH1, slot, opcode::SSTORE]);
+        }
+        code.push(opcode::STOP);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(10_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            writer,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(code))),
+                ..Default::default()
+            },
+        );
+        // calls the writer, discarding the success flag
+        let mut code = [opcode::PUSH1, 0].repeat(5);
+        code.push(opcode::PUSH20);
+        code.extend_from_slice(writer.as_slice());
+        code.extend([opcode::GAS, opcode::CALL, opcode::POP, opcode::STOP]);
+        db.insert_account_info(
+            proxy,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from(code))),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_cfg_env(|cfg| cfg.frame_journal_entries_limit = Some(4))
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(proxy);
+                tx.gas_limit = 500_000;
+            })
+            .build();
+
+        // the writer fails without its writes, while the proxy succeeds
+        let ResultAndState { result, state } = evm.transact().unwrap();
+        assert!(result.is_success());
+        assert!(state.accounts[&writer]
+            .storage
+            .values()
+            .all(|slot| !slot.is_changed()));
+
+        // writing directly is halted
+        evm.tx_mut().transact_to = TransactTo::Call(writer);
+        let result = evm.transact().unwrap().result;
+        assert_eq!(result.halt_reason(), Some(HaltReason::JournalLimitExceeded));
+        assert_eq!(result.gas_used(), 500_000);
+
+        evm.cfg_mut().frame_journal_entries_limit = None;
+        assert!(evm.transact().unwrap().result.is_success());
+        evm.cfg_mut().journal_entries_limit = Some(4);
+        assert_eq!(
+            evm.transact().unwrap().result.halt_reason(),
+            Some(HaltReason::JournalLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn blockhash_reads_history_storage() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        // BLOCKHASH(500) stored at slot 0
+        let bytecode = Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH2,
+            0x01,
+            0xf4,
+            opcode::BLOCKHASH,
+            opcode::PUSH1,
+            0x00,
+            opcode::SSTORE,
+            opcode::STOP,
+        ]));
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                code_hash: bytecode.hash_slow(),
+                code: Some(bytecode),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_spec_id(SpecId::PRAGUE)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(contract);
+                tx.gas_limit = 100_000;
+            })
+            .build();
+        let hash = evm.db_mut().block_hash(U256::from(500)).unwrap();
+
+        // the transactions of block 501 store the hash of block 500
+        evm.block_mut().number = U256::from(501);
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(evm.db().accounts[&BLOCKHASH_STORAGE_ADDRESS].info.nonce, 1);
+
+        // which is out of the 256 blocks window of block 1000
+        evm.block_mut().number = U256::from(1000);
+        assert!(evm.transact_commit().unwrap().is_success());
+        let stored = evm.db_mut().storage(contract, U256::ZERO).unwrap();
+        assert_eq!(B256::from(stored.to_be_bytes()), hash);
+
+        // only served from Prague on
+        evm.modify_spec_id(SpecId::CANCUN);
+        assert!(evm.transact_commit().unwrap().is_success());
+        let stored = evm.db_mut().storage(contract, U256::ZERO).unwrap();
+        assert_eq!(stored, U256::ZERO);
+    }
+
+    #[test]
+    fn preflight_reports_every_problem() {
+        let mut evm = Evm::builder()
+            .with_db(InMemoryDB::default())
+            .modify_tx_env(|tx| {
+                tx.caller = address!("00000000000000000000000000000000000000aa");
+                tx.gas_limit = 1_000;
+                tx.gas_price = U256::from(1);
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: U256::from(7),
+                    amount: U256::from(1),
+                }];
+            })
+            .modify_block_env(|block| block.prevrandao = None)
+            .build();
+
+        let problems = evm.preflight().unwrap();
+        assert_eq!(
+            problems,
+            vec![
+                InvalidHeader::PrevrandaoNotSet.into(),
+                InvalidTransaction::CallGasCostMoreThanGasLimit.into(),
+                InvalidTransaction::InvalidTokenId {
+                    token_id: Box::new(U256::from(7)),
+                }
+                .into(),
+                InvalidTransaction::LackOfFundForMaxFee {
+                    fee: Box::new(U256::from(1_000)),
+                    balance: Box::new(U256::ZERO),
+                }
+                .into(),
+                InvalidTransaction::NotEnoughTokenBalanceForTransfer {
+                    token_id: Box::new(U256::from(7)),
+                    required_balance: Box::new(U256::from(1)),
+                    actual_balance: Box::new(U256::ZERO),
+                }
+                .into(),
+            ]
+        );
+
+        // The first problem is the one `transact` fails with
+        assert!(matches!(
+            evm.transact(),
+            Err(EVMError::Header(InvalidHeader::PrevrandaoNotSet))
+        ));
+    }
+
+    #[test]
+    fn sponsored_transaction_is_paid_by_gas_payer() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let payer = address!("00000000000000000000000000000000000000ee");
+        let payer_balance = U256::from(1_000_000);
+
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            payer,
+            AccountInfo {
+                balances: init_balances(payer_balance),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to =
+                    TransactTo::Call(address!("00000000000000000000000000000000000000bb"));
+                tx.gas_limit = 100_000;
+                tx.gas_price = U256::from(1);
+                tx.gas_payer = Some(payer);
+            })
+            .build();
+
+        // The caller can't pay for the gas, but the payer can
+        assert!(evm.preflight().unwrap().is_empty());
+        let result = evm.transact_commit().unwrap();
+        assert!(result.is_success());
+
+        let gas_used = U256::from(result.gas_used());
+        assert_eq!(
+            evm.context.evm.balance(BASE_TOKEN_ID, payer).unwrap().0,
+            payer_balance - gas_used
+        );
+        let caller_account = evm.context.evm.db.basic(caller).unwrap().unwrap();
+        assert_eq!(caller_account.get_base_balance(), U256::ZERO);
+        assert_eq!(caller_account.nonce, 1);
+
+        // The payer can't pay for more gas than it has
+        evm.tx_mut().gas_limit = 2_000_000;
+        evm.tx_mut().nonce = None;
+        assert_eq!(
+            evm.preflight().unwrap(),
+            vec![InvalidTransaction::GasPayerLackOfFundForMaxFee {
+                fee: Box::new(U256::from(2_000_000)),
+                balance: Box::new(payer_balance - gas_used),
+            }
+            .into()]
+        );
+        assert!(matches!(
+            evm.transact(),
+            Err(EVMError::Transaction(
+                InvalidTransaction::GasPayerLackOfFundForMaxFee { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn set_code_transaction_runs_delegated_code() {
+        let authority = address!("00000000000000000000000000000000000000aa");
+        let sponsor = address!("00000000000000000000000000000000000000bb");
+        let other_authority = address!("00000000000000000000000000000000000000cc");
+        let delegate = address!("00000000000000000000000000000000000000dd");
+
+        // Stores 1 in slot 0 of the account the code runs for
+        let code = Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH1,
+            0x1,
+            opcode::PUSH1,
+            0x0,
+            opcode::SSTORE,
+            opcode::STOP,
+        ]));
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            delegate,
+            AccountInfo {
+                code_hash: code.hash_slow(),
+                code: Some(code),
+                ..Default::default()
+            },
+        );
+        let authorization = |authority, nonce| {
+            RecoveredAuthorization::new_unchecked(
+                Authorization {
+                    chain_id: 0,
+                    address: delegate,
+                    nonce,
+                },
+                Some(authority),
+            )
+        };
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_spec_id(SpecId::PRAGUE)
+            .modify_tx_env(|tx| {
+                tx.caller = sponsor;
+                tx.transact_to = TransactTo::Call(authority);
+                tx.gas_limit = 200_000;
+                // the second authorization has the wrong nonce and is skipped
+                tx.authorization_list = vec![
+                    authorization(authority, 0),
+                    authorization(other_authority, 1),
+                ];
+            })
+            .build();
+
+        let result = evm.transact_commit().unwrap();
+        assert!(result.is_success());
+
+        let db = &mut evm.context.evm.db;
+        let account = db.basic(authority).unwrap().unwrap();
+        assert_eq!(account.nonce, 1);
+        assert_eq!(account.code.unwrap().eip7702_address(), Some(delegate));
+        assert_eq!(db.storage(authority, U256::ZERO).unwrap(), U256::from(1));
+        assert_eq!(db.storage(delegate, U256::ZERO).unwrap(), U256::ZERO);
+        let other_account = db.basic(other_authority).unwrap().unwrap_or_default();
+        assert_eq!(other_account.nonce, 0);
+        assert!(other_account.code.unwrap_or_default().is_empty());
+
+        // The delegating EOA can still send transactions
+        evm.tx_mut().caller = authority;
+        evm.tx_mut().transact_to = TransactTo::Call(sponsor);
+        evm.tx_mut().authorization_list.clear();
+        assert!(evm.preflight().unwrap().is_empty());
+        assert!(evm.transact().unwrap().result.is_success());
+    }
+
+    #[test]
+    fn applies_block_emissions_once_per_block() {
+        let validator = address!("00000000000000000000000000000000000000cc");
+        let reward_token_id = U256::from(9);
+        let mut db = InMemoryDB::default();
+        db.register_token(BASE_TOKEN_ID);
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_block_env(|block| {
+                block.number = U256::from(1);
+                block.emissions = vec![
+                    TokenEmission {
+                        recipient: validator,
+                        token_id: BASE_TOKEN_ID,
+                        amount: U256::from(5),
+                    },
+                    TokenEmission {
+                        recipient: validator,
+                        token_id: reward_token_id,
+                        amount: U256::from(3),
+                    },
+                ];
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = address!("00000000000000000000000000000000000000aa");
+                tx.transact_to =
+                    TransactTo::Call(address!("00000000000000000000000000000000000000bb"));
+            })
+            .build();
+        let balance = |evm: &mut Evm<'_, (), InMemoryDB>, token_id| {
+            evm.db_mut()
+                .basic(validator)
+                .unwrap()
+                .unwrap_or_default()
+                .get_balance(token_id)
+        };
+
+        // the first transaction of the block mints the emissions, registering the new token
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(balance(&mut evm, BASE_TOKEN_ID), U256::from(5));
+        assert_eq!(balance(&mut evm, reward_token_id), U256::from(3));
+        assert_eq!(evm.db().token_ids, vec![BASE_TOKEN_ID, reward_token_id]);
+        assert_eq!(evm.db().accounts[&EMISSIONS_ADDRESS].info.nonce, 1);
+
+        // but not the next ones
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(balance(&mut evm, BASE_TOKEN_ID), U256::from(5));
+
+        // which the next block mints again
+        evm.block_mut().number = U256::from(2);
+        let result_and_state = evm.transact().unwrap();
+        assert_eq!(
+            result_and_state.state.accounts[&validator]
+                .info
+                .get_balance(BASE_TOKEN_ID),
+            U256::from(10)
+        );
+
+        evm.block_mut().emissions[0].amount = U256::MAX;
+        assert_eq!(
+            evm.transact().unwrap_err(),
+            EVMError::Header(InvalidHeader::EmissionOverflow)
+        );
+    }
+
+    #[test]
+    fn authorization_list_requires_prague() {
+        let mut evm = Evm::builder()
+            .with_db(InMemoryDB::default())
+            .with_spec_id(SpecId::CANCUN)
+            .modify_tx_env(|tx| {
+                tx.authorization_list = vec![RecoveredAuthorization::new_unchecked(
+                    Authorization::default(),
+                    None,
+                )];
+            })
+            .build();
+        assert!(matches!(
+            evm.transact(),
+            Err(EVMError::Transaction(
+                InvalidTransaction::AuthorizationListNotSupported
+            ))
+        ));
+    }
+
+    #[test]
+    fn impersonated_contracts_send_transactions() {
+        let contract = address!("00000000000000000000000000000000000000aa");
+        let other_contract = address!("00000000000000000000000000000000000000bb");
+        let recipient = address!("00000000000000000000000000000000000000cc");
+        let mut db = InMemoryDB::default();
+        for address in [contract, other_contract] {
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    balances: init_balances(U256::from(10)),
+                    code: Some(Bytecode::new_raw(Bytes::from_static(&[opcode::STOP]))),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = contract;
+                tx.transact_to = TransactTo::Call(recipient);
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(4),
+                }];
+            })
+            .build();
+
+        // EIP-3607 rejects the transactions of contracts
+        assert!(matches!(
+            evm.transact(),
+            Err(EVMError::Transaction(
+                InvalidTransaction::RejectCallerWithCode
+            ))
+        ));
+
+        evm.cfg_mut().impersonation = Impersonation::Accounts([contract].into_iter().collect());
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.context.balance(BASE_TOKEN_ID, recipient).unwrap().0,
+            U256::from(4)
+        );
+
+        // the other contracts are still rejected, unless every caller is impersonated
+        evm.tx_mut().caller = other_contract;
+        assert!(matches!(
+            evm.transact(),
+            Err(EVMError::Transaction(
+                InvalidTransaction::RejectCallerWithCode
+            ))
+        ));
+        evm.cfg_mut().impersonation = Impersonation::All;
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.context.balance(BASE_TOKEN_ID, recipient).unwrap().0,
+            U256::from(8)
+        );
+    }
+
+    #[test]
+    fn prices_memory_with_the_configured_quotient() {
+        let contract = address!("00000000000000000000000000000000000000bb");
+        // loads the word at 0x10000, which expands the memory to 2049 words
+        let code = [opcode::PUSH3, 1, 0, 0, opcode::MLOAD, opcode::STOP];
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::copy_from_slice(&code))),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.transact_to = TransactTo::Call(contract);
+                tx.gas_limit = 100_000;
+            })
+            .build();
+
+        let words = 2049;
+        let default_gas = evm.transact().unwrap().result.gas_used();
+        evm.cfg_mut().memory_gas_quotient = 64;
+        let expensive_gas = evm.transact().unwrap().result.gas_used();
+        assert_eq!(
+            expensive_gas - default_gas,
+            words * words / 64 - words * words / 512
+        );
+
+        // the quadratic term alone exceeds the gas limit
+        evm.cfg_mut().memory_gas_quotient = 1;
+        assert_eq!(
+            evm.transact().unwrap().result,
+            ExecutionResult::Halt {
+                reason: HaltReason::OutOfGas(OutOfGasError::Memory),
+                gas_used: 100_000,
+            }
+        );
+
+        #[cfg(feature = "memory_limit")]
+        {
+            evm.cfg_mut().memory_gas_quotient = crate::primitives::MEMORY_GAS_QUOTIENT;
+            evm.cfg_mut().memory_limit = 0x10000;
+            assert_eq!(
+                evm.transact().unwrap().result,
+                ExecutionResult::Halt {
+                    reason: HaltReason::OutOfGas(OutOfGasError::MemoryLimit),
+                    gas_used: 100_000,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn selfdestruct_to_the_burn_address_burns_every_token() {
+        #[derive(Default)]
+        struct SelfdestructRecorder(Vec<(Address, Address, Vec<TokenTransfer>)>);
+
+        impl<DB: Database> Inspector<DB> for SelfdestructRecorder {
+            fn selfdestruct(
+                &mut self,
+                contract: Address,
+                target: Address,
+                values: &[TokenTransfer],
+            ) {
+                self.0.push((contract, target, values.to_vec()));
+            }
+        }
+
+        let contract = address!("00000000000000000000000000000000000000bb");
+        let burn_address = address!("00000000000000000000000000000000000000dd");
+        let token_id = U256::from(7);
+        let mut code = vec![opcode::PUSH20];
+        code.extend_from_slice(burn_address.as_slice());
+        code.push(opcode::SELFDESTRUCT);
+        let balances = vec![
+            TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(3),
+            },
+            TokenTransfer {
+                id: token_id,
+                amount: U256::from(5),
+            },
+        ];
+        let mut db = InMemoryDB::default();
+        db.token_ids.push(token_id);
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                balances: balances
+                    .iter()
+                    .map(|balance| (balance.id, balance.amount))
+                    .collect(),
+                code: Some(Bytecode::new_raw(code.into())),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(SelfdestructRecorder::default())
+            .modify_cfg_env(|cfg| cfg.burn_address = Some(burn_address))
+            .modify_tx_env(|tx| {
+                tx.transact_to = TransactTo::Call(contract);
+                tx.gas_limit = 100_000;
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+
+        let ResultAndState { result, state } = evm.transact().unwrap();
+        assert!(result.is_success());
+        for balance in &balances {
+            assert_eq!(
+                state.accounts[&contract].info.get_balance(balance.id),
+                U256::ZERO
+            );
+            assert_eq!(
+                state.accounts[&burn_address].info.get_balance(balance.id),
+                U256::ZERO
+            );
+        }
+        assert_eq!(
+            result.supply_changes().unwrap().burned(token_id),
+            U256::from(5)
+        );
+        assert_eq!(
+            evm.context.external.0,
+            vec![(contract, burn_address, balances)]
+        );
+    }
+
+    #[test]
+    fn reverted_set_code_transactions_refund_the_authorizations() {
+        let authority = address!("00000000000000000000000000000000000000aa");
+        let sponsor = address!("00000000000000000000000000000000000000bb");
+        let delegate = address!("00000000000000000000000000000000000000dd");
+
+        // Reverts the transaction
+        let code = Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH1,
+            0x0,
+            opcode::PUSH1,
+            0x0,
+            opcode::REVERT,
+        ]));
+        let gas_used = |authority_exists: bool| {
+            let mut db = InMemoryDB::default();
+            db.insert_account_info(
+                delegate,
+                AccountInfo {
+                    code_hash: code.hash_slow(),
+                    code: Some(code.clone()),
+                    ..Default::default()
+                },
+            );
+            if authority_exists {
+                db.insert_account_info(
+                    authority,
+                    AccountInfo {
+                        balances: init_balances(U256::from(1)),
+                        ..Default::default()
+                    },
+                );
+            }
+            let mut evm = Evm::builder()
+                .with_db(db)
+                .with_spec_id(SpecId::PRAGUE)
+                .modify_tx_env(|tx| {
+                    tx.caller = sponsor;
+                    tx.transact_to = TransactTo::Call(authority);
+                    tx.gas_limit = 200_000;
+                    tx.authorization_list = vec![RecoveredAuthorization::new_unchecked(
+                        Authorization {
+                            chain_id: 0,
+                            address: delegate,
+                            nonce: 0,
+                        },
+                        Some(authority),
+                    )];
+                })
+                .build();
+            let result = evm.transact().unwrap().result;
+            assert!(matches!(result, ExecutionResult::Revert { .. }));
+            result.gas_used()
+        };
+
+        // the authorization of an existing account is refunded although the transaction reverts
+        assert!(gas_used(true) < gas_used(false));
+    }
+}
@@ -1,13 +1,16 @@
 use crate::{
-    interpreter::Interpreter,
-    primitives::{Address, Output},
-    JournalCheckpoint,
+    interpreter::{analysis::to_analysed, Interpreter, SharedMemory},
+    primitives::{
+        Address, Bytecode, HashMap, Output, TokenTransfer, TokenTransfers, B256, KECCAK_EMPTY,
+    },
+    JournalCheckpoint, CALL_STACK_LIMIT,
 };
 use core::ops::Range;
 use revm_interpreter::{
-    CallOutcome, CreateOutcome, EOFCreateOutcome, Gas, InstructionResult, InterpreterResult,
+    CallInputs, CallOutcome, CreateOutcome, EOFCreateOutcome, Gas, InstructionResult,
+    InterpreterResult,
 };
-use std::boxed::Box;
+use std::{boxed::Box, vec::Vec};
 
 /// Call CallStackFrame.
 #[derive(Debug)]
@@ -15,6 +18,16 @@ use std::boxed::Box;
 pub struct CallFrame {
     /// Call frame has return memory range where output will be stored.
     pub return_memory_range: Range<usize>,
+    /// Gas withheld from the interpreter that is given back to the caller when the frame returns.
+    ///
+    /// Only non-zero for calls spawned by a precompile, which keep one 64th of the gas left in
+    /// the precompile frame, as `CALL` does.
+    pub retained_gas: u64,
+    /// Inputs of the call, for calls spawned by a precompile.
+    ///
+    /// The spawned call runs in place of the precompile frame, which is made with the inputs of
+    /// the call to the precompile.
+    pub spawned_call: Option<Box<CallInputs>>,
     /// Frame data.
     pub frame_data: FrameData,
 }
@@ -165,6 +178,8 @@ impl Frame {
     ) -> Self {
         Frame::Call(Box::new(CallFrame {
             return_memory_range,
+            retained_gas: 0,
+            spawned_call: None,
             frame_data: FrameData {
                 checkpoint,
                 interpreter,
@@ -228,6 +243,181 @@ impl Frame {
     }
 }
 
+/// The frames of a running transaction, with the memory they share.
+///
+/// The frames don't run on the native stack: a call or a create pushes the frame it makes, which
+/// runs until it makes its own call or returns. An embedder can drive the execution one frame at
+/// a time, e.g. to schedule transactions or to pause between frames: [Evm::start_frames] makes
+/// the stack of the first frame, [Evm::step_frame] runs the top frame and is called until it
+/// returns the result of the first frame, and [Evm::finish_frames] hands the allocations of the
+/// stack back to the context.
+///
+/// [Evm::start_frames]: crate::Evm::start_frames
+/// [Evm::finish_frames]: crate::Evm::finish_frames
+/// [Evm::step_frame]: crate::Evm::step_frame
+#[derive(Debug)]
+pub struct FrameStack {
+    /// The frames, from the first frame to the running one.
+    pub(crate) frames: Vec<Frame>,
+    /// The memory of the frames, with a context per frame.
+    pub(crate) shared_memory: SharedMemory,
+}
+
+impl FrameStack {
+    /// Creates an empty frame stack on top of the call stack and the shared memory.
+    #[inline]
+    pub fn new(frames: Vec<Frame>, shared_memory: SharedMemory) -> Self {
+        Self {
+            frames,
+            shared_memory,
+        }
+    }
+
+    /// Pushes the frame, which runs at the next step, with a new memory context.
+    #[inline]
+    pub fn push(&mut self, frame: Frame) {
+        self.shared_memory.new_context();
+        self.frames.push(frame);
+    }
+
+    /// Returns the frames, from the first frame to the running one.
+    #[inline]
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Returns the frame that runs at the next step.
+    #[inline]
+    pub fn top(&self) -> Option<&Frame> {
+        self.frames.last()
+    }
+
+    /// Returns the number of frames.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if there is no frame left, i.e. if the first frame returned.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the memory shared by the frames.
+    #[inline]
+    pub fn shared_memory(&self) -> &SharedMemory {
+        &self.shared_memory
+    }
+
+    /// Consumes the frame stack, returning its call stack and its shared memory.
+    #[inline]
+    pub fn into_parts(self) -> (Vec<Frame>, SharedMemory) {
+        (self.frames, self.shared_memory)
+    }
+}
+
+/// Maximum number of analysed bytecodes held by a [FramePool]. The pool drops them all when it
+/// is full.
+pub const ANALYSED_CODE_LIMIT: usize = 1024;
+
+/// Pool of allocations that are reused between call frames and across transactions.
+#[derive(Debug, Default)]
+pub struct FramePool {
+    /// Call stack, kept for its capacity.
+    call_stack: Vec<Frame>,
+    /// Free list of token transfer vectors that outgrew their inline storage.
+    token_transfers: Vec<TokenTransfers>,
+    /// Analysed legacy bytecode, by code hash.
+    analysed_code: HashMap<B256, Bytecode>,
+}
+
+impl FramePool {
+    /// Takes the pooled call stack, empty and with room for the maximum call depth.
+    #[inline]
+    pub fn take_call_stack(&mut self) -> Vec<Frame> {
+        let mut call_stack = core::mem::take(&mut self.call_stack);
+        call_stack.clear();
+        call_stack.reserve(CALL_STACK_LIMIT as usize + 1);
+        call_stack
+    }
+
+    /// Returns the call stack to the pool so its allocation can be reused.
+    #[inline]
+    pub fn return_call_stack(&mut self, call_stack: Vec<Frame>) {
+        self.call_stack = call_stack;
+    }
+
+    /// Returns a vector holding a copy of `values`, reusing a pooled allocation if there is one.
+    #[inline]
+    pub fn token_transfers_from<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = &'a TokenTransfer>,
+    ) -> TokenTransfers {
+        let mut token_transfers = self.token_transfers.pop().unwrap_or_default();
+        token_transfers.extend(values.into_iter().cloned());
+        token_transfers
+    }
+
+    /// Puts the token transfer vector back into the pool.
+    ///
+    /// Vectors stored inline, which don't own an allocation, are dropped, and the pool never
+    /// holds more vectors than the maximum call depth.
+    #[inline]
+    pub fn recycle_token_transfers(&mut self, mut token_transfers: TokenTransfers) {
+        if !token_transfers.spilled() || self.token_transfers.len() > CALL_STACK_LIMIT as usize {
+            return;
+        }
+        token_transfers.clear();
+        self.token_transfers.push(token_transfers);
+    }
+
+    /// Hands the token transfers of a finished frame back to the pool.
+    #[inline]
+    pub fn recycle_frame(&mut self, frame: &mut Frame) {
+        let call_values = core::mem::take(&mut frame.interpreter_mut().contract.call_values);
+        self.recycle_token_transfers(call_values);
+    }
+
+    /// Returns the number of pooled token transfer vectors.
+    #[inline]
+    pub fn pooled_token_transfers(&self) -> usize {
+        self.token_transfers.len()
+    }
+
+    /// Returns the analysed bytecode of the code with the hash, analysing and pooling it the
+    /// first time.
+    ///
+    /// Raw legacy bytecode is copied with padding and scanned for jump destinations by every
+    /// frame running it, so the analysed bytecode is kept instead. Its clones share the padded
+    /// bytes and the jump table. Bytecode that is already analysed and bytecode without a hash
+    /// aren't pooled.
+    #[inline]
+    pub fn analysed_code(&mut self, code_hash: B256, bytecode: Bytecode) -> Bytecode {
+        if !matches!(bytecode, Bytecode::LegacyRaw(_))
+            || code_hash == KECCAK_EMPTY
+            || code_hash == B256::ZERO
+        {
+            return to_analysed(bytecode);
+        }
+        if let Some(analysed) = self.analysed_code.get(&code_hash) {
+            return analysed.clone();
+        }
+        if self.analysed_code.len() >= ANALYSED_CODE_LIMIT {
+            self.analysed_code.clear();
+        }
+        let analysed = to_analysed(bytecode);
+        self.analysed_code.insert(code_hash, analysed.clone());
+        analysed
+    }
+
+    /// Returns the number of pooled analysed bytecodes.
+    #[inline]
+    pub fn pooled_code(&self) -> usize {
+        self.analysed_code.len()
+    }
+}
+
 impl FrameOrResult {
     /// Creates new create frame.
     pub fn new_create_frame(
@@ -267,6 +457,25 @@ impl FrameOrResult {
         ))
     }
 
+    /// Creates new call frame for a call spawned by a precompile, which gives `retained_gas` back
+    /// to the caller when it returns.
+    pub fn new_spawned_call_frame(
+        inputs: Box<CallInputs>,
+        retained_gas: u64,
+        checkpoint: JournalCheckpoint,
+        interpreter: Interpreter,
+    ) -> Self {
+        Self::Frame(Frame::Call(Box::new(CallFrame {
+            return_memory_range: inputs.return_memory_offset.clone(),
+            retained_gas,
+            spawned_call: Some(inputs),
+            frame_data: FrameData {
+                checkpoint,
+                interpreter,
+            },
+        })))
+    }
+
     /// Creates new create result.
     pub fn new_create_result(
         interpreter_result: InterpreterResult,
@@ -300,3 +509,71 @@ impl FrameOrResult {
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{BASE_TOKEN_ID, U256};
+    use std::vec;
+
+    #[test]
+    fn frame_pool_reuses_token_transfers() {
+        let mut pool = FramePool::default();
+        let values = vec![
+            TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(1),
+            },
+            TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(2),
+            },
+        ];
+
+        let first = pool.token_transfers_from(&values);
+        assert_eq!(first.as_slice(), values);
+        let ptr = first.as_ptr();
+        pool.recycle_token_transfers(first);
+        assert_eq!(pool.pooled_token_transfers(), 1);
+
+        let second = pool.token_transfers_from(&values);
+        assert_eq!(second.as_slice(), values);
+        assert_eq!(second.as_ptr(), ptr);
+        assert_eq!(pool.pooled_token_transfers(), 0);
+
+        // a single token transfer is stored inline, so it is not worth pooling.
+        let single = pool.token_transfers_from(&values[..1]);
+        assert!(!single.spilled());
+        pool.recycle_token_transfers(single);
+        assert_eq!(pool.pooled_token_transfers(), 0);
+    }
+
+    #[test]
+    fn frame_pool_reuses_call_stack() {
+        let mut pool = FramePool::default();
+        let call_stack = pool.take_call_stack();
+        assert!(call_stack.capacity() > CALL_STACK_LIMIT as usize);
+        let ptr = call_stack.as_ptr();
+        pool.return_call_stack(call_stack);
+        let call_stack = pool.take_call_stack();
+        assert_eq!(call_stack.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn frame_pool_reuses_analysed_code() {
+        let mut pool = FramePool::default();
+        let raw = Bytecode::new_raw(vec![0x60, 0x01, 0x56, 0x5b].into());
+        let code_hash = raw.hash_slow();
+
+        let first = pool.analysed_code(code_hash, raw.clone());
+        assert!(matches!(first, Bytecode::LegacyAnalyzed(_)));
+        let second = pool.analysed_code(code_hash, raw.clone());
+        assert_eq!(second.bytecode().as_ptr(), first.bytecode().as_ptr());
+        assert_eq!(pool.pooled_code(), 1);
+
+        // code without a hash is analysed every time
+        let unhashed = pool.analysed_code(KECCAK_EMPTY, raw);
+        assert_ne!(unhashed.bytecode().as_ptr(), first.bytecode().as_ptr());
+        assert_eq!(pool.pooled_code(), 1);
+    }
+}
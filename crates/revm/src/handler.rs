@@ -1,3 +1,8 @@
+//! Handler that defines the behavior of the EVM, and the extension points of other chains.
+//!
+//! The handle types, the [mainnet] handles and the [ChainHandler] trait are the interface used
+//! by crates that provide the handler of another chain, and they are kept stable.
+
 // Modules.
 mod handle_types;
 pub mod mainnet;
@@ -30,6 +35,8 @@ pub struct Handler<'a, H: Host + 'a, EXT, DB: Database> {
     pub registers: Vec<HandleRegisters<EXT, DB>>,
     /// Validity handles.
     pub validation: ValidationHandler<'a, EXT, DB>,
+    /// Intrinsic gas handles.
+    pub intrinsic_gas: IntrinsicGasHandler<'a>,
     /// Pre execution handle.
     pub pre_execution: PreExecutionHandler<'a, EXT, DB>,
     /// Post Execution handle.
@@ -38,6 +45,22 @@ pub struct Handler<'a, H: Host + 'a, EXT, DB: Database> {
     pub execution: ExecutionHandler<'a, EXT, DB>,
 }
 
+/// A chain whose handler is the mainnet one with the handles of the chain registered over it.
+///
+/// Crates that provide the handler of another chain implement this trait, and the handler is
+/// built with [`Handler::chain`] or
+/// [`EvmBuilder::with_chain_handler`](crate::EvmBuilder::with_chain_handler).
+pub trait ChainHandler {
+    /// Returns the configuration of the handler of the chain at the spec.
+    #[inline]
+    fn handler_cfg(spec_id: SpecId) -> HandlerCfg {
+        HandlerCfg::new(spec_id)
+    }
+
+    /// Replaces the mainnet handles with the handles of the chain, for the spec of the handler.
+    fn handle_register<EXT, DB: Database>(handler: &mut EvmHandler<'_, EXT, DB>);
+}
+
 impl<'a, EXT, DB: Database> EvmHandler<'a, EXT, DB> {
     /// Created new Handler with given configuration.
     ///
@@ -64,6 +87,7 @@ impl<'a, EXT, DB: Database> EvmHandler<'a, EXT, DB> {
             instruction_table: InstructionTables::new_plain::<SPEC>(),
             registers: Vec::new(),
             validation: ValidationHandler::new::<SPEC>(),
+            intrinsic_gas: IntrinsicGasHandler::new::<SPEC>(),
             pre_execution: PreExecutionHandler::new::<SPEC>(),
             post_execution: PostExecutionHandler::new::<SPEC>(),
             execution: ExecutionHandler::new::<SPEC>(),
@@ -75,21 +99,18 @@ impl<'a, EXT, DB: Database> EvmHandler<'a, EXT, DB> {
         self.cfg.is_optimism()
     }
 
-    /// Handler for optimism
-    #[cfg(feature = "optimism")]
-    pub fn optimism<SPEC: Spec>() -> Self {
+    /// Handler for the chain: the mainnet handler with the handles of the chain registered over
+    /// it.
+    pub fn chain<C: ChainHandler, SPEC: Spec>() -> Self {
         let mut handler = Self::mainnet::<SPEC>();
-        handler.cfg.is_optimism = true;
-        handler.append_handler_register(HandleRegisters::Plain(
-            crate::optimism::optimism_handle_register::<DB, EXT>,
-        ));
+        handler.cfg = C::handler_cfg(SPEC::SPEC_ID);
+        handler.append_handler_register(HandleRegisters::Plain(C::handle_register::<EXT, DB>));
         handler
     }
 
-    /// Optimism with spec. Similar to [`Self::mainnet_with_spec`].
-    #[cfg(feature = "optimism")]
-    pub fn optimism_with_spec(spec_id: SpecId) -> Self {
-        spec_to_generic!(spec_id, Self::optimism::<SPEC>())
+    /// Handler for the chain with spec. Similar to [`Self::mainnet_with_spec`].
+    pub fn chain_with_spec<C: ChainHandler>(spec_id: SpecId) -> Self {
+        spec_to_generic!(spec_id, Self::chain::<C, SPEC>())
     }
 
     /// Creates handler with variable spec id, inside it will call `mainnet::<SPEC>` for
@@ -153,6 +174,11 @@ impl<'a, EXT, DB: Database> EvmHandler<'a, EXT, DB> {
         &self.validation
     }
 
+    /// Returns reference to intrinsic gas handler.
+    pub fn intrinsic_gas(&self) -> &IntrinsicGasHandler<'a> {
+        &self.intrinsic_gas
+    }
+
     /// Append handle register.
     pub fn append_handler_register(&mut self, register: HandleRegisters<EXT, DB>) {
         register.register(self);
@@ -220,7 +246,15 @@ impl<'a, EXT, DB: Database> EvmHandler<'a, EXT, DB> {
 mod test {
     use core::cell::RefCell;
 
-    use crate::{db::EmptyDB, primitives::EVMError};
+    use crate::{
+        db::{EmptyDB, InMemoryDB},
+        interpreter::gas,
+        primitives::{
+            address, init_balances, AccountInfo, Bytes, EVMError, InvalidTransaction,
+            ResultAndState, TransactTo, U256,
+        },
+        Evm,
+    };
     use std::{rc::Rc, sync::Arc};
 
     use super::*;
@@ -249,4 +283,105 @@ mod test {
         // first handler is reapplied
         assert_eq!(*test.borrow(), 3);
     }
+
+    /// A chain that doesn't reward the beneficiary.
+    struct NoRewardChain;
+
+    impl ChainHandler for NoRewardChain {
+        fn handle_register<EXT, DB: Database>(handler: &mut EvmHandler<'_, EXT, DB>) {
+            handler.post_execution.reward_beneficiary = Arc::new(|_, _| Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_chain_handler() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let coinbase = address!("00000000000000000000000000000000000000cc");
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_spec_id(SpecId::CANCUN)
+            .with_chain_handler::<NoRewardChain>()
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(coinbase);
+                tx.gas_limit = 21_000;
+                tx.gas_price = U256::from(1);
+            })
+            .modify_block_env(|block| block.coinbase = coinbase)
+            .build();
+        assert_eq!(evm.handler.cfg(), HandlerCfg::new(SpecId::CANCUN));
+
+        let ResultAndState { result, state } = evm.transact().unwrap();
+        assert!(result.is_success());
+        assert_eq!(
+            state.accounts[&coinbase].info.get_base_balance(),
+            U256::ZERO
+        );
+
+        // the chain handles are kept when the spec changes
+        evm.modify_spec_id(SpecId::PRAGUE);
+        assert_eq!(evm.handler.registers.len(), 1);
+    }
+
+    #[test]
+    fn test_intrinsic_gas_handles() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let callee = address!("00000000000000000000000000000000000000bb");
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        let evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(callee);
+                tx.data = Bytes::from_static(&[1, 0, 2]);
+                tx.gas_limit = 100_000;
+            })
+            .build();
+        let env = &evm.context.evm.env;
+        assert_eq!(
+            evm.handler.intrinsic_gas().total(env),
+            gas::validate_initial_tx_gas(SpecId::LATEST, &[1, 0, 2], false, &[], &[], &[], 0)
+        );
+        // the creation surcharge is only charged to contract creations
+        assert_eq!(evm.handler.intrinsic_gas().total(env), 21_000 + 16 + 4 + 16);
+
+        // a chain charging a flat price for the calldata, whatever its bytes
+        let mut evm = evm
+            .modify()
+            .append_handler_register(|handler| {
+                handler.intrinsic_gas.calldata = Arc::new(|env| env.tx.data.len() as u64 * 10);
+            })
+            .build();
+        let result = evm.transact().unwrap().result;
+        assert_eq!(result.gas_used(), 21_000 + 30);
+        // and the breakdown reports the intrinsic gas it priced
+        assert_eq!(result.gas_breakdown().unwrap().intrinsic, 21_000 + 30);
+
+        evm.tx_mut().gas_limit = 21_029;
+        assert!(matches!(
+            evm.transact(),
+            Err(EVMError::Transaction(
+                InvalidTransaction::CallGasCostMoreThanGasLimit
+            ))
+        ));
+        assert!(evm
+            .preflight()
+            .unwrap()
+            .contains(&InvalidTransaction::CallGasCostMoreThanGasLimit.into()));
+    }
 }
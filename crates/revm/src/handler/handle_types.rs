@@ -1,6 +1,7 @@
 // Modules
 
 pub mod execution;
+pub mod intrinsic_gas;
 pub mod post_execution;
 pub mod pre_execution;
 pub mod validation;
@@ -11,13 +12,16 @@ pub use validation::{
     ValidateEnvHandle, ValidateInitialTxGasHandle, ValidateTxEnvAgainstState, ValidationHandler,
 };
 
+pub use intrinsic_gas::{IntrinsicGasHandle, IntrinsicGasHandler};
+
 pub use execution::{
     ExecutionHandler, FrameCallHandle, FrameCallReturnHandle, FrameCreateHandle,
     FrameCreateReturnHandle, InsertCallOutcomeHandle, InsertCreateOutcomeHandle,
 };
 
 pub use pre_execution::{
-    DeductCallerHandle, LoadAccountsHandle, LoadPrecompilesHandle, PreExecutionHandler,
+    ApplyEIP7702AuthListHandle, DeductCallerHandle, LoadAccountsHandle, LoadPrecompilesHandle,
+    PreExecutionHandler,
 };
 
 pub use post_execution::{
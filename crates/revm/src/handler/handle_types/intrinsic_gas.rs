@@ -0,0 +1,60 @@
+use crate::{
+    handler::mainnet,
+    primitives::{Env, Spec},
+};
+use std::sync::Arc;
+
+/// Returns a part of the intrinsic gas of the transaction.
+pub type IntrinsicGasHandle<'a> = Arc<dyn Fn(&Env) -> u64 + 'a>;
+
+/// Handles that price the parts of the intrinsic gas, charged before a transaction is executed.
+///
+/// Chains can tune the pricing of a part by replacing its handle, without replacing the
+/// [validation](crate::handler::ValidationHandler) handles, which check the [total] against the
+/// gas limit of the transaction.
+///
+/// [total]: IntrinsicGasHandler::total
+pub struct IntrinsicGasHandler<'a> {
+    /// Base stipend of every transaction.
+    pub base: IntrinsicGasHandle<'a>,
+    /// Input data of the transaction.
+    pub calldata: IntrinsicGasHandle<'a>,
+    /// Access list and token access list.
+    pub access_list: IntrinsicGasHandle<'a>,
+    /// Surcharge of contract creations, including the init code. Only charged if the
+    /// transaction creates a contract.
+    pub creation: IntrinsicGasHandle<'a>,
+    /// Authorizations of set-code transactions.
+    pub authorization_list: IntrinsicGasHandle<'a>,
+    /// Transferred Native Tokens.
+    pub transferred_tokens: IntrinsicGasHandle<'a>,
+}
+
+impl<'a> IntrinsicGasHandler<'a> {
+    /// Creates the mainnet handles.
+    pub fn new<SPEC: Spec + 'a>() -> Self {
+        Self {
+            base: Arc::new(mainnet::base_gas::<SPEC>),
+            calldata: Arc::new(mainnet::calldata_gas::<SPEC>),
+            access_list: Arc::new(mainnet::access_list_gas::<SPEC>),
+            creation: Arc::new(mainnet::creation_gas::<SPEC>),
+            authorization_list: Arc::new(mainnet::authorization_list_gas::<SPEC>),
+            transferred_tokens: Arc::new(mainnet::transferred_tokens_gas::<SPEC>),
+        }
+    }
+}
+
+impl IntrinsicGasHandler<'_> {
+    /// Returns the intrinsic gas of the transaction, the sum of its parts.
+    pub fn total(&self, env: &Env) -> u64 {
+        let mut total = (self.base)(env)
+            .saturating_add((self.calldata)(env))
+            .saturating_add((self.access_list)(env))
+            .saturating_add((self.authorization_list)(env))
+            .saturating_add((self.transferred_tokens)(env));
+        if env.tx.transact_to.is_create() {
+            total = total.saturating_add((self.creation)(env));
+        }
+        total
+    }
+}
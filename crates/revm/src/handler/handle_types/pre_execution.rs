@@ -19,6 +19,18 @@ pub type LoadAccountsHandle<'a, EXT, DB> =
 pub type DeductCallerHandle<'a, EXT, DB> =
     Arc<dyn Fn(&mut Context<EXT, DB>) -> EVMResultGeneric<(), <DB as Database>::Error> + 'a>;
 
+/// Writes the parent block hash to the EIP-2935 history storage contract.
+pub type ApplyBlockhashHistoryHandle<'a, EXT, DB> =
+    Arc<dyn Fn(&mut Context<EXT, DB>) -> EVMResultGeneric<(), <DB as Database>::Error> + 'a>;
+
+/// Mints the emissions of the block before its first transaction.
+pub type ApplyBlockEmissionsHandle<'a, EXT, DB> =
+    Arc<dyn Fn(&mut Context<EXT, DB>) -> EVMResultGeneric<(), <DB as Database>::Error> + 'a>;
+
+/// Applies the EIP-7702 authorization list, returning the gas refund.
+pub type ApplyEIP7702AuthListHandle<'a, EXT, DB> =
+    Arc<dyn Fn(&mut Context<EXT, DB>) -> EVMResultGeneric<u64, <DB as Database>::Error> + 'a>;
+
 /// Handles related to pre execution before the stack loop is started.
 pub struct PreExecutionHandler<'a, EXT, DB: Database> {
     /// Load precompiles
@@ -27,6 +39,12 @@ pub struct PreExecutionHandler<'a, EXT, DB: Database> {
     pub load_accounts: LoadAccountsHandle<'a, EXT, DB>,
     /// Deduct max value from the caller.
     pub deduct_caller: DeductCallerHandle<'a, EXT, DB>,
+    /// Write the parent block hash to the EIP-2935 history storage contract.
+    pub apply_blockhash_history: ApplyBlockhashHistoryHandle<'a, EXT, DB>,
+    /// Mint the emissions of the block before its first transaction.
+    pub apply_block_emissions: ApplyBlockEmissionsHandle<'a, EXT, DB>,
+    /// Apply the EIP-7702 authorization list.
+    pub apply_eip7702_auth_list: ApplyEIP7702AuthListHandle<'a, EXT, DB>,
 }
 
 impl<'a, EXT: 'a, DB: Database + 'a> PreExecutionHandler<'a, EXT, DB> {
@@ -36,6 +54,9 @@ impl<'a, EXT: 'a, DB: Database + 'a> PreExecutionHandler<'a, EXT, DB> {
             load_precompiles: Arc::new(mainnet::load_precompiles::<SPEC, DB>),
             load_accounts: Arc::new(mainnet::load_accounts::<SPEC, EXT, DB>),
             deduct_caller: Arc::new(mainnet::deduct_caller::<SPEC, EXT, DB>),
+            apply_blockhash_history: Arc::new(mainnet::apply_blockhash_history::<SPEC, EXT, DB>),
+            apply_block_emissions: Arc::new(mainnet::apply_block_emissions::<SPEC, EXT, DB>),
+            apply_eip7702_auth_list: Arc::new(mainnet::apply_eip7702_auth_list::<SPEC, EXT, DB>),
         }
     }
 }
@@ -46,6 +67,30 @@ impl<'a, EXT, DB: Database> PreExecutionHandler<'a, EXT, DB> {
         (self.deduct_caller)(context)
     }
 
+    /// Write the parent block hash to the EIP-2935 history storage contract.
+    pub fn apply_blockhash_history(
+        &self,
+        context: &mut Context<EXT, DB>,
+    ) -> Result<(), EVMError<DB::Error>> {
+        (self.apply_blockhash_history)(context)
+    }
+
+    /// Mint the emissions of the block before its first transaction.
+    pub fn apply_block_emissions(
+        &self,
+        context: &mut Context<EXT, DB>,
+    ) -> Result<(), EVMError<DB::Error>> {
+        (self.apply_block_emissions)(context)
+    }
+
+    /// Apply the EIP-7702 authorization list, returning the gas refund.
+    pub fn apply_eip7702_auth_list(
+        &self,
+        context: &mut Context<EXT, DB>,
+    ) -> Result<u64, EVMError<DB::Error>> {
+        (self.apply_eip7702_auth_list)(context)
+    }
+
     /// Main load
     pub fn load_accounts(&self, context: &mut Context<EXT, DB>) -> Result<(), EVMError<DB::Error>> {
         (self.load_accounts)(context)
@@ -14,13 +14,13 @@ pub type ValidateEnvHandle<'a, DB> =
 pub type ValidateTxEnvAgainstState<'a, EXT, DB> =
     Arc<dyn Fn(&mut Context<EXT, DB>) -> Result<(), EVMError<<DB as Database>::Error>> + 'a>;
 
-/// Initial gas calculation handle
+/// Initial gas validation handle, given the intrinsic gas of the transaction.
 pub type ValidateInitialTxGasHandle<'a, DB> =
-    Arc<dyn Fn(&Env) -> Result<u64, EVMError<<DB as Database>::Error>> + 'a>;
+    Arc<dyn Fn(&Env, u64) -> Result<u64, EVMError<<DB as Database>::Error>> + 'a>;
 
 /// Handles related to validation.
 pub struct ValidationHandler<'a, EXT, DB: Database> {
-    /// Validate and calculate initial transaction gas.
+    /// Validate initial transaction gas.
     pub initial_tx_gas: ValidateInitialTxGasHandle<'a, DB>,
     /// Validate transactions against state data.
     pub tx_against_state: ValidateTxEnvAgainstState<'a, EXT, DB>,
@@ -45,9 +45,13 @@ impl<'a, EXT, DB: Database> ValidationHandler<'a, EXT, DB> {
         (self.env)(env)
     }
 
-    /// Initial gas
-    pub fn initial_tx_gas(&self, env: &Env) -> Result<u64, EVMError<DB::Error>> {
-        (self.initial_tx_gas)(env)
+    /// Initial gas, given the intrinsic gas of the transaction.
+    pub fn initial_tx_gas(
+        &self,
+        env: &Env,
+        intrinsic_gas: u64,
+    ) -> Result<u64, EVMError<DB::Error>> {
+        (self.initial_tx_gas)(env, intrinsic_gas)
     }
 
     /// Validate ttansaction against the state.
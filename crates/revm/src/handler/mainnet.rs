@@ -1,6 +1,7 @@
 //! Mainnet related handlers.
 
 mod execution;
+mod intrinsic_gas;
 mod post_execution;
 mod pre_execution;
 mod validation;
@@ -10,6 +11,16 @@ pub use execution::{
     frame_return_with_refund_flag, insert_call_outcome, insert_create_outcome,
     insert_eofcreate_outcome, last_frame_return,
 };
-pub use post_execution::{clear, end, output, reimburse_caller, reward_beneficiary};
-pub use pre_execution::{deduct_caller, deduct_caller_inner, load_accounts, load_precompiles};
+pub use intrinsic_gas::{
+    access_list_gas, authorization_list_gas, base_gas, calldata_gas, creation_gas,
+    transferred_tokens_gas,
+};
+pub use post_execution::{
+    clear, end, gas_breakdown, output, reimburse_caller, reward_beneficiary, token_supply_changes,
+    token_transfer_summary,
+};
+pub use pre_execution::{
+    apply_block_emissions, apply_blockhash_history, apply_eip7702_auth_list, deduct_caller,
+    deduct_caller_inner, deduct_gas_payer_inner, load_accounts, load_precompiles,
+};
 pub use validation::{validate_env, validate_initial_tx_gas, validate_tx_against_state};
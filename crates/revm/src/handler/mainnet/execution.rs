@@ -5,7 +5,7 @@ use crate::{
         return_ok, return_revert, CallInputs, CreateInputs, CreateOutcome, Gas, InstructionResult,
         SharedMemory,
     },
-    primitives::{EVMError, Env, Spec, SpecId},
+    primitives::{EVMError, Env, Spec},
     CallFrame, Context, CreateFrame, Frame, FrameOrResult, FrameResult,
 };
 use core::mem;
@@ -63,12 +63,14 @@ pub fn frame_return_with_refund_flag<SPEC: Spec>(
 
     // Calculate gas refund for transaction.
     // If config is set to disable gas refund, it will return 0.
-    // If spec is set to london, it will decrease the maximum refund amount to 5th part of
-    // gas spend. (Before london it was 2th part of gas spend)
-    if refund_enabled {
-        // EIP-3529: Reduction in refunds
-        gas.set_final_refund(SPEC::SPEC_ID.is_enabled_in(SpecId::LONDON));
-    }
+    // The refund is capped to the part of the gas spent set by the config, by default a 5th
+    // since london (EIP-3529: Reduction in refunds) and a 2th before.
+    let max_refund_quotient = if refund_enabled {
+        env.cfg.refund_quotient(SPEC::SPEC_ID)
+    } else {
+        0
+    };
+    gas.set_final_refund_with_quotient(max_refund_quotient);
 }
 
 /// Handle output of the transaction
@@ -94,11 +96,12 @@ pub fn call<SPEC: Spec, EXT, DB: Database>(
 pub fn call_return<EXT, DB: Database>(
     context: &mut Context<EXT, DB>,
     frame: Box<CallFrame>,
-    interpreter_result: InterpreterResult,
+    mut interpreter_result: InterpreterResult,
 ) -> Result<CallOutcome, EVMError<DB::Error>> {
     context
         .evm
-        .call_return(&interpreter_result, frame.frame_data.checkpoint);
+        .call_return(&mut interpreter_result, frame.frame_data.checkpoint);
+    interpreter_result.gas.erase_cost(frame.retained_gas);
     Ok(CallOutcome::new(
         interpreter_result,
         frame.return_memory_range,
@@ -209,7 +212,14 @@ mod tests {
 
     /// Creates frame result.
     fn call_last_frame_return(instruction_result: InstructionResult, gas: Gas) -> Gas {
-        let mut env = Env::default();
+        call_last_frame_return_with_env(Env::default(), instruction_result, gas)
+    }
+
+    fn call_last_frame_return_with_env(
+        mut env: Env,
+        instruction_result: InstructionResult,
+        gas: Gas,
+    ) -> Gas {
         env.tx.gas_limit = 100;
 
         let mut first_frame = FrameResult::Call(CallOutcome::new(
@@ -249,6 +259,23 @@ mod tests {
         assert_eq!(gas.refunded(), 0);
     }
 
+    #[test]
+    fn test_configured_refund_quotient() {
+        let mut return_gas = Gas::new(60);
+        return_gas.record_refund(30);
+        let refund = |max_refund_quotient| {
+            let mut env = Env::default();
+            env.cfg.max_refund_quotient = max_refund_quotient;
+            call_last_frame_return_with_env(env, InstructionResult::Stop, return_gas).refunded()
+        };
+        // a fifth of the gas spent since london
+        assert_eq!(refund(None), 8);
+        assert_eq!(refund(Some(2)), 20);
+        assert_eq!(refund(Some(1)), 30);
+        // refunds disabled
+        assert_eq!(refund(Some(0)), 0);
+    }
+
     #[test]
     fn test_revert_gas() {
         let gas = call_last_frame_return(InstructionResult::Revert, Gas::new(90));
@@ -0,0 +1,44 @@
+use crate::{
+    interpreter::gas,
+    primitives::{Env, Spec},
+};
+
+/// Base stipend of every transaction.
+#[inline]
+pub fn base_gas<SPEC: Spec>(_env: &Env) -> u64 {
+    21000
+}
+
+/// Gas of the input data, by zero and nonzero bytes.
+#[inline]
+pub fn calldata_gas<SPEC: Spec>(env: &Env) -> u64 {
+    gas::calldata_cost(SPEC::SPEC_ID, &env.tx.data)
+}
+
+/// Gas of the accounts, storage slots and token balances of the access lists.
+#[inline]
+pub fn access_list_gas<SPEC: Spec>(env: &Env) -> u64 {
+    gas::access_list_cost(
+        SPEC::SPEC_ID,
+        &env.tx.access_list,
+        &env.tx.token_access_list,
+    )
+}
+
+/// Surcharge of contract creations, and gas of the init code.
+#[inline]
+pub fn creation_gas<SPEC: Spec>(env: &Env) -> u64 {
+    gas::creation_cost(SPEC::SPEC_ID, &env.tx.data)
+}
+
+/// Gas of the EIP-7702 authorizations.
+#[inline]
+pub fn authorization_list_gas<SPEC: Spec>(env: &Env) -> u64 {
+    gas::authorization_list_cost(SPEC::SPEC_ID, env.tx.authorization_list.len() as u64)
+}
+
+/// Gas of the transferred Native Tokens.
+#[inline]
+pub fn transferred_tokens_gas<SPEC: Spec>(env: &Env) -> u64 {
+    gas::transferred_tokens_cost(&env.tx.transferred_tokens)
+}
@@ -1,10 +1,12 @@
 use crate::{
-    interpreter::{Gas, SuccessOrHalt},
+    interpreter::{gas, Gas, SuccessOrHalt},
     primitives::{
-        db::Database, EVMError, ExecutionResult, ResultAndState, Spec, SpecId::LONDON, U256,
+        db::Database, nonzero_token_transfers, EVMError, ExecutionResult, GasBreakdown,
+        ResultAndState, Spec, SpecId::LONDON, TokenSupplyChanges, TokenTransferSummary, U256,
     },
-    Context, FrameResult,
+    Context, FrameResult, JournalEntry,
 };
+use std::vec::Vec;
 
 /// Mainnet end handle does not change the output.
 #[inline]
@@ -47,10 +49,34 @@ pub fn reward_beneficiary<SPEC: Spec, EXT, DB: Database>(
         .load_account(beneficiary, &mut context.evm.inner.db)?;
 
     coinbase_account.mark_touch();
-    coinbase_account.info.increase_base_balance_saturating(
+    coinbase_account.info.increase_balance_saturating(
+        context.evm.inner.env.cfg.base_token_id,
         coinbase_gas_price * U256::from(gas.spent() - gas.refunded() as u64),
     );
 
+    // the tips were deducted from the caller before execution. The validation rejects the tips
+    // overflowing the balances of the beneficiary, which can still be credited during the
+    // execution, so the tips saturate the balances.
+    let mut paid_tips = Vec::new();
+    for tip in nonzero_token_transfers(&context.evm.inner.env.tx.tip_tokens) {
+        let balance = coinbase_account.info.get_balance(tip.id);
+        let amount = tip.amount.min(U256::MAX - balance);
+        coinbase_account.info.set_balance(tip.id, balance + amount);
+        paid_tips.push(JournalEntry::TipPaid {
+            beneficiary,
+            token_id: tip.id,
+            amount,
+        });
+    }
+    context
+        .evm
+        .inner
+        .journaled_state
+        .journal
+        .last_mut()
+        .unwrap()
+        .extend(paid_tips);
+
     Ok(())
 }
 
@@ -59,17 +85,19 @@ pub fn reimburse_caller<SPEC: Spec, EXT, DB: Database>(
     context: &mut Context<EXT, DB>,
     gas: &Gas,
 ) -> Result<(), EVMError<DB::Error>> {
-    let caller = context.evm.env.tx.caller;
+    // the gas payer of sponsored transactions is reimbursed instead of the caller.
+    let payer = context.evm.env.tx.fee_payer();
     let effective_gas_price = context.evm.env.effective_gas_price();
 
     // return balance of not spend gas.
-    let (caller_account, _) = context
+    let (payer_account, _) = context
         .evm
         .inner
         .journaled_state
-        .load_account(caller, &mut context.evm.inner.db)?;
+        .load_account(payer, &mut context.evm.inner.db)?;
 
-    caller_account.info.increase_base_balance_saturating(
+    payer_account.info.increase_balance_saturating(
+        context.evm.inner.env.cfg.base_token_id,
         effective_gas_price * U256::from(gas.remaining() + gas.refunded() as u64),
     );
 
@@ -86,11 +114,17 @@ pub fn output<EXT, DB: Database>(
     // used gas with refund calculated.
     let gas_refunded = result.gas().refunded() as u64;
     let final_gas_used = result.gas().spent() - gas_refunded;
+    let gas_breakdown = gas_breakdown(context, result.gas());
+    let token_transfers = token_transfer_summary(context);
+    let supply_changes = token_supply_changes(context);
     let output = result.output();
     let instruction_result = result.into_interpreter_result();
 
     // reset journal and return present state.
-    let (state, logs) = context.evm.journaled_state.finalize();
+    let (mut state, logs) = context.evm.journaled_state.finalize();
+    if context.evm.env.cfg.prune_zero_balances {
+        state.prune_zero_balances();
+    }
 
     let result = match instruction_result.result.into() {
         SuccessOrHalt::Success(reason) => ExecutionResult::Success {
@@ -99,6 +133,9 @@ pub fn output<EXT, DB: Database>(
             gas_refunded,
             logs,
             output,
+            gas_breakdown: Some(gas_breakdown),
+            token_transfers: Some(token_transfers),
+            supply_changes: Some(supply_changes),
         },
         SuccessOrHalt::Revert => ExecutionResult::Revert {
             gas_used: final_gas_used,
@@ -121,3 +158,72 @@ pub fn output<EXT, DB: Database>(
 
     Ok(ResultAndState { result, state })
 }
+
+/// Breaks down the gas spent by the transaction, as it is after the final refund.
+///
+/// The intrinsic gas is the one priced by the intrinsic gas handles before the execution.
+#[inline]
+pub fn gas_breakdown<EXT, DB: Database>(context: &Context<EXT, DB>, gas: &Gas) -> GasBreakdown {
+    let spec_id = context.evm.spec_id();
+    let tx = &context.evm.env.tx;
+    let intrinsic = context.evm.intrinsic_gas;
+    let refunded = gas.refunded() as u64;
+    // the final refund is at most the configured part of the gas spent.
+    let max_refund_quotient = context.evm.env.cfg.refund_quotient(spec_id);
+    GasBreakdown {
+        intrinsic,
+        execution: gas.spent().saturating_sub(intrinsic),
+        refunded,
+        refund_capped: refunded != 0
+            && gas.spent().checked_div(max_refund_quotient) == Some(refunded),
+        token_transfer_surcharge: gas::transferred_tokens_cost(&tx.transferred_tokens),
+    }
+}
+
+/// Summarizes the token transfers recorded in the journal, before it is finalized, including the
+/// tips paid to the beneficiary.
+///
+/// The entries of the reverted calls are no longer in the journal, so only the transfers that
+/// took effect are summarized.
+#[inline]
+pub fn token_transfer_summary<EXT, DB: Database>(
+    context: &Context<EXT, DB>,
+) -> TokenTransferSummary {
+    let mut summary = TokenTransferSummary::default();
+    for entry in context.evm.journaled_state.journal.iter().flatten() {
+        if let JournalEntry::BalanceTransfer {
+            token_id, amount, ..
+        }
+        | JournalEntry::TipPaid {
+            token_id, amount, ..
+        } = entry
+        {
+            summary.record(*token_id, *amount);
+        }
+    }
+    summary
+}
+
+/// Sums the tokens minted and burned recorded in the journal, before it is finalized.
+///
+/// Like in [token_transfer_summary], only the mints and burns that took effect are summed.
+#[inline]
+pub fn token_supply_changes<EXT, DB: Database>(context: &Context<EXT, DB>) -> TokenSupplyChanges {
+    let mut changes = TokenSupplyChanges::default();
+    for entry in context.evm.journaled_state.journal.iter().flatten() {
+        match entry {
+            JournalEntry::TokensMinted {
+                token_id,
+                minted_amount,
+                ..
+            } => changes.record_mint(*token_id, *minted_amount),
+            JournalEntry::TokensBurned {
+                token_id,
+                burned_amount,
+                ..
+            } => changes.record_burn(*token_id, *burned_amount),
+            _ => {}
+        }
+    }
+    changes
+}
@@ -6,23 +6,24 @@ use crate::{
     precompile::{PrecompileSpecId, Precompiles},
     primitives::{
         db::Database,
-        Account, EVMError, Env, Spec,
+        nonzero_token_transfers, Account, Authorization, Bytecode, EVMError, Env, InvalidHeader,
+        Spec,
         SpecId::{CANCUN, PRAGUE, SHANGHAI},
-        TransactTo, BLOCKHASH_STORAGE_ADDRESS, U256,
+        TransactTo, BLOCKHASH_SERVE_WINDOW, BLOCKHASH_STORAGE_ADDRESS, EMISSIONS_ADDRESS,
+        PER_AUTH_BASE_COST, PER_EMPTY_ACCOUNT_COST, U256,
     },
-    Context, ContextPrecompiles,
+    Context, ContextPrecompiles, InnerEvmContext,
 };
 
 #[cfg(feature = "std")]
-use crate::{
-    sablier::{native_tokens, native_tokens::NativeTokensContextPrecompile},
-    ContextPrecompile,
-};
-
-#[cfg(feature = "std")]
-use std::boxed::Box;
+use crate::sablier::SablierPrecompileSet;
 
 /// Main precompile load
+///
+/// Loads the standard precompiles of the hardfork, e.g. from `0x01` to the KZG point evaluation
+/// precompile at `0x0a` in Cancun, and the Sablier precompiles, which live at their own
+/// addresses. The point evaluation precompile is only loaded with the `c-kzg` feature, and
+/// verifies proofs with the trusted setup of [`CfgEnv::kzg_settings`](crate::primitives::CfgEnv).
 #[inline]
 pub fn load_precompiles<SPEC: Spec, DB: Database>() -> ContextPrecompiles<DB> {
     // Load the vanilla EVM precompiles.
@@ -33,10 +34,7 @@ pub fn load_precompiles<SPEC: Spec, DB: Database>() -> ContextPrecompiles<DB> {
 
     #[cfg(feature = "std")]
     // Add the SabVM precompiles.
-    precompiles.extend([(
-        native_tokens::ADDRESS,
-        ContextPrecompile::ContextStatefulMut(Box::new(NativeTokensContextPrecompile {})),
-    )]);
+    precompiles.extend(SablierPrecompileSet::mainnet().precompiles(SPEC::SPEC_ID));
 
     precompiles
 }
@@ -48,35 +46,100 @@ pub fn load_accounts<SPEC: Spec, EXT, DB: Database>(
 ) -> Result<(), EVMError<DB::Error>> {
     // set journaling state flag.
     context.evm.journaled_state.set_spec_id(SPEC::SPEC_ID);
+    let base_token_id = context.evm.env.cfg.base_token_id;
+    context.evm.journaled_state.set_base_token_id(base_token_id);
 
-    // load coinbase
-    // EIP-3651: Warm COINBASE. Starts the `COINBASE` address warm
-    if SPEC::enabled(SHANGHAI) {
-        context.evm.inner.journaled_state.initial_account_load(
-            context.evm.inner.env.block.coinbase,
-            &[],
-            &mut context.evm.inner.db,
-        )?;
-    }
+    let InnerEvmContext {
+        env,
+        journaled_state,
+        db,
+        ..
+    } = &mut context.evm.inner;
 
-    // Load blockhash storage address
+    // EIP-3651: Warm COINBASE. Starts the `COINBASE` address warm
+    let coinbase = SPEC::enabled(SHANGHAI).then_some(env.block.coinbase);
     // EIP-2935: Serve historical block hashes from state
-    if SPEC::enabled(PRAGUE) {
-        context.evm.inner.journaled_state.initial_account_load(
-            BLOCKHASH_STORAGE_ADDRESS,
-            &[],
-            &mut context.evm.inner.db,
+    let blockhash_storage = SPEC::enabled(PRAGUE).then_some(BLOCKHASH_STORAGE_ADDRESS);
+    journaled_state.warm_accounts(
+        coinbase
+            .into_iter()
+            .chain(blockhash_storage)
+            .map(|address| (address, &[][..])),
+        db,
+    )?;
+
+    // The accounts of the block emissions start warm in every transaction of the block, not
+    // only in the first one, which applies the emissions.
+    if !env.block.emissions.is_empty() {
+        let recipients = env
+            .block
+            .emissions
+            .iter()
+            .map(|emission| emission.recipient);
+        journaled_state.warm_accounts(
+            [EMISSIONS_ADDRESS]
+                .into_iter()
+                .chain(recipients)
+                .map(|address| (address, &[][..])),
+            db,
         )?;
     }
 
-    context.evm.load_access_list()?;
+    // EIP-2930: the accounts and the storage slots of the access list start warm
+    let access_list = env
+        .tx
+        .access_list
+        .iter()
+        .map(|(address, slots)| (*address, slots.as_slice()));
+    journaled_state.warm_accounts(access_list, db)?;
+
+    // The accounts and the token balances of the token access list start warm too.
+    let token_access_list = &env.tx.token_access_list;
+    journaled_state.warm_accounts(
+        token_access_list
+            .iter()
+            .map(|(address, _)| (*address, &[][..])),
+        db,
+    )?;
+    journaled_state.warm_token_balances(token_access_list.iter().flat_map(
+        |(address, token_ids)| token_ids.iter().map(move |token_id| (*address, *token_id)),
+    ));
+
+    // The balances of the tokens transferred by the transaction start warm, like the accounts of
+    // the caller and of the target (EIP-2929).
+    let target = match env.tx.transact_to {
+        TransactTo::Call(address) => Some(address),
+        TransactTo::Create => None,
+    };
+    let transferred_tokens = || nonzero_token_transfers(&env.tx.transferred_tokens);
+    journaled_state.warm_token_balances(transferred_tokens().flat_map(|token| {
+        [Some(env.tx.caller), target]
+            .into_iter()
+            .flatten()
+            .map(move |address| (address, token.id))
+    }));
+
+    // The Native Tokens transferred, tipped or declared by the transaction, other than the base
+    // token, are looked up in the registry.
+    let base_token_id = env.cfg.base_token_id;
+    if transferred_tokens()
+        .chain(&env.tx.tip_tokens)
+        .map(|token| &token.id)
+        .chain(
+            token_access_list
+                .iter()
+                .flat_map(|(_, token_ids)| token_ids),
+        )
+        .any(|token_id| *token_id != base_token_id)
+    {
+        journaled_state.warm_token_ids(db)?;
+    }
     Ok(())
 }
 
-/// Helper function that deducts the caller balance.
+/// Returns the gas cost of the transaction, which is deducted from the gas payer.
 #[inline]
-pub fn deduct_caller_inner<SPEC: Spec>(caller_account: &mut Account, env: &Env) {
-    // Subtract gas costs from the caller's account.
+fn gas_cost<SPEC: Spec>(env: &Env) -> U256 {
     // We need to saturate the gas cost to prevent underflow in case that `disable_balance_check` is enabled.
     let mut gas_cost = U256::from(env.tx.gas_limit).saturating_mul(env.effective_gas_price());
 
@@ -85,11 +148,28 @@ pub fn deduct_caller_inner<SPEC: Spec>(caller_account: &mut Account, env: &Env)
         let data_fee = env.calc_data_fee().expect("already checked");
         gas_cost = gas_cost.saturating_add(data_fee);
     }
+    gas_cost
+}
 
-    // set new caller account balance.
-    caller_account
-        .info
-        .decrease_base_balance_saturating(gas_cost);
+/// Helper function that deducts the caller balance.
+///
+/// The gas cost of sponsored transactions is deducted from the gas payer instead, see
+/// [deduct_gas_payer_inner].
+#[inline]
+pub fn deduct_caller_inner<SPEC: Spec>(caller_account: &mut Account, env: &Env) {
+    // Subtract gas costs from the caller's account.
+    if !env.tx.is_sponsored() {
+        caller_account
+            .info
+            .decrease_balance_saturating(env.cfg.base_token_id, gas_cost::<SPEC>(env));
+    }
+
+    // Subtract the tips, which are paid to the beneficiary after execution.
+    for tip in &env.tx.tip_tokens {
+        caller_account
+            .info
+            .decrease_balance_saturating(tip.id, tip.amount);
+    }
 
     // bump the nonce for calls. Nonce for CREATE will be bumped in `handle_create`.
     if matches!(env.tx.transact_to, TransactTo::Call(_)) {
@@ -101,6 +181,17 @@ pub fn deduct_caller_inner<SPEC: Spec>(caller_account: &mut Account, env: &Env)
     caller_account.mark_touch();
 }
 
+/// Helper function that deducts the gas cost of a sponsored transaction from the gas payer.
+#[inline]
+pub fn deduct_gas_payer_inner<SPEC: Spec>(payer_account: &mut Account, env: &Env) {
+    payer_account
+        .info
+        .decrease_balance_saturating(env.cfg.base_token_id, gas_cost::<SPEC>(env));
+
+    // touch account so we know it is changed.
+    payer_account.mark_touch();
+}
+
 /// Deducts the caller balance to the transaction limit.
 #[inline]
 pub fn deduct_caller<SPEC: Spec, EXT, DB: Database>(
@@ -116,5 +207,330 @@ pub fn deduct_caller<SPEC: Spec, EXT, DB: Database>(
     // deduct gas cost from caller's account.
     deduct_caller_inner::<SPEC>(caller_account, &context.evm.inner.env);
 
+    // deduct gas cost from the gas payer's account of sponsored transactions.
+    if context.evm.inner.env.tx.is_sponsored() {
+        let (payer_account, _) = context.evm.inner.journaled_state.load_account(
+            context.evm.inner.env.tx.fee_payer(),
+            &mut context.evm.inner.db,
+        )?;
+        deduct_gas_payer_inner::<SPEC>(payer_account, &context.evm.inner.env);
+    }
+
+    Ok(())
+}
+
+/// Writes the hash of the parent block to the [EIP-2935] history storage contract, from which
+/// BLOCKHASH serves the hashes older than the last 256 blocks.
+///
+/// The hash is written before every transaction of the block, which leaves the slot unchanged
+/// after the first one. The history account is given a nonce so that it isn't cleared as an
+/// empty account.
+///
+/// [EIP-2935]: https://eips.ethereum.org/EIPS/eip-2935
+#[inline]
+pub fn apply_blockhash_history<SPEC: Spec, EXT, DB: Database>(
+    context: &mut Context<EXT, DB>,
+) -> Result<(), EVMError<DB::Error>> {
+    if !SPEC::enabled(PRAGUE) || context.evm.env.block.number == U256::ZERO {
+        return Ok(());
+    }
+
+    let parent_number = context.evm.env.block.number - U256::from(1);
+    let parent_hash = context.evm.block_hash(parent_number)?;
+
+    // the account is warm loaded in `load_accounts`.
+    let (account, _) = context.evm.load_account(BLOCKHASH_STORAGE_ADDRESS)?;
+    if account.info.nonce == 0 {
+        account.info.nonce = 1;
+    }
+    context.evm.touch(&BLOCKHASH_STORAGE_ADDRESS);
+    context.evm.sstore(
+        BLOCKHASH_STORAGE_ADDRESS,
+        parent_number % U256::from(BLOCKHASH_SERVE_WINDOW),
+        U256::from_be_bytes(parent_hash.0),
+    )?;
     Ok(())
 }
+
+/// Mints the [emissions](crate::primitives::BlockEnv::emissions) of the block, with
+/// [EMISSIONS_ADDRESS] as the minter.
+///
+/// The emissions are applied before the first transaction of the block, whose number is then
+/// recorded in the storage of [EMISSIONS_ADDRESS]; a block without transactions emits nothing.
+/// The account is given a nonce so that it isn't cleared as an empty account.
+#[inline]
+pub fn apply_block_emissions<SPEC: Spec, EXT, DB: Database>(
+    context: &mut Context<EXT, DB>,
+) -> Result<(), EVMError<DB::Error>> {
+    if context.evm.env.block.emissions.is_empty() {
+        return Ok(());
+    }
+
+    // the account is warm loaded in `load_accounts`.
+    let (account, _) = context.evm.load_account(EMISSIONS_ADDRESS)?;
+    if account.info.nonce == 0 {
+        account.info.nonce = 1;
+    }
+    let applied_block = context.evm.env.block.number.saturating_add(U256::from(1));
+    let (last_applied_block, _) = context.evm.sload(EMISSIONS_ADDRESS, U256::ZERO)?;
+    if last_applied_block == applied_block {
+        return Ok(());
+    }
+    context.evm.touch(&EMISSIONS_ADDRESS);
+    context
+        .evm
+        .sstore(EMISSIONS_ADDRESS, U256::ZERO, applied_block)?;
+
+    let InnerEvmContext {
+        env,
+        journaled_state,
+        db,
+        ..
+    } = &mut context.evm.inner;
+    journaled_state.load_native_token_ids(db)?;
+    for emission in &env.block.emissions {
+        if emission.amount.is_zero() {
+            continue;
+        }
+        journaled_state.load_account(emission.recipient, db)?;
+        journaled_state.touch(&emission.recipient);
+        if !journaled_state.mint_token(
+            EMISSIONS_ADDRESS,
+            emission.recipient,
+            emission.token_id,
+            emission.amount,
+        ) {
+            return Err(InvalidHeader::EmissionOverflow.into());
+        }
+    }
+    Ok(())
+}
+
+/// Applies the [EIP-7702] authorization list of the transaction, setting the code of every
+/// authority to a delegation to the authorized address.
+///
+/// Invalid authorizations are skipped. Returns the gas refunded for the authorities that
+/// already existed.
+///
+/// [EIP-7702]: https://eips.ethereum.org/EIPS/eip-7702
+#[inline]
+pub fn apply_eip7702_auth_list<SPEC: Spec, EXT, DB: Database>(
+    context: &mut Context<EXT, DB>,
+) -> Result<u64, EVMError<DB::Error>> {
+    if !SPEC::enabled(PRAGUE) {
+        return Ok(0);
+    }
+
+    let InnerEvmContext {
+        env,
+        journaled_state,
+        db,
+        ..
+    } = &mut context.evm.inner;
+    let mut refunded_accounts = 0;
+    for authorization in env.tx.authorization_list.iter() {
+        let Authorization {
+            chain_id,
+            address,
+            nonce,
+        } = authorization.inner;
+
+        // the authorization has to be valid on this chain
+        if chain_id != 0 && chain_id != env.cfg.chain_id {
+            continue;
+        }
+
+        // the nonce of the authority has to be bumpable
+        if nonce == u64::MAX {
+            continue;
+        }
+
+        // the signature has to be valid
+        let Some(authority) = authorization.authority else {
+            continue;
+        };
+
+        // loading the authority warms it
+        let (account, _) = journaled_state.load_code(authority, db)?;
+
+        // the authority has to be an EOA, optionally already delegating its code
+        let code = account.info.code.as_ref().expect("code is loaded");
+        if !code.is_empty() && !code.is_eip7702() {
+            continue;
+        }
+
+        if account.info.nonce != nonce {
+            continue;
+        }
+
+        // authorizations of existing accounts are charged less
+        if !account.is_loaded_as_not_existing() {
+            refunded_accounts += 1;
+        }
+
+        // delegating to the zero address clears the delegation
+        let code = if address.is_zero() {
+            Bytecode::default()
+        } else {
+            Bytecode::new_eip7702(address)
+        };
+        account.info.code_hash = code.hash_slow();
+        account.info.code = Some(code);
+        account.info.nonce += 1;
+
+        // touch account so we know it is changed.
+        account.mark_touch();
+    }
+
+    Ok(refunded_accounts * (PER_EMPTY_ACCOUNT_COST - PER_AUTH_BASE_COST))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::EmptyDB,
+        precompile::u64_to_address,
+        primitives::{CancunSpec, ShanghaiSpec},
+    };
+
+    #[test]
+    fn loads_standard_and_sablier_precompiles() {
+        let cancun = load_precompiles::<CancunSpec, EmptyDB>();
+        for index in 1..=10 {
+            assert!(
+                cancun.contains_key(&u64_to_address(index)),
+                "missing {index:#x}"
+            );
+        }
+        #[cfg(feature = "std")]
+        assert!(cancun.contains_key(&crate::sablier::native_tokens::ADDRESS));
+
+        // the point evaluation precompile came with Cancun
+        let shanghai = load_precompiles::<ShanghaiSpec, EmptyDB>();
+        assert!(!shanghai.contains_key(&u64_to_address(10)));
+    }
+
+    #[test]
+    fn warms_access_list_coinbase_and_token_ids() {
+        use crate::{
+            db::InMemoryDB,
+            primitives::{address, TokenTransfer, BASE_TOKEN_ID},
+            Evm,
+        };
+
+        let coinbase = address!("00000000000000000000000000000000000000aa");
+        let listed = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(5);
+        let mut db = InMemoryDB::default();
+        db.token_ids.push(token_id);
+        db.insert_account_storage(listed, U256::from(1), U256::from(7))
+            .unwrap();
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_block_env(|block| block.coinbase = coinbase)
+            .modify_tx_env(|tx| {
+                tx.access_list = vec![(listed, vec![U256::from(1)])];
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: token_id,
+                    amount: U256::from(1),
+                }];
+            })
+            .build();
+
+        load_accounts::<CancunSpec, _, _>(&mut evm.context).unwrap();
+        let InnerEvmContext {
+            journaled_state,
+            db,
+            ..
+        } = &mut evm.context.evm.inner;
+        assert!(!journaled_state.load_account(coinbase, db).unwrap().1);
+        assert_eq!(
+            journaled_state.sload(listed, U256::from(1), db).unwrap(),
+            (U256::from(7), false)
+        );
+        assert_eq!(journaled_state.state.token_ids, [BASE_TOKEN_ID, token_id]);
+        // warming isn't journaled
+        assert!(journaled_state.journal.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn warms_and_charges_token_access_list() {
+        use crate::{
+            db::InMemoryDB,
+            interpreter::gas,
+            primitives::{address, SpecId, BASE_TOKEN_ID},
+            Evm,
+        };
+
+        let holder = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(5);
+        let mut db = InMemoryDB::default();
+        db.token_ids.push(token_id);
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.token_access_list = vec![(holder, vec![BASE_TOKEN_ID, token_id])];
+            })
+            .build();
+
+        load_accounts::<CancunSpec, _, _>(&mut evm.context).unwrap();
+        let InnerEvmContext {
+            env,
+            journaled_state,
+            db,
+            ..
+        } = &mut evm.context.evm.inner;
+        for token_id in [BASE_TOKEN_ID, token_id] {
+            assert!(
+                !journaled_state
+                    .token_balance(holder, token_id, db)
+                    .unwrap()
+                    .1
+            );
+        }
+        assert_eq!(journaled_state.state.token_ids, [BASE_TOKEN_ID, token_id]);
+
+        // the holder and its two balances are charged like an account with two storage keys
+        let intrinsic = |token_access_list: &[_]| {
+            gas::validate_initial_tx_gas(SpecId::CANCUN, &[], false, &[], token_access_list, &[], 0)
+        };
+        assert_eq!(
+            intrinsic(&env.tx.token_access_list) - intrinsic(&[]),
+            gas::ACCESS_LIST_ADDRESS + 2 * gas::ACCESS_LIST_TOKEN_BALANCE
+        );
+    }
+
+    #[cfg(feature = "c-kzg")]
+    #[test]
+    fn evaluates_kzg_points() {
+        use crate::primitives::{hex, EnvKzgSettings, SpecId, TransactTo};
+        use crate::Evm;
+
+        // test data from: https://github.com/ethereum/c-kzg-4844/blob/main/tests/verify_kzg_proof/kzg-mainnet/verify_kzg_proof_case_correct_proof_31ebd010e6098750/data.yaml
+        let input = [
+            &hex!("01e798154708fe7789429634053cbf9f99b619f9f084048927333fce637f549b")[..],
+            &hex!("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000"),
+            &hex!("1522a4a7f34e1ea350ae07c29c96c7e79655aa926122e95fe69fcbd932ca49e9"),
+            &hex!("8f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7"),
+            &hex!("a62ad71d14c5719385c0686f1871430475bf3a00f0aa3f7b8dd99a9abc2160744faf0070725e00b60ad9a026a15b1a8c"),
+        ]
+        .concat();
+
+        let mut evm = Evm::builder()
+            .with_empty_db()
+            .with_spec_id(SpecId::CANCUN)
+            .with_kzg_settings(EnvKzgSettings::Default)
+            .modify_tx_env(|tx| {
+                tx.transact_to = TransactTo::Call(u64_to_address(10));
+                tx.data = input.into();
+                tx.gas_limit = 100_000;
+            })
+            .build();
+        let result = evm.transact().unwrap().result;
+        assert_eq!(
+            result.output().unwrap()[..],
+            hex!("000000000000000000000000000000000000000000000000000000000000100073eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001")
+        );
+    }
+}
@@ -1,5 +1,3 @@
-use revm_interpreter::gas;
-
 use crate::{
     primitives::{db::Database, EVMError, Env, InvalidTransaction, Spec},
     Context,
@@ -19,8 +17,9 @@ pub fn validate_env<SPEC: Spec, DB: Database>(env: &Env) -> Result<(), EVMError<
 pub fn validate_tx_against_state<SPEC: Spec, EXT, DB: Database>(
     context: &mut Context<EXT, DB>,
 ) -> Result<(), EVMError<DB::Error>> {
-    // Check whether all of the transferred assets are valid
-    for token in context.evm.env.tx.transferred_tokens.iter() {
+    // Check whether all of the transferred and tipped assets are valid
+    let tx = &context.evm.env.tx;
+    for token in tx.transferred_tokens.iter().chain(&tx.tip_tokens) {
         let result = context.evm.db.is_token_id_valid(token.id);
         if result.is_err() || result.is_ok_and(|r| !r) {
             return Err(InvalidTransaction::InvalidTokenId {
@@ -36,7 +35,7 @@ pub fn validate_tx_against_state<SPEC: Spec, EXT, DB: Database>(
         .evm
         .inner
         .journaled_state
-        .load_account(tx_caller, &mut context.evm.inner.db)?;
+        .load_code(tx_caller, &mut context.evm.inner.db)?;
 
     context
         .evm
@@ -45,25 +44,54 @@ pub fn validate_tx_against_state<SPEC: Spec, EXT, DB: Database>(
         .validate_tx_against_state::<SPEC>(caller_account)
         .map_err(EVMError::Transaction)?;
 
+    // the gas payer of sponsored transactions has to cover the gas cost
+    if context.evm.inner.env.tx.is_sponsored() {
+        let (payer_account, _) = context.evm.inner.journaled_state.load_account(
+            context.evm.inner.env.tx.fee_payer(),
+            &mut context.evm.inner.db,
+        )?;
+
+        context
+            .evm
+            .inner
+            .env
+            .validate_gas_payer_against_state::<SPEC>(payer_account)
+            .map_err(EVMError::Transaction)?;
+    }
+
+    // the balances of the beneficiary have to be able to receive the tips, which are paid after
+    // the execution. The beneficiary is read from the database so that it isn't warmed.
+    if !context.evm.inner.env.tx.tip_tokens.is_empty() {
+        let beneficiary = context
+            .evm
+            .inner
+            .db
+            .basic(context.evm.inner.env.block.coinbase)
+            .map_err(EVMError::Database)?
+            .unwrap_or_default();
+        context
+            .evm
+            .inner
+            .env
+            .validate_tips_against_beneficiary(&beneficiary)
+            .map_err(EVMError::Transaction)?;
+    }
+
+    // the precompiles are loaded before the validation.
+    context
+        .evm
+        .validate_tx_destination()
+        .map_err(EVMError::Transaction)?;
+
     Ok(())
 }
 
-/// Validate initial transaction gas.
+/// Validate initial transaction gas, priced by the
+/// [IntrinsicGasHandler](crate::handler::IntrinsicGasHandler).
 pub fn validate_initial_tx_gas<SPEC: Spec, DB: Database>(
     env: &Env,
+    initial_gas_spend: u64,
 ) -> Result<u64, EVMError<DB::Error>> {
-    let input = &env.tx.data;
-    let is_create = env.tx.transact_to.is_create();
-    let access_list = &env.tx.access_list;
-
-    let initial_gas_spend = gas::validate_initial_tx_gas(
-        SPEC::SPEC_ID,
-        input,
-        is_create,
-        access_list,
-        &env.tx.transferred_tokens,
-    );
-
     // Additional check to see if limit is big enough to cover initial gas.
     if initial_gas_spend > env.tx.gas_limit {
         return Err(InvalidTransaction::CallGasCostMoreThanGasLimit.into());
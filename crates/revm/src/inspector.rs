@@ -1,18 +1,25 @@
 use crate::{
-    interpreter::{CallInputs, CreateInputs, EOFCreateInput, EOFCreateOutcome, Interpreter},
-    primitives::{db::Database, Address, Log, U256},
+    interpreter::{
+        CallInputs, CreateInputs, EOFCreateInput, EOFCreateOutcome, InstructionResult, Interpreter,
+    },
+    primitives::{db::Database, Address, Log, TokenTransfer, U256},
     EvmContext,
 };
 use auto_impl::auto_impl;
 
 #[cfg(feature = "std")]
-mod customprinter;
+use crate::sablier::native_tokens::NativeTokenCall;
 
+#[cfg(feature = "std")]
+mod customprinter;
+mod debugger;
 #[cfg(all(feature = "std", feature = "serde-json"))]
 mod eip3155;
+mod filter;
 mod gas;
 mod handler_register;
 mod noop;
+mod watchdog;
 
 // Exports.
 
@@ -23,10 +30,15 @@ use revm_interpreter::{CallOutcome, CreateOutcome};
 pub mod inspectors {
     #[cfg(feature = "std")]
     pub use super::customprinter::CustomPrintTracer;
+    pub use super::debugger::{
+        Breakpoint, DebuggerAction, DebuggerCallback, DebuggerInspector, Pause,
+    };
     #[cfg(all(feature = "std", feature = "serde-json"))]
     pub use super::eip3155::TracerEip3155;
+    pub use super::filter::TraceFilter;
     pub use super::gas::GasInspector;
     pub use super::noop::NoOpInspector;
+    pub use super::watchdog::{WatchdogCallback, WatchdogInspector};
 }
 
 /// EVM [Interpreter] callbacks.
@@ -87,6 +99,47 @@ pub trait Inspector<DB: Database> {
         None
     }
 
+    /// Called after [Inspector::call] when the call is made to the native tokens precompile, with
+    /// the decoded token operation.
+    ///
+    /// Calls with calldata that can't be decoded, which the precompile rejects, are not reported.
+    #[cfg(feature = "std")]
+    #[inline]
+    fn native_token_call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        call: &NativeTokenCall,
+    ) {
+        let _ = context;
+        let _ = inputs;
+        let _ = call;
+    }
+
+    /// Called before tokens are transferred by a call, a creation or the native tokens
+    /// precompile, once for every token with a nonzero amount.
+    ///
+    /// Returning `Some` blocks the transfer: the call or creation fails with the returned
+    /// [InstructionResult] without spending gas, and none of its tokens are transferred.
+    ///
+    /// If this returns `None` then the transfer proceeds as normal.
+    #[inline]
+    fn token_transfer(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        from: Address,
+        to: Address,
+        token_id: U256,
+        amount: U256,
+    ) -> Option<InstructionResult> {
+        let _ = context;
+        let _ = from;
+        let _ = to;
+        let _ = token_id;
+        let _ = amount;
+        None
+    }
+
     /// Called when a call to a contract has concluded.
     ///
     /// The returned [CallOutcome] is used as the result of the call.
@@ -157,11 +210,12 @@ pub trait Inspector<DB: Database> {
         outcome
     }
 
-    /// Called when a contract has been self-destructed with funds transferred to target.
+    /// Called when a contract has been self-destructed with funds transferred to target, every
+    /// token it held in `values`.
     #[inline]
-    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+    fn selfdestruct(&mut self, contract: Address, target: Address, values: &[TokenTransfer]) {
         let _ = contract;
         let _ = target;
-        let _ = value;
+        let _ = values;
     }
 }
@@ -2,9 +2,10 @@
 //! It is a great tool if some debugging is needed.
 
 use crate::{
-    inspectors::GasInspector,
+    inspectors::{GasInspector, TraceFilter},
     interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter, OpCode},
-    primitives::{Address, U256},
+    primitives::{Address, TokenTransfer},
+    sablier::native_tokens::NativeTokenCall,
     Database, EvmContext, Inspector,
 };
 
@@ -14,6 +15,15 @@ use crate::{
 #[derive(Clone, Debug, Default)]
 pub struct CustomPrintTracer {
     gas_inspector: GasInspector,
+    filter: TraceFilter,
+}
+
+impl CustomPrintTracer {
+    /// Only print the steps, calls and creations that match the filter.
+    pub fn with_filter(mut self, filter: TraceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
 }
 
 impl<DB: Database> Inspector<DB> for CustomPrintTracer {
@@ -24,6 +34,11 @@ impl<DB: Database> Inspector<DB> for CustomPrintTracer {
     // get opcode by calling `interp.contract.opcode(interp.program_counter())`.
     // all other information can be obtained from interp.
     fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if !self.filter.matches_contract(&interp.contract) {
+            self.gas_inspector.step(interp, context);
+            return;
+        }
+
         let opcode = interp.current_opcode();
         let name = OpCode::name_by_op(opcode);
 
@@ -75,6 +90,9 @@ impl<DB: Database> Inspector<DB> for CustomPrintTracer {
         _context: &mut EvmContext<DB>,
         inputs: &mut CallInputs,
     ) -> Option<CallOutcome> {
+        if !self.filter.matches_call(inputs) {
+            return None;
+        }
         println!(
             "SM Address: {:?}, caller:{:?},target:{:?} is_static:{:?}, transfer:{:?}, input_size:{:?}",
             inputs.bytecode_address,
@@ -87,11 +105,32 @@ impl<DB: Database> Inspector<DB> for CustomPrintTracer {
         None
     }
 
-    fn create(
+    fn native_token_call(
         &mut self,
         _context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        call: &NativeTokenCall,
+    ) {
+        if !self.filter.matches_call(inputs) {
+            return;
+        }
+        println!(
+            "NATIVE TOKEN CALL: caller:{:?}, {:?}",
+            inputs.target_address, call
+        );
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
         inputs: &mut CreateInputs,
     ) -> Option<CreateOutcome> {
+        if !self
+            .filter
+            .matches_create(inputs, context.env.cfg.base_token_id)
+        {
+            return None;
+        }
         println!(
             "CREATE CALL: caller:{:?}, scheme:{:?}, value:{:?}, init_code:{:?}, gas:{:?}",
             inputs.caller, inputs.scheme, inputs.value, inputs.init_code, inputs.gas_limit
@@ -99,10 +138,15 @@ impl<DB: Database> Inspector<DB> for CustomPrintTracer {
         None
     }
 
-    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+    fn selfdestruct(&mut self, contract: Address, target: Address, values: &[TokenTransfer]) {
+        if !(self.filter.matches_address(&contract) || self.filter.matches_address(&target))
+            || !self.filter.matches_tokens(values)
+        {
+            return;
+        }
         println!(
-            "SELFDESTRUCT: contract: {:?}, refund target: {:?}, value {:?}",
-            contract, target, value
+            "SELFDESTRUCT: contract: {:?}, refund target: {:?}, values {:?}",
+            contract, target, values
         );
     }
 }
@@ -113,12 +157,10 @@ mod test {
         inspector_handle_register,
         inspectors::CustomPrintTracer,
         primitives::{
-            address, bytes, keccak256, AccountInfo, Bytecode, Bytes, SpecId, TokenTransfer,
-            TransactTo, BASE_TOKEN_ID, U256,
+            address, bytes, Bytecode, Bytes, SpecId, TokenTransfer, TransactTo, BASE_TOKEN_ID, U256,
         },
         Evm, InMemoryDB,
     };
-    use revm_precompile::HashMap;
 
     #[test]
     fn gas_calculation_underflow() {
@@ -129,14 +171,9 @@ mod test {
         let mut evm = Evm::builder()
             .with_db(InMemoryDB::default())
             .modify_db(|db| {
-                let code = bytes!("5b597fb075978b6c412c64d169d56d839a8fe01b3f4607ed603b2c78917ce8be1430fe6101e8527ffe64706ecad72a2f5c97a95e006e279dc57081902029ce96af7edae5de116fec610208527f9fc1ef09d4dd80683858ae3ea18869fe789ddc365d8d9d800e26c9872bac5e5b6102285260276102485360d461024953601661024a53600e61024b53607d61024c53600961024d53600b61024e5360b761024f5360596102505360796102515360a061025253607261025353603a6102545360fb61025553601261025653602861025753600761025853606f61025953601761025a53606161025b53606061025c5360a661025d53602b61025e53608961025f53607a61026053606461026153608c6102625360806102635360d56102645360826102655360ae61026653607f6101e8610146610220677a814b184591c555735fdcca53617f4d2b9134b29090c87d01058e27e962047654f259595947443b1b816b65cdb6277f4b59c10a36f4e7b8658f5a5e6f5561");
-                let info = AccountInfo {
-                    balances: HashMap::from([(BASE_TOKEN_ID, "0x100c5d668240db8e00".parse().unwrap())]),
-                    code_hash: keccak256(&code),
-                    code: Some(Bytecode::new_raw(code)),
-                    nonce: 1,
-                };
-                db.insert_account_info(callee, info);
+                let code = Bytecode::new_raw(bytes!("5b597fb075978b6c412c64d169d56d839a8fe01b3f4607ed603b2c78917ce8be1430fe6101e8527ffe64706ecad72a2f5c97a95e006e279dc57081902029ce96af7edae5de116fec610208527f9fc1ef09d4dd80683858ae3ea18869fe789ddc365d8d9d800e26c9872bac5e5b6102285260276102485360d461024953601661024a53600e61024b53607d61024c53600961024d53600b61024e5360b761024f5360596102505360796102515360a061025253607261025353603a6102545360fb61025553601261025653602861025753600761025853606f61025953601761025a53606161025b53606061025c5360a661025d53602b61025e53608961025f53607a61026053606461026153608c6102625360806102635360d56102645360826102655360ae61026653607f6101e8610146610220677a814b184591c555735fdcca53617f4d2b9134b29090c87d01058e27e962047654f259595947443b1b816b65cdb6277f4b59c10a36f4e7b8658f5a5e6f5561"));
+                db.deploy(callee, code).unwrap();
+                db.fund(callee, BASE_TOKEN_ID, "0x100c5d668240db8e00".parse().unwrap()).unwrap();
             })
             .modify_tx_env(|tx| {
                 tx.caller = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
@@ -0,0 +1,368 @@
+use crate::{
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, Gas, InstructionResult, Interpreter,
+        InterpreterResult,
+    },
+    primitives::{Address, Bytes},
+    Database, EvmContext, Inspector,
+};
+use std::vec::Vec;
+
+/// A condition on which the [DebuggerInspector] pauses execution.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    /// Pauses before the instruction at `pc` in the code running at `address`.
+    Location {
+        /// Address of the account whose code is running.
+        address: Address,
+        /// Program counter of the instruction.
+        pc: usize,
+    },
+    /// Pauses before every instruction with the given opcode.
+    Opcode(u8),
+    /// Pauses before every call that transfers tokens from or to the address.
+    TokenTransfer(Address),
+}
+
+/// Why the [DebuggerInspector] paused execution.
+#[derive(Debug)]
+pub enum Pause<'a> {
+    /// Paused before executing the current instruction of the interpreter.
+    Step {
+        /// The interpreter that is about to execute the instruction.
+        interpreter: &'a Interpreter,
+        /// The breakpoint that was hit, or `None` if stepping.
+        breakpoint: Option<&'a Breakpoint>,
+    },
+    /// Paused before a call that transfers tokens.
+    TokenTransfer {
+        /// Inputs of the call.
+        inputs: &'a CallInputs,
+        /// The breakpoint that was hit.
+        breakpoint: &'a Breakpoint,
+    },
+}
+
+/// What the [DebuggerInspector] does once the callback returns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DebuggerAction {
+    /// Resumes execution until the next breakpoint.
+    #[default]
+    Continue,
+    /// Resumes execution and pauses again before the next instruction.
+    Step,
+    /// Halts the transaction with [InstructionResult::DebuggerHalt].
+    Halt,
+}
+
+/// Callback called by the [DebuggerInspector] when execution is paused.
+///
+/// Execution stays suspended until the callback returns, so an interactive debugger can
+/// inspect the interpreter and wait for user input in it.
+pub trait DebuggerCallback {
+    /// Called when execution is paused.
+    fn on_pause(&mut self, pause: Pause<'_>) -> DebuggerAction;
+}
+
+impl<F: FnMut(Pause<'_>) -> DebuggerAction> DebuggerCallback for F {
+    #[inline]
+    fn on_pause(&mut self, pause: Pause<'_>) -> DebuggerAction {
+        self(pause)
+    }
+}
+
+/// Debugging [Inspector] that pauses execution on [Breakpoint]s.
+///
+/// Halting unwinds every frame of the transaction with [InstructionResult::DebuggerHalt], which
+/// reverts its state changes.
+#[derive(Clone, Debug)]
+pub struct DebuggerInspector<C> {
+    breakpoints: Vec<Breakpoint>,
+    callback: C,
+    stepping: bool,
+    halted: bool,
+}
+
+impl<C: DebuggerCallback> DebuggerInspector<C> {
+    /// Creates a debugger without breakpoints.
+    pub fn new(callback: C) -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            callback,
+            stepping: false,
+            halted: false,
+        }
+    }
+
+    /// Adds a breakpoint.
+    pub fn with_breakpoint(mut self, breakpoint: Breakpoint) -> Self {
+        self.add_breakpoint(breakpoint);
+        self
+    }
+
+    /// Adds a breakpoint.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    /// Removes a breakpoint, returning whether it was set.
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|b| b != breakpoint);
+        self.breakpoints.len() != len
+    }
+
+    /// Returns the breakpoints.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Pauses before the next instruction.
+    pub fn step(&mut self) {
+        self.stepping = true;
+    }
+
+    /// Returns `true` if the callback halted the transaction.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns the callback.
+    pub fn callback(&self) -> &C {
+        &self.callback
+    }
+
+    /// Consumes the debugger, returning the callback.
+    pub fn into_callback(self) -> C {
+        self.callback
+    }
+
+    /// Resets the stepping and halted state, keeping the breakpoints.
+    pub fn reset(&mut self) {
+        self.stepping = false;
+        self.halted = false;
+    }
+
+    fn apply(&mut self, action: DebuggerAction) {
+        match action {
+            DebuggerAction::Continue => self.stepping = false,
+            DebuggerAction::Step => self.stepping = true,
+            DebuggerAction::Halt => {
+                self.stepping = false;
+                self.halted = true;
+            }
+        }
+    }
+
+    fn halted_call(inputs: &CallInputs) -> CallOutcome {
+        CallOutcome::new(
+            InterpreterResult {
+                result: InstructionResult::DebuggerHalt,
+                output: Bytes::new(),
+                gas: Gas::new(inputs.gas_limit),
+            },
+            inputs.return_memory_offset.clone(),
+        )
+    }
+}
+
+impl<DB: Database, C: DebuggerCallback> Inspector<DB> for DebuggerInspector<C> {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if !self.halted {
+            let address = interp.contract.target_address;
+            let pc = interp.program_counter();
+            let opcode = interp.current_opcode();
+            let breakpoint = self.breakpoints.iter().find(|breakpoint| match breakpoint {
+                Breakpoint::Location {
+                    address: bp_address,
+                    pc: bp_pc,
+                } => *bp_address == address && *bp_pc == pc,
+                Breakpoint::Opcode(bp_opcode) => *bp_opcode == opcode,
+                Breakpoint::TokenTransfer(_) => false,
+            });
+            if breakpoint.is_none() && !self.stepping {
+                return;
+            }
+            let action = self.callback.on_pause(Pause::Step {
+                interpreter: interp,
+                breakpoint,
+            });
+            self.apply(action);
+        }
+        if self.halted {
+            interp.instruction_result = InstructionResult::DebuggerHalt;
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if !self.halted && inputs.transfers_value() {
+            let from = inputs.transfer_from();
+            let to = inputs.transfer_to();
+            let breakpoint = self.breakpoints.iter().find(|breakpoint| {
+                matches!(breakpoint, Breakpoint::TokenTransfer(address) if *address == from || *address == to)
+            });
+            if let Some(breakpoint) = breakpoint {
+                let action = self
+                    .callback
+                    .on_pause(Pause::TokenTransfer { inputs, breakpoint });
+                self.apply(action);
+            }
+        }
+        self.halted.then(|| Self::halted_call(inputs))
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.halted.then(|| {
+            CreateOutcome::new(
+                InterpreterResult {
+                    result: InstructionResult::DebuggerHalt,
+                    output: Bytes::new(),
+                    gas: Gas::new(inputs.gas_limit),
+                },
+                None,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inspector_handle_register,
+        interpreter::opcode,
+        primitives::{
+            address, init_balances, AccountInfo, Bytecode, ExecutionResult, HaltReason,
+            TokenTransfer, TransactTo, BASE_TOKEN_ID, U256,
+        },
+        Evm, InMemoryDB,
+    };
+
+    const CALLER: Address = address!("00000000000000000000000000000000000000aa");
+    const CONTRACT: Address = address!("00000000000000000000000000000000000000bb");
+
+    /// Runs `PUSH1 1, PUSH1 2, ADD, POP, STOP` with the debugger.
+    fn run<C: DebuggerCallback>(
+        debugger: DebuggerInspector<C>,
+        transferred_tokens: Vec<TokenTransfer>,
+    ) -> (ExecutionResult, DebuggerInspector<C>) {
+        let bytecode = Bytecode::new_raw(Bytes::from(vec![
+            opcode::PUSH1,
+            0x1,
+            opcode::PUSH1,
+            0x2,
+            opcode::ADD,
+            opcode::POP,
+            opcode::STOP,
+        ]));
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            CONTRACT,
+            AccountInfo {
+                code_hash: bytecode.hash_slow(),
+                code: Some(bytecode),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(debugger)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TransactTo::Call(CONTRACT);
+                tx.gas_limit = 100_000;
+                tx.transferred_tokens = transferred_tokens;
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let result = evm.transact().unwrap().result;
+        (result, evm.into_context().external)
+    }
+
+    fn step_pc(pause: &Pause<'_>) -> Option<usize> {
+        match pause {
+            Pause::Step { interpreter, .. } => Some(interpreter.program_counter()),
+            Pause::TokenTransfer { .. } => None,
+        }
+    }
+
+    #[test]
+    fn pauses_on_opcode_and_location() {
+        let mut pauses = Vec::new();
+        let debugger = DebuggerInspector::new(|pause: Pause<'_>| {
+            pauses.push(step_pc(&pause));
+            DebuggerAction::Continue
+        })
+        .with_breakpoint(Breakpoint::Opcode(opcode::ADD))
+        .with_breakpoint(Breakpoint::Location {
+            address: CONTRACT,
+            pc: 5,
+        });
+        let (result, _) = run(debugger, Vec::new());
+        assert!(result.is_success());
+        assert_eq!(pauses, vec![Some(4), Some(5)]);
+    }
+
+    #[test]
+    fn steps_after_breakpoint() {
+        let mut pauses = Vec::new();
+        let debugger = DebuggerInspector::new(|pause: Pause<'_>| {
+            pauses.push(step_pc(&pause));
+            DebuggerAction::Step
+        })
+        .with_breakpoint(Breakpoint::Opcode(opcode::ADD));
+        let (result, _) = run(debugger, Vec::new());
+        assert!(result.is_success());
+        assert_eq!(pauses, vec![Some(4), Some(5), Some(6)]);
+    }
+
+    #[test]
+    fn halts_transaction() {
+        let debugger = DebuggerInspector::new(|_: Pause<'_>| DebuggerAction::Halt)
+            .with_breakpoint(Breakpoint::Opcode(opcode::ADD));
+        let (result, debugger) = run(debugger, Vec::new());
+        assert!(debugger.is_halted());
+        assert!(matches!(
+            result,
+            ExecutionResult::Halt {
+                reason: HaltReason::DebuggerHalt,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn pauses_on_token_transfer() {
+        let mut pauses = Vec::new();
+        let debugger = DebuggerInspector::new(|pause: Pause<'_>| {
+            if let Pause::TokenTransfer { inputs, .. } = pause {
+                pauses.push(inputs.values.amount_of(BASE_TOKEN_ID));
+            }
+            DebuggerAction::Continue
+        })
+        .with_breakpoint(Breakpoint::TokenTransfer(CONTRACT));
+
+        let transfer = TokenTransfer {
+            id: BASE_TOKEN_ID,
+            amount: U256::from(10),
+        };
+        let (result, _) = run(debugger, vec![transfer]);
+        assert!(result.is_success());
+        assert_eq!(pauses, vec![U256::from(10)]);
+    }
+}
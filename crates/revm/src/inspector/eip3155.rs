@@ -1,5 +1,5 @@
 use crate::{
-    inspectors::GasInspector,
+    inspectors::{GasInspector, TraceFilter},
     interpreter::{
         CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter, InterpreterResult,
     },
@@ -27,6 +27,7 @@ pub struct TracerEip3155 {
     skip: bool,
     include_memory: bool,
     memory: Option<String>,
+    filter: TraceFilter,
 }
 
 // # Output
@@ -141,6 +142,7 @@ impl TracerEip3155 {
             refunded: 0,
             mem_size: 0,
             skip: false,
+            filter: TraceFilter::default(),
         }
     }
 
@@ -156,6 +158,12 @@ impl TracerEip3155 {
         self
     }
 
+    /// Only output the steps of the frames that match the filter.
+    pub fn with_filter(mut self, filter: TraceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
     fn write_value(&mut self, value: &impl serde::Serialize) -> std::io::Result<()> {
         serde_json::to_writer(&mut *self.output, value)?;
         self.output.write_all(b"\n")?;
@@ -191,6 +199,10 @@ impl<DB: Database> Inspector<DB> for TracerEip3155 {
 
     fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
         self.gas_inspector.step(interp, context);
+        if !self.filter.matches_contract(&interp.contract) {
+            self.skip = true;
+            return;
+        }
         self.stack.clone_from(interp.stack.data());
         self.memory = if self.include_memory {
             Some(hex::encode_prefixed(interp.shared_memory.context_memory()))
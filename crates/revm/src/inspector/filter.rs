@@ -0,0 +1,179 @@
+use crate::{
+    interpreter::{CallInputs, Contract, CreateInputs},
+    primitives::{Address, HashSet, TokenTransfer, U256},
+};
+
+/// Limits the events recorded by the tracing inspectors to a set of addresses and/or token IDs.
+///
+/// The filter is checked when the inspector hooks are called, so events that don't match are
+/// never recorded.
+///
+/// An empty set matches everything. When both sets are non-empty, an event has to match both:
+/// it has to involve one of the addresses and transfer one of the token IDs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    addresses: HashSet<Address>,
+    token_ids: HashSet<U256>,
+}
+
+impl TraceFilter {
+    /// Creates a filter that matches everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an address to the filter.
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.addresses.insert(address);
+        self
+    }
+
+    /// Adds addresses to the filter.
+    pub fn with_addresses(mut self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.addresses.extend(addresses);
+        self
+    }
+
+    /// Adds a token ID to the filter.
+    pub fn with_token_id(mut self, token_id: U256) -> Self {
+        self.token_ids.insert(token_id);
+        self
+    }
+
+    /// Adds token IDs to the filter.
+    pub fn with_token_ids(mut self, token_ids: impl IntoIterator<Item = U256>) -> Self {
+        self.token_ids.extend(token_ids);
+        self
+    }
+
+    /// Returns `true` if the filter matches everything.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.token_ids.is_empty()
+    }
+
+    /// Returns `true` if the address passes the address filter.
+    pub fn matches_address(&self, address: &Address) -> bool {
+        self.addresses.is_empty() || self.addresses.contains(address)
+    }
+
+    /// Returns `true` if one of the transfers passes the token ID filter.
+    pub fn matches_tokens(&self, transfers: &[TokenTransfer]) -> bool {
+        self.token_ids.is_empty()
+            || transfers
+                .iter()
+                .any(|transfer| self.token_ids.contains(&transfer.id))
+    }
+
+    /// Returns `true` if the steps executed by the contract should be recorded.
+    pub fn matches_contract(&self, contract: &Contract) -> bool {
+        self.matches_address(&contract.target_address) && self.matches_tokens(&contract.call_values)
+    }
+
+    /// Returns `true` if the call should be recorded.
+    pub fn matches_call(&self, inputs: &CallInputs) -> bool {
+        (self.matches_address(&inputs.caller)
+            || self.matches_address(&inputs.target_address)
+            || self.matches_address(&inputs.bytecode_address))
+            && self.matches_tokens(inputs.values.as_slice())
+    }
+
+    /// Returns `true` if the contract creation, whose value is an amount of the base token,
+    /// should be recorded.
+    pub fn matches_create(&self, inputs: &CreateInputs, base_token_id: U256) -> bool {
+        let transfer = TokenTransfer {
+            id: base_token_id,
+            amount: inputs.value,
+        };
+        let transfers: &[TokenTransfer] = if transfer.is_zero() { &[] } else { &[transfer] };
+        self.matches_address(&inputs.caller) && self.matches_tokens(transfers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{address, Bytes, TransactTo, TxEnv};
+
+    const CALLER: Address = address!("00000000000000000000000000000000000000aa");
+    const TARGET: Address = address!("00000000000000000000000000000000000000bb");
+    const OTHER: Address = address!("00000000000000000000000000000000000000cc");
+
+    fn call(token_ids: &[u64]) -> CallInputs {
+        let tx = TxEnv {
+            caller: CALLER,
+            transact_to: TransactTo::Call(TARGET),
+            data: Bytes::new(),
+            transferred_tokens: token_ids
+                .iter()
+                .map(|id| TokenTransfer {
+                    id: U256::from(*id),
+                    amount: U256::from(1),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        CallInputs::new(&tx, 0).unwrap()
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = TraceFilter::new();
+        assert!(filter.is_empty());
+        assert!(filter.matches_call(&call(&[])));
+        assert!(filter.matches_call(&call(&[1, 2])));
+    }
+
+    #[test]
+    fn filters_by_address() {
+        let filter = TraceFilter::new().with_address(TARGET);
+        assert!(filter.matches_call(&call(&[])));
+        assert!(TraceFilter::new()
+            .with_address(CALLER)
+            .matches_call(&call(&[])));
+        assert!(!TraceFilter::new()
+            .with_address(OTHER)
+            .matches_call(&call(&[])));
+    }
+
+    #[test]
+    fn filters_by_token_id() {
+        let filter = TraceFilter::new().with_token_ids([U256::from(2), U256::from(3)]);
+        assert!(!filter.matches_call(&call(&[])));
+        assert!(!filter.matches_call(&call(&[1])));
+        assert!(filter.matches_call(&call(&[1, 2])));
+    }
+
+    #[test]
+    fn address_and_token_id_filters_both_apply() {
+        let filter = TraceFilter::new()
+            .with_addresses([OTHER])
+            .with_token_id(U256::from(1));
+        assert!(!filter.matches_call(&call(&[1])));
+
+        let filter = filter.with_address(TARGET);
+        assert!(filter.matches_call(&call(&[1])));
+        assert!(!filter.matches_call(&call(&[2])));
+    }
+
+    #[test]
+    fn filters_creates_by_their_base_token_value() {
+        let base_token_id = U256::from(7);
+        let create = |value: u64| {
+            let tx = TxEnv {
+                caller: CALLER,
+                transact_to: TransactTo::Create,
+                transferred_tokens: vec![TokenTransfer {
+                    id: base_token_id,
+                    amount: U256::from(value),
+                }],
+                ..Default::default()
+            };
+            CreateInputs::new(&tx, 0, base_token_id).unwrap()
+        };
+        let filter = TraceFilter::new().with_token_id(base_token_id);
+        assert!(filter.matches_create(&create(1), base_token_id));
+        assert!(!filter.matches_create(&create(0), base_token_id));
+        assert!(!filter.matches_create(&create(1), U256::from(1)));
+        assert!(TraceFilter::new().matches_create(&create(0), base_token_id));
+    }
+}
@@ -3,10 +3,10 @@ use crate::{
     handler::register::EvmHandler,
     interpreter::{
         opcode::{self, BoxedInstruction},
-        InstructionResult, Interpreter,
+        CreateOutcome, Gas, InstructionResult, Interpreter, InterpreterResult,
     },
-    primitives::EVMError,
-    Context, FrameOrResult, FrameResult, Inspector, JournalEntry,
+    primitives::{Address, Bytes, EVMError, TokenTransfer, U256},
+    Context, Frame, FrameOrResult, FrameResult, Inspector, JournalEntry,
 };
 use core::cell::RefCell;
 use revm_interpreter::opcode::InstructionTables;
@@ -87,20 +87,43 @@ pub fn inspector_handle_register<DB: Database, EXT: GetInspector<DB>>(
         let old = core::mem::replace(i, Box::new(|_, _| ()));
         *i = Box::new(
             move |interpreter: &mut Interpreter, host: &mut Context<EXT, DB>| {
+                let target = interpreter
+                    .stack
+                    .peek(0)
+                    .map(|word| Address::from_word(word.into()));
+                let entries = host.evm.journaled_state.journal.last().map_or(0, Vec::len);
                 // execute selfdestruct
                 old(interpreter, host);
-                // check if selfdestruct was successful and if journal entry is made.
-                if let Some(JournalEntry::AccountDestroyed {
-                    address,
-                    target,
-                    had_balance,
-                    ..
-                }) = host.evm.journaled_state.journal.last().unwrap().last()
-                {
-                    host.external
-                        .get_inspector()
-                        .selfdestruct(*address, *target, *had_balance);
+                // check if selfdestruct was successful
+                let Ok(target) = target else {
+                    return;
+                };
+                if interpreter.instruction_result != InstructionResult::SelfDestruct {
+                    return;
                 }
+                // The balances are either destroyed with the account, or swept to the target
+                // (EIP-6780), and the ones sent to the burn address are then burned.
+                let contract = interpreter.contract.target_address;
+                let values: Vec<TokenTransfer> = host.evm.journaled_state.journal.last().unwrap()
+                    [entries..]
+                    .iter()
+                    .flat_map(|entry| match entry {
+                        JournalEntry::AccountDestroyed { had_balances, .. } => had_balances.clone(),
+                        JournalEntry::BalanceTransfer {
+                            from,
+                            token_id,
+                            amount,
+                            ..
+                        } if *from == contract => vec![TokenTransfer {
+                            id: *token_id,
+                            amount: *amount,
+                        }],
+                        _ => Vec::new(),
+                    })
+                    .collect();
+                host.external
+                    .get_inspector()
+                    .selfdestruct(contract, target, &values);
             },
         )
     }
@@ -129,6 +152,37 @@ pub fn inspector_handle_register<DB: Database, EXT: GetInspector<DB>>(
             }
             create_input_stack_inner.borrow_mut().push(inputs.clone());
 
+            if inputs.value != U256::ZERO {
+                // the created address is derived from the nonce before the creation.
+                let nonce = ctx
+                    .evm
+                    .journaled_state
+                    .state
+                    .accounts
+                    .get(&inputs.caller)
+                    .map_or(0, |account| account.info.nonce);
+                let transfers = [(
+                    inputs.caller,
+                    inputs.created_address(nonce),
+                    TokenTransfer {
+                        id: ctx.evm.env.cfg.base_token_id,
+                        amount: inputs.value,
+                    },
+                )];
+                if let Some(result) = inspect_token_transfers(ctx, transfers) {
+                    return Ok(FrameOrResult::Result(FrameResult::Create(
+                        CreateOutcome::new(
+                            InterpreterResult {
+                                result,
+                                gas: Gas::new(inputs.gas_limit),
+                                output: Bytes::new(),
+                            },
+                            None,
+                        ),
+                    )));
+                }
+            }
+
             let mut frame_or_result = old_handle(ctx, inputs);
             if let Ok(FrameOrResult::Frame(frame)) = &mut frame_or_result {
                 ctx.external
@@ -151,18 +205,92 @@ pub fn inspector_handle_register<DB: Database, EXT: GetInspector<DB>>(
                 return Ok(FrameOrResult::Result(FrameResult::Call(outcome)));
             }
 
-            let mut frame_or_result = old_handle(ctx, inputs);
-            if let Ok(FrameOrResult::Frame(frame)) = &mut frame_or_result {
+            #[cfg_attr(not(feature = "std"), allow(unused_mut))]
+            let mut transfers: Vec<_> = inputs
+                .values
+                .transferred()
+                .iter()
+                .map(|transfer| (inputs.caller, inputs.target_address, transfer.clone()))
+                .collect();
+
+            #[cfg(feature = "std")]
+            if let Some(call) = ctx.evm.precompiles.native_token_call(&inputs) {
                 ctx.external
                     .get_inspector()
-                    .initialize_interp(frame.interpreter_mut(), &mut ctx.evm)
+                    .native_token_call(&mut ctx.evm, &inputs, &call);
+                // the precompile transfers the tokens of the delegating contract, unless it
+                // spends an allowance.
+                for operation in call.operations() {
+                    if let Some((recipient, token_transfers)) = operation.token_transfers() {
+                        let sender = operation.token_sender().unwrap_or(inputs.target_address);
+                        transfers.extend(
+                            token_transfers
+                                .into_iter()
+                                .map(|transfer| (sender, recipient, transfer)),
+                        );
+                    }
+                }
             }
-            frame_or_result
+
+            if let Some(result) = inspect_token_transfers(ctx, transfers) {
+                return Ok(FrameOrResult::new_call_result(
+                    InterpreterResult {
+                        result,
+                        gas: Gas::new(inputs.gas_limit),
+                        output: Bytes::new(),
+                    },
+                    inputs.return_memory_offset.clone(),
+                ));
+            }
+
+            let mut frame = match old_handle(ctx, inputs)? {
+                FrameOrResult::Frame(frame) => frame,
+                result => return Ok(result),
+            };
+            let inspector = ctx.external.get_inspector();
+            // The call spawned by a precompile runs in place of the precompile frame. It is
+            // inspected as a call made by the precompile, whose inputs can't be changed as the
+            // frame is already made.
+            if let Frame::Call(call_frame) = &frame {
+                if let Some(spawned_call) = &call_frame.spawned_call {
+                    let mut spawned_inputs = spawned_call.clone();
+                    let checkpoint = call_frame.frame_data.checkpoint;
+                    let retained_gas = call_frame.retained_gas;
+                    if let Some(outcome) = inspector.call(&mut ctx.evm, &mut spawned_inputs) {
+                        let mut outcome =
+                            inspector.call_end(&mut ctx.evm, &spawned_inputs, outcome);
+                        // the precompile call is charged like the spawned call had returned.
+                        outcome.result.gas.erase_cost(retained_gas);
+                        ctx.evm.journaled_state.checkpoint_revert(checkpoint);
+                        ctx.evm.frame_pool.recycle_frame(&mut frame);
+                        return Ok(FrameOrResult::Result(FrameResult::Call(outcome)));
+                    }
+                    call_input_stack_inner.borrow_mut().push(spawned_inputs);
+                }
+            }
+            inspector.initialize_interp(frame.interpreter_mut(), &mut ctx.evm);
+            Ok(FrameOrResult::Frame(frame))
         },
     );
 
     // TODO(EOF) EOF create call.
 
+    // The call spawned by a precompile ends when its frame returns, before the precompile call.
+    let call_input_stack_inner = call_input_stack.clone();
+    let old_handle = handler.execution.call_return.clone();
+    handler.execution.call_return = Arc::new(move |ctx, frame, result| {
+        let spawned = frame.spawned_call.is_some();
+        let outcome = old_handle(ctx, frame, result)?;
+        if !spawned {
+            return Ok(outcome);
+        }
+        let spawned_inputs = call_input_stack_inner.borrow_mut().pop().unwrap();
+        Ok(ctx
+            .external
+            .get_inspector()
+            .call_end(&mut ctx.evm, &spawned_inputs, outcome))
+    });
+
     // call outcome
     let call_input_stack_inner = call_input_stack.clone();
     let old_handle = handler.execution.insert_call_outcome.clone();
@@ -213,6 +341,26 @@ pub fn inspector_handle_register<DB: Database, EXT: GetInspector<DB>>(
     });
 }
 
+/// Calls [Inspector::token_transfer] for the transfers with a nonzero amount, returning the
+/// result of the first one that is blocked.
+fn inspect_token_transfers<DB: Database, EXT: GetInspector<DB>>(
+    ctx: &mut Context<EXT, DB>,
+    transfers: impl IntoIterator<Item = (Address, Address, TokenTransfer)>,
+) -> Option<InstructionResult> {
+    transfers
+        .into_iter()
+        .filter(|(.., transfer)| !transfer.is_zero())
+        .find_map(|(from, to, transfer)| {
+            ctx.external.get_inspector().token_transfer(
+                &mut ctx.evm,
+                from,
+                to,
+                transfer.id,
+                transfer.amount,
+            )
+        })
+}
+
 /// Outer closure that calls Inspector for every instruction.
 pub fn inspector_instruction<
     'a,
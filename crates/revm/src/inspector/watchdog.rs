@@ -0,0 +1,237 @@
+use crate::{
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, Gas, InstructionResult, Interpreter,
+        InterpreterResult,
+    },
+    primitives::Bytes,
+    Database, EvmContext, Inspector,
+};
+use core::ops::ControlFlow;
+
+/// Callback called by the [WatchdogInspector] every given number of interpreter steps.
+///
+/// It is given the number of steps executed by the transaction so far, and breaks to interrupt
+/// the transaction, e.g. when a deadline has passed.
+pub trait WatchdogCallback {
+    /// Called every interval of steps.
+    fn check(&mut self, steps: u64) -> ControlFlow<()>;
+}
+
+impl<F: FnMut(u64) -> ControlFlow<()>> WatchdogCallback for F {
+    #[inline]
+    fn check(&mut self, steps: u64) -> ControlFlow<()> {
+        self(steps)
+    }
+}
+
+/// [Inspector] that lets a callback interrupt long executions.
+///
+/// The callback is called every `interval` interpreter steps. Interrupting unwinds every frame
+/// of the transaction with [InstructionResult::Interrupted], which reverts its state changes, so
+/// a server enforcing a timeout can stop the execution without killing the thread running it.
+///
+/// The steps are counted from the start of every transaction.
+#[derive(Clone, Debug)]
+pub struct WatchdogInspector<C> {
+    interval: u64,
+    callback: C,
+    steps: u64,
+    interrupted: bool,
+}
+
+impl<C: WatchdogCallback> WatchdogInspector<C> {
+    /// Creates a watchdog calling the callback every `interval` steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn new(interval: u64, callback: C) -> Self {
+        assert!(interval > 0, "the watchdog interval must be nonzero");
+        Self {
+            interval,
+            callback,
+            steps: 0,
+            interrupted: false,
+        }
+    }
+
+    /// Returns the number of steps executed by the current or last transaction.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Returns `true` if the callback interrupted the current or last transaction.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Returns the callback.
+    pub fn callback(&self) -> &C {
+        &self.callback
+    }
+
+    /// Consumes the watchdog, returning the callback.
+    pub fn into_callback(self) -> C {
+        self.callback
+    }
+
+    /// Resets the step count and the interrupted state.
+    pub fn reset(&mut self) {
+        self.steps = 0;
+        self.interrupted = false;
+    }
+
+    fn interrupted_result(gas_limit: u64) -> InterpreterResult {
+        InterpreterResult {
+            result: InstructionResult::Interrupted,
+            output: Bytes::new(),
+            gas: Gas::new(gas_limit),
+        }
+    }
+}
+
+impl<DB: Database, C: WatchdogCallback> Inspector<DB> for WatchdogInspector<C> {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if !self.interrupted {
+            self.steps += 1;
+            if self.steps.is_multiple_of(self.interval)
+                && self.callback.check(self.steps).is_break()
+            {
+                self.interrupted = true;
+            }
+        }
+        if self.interrupted {
+            interp.instruction_result = InstructionResult::Interrupted;
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if context.journaled_state.depth() == 0 {
+            self.reset();
+        }
+        self.interrupted.then(|| {
+            CallOutcome::new(
+                Self::interrupted_result(inputs.gas_limit),
+                inputs.return_memory_offset.clone(),
+            )
+        })
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        if context.journaled_state.depth() == 0 {
+            self.reset();
+        }
+        self.interrupted
+            .then(|| CreateOutcome::new(Self::interrupted_result(inputs.gas_limit), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inspector_handle_register,
+        interpreter::opcode,
+        primitives::{
+            address, init_balances, AccountInfo, Address, Bytecode, ExecutionResult, HaltReason,
+            TransactTo, U256,
+        },
+        Evm, InMemoryDB,
+    };
+    use std::vec::Vec;
+
+    const CALLER: Address = address!("00000000000000000000000000000000000000aa");
+    const CONTRACT: Address = address!("00000000000000000000000000000000000000bb");
+
+    /// Runs two transactions of an infinite loop, `JUMPDEST, PUSH1 0, JUMP`, with the watchdog.
+    fn run<C: WatchdogCallback>(
+        watchdog: WatchdogInspector<C>,
+    ) -> ([ExecutionResult; 2], WatchdogInspector<C>) {
+        let bytecode = Bytecode::new_raw(Bytes::from(vec![
+            opcode::JUMPDEST,
+            opcode::PUSH1,
+            0x0,
+            opcode::JUMP,
+        ]));
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            CONTRACT,
+            AccountInfo {
+                code_hash: bytecode.hash_slow(),
+                code: Some(bytecode),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(watchdog)
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TransactTo::Call(CONTRACT);
+                tx.gas_limit = 1_000_000;
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let results = [
+            evm.transact().unwrap().result,
+            evm.transact().unwrap().result,
+        ];
+        (results, evm.into_context().external)
+    }
+
+    #[test]
+    fn interrupts_transaction() {
+        let mut checks = Vec::new();
+        let watchdog = WatchdogInspector::new(100, |steps| {
+            checks.push(steps);
+            if steps < 300 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+        let (results, watchdog) = run(watchdog);
+        assert!(watchdog.is_interrupted());
+        assert_eq!(watchdog.steps(), 300);
+        for result in results {
+            assert!(matches!(
+                result,
+                ExecutionResult::Halt {
+                    reason: HaltReason::Interrupted,
+                    ..
+                }
+            ));
+        }
+        // the steps are counted again for the second transaction
+        assert_eq!(checks, vec![100, 200, 300, 100, 200, 300]);
+    }
+
+    #[test]
+    fn lets_transaction_run_out_of_gas() {
+        let watchdog = WatchdogInspector::new(1000, |_| ControlFlow::Continue(()));
+        let (results, watchdog) = run(watchdog);
+        assert!(!watchdog.is_interrupted());
+        assert!(matches!(
+            results[0],
+            ExecutionResult::Halt {
+                reason: HaltReason::OutOfGas(_),
+                ..
+            }
+        ));
+    }
+}
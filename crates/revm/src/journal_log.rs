@@ -0,0 +1,318 @@
+//! Append-only log of the journals of executed transactions.
+//!
+//! An embedder that executes a block can persist the journal of every transaction as it is
+//! executed, and after a crash replay the log against the state the block started from to
+//! recover the transactions that were already executed.
+use crate::{
+    handler::register::EvmHandler,
+    primitives::{EVMError, EvmState},
+    Database, DatabaseCommit, JournalEntry,
+};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, BufRead, Write},
+    string::ToString,
+    sync::{Arc, Mutex},
+    vec::Vec,
+};
+
+/// The journal of a transaction, as appended to a [JournalLog].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalLogRecord {
+    /// The entries journaled by the transaction, in the order they were made. The entries of
+    /// reverted calls are not included.
+    pub entries: Vec<JournalEntry>,
+    /// The state changed by the transaction, as it is committed to the database.
+    pub state: EvmState,
+}
+
+/// Append-only log of [JournalLogRecord]s, written as one line of JSON per transaction.
+///
+/// The log is installed with [`EvmBuilder::with_journal_log`](crate::EvmBuilder::with_journal_log),
+/// and read back with [read_journal_log].
+#[derive(Debug)]
+pub struct JournalLog<W> {
+    writer: W,
+}
+
+impl<W: Write> JournalLog<W> {
+    /// Creates a log appending to the writer.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Appends the record and flushes the writer.
+    ///
+    /// The record is written at once, so a crash leaves at most a truncated last line.
+    pub fn append(&mut self, record: &JournalLogRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.writer.flush()
+    }
+
+    /// Returns the writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes the log, returning the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Makes the handler append the journal of every executed transaction to the log.
+    ///
+    /// A transaction whose record can't be appended fails with [EVMError::Custom].
+    pub fn register<EXT, DB: Database>(log: Arc<Mutex<Self>>, handler: &mut EvmHandler<'_, EXT, DB>)
+    where
+        W: Send + 'static,
+    {
+        let output = handler.post_execution.output.clone();
+        handler.post_execution.output = Arc::new(move |ctx, result| {
+            // the journal is reset when the output is made.
+            let entries = ctx.evm.journaled_state.journal.concat();
+            let result_and_state = output(ctx, result)?;
+            let record = JournalLogRecord {
+                entries,
+                state: result_and_state.state.clone(),
+            };
+            log.lock()
+                .map_err(|e| EVMError::Custom(e.to_string()))?
+                .append(&record)
+                .map_err(|e| EVMError::Custom(e.to_string()))?;
+            Ok(result_and_state)
+        });
+    }
+}
+
+/// Reads the records of a [JournalLog].
+///
+/// A truncated last line, left by a crash while the record was appended, is ignored.
+pub fn read_journal_log<R: BufRead>(mut reader: R) -> io::Result<Vec<JournalLogRecord>> {
+    let mut records = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 || line.last() != Some(&b'\n') {
+            return Ok(records);
+        }
+        records.push(serde_json::from_slice(&line)?);
+    }
+}
+
+/// Error of [replay], for a record whose entries change an account or a token missing from the
+/// state it commits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InconsistentRecord {
+    /// The index of the record among the replayed ones.
+    pub index: usize,
+    /// The first entry of the record that doesn't match its state.
+    pub entry: JournalEntry,
+}
+
+impl std::error::Error for InconsistentRecord {}
+
+impl fmt::Display for InconsistentRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "journal log record {} has a {} entry missing from its state",
+            self.index,
+            self.entry.kind()
+        )
+    }
+}
+
+impl JournalLogRecord {
+    /// Returns the first entry that loads or changes an account missing from the state, or adds a
+    /// token missing from it.
+    pub fn inconsistent_entry(&self) -> Option<&JournalEntry> {
+        self.entries.iter().find(|entry| {
+            entry
+                .addresses()
+                .any(|address| !self.state.accounts.contains_key(&address))
+                || matches!(entry, JournalEntry::TokenIdAdded { token_id }
+                    if !self.state.token_ids.contains(token_id))
+        })
+    }
+}
+
+/// Replays the records against the state they were executed on, committing the changes of every
+/// transaction to the database in order.
+///
+/// The entries of every record are checked against its state before it is committed, so the
+/// replay stops at the first inconsistent record, after committing the ones before it.
+pub fn replay<DB: DatabaseCommit>(
+    db: &mut DB,
+    records: impl IntoIterator<Item = JournalLogRecord>,
+) -> Result<(), InconsistentRecord> {
+    for (index, record) in records.into_iter().enumerate() {
+        if let Some(entry) = record.inconsistent_entry() {
+            return Err(InconsistentRecord {
+                index,
+                entry: entry.clone(),
+            });
+        }
+        db.commit(record.state);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        primitives::{
+            address, init_balances, AccountInfo, Address, TokenTransfer, TransactTo, BASE_TOKEN_ID,
+            U256,
+        },
+        Evm,
+    };
+
+    const CALLER: Address = address!("00000000000000000000000000000000000000aa");
+    const RECIPIENT: Address = address!("00000000000000000000000000000000000000bb");
+
+    fn prior_state() -> CacheDB<EmptyDB> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            CALLER,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        db
+    }
+
+    #[test]
+    fn replays_logged_transactions() {
+        let log = Arc::new(Mutex::new(JournalLog::new(Vec::new())));
+        let mut evm = Evm::builder()
+            .with_db(prior_state())
+            .with_journal_log(log.clone())
+            .modify_tx_env(|tx| {
+                tx.caller = CALLER;
+                tx.transact_to = TransactTo::Call(RECIPIENT);
+                tx.gas_limit = 21_000;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(10),
+                }];
+            })
+            .build();
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert!(evm.transact_commit().unwrap().is_success());
+
+        let mut bytes = log.lock().unwrap().get_ref().clone();
+        // a crash while the third transaction was appended
+        bytes.extend_from_slice(b"{\"entries\":[");
+        let records = read_journal_log(bytes.as_slice()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records[0].entries.contains(&JournalEntry::BalanceTransfer {
+            from: CALLER,
+            to: RECIPIENT,
+            token_id: BASE_TOKEN_ID,
+            amount: U256::from(10),
+        }));
+
+        let mut recovered = prior_state();
+        replay(&mut recovered, records.clone()).unwrap();
+        for address in [CALLER, RECIPIENT] {
+            assert_eq!(
+                recovered.accounts[&address].info,
+                evm.db().accounts[&address].info
+            );
+        }
+
+        // a record whose state misses an account of its entries isn't committed
+        let mut records = records;
+        records[1].state.accounts.remove(&RECIPIENT);
+        let mut recovered = prior_state();
+        let error = replay(&mut recovered, records.clone()).unwrap_err();
+        assert_eq!(error.index, 1);
+        assert_eq!(records[1].inconsistent_entry(), Some(&error.entry));
+        assert_eq!(
+            recovered.accounts[&CALLER].info,
+            records[0].state.accounts[&CALLER].info
+        );
+    }
+
+    #[test]
+    fn journal_entries_serde_is_stable() {
+        let holder = address!("00000000000000000000000000000000000000cc");
+        let entry = JournalEntry::TokensMinted {
+            minter: CALLER,
+            recipient: holder,
+            token_id: U256::from(1),
+            minted_amount: U256::from(2),
+        };
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            "{\"TokensMinted\":{\"minter\":\"0x00000000000000000000000000000000000000aa\",\
+             \"recipient\":\"0x00000000000000000000000000000000000000cc\",\
+             \"token_id\":\"0x1\",\"minted_amount\":\"0x2\"}}"
+        );
+
+        let entries = vec![
+            JournalEntry::AccountLoaded { address: holder },
+            JournalEntry::AccountDestroyed {
+                address: holder,
+                target: CALLER,
+                was_destroyed: false,
+                had_balances: vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(3),
+                }],
+            },
+            JournalEntry::AccountTouched { address: holder },
+            JournalEntry::TokenBalanceWarmed {
+                address: holder,
+                token_id: U256::from(1),
+            },
+            JournalEntry::BalanceTransfer {
+                from: holder,
+                to: CALLER,
+                token_id: U256::from(1),
+                amount: U256::from(4),
+            },
+            JournalEntry::NonceChange { address: holder },
+            JournalEntry::AccountCreated { address: holder },
+            JournalEntry::StorageChange {
+                address: holder,
+                key: U256::from(5),
+                had_value: None,
+            },
+            JournalEntry::TransientStorageChange {
+                address: holder,
+                key: U256::from(6),
+                had_value: U256::from(7),
+            },
+            JournalEntry::CodeChange { address: holder },
+            JournalEntry::TokensBurned {
+                token_holder: holder,
+                token_id: U256::from(1),
+                burned_amount: U256::from(8),
+            },
+            JournalEntry::TokenIdsLoaded {
+                token_ids: vec![BASE_TOKEN_ID, U256::from(1)],
+            },
+            JournalEntry::TokenIdAdded {
+                token_id: U256::from(1),
+            },
+            entry,
+            JournalEntry::TipPaid {
+                beneficiary: holder,
+                token_id: U256::from(1),
+                amount: U256::from(9),
+            },
+        ];
+        let json = serde_json::to_string(&entries).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<JournalEntry>>(&json).unwrap(),
+            entries
+        );
+    }
+}
@@ -1,13 +1,21 @@
-use crate::interpreter::{InstructionResult, SelfDestructResult};
+use crate::interpreter::{InstructionResult, SelfDestructResult, TransferFailure};
 use crate::primitives::{
-    db::Database, hash_map::Entry, token_id_address, Account, Address, Bytecode, EVMError,
-    EvmState, EvmStorageSlot, HashSet, Log, SpecId::*, TokenTransfer, TransientStorage,
-    BASE_TOKEN_ID, KECCAK_EMPTY, PRECOMPILE3, U256,
+    db::Database, hash_map::Entry, nonzero_token_transfers, Account, Address, Bytecode, EVMError,
+    EvmState, EvmStorageSlot, HashSet, Log, SpecId::*, StateChunks, TokenIdDerivation,
+    TokenTransfer, TransientStorage, BASE_TOKEN_ID, KECCAK_EMPTY, PRECOMPILE3, U256,
 };
-use core::mem;
+use crate::sablier;
+use core::{fmt, mem};
 use revm_interpreter::primitives::SpecId;
 use revm_interpreter::{LoadAccountResult, SStoreResult};
-use std::vec::Vec;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+/// The outcome of [JournaledState::selfdestruct]: its result and the balances moved to the
+/// target, or why they couldn't be moved.
+pub type SelfDestructOutcome = Result<(SelfDestructResult, Vec<TokenTransfer>), TransferFailure>;
 
 /// JournalState is internal EVM state that is used to contain state and track changes to that state.
 /// It contains journal of changes that happened to state so that they can be reverted.
@@ -28,6 +36,8 @@ pub struct JournaledState {
     /// Spec is needed for two things SpuriousDragon's `EIP-161 State clear`,
     /// and for Cancun's `EIP-6780: SELFDESTRUCT in same transaction`
     pub spec: SpecId,
+    /// The ID of the base token of the chain, transferred by `CREATE` and `SELFDESTRUCT`.
+    pub base_token_id: U256,
     /// Warm loaded addresses are used to check if loaded address
     /// should be considered cold or warm loaded when the account
     /// is first accessed.
@@ -35,6 +45,15 @@ pub struct JournaledState {
     /// Note that this not include newly loaded accounts, account and storage
     /// is considered warm if it is found in the `State`.
     pub warm_preloaded_addresses: HashSet<Address>,
+    /// The balances of the Native Tokens other than the base token that were accessed by the
+    /// transaction, per account and token ID, which are warm in the rest of it.
+    ///
+    /// The balance of the base token is warm when its account is.
+    pub warm_token_balances: HashSet<(Address, U256)>,
+    /// The number of entries in the frames of the journal before the last one, which only
+    /// change when a checkpoint is made or reverted, so that the entries are counted without
+    /// walking the journal.
+    previous_frames_entries: usize,
 }
 
 impl JournaledState {
@@ -57,7 +76,10 @@ impl JournaledState {
             journal: vec![vec![]],
             depth: 0,
             spec,
+            base_token_id: BASE_TOKEN_ID,
             warm_preloaded_addresses,
+            warm_token_balances: HashSet::new(),
+            previous_frames_entries: 0,
         }
     }
 
@@ -73,6 +95,12 @@ impl JournaledState {
         self.spec = spec;
     }
 
+    /// Sets the ID of the base token.
+    #[inline]
+    pub fn set_base_token_id(&mut self, base_token_id: U256) {
+        self.base_token_id = base_token_id;
+    }
+
     /// Mark account as touched as only touched accounts will be added to state.
     /// This is especially important for state clear where touched empty accounts needs to
     /// be removed from state.
@@ -109,20 +137,37 @@ impl JournaledState {
             logs,
             depth,
             journal,
+            warm_token_balances,
+            previous_frames_entries,
             // kept, see [Self::new]
             spec: _,
+            base_token_id: _,
             warm_preloaded_addresses: _,
         } = self;
 
         *transient_storage = TransientStorage::default();
         *journal = vec![vec![]];
+        *previous_frames_entries = 0;
         *depth = 0;
+        warm_token_balances.clear();
         let state = mem::take(state);
         let logs = mem::take(logs);
 
         (state, logs)
     }
 
+    /// Does cleanup like [Self::finalize], returning the modified state as an iterator draining
+    /// it in chunks of at most `chunk_size` accounts, see [EvmState::into_chunks].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    #[inline]
+    pub fn finalize_in_chunks(&mut self, chunk_size: usize) -> (StateChunks, Vec<Log>) {
+        let (state, logs) = self.finalize();
+        (state.into_chunks(chunk_size), logs)
+    }
+
     /// Returns the _loaded_ [Account] for the given address.
     ///
     /// This assumes that the account has already been loaded.
@@ -178,21 +223,27 @@ impl JournaledState {
         Some(account.info.nonce)
     }
 
-    /// Transfers tokens between two accounts. Returns error if sender balance is not enough.
+    /// Transfers tokens between two accounts, returning why the transfer failed, if it did.
     #[inline]
     pub fn transfer<DB: Database>(
         &mut self,
         from: &Address,
         to: &Address,
-        transfers: &Vec<TokenTransfer>,
+        transfers: &[TokenTransfer],
         db: &mut DB,
-    ) -> Result<Option<InstructionResult>, EVMError<DB::Error>> {
-        self.load_native_token_ids(db)?;
-
+    ) -> Result<Option<TransferFailure>, EVMError<DB::Error>> {
         // load accounts
         self.load_account(*from, db)?;
         self.load_account(*to, db)?;
 
+        // The recipient is touched whatever the amounts, see `nonzero_token_transfers`.
+        self.touch(to);
+        let mut transfers = nonzero_token_transfers(transfers).peekable();
+        if transfers.peek().is_none() {
+            return Ok(None);
+        }
+        self.load_native_token_ids(db)?;
+
         for transfer in transfers {
             let token_id = transfer.id;
             let amount = transfer.amount;
@@ -203,7 +254,7 @@ impl JournaledState {
 
             let from_balance = from_account.info.get_balance(token_id);
             let Some(from_balance_decr) = from_balance.checked_sub(amount) else {
-                return Ok(Some(InstructionResult::OutOfFunds));
+                return Ok(Some(TransferFailure::OutOfFunds));
             };
             from_account.info.set_balance(token_id, from_balance_decr);
 
@@ -212,7 +263,7 @@ impl JournaledState {
             Self::touch_account(self.journal.last_mut().unwrap(), to, to_account);
             let to_balance = to_account.info.get_balance(token_id);
             let Some(to_balance_incr) = to_balance.checked_add(amount) else {
-                return Ok(Some(InstructionResult::OverflowPayment));
+                return Ok(Some(TransferFailure::OverflowPayment));
             };
             to_account.info.set_balance(token_id, to_balance_incr);
 
@@ -258,6 +309,19 @@ impl JournaledState {
         // Enter subroutine
         let checkpoint = self.checkpoint();
 
+        let base_token_id = self.base_token_id;
+
+        // Balance is already checked in `create_inner`.
+        let Some(new_caller_base_balance) = self
+            .account(caller)
+            .info
+            .get_balance(base_token_id)
+            .checked_sub(balance)
+        else {
+            self.checkpoint_revert(checkpoint);
+            return Err(TransferFailure::OutOfFunds.into());
+        };
+
         // Newly created account is present, as we just loaded it.
         let account = self.state.accounts.get_mut(&address).unwrap();
         let last_journal = self.journal.last_mut().unwrap();
@@ -266,9 +330,11 @@ impl JournaledState {
         // Bytecode is not empty.
         // Nonce is not zero
         // Account is not precompile.
+        // Account is not in the namespace reserved for the Sablier precompiles.
         if account.info.code_hash != KECCAK_EMPTY
             || account.info.nonce != 0
             || self.warm_preloaded_addresses.contains(&address)
+            || sablier::is_reserved_address(&address)
         {
             self.checkpoint_revert(checkpoint);
             return Err(InstructionResult::CreateCollision);
@@ -295,11 +361,12 @@ impl JournaledState {
         Self::touch_account(last_journal, &address, account);
 
         // Add balance to created account, as we already have target here.
-        let Some(new_base_balance) = account.info.get_base_balance().checked_add(balance) else {
+        let Some(new_base_balance) = account.info.get_balance(base_token_id).checked_add(balance)
+        else {
             self.checkpoint_revert(checkpoint);
-            return Err(InstructionResult::OverflowPayment);
+            return Err(TransferFailure::OverflowPayment.into());
         };
-        account.info.set_base_balance(new_base_balance);
+        account.info.set_balance(base_token_id, new_base_balance);
 
         // EIP-161: State trie clearing (invariant-preserving alternative)
         if spec_id.is_enabled_in(SPURIOUS_DRAGON) {
@@ -309,16 +376,19 @@ impl JournaledState {
 
         // Sub balance from caller
         let caller_account = self.state.accounts.get_mut(&caller).unwrap();
-        // Balance is already checked in `create_inner`, so it is safe to just subtract.
-        caller_account.info.decrease_base_balance(balance);
-
-        // add journal entry of transferred balance
-        last_journal.push(JournalEntry::BalanceTransfer {
-            from: caller,
-            to: address,
-            token_id: BASE_TOKEN_ID,
-            amount: balance,
-        });
+        caller_account
+            .info
+            .set_balance(base_token_id, new_caller_base_balance);
+
+        // add journal entry of transferred balance, see `nonzero_token_transfers`
+        if balance != U256::ZERO {
+            last_journal.push(JournalEntry::BalanceTransfer {
+                from: caller,
+                to: address,
+                token_id: base_token_id,
+                amount: balance,
+            });
+        }
 
         Ok(checkpoint)
     }
@@ -328,6 +398,7 @@ impl JournaledState {
     fn journal_revert(
         state: &mut EvmState,
         transient_storage: &mut TransientStorage,
+        warm_token_balances: &mut HashSet<(Address, U256)>,
         journal_entries: Vec<JournalEntry>,
         is_spurious_dragon_enabled: bool,
     ) {
@@ -336,6 +407,9 @@ impl JournaledState {
                 JournalEntry::AccountLoaded { address } => {
                     state.accounts.remove(&address);
                 }
+                JournalEntry::TokenBalanceWarmed { address, token_id } => {
+                    warm_token_balances.remove(&(address, token_id));
+                }
                 JournalEntry::AccountTouched { address } => {
                     if is_spurious_dragon_enabled && address == PRECOMPILE3 {
                         continue;
@@ -347,7 +421,7 @@ impl JournaledState {
                     address,
                     target,
                     was_destroyed,
-                    had_balance,
+                    had_balances,
                 } => {
                     let account = state.accounts.get_mut(&address).unwrap();
                     // set previous state of selfdestructed flag, as there could be multiple
@@ -359,11 +433,19 @@ impl JournaledState {
                         // flag that is not selfdestructed
                         account.unmark_selfdestruct();
                     }
-                    account.info.increase_base_balance(had_balance);
+                    for balance in &had_balances {
+                        account
+                            .info
+                            .wrapping_increase_balance(balance.id, balance.amount);
+                    }
 
                     if address != target {
                         let target = state.accounts.get_mut(&target).unwrap();
-                        target.info.decrease_base_balance(had_balance);
+                        for balance in &had_balances {
+                            target
+                                .info
+                                .wrapping_decrease_balance(balance.id, balance.amount);
+                        }
                     }
                 }
                 JournalEntry::BalanceTransfer {
@@ -374,9 +456,9 @@ impl JournaledState {
                 } => {
                     // we don't need to check overflow and underflow when adding and subtracting the balance.
                     let from = state.accounts.get_mut(&from).unwrap();
-                    from.info.increase_balance(token_id, amount);
+                    from.info.wrapping_increase_balance(token_id, amount);
                     let to = state.accounts.get_mut(&to).unwrap();
-                    to.info.decrease_balance(token_id, amount);
+                    to.info.wrapping_decrease_balance(token_id, amount);
                 }
                 JournalEntry::NonceChange { address } => {
                     state.accounts.get_mut(&address).unwrap().info.nonce -= 1;
@@ -423,11 +505,16 @@ impl JournaledState {
                     burned_amount,
                 } => {
                     let holder_acc = state.accounts.get_mut(&token_holder).unwrap();
-                    holder_acc.info.increase_balance(token_id, burned_amount);
+                    holder_acc
+                        .info
+                        .wrapping_increase_balance(token_id, burned_amount);
                 }
                 JournalEntry::TokenIdsLoaded { token_ids: _ } => {
                     state.token_ids.clear();
                 }
+                JournalEntry::TokenIdAdded { token_id } => {
+                    state.token_ids.retain(|id| *id != token_id);
+                }
                 JournalEntry::TokensMinted {
                     minter: _,
                     recipient,
@@ -435,7 +522,17 @@ impl JournaledState {
                     minted_amount,
                 } => {
                     let minter_acc = state.accounts.get_mut(&recipient).unwrap();
-                    minter_acc.info.decrease_balance(token_id, minted_amount);
+                    minter_acc
+                        .info
+                        .wrapping_decrease_balance(token_id, minted_amount);
+                }
+                JournalEntry::TipPaid {
+                    beneficiary,
+                    token_id,
+                    amount,
+                } => {
+                    let beneficiary = state.accounts.get_mut(&beneficiary).unwrap();
+                    beneficiary.info.wrapping_decrease_balance(token_id, amount);
                 }
             }
         }
@@ -447,12 +544,43 @@ impl JournaledState {
         let checkpoint = JournalCheckpoint {
             log_i: self.logs.len(),
             journal_i: self.journal.len(),
+            previous_frames_entries: self.previous_frames_entries,
         };
+        self.previous_frames_entries = self.entries();
         self.depth += 1;
         self.journal.push(Default::default());
         checkpoint
     }
 
+    /// Returns the number of entries in the journal.
+    #[inline]
+    pub fn entries(&self) -> usize {
+        self.previous_frames_entries + self.journal.last().map_or(0, Vec::len)
+    }
+
+    /// Returns the number of entries added since the checkpoint was made, including the entries
+    /// of the checkpoints committed since.
+    #[inline]
+    pub fn entries_since(&self, checkpoint: JournalCheckpoint) -> usize {
+        // the frame that was the last one when the checkpoint was made is left as it was.
+        let checkpoint_entries =
+            checkpoint.previous_frames_entries + self.journal[checkpoint.journal_i - 1].len();
+        self.entries() - checkpoint_entries
+    }
+
+    /// Summarizes the journal, to see what the frames did when debugging a revert.
+    pub fn journal_summary(&self) -> JournalSummary {
+        let mut summary = JournalSummary {
+            entries_per_frame: self.journal.iter().map(Vec::len).collect(),
+            ..Default::default()
+        };
+        for entry in self.journal.iter().flatten() {
+            *summary.entries_per_kind.entry(entry.kind()).or_default() += 1;
+            summary.token_ids.extend(entry.token_ids());
+        }
+        summary
+    }
+
     /// Commit the checkpoint.
     #[inline]
     pub fn checkpoint_commit(&mut self) {
@@ -465,6 +593,7 @@ impl JournaledState {
         let is_spurious_dragon_enabled = SpecId::enabled(self.spec, SPURIOUS_DRAGON);
         let state = &mut self.state;
         let transient_storage = &mut self.transient_storage;
+        let warm_token_balances = &mut self.warm_token_balances;
         self.depth -= 1;
         // iterate over last N journals sets and revert our global state
         let leng = self.journal.len();
@@ -476,6 +605,7 @@ impl JournaledState {
                 Self::journal_revert(
                     state,
                     transient_storage,
+                    warm_token_balances,
                     mem::take(cs),
                     is_spurious_dragon_enabled,
                 )
@@ -483,14 +613,20 @@ impl JournaledState {
 
         self.logs.truncate(checkpoint.log_i);
         self.journal.truncate(checkpoint.journal_i);
+        self.previous_frames_entries = checkpoint.previous_frames_entries;
     }
 
     /// Performances selfdestruct action.
-    /// Transfers balance from address to target. Check if target exist/is_cold
+    /// Transfers the balances of every token from address to target. Check if target exist/is_cold
+    ///
+    /// Returns the result and the balances moved to the target, which are none if the account is
+    /// its own target. If a balance of the target would overflow, nothing is changed and
+    /// [TransferFailure::OverflowPayment] is returned instead, halting the frame.
     ///
-    /// Note: balance will be lost if address and target are the same BUT when
+    /// Note: the balances will be lost if address and target are the same BUT when
     /// current spec enables Cancun, this happens only when the account associated to address
-    /// is created in the same tx
+    /// is created in the same tx. Otherwise (EIP-6780) the account is not destroyed and its
+    /// balances are only swept to the target.
     ///
     /// references:
     ///  * <https://github.com/ethereum/go-ethereum/blob/141cd425310b503c5678e674a8c3872cf46b7086/core/vm/instructions.go#L832-L833>
@@ -502,66 +638,149 @@ impl JournaledState {
         address: Address,
         target: Address,
         db: &mut DB,
-    ) -> Result<SelfDestructResult, EVMError<DB::Error>> {
+    ) -> Result<SelfDestructOutcome, EVMError<DB::Error>> {
         let load_result = self.load_account_exist(target, db)?;
 
-        if address != target {
-            // Both accounts are loaded before this point, `address` as we execute its contract.
-            // and `target` at the beginning of the function.
-            let acc_balance = self
-                .state
-                .accounts
-                .get_mut(&address)
-                .unwrap()
-                .info
-                .get_base_balance();
+        // `address` is loaded before this point as we execute its contract.
+        let acc = self.state.accounts.get_mut(&address).unwrap();
+        let mut balances: Vec<TokenTransfer> = acc
+            .info
+            .balances
+            .iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|(id, amount)| TokenTransfer {
+                id: *id,
+                amount: *amount,
+            })
+            .collect();
+        balances.sort_unstable_by_key(|balance| balance.id);
+        let previously_destroyed = acc.is_selfdestructed();
+        let is_cancun_enabled = SpecId::enabled(self.spec, CANCUN);
+        // EIP-6780 (Cancun hard-fork): selfdestruct only if contract is created in the same tx
+        let is_destroyed = acc.is_created() || !is_cancun_enabled;
+
+        // the balances of the target are checked before any of them is moved
+        let target_balances = if address != target {
+            let target_account = &self.state.accounts[&target];
+            let Some(target_balances) = balances
+                .iter()
+                .map(|balance| {
+                    target_account
+                        .info
+                        .get_balance(balance.id)
+                        .checked_add(balance.amount)
+                })
+                .collect::<Option<Vec<U256>>>()
+            else {
+                return Ok(Err(TransferFailure::OverflowPayment));
+            };
+            target_balances
+        } else {
+            Vec::new()
+        };
+
+        let acc = self.state.accounts.get_mut(&address).unwrap();
+        if is_destroyed || address != target {
+            for balance in &balances {
+                acc.info.set_balance(balance.id, U256::ZERO);
+            }
+        }
 
+        if address != target {
             let target_account = self.state.accounts.get_mut(&target).unwrap();
             Self::touch_account(self.journal.last_mut().unwrap(), &target, target_account);
-            target_account.info.increase_base_balance(acc_balance);
+            for (balance, target_balance) in balances.iter().zip(target_balances) {
+                target_account.info.set_balance(balance.id, target_balance);
+            }
         }
 
-        let acc = self.state.accounts.get_mut(&address).unwrap();
-        let balance = acc.info.get_base_balance();
-        let previously_destroyed = acc.is_selfdestructed();
-        let is_cancun_enabled = SpecId::enabled(self.spec, CANCUN);
-
-        // EIP-6780 (Cancun hard-fork): selfdestruct only if contract is created in the same tx
-        let journal_entry = if acc.is_created() || !is_cancun_enabled {
-            acc.mark_selfdestruct();
-            acc.info.set_base_balance(U256::ZERO);
-            Some(JournalEntry::AccountDestroyed {
+        let had_value = !balances.is_empty();
+        let transferred = if address != target {
+            balances.clone()
+        } else {
+            Vec::new()
+        };
+        let journal = self.journal.last_mut().unwrap();
+        if is_destroyed {
+            self.state
+                .accounts
+                .get_mut(&address)
+                .unwrap()
+                .mark_selfdestruct();
+            journal.push(JournalEntry::AccountDestroyed {
                 address,
                 target,
                 was_destroyed: previously_destroyed,
-                had_balance: balance,
-            })
+                had_balances: balances,
+            });
         } else if address != target {
-            acc.info.set_base_balance(U256::ZERO);
-            Some(JournalEntry::BalanceTransfer {
-                from: address,
-                to: target,
-                token_id: BASE_TOKEN_ID,
-                amount: balance,
-            })
-        } else {
-            // State is not changed:
-            // * if we are after Cancun upgrade and
-            // * Selfdestruct account that is created in the same transaction and
-            // * Specify the target is same as selfdestructed account. The balance stays unchanged.
-            None
-        };
-
-        if let Some(entry) = journal_entry {
-            self.journal.last_mut().unwrap().push(entry);
-        };
+            journal.extend(
+                balances
+                    .into_iter()
+                    .map(|balance| JournalEntry::BalanceTransfer {
+                        from: address,
+                        to: target,
+                        token_id: balance.id,
+                        amount: balance.amount,
+                    }),
+            );
+        }
+        // Otherwise the state is not changed:
+        // * if we are after Cancun upgrade and
+        // * Selfdestruct account that is not created in the same transaction and
+        // * Specify the target is same as selfdestructed account. The balances stay unchanged.
 
-        Ok(SelfDestructResult {
-            had_value: balance != U256::ZERO,
+        let result = SelfDestructResult {
+            had_value,
             is_cold: load_result.is_cold,
             target_exists: !load_result.is_empty,
             previously_destroyed,
-        })
+        };
+        Ok(Ok((result, transferred)))
+    }
+
+    /// Warms the accounts and their storage slots, e.g. the access list of the transaction
+    /// (EIP-2930), so that the first access to them is warm.
+    ///
+    /// The accounts and the slots are loaded with [Self::initial_account_load], which is not
+    /// tracked inside the journal.
+    #[inline]
+    pub fn warm_accounts<'a, DB: Database>(
+        &mut self,
+        accounts: impl IntoIterator<Item = (Address, &'a [U256])>,
+        db: &mut DB,
+    ) -> Result<(), EVMError<DB::Error>> {
+        for (address, slots) in accounts {
+            self.initial_account_load(address, slots, db)?;
+        }
+        Ok(())
+    }
+
+    /// Warms the addresses without loading their accounts, e.g. the precompiles, whose accounts
+    /// are loaded only when they are called.
+    #[inline]
+    pub fn warm_addresses(&mut self, addresses: impl IntoIterator<Item = Address>) {
+        self.warm_preloaded_addresses.extend(addresses);
+    }
+
+    /// Warms the balances of the Native Tokens, e.g. the balances of the tokens transferred by
+    /// the transaction, without loading their accounts. Like [Self::initial_account_load], the
+    /// warming is not tracked inside the journal.
+    #[inline]
+    pub fn warm_token_balances(&mut self, balances: impl IntoIterator<Item = (Address, U256)>) {
+        self.warm_token_balances.extend(balances);
+    }
+
+    /// Warms the Native Token registry, which the token ids are looked up in.
+    ///
+    /// Like [Self::initial_account_load], the loading of the token ids is not tracked inside the
+    /// journal, so minting and burning during the execution don't load them again.
+    #[inline]
+    pub fn warm_token_ids<DB: Database>(&mut self, db: &mut DB) -> Result<(), EVMError<DB::Error>> {
+        if self.state.token_ids.is_empty() {
+            self.state.token_ids = db.get_token_ids().map_err(EVMError::Database)?;
+        }
+        Ok(())
     }
 
     /// Initial load of account. This load will not be tracked inside journal
@@ -592,6 +811,34 @@ impl JournaledState {
         Ok(account)
     }
 
+    /// Loads the balance of the Native Token of the account, returning it with whether it was
+    /// cold accessed.
+    ///
+    /// The balance of the base token is cold when the account is, while the balances of the
+    /// other tokens are warmed on their first access, see [Self::warm_token_balances].
+    #[inline]
+    pub fn token_balance<DB: Database>(
+        &mut self,
+        address: Address,
+        token_id: U256,
+        db: &mut DB,
+    ) -> Result<(U256, bool), EVMError<DB::Error>> {
+        let (account, account_is_cold) = self.load_account(address, db)?;
+        let balance = account.info.get_balance(token_id);
+        if token_id == self.base_token_id {
+            return Ok((balance, account_is_cold));
+        }
+
+        let is_cold = self.warm_token_balances.insert((address, token_id));
+        if is_cold {
+            self.journal
+                .last_mut()
+                .unwrap()
+                .push(JournalEntry::TokenBalanceWarmed { address, token_id });
+        }
+        Ok((balance, is_cold))
+    }
+
     /// load account into memory. return if it is cold or warm accessed
     #[inline]
     pub fn load_account<DB: Database>(
@@ -840,7 +1087,7 @@ impl JournaledState {
             return false;
         }
 
-        let token_id = token_id_address(burner, sub_id);
+        let token_id = self.minted_token_id(burner, sub_id);
 
         let result = db.is_token_id_valid(token_id);
         if result.is_err() || result.is_ok_and(|r| !r) {
@@ -867,6 +1114,27 @@ impl JournaledState {
         true
     }
 
+    /// Burns the tokens from the balances of the holder, which has to be loaded and to hold them.
+    ///
+    /// Unlike [JournaledState::burn], any token can be burned, as the tokens are burned on behalf
+    /// of the holder.
+    #[inline]
+    pub fn burn_balances(&mut self, token_holder: Address, tokens: &[TokenTransfer]) {
+        let account = self.state.accounts.get_mut(&token_holder).unwrap();
+        for token in nonzero_token_transfers(tokens) {
+            let balance = account.info.get_balance(token.id);
+            account.info.set_balance(token.id, balance - token.amount);
+            self.journal
+                .last_mut()
+                .unwrap()
+                .push(JournalEntry::TokensBurned {
+                    token_holder,
+                    token_id: token.id,
+                    burned_amount: token.amount,
+                });
+        }
+    }
+
     /// Load the token ids into memory.
     ///
     /// Return whether the loading was cold.
@@ -892,6 +1160,22 @@ impl JournaledState {
         }
     }
 
+    /// Returns the ID of the token minted by the contract with the sub ID, derived as
+    /// [TokenIdDerivation::for_spec] unless the token is registered with its legacy ID.
+    ///
+    /// The registered token IDs have to be loaded.
+    #[inline]
+    pub fn minted_token_id(&self, minter: Address, sub_id: U256) -> U256 {
+        let legacy_id = TokenIdDerivation::Legacy.token_id(minter, sub_id);
+        match TokenIdDerivation::for_spec(self.spec) {
+            // the tokens registered before the domain separation keep their IDs
+            TokenIdDerivation::V1 if !self.state.token_ids.contains(&legacy_id) => {
+                TokenIdDerivation::V1.token_id(minter, sub_id)
+            }
+            _ => legacy_id,
+        }
+    }
+
     pub fn mint<DB: Database>(
         &mut self,
         minter: Address,
@@ -908,7 +1192,22 @@ impl JournaledState {
             return false;
         }
 
-        let token_id = token_id_address(minter, sub_id);
+        let token_id = self.minted_token_id(minter, sub_id);
+        self.mint_token(minter, recipient, token_id, amount)
+    }
+
+    /// Mints the amount of the token to the recipient, registering the token if it's new.
+    ///
+    /// The recipient and the registered token IDs have to be loaded. Returns `false` if the
+    /// balance of the recipient would overflow.
+    #[inline]
+    pub fn mint_token(
+        &mut self,
+        minter: Address,
+        recipient: Address,
+        token_id: U256,
+        amount: U256,
+    ) -> bool {
         let account = self.state.accounts.get_mut(&recipient).unwrap();
         let balance = account.info.get_balance(token_id);
         if let Some(new_balance) = balance.checked_add(amount) {
@@ -920,6 +1219,10 @@ impl JournaledState {
         // add the id of the minted token to the collection, if it's not already there
         if !self.state.token_ids.contains(&token_id) {
             self.state.token_ids.push(token_id);
+            self.journal
+                .last_mut()
+                .unwrap()
+                .push(JournalEntry::TokenIdAdded { token_id });
         }
 
         // add journal entry of the minted tokens
@@ -945,20 +1248,25 @@ pub enum JournalEntry {
     /// Action: We will add Account to state.
     /// Revert: we will remove account from state.
     AccountLoaded { address: Address },
-    /// Mark account to be destroyed and journal balance to be reverted
-    /// Action: Mark account and transfer the balance
-    /// Revert: Unmark the account and transfer balance back
+    /// Mark account to be destroyed and journal balances to be reverted
+    /// Action: Mark account and transfer the balances of every token
+    /// Revert: Unmark the account and transfer balances back
     AccountDestroyed {
         address: Address,
         target: Address,
         was_destroyed: bool, // if account had already been destroyed before this journal entry
-        had_balance: U256,
+        had_balances: Vec<TokenTransfer>, // the nonzero balances, ordered by token ID
     },
     /// Loading account does not mean that account will need to be added to MerkleTree (touched).
     /// Only when account is called (to execute contract or transfer balance) only then account is made touched.
     /// Action: Mark account touched
     /// Revert: Unmark account touched
     AccountTouched { address: Address },
+    /// The balance of a Native Token other than the base token was accessed for the first time,
+    /// see [JournaledState::warm_token_balances].
+    /// Action: Mark the balance warm
+    /// Revert: Mark the balance cold
+    TokenBalanceWarmed { address: Address, token_id: U256 },
     /// Transfer balance between two accounts
     /// Action: Transfer balance
     /// Revert: Transfer balance back
@@ -1011,6 +1319,10 @@ pub enum JournalEntry {
     /// Action: Add the loaded token ids to the state
     /// Revert: Remove the loaded token ids from the state
     TokenIdsLoaded { token_ids: Vec<U256> },
+    /// Token id added
+    /// Action: Add the id of a newly minted token to the state
+    /// Revert: Remove the token id from the state
+    TokenIdAdded { token_id: U256 },
     /// Tokens minted
     /// Action: Mint tokens
     /// Revert: Burn the minted tokens
@@ -1020,6 +1332,141 @@ pub enum JournalEntry {
         token_id: U256,
         minted_amount: U256,
     },
+    /// Tip paid to the beneficiary, deducted from the caller before execution
+    /// Action: Credit the tip to the beneficiary
+    /// Revert: Debit the tip from the beneficiary
+    TipPaid {
+        beneficiary: Address,
+        token_id: U256,
+        amount: U256,
+    },
+}
+
+impl JournalEntry {
+    /// Returns the name of the variant of the entry, e.g. `"BalanceTransfer"`.
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::AccountLoaded { .. } => "AccountLoaded",
+            Self::AccountDestroyed { .. } => "AccountDestroyed",
+            Self::AccountTouched { .. } => "AccountTouched",
+            Self::TokenBalanceWarmed { .. } => "TokenBalanceWarmed",
+            Self::BalanceTransfer { .. } => "BalanceTransfer",
+            Self::NonceChange { .. } => "NonceChange",
+            Self::AccountCreated { .. } => "AccountCreated",
+            Self::StorageChange { .. } => "StorageChange",
+            Self::TransientStorageChange { .. } => "TransientStorageChange",
+            Self::CodeChange { .. } => "CodeChange",
+            Self::TokensBurned { .. } => "TokensBurned",
+            Self::TokenIdsLoaded { .. } => "TokenIdsLoaded",
+            Self::TokenIdAdded { .. } => "TokenIdAdded",
+            Self::TokensMinted { .. } => "TokensMinted",
+            Self::TipPaid { .. } => "TipPaid",
+        }
+    }
+
+    /// Returns the IDs of the tokens whose balances or registration the entry changes or warms.
+    ///
+    /// Loading the token IDs doesn't touch them.
+    pub fn token_ids(&self) -> impl Iterator<Item = U256> + '_ {
+        let (token_id, balances) = match self {
+            Self::TokenBalanceWarmed { token_id, .. }
+            | Self::BalanceTransfer { token_id, .. }
+            | Self::TokensBurned { token_id, .. }
+            | Self::TokenIdAdded { token_id }
+            | Self::TokensMinted { token_id, .. }
+            | Self::TipPaid { token_id, .. } => (Some(*token_id), &[][..]),
+            Self::AccountDestroyed { had_balances, .. } => (None, &had_balances[..]),
+            _ => (None, &[][..]),
+        };
+        token_id
+            .into_iter()
+            .chain(balances.iter().map(|balance| balance.id))
+    }
+
+    /// Returns the addresses of the accounts that the entry loads or changes.
+    ///
+    /// The minter of a mint isn't changed, e.g. the emissions of a block are minted by an address
+    /// without an account.
+    pub fn addresses(&self) -> impl Iterator<Item = Address> {
+        let (address, other) = match *self {
+            Self::AccountLoaded { address }
+            | Self::AccountTouched { address }
+            | Self::TokenBalanceWarmed { address, .. }
+            | Self::NonceChange { address }
+            | Self::AccountCreated { address }
+            | Self::StorageChange { address, .. }
+            | Self::TransientStorageChange { address, .. }
+            | Self::CodeChange { address } => (Some(address), None),
+            Self::AccountDestroyed {
+                address, target, ..
+            } => (Some(address), Some(target)),
+            Self::BalanceTransfer { from, to, .. } => (Some(from), Some(to)),
+            Self::TokensBurned { token_holder, .. } => (Some(token_holder), None),
+            Self::TokensMinted { recipient, .. } => (Some(recipient), None),
+            Self::TipPaid { beneficiary, .. } => (Some(beneficiary), None),
+            Self::TokenIdsLoaded { .. } | Self::TokenIdAdded { .. } => (None, None),
+        };
+        address.into_iter().chain(other)
+    }
+}
+
+/// A summary of the journal, see [JournaledState::journal_summary].
+///
+/// It is printed as a short report, e.g.:
+///
+/// ```text
+/// 5 journal entries in 2 frames
+///   frame 0: 3 entries
+///   frame 1: 2 entries
+/// entries:
+///   AccountLoaded: 3
+///   BalanceTransfer: 2
+/// tokens: 0x0, 0x1
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JournalSummary {
+    /// The number of entries of every frame, from the outermost one.
+    pub entries_per_frame: Vec<usize>,
+    /// The number of entries of every [kind](JournalEntry::kind).
+    pub entries_per_kind: BTreeMap<&'static str, usize>,
+    /// The IDs of the tokens touched by the entries, see [JournalEntry::token_ids].
+    pub token_ids: BTreeSet<U256>,
+}
+
+impl JournalSummary {
+    /// Returns the number of entries of the journal.
+    pub fn entries(&self) -> usize {
+        self.entries_per_frame.iter().sum()
+    }
+}
+
+impl fmt::Display for JournalSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} journal entries in {} frames",
+            self.entries(),
+            self.entries_per_frame.len()
+        )?;
+        for (frame, entries) in self.entries_per_frame.iter().enumerate() {
+            writeln!(f, "  frame {frame}: {entries} entries")?;
+        }
+        if !self.entries_per_kind.is_empty() {
+            writeln!(f, "entries:")?;
+            for (kind, entries) in &self.entries_per_kind {
+                writeln!(f, "  {kind}: {entries}")?;
+            }
+        }
+        if !self.token_ids.is_empty() {
+            write!(f, "tokens:")?;
+            for (i, token_id) in self.token_ids.iter().enumerate() {
+                let separator = if i == 0 { " " } else { ", " };
+                write!(f, "{separator}{token_id:#x}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
 }
 
 /// SubRoutine checkpoint that will help us to go back from this
@@ -1028,4 +1475,585 @@ pub enum JournalEntry {
 pub struct JournalCheckpoint {
     log_i: usize,
     journal_i: usize,
+    previous_frames_entries: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CacheDB, EmptyDB},
+        primitives::{init_balances, token_id_address, AccountInfo, HashMap, B256},
+    };
+    use proptest::prelude::*;
+    use std::string::ToString;
+
+    /// Note: the addresses stay clear of the precompiles, as touching `PRECOMPILE3` is never reverted.
+    const ACCOUNTS: [Address; 3] = [
+        Address::with_last_byte(0x11),
+        Address::with_last_byte(0x12),
+        Address::with_last_byte(0x13),
+    ];
+
+    /// Sub ids whose tokens are valid in the database, and can therefore be burned.
+    const VALID_SUB_IDS: u64 = 2;
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Transfer {
+            from: usize,
+            to: usize,
+            token: usize,
+            amount: u64,
+        },
+        Mint {
+            minter: usize,
+            recipient: usize,
+            sub_id: u64,
+            amount: u64,
+        },
+        Burn {
+            burner: usize,
+            holder: usize,
+            sub_id: u64,
+            amount: u64,
+        },
+        Checkpoint,
+        Commit,
+        Revert,
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        let account = 0..ACCOUNTS.len();
+        let amount = 0..2_000u64;
+        prop_oneof![
+            3 => (account.clone(), account.clone(), 0..3usize, amount.clone()).prop_map(
+                |(from, to, token, amount)| Op::Transfer {
+                    from,
+                    to,
+                    token,
+                    amount
+                }
+            ),
+            2 => (account.clone(), account.clone(), 0..VALID_SUB_IDS + 1, amount.clone()).prop_map(
+                |(minter, recipient, sub_id, amount)| Op::Mint {
+                    minter,
+                    recipient,
+                    sub_id,
+                    amount
+                }
+            ),
+            2 => (account.clone(), account, 0..VALID_SUB_IDS + 1, amount).prop_map(
+                |(burner, holder, sub_id, amount)| Op::Burn {
+                    burner,
+                    holder,
+                    sub_id,
+                    amount
+                }
+            ),
+            2 => Just(Op::Checkpoint),
+            1 => Just(Op::Commit),
+            2 => Just(Op::Revert),
+        ]
+    }
+
+    /// Returns the ids of every token that can appear in the state.
+    fn all_token_ids() -> Vec<U256> {
+        let mut token_ids = vec![BASE_TOKEN_ID, U256::from(1), U256::from(2)];
+        for minter in ACCOUNTS {
+            for sub_id in 0..=VALID_SUB_IDS {
+                token_ids.push(token_id_address(minter, U256::from(sub_id)));
+            }
+        }
+        token_ids
+    }
+
+    /// Creates a journaled state with every account and the token ids already loaded.
+    fn journaled_state() -> (JournaledState, CacheDB<EmptyDB>) {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.token_ids = vec![BASE_TOKEN_ID, U256::from(1), U256::from(2)];
+        for minter in ACCOUNTS {
+            for sub_id in 0..VALID_SUB_IDS {
+                db.token_ids
+                    .push(token_id_address(minter, U256::from(sub_id)));
+            }
+        }
+        for address in ACCOUNTS {
+            let mut balances = init_balances(U256::from(1_000));
+            balances.insert(U256::from(1), U256::from(1_000));
+            balances.insert(U256::from(2), U256::from(1_000));
+            db.insert_account_info(
+                address,
+                AccountInfo {
+                    balances,
+                    ..Default::default()
+                },
+            );
+        }
+
+        let mut journaled_state = JournaledState::new(SpecId::CANCUN, HashSet::new());
+        journaled_state.load_native_token_ids(&mut db).unwrap();
+        for address in ACCOUNTS {
+            journaled_state.load_account(address, &mut db).unwrap();
+        }
+        (journaled_state, db)
+    }
+
+    fn total_balances(state: &EvmState) -> HashMap<U256, U256> {
+        all_token_ids()
+            .into_iter()
+            .map(|token_id| (token_id, state.total_balance(token_id)))
+            .collect()
+    }
+
+    #[test]
+    fn token_balances_are_warm_after_first_access() {
+        let (mut journaled_state, mut db) = journaled_state();
+        let [holder, other, ..] = ACCOUNTS;
+        let token_id = U256::from(1);
+
+        // the base token balance is warm with the loaded account
+        assert_eq!(
+            journaled_state.token_balance(holder, BASE_TOKEN_ID, &mut db),
+            Ok((U256::from(1_000), false))
+        );
+        assert_eq!(
+            journaled_state.token_balance(holder, token_id, &mut db),
+            Ok((U256::from(1_000), true))
+        );
+        assert_eq!(
+            journaled_state.token_balance(holder, token_id, &mut db),
+            Ok((U256::from(1_000), false))
+        );
+        // per account and token
+        assert!(
+            journaled_state
+                .token_balance(other, token_id, &mut db)
+                .unwrap()
+                .1
+        );
+        assert!(
+            journaled_state
+                .token_balance(holder, U256::from(2), &mut db)
+                .unwrap()
+                .1
+        );
+
+        // the balances warmed by a reverted checkpoint are cold again
+        let checkpoint = journaled_state.checkpoint();
+        let cold = (ACCOUNTS[2], token_id);
+        assert!(
+            journaled_state
+                .token_balance(cold.0, cold.1, &mut db)
+                .unwrap()
+                .1
+        );
+        journaled_state.checkpoint_revert(checkpoint);
+        assert!(
+            journaled_state
+                .token_balance(cold.0, cold.1, &mut db)
+                .unwrap()
+                .1
+        );
+        assert!(
+            !journaled_state
+                .token_balance(holder, token_id, &mut db)
+                .unwrap()
+                .1
+        );
+
+        journaled_state.finalize();
+        assert!(journaled_state.warm_token_balances.is_empty());
+    }
+
+    #[test]
+    fn zero_amount_transfers_are_skipped() {
+        let (mut journaled_state, mut db) = journaled_state();
+        let [from, to, ..] = ACCOUNTS;
+        let transfer = |id: u64, amount: u64| TokenTransfer {
+            id: U256::from(id),
+            amount: U256::from(amount),
+        };
+
+        journaled_state.journal.last_mut().unwrap().clear();
+
+        // only zero amounts: the recipient is touched
+        let result =
+            journaled_state.transfer(&from, &to, &[transfer(1, 0), transfer(2, 0)], &mut db);
+        assert_eq!(result, Ok(None));
+        assert_eq!(
+            journaled_state.journal.last().unwrap(),
+            &[JournalEntry::AccountTouched { address: to }]
+        );
+
+        // the zero amount among nonzero ones isn't journaled
+        journaled_state.journal.last_mut().unwrap().clear();
+        let result =
+            journaled_state.transfer(&from, &to, &[transfer(1, 0), transfer(2, 5)], &mut db);
+        assert_eq!(result, Ok(None));
+        assert_eq!(
+            journaled_state.journal.last().unwrap(),
+            &[
+                JournalEntry::AccountTouched { address: from },
+                JournalEntry::BalanceTransfer {
+                    from,
+                    to,
+                    token_id: U256::from(2),
+                    amount: U256::from(5),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn registered_tokens_keep_their_legacy_ids() {
+        let (mut journaled_state, mut db) = journaled_state();
+        journaled_state.set_spec_id(SpecId::PRAGUE);
+        let [minter, recipient, ..] = ACCOUNTS;
+        let registered = U256::ZERO;
+        let unregistered = U256::from(VALID_SUB_IDS);
+
+        assert_eq!(
+            journaled_state.minted_token_id(minter, registered),
+            token_id_address(minter, registered)
+        );
+        let token_id = journaled_state.minted_token_id(minter, unregistered);
+        assert_eq!(
+            TokenIdDerivation::verify(token_id, minter, unregistered),
+            Some(TokenIdDerivation::V1)
+        );
+
+        assert!(journaled_state.mint(minter, recipient, unregistered, U256::from(7), &mut db));
+        assert_eq!(
+            journaled_state
+                .account(recipient)
+                .info
+                .get_balance(token_id),
+            U256::from(7)
+        );
+        assert!(journaled_state.state.token_ids.contains(&token_id));
+
+        // the legacy derivation is kept before Prague
+        journaled_state.set_spec_id(SpecId::CANCUN);
+        assert_eq!(
+            journaled_state.minted_token_id(minter, unregistered),
+            token_id_address(minter, unregistered)
+        );
+    }
+
+    #[test]
+    fn summarizes_the_journal() {
+        let (mut journaled_state, mut db) = journaled_state();
+        let [from, to, minter] = ACCOUNTS;
+        let loaded = journaled_state.entries();
+        journaled_state.checkpoint();
+        let transfers = [TokenTransfer {
+            id: U256::from(1),
+            amount: U256::from(5),
+        }];
+        assert_eq!(
+            journaled_state.transfer(&from, &to, &transfers, &mut db),
+            Ok(None)
+        );
+        journaled_state.checkpoint();
+        assert!(journaled_state.mint(minter, to, U256::ZERO, U256::from(7), &mut db));
+
+        let summary = journaled_state.journal_summary();
+        assert_eq!(summary.entries(), journaled_state.entries());
+        assert_eq!(summary.entries_per_frame[0], loaded);
+        assert_eq!(summary.entries_per_frame.len(), 3);
+        assert_eq!(summary.entries_per_kind["BalanceTransfer"], 1);
+        assert_eq!(summary.entries_per_kind["TokensMinted"], 1);
+        assert_eq!(
+            summary.token_ids,
+            BTreeSet::from([U256::from(1), token_id_address(minter, U256::ZERO)])
+        );
+
+        let summary = JournalSummary {
+            entries_per_frame: vec![3, 2],
+            entries_per_kind: BTreeMap::from([("AccountLoaded", 3), ("BalanceTransfer", 2)]),
+            token_ids: BTreeSet::from([U256::ZERO, U256::from(1)]),
+        };
+        assert_eq!(
+            summary.to_string(),
+            "5 journal entries in 2 frames\n  frame 0: 3 entries\n  frame 1: 2 entries\n\
+             entries:\n  AccountLoaded: 3\n  BalanceTransfer: 2\ntokens: 0x0, 0x1\n"
+        );
+    }
+
+    #[test]
+    fn contracts_are_not_created_in_the_reserved_namespace() {
+        let (mut journaled_state, mut db) = journaled_state();
+        let [caller, ..] = ACCOUNTS;
+        let reserved = sablier::u64_to_prefixed_address(u64::MAX);
+        let created = sablier::create_address(caller, 0);
+        for address in [reserved, created] {
+            journaled_state.load_account(address, &mut db).unwrap();
+        }
+
+        assert_eq!(
+            journaled_state.create_account_checkpoint(
+                caller,
+                reserved,
+                U256::from(1),
+                SpecId::CANCUN
+            ),
+            Err(InstructionResult::CreateCollision)
+        );
+        assert!(journaled_state
+            .create_account_checkpoint(caller, created, U256::from(1), SpecId::CANCUN)
+            .is_ok());
+        assert_eq!(
+            journaled_state.account(created).info.get_base_balance(),
+            U256::from(1)
+        );
+    }
+
+    #[test]
+    fn selfdestruct_sweeps_every_token() {
+        let (mut journaled_state, mut db) = journaled_state();
+        let [address, target, ..] = ACCOUNTS;
+        let balances: Vec<TokenTransfer> = [BASE_TOKEN_ID, U256::from(1), U256::from(2)]
+            .into_iter()
+            .map(|id| TokenTransfer {
+                id,
+                amount: U256::from(1_000),
+            })
+            .collect();
+        let balance = |journaled_state: &JournaledState, address: Address, id: U256| {
+            journaled_state.account(address).info.get_balance(id)
+        };
+        let initial_state = journaled_state.state.clone();
+
+        // EIP-6780: an account not created in the transaction only has its balances swept
+        let checkpoint = journaled_state.checkpoint();
+        let (result, transferred) = journaled_state
+            .selfdestruct(address, target, &mut db)
+            .unwrap()
+            .unwrap();
+        assert!(result.had_value);
+        assert_eq!(transferred, balances);
+        assert!(!journaled_state.account(address).is_selfdestructed());
+        for transfer in &balances {
+            assert_eq!(balance(&journaled_state, address, transfer.id), U256::ZERO);
+            assert_eq!(
+                balance(&journaled_state, target, transfer.id),
+                U256::from(2_000)
+            );
+        }
+        let transfers: Vec<JournalEntry> = balances
+            .iter()
+            .map(|transfer| JournalEntry::BalanceTransfer {
+                from: address,
+                to: target,
+                token_id: transfer.id,
+                amount: transfer.amount,
+            })
+            .collect();
+        assert_eq!(journaled_state.journal.last().unwrap()[1..], transfers);
+        journaled_state.checkpoint_revert(checkpoint);
+        assert_eq!(journaled_state.state, initial_state);
+
+        // and nothing happens if it is its own target
+        let checkpoint = journaled_state.checkpoint();
+        let (_, transferred) = journaled_state
+            .selfdestruct(address, address, &mut db)
+            .unwrap()
+            .unwrap();
+        assert!(transferred.is_empty());
+        assert!(journaled_state.journal.last().unwrap().is_empty());
+        journaled_state.checkpoint_revert(checkpoint);
+
+        // an account created in the transaction is destroyed, burning its balances when it is
+        // its own target
+        journaled_state
+            .state
+            .accounts
+            .get_mut(&address)
+            .unwrap()
+            .mark_created();
+        let initial_state = journaled_state.state.clone();
+        let checkpoint = journaled_state.checkpoint();
+        journaled_state
+            .selfdestruct(address, address, &mut db)
+            .unwrap()
+            .unwrap();
+        assert!(journaled_state.account(address).is_selfdestructed());
+        for transfer in &balances {
+            assert_eq!(balance(&journaled_state, address, transfer.id), U256::ZERO);
+        }
+        assert_eq!(
+            journaled_state.journal.last().unwrap(),
+            &[JournalEntry::AccountDestroyed {
+                address,
+                target: address,
+                was_destroyed: false,
+                had_balances: balances.clone(),
+            }]
+        );
+        journaled_state.checkpoint_revert(checkpoint);
+        assert_eq!(journaled_state.state, initial_state);
+
+        // or sweeping them to the target
+        let checkpoint = journaled_state.checkpoint();
+        journaled_state
+            .selfdestruct(address, target, &mut db)
+            .unwrap()
+            .unwrap();
+        assert!(journaled_state.account(address).is_selfdestructed());
+        for transfer in &balances {
+            assert_eq!(balance(&journaled_state, address, transfer.id), U256::ZERO);
+            assert_eq!(
+                balance(&journaled_state, target, transfer.id),
+                U256::from(2_000)
+            );
+        }
+        journaled_state.checkpoint_revert(checkpoint);
+        assert_eq!(journaled_state.state, initial_state);
+
+        // a balance of the target that would overflow leaves every balance unchanged
+        journaled_state
+            .state
+            .accounts
+            .get_mut(&target)
+            .unwrap()
+            .info
+            .set_balance(U256::from(2), U256::MAX);
+        let initial_state = journaled_state.state.clone();
+        assert_eq!(
+            journaled_state
+                .selfdestruct(address, target, &mut db)
+                .unwrap(),
+            Err(TransferFailure::OverflowPayment)
+        );
+        assert_eq!(journaled_state.state, initial_state);
+    }
+
+    proptest! {
+        #[test]
+        fn checkpoint_revert_restores_state(ops in proptest::collection::vec(op(), 1..64)) {
+            let (mut journaled_state, mut db) = journaled_state();
+
+            // The expected token supplies, tracked independently of the state.
+            let mut supplies = total_balances(&journaled_state.state);
+            // For every open checkpoint: the checkpoint, the state fingerprint and the supplies.
+            let mut checkpoints: Vec<(JournalCheckpoint, B256, HashMap<U256, U256>)> = Vec::new();
+
+            for op in ops {
+                match op {
+                    Op::Transfer { from, to, token, amount } => {
+                        // Like a call frame, revert the transfer if it fails half-way.
+                        let checkpoint = journaled_state.checkpoint();
+                        let result = journaled_state
+                            .transfer(
+                                &ACCOUNTS[from],
+                                &ACCOUNTS[to],
+                                &[TokenTransfer {
+                                    id: U256::from(token),
+                                    amount: U256::from(amount),
+                                }],
+                                &mut db,
+                            )
+                            .unwrap();
+                        if result.is_some() {
+                            journaled_state.checkpoint_revert(checkpoint);
+                        } else {
+                            journaled_state.checkpoint_commit();
+                        }
+                    }
+                    Op::Mint { minter, recipient, sub_id, amount } => {
+                        let minted = journaled_state.mint(
+                            ACCOUNTS[minter],
+                            ACCOUNTS[recipient],
+                            U256::from(sub_id),
+                            U256::from(amount),
+                            &mut db,
+                        );
+                        if minted {
+                            let token_id = token_id_address(ACCOUNTS[minter], U256::from(sub_id));
+                            *supplies.get_mut(&token_id).unwrap() += U256::from(amount);
+                        }
+                    }
+                    Op::Burn { burner, holder, sub_id, amount } => {
+                        let burned = journaled_state.burn(
+                            ACCOUNTS[burner],
+                            U256::from(sub_id),
+                            ACCOUNTS[holder],
+                            U256::from(amount),
+                            &mut db,
+                        );
+                        if burned {
+                            let token_id = token_id_address(ACCOUNTS[burner], U256::from(sub_id));
+                            *supplies.get_mut(&token_id).unwrap() -= U256::from(amount);
+                        }
+                    }
+                    Op::Checkpoint => {
+                        let fingerprint = journaled_state.state.fingerprint();
+                        checkpoints.push((journaled_state.checkpoint(), fingerprint, supplies.clone()));
+                    }
+                    Op::Commit => {
+                        if checkpoints.pop().is_some() {
+                            journaled_state.checkpoint_commit();
+                        }
+                    }
+                    Op::Revert => {
+                        if let Some((checkpoint, fingerprint, old_supplies)) = checkpoints.pop() {
+                            journaled_state.checkpoint_revert(checkpoint);
+                            prop_assert_eq!(journaled_state.state.fingerprint(), fingerprint);
+                            supplies = old_supplies;
+                        }
+                    }
+                }
+
+                prop_assert_eq!(&total_balances(&journaled_state.state), &supplies);
+            }
+
+            // Reverting every open checkpoint brings back the state of the outermost one.
+            while let Some((checkpoint, fingerprint, _)) = checkpoints.pop() {
+                journaled_state.checkpoint_revert(checkpoint);
+                prop_assert_eq!(journaled_state.state.fingerprint(), fingerprint);
+            }
+        }
+    }
+
+    #[test]
+    fn counts_the_entries_across_checkpoints() {
+        let (mut journaled_state, mut db) = journaled_state();
+        let [from, to, _] = ACCOUNTS;
+        let transfers = [TokenTransfer {
+            id: U256::from(1),
+            amount: U256::from(1),
+        }];
+        let count = |journaled_state: &JournaledState| {
+            journaled_state.journal.iter().map(Vec::len).sum::<usize>()
+        };
+
+        let outer = journaled_state.checkpoint();
+        journaled_state
+            .transfer(&from, &to, &transfers, &mut db)
+            .unwrap();
+        let inner = journaled_state.checkpoint();
+        journaled_state
+            .transfer(&from, &to, &transfers, &mut db)
+            .unwrap();
+        journaled_state.checkpoint_commit();
+        assert_eq!(journaled_state.entries(), count(&journaled_state));
+        assert_eq!(journaled_state.entries_since(inner), 1);
+
+        let reverted = journaled_state.checkpoint();
+        journaled_state
+            .transfer(&from, &to, &transfers, &mut db)
+            .unwrap();
+        journaled_state.checkpoint_revert(reverted);
+        assert_eq!(journaled_state.entries(), count(&journaled_state));
+        assert_eq!(journaled_state.entries_since(inner), 1);
+        assert_eq!(
+            journaled_state.entries_since(outer),
+            count(&journaled_state) - journaled_state.journal[0].len()
+        );
+
+        journaled_state.checkpoint_revert(outer);
+        assert_eq!(journaled_state.entries(), count(&journaled_state));
+        assert_eq!(journaled_state.entries(), journaled_state.journal[0].len());
+    }
 }
@@ -19,10 +19,15 @@ mod evm;
 mod frame;
 pub mod handler;
 mod inspector;
+#[cfg(feature = "journal-log")]
+mod journal_log;
 mod journaled_state;
 #[cfg(feature = "optimism")]
 pub mod optimism;
+pub mod report;
 pub mod sablier;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
 
 // Export items.
 
@@ -37,15 +42,26 @@ pub use db::{
 };
 pub use db::{Database, DatabaseCommit, DatabaseRef, InMemoryDB};
 pub use evm::{Evm, CALL_STACK_LIMIT};
-pub use frame::{CallFrame, CreateFrame, Frame, FrameData, FrameOrResult, FrameResult};
-pub use handler::Handler;
+pub use frame::{
+    CallFrame, CreateFrame, Frame, FrameData, FrameOrResult, FramePool, FrameResult, FrameStack,
+    ANALYSED_CODE_LIMIT,
+};
+pub use handler::{ChainHandler, Handler};
 pub use inspector::{
     inspector_handle_register, inspector_instruction, inspectors, GetInspector, Inspector,
 };
-pub use journaled_state::{JournalCheckpoint, JournalEntry, JournaledState};
+#[cfg(feature = "journal-log")]
+pub use journal_log::{read_journal_log, replay, InconsistentRecord, JournalLog, JournalLogRecord};
+pub use journaled_state::{
+    JournalCheckpoint, JournalEntry, JournalSummary, JournaledState, SelfDestructOutcome,
+};
+#[cfg(feature = "metrics")]
+pub use telemetry::{register_metrics, MetricsDB};
 // export Optimism types, helpers, and constants
 #[cfg(feature = "optimism")]
-pub use optimism::{L1BlockInfo, BASE_FEE_RECIPIENT, L1_BLOCK_CONTRACT, L1_FEE_RECIPIENT};
+pub use optimism::{
+    L1BlockInfo, OptimismHandler, BASE_FEE_RECIPIENT, L1_BLOCK_CONTRACT, L1_FEE_RECIPIENT,
+};
 
 // Reexport libraries
 
@@ -7,5 +7,6 @@ mod l1block;
 pub use handler_register::{
     deduct_caller, end, last_frame_return, load_accounts, load_precompiles,
     optimism_handle_register, output, reward_beneficiary, validate_env, validate_tx_against_state,
+    OptimismHandler,
 };
 pub use l1block::{L1BlockInfo, BASE_FEE_RECIPIENT, L1_BLOCK_CONTRACT, L1_FEE_RECIPIENT};
@@ -2,8 +2,9 @@
 
 use crate::{
     handler::{
-        mainnet::{self, deduct_caller_inner},
+        mainnet::{self, deduct_caller_inner, deduct_gas_payer_inner},
         register::EvmHandler,
+        ChainHandler,
     },
     interpreter::{return_ok, return_revert, Gas, InstructionResult},
     optimism,
@@ -11,8 +12,8 @@ use crate::{
         db::Database,
         spec_to_generic,
         state::EvmState,
-        Account, EVMError, Env, ExecutionResult, HaltReason, HashMap, InvalidTransaction,
-        ResultAndState, Spec,
+        Account, EVMError, Env, ExecutionResult, HaltReason, HandlerCfg, HashMap,
+        InvalidTransaction, ResultAndState, Spec,
         SpecId::{self, REGOLITH},
         U256,
     },
@@ -23,6 +24,32 @@ use revm_precompile::{secp256r1, PrecompileSpecId, Precompiles};
 use std::string::ToString;
 use std::sync::Arc;
 
+/// The [ChainHandler] of the Optimism chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OptimismHandler;
+
+impl ChainHandler for OptimismHandler {
+    fn handler_cfg(spec_id: SpecId) -> HandlerCfg {
+        HandlerCfg::new_with_optimism(spec_id, true)
+    }
+
+    fn handle_register<EXT, DB: Database>(handler: &mut EvmHandler<'_, EXT, DB>) {
+        optimism_handle_register(handler)
+    }
+}
+
+impl<'a, EXT, DB: Database> EvmHandler<'a, EXT, DB> {
+    /// Handler for optimism
+    pub fn optimism<SPEC: Spec>() -> Self {
+        Self::chain::<OptimismHandler, SPEC>()
+    }
+
+    /// Optimism with spec. Similar to [`Self::mainnet_with_spec`].
+    pub fn optimism_with_spec(spec_id: SpecId) -> Self {
+        Self::chain_with_spec::<OptimismHandler>(spec_id)
+    }
+}
+
 pub fn optimism_handle_register<DB: Database, EXT>(handler: &mut EvmHandler<'_, EXT, DB>) {
     spec_to_generic!(handler.cfg.spec_id, {
         // validate environment
@@ -139,9 +166,12 @@ pub fn last_frame_return<SPEC: Spec, EXT, DB: Database>(
     }
     // Prior to Regolith, deposit transactions did not receive gas refunds.
     let is_gas_refund_disabled = env.cfg.is_gas_refund_disabled() || (is_deposit && !is_regolith);
-    if !is_gas_refund_disabled {
-        gas.set_final_refund(SPEC::SPEC_ID.is_enabled_in(SpecId::LONDON));
-    }
+    let max_refund_quotient = if is_gas_refund_disabled {
+        0
+    } else {
+        env.cfg.refund_quotient(SPEC::SPEC_ID)
+    };
+    gas.set_final_refund_with_quotient(max_refund_quotient);
     Ok(())
 }
 
@@ -184,6 +214,7 @@ pub fn load_accounts<SPEC: Spec, EXT, DB: Database>(
 pub fn deduct_caller<SPEC: Spec, EXT, DB: Database>(
     context: &mut Context<EXT, DB>,
 ) -> Result<(), EVMError<DB::Error>> {
+    let base_token_id = context.evm.inner.env.cfg.base_token_id;
     // load caller's account.
     let (caller_account, _) = context
         .evm
@@ -195,7 +226,9 @@ pub fn deduct_caller<SPEC: Spec, EXT, DB: Database>(
     // in wei to the caller's balance. This should be persisted to the database
     // prior to the rest of execution.
     if let Some(mint) = context.evm.inner.env.tx.optimism.mint {
-        caller_account.info.increase_base_balance(U256::from(mint));
+        caller_account
+            .info
+            .increase_balance_saturating(base_token_id, U256::from(mint));
     }
 
     // We deduct caller max balance after minting and before deducing the
@@ -219,17 +252,27 @@ pub fn deduct_caller<SPEC: Spec, EXT, DB: Database>(
             .as_ref()
             .expect("L1BlockInfo should be loaded")
             .calculate_tx_l1_cost(enveloped_tx, SPEC::SPEC_ID);
-        if tx_l1_cost.gt(&caller_account.info.get_base_balance()) {
+        let balance = caller_account.info.get_balance(base_token_id);
+        if tx_l1_cost.gt(&balance) {
             return Err(EVMError::Transaction(
                 InvalidTransaction::LackOfFundForMaxFee {
                     fee: tx_l1_cost.into(),
-                    balance: caller_account.info.get_base_balance().into(),
+                    balance: balance.into(),
                 },
             ));
         }
         caller_account
             .info
-            .decrease_base_balance_saturating(tx_l1_cost);
+            .decrease_balance_saturating(base_token_id, tx_l1_cost);
+    }
+
+    // deduct gas cost from the gas payer's account of sponsored transactions.
+    if context.evm.inner.env.tx.is_sponsored() {
+        let (payer_account, _) = context.evm.inner.journaled_state.load_account(
+            context.evm.inner.env.tx.fee_payer(),
+            &mut context.evm.inner.db,
+        )?;
+        deduct_gas_payer_inner::<SPEC>(payer_account, &context.evm.inner.env);
     }
     Ok(())
 }
@@ -248,6 +291,7 @@ pub fn reward_beneficiary<SPEC: Spec, EXT, DB: Database>(
     }
 
     if !is_deposit {
+        let base_token_id = context.evm.inner.env.cfg.base_token_id;
         // If the transaction is not a deposit transaction, fees are paid out
         // to both the Base Fee Vault as well as the L1 Fee Vault.
         let Some(l1_block_info) = &context.evm.inner.l1_block_info else {
@@ -271,7 +315,9 @@ pub fn reward_beneficiary<SPEC: Spec, EXT, DB: Database>(
             .journaled_state
             .load_account(optimism::L1_FEE_RECIPIENT, &mut context.evm.inner.db)?;
         l1_fee_vault_account.mark_touch();
-        l1_fee_vault_account.info.increase_base_balance(l1_cost);
+        l1_fee_vault_account
+            .info
+            .increase_balance_saturating(base_token_id, l1_cost);
 
         // Send the base fee of the transaction to the Base Fee Vault.
         let (base_fee_vault_account, _) = context
@@ -287,7 +333,9 @@ pub fn reward_beneficiary<SPEC: Spec, EXT, DB: Database>(
             .block
             .basefee
             .mul(U256::from(gas.spent() - gas.refunded() as u64));
-        base_fee_vault_account.info.set_base_balance(new_balance);
+        base_fee_vault_account
+            .info
+            .set_balance(base_token_id, new_balance);
     }
     Ok(())
 }
@@ -344,9 +392,10 @@ pub fn end<SPEC: Spec, EXT, DB: Database>(
                         .unwrap_or_default(),
                 );
                 acc.info.nonce = acc.info.nonce.saturating_add(1);
-                acc.info.increase_base_balance_saturating(U256::from(
-                    context.evm.inner.env().tx.optimism.mint.unwrap_or(0),
-                ));
+                acc.info.increase_balance_saturating(
+                    context.evm.inner.env().cfg.base_token_id,
+                    U256::from(context.evm.inner.env().tx.optimism.mint.unwrap_or(0)),
+                );
                 acc.mark_touch();
                 acc
             };
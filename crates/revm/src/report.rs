@@ -0,0 +1,543 @@
+//! Human-readable reports of executed transactions.
+//!
+//! A [Report] renders the result and the state changes of a transaction as markdown: its
+//! outcome and gas, the calls and the token transfers recorded by a [ReportTracer], the changes
+//! of the token supplies, the storage writes and the logs.
+//!
+//! Accounts and storage slots are sorted, so that the report only depends on the execution and
+//! the reports of two versions of the VM can be diffed, e.g. in CI.
+use crate::{
+    interpreter::{
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, InstructionResult,
+    },
+    primitives::{
+        hex, Address, CreateScheme, ExecutionResult, Output, ResultAndState, TokenTransfer, U256,
+    },
+    Database, EvmContext, Inspector,
+};
+use core::fmt;
+use std::vec::Vec;
+
+/// The kind of a traced call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+impl CallKind {
+    /// Returns the name of the opcode making the call, e.g. `DELEGATECALL`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Call => "CALL",
+            Self::CallCode => "CALLCODE",
+            Self::DelegateCall => "DELEGATECALL",
+            Self::StaticCall => "STATICCALL",
+            Self::Create => "CREATE",
+            Self::Create2 => "CREATE2",
+        }
+    }
+}
+
+impl From<CallScheme> for CallKind {
+    fn from(scheme: CallScheme) -> Self {
+        match scheme {
+            CallScheme::Call => Self::Call,
+            CallScheme::CallCode => Self::CallCode,
+            CallScheme::DelegateCall => Self::DelegateCall,
+            CallScheme::StaticCall => Self::StaticCall,
+        }
+    }
+}
+
+impl From<CreateScheme> for CallKind {
+    fn from(scheme: CreateScheme) -> Self {
+        match scheme {
+            CreateScheme::Create => Self::Create,
+            CreateScheme::Create2 { .. } => Self::Create2,
+        }
+    }
+}
+
+/// A call or a contract creation recorded by the [ReportTracer].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallTrace {
+    /// The depth of the call, zero for the call of the transaction.
+    pub depth: u64,
+    pub kind: CallKind,
+    pub caller: Address,
+    /// The called account, or the created one. `None` for a creation that failed.
+    pub target: Option<Address>,
+    /// The tokens transferred by the call.
+    pub values: Vec<TokenTransfer>,
+    pub gas_used: u64,
+    /// The result of the call, `None` while it runs.
+    pub result: Option<InstructionResult>,
+}
+
+/// A token transfer recorded by the [ReportTracer].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenFlow {
+    pub from: Address,
+    pub to: Address,
+    pub token_id: U256,
+    pub amount: U256,
+    /// Whether the transfer was reverted, with the call making it or one of its callers.
+    pub reverted: bool,
+}
+
+/// [Inspector] recording the calls and the token transfers of a transaction for a [Report].
+///
+/// The records are cleared when the next transaction starts.
+#[derive(Clone, Debug, Default)]
+pub struct ReportTracer {
+    calls: Vec<CallTrace>,
+    token_flows: Vec<TokenFlow>,
+    /// The index of every running call, with the number of token flows when it started.
+    running: Vec<(usize, usize)>,
+}
+
+impl ReportTracer {
+    /// Creates a tracer with no records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the calls, in the order they started.
+    pub fn calls(&self) -> &[CallTrace] {
+        &self.calls
+    }
+
+    /// Returns the token transfers, in the order they were made.
+    pub fn token_flows(&self) -> &[TokenFlow] {
+        &self.token_flows
+    }
+
+    /// Clears the records.
+    pub fn clear(&mut self) {
+        self.calls.clear();
+        self.token_flows.clear();
+        self.running.clear();
+    }
+
+    fn start(&mut self, call: CallTrace) {
+        if call.depth == 0 {
+            self.clear();
+        }
+        self.running
+            .push((self.calls.len(), self.token_flows.len()));
+        self.calls.push(call);
+    }
+
+    fn end(&mut self, result: InstructionResult, gas_used: u64, target: Option<Address>) {
+        let Some((index, flows_start)) = self.running.pop() else {
+            return;
+        };
+        let call = &mut self.calls[index];
+        call.result = Some(result);
+        call.gas_used = gas_used;
+        if call.kind == CallKind::Create || call.kind == CallKind::Create2 {
+            call.target = target;
+        }
+        if !result.is_ok() {
+            for flow in &mut self.token_flows[flows_start..] {
+                flow.reverted = true;
+            }
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for ReportTracer {
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.start(CallTrace {
+            depth: context.journaled_state.depth(),
+            kind: inputs.scheme.into(),
+            caller: inputs.caller,
+            target: Some(inputs.target_address),
+            values: inputs.values.transferred().to_vec(),
+            gas_used: 0,
+            result: None,
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.end(outcome.result.result, outcome.result.gas.spent(), None);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.start(CallTrace {
+            depth: context.journaled_state.depth(),
+            kind: inputs.scheme.into(),
+            caller: inputs.caller,
+            target: None,
+            values: [TokenTransfer {
+                id: context.env.cfg.base_token_id,
+                amount: inputs.value,
+            }]
+            .into_iter()
+            .filter(|transfer| !transfer.is_zero())
+            .collect(),
+            gas_used: 0,
+            result: None,
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.end(
+            outcome.result.result,
+            outcome.result.gas.spent(),
+            outcome.address,
+        );
+        outcome
+    }
+
+    fn token_transfer(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        from: Address,
+        to: Address,
+        token_id: U256,
+        amount: U256,
+    ) -> Option<InstructionResult> {
+        self.token_flows.push(TokenFlow {
+            from,
+            to,
+            token_id,
+            amount,
+            reverted: false,
+        });
+        None
+    }
+}
+
+/// A markdown report of an executed transaction, see the [module documentation](self).
+///
+/// The report is rendered by its [Display](fmt::Display) implementation.
+#[derive(Clone, Copy, Debug)]
+pub struct Report<'a> {
+    result_and_state: &'a ResultAndState,
+    tracer: Option<&'a ReportTracer>,
+}
+
+impl<'a> Report<'a> {
+    /// Creates the report of the result and the state changes of a transaction.
+    pub fn new(result_and_state: &'a ResultAndState) -> Self {
+        Self {
+            result_and_state,
+            tracer: None,
+        }
+    }
+
+    /// Adds the calls and the token transfers recorded during the transaction to the report.
+    pub fn with_tracer(mut self, tracer: &'a ReportTracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    fn fmt_result(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "## Result")?;
+        writeln!(f)?;
+        let result = &self.result_and_state.result;
+        match result {
+            ExecutionResult::Success {
+                reason,
+                gas_refunded,
+                output,
+                gas_breakdown,
+                ..
+            } => {
+                writeln!(f, "- Status: success ({reason:?})")?;
+                writeln!(f, "- Gas used: {}", result.gas_used())?;
+                if let Some(breakdown) = gas_breakdown {
+                    writeln!(
+                        f,
+                        "- Gas breakdown: {} intrinsic, {} execution",
+                        breakdown.intrinsic, breakdown.execution
+                    )?;
+                }
+                writeln!(f, "- Gas refunded: {gas_refunded}")?;
+                match output {
+                    Output::Call(data) => writeln!(f, "- Output: 0x{}", hex::encode(data))?,
+                    Output::Create(data, address) => {
+                        if let Some(address) = address {
+                            writeln!(f, "- Created: {address}")?;
+                        }
+                        writeln!(f, "- Output: 0x{}", hex::encode(data))?
+                    }
+                }
+            }
+            ExecutionResult::Revert { gas_used, output } => {
+                writeln!(f, "- Status: revert")?;
+                writeln!(f, "- Gas used: {gas_used}")?;
+                writeln!(f, "- Output: 0x{}", hex::encode(output))?;
+            }
+            ExecutionResult::Halt { reason, gas_used } => {
+                writeln!(f, "- Status: halt ({reason:?})")?;
+                writeln!(f, "- Gas used: {gas_used}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fmt_calls(&self, f: &mut fmt::Formatter<'_>, tracer: &ReportTracer) -> fmt::Result {
+        writeln!(f)?;
+        writeln!(f, "## Calls")?;
+        writeln!(f)?;
+        for call in tracer.calls() {
+            write!(
+                f,
+                "{:indent$}- {} {} -> ",
+                "",
+                call.kind.as_str(),
+                call.caller,
+                indent = 2 * call.depth as usize
+            )?;
+            match call.target {
+                Some(target) => write!(f, "{target}")?,
+                None => write!(f, "none")?,
+            }
+            for value in &call.values {
+                write!(f, ", {} of token {:#x}", value.amount, value.id)?;
+            }
+            write!(f, ", gas used {}", call.gas_used)?;
+            match call.result {
+                Some(result) => writeln!(f, ": {result:?}")?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn fmt_token_flows(&self, f: &mut fmt::Formatter<'_>, tracer: &ReportTracer) -> fmt::Result {
+        let mut flows = tracer
+            .token_flows()
+            .iter()
+            .filter(|flow| !flow.reverted)
+            .peekable();
+        if flows.peek().is_none() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(f, "## Token transfers")?;
+        writeln!(f)?;
+        writeln!(f, "| From | To | Token | Amount |")?;
+        writeln!(f, "|------|----|-------|--------|")?;
+        for flow in flows {
+            writeln!(
+                f,
+                "| {} | {} | {:#x} | {} |",
+                flow.from, flow.to, flow.token_id, flow.amount
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fmt_supply_changes(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ExecutionResult::Success {
+            supply_changes: Some(changes),
+            ..
+        } = &self.result_and_state.result
+        else {
+            return Ok(());
+        };
+        if changes.minted.is_empty() && changes.burned.is_empty() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(f, "## Token supply changes")?;
+        writeln!(f)?;
+        writeln!(f, "| Token | Minted | Burned |")?;
+        writeln!(f, "|-------|--------|--------|")?;
+        let mut token_ids: Vec<U256> = changes
+            .minted
+            .iter()
+            .chain(&changes.burned)
+            .map(|change| change.id)
+            .collect();
+        token_ids.sort_unstable();
+        token_ids.dedup();
+        for token_id in token_ids {
+            let amount = |changes: &[TokenTransfer]| {
+                changes
+                    .iter()
+                    .find(|change| change.id == token_id)
+                    .map_or(U256::ZERO, |change| change.amount)
+            };
+            writeln!(
+                f,
+                "| {token_id:#x} | {} | {} |",
+                amount(&changes.minted),
+                amount(&changes.burned)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fmt_storage_writes(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut writes: Vec<(Address, U256, U256, U256)> = self
+            .result_and_state
+            .state
+            .accounts
+            .iter()
+            .flat_map(|(address, account)| {
+                account
+                    .storage
+                    .iter()
+                    .filter(|(_, slot)| slot.is_changed())
+                    .map(|(key, slot)| (*address, *key, slot.original_value, slot.present_value))
+            })
+            .collect();
+        if writes.is_empty() {
+            return Ok(());
+        }
+        writes.sort_unstable();
+        writeln!(f)?;
+        writeln!(f, "## Storage writes")?;
+        writeln!(f)?;
+        writeln!(f, "| Account | Slot | Before | After |")?;
+        writeln!(f, "|---------|------|--------|-------|")?;
+        for (address, key, original, present) in writes {
+            writeln!(f, "| {address} | {key:#x} | {original:#x} | {present:#x} |")?;
+        }
+        Ok(())
+    }
+
+    fn fmt_logs(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let logs = self.result_and_state.result.logs();
+        if logs.is_empty() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(f, "## Logs")?;
+        writeln!(f)?;
+        for log in logs {
+            write!(f, "- {}", log.address)?;
+            for topic in log.data.topics() {
+                write!(f, ", topic {topic}")?;
+            }
+            writeln!(f, ", data 0x{}", hex::encode(&log.data.data))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Transaction report")?;
+        writeln!(f)?;
+        self.fmt_result(f)?;
+        if let Some(tracer) = self.tracer {
+            self.fmt_calls(f, tracer)?;
+            self.fmt_token_flows(f, tracer)?;
+        }
+        self.fmt_supply_changes(f)?;
+        self.fmt_storage_writes(f)?;
+        self.fmt_logs(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inspector_handle_register,
+        interpreter::opcode,
+        primitives::{
+            address, init_balances, AccountInfo, Bytecode, Bytes, TransactTo, BASE_TOKEN_ID,
+        },
+        Evm, InMemoryDB,
+    };
+
+    #[test]
+    fn renders_report() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let contract = address!("00000000000000000000000000000000000000bb");
+        // SSTORE(1, 2), STOP
+        let bytecode = Bytecode::new_raw(Bytes::from(
+            [
+                opcode::PUSH1,
+                2,
+                opcode::PUSH1,
+                1,
+                opcode::SSTORE,
+                opcode::STOP,
+            ]
+            .to_vec(),
+        ));
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000_000)),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                code_hash: bytecode.hash_slow(),
+                code: Some(bytecode),
+                ..Default::default()
+            },
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(ReportTracer::new())
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(contract);
+                tx.gas_limit = 100_000;
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: U256::from(5),
+                }];
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let result_and_state = evm.transact().unwrap();
+        let tracer = &evm.context.external;
+        assert_eq!(tracer.calls().len(), 1);
+        assert_eq!(tracer.token_flows().len(), 1);
+
+        let gas_used = result_and_state.result.gas_used();
+        let report = Report::new(&result_and_state)
+            .with_tracer(tracer)
+            .to_string();
+        assert!(
+            report.starts_with("# Transaction report\n\n## Result\n\n- Status: success (Stop)\n")
+        );
+        assert!(report.contains(&format!("- Gas used: {gas_used}\n")));
+        assert!(report.contains(&format!(
+            "- CALL {caller} -> {contract}, 5 of token 0x0, gas used "
+        )));
+        assert!(report.contains(&format!("| {caller} | {contract} | 0x0 | 5 |\n")));
+        assert!(report.ends_with(&format!(
+            "## Storage writes\n\n| Account | Slot | Before | After |\n|---------|------|--------|-------|\n| {contract} | 0x1 | 0x0 | 0x2 |\n"
+        )));
+    }
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: MIT
+// Generated by `revm::sablier::native_tokens_abi`.
+pragma solidity >=0.8.0;
+
+/// @title INativeTokens
+/// @notice The Native Tokens Precompile, at 0x7060000000000000000000000000000000000001.
+/// @dev The precompile acts on behalf of the contract that calls it, so it has to be called with `delegatecall`,
+/// e.g. with the calldata made by `abi.encodeCall`. The `data` of `transferAndCall` and `transferMultipleAndCall`
+/// is the calldata of the callee prefixed with 28 zero bytes, so that its selector ends a word.
+interface INativeTokens {
+    /// @notice Returns at most `limit` registered token IDs, from the `offset`-th one.
+    function allTokenIDs(uint256 offset, uint256 limit) external view returns (uint256[] memory tokenIDs);
+
+    /// @notice Returns the amount of the token of the owner that the spender may transfer with `transferFrom`.
+    function allowance(address owner, address spender, uint256 tokenID) external view returns (uint256);
+
+    /// @notice Returns the balance of the token held by the account.
+    function balanceOf(address account, uint256 tokenID) external view returns (uint256);
+
+    /// @notice Burns the amount of the token with the sub-ID, minted by the calling contract, from the balance of the holder.
+    function burn(uint256 subID, address tokenHolder, uint256 amount) external;
+
+    /// @notice Returns the `index`-th token transferred to the calling contract by its call.
+    function callValueAt(uint256 index) external view returns (uint256 tokenID, uint256 amount);
+
+    /// @notice Returns the number of tokens transferred to the calling contract by its call.
+    function callValueCount() external view returns (uint256);
+
+    /// @notice Returns the tokens transferred to the calling contract by its call.
+    function getCallValues() external view returns (uint256[] memory tokenIDs, uint256[] memory amounts);
+
+    /// @notice Mints the amount of the token with the sub-ID of the calling contract to the recipient.
+    function mint(uint256 subID, address recipient, uint256 amount) external;
+
+    /// @notice Makes the calls to the precompile in their order, and returns what they return. If any of them fails, none of them changes the state.
+    function multicall(bytes[] calldata calls) external returns (bytes[] memory results);
+
+    /// @notice Returns the nonce of the next permit signed by the owner.
+    function nonces(address owner) external view returns (uint256);
+
+    /// @notice Sets the allowance of the spender to the amount of the token of the owner, with the EIP-712 signature `r ++ s ++ v` of the owner's permit.
+    function permit(address owner, address spender, uint256 tokenID, uint256 amount, uint256 deadline, bytes calldata signature) external;
+
+    /// @notice Transfers the amount of the token from the calling contract to `to`.
+    function transfer(address to, uint256 tokenID, uint256 amount) external;
+
+    /// @notice Transfers the amount of the token from the calling contract to the callee, and calls it with `data`, returning what it returns.
+    function transferAndCall(address recipientAndCallee, uint256 tokenID, uint256 amount, bytes calldata data) external;
+
+    /// @notice Transfers the amount of the token from `from` to `to`, spending the allowance of the calling contract.
+    function transferFrom(address from, address to, uint256 tokenID, uint256 amount) external;
+
+    /// @notice Transfers the amounts of the tokens from the calling contract to `to`.
+    function transferMultiple(address to, uint256[] calldata tokenIDs, uint256[] calldata amounts) external;
+
+    /// @notice Transfers the amounts of the tokens from the calling contract to the callee, and calls it with `data`, returning what it returns.
+    function transferMultipleAndCall(address recipientAndCallee, uint256[] calldata tokenIDs, uint256[] calldata amounts, bytes calldata data) external;
+}
@@ -1,11 +1,30 @@
-use crate::primitives::Address;
+use crate::primitives::{Address, B256, U256};
 
 #[cfg(feature = "std")]
 pub mod native_tokens;
 
+#[cfg(feature = "std")]
+pub mod native_tokens_abi;
+
+#[cfg(feature = "std")]
+pub mod precompile_set;
+
+#[cfg(feature = "std")]
+pub mod streams;
+
 #[cfg(feature = "std")]
 mod test_native_tokens;
 
+#[cfg(feature = "std")]
+mod utilities;
+
+#[cfg(feature = "std")]
+pub use precompile_set::{SablierPrecompile, SablierPrecompileSet};
+
+/// The first 12 bytes of the addresses of [u64_to_prefixed_address], which are reserved for the
+/// Sablier precompiles.
+pub const PREFIXED_ADDRESS_NAMESPACE: [u8; 12] = [112, 96, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
 /// Similar to `crate::u64_to_address`, but adds the number 706 as a prefix. 706 is the sum of the ASCII value
 /// of the characters in the string "Sablier".
 ///
@@ -18,10 +37,37 @@ pub const fn u64_to_prefixed_address(x: u64) -> Address {
     ])
 }
 
+/// Returns `true` if the address is in the [PREFIXED_ADDRESS_NAMESPACE].
+///
+/// No contract can be created at such an address, even if no precompile is there yet, so that a
+/// precompile added later can't collide with a deployed contract.
+#[inline]
+pub fn is_reserved_address(address: &Address) -> bool {
+    address.starts_with(&PREFIXED_ADDRESS_NAMESPACE)
+}
+
+/// Returns the address of the contract created with `CREATE` by the caller, whose nonce was
+/// `nonce` before the creation.
+///
+/// The creation fails with a collision if the address [is reserved](is_reserved_address).
+#[inline]
+pub fn create_address(caller: Address, nonce: u64) -> Address {
+    caller.create(nonce)
+}
+
+/// Returns the address of the contract created with `CREATE2` by the caller, with the salt and
+/// the hash of the init code.
+///
+/// The creation fails with a collision if the address [is reserved](is_reserved_address).
+#[inline]
+pub fn create2_address(caller: Address, salt: U256, init_code_hash: B256) -> Address {
+    caller.create2(salt.to_be_bytes(), init_code_hash)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::primitives::address;
+    use crate::primitives::{address, b256, keccak256};
 
     #[test]
     fn test_u64_to_prefixed_address() {
@@ -29,5 +75,38 @@ mod tests {
             u64_to_prefixed_address(1),
             address!("7060000000000000000000000000000000000001")
         );
+        assert!(is_reserved_address(&u64_to_prefixed_address(u64::MAX)));
+        assert!(!is_reserved_address(&address!(
+            "7060000000000000000000010000000000000001"
+        )));
+        assert!(!is_reserved_address(&crate::precompile::u64_to_address(1)));
+    }
+
+    #[test]
+    fn test_created_addresses() {
+        let caller = address!("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        assert_eq!(
+            create_address(caller, 0),
+            address!("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d")
+        );
+        assert_eq!(
+            create_address(caller, 1),
+            address!("343c43a37d37dff08ae8c4a11544c718abb4fcf8")
+        );
+
+        // EIP-1014 examples
+        assert_eq!(
+            create2_address(Address::ZERO, U256::ZERO, keccak256([0x00])),
+            address!("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38")
+        );
+        let salt = b256!("000000000000000000000000feed000000000000000000000000000000000000");
+        assert_eq!(
+            create2_address(
+                address!("deadbeef00000000000000000000000000000000"),
+                salt.into(),
+                keccak256([0x00])
+            ),
+            address!("d04116cdd17bebe565eb2422f2497e06cc1c9833")
+        );
     }
 }
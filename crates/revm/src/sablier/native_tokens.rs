@@ -1,28 +1,109 @@
 use crate::{
-    interpreter::CallInputs,
-    precompile::{Error, PrecompileResult, PrimitiveCallInfo, ResultInfo, ResultOrNewCall},
-    primitives::{utilities::bytes_parsing::*, Address, Bytes, EVMError, TokenTransfer, U256},
+    interpreter::{gas, CallInputs, CallScheme, Host},
+    precompile::{
+        secp256k1::ecrecover, Error, PrecompileResult, PrimitiveCallInfo, ResultInfo,
+        ResultOrNewCall,
+    },
+    primitives::{
+        alloy_primitives::B512, utilities::bytes_parsing::*, Address, Bytes, Eip712Domain, HashMap,
+        HashSet, TokenPermit, TokenTransfer, U256,
+    },
+    sablier::utilities::{charge, consume_address, consume_u256, mapping_slot, sload, sstore},
     ContextStatefulPrecompileMut, Database, InnerEvmContext,
 };
-use std::{string::String, vec::Vec};
+use core::cell::Cell;
+use std::{string::String, vec, vec::Vec};
 
 pub const ADDRESS: Address = crate::sablier::u64_to_prefixed_address(1);
 
 /// The base gas cost for the Native Tokens Precompile operations.
 pub const BASE_GAS_COST: u64 = 15;
 
+/// The gas cost of a cold access to a balance by `balanceOf`, on top of [BASE_GAS_COST], like
+/// the cold access to a storage slot.
+pub const COLD_BALANCE_GAS_COST: u64 = gas::COLD_SLOAD_COST;
+
+/// The gas cost of every token ID returned by `allTokenIDs`.
+pub const TOKEN_ID_GAS_COST: u64 = 3;
+
+/// The gas cost of every token transferred by `transferMultiple` and `transferMultipleAndCall`,
+/// on top of [BASE_GAS_COST], like the copy of its ID and its amount. It is charged as the arrays
+/// are decoded, for their declared lengths, before they are allocated.
+pub const TOKEN_TRANSFER_GAS_COST: u64 = 2 * gas::COPY;
+
+/// The maximum number of tokens transferred by `transferMultiple` and
+/// `transferMultipleAndCall`. Longer arrays are an invalid input, rejected before they are read.
+pub const MAX_TOKEN_TRANSFERS: usize = 256;
+
+/// The gas cost of reading a storage slot of the precompile, i.e. an allowance or a nonce.
+pub const SLOT_READ_GAS_COST: u64 = gas::COLD_SLOAD_COST;
+
+/// The gas cost of writing a storage slot of the precompile.
+pub const SLOT_WRITE_GAS_COST: u64 = gas::SSTORE_SET;
+
+/// The gas cost of recovering the signer of a permit, like the `ecrecover` precompile.
+pub const ECRECOVER_GAS_COST: u64 = 3_000;
+
+/// The size of the signature of a permit: `r`, `s` and `v`, with `v` either 27 or 28.
+pub const PERMIT_SIGNATURE_SIZE: usize = 65;
+
+/// The name of the [EIP-712](Eip712Domain) domain of the permits, whose verifying contract is
+/// [ADDRESS] and whose chain ID is the one of the chain.
+pub const PERMIT_DOMAIN_NAME: &str = "Native Tokens";
+
+/// The version of the [EIP-712](Eip712Domain) domain of the permits.
+pub const PERMIT_DOMAIN_VERSION: &str = "1";
+
+/// The slot of the allowances in the storage of the precompile, laid out like
+/// `mapping(address owner => mapping(address spender => mapping(uint256 tokenID => uint256)))`.
+const ALLOWANCES_SLOT: U256 = U256::ZERO;
+
+/// The slot of the nonces of the permits in the storage of the precompile, laid out like
+/// `mapping(address owner => uint256)`.
+const NONCES_SLOT: U256 = U256::from_limbs([1, 0, 0, 0]);
+
+/// The maximum number of calls batched by `multicall`. Longer arrays are an invalid input,
+/// rejected before they are read.
+pub const MAX_MULTICALL_CALLS: usize = 64;
+
+/// The maximum size of the calldata forwarded to the callee of `transferAndCall` and
+/// `transferMultipleAndCall`, the maximum size of the initcode of a contract creation
+/// (EIP-3860). Larger calldata is an invalid input, rejected before it is read.
+pub const MAX_CALLDATA_SIZE: usize = crate::primitives::MAX_INITCODE_SIZE;
+
+// The function selector of `allTokenIDs(uint256 offset, uint256 limit) external returns (uint256[] memory)`
+pub const ALL_TOKEN_IDS_SELECTOR: u32 = 0x912e49dc;
+
+// The function selector of `allowance(address owner, address spender, uint256 tokenID)`
+pub const ALLOWANCE_SELECTOR: u32 = 0x598af9e7;
+
 // The function selector of `balanceOf(address account, uint256 tokenID)`
 pub const BALANCEOF_SELECTOR: u32 = 0x00fdd58e;
 
 // The function selector of `burn(uint256 subID, address tokenHolder, uint256 amount)`
 pub const BURN_SELECTOR: u32 = 0x9eea5f66;
 
+// The function selector of `callValueAt(uint256 index) external returns (uint256 tokenID, uint256 amount)`
+pub const CALL_VALUE_AT_SELECTOR: u32 = 0xcfaadbfe;
+
+// The function selector of `callValueCount() external returns (uint256)`
+pub const CALL_VALUE_COUNT_SELECTOR: u32 = 0xe94ad055;
+
 // The function selector of `getCallValues() external returns (uint256[] calldata, uint256[] calldata)`
 pub const GET_CALL_VALUES_SELECTOR: u32 = 0x6141a8b9;
 
 // The function selector of `mint(uint256 subID, address recipient, uint256 amount)`
 pub const MINT_SELECTOR: u32 = 0x836a1040;
 
+// The function selector of `multicall(bytes[] calldata calls) external returns (bytes[] memory)`
+pub const MULTICALL_SELECTOR: u32 = 0xac9650d8;
+
+// The function selector of `nonces(address owner)`
+pub const NONCES_SELECTOR: u32 = 0x7ecebe00;
+
+// The function selector of `permit(address owner, address spender, uint256 tokenID, uint256 amount, uint256 deadline, bytes calldata signature)`
+pub const PERMIT_SELECTOR: u32 = 0x4f6be2b7;
+
 // The function selector of `transferAndCall(address recipientAndCallee, uint256 tokenID, uint256 amount, bytes calldata data)`
 pub const TRANSFER_AND_CALL_SELECTOR: u32 = 0xd1c673e9;
 
@@ -35,12 +116,169 @@ pub const TRANSFER_MULTIPLE_AND_CALL_SELECTOR: u32 = 0x822bbe4c;
 // The function selector of `transfer(address to, uint256 tokenID, uint256 amount)`
 pub const TRANSFER_SELECTOR: u32 = 0x095bcdb6;
 
+// The function selector of `transferFrom(address from, address to, uint256 tokenID, uint256 amount)`
+pub const TRANSFER_FROM_SELECTOR: u32 = 0xfe99049a;
+
+/// How the Native Tokens Precompile decodes the dynamic arguments of its calls, i.e. the arrays
+/// and the `bytes`, whose values are placed after the head of the arguments, at the offsets the
+/// head holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AbiMode {
+    /// The dynamic arguments have to follow the head in their order, without gaps, like
+    /// Solidity encodes them, and nothing can follow them. The `bytes` aren't padded. An offset
+    /// that doesn't point where the layout places its value is an invalid input.
+    #[default]
+    Sequential,
+    /// The dynamic arguments are read where their offsets point, as specified by the Solidity
+    /// ABI, so that the encodings of the same arguments by different encoders are equally
+    /// accepted. The padding of the `bytes` and whatever follows the arguments are ignored.
+    Lenient,
+}
+
+/// How the calldata that `transferAndCall` and `transferMultipleAndCall` forward to their callee
+/// is passed in their `bytes data` argument.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ForwardedCalldata {
+    /// The calldata of the callee, prefixed with 28 zero bytes, so that its selector ends a
+    /// word.
+    #[default]
+    SelectorWord,
+    /// The calldata of the callee as it is, of any length, e.g. as made by `abi.encodeCall`. In
+    /// [AbiMode::Sequential], it is padded with zeros to a whole number of words, like the ABI
+    /// pads `bytes`.
+    Raw,
+}
+
+/// The function selectors the Native Tokens Precompile dispatches on, and how it decodes their
+/// arguments, i.e. the version of its ABI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NativeTokensSelectors {
+    /// The selector of `allTokenIDs`.
+    pub all_token_ids: u32,
+    /// The selector of `allowance`.
+    pub allowance: u32,
+    /// The selector of `balanceOf`.
+    pub balance_of: u32,
+    /// The selector of `burn`.
+    pub burn: u32,
+    /// The selector of `callValueAt`.
+    pub call_value_at: u32,
+    /// The selector of `callValueCount`.
+    pub call_value_count: u32,
+    /// The selector of `getCallValues`.
+    pub get_call_values: u32,
+    /// The selector of `mint`.
+    pub mint: u32,
+    /// The selector of `multicall`.
+    pub multicall: u32,
+    /// The selector of `nonces`.
+    pub nonces: u32,
+    /// The selector of `permit`.
+    pub permit: u32,
+    /// The selector of `transferAndCall`.
+    pub transfer_and_call: u32,
+    /// The selector of `transferMultiple`.
+    pub transfer_multiple: u32,
+    /// The selector of `transferMultipleAndCall`.
+    pub transfer_multiple_and_call: u32,
+    /// The selector of `transfer`.
+    pub transfer: u32,
+    /// The selector of `transferFrom`.
+    pub transfer_from: u32,
+    /// How the dynamic arguments are decoded.
+    pub abi_mode: AbiMode,
+    /// How the calldata forwarded to a callee is passed.
+    pub forwarded_calldata: ForwardedCalldata,
+}
+
+impl NativeTokensSelectors {
+    /// The first version of the ABI, served at [ADDRESS].
+    pub const V1: Self = Self {
+        all_token_ids: ALL_TOKEN_IDS_SELECTOR,
+        allowance: ALLOWANCE_SELECTOR,
+        balance_of: BALANCEOF_SELECTOR,
+        burn: BURN_SELECTOR,
+        call_value_at: CALL_VALUE_AT_SELECTOR,
+        call_value_count: CALL_VALUE_COUNT_SELECTOR,
+        get_call_values: GET_CALL_VALUES_SELECTOR,
+        mint: MINT_SELECTOR,
+        multicall: MULTICALL_SELECTOR,
+        nonces: NONCES_SELECTOR,
+        permit: PERMIT_SELECTOR,
+        transfer_and_call: TRANSFER_AND_CALL_SELECTOR,
+        transfer_multiple: TRANSFER_MULTIPLE_SELECTOR,
+        transfer_multiple_and_call: TRANSFER_MULTIPLE_AND_CALL_SELECTOR,
+        transfer: TRANSFER_SELECTOR,
+        transfer_from: TRANSFER_FROM_SELECTOR,
+        abi_mode: AbiMode::Sequential,
+        forwarded_calldata: ForwardedCalldata::SelectorWord,
+    };
+}
+
+impl Default for NativeTokensSelectors {
+    fn default() -> Self {
+        Self::V1
+    }
+}
+
+/// A handler of the calls to a selector of the Native Tokens Precompile, registered with
+/// [NativeTokensContextPrecompile::register_selector].
+///
+/// The handler is given the host, the inputs of the call, whose calldata starts with the selector,
+/// the gas already used by the call, i.e. [BASE_GAS_COST], and the gas limit of the call. The gas
+/// used it returns includes the gas it was given.
+pub type SelectorHandler = fn(&mut dyn Host, &CallInputs, u64, u64) -> PrecompileResult;
+
 /// The Context Stateful Precompile that implements the Native Tokens functionalities.
-pub struct NativeTokensContextPrecompile;
+///
+/// Besides the built-in functions, the precompile routes the selectors registered with
+/// [Self::register_selector] to their handlers, so that a chain can extend it with operations of
+/// its own, e.g. streaming-specific ones, without forking it.
+#[derive(Clone, Debug, Default)]
+pub struct NativeTokensContextPrecompile {
+    selectors: NativeTokensSelectors,
+    /// The handlers of the registered selectors, which take precedence over the built-in ones.
+    handlers: HashMap<u32, SelectorHandler>,
+}
+
+impl NativeTokensContextPrecompile {
+    /// Creates the precompile answering to the given version of the ABI.
+    pub fn new(selectors: NativeTokensSelectors) -> Self {
+        Self {
+            selectors,
+            handlers: HashMap::default(),
+        }
+    }
 
-impl Clone for NativeTokensContextPrecompile {
-    fn clone(&self) -> Self {
-        NativeTokensContextPrecompile
+    /// Returns the selectors the precompile dispatches on.
+    pub fn selectors(&self) -> &NativeTokensSelectors {
+        &self.selectors
+    }
+
+    /// Routes the calls to the selector to the handler, replacing the built-in function with the
+    /// selector, if any.
+    ///
+    /// Returns the handler previously registered for the selector.
+    pub fn register_selector(
+        &mut self,
+        selector: u32,
+        handler: SelectorHandler,
+    ) -> Option<SelectorHandler> {
+        self.handlers.insert(selector, handler)
+    }
+
+    /// Returns the precompile with the calls to the selector routed to the handler.
+    ///
+    /// See [Self::register_selector].
+    pub fn with_selector(mut self, selector: u32, handler: SelectorHandler) -> Self {
+        self.register_selector(selector, handler);
+        self
+    }
+
+    /// Returns the handler registered for the selector of the calldata, if any.
+    fn handler(&self, input: &Bytes) -> Option<SelectorHandler> {
+        let selector = u32::from_be_bytes(input.get(..4)?.try_into().ok()?);
+        self.handlers.get(&selector).copied()
     }
 }
 
@@ -56,105 +294,935 @@ impl<DB: Database> ContextStatefulPrecompileMut<DB> for NativeTokensContextPreco
             return Err(Error::OutOfGas);
         }
 
+        if let Some(handler) = self.handler(&inputs.input) {
+            return handler(evmctx, inputs, gas_used, gas_limit);
+        }
+
+        // Figure out what functionality (i.e. "function") is being requested
+        let gas_used = Cell::new(gas_used);
+        let call =
+            NativeTokenCall::decode_charged(&self.selectors, &inputs.input, &gas_used, gas_limit)?;
+
+        self.execute(evmctx, inputs, gas_used.get(), gas_limit, call)
+    }
+
+    fn native_token_call(&self, inputs: &CallInputs) -> Option<NativeTokenCall> {
+        if self.handler(&inputs.input).is_some() {
+            return None;
+        }
+        let call = NativeTokenCall::decode(&self.selectors, &inputs.input).ok()?;
+        // The batched calls routed to a handler aren't reported, like the calls to a handler
+        if call.operations().any(|operation| {
+            self.handlers
+                .contains_key(&operation.selector(&self.selectors))
+        }) {
+            return None;
+        }
+        Some(call)
+    }
+}
+
+impl NativeTokensContextPrecompile {
+    /// Executes the decoded call, with the gas already used by the call.
+    fn execute<DB: Database>(
+        &self,
+        evmctx: &mut InnerEvmContext<DB>,
+        inputs: &CallInputs,
+        gas_used: u64,
+        gas_limit: u64,
+        call: NativeTokenCall,
+    ) -> PrecompileResult {
+        match call {
+            NativeTokenCall::AllTokenIds { offset, limit } => {
+                all_token_ids(evmctx, gas_used, gas_limit, offset, limit)
+            }
+
+            NativeTokenCall::Allowance {
+                owner,
+                spender,
+                token_id,
+            } => allowance(evmctx, gas_used, gas_limit, owner, spender, token_id),
+
+            NativeTokenCall::BalanceOf { account, token_id } => {
+                balance_of(evmctx, gas_used, gas_limit, account, token_id)
+            }
+
+            NativeTokenCall::Burn {
+                sub_id,
+                token_holder,
+                amount,
+            } => burn(evmctx, inputs, gas_used, sub_id, token_holder, amount),
+
+            NativeTokenCall::CallValueAt { index } => call_value_at(inputs, gas_used, index),
+
+            NativeTokenCall::CallValueCount => call_value_count(inputs, gas_used),
+
+            NativeTokenCall::GetCallValues => get_call_values(inputs, gas_used),
+
+            NativeTokenCall::Mint {
+                sub_id,
+                recipient,
+                amount,
+            } => mint(evmctx, inputs, gas_used, sub_id, recipient, amount),
+
+            NativeTokenCall::Multicall { calls } => {
+                self.multicall(evmctx, inputs, gas_used, gas_limit, calls)
+            }
+
+            NativeTokenCall::Nonces { owner } => nonces(evmctx, gas_used, gas_limit, owner),
+
+            NativeTokenCall::Permit {
+                owner,
+                spender,
+                token_id,
+                amount,
+                deadline,
+                signature,
+            } => permit(
+                evmctx,
+                inputs,
+                gas_used,
+                gas_limit,
+                TokenPermit {
+                    owner,
+                    spender,
+                    token_id,
+                    amount,
+                    // the next nonce of the owner, read from the storage
+                    nonce: U256::ZERO,
+                    deadline,
+                },
+                &signature,
+            ),
+
+            NativeTokenCall::TransferAndCall {
+                recipient_and_callee,
+                token_id,
+                amount,
+                data,
+            } => transfer_and_call(
+                evmctx,
+                inputs,
+                gas_used,
+                recipient_and_callee,
+                vec![TokenTransfer {
+                    id: token_id,
+                    amount,
+                }],
+                data,
+            ),
+
+            NativeTokenCall::TransferMultipleAndCall {
+                recipient_and_callee,
+                transfers,
+                data,
+            } => transfer_and_call(
+                evmctx,
+                inputs,
+                gas_used,
+                recipient_and_callee,
+                transfers,
+                data,
+            ),
+
+            NativeTokenCall::TransferMultiple {
+                recipient,
+                transfers,
+            } => transfer(evmctx, inputs, gas_used, recipient, &transfers),
+
+            NativeTokenCall::Transfer {
+                recipient,
+                token_id,
+                amount,
+            } => transfer(
+                evmctx,
+                inputs,
+                gas_used,
+                recipient,
+                &[TokenTransfer {
+                    id: token_id,
+                    amount,
+                }],
+            ),
+
+            NativeTokenCall::TransferFrom {
+                sender,
+                recipient,
+                token_id,
+                amount,
+            } => transfer_from(
+                evmctx,
+                inputs,
+                gas_used,
+                gas_limit,
+                sender,
+                recipient,
+                TokenTransfer {
+                    id: token_id,
+                    amount,
+                },
+            ),
+        }
+    }
+
+    /// Implements `multicall`: executes the calls in their order, as if each was made by the
+    /// delegating frame, and returns what they return.
+    ///
+    /// Each call is charged the base gas cost, on top of the one of the multicall, so that a
+    /// batched call costs at least as much as the same call made on its own. If any of them
+    /// fails, the multicall fails, so that none of them changes the state.
+    fn multicall<DB: Database>(
+        &self,
+        evmctx: &mut InnerEvmContext<DB>,
+        inputs: &CallInputs,
+        mut gas_used: u64,
+        gas_limit: u64,
+        calls: Vec<NativeTokenCall>,
+    ) -> PrecompileResult {
+        let mut outputs = Vec::with_capacity(calls.len());
+        for call in calls {
+            charge(&mut gas_used, gas_limit, BASE_GAS_COST)?;
+            let selector = call.selector(&self.selectors);
+            let result = match self.handlers.get(&selector) {
+                Some(handler) => {
+                    let inputs = CallInputs {
+                        input: call.encode(&self.selectors),
+                        ..inputs.clone()
+                    };
+                    handler(evmctx, &inputs, gas_used, gas_limit)
+                }
+                None => self.execute(evmctx, inputs, gas_used, gas_limit, call),
+            };
+            // A batched call can't make a call of its own
+            let ResultOrNewCall::Result(result) = result? else {
+                return Err(Error::InvalidInput);
+            };
+            gas_used = result.gas_used;
+            outputs.push(result.returned_bytes);
+        }
+
+        Ok(ResultOrNewCall::Result(ResultInfo {
+            gas_used,
+            returned_bytes: encode_bytes_array(&outputs),
+        }))
+    }
+}
+
+/// A call to the Native Tokens Precompile, decoded from its calldata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NativeTokenCall {
+    /// `allTokenIDs(uint256 offset, uint256 limit)`
+    AllTokenIds { offset: U256, limit: U256 },
+    /// `allowance(address owner, address spender, uint256 tokenID)`
+    Allowance {
+        owner: Address,
+        spender: Address,
+        token_id: U256,
+    },
+    /// `balanceOf(address account, uint256 tokenID)`
+    BalanceOf { account: Address, token_id: U256 },
+    /// `burn(uint256 subID, address tokenHolder, uint256 amount)`
+    Burn {
+        sub_id: U256,
+        token_holder: Address,
+        amount: U256,
+    },
+    /// `callValueAt(uint256 index)`
+    CallValueAt { index: U256 },
+    /// `callValueCount()`
+    CallValueCount,
+    /// `getCallValues()`
+    GetCallValues,
+    /// `mint(uint256 subID, address recipient, uint256 amount)`
+    Mint {
+        sub_id: U256,
+        recipient: Address,
+        amount: U256,
+    },
+    /// `multicall(bytes[] calldata calls)`, with the batched calls decoded. They neither make
+    /// calls of their own nor are multicalls.
+    Multicall { calls: Vec<NativeTokenCall> },
+    /// `nonces(address owner)`
+    Nonces { owner: Address },
+    /// `permit(address owner, address spender, uint256 tokenID, uint256 amount, uint256 deadline, bytes calldata signature)`
+    Permit {
+        owner: Address,
+        spender: Address,
+        token_id: U256,
+        amount: U256,
+        deadline: U256,
+        signature: Bytes,
+    },
+    /// `transfer(address to, uint256 tokenID, uint256 amount)`
+    Transfer {
+        recipient: Address,
+        token_id: U256,
+        amount: U256,
+    },
+    /// `transferAndCall(address recipientAndCallee, uint256 tokenID, uint256 amount, bytes calldata data)`
+    TransferAndCall {
+        recipient_and_callee: Address,
+        token_id: U256,
+        amount: U256,
+        data: Bytes,
+    },
+    /// `transferMultiple(address to, uint256[] calldata tokenIDs, uint256[] calldata amounts)`
+    TransferMultiple {
+        recipient: Address,
+        transfers: Vec<TokenTransfer>,
+    },
+    /// `transferMultipleAndCall(address recipientAndCallee, uint256[] calldata tokenIDs, uint256[] calldata amounts, bytes calldata data)`
+    TransferMultipleAndCall {
+        recipient_and_callee: Address,
+        transfers: Vec<TokenTransfer>,
+        data: Bytes,
+    },
+    /// `transferFrom(address from, address to, uint256 tokenID, uint256 amount)`
+    TransferFrom {
+        sender: Address,
+        recipient: Address,
+        token_id: U256,
+        amount: U256,
+    },
+}
+
+impl NativeTokenCall {
+    /// Decodes the calldata of a call to a Native Tokens Precompile answering to `selectors`.
+    ///
+    /// The offsets of the dynamic arguments are checked or followed according to the
+    /// [AbiMode] of the `selectors`.
+    pub fn decode(selectors: &NativeTokensSelectors, input: &Bytes) -> Result<Self, Error> {
+        Self::decode_charged(selectors, input, &Cell::new(0), u64::MAX)
+    }
+
+    /// Decodes the calldata like [Self::decode], adding the gas cost of the decoded token
+    /// transfers to `gas_used`, see [TOKEN_TRANSFER_GAS_COST].
+    fn decode_charged(
+        selectors: &NativeTokensSelectors,
+        input: &Bytes,
+        gas_used: &Cell<u64>,
+        gas_limit: u64,
+    ) -> Result<Self, Error> {
         // Create a local mutable copy of the input bytes
-        let mut input = inputs.input.clone();
+        let mut input = input.clone();
 
-        // Parse the input bytes, to figure out what functionality (i.e. "function") is being requested
         let function_selector = consume_u32_from(&mut input).map_err(|_| Error::InvalidInput)?;
+        let args = |head_len| Args::new(input.clone(), head_len, selectors, gas_used, gas_limit);
+        let call = match function_selector {
+            s if s == selectors.all_token_ids => {
+                let mut args = args(2);
+                let call = Self::AllTokenIds {
+                    offset: args.u256()?,
+                    limit: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
 
-        // Handle the different function selectors
-        match function_selector {
-            BALANCEOF_SELECTOR => balance_of(evmctx, gas_used, input),
+            s if s == selectors.allowance => {
+                let mut args = args(3);
+                let call = Self::Allowance {
+                    owner: args.address()?,
+                    spender: args.address()?,
+                    token_id: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
 
-            BURN_SELECTOR => burn(evmctx, inputs, gas_used, input),
+            s if s == selectors.balance_of => {
+                let mut args = args(2);
+                let call = Self::BalanceOf {
+                    account: args.address()?,
+                    token_id: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
 
-            GET_CALL_VALUES_SELECTOR => get_call_values(evmctx, inputs, gas_used),
+            s if s == selectors.burn => {
+                let mut args = args(3);
+                let call = Self::Burn {
+                    sub_id: args.u256()?,
+                    token_holder: args.address()?,
+                    amount: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
 
-            MINT_SELECTOR => mint(evmctx, inputs, gas_used, input),
+            s if s == selectors.call_value_at => {
+                let mut args = args(1);
+                let call = Self::CallValueAt {
+                    index: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
 
-            TRANSFER_AND_CALL_SELECTOR => transfer_and_call(evmctx, inputs, input),
+            s if s == selectors.call_value_count => {
+                args(0).finish()?;
+                Self::CallValueCount
+            }
 
-            TRANSFER_MULTIPLE_AND_CALL_SELECTOR => {
-                transfer_multiple_and_call(evmctx, inputs, input)
+            s if s == selectors.get_call_values => {
+                args(0).finish()?;
+                Self::GetCallValues
             }
 
-            TRANSFER_MULTIPLE_SELECTOR => transfer_multiple(evmctx, inputs, gas_used, input),
+            s if s == selectors.mint => {
+                let mut args = args(3);
+                let call = Self::Mint {
+                    sub_id: args.u256()?,
+                    recipient: args.address()?,
+                    amount: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
+
+            s if s == selectors.multicall => {
+                let mut args = args(1);
+                let calls = args
+                    .bytes_array(MAX_MULTICALL_CALLS, MAX_CALLDATA_SIZE)?
+                    .iter()
+                    .map(|calldata| Self::decode_charged(selectors, calldata, gas_used, gas_limit))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if calls.iter().any(|call| !call.can_be_batched()) {
+                    return Err(Error::InvalidInput);
+                }
+                args.finish()?;
+                Self::Multicall { calls }
+            }
+
+            s if s == selectors.nonces => {
+                let mut args = args(1);
+                let call = Self::Nonces {
+                    owner: args.address()?,
+                };
+                args.finish()?;
+                call
+            }
+
+            s if s == selectors.permit => {
+                let mut args = args(6);
+                let call = Self::Permit {
+                    owner: args.address()?,
+                    spender: args.address()?,
+                    token_id: args.u256()?,
+                    amount: args.u256()?,
+                    deadline: args.u256()?,
+                    signature: args.bytes(PERMIT_SIGNATURE_SIZE, true)?,
+                };
+                args.finish()?;
+                call
+            }
+
+            s if s == selectors.transfer_and_call => {
+                let mut args = args(4);
+                let call = Self::TransferAndCall {
+                    recipient_and_callee: args.address()?,
+                    token_id: args.u256()?,
+                    amount: args.u256()?,
+                    data: args.calldata()?,
+                };
+                args.finish()?;
+                call
+            }
 
-            TRANSFER_SELECTOR => transfer(evmctx, inputs, gas_used, input),
+            s if s == selectors.transfer_multiple_and_call => {
+                let mut args = args(4);
+                let call = Self::TransferMultipleAndCall {
+                    recipient_and_callee: args.address()?,
+                    transfers: token_transfers(args.u256_array()?, args.u256_array()?)?,
+                    data: args.calldata()?,
+                };
+                args.finish()?;
+                call
+            }
+
+            s if s == selectors.transfer_multiple => {
+                let mut args = args(3);
+                let call = Self::TransferMultiple {
+                    recipient: args.address()?,
+                    transfers: token_transfers(args.u256_array()?, args.u256_array()?)?,
+                };
+                args.finish()?;
+                call
+            }
+
+            s if s == selectors.transfer => {
+                let mut args = args(3);
+                let call = Self::Transfer {
+                    recipient: args.address()?,
+                    token_id: args.u256()?,
+                    amount: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
+
+            s if s == selectors.transfer_from => {
+                let mut args = args(4);
+                let call = Self::TransferFrom {
+                    sender: args.address()?,
+                    recipient: args.address()?,
+                    token_id: args.u256()?,
+                    amount: args.u256()?,
+                };
+                args.finish()?;
+                call
+            }
 
             // TODO: MNTCREATE
-            _ => Err(Error::InvalidInput),
+            _ => return Err(Error::InvalidInput),
+        };
+
+        Ok(call)
+    }
+
+    /// Returns the recipient and the tokens transferred by the call, or `None` if the call
+    /// doesn't transfer tokens.
+    ///
+    /// The tokens are transferred from the [token_sender](Self::token_sender) if any, from the
+    /// contract that delegatecalled the precompile otherwise.
+    pub fn token_transfers(&self) -> Option<(Address, Vec<TokenTransfer>)> {
+        match self {
+            Self::Transfer {
+                recipient,
+                token_id,
+                amount,
+            } => Some((
+                *recipient,
+                vec![TokenTransfer {
+                    id: *token_id,
+                    amount: *amount,
+                }],
+            )),
+            Self::TransferAndCall {
+                recipient_and_callee,
+                token_id,
+                amount,
+                ..
+            } => Some((
+                *recipient_and_callee,
+                vec![TokenTransfer {
+                    id: *token_id,
+                    amount: *amount,
+                }],
+            )),
+            Self::TransferMultiple {
+                recipient,
+                transfers,
+            } => Some((*recipient, transfers.clone())),
+            Self::TransferMultipleAndCall {
+                recipient_and_callee,
+                transfers,
+                ..
+            } => Some((*recipient_and_callee, transfers.clone())),
+            Self::TransferFrom {
+                recipient,
+                token_id,
+                amount,
+                ..
+            } => Some((
+                *recipient,
+                vec![TokenTransfer {
+                    id: *token_id,
+                    amount: *amount,
+                }],
+            )),
+            Self::AllTokenIds { .. }
+            | Self::Allowance { .. }
+            | Self::BalanceOf { .. }
+            | Self::Burn { .. }
+            | Self::CallValueAt { .. }
+            | Self::CallValueCount
+            | Self::GetCallValues
+            | Self::Mint { .. }
+            | Self::Multicall { .. }
+            | Self::Nonces { .. }
+            | Self::Permit { .. } => None,
+        }
+    }
+
+    /// Returns the operations of the call: the batched calls of a multicall, the call itself
+    /// otherwise.
+    pub fn operations(&self) -> impl Iterator<Item = &Self> {
+        match self {
+            Self::Multicall { calls } => calls.iter(),
+            call => core::slice::from_ref(call).iter(),
+        }
+    }
+
+    /// Returns `true` if the call can be batched by a multicall, i.e. it doesn't make a call of
+    /// its own and isn't a multicall.
+    pub fn can_be_batched(&self) -> bool {
+        !matches!(
+            self,
+            Self::Multicall { .. }
+                | Self::TransferAndCall { .. }
+                | Self::TransferMultipleAndCall { .. }
+        )
+    }
+
+    /// Returns the account whose tokens the call transfers, if it isn't the contract that
+    /// delegatecalled the precompile, i.e. the owner of the allowance spent by `transferFrom`.
+    pub fn token_sender(&self) -> Option<Address> {
+        match self {
+            Self::TransferFrom { sender, .. } => Some(*sender),
+            _ => None,
         }
     }
 }
 
-/// Checks whether the given address is an EOA.
-fn is_address_eoa<DB: Database>(
-    evmctx: &mut InnerEvmContext<DB>,
-    address: Address,
-) -> Result<bool, EVMError<DB::Error>> {
-    evmctx
-        .code(address)
-        .map(|(bytecode, _)| bytecode.is_empty())
+/// The ABI-encoded arguments of a call, read in their order: the static ones from the head, and
+/// the dynamic ones where the offsets in the head point.
+struct Args<'a> {
+    /// The arguments, from whose start the offsets are counted.
+    args: Bytes,
+    /// The words of the head that have not been read yet.
+    head: Bytes,
+    /// The end of the values read so far, i.e. where the next dynamic value starts in the
+    /// [AbiMode::Sequential] layout.
+    end: usize,
+    mode: AbiMode,
+    forwarded_calldata: ForwardedCalldata,
+    /// The gas used by the call, to which the decoded arrays are charged.
+    gas_used: &'a Cell<u64>,
+    gas_limit: u64,
 }
 
-fn balance_of<DB: Database>(
-    evmctx: &mut InnerEvmContext<DB>,
-    gas_used: u64,
-    mut input: Bytes,
-) -> PrecompileResult {
-    // Extract the address from the input
-    let address = consume_address_from(&mut input).map_err(|_| Error::InvalidInput)?;
+impl<'a> Args<'a> {
+    /// Reads the arguments, whose head is `head_len` words long, as the version of the ABI
+    /// encodes them, charging the decoded arrays to `gas_used`.
+    fn new(
+        args: Bytes,
+        head_len: usize,
+        selectors: &NativeTokensSelectors,
+        gas_used: &'a Cell<u64>,
+        gas_limit: u64,
+    ) -> Self {
+        Self {
+            head: args.clone(),
+            args,
+            end: head_len * 32,
+            mode: selectors.abi_mode,
+            forwarded_calldata: selectors.forwarded_calldata,
+            gas_used,
+            gas_limit,
+        }
+    }
+
+    fn address(&mut self) -> Result<Address, Error> {
+        consume_address(&mut self.head)
+    }
+
+    fn u256(&mut self) -> Result<U256, Error> {
+        consume_u256(&mut self.head)
+    }
+
+    /// Returns the encoding of the next dynamic argument, up to the end of the arguments.
+    fn dynamic(&mut self) -> Result<Bytes, Error> {
+        let offset = consume_u256(&mut self.head)?;
+        let offset = usize::try_from(offset)
+            .ok()
+            .filter(|offset| *offset <= self.args.len())
+            .ok_or(Error::InvalidInput)?;
+
+        // The value has to be where the sequential layout places it
+        if self.mode == AbiMode::Sequential && offset != self.end {
+            return Err(Error::InvalidInput);
+        }
+
+        Ok(self.args.slice(offset..))
+    }
+
+    /// Records that the value of a dynamic argument ends where `rest` starts.
+    fn read_up_to(&mut self, rest: &Bytes) {
+        self.end = self.args.len() - rest.len();
+    }
+
+    /// Reads the next `uint256[]` argument, the token IDs or the amounts of token transfers.
+    fn u256_array(&mut self) -> Result<Vec<U256>, Error> {
+        let mut value = self.dynamic()?;
+        let len = consume_u256(&mut value)?;
+
+        // Make sure the length is capped, backed by the input and paid for before allocating the
+        // array. The token IDs and the amounts each pay for half of the cost of the transfers.
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= MAX_TOKEN_TRANSFERS && len * 32 <= value.len())
+            .ok_or(Error::InvalidInput)?;
+        let mut gas_used = self.gas_used.get();
+        charge(
+            &mut gas_used,
+            self.gas_limit,
+            TOKEN_TRANSFER_GAS_COST / 2 * len as u64,
+        )?;
+        self.gas_used.set(gas_used);
+
+        let mut array = Vec::with_capacity(len);
+        for _ in 0..len {
+            array.push(consume_u256(&mut value)?);
+        }
+        self.read_up_to(&value);
+        Ok(array)
+    }
+
+    /// Reads the next `bytes` argument, of at most `max_size` bytes. In [AbiMode::Sequential],
+    /// the `padded` bytes have to be followed by zeros up to a whole number of words.
+    fn bytes(&mut self, max_size: usize, padded: bool) -> Result<Bytes, Error> {
+        let mut value = self.dynamic()?;
+
+        // Extract the byte size of the bytes from the input
+        let size = consume_u256(&mut value)?;
+        let size = usize::try_from(size)
+            .ok()
+            .filter(|size| *size <= max_size)
+            .ok_or(Error::InvalidInput)?;
+
+        // Extract the bytes from the input
+        let bytes = consume_bytes_from(&mut value, size).map_err(|_| Error::InvalidInput)?;
+
+        if padded && self.mode == AbiMode::Sequential {
+            let padding = consume_bytes_from(&mut value, (32 - size % 32) % 32)
+                .map_err(|_| Error::InvalidInput)?;
+            if padding.iter().any(|byte| *byte != 0) {
+                return Err(Error::InvalidInput);
+            }
+        }
+        self.read_up_to(&value);
+        Ok(bytes.into())
+    }
+
+    /// Reads the next `bytes[]` argument, of at most `max_len` elements of at most `max_size`
+    /// bytes each.
+    fn bytes_array(&mut self, max_len: usize, max_size: usize) -> Result<Vec<Bytes>, Error> {
+        let mut value = self.dynamic()?;
+        let len = consume_u256(&mut value)?;
+
+        // Make sure the length is capped and backed by the input before allocating the array
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= max_len && len * 32 <= value.len())
+            .ok_or(Error::InvalidInput)?;
+
+        // The elements are encoded like arguments: their offsets, counted from the first one,
+        // followed by their values
+        let mut elements = Self {
+            head: value.clone(),
+            args: value,
+            end: len * 32,
+            mode: self.mode,
+            forwarded_calldata: self.forwarded_calldata,
+            gas_used: self.gas_used,
+            gas_limit: self.gas_limit,
+        };
+        let mut array = Vec::with_capacity(len);
+        for _ in 0..len {
+            array.push(elements.bytes(max_size, true)?);
+        }
+        self.read_up_to(&elements.args.slice(elements.end..));
+        Ok(array)
+    }
+
+    /// Reads the next `bytes` argument, the calldata forwarded to a callee.
+    fn calldata(&mut self) -> Result<Bytes, Error> {
+        let prefix_len = match self.forwarded_calldata {
+            ForwardedCalldata::SelectorWord => 28,
+            ForwardedCalldata::Raw => 0,
+        };
+
+        // The raw calldata is padded to a whole number of words with zeros
+        let calldata = self.bytes(
+            prefix_len + MAX_CALLDATA_SIZE,
+            self.forwarded_calldata == ForwardedCalldata::Raw,
+        )?;
+
+        // Renounce the 28-byte 0 prefix, if any, forming the EVM word together with the 4-byte
+        // function selector. Calldata shorter than the prefix is ill-formed.
+        calldata
+            .get(prefix_len..)
+            .map(Bytes::copy_from_slice)
+            .ok_or(Error::InvalidInput)
+    }
 
-    // Extract the token id from the input
-    let token_id = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
+    /// Checks that the arguments have been fully read, unless the mode ignores what follows
+    /// them.
+    fn finish(self) -> Result<(), Error> {
+        // if the input has not been fully consumed by this point, it has been ill-formed
+        let end = self.end.max(self.args.len() - self.head.len());
+        if self.mode == AbiMode::Sequential && end != self.args.len() {
+            return Err(Error::InvalidInput);
+        }
+        Ok(())
+    }
+}
 
-    // if the input has not been fully consumed by this point, it has been ill-formed
-    if !input.is_empty() {
+/// Pairs the token IDs with the transfer amounts. The arrays have to be of the same length, and
+/// the token IDs have to be unique.
+fn token_transfers(token_ids: Vec<U256>, amounts: Vec<U256>) -> Result<Vec<TokenTransfer>, Error> {
+    // Make sure the token IDs are unique
+    if token_ids.len() != token_ids.iter().collect::<HashSet<_>>().len() {
         return Err(Error::InvalidInput);
     }
 
-    // Query the balance of the given address for the given token ID
-    match evmctx.balance(token_id, address) {
-        Ok(balance) => Ok(ResultOrNewCall::Result(ResultInfo {
-            gas_used,
-            returned_bytes: balance.0.to_be_bytes::<{ U256::BYTES }>().into(),
-        })),
-        Err(_) => Err(Error::InvalidInput),
+    // Make sure the token IDs and transfer amounts arrays have the same length
+    if token_ids.len() != amounts.len() {
+        return Err(Error::InvalidInput);
     }
+
+    Ok(token_ids
+        .into_iter()
+        .zip(amounts)
+        .map(|(id, amount)| TokenTransfer { id, amount })
+        .collect())
 }
 
-fn mint<DB: Database>(
+/// Returns the contract on whose behalf the precompile acts.
+///
+/// The precompile is a library: it has to be called with `DELEGATECALL`, so that it runs in the
+/// context of the calling contract. That contract, i.e. `inputs.target_address`, is the account
+/// that mints, burns and sends tokens, and the caller of the calls made by `transferAndCall` and
+/// `transferMultipleAndCall`. Calling the precompile with any other scheme would make it act on
+/// behalf of its own address, so it is rejected.
+fn delegating_contract<DB: Database>(
     evmctx: &mut InnerEvmContext<DB>,
     inputs: &CallInputs,
-    gas_used: u64,
-    mut input: Bytes,
-) -> PrecompileResult {
-    // Make sure that the Call Context is not static
-    if inputs.is_static {
-        return Err(Error::AttemptedStateChangeDuringStaticCall);
+) -> Result<Address, Error> {
+    if inputs.scheme != CallScheme::DelegateCall {
+        return Err(Error::UnauthorizedCaller);
     }
 
-    // Make sure that the caller is a contract
+    // The delegating frame runs on the account of a contract, whatever sent the transaction. An
+    // EOA that delegates its code with EIP-7702 runs that code, so it's a contract too.
     let caller = inputs.target_address;
-    if is_address_eoa(evmctx, caller).map_err(|_| Error::UnauthorizedCaller)? {
+    if Host::is_contract(evmctx, caller) != Some(true) {
+        return Err(Error::UnauthorizedCaller);
+    }
+    Ok(caller)
+}
+
+/// Returns the contract that mints or burns the tokens, see [delegating_contract].
+///
+/// With [CfgEnv::reject_delegated_mint_burn], the calling code has to be the contract's own code:
+/// a library delegatecalled by a proxy would otherwise mint and burn the tokens of the proxy.
+///
+/// [CfgEnv::reject_delegated_mint_burn]: crate::primitives::CfgEnv::reject_delegated_mint_burn
+fn minting_contract<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    inputs: &CallInputs,
+) -> Result<Address, Error> {
+    let caller = delegating_contract(evmctx, inputs)?;
+    if evmctx.env.cfg.reject_delegated_mint_burn && inputs.caller_bytecode_address != caller {
         return Err(Error::UnauthorizedCaller);
     }
+    Ok(caller)
+}
 
-    // Extract the sub_id from the input
-    let sub_id = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
+/// Returns the call values of the frame that delegatecalled the precompile.
+///
+/// Reading the call values doesn't act on behalf of the delegating frame, so unlike
+/// [delegating_contract] it doesn't require that frame to run a deployed contract nor to be
+/// non-static: a constructor, whose code isn't deployed yet, can read what its creator sent it.
+fn delegated_call_values(
+    inputs: &CallInputs,
+) -> Result<impl Iterator<Item = &TokenTransfer>, Error> {
+    if inputs.scheme != CallScheme::DelegateCall {
+        return Err(Error::UnauthorizedCaller);
+    }
 
-    // Extract the recipient's address from the input
-    let recipient = consume_address_from(&mut input).map_err(|_| Error::InvalidInput)?;
+    // The values of the delegatecall are the call values of the delegating frame
+    Ok(inputs.values.frame_values())
+}
 
-    // Extract the amount from the input
-    let amount = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
+/// Returns the registered token IDs, from the `offset`-th one and at most `limit` of them.
+///
+/// An offset past the registered tokens returns no token IDs, so that callers can page through
+/// the registry until they get a page shorter than the limit.
+fn all_token_ids<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    gas_used: u64,
+    gas_limit: u64,
+    offset: U256,
+    limit: U256,
+) -> PrecompileResult {
+    let token_ids = evmctx
+        .registered_token_ids()
+        .map_err(|_| Error::InvalidInput)?;
+
+    let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+    let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+    let page = token_ids
+        .get(offset..)
+        .map(|rest| &rest[..rest.len().min(limit)])
+        .unwrap_or_default();
+
+    let gas_used = gas_used + TOKEN_ID_GAS_COST * page.len() as u64;
+    if gas_used > gas_limit {
+        return Err(Error::OutOfGas);
+    }
+
+    // Returned data structure:
+    // 0/0: token ids offset (== 32)
+    // 1/32: token ids length
+    // 2+/64+: token ids elements
+    let mut data = U256::from(32).to_be_bytes_vec();
+    data.append(U256::from(page.len()).to_be_bytes_vec().as_mut());
+    for token_id in page {
+        data.append(token_id.to_be_bytes_vec().as_mut());
+    }
 
-    // if the input has not been fully consumed by this point, it has been ill-formed
-    if !input.is_empty() {
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: Bytes::from(data),
+    }))
+}
+
+fn balance_of<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    gas_used: u64,
+    gas_limit: u64,
+    address: Address,
+    token_id: U256,
+) -> PrecompileResult {
+    // Query the balance of the given address for the given token ID
+    let Ok((balance, is_cold)) = evmctx.balance(token_id, address) else {
         return Err(Error::InvalidInput);
+    };
+
+    let gas_used = if is_cold {
+        gas_used + COLD_BALANCE_GAS_COST
+    } else {
+        gas_used
+    };
+    if gas_used > gas_limit {
+        return Err(Error::OutOfGas);
+    }
+
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: balance.to_be_bytes::<{ U256::BYTES }>().into(),
+    }))
+}
+
+fn mint<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    inputs: &CallInputs,
+    gas_used: u64,
+    sub_id: U256,
+    recipient: Address,
+    amount: U256,
+) -> PrecompileResult {
+    // Make sure that the Call Context is not static
+    if inputs.is_static {
+        return Err(Error::AttemptedStateChangeDuringStaticCall);
     }
 
+    // Make sure that the caller is a contract that delegatecalled the precompile
+    let caller = minting_contract(evmctx, inputs)?;
+
     // Mint the given amount of tokens to the recipient
     let minter = caller;
     if evmctx
@@ -174,7 +1242,9 @@ fn burn<DB: Database>(
     evmctx: &mut InnerEvmContext<DB>,
     inputs: &CallInputs,
     gas_used: u64,
-    mut input: Bytes,
+    sub_id: U256,
+    token_holder: Address,
+    amount: U256,
 ) -> PrecompileResult {
     // TODO: consider forcing the to-be-burned tokens to be transferred as MNTs.
     // This would allow us to deduce the token ID from the call itself, as well as make the burning process more transparent to the caller
@@ -185,25 +1255,8 @@ fn burn<DB: Database>(
         return Err(Error::AttemptedStateChangeDuringStaticCall);
     }
 
-    // Make sure that the caller is a contract
-    let caller = inputs.target_address;
-    if is_address_eoa(evmctx, caller).map_err(|_| Error::UnauthorizedCaller)? {
-        return Err(Error::UnauthorizedCaller);
-    }
-
-    // Extract the sub_id from the input
-    let sub_id = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the token holder address from the input
-    let token_holder = consume_address_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the amount from the input
-    let amount = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // if the input has not been fully consumed by this point, it has been ill-formed
-    if !input.is_empty() {
-        return Err(Error::InvalidInput);
-    }
+    // Make sure that the caller is a contract that delegatecalled the precompile
+    let caller = minting_contract(evmctx, inputs)?;
 
     // Burn the given amount of tokens from the burner's balance
     let burner = caller;
@@ -220,54 +1273,28 @@ fn burn<DB: Database>(
     }
 }
 
+/// Implements `transfer` and `transferMultiple`.
 fn transfer<DB: Database>(
     evmctx: &mut InnerEvmContext<DB>,
     inputs: &CallInputs,
     gas_used: u64,
-    mut input: Bytes,
+    recipient: Address,
+    token_transfers: &[TokenTransfer],
 ) -> PrecompileResult {
     // Make sure that the Call Context is not static
     if inputs.is_static {
         return Err(Error::AttemptedStateChangeDuringStaticCall);
     }
 
-    // Make sure that the caller is a contract
-    let caller = inputs.target_address;
-    if is_address_eoa(evmctx, caller).map_err(|_| Error::UnauthorizedCaller)? {
-        return Err(Error::UnauthorizedCaller);
-    }
-
-    // Extract the recipient's address from the input
-    let recipient = consume_address_from(&mut input).map_err(|_| Error::InvalidInput)?;
+    // Make sure that the caller is a contract that delegatecalled the precompile
+    let caller = delegating_contract(evmctx, inputs)?;
 
-    // Extract the token ID from the input
-    let token_id = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the amount from the input
-    let amount = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // if the input has not been fully consumed by this point, it has been ill-formed
-    if !input.is_empty() {
-        return Err(Error::InvalidInput);
-    }
-
-    // Transfer the given amount of tokens from the sender to the recipient
+    // Transfer the given amounts of tokens from the sender to the recipient
     let sender = caller;
-    if evmctx
-        .journaled_state
-        .transfer(
-            &sender,
-            &recipient,
-            &vec![
-                (TokenTransfer {
-                    id: token_id,
-                    amount,
-                }),
-            ],
-            &mut evmctx.db,
-        )
-        .is_ok()
-    {
+    if matches!(
+        evmctx.transfer(&sender, &recipient, token_transfers),
+        Ok(None)
+    ) {
         Ok(ResultOrNewCall::Result(ResultInfo {
             gas_used,
             returned_bytes: Bytes::new(),
@@ -277,154 +1304,154 @@ fn transfer<DB: Database>(
     }
 }
 
-fn transfer_and_call<DB: Database>(
+/// Returns the slot of `allowances[owner][spender][token_id]`.
+fn allowance_slot(owner: Address, spender: Address, token_id: U256) -> U256 {
+    let slot = mapping_slot(ALLOWANCES_SLOT, owner.into_word().into());
+    let slot = mapping_slot(slot, spender.into_word().into());
+    mapping_slot(slot, token_id)
+}
+
+/// Returns the slot of `nonces[owner]`.
+fn nonce_slot(owner: Address) -> U256 {
+    mapping_slot(NONCES_SLOT, owner.into_word().into())
+}
+
+/// Returns the amount of the token of `owner` that `spender` may transfer with `transferFrom`.
+fn allowance<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    mut gas_used: u64,
+    gas_limit: u64,
+    owner: Address,
+    spender: Address,
+    token_id: U256,
+) -> PrecompileResult {
+    charge(&mut gas_used, gas_limit, SLOT_READ_GAS_COST)?;
+    let allowance = sload(evmctx, ADDRESS, allowance_slot(owner, spender, token_id))?;
+
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: allowance.to_be_bytes::<{ U256::BYTES }>().into(),
+    }))
+}
+
+/// Returns the nonce of the next permit signed by `owner`.
+fn nonces<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    mut gas_used: u64,
+    gas_limit: u64,
+    owner: Address,
+) -> PrecompileResult {
+    charge(&mut gas_used, gas_limit, SLOT_READ_GAS_COST)?;
+    let nonce = sload(evmctx, ADDRESS, nonce_slot(owner))?;
+
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: nonce.to_be_bytes::<{ U256::BYTES }>().into(),
+    }))
+}
+
+/// Returns the [EIP-712](Eip712Domain) domain of the permits on the chain.
+pub fn permit_domain(chain_id: u64) -> Eip712Domain {
+    Eip712Domain::new(PERMIT_DOMAIN_NAME, PERMIT_DOMAIN_VERSION, chain_id, ADDRESS)
+}
+
+/// Sets the allowance of the permit, signed by its owner with the next nonce of the owner.
+///
+/// The permit doesn't act on behalf of the caller, so it can be submitted by anyone, e.g. by the
+/// spender in the transaction that spends the allowance.
+fn permit<DB: Database>(
     evmctx: &mut InnerEvmContext<DB>,
     inputs: &CallInputs,
-    mut input: Bytes,
+    mut gas_used: u64,
+    gas_limit: u64,
+    mut permit: TokenPermit,
+    signature: &[u8],
 ) -> PrecompileResult {
     // Make sure that the Call Context is not static
     if inputs.is_static {
         return Err(Error::AttemptedStateChangeDuringStaticCall);
     }
 
-    // Make sure that the caller is a contract
-    let caller = inputs.target_address;
-    if is_address_eoa(evmctx, caller).map_err(|_| Error::UnauthorizedCaller)? {
-        return Err(Error::UnauthorizedCaller);
-    }
-
-    // Extract the recipient's address from the input
-    let recipient_and_callee = consume_address_from(&mut input).map_err(|_| Error::InvalidInput)?;
+    charge(
+        &mut gas_used,
+        gas_limit,
+        ECRECOVER_GAS_COST + SLOT_READ_GAS_COST + 2 * SLOT_WRITE_GAS_COST,
+    )?;
 
-    // Make sure that the callee is a contract
-    if is_address_eoa(evmctx, recipient_and_callee).map_err(|_| Error::InvalidInput)? {
-        return Err(Error::InvalidInput);
+    if U256::from(evmctx.env.block.timestamp) > permit.deadline {
+        return Err(Error::Other(String::from("Permit expired")));
     }
 
-    // Extract the token ID from the input
-    let token_id = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the amount from the input
-    let amount = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract and ignore the calldata offset from the input
-    let _ = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the byte size of the calldata from the input
-    let calldata_size = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    let calldata_usize: usize = calldata_size.try_into().unwrap_or_default();
-
-    // Extract the calldata from the input
-    let mut calldata =
-        consume_bytes_from(&mut input, calldata_usize).map_err(|_| Error::InvalidInput)?;
-
-    // if the input has not been fully consumed by this point, it has been ill-formed
-    if !input.is_empty() {
+    // The signature is `r ++ s ++ v`, with `v` either 27 or 28
+    let Some((&v, rs)) = signature
+        .split_last()
+        .filter(|(v, rs)| matches!(v, 27 | 28) && rs.len() == 64)
+    else {
         return Err(Error::InvalidInput);
+    };
+
+    let nonce_slot = nonce_slot(permit.owner);
+    permit.nonce = sload(evmctx, ADDRESS, nonce_slot)?;
+    let hash = permit_domain(evmctx.env.cfg.chain_id).signing_hash(&permit);
+    let signer = ecrecover(&B512::from_slice(rs), v - 27, &hash)
+        .map(Address::from_word)
+        .map_err(|_| Error::Other(String::from("Invalid permit signature")))?;
+    if signer != permit.owner || permit.owner.is_zero() {
+        return Err(Error::Other(String::from("Invalid permit signature")));
     }
 
-    // Renounce the 28-byte 0 prefix, forming the EVM word together with the 4-byte function selector
-    calldata = calldata[28..].to_vec();
+    sstore(evmctx, ADDRESS, nonce_slot, permit.nonce + U256::from(1))?;
+    sstore(
+        evmctx,
+        ADDRESS,
+        allowance_slot(permit.owner, permit.spender, permit.token_id),
+        permit.amount,
+    )?;
 
-    // Signal to the external context that a Call to the callee must be performed,
-    // transferring the MNTs and passing the calldata to it
-    Ok(ResultOrNewCall::Call(PrimitiveCallInfo {
-        target_address: recipient_and_callee,
-        token_transfers: vec![
-            (TokenTransfer {
-                id: token_id,
-                amount,
-            }),
-        ],
-        input_data: calldata.into(),
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: Bytes::new(),
     }))
 }
 
-fn transfer_multiple<DB: Database>(
+/// Transfers the tokens of `sender` to `recipient`, spending the allowance of the contract that
+/// delegatecalled the precompile. An allowance of `type(uint256).max` is never spent.
+fn transfer_from<DB: Database>(
     evmctx: &mut InnerEvmContext<DB>,
     inputs: &CallInputs,
-    gas_used: u64,
-    mut input: Bytes,
+    mut gas_used: u64,
+    gas_limit: u64,
+    sender: Address,
+    recipient: Address,
+    token_transfer: TokenTransfer,
 ) -> PrecompileResult {
     // Make sure that the Call Context is not static
     if inputs.is_static {
         return Err(Error::AttemptedStateChangeDuringStaticCall);
     }
 
-    // Make sure that the caller is a contract
-    let caller = inputs.target_address;
-    if is_address_eoa(evmctx, caller).map_err(|_| Error::UnauthorizedCaller)? {
-        return Err(Error::UnauthorizedCaller);
-    }
-
-    // Extract the recipient's address from the input
-    let recipient = consume_address_from(&mut input).map_err(|_| Error::InvalidInput)?;
+    // Make sure that the caller is a contract that delegatecalled the precompile
+    let spender = delegating_contract(evmctx, inputs)?;
 
-    // Extract & ignore the token_ids offset
-    consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
+    charge(
+        &mut gas_used,
+        gas_limit,
+        SLOT_READ_GAS_COST + SLOT_WRITE_GAS_COST,
+    )?;
 
-    // Extract & ignore the transfer_amounts offset
-    consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the length of the token IDs array from the input
-    let token_ids_len = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the token IDs from the input
-    let mut token_ids = Vec::new();
-    let last_64_bits: &[u8] = &token_ids_len.to_be_bytes::<32>()[24..];
-    let token_ids_len_u64 = u64::from_be_bytes(last_64_bits.try_into().unwrap());
-    for _ in 0..token_ids_len_u64 {
-        token_ids.push(consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?);
+    let slot = allowance_slot(sender, spender, token_transfer.id);
+    let allowance = sload(evmctx, ADDRESS, slot)?;
+    if allowance < token_transfer.amount {
+        return Err(Error::Other(String::from("Insufficient allowance")));
     }
-
-    // Make sure the token IDs are unique
-    if token_ids.len()
-        != token_ids
-            .iter()
-            .collect::<std::collections::HashSet<_>>()
-            .len()
-    {
-        return Err(Error::InvalidInput);
+    if allowance != U256::MAX {
+        sstore(evmctx, ADDRESS, slot, allowance - token_transfer.amount)?;
     }
 
-    // Extract the length of the token IDs array from the input
-    let transfer_amounts_len = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    if token_ids_len != transfer_amounts_len {
-        return Err(Error::InvalidInput);
-    }
-
-    // Extract the transfer amounts from the input
-    let mut transfer_amounts = Vec::new();
-    let last_64_bits: &[u8] = &transfer_amounts_len.to_be_bytes::<32>()[24..];
-    let transfer_amounts_len_u64 = u64::from_be_bytes(last_64_bits.try_into().unwrap());
-    for _ in 0..transfer_amounts_len_u64 {
-        transfer_amounts.push(consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?);
-    }
-
-    // if the input has not been fully consumed by this point, it has been ill-formed
-    if !input.is_empty() {
-        return Err(Error::InvalidInput);
-    }
-
-    // Transform the passed token IDs & amounts into a vector of TokenTransfers
-    let token_transfers = token_ids
-        .iter()
-        .zip(transfer_amounts.iter())
-        .map(|(id, amount)| TokenTransfer {
-            id: *id,
-            amount: *amount,
-        })
-        .collect::<Vec<TokenTransfer>>();
-
-    // Transfer the given amounts of tokens from the sender to the recipient
-    let sender = caller;
-    if evmctx
-        .journaled_state
-        .transfer(&sender, &recipient, &token_transfers, &mut evmctx.db)
-        .is_ok()
-    {
+    if matches!(
+        evmctx.transfer(&sender, &recipient, &[token_transfer]),
+        Ok(None)
+    ) {
         Ok(ResultOrNewCall::Result(ResultInfo {
             gas_used,
             returned_bytes: Bytes::new(),
@@ -434,120 +1461,74 @@ fn transfer_multiple<DB: Database>(
     }
 }
 
-fn transfer_multiple_and_call<DB: Database>(
+/// Implements `transferAndCall` and `transferMultipleAndCall`.
+///
+/// The gas used is charged to the delegating frame before the gas of the call is forwarded.
+fn transfer_and_call<DB: Database>(
     evmctx: &mut InnerEvmContext<DB>,
     inputs: &CallInputs,
-    mut input: Bytes,
+    gas_used: u64,
+    recipient_and_callee: Address,
+    token_transfers: Vec<TokenTransfer>,
+    data: Bytes,
 ) -> PrecompileResult {
     // Make sure that the Call Context is not static
     if inputs.is_static {
         return Err(Error::AttemptedStateChangeDuringStaticCall);
     }
 
-    // Make sure that the caller is a contract
-    let caller = inputs.target_address;
-    if is_address_eoa(evmctx, caller).map_err(|_| Error::UnauthorizedCaller)? {
-        return Err(Error::UnauthorizedCaller);
-    }
-
-    // Extract the recipient's address from the input
-    let recipient_and_callee = consume_address_from(&mut input).map_err(|_| Error::InvalidInput)?;
+    // Make sure that the caller is a contract that delegatecalled the precompile
+    delegating_contract(evmctx, inputs)?;
 
     // Make sure that the callee is a contract
-    if is_address_eoa(evmctx, recipient_and_callee).map_err(|_| Error::InvalidInput)? {
+    if Host::is_contract(evmctx, recipient_and_callee) != Some(true) {
         return Err(Error::InvalidInput);
     }
 
-    // Extract & ignore the token_ids offset
-    let _ = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract & ignore the transfer_amounts offset
-    let _ = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract & ignore the calldata offset from the input
-    let _ = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Extract the length of the token IDs array from the input
-    let token_ids_len = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Create the vector of TokenTransfers with the correct capacity
-    let capacity: usize = token_ids_len.try_into().unwrap_or_default();
-    let mut token_transfers: Vec<TokenTransfer> = Vec::with_capacity(capacity);
-
-    // Extract the token IDs from the input
-    for _ in 0..capacity {
-        let token_id = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-        token_transfers.push(TokenTransfer {
-            id: token_id,
-            amount: U256::ZERO,
-        });
-    }
-
-    // Make sure the token IDs inside the vector are unique
-    if token_transfers.len()
-        != token_transfers
-            .iter()
-            .map(|x| x.id)
-            .collect::<std::collections::HashSet<_>>()
-            .len()
-    {
-        return Err(Error::InvalidInput);
-    }
-
-    // Extract the length of the transfer array from the input
-    let transfer_amounts_len = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-
-    // Make sure the token IDs and transfer amounts arrays have the same length
-    if token_ids_len != transfer_amounts_len {
-        return Err(Error::InvalidInput);
-    }
-
-    // Extract the transfer amounts from the input
-    for transfer in token_transfers.iter_mut() {
-        transfer.amount = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
-    }
+    // Signal to the external context that a Call to the callee must be performed,
+    // transferring the MNTs and passing the calldata to it. The callee's return data
+    // is returned to the caller as the output of this precompile call
+    Ok(ResultOrNewCall::Call(PrimitiveCallInfo {
+        target_address: recipient_and_callee,
+        token_transfers,
+        input_data: data,
+        gas_used,
+    }))
+}
 
-    // Extract the byte size of the calldata from the input
-    let calldata_size = consume_u256_from(&mut input).map_err(|_| Error::InvalidInput)?;
+fn call_value_count(inputs: &CallInputs, gas_used: u64) -> PrecompileResult {
+    let call_values = delegated_call_values(inputs)?;
 
-    let calldata_usize: usize = calldata_size.try_into().unwrap_or_default();
+    let count = U256::from(call_values.count());
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: count.to_be_bytes::<{ U256::BYTES }>().into(),
+    }))
+}
 
-    // Extract the calldata from the input
-    let mut calldata =
-        consume_bytes_from(&mut input, calldata_usize).map_err(|_| Error::InvalidInput)?;
+fn call_value_at(inputs: &CallInputs, gas_used: u64, index: U256) -> PrecompileResult {
+    let call_values = delegated_call_values(inputs)?;
 
-    // if the input has not been fully consumed by this point, it has been ill-formed
-    if !input.is_empty() {
-        return Err(Error::InvalidInput);
-    }
+    // An index past the call values is an invalid input, like an out-of-bounds array access
+    let token = usize::try_from(index)
+        .ok()
+        .and_then(|index| call_values.into_iter().nth(index))
+        .ok_or(Error::InvalidInput)?;
 
-    // Renounce the 28-byte 0 prefix, forming the EVM word together with the 4-byte function selector
-    calldata = calldata[28..].to_vec();
+    // Returned data structure:
+    // 0/0: token id
+    // 1/32: transfer amount
+    let mut data = token.id.to_be_bytes_vec();
+    data.append(token.amount.to_be_bytes_vec().as_mut());
 
-    // Signal to the external context that a Call to the callee must be performed,
-    // transferring the MNTs and passing the calldata to it
-    Ok(ResultOrNewCall::Call(PrimitiveCallInfo {
-        target_address: recipient_and_callee,
-        token_transfers,
-        input_data: calldata.into(),
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: Bytes::from(data),
     }))
 }
 
-fn get_call_values<DB: Database>(
-    evmctx: &mut InnerEvmContext<DB>,
-    inputs: &CallInputs,
-    gas_used: u64,
-) -> PrecompileResult {
-    // Make sure that the Call Context is not static
-    if inputs.is_static {
-        return Err(Error::AttemptedStateChangeDuringStaticCall);
-    }
-
-    // Make sure that the caller is a contract
-    let caller = inputs.target_address;
-    if is_address_eoa(evmctx, caller).map_err(|_| Error::UnauthorizedCaller)? {
-        return Err(Error::UnauthorizedCaller);
-    }
+fn get_call_values(inputs: &CallInputs, gas_used: u64) -> PrecompileResult {
+    let call_values: Vec<&TokenTransfer> = delegated_call_values(inputs)?.collect();
 
     // Returned data structure:
     // 0/0: token ids offset (== 64)
@@ -561,19 +1542,19 @@ fn get_call_values<DB: Database>(
     let token_ids_offset = U256::from(64);
     let mut data = token_ids_offset.to_be_bytes_vec();
 
-    let token_ids_len = U256::from(inputs.call_values().len());
+    let token_ids_len = U256::from(call_values.len());
 
     let evm_word_size = U256::from(32);
     let transfer_amounts_offset = token_ids_offset + evm_word_size + token_ids_len * evm_word_size;
     data.append(transfer_amounts_offset.to_be_bytes_vec().as_mut());
 
     data.append(token_ids_len.to_be_bytes_vec().as_mut());
-    for token in inputs.call_values().iter() {
+    for token in call_values.iter() {
         data.append(token.id.to_be_bytes_vec().as_mut());
     }
 
     data.append(token_ids_len.to_be_bytes_vec().as_mut());
-    for token in inputs.call_values().iter() {
+    for token in call_values.iter() {
         data.append(token.amount.to_be_bytes_vec().as_mut());
     }
 
@@ -582,3 +1563,24 @@ fn get_call_values<DB: Database>(
         returned_bytes: Bytes::from(data),
     }))
 }
+
+/// Returns the ABI encoding of a `bytes[]` return value.
+fn encode_bytes_array(values: &[Bytes]) -> Bytes {
+    // Returned data structure:
+    // 0/0: array offset (== 32)
+    // 1/32: array length
+    // 2+/64+: offsets of the elements, from the first offset
+    // then, for every element: its length, and its bytes padded to a whole number of words
+    let mut data = U256::from(32).to_be_bytes_vec();
+    data.append(U256::from(values.len()).to_be_bytes_vec().as_mut());
+    let mut tail = Vec::new();
+    for value in values {
+        let offset = U256::from(values.len() * 32 + tail.len());
+        data.append(offset.to_be_bytes_vec().as_mut());
+        tail.append(U256::from(value.len()).to_be_bytes_vec().as_mut());
+        tail.extend_from_slice(value);
+        tail.resize(tail.len().next_multiple_of(32), 0);
+    }
+    data.append(&mut tail);
+    Bytes::from(data)
+}
@@ -0,0 +1,774 @@
+//! The ABI of the Native Tokens Precompile: the encoding of the calls to it, and its Solidity
+//! interface.
+//!
+//! A call is built as a [NativeTokenCall] and encoded with [NativeTokenCall::encode], the inverse
+//! of [NativeTokenCall::decode]:
+//!
+//! ```
+//! use revm::{
+//!     primitives::{address, U256},
+//!     sablier::native_tokens::{NativeTokenCall, NativeTokensSelectors},
+//! };
+//!
+//! let call = NativeTokenCall::Transfer {
+//!     recipient: address!("00000000000000000000000000000000000000aa"),
+//!     token_id: U256::from(1),
+//!     amount: U256::from(100),
+//! };
+//! let calldata = call.encode(&NativeTokensSelectors::V1);
+//! assert_eq!(NativeTokenCall::decode(&NativeTokensSelectors::V1, &calldata), Ok(call));
+//! ```
+//!
+//! The Solidity interface of the precompile, `INativeTokens.sol`, is generated from [FUNCTIONS]
+//! by [solidity_interface].
+
+use super::native_tokens::{ForwardedCalldata, NativeTokenCall, NativeTokensSelectors, ADDRESS};
+use crate::primitives::{keccak256, Address, Bytes, TokenTransfer, U256};
+use core::fmt::Write;
+use std::{format, string::String, vec, vec::Vec};
+
+/// A parameter of a function of the precompile: its Solidity type and its name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Param {
+    pub ty: &'static str,
+    pub name: &'static str,
+}
+
+const fn param(ty: &'static str, name: &'static str) -> Param {
+    Param { ty, name }
+}
+
+/// A function of the Native Tokens Precompile, as declared in its Solidity interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NativeTokensFunction {
+    pub name: &'static str,
+    pub inputs: &'static [Param],
+    pub outputs: &'static [Param],
+    /// Whether the function leaves the state unchanged.
+    pub is_view: bool,
+    /// The NatSpec `@notice` of the function.
+    pub notice: &'static str,
+}
+
+impl NativeTokensFunction {
+    /// Returns the canonical signature of the function, e.g. `transfer(address,uint256,uint256)`.
+    pub fn signature(&self) -> String {
+        let types: Vec<&str> = self.inputs.iter().map(|input| input.ty).collect();
+        format!("{}({})", self.name, types.join(","))
+    }
+
+    /// Returns the selector of the function: the first 4 bytes of the hash of its signature.
+    pub fn selector(&self) -> u32 {
+        let hash = keccak256(self.signature());
+        u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+    }
+
+    /// Returns the Solidity declaration of the function.
+    pub fn declaration(&self) -> String {
+        let inputs: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|input| format_param(input, "calldata"))
+            .collect();
+        let mut declaration = format!("function {}({}) external", self.name, inputs.join(", "));
+        if self.is_view {
+            declaration.push_str(" view");
+        }
+        if !self.outputs.is_empty() {
+            let outputs: Vec<String> = self
+                .outputs
+                .iter()
+                .map(|output| format_param(output, "memory"))
+                .collect();
+            let _ = write!(declaration, " returns ({})", outputs.join(", "));
+        }
+        declaration.push(';');
+        declaration
+    }
+}
+
+/// Formats the parameter, in the data location if its type is dynamic.
+fn format_param(param: &Param, location: &str) -> String {
+    let mut formatted = String::from(param.ty);
+    if param.ty.ends_with("[]") || param.ty == "bytes" {
+        formatted.push(' ');
+        formatted.push_str(location);
+    }
+    if !param.name.is_empty() {
+        formatted.push(' ');
+        formatted.push_str(param.name);
+    }
+    formatted
+}
+
+/// The functions of the first version of the ABI, i.e. [NativeTokensSelectors::V1], sorted by
+/// name.
+pub const FUNCTIONS: [NativeTokensFunction; 16] = [
+    NativeTokensFunction {
+        name: "allTokenIDs",
+        inputs: &[param("uint256", "offset"), param("uint256", "limit")],
+        outputs: &[param("uint256[]", "tokenIDs")],
+        is_view: true,
+        notice: "Returns at most `limit` registered token IDs, from the `offset`-th one.",
+    },
+    NativeTokensFunction {
+        name: "allowance",
+        inputs: &[
+            param("address", "owner"),
+            param("address", "spender"),
+            param("uint256", "tokenID"),
+        ],
+        outputs: &[param("uint256", "")],
+        is_view: true,
+        notice: "Returns the amount of the token of the owner that the spender may transfer with \
+                 `transferFrom`.",
+    },
+    NativeTokensFunction {
+        name: "balanceOf",
+        inputs: &[param("address", "account"), param("uint256", "tokenID")],
+        outputs: &[param("uint256", "")],
+        is_view: true,
+        notice: "Returns the balance of the token held by the account.",
+    },
+    NativeTokensFunction {
+        name: "burn",
+        inputs: &[
+            param("uint256", "subID"),
+            param("address", "tokenHolder"),
+            param("uint256", "amount"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Burns the amount of the token with the sub-ID, minted by the calling contract, \
+                 from the balance of the holder.",
+    },
+    NativeTokensFunction {
+        name: "callValueAt",
+        inputs: &[param("uint256", "index")],
+        outputs: &[param("uint256", "tokenID"), param("uint256", "amount")],
+        is_view: true,
+        notice: "Returns the `index`-th token transferred to the calling contract by its call.",
+    },
+    NativeTokensFunction {
+        name: "callValueCount",
+        inputs: &[],
+        outputs: &[param("uint256", "")],
+        is_view: true,
+        notice: "Returns the number of tokens transferred to the calling contract by its call.",
+    },
+    NativeTokensFunction {
+        name: "getCallValues",
+        inputs: &[],
+        outputs: &[
+            param("uint256[]", "tokenIDs"),
+            param("uint256[]", "amounts"),
+        ],
+        is_view: true,
+        notice: "Returns the tokens transferred to the calling contract by its call.",
+    },
+    NativeTokensFunction {
+        name: "mint",
+        inputs: &[
+            param("uint256", "subID"),
+            param("address", "recipient"),
+            param("uint256", "amount"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Mints the amount of the token with the sub-ID of the calling contract to the \
+                 recipient.",
+    },
+    NativeTokensFunction {
+        name: "multicall",
+        inputs: &[param("bytes[]", "calls")],
+        outputs: &[param("bytes[]", "results")],
+        is_view: false,
+        notice: "Makes the calls to the precompile in their order, and returns what they return. \
+                 If any of them fails, none of them changes the state.",
+    },
+    NativeTokensFunction {
+        name: "nonces",
+        inputs: &[param("address", "owner")],
+        outputs: &[param("uint256", "")],
+        is_view: true,
+        notice: "Returns the nonce of the next permit signed by the owner.",
+    },
+    NativeTokensFunction {
+        name: "permit",
+        inputs: &[
+            param("address", "owner"),
+            param("address", "spender"),
+            param("uint256", "tokenID"),
+            param("uint256", "amount"),
+            param("uint256", "deadline"),
+            param("bytes", "signature"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Sets the allowance of the spender to the amount of the token of the owner, with \
+                 the EIP-712 signature `r ++ s ++ v` of the owner's permit.",
+    },
+    NativeTokensFunction {
+        name: "transfer",
+        inputs: &[
+            param("address", "to"),
+            param("uint256", "tokenID"),
+            param("uint256", "amount"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Transfers the amount of the token from the calling contract to `to`.",
+    },
+    NativeTokensFunction {
+        name: "transferAndCall",
+        inputs: &[
+            param("address", "recipientAndCallee"),
+            param("uint256", "tokenID"),
+            param("uint256", "amount"),
+            param("bytes", "data"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Transfers the amount of the token from the calling contract to the callee, and \
+                 calls it with `data`, returning what it returns.",
+    },
+    NativeTokensFunction {
+        name: "transferFrom",
+        inputs: &[
+            param("address", "from"),
+            param("address", "to"),
+            param("uint256", "tokenID"),
+            param("uint256", "amount"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Transfers the amount of the token from `from` to `to`, spending the allowance of \
+                 the calling contract.",
+    },
+    NativeTokensFunction {
+        name: "transferMultiple",
+        inputs: &[
+            param("address", "to"),
+            param("uint256[]", "tokenIDs"),
+            param("uint256[]", "amounts"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Transfers the amounts of the tokens from the calling contract to `to`.",
+    },
+    NativeTokensFunction {
+        name: "transferMultipleAndCall",
+        inputs: &[
+            param("address", "recipientAndCallee"),
+            param("uint256[]", "tokenIDs"),
+            param("uint256[]", "amounts"),
+            param("bytes", "data"),
+        ],
+        outputs: &[],
+        is_view: false,
+        notice: "Transfers the amounts of the tokens from the calling contract to the callee, \
+                 and calls it with `data`, returning what it returns.",
+    },
+];
+
+/// Returns the Solidity interface of the precompile, i.e. the content of `INativeTokens.sol`.
+pub fn solidity_interface() -> String {
+    let mut interface = String::new();
+    let _ = writeln!(interface, "// SPDX-License-Identifier: MIT");
+    let _ = writeln!(
+        interface,
+        "// Generated by `revm::sablier::native_tokens_abi`."
+    );
+    let _ = writeln!(interface, "pragma solidity >=0.8.0;");
+    let _ = writeln!(interface);
+    let _ = writeln!(interface, "/// @title INativeTokens");
+    let _ = writeln!(
+        interface,
+        "/// @notice The Native Tokens Precompile, at {ADDRESS}."
+    );
+    let _ = writeln!(
+        interface,
+        "/// @dev The precompile acts on behalf of the contract that calls it, so it has to be \
+         called with `delegatecall`,"
+    );
+    let _ = writeln!(
+        interface,
+        "/// e.g. with the calldata made by `abi.encodeCall`. The `data` of `transferAndCall` and \
+         `transferMultipleAndCall`"
+    );
+    let _ = writeln!(
+        interface,
+        "/// is the calldata of the callee prefixed with 28 zero bytes, so that its selector ends \
+         a word."
+    );
+    let _ = writeln!(interface, "interface INativeTokens {{");
+    for (index, function) in FUNCTIONS.iter().enumerate() {
+        if index > 0 {
+            let _ = writeln!(interface);
+        }
+        let _ = writeln!(interface, "    /// @notice {}", function.notice);
+        let _ = writeln!(interface, "    {}", function.declaration());
+    }
+    let _ = writeln!(interface, "}}");
+    interface
+}
+
+/// An argument of a call to a precompile, ABI-encoded by [encode_call].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiArg {
+    /// `address`
+    Address(Address),
+    /// `uint256`
+    Uint(U256),
+    /// `uint256[]`
+    UintArray(Vec<U256>),
+    /// The calldata of a callee, passed as the version of the ABI forwards it.
+    Calldata(Bytes, ForwardedCalldata),
+    /// `bytes`, passed as they are, e.g. a signature.
+    Bytes(Bytes),
+    /// `bytes[]`, passed as they are, e.g. the calldata of batched calls.
+    BytesArray(Vec<Bytes>),
+}
+
+impl AbiArg {
+    /// Returns the `uint256[] tokenIDs` and `uint256[] amounts` arguments of the transfers.
+    pub fn token_transfers(transfers: &[TokenTransfer]) -> [Self; 2] {
+        [
+            Self::UintArray(transfers.iter().map(|transfer| transfer.id).collect()),
+            Self::UintArray(transfers.iter().map(|transfer| transfer.amount).collect()),
+        ]
+    }
+}
+
+impl NativeTokenCall {
+    /// Returns the selector of the call in the version of the ABI.
+    pub fn selector(&self, selectors: &NativeTokensSelectors) -> u32 {
+        match self {
+            Self::AllTokenIds { .. } => selectors.all_token_ids,
+            Self::Allowance { .. } => selectors.allowance,
+            Self::BalanceOf { .. } => selectors.balance_of,
+            Self::Burn { .. } => selectors.burn,
+            Self::CallValueAt { .. } => selectors.call_value_at,
+            Self::CallValueCount => selectors.call_value_count,
+            Self::GetCallValues => selectors.get_call_values,
+            Self::Mint { .. } => selectors.mint,
+            Self::Multicall { .. } => selectors.multicall,
+            Self::Nonces { .. } => selectors.nonces,
+            Self::Permit { .. } => selectors.permit,
+            Self::Transfer { .. } => selectors.transfer,
+            Self::TransferAndCall { .. } => selectors.transfer_and_call,
+            Self::TransferMultiple { .. } => selectors.transfer_multiple,
+            Self::TransferMultipleAndCall { .. } => selectors.transfer_multiple_and_call,
+            Self::TransferFrom { .. } => selectors.transfer_from,
+        }
+    }
+
+    /// Encodes the calldata of the call to a Native Tokens Precompile answering to `selectors`.
+    ///
+    /// The `data` of `TransferAndCall` and `TransferMultipleAndCall` is the calldata of the
+    /// callee, passed as the [ForwardedCalldata] of the `selectors` says and padded to a whole
+    /// number of words. Prefixed with a [ForwardedCalldata::SelectorWord] in
+    /// [AbiMode](super::native_tokens::AbiMode)`::Sequential`, it has to be made of a selector
+    /// followed by whole words, which needs no padding.
+    pub fn encode(&self, selectors: &NativeTokensSelectors) -> Bytes {
+        let args = match self {
+            Self::AllTokenIds { offset, limit } => {
+                vec![AbiArg::Uint(*offset), AbiArg::Uint(*limit)]
+            }
+            Self::Allowance {
+                owner,
+                spender,
+                token_id,
+            } => vec![
+                AbiArg::Address(*owner),
+                AbiArg::Address(*spender),
+                AbiArg::Uint(*token_id),
+            ],
+            Self::BalanceOf { account, token_id } => {
+                vec![AbiArg::Address(*account), AbiArg::Uint(*token_id)]
+            }
+            Self::Burn {
+                sub_id,
+                token_holder,
+                amount,
+            } => vec![
+                AbiArg::Uint(*sub_id),
+                AbiArg::Address(*token_holder),
+                AbiArg::Uint(*amount),
+            ],
+            Self::CallValueAt { index } => vec![AbiArg::Uint(*index)],
+            Self::CallValueCount | Self::GetCallValues => vec![],
+            Self::Mint {
+                sub_id,
+                recipient,
+                amount,
+            } => vec![
+                AbiArg::Uint(*sub_id),
+                AbiArg::Address(*recipient),
+                AbiArg::Uint(*amount),
+            ],
+            Self::Multicall { calls } => vec![AbiArg::BytesArray(
+                calls.iter().map(|call| call.encode(selectors)).collect(),
+            )],
+            Self::Nonces { owner } => vec![AbiArg::Address(*owner)],
+            Self::Permit {
+                owner,
+                spender,
+                token_id,
+                amount,
+                deadline,
+                signature,
+            } => vec![
+                AbiArg::Address(*owner),
+                AbiArg::Address(*spender),
+                AbiArg::Uint(*token_id),
+                AbiArg::Uint(*amount),
+                AbiArg::Uint(*deadline),
+                AbiArg::Bytes(signature.clone()),
+            ],
+            Self::Transfer {
+                recipient,
+                token_id,
+                amount,
+            } => vec![
+                AbiArg::Address(*recipient),
+                AbiArg::Uint(*token_id),
+                AbiArg::Uint(*amount),
+            ],
+            Self::TransferFrom {
+                sender,
+                recipient,
+                token_id,
+                amount,
+            } => vec![
+                AbiArg::Address(*sender),
+                AbiArg::Address(*recipient),
+                AbiArg::Uint(*token_id),
+                AbiArg::Uint(*amount),
+            ],
+            Self::TransferAndCall {
+                recipient_and_callee,
+                token_id,
+                amount,
+                data,
+            } => vec![
+                AbiArg::Address(*recipient_and_callee),
+                AbiArg::Uint(*token_id),
+                AbiArg::Uint(*amount),
+                AbiArg::Calldata(data.clone(), selectors.forwarded_calldata),
+            ],
+            Self::TransferMultiple {
+                recipient,
+                transfers,
+            } => {
+                let [token_ids, amounts] = AbiArg::token_transfers(transfers);
+                vec![AbiArg::Address(*recipient), token_ids, amounts]
+            }
+            Self::TransferMultipleAndCall {
+                recipient_and_callee,
+                transfers,
+                data,
+            } => {
+                let [token_ids, amounts] = AbiArg::token_transfers(transfers);
+                vec![
+                    AbiArg::Address(*recipient_and_callee),
+                    token_ids,
+                    amounts,
+                    AbiArg::Calldata(data.clone(), selectors.forwarded_calldata),
+                ]
+            }
+        };
+        encode_call(self.selector(selectors), &args)
+    }
+}
+
+/// Returns the calldata of a call to the function with the selector: the static arguments and
+/// the offsets of the dynamic ones in the head, followed by the dynamic arguments in the tail.
+pub fn encode_call(selector: u32, args: &[AbiArg]) -> Bytes {
+    let mut head = selector.to_be_bytes().to_vec();
+    let mut tail = Vec::new();
+    let head_len = args.len() * 32;
+    for arg in args {
+        match arg {
+            AbiArg::Address(address) => head.extend_from_slice(address.into_word().as_slice()),
+            AbiArg::Uint(value) => head.extend_from_slice(&value.to_be_bytes::<32>()),
+            AbiArg::UintArray(_)
+            | AbiArg::Calldata(..)
+            | AbiArg::Bytes(_)
+            | AbiArg::BytesArray(_) => {
+                let offset = U256::from(head_len + tail.len());
+                head.extend_from_slice(&offset.to_be_bytes::<32>());
+            }
+        }
+        match arg {
+            AbiArg::Address(_) | AbiArg::Uint(_) => {}
+            AbiArg::UintArray(values) => {
+                tail.extend_from_slice(&U256::from(values.len()).to_be_bytes::<32>());
+                for value in values {
+                    tail.extend_from_slice(&value.to_be_bytes::<32>());
+                }
+            }
+            AbiArg::Calldata(data, _) | AbiArg::Bytes(data) => {
+                let prefix: &[u8] = match arg {
+                    AbiArg::Calldata(_, ForwardedCalldata::SelectorWord) => &[0; 28],
+                    _ => &[],
+                };
+                let len = prefix.len() + data.len();
+                tail.extend_from_slice(&U256::from(len).to_be_bytes::<32>());
+                tail.extend_from_slice(prefix);
+                tail.extend_from_slice(data);
+                // padded to a whole number of words
+                tail.resize(tail.len().next_multiple_of(32), 0);
+            }
+            AbiArg::BytesArray(values) => {
+                // the offsets of the elements, from the first offset, then the elements
+                tail.extend_from_slice(&U256::from(values.len()).to_be_bytes::<32>());
+                let mut elements = Vec::new();
+                for value in values {
+                    let offset = U256::from(values.len() * 32 + elements.len());
+                    tail.extend_from_slice(&offset.to_be_bytes::<32>());
+                    elements.extend_from_slice(&U256::from(value.len()).to_be_bytes::<32>());
+                    elements.extend_from_slice(value);
+                    elements.resize(elements.len().next_multiple_of(32), 0);
+                }
+                tail.extend(elements);
+            }
+        }
+    }
+    head.extend(tail);
+    head.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitives::address, sablier::native_tokens::AbiMode};
+
+    #[test]
+    fn functions_match_the_selectors() {
+        let selectors = FUNCTIONS.map(|function| function.selector());
+        let v1 = NativeTokensSelectors::V1;
+        assert_eq!(
+            selectors,
+            [
+                v1.all_token_ids,
+                v1.allowance,
+                v1.balance_of,
+                v1.burn,
+                v1.call_value_at,
+                v1.call_value_count,
+                v1.get_call_values,
+                v1.mint,
+                v1.multicall,
+                v1.nonces,
+                v1.permit,
+                v1.transfer,
+                v1.transfer_and_call,
+                v1.transfer_from,
+                v1.transfer_multiple,
+                v1.transfer_multiple_and_call,
+            ]
+        );
+    }
+
+    #[test]
+    fn interface_file_is_up_to_date() {
+        assert_eq!(include_str!("INativeTokens.sol"), solidity_interface());
+    }
+
+    #[test]
+    fn encoded_calls_decode_to_themselves() {
+        let account = address!("00000000000000000000000000000000000000aa");
+        let transfers = vec![
+            TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(2),
+            },
+            TokenTransfer {
+                id: U256::from(3),
+                amount: U256::from(4),
+            },
+        ];
+        // a selector followed by a word
+        let data = Bytes::from([[0x12, 0x34, 0x56, 0x78].as_slice(), &[0xab; 32]].concat());
+        let calls = [
+            NativeTokenCall::AllTokenIds {
+                offset: U256::from(5),
+                limit: U256::from(10),
+            },
+            NativeTokenCall::Allowance {
+                owner: account,
+                spender: account,
+                token_id: U256::from(1),
+            },
+            NativeTokenCall::BalanceOf {
+                account,
+                token_id: U256::from(1),
+            },
+            NativeTokenCall::Burn {
+                sub_id: U256::from(1),
+                token_holder: account,
+                amount: U256::from(2),
+            },
+            NativeTokenCall::CallValueAt {
+                index: U256::from(1),
+            },
+            NativeTokenCall::CallValueCount,
+            NativeTokenCall::GetCallValues,
+            NativeTokenCall::Mint {
+                sub_id: U256::from(1),
+                recipient: account,
+                amount: U256::from(2),
+            },
+            NativeTokenCall::Multicall {
+                calls: vec![
+                    NativeTokenCall::Transfer {
+                        recipient: account,
+                        token_id: U256::from(1),
+                        amount: U256::from(2),
+                    },
+                    NativeTokenCall::BalanceOf {
+                        account,
+                        token_id: U256::from(1),
+                    },
+                ],
+            },
+            NativeTokenCall::Nonces { owner: account },
+            NativeTokenCall::Permit {
+                owner: account,
+                spender: account,
+                token_id: U256::from(1),
+                amount: U256::from(2),
+                deadline: U256::from(3),
+                signature: Bytes::from([0xcd; 65]),
+            },
+            NativeTokenCall::Transfer {
+                recipient: account,
+                token_id: U256::from(1),
+                amount: U256::from(2),
+            },
+            NativeTokenCall::TransferAndCall {
+                recipient_and_callee: account,
+                token_id: U256::from(1),
+                amount: U256::from(2),
+                data: data.clone(),
+            },
+            NativeTokenCall::TransferFrom {
+                sender: account,
+                recipient: account,
+                token_id: U256::from(1),
+                amount: U256::from(2),
+            },
+            NativeTokenCall::TransferMultiple {
+                recipient: account,
+                transfers: transfers.clone(),
+            },
+            NativeTokenCall::TransferMultipleAndCall {
+                recipient_and_callee: account,
+                transfers,
+                data,
+            },
+        ];
+
+        for (abi_mode, forwarded_calldata) in [
+            (AbiMode::Sequential, ForwardedCalldata::SelectorWord),
+            (AbiMode::Lenient, ForwardedCalldata::SelectorWord),
+            (AbiMode::Sequential, ForwardedCalldata::Raw),
+            (AbiMode::Lenient, ForwardedCalldata::Raw),
+        ] {
+            let selectors = NativeTokensSelectors {
+                abi_mode,
+                forwarded_calldata,
+                ..NativeTokensSelectors::V1
+            };
+            for call in &calls {
+                let calldata = call.encode(&selectors);
+                assert_eq!((calldata.len() - 4) % 32, 0, "{call:?}");
+                assert_eq!(
+                    NativeTokenCall::decode(&selectors, &calldata).as_ref(),
+                    Ok(call)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn raw_calldata_of_any_length_decodes_to_itself() {
+        for abi_mode in [AbiMode::Sequential, AbiMode::Lenient] {
+            let selectors = NativeTokensSelectors {
+                abi_mode,
+                forwarded_calldata: ForwardedCalldata::Raw,
+                ..NativeTokensSelectors::V1
+            };
+            for len in [0, 1, 4, 31, 32, 33, 100] {
+                let call = NativeTokenCall::TransferAndCall {
+                    recipient_and_callee: address!("00000000000000000000000000000000000000bb"),
+                    token_id: U256::from(1),
+                    amount: U256::from(2),
+                    data: (0..len).map(|byte| byte as u8 + 1).collect(),
+                };
+                let calldata = call.encode(&selectors);
+                assert_eq!((calldata.len() - 4) % 32, 0);
+                assert_eq!(NativeTokenCall::decode(&selectors, &calldata), Ok(call));
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_transfer_multiple_and_call() {
+        let callee = address!("00000000000000000000000000000000000000bb");
+        let call = NativeTokenCall::TransferMultipleAndCall {
+            recipient_and_callee: callee,
+            transfers: vec![TokenTransfer {
+                id: U256::from(7),
+                amount: U256::from(9),
+            }],
+            data: Bytes::from_static(&[0x12, 0x34, 0x56, 0x78]),
+        };
+        let calldata = call.encode(&NativeTokensSelectors::V1);
+
+        let word =
+            |index: usize| U256::from_be_slice(&calldata[4 + index * 32..4 + (index + 1) * 32]);
+        assert_eq!(&calldata[..4], &[0x82, 0x2b, 0xbe, 0x4c]);
+        assert_eq!(word(0), U256::from_be_slice(callee.as_slice()));
+        // the arrays and the data follow the four words of the head
+        assert_eq!(word(1), U256::from(128));
+        assert_eq!(word(2), U256::from(192));
+        assert_eq!(word(3), U256::from(256));
+        assert_eq!(word(4), U256::from(1));
+        assert_eq!(word(5), U256::from(7));
+        assert_eq!(word(6), U256::from(1));
+        assert_eq!(word(7), U256::from(9));
+        // the data is the selector of the callee, as a word
+        assert_eq!(word(8), U256::from(32));
+        assert_eq!(word(9), U256::from(0x12345678));
+        assert_eq!(calldata.len(), 4 + 10 * 32);
+    }
+
+    #[test]
+    fn encodes_dynamic_arguments_after_the_head() {
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let calldata = encode_call(
+            0x822bbe4c,
+            &[
+                AbiArg::Address(recipient),
+                AbiArg::UintArray(vec![U256::from(1)]),
+                AbiArg::Bytes(Bytes::from_static(&[0xab])),
+            ],
+        );
+
+        let word =
+            |index: usize| U256::from_be_slice(&calldata[4 + index * 32..4 + (index + 1) * 32]);
+        assert_eq!(&calldata[..4], &[0x82, 0x2b, 0xbe, 0x4c]);
+        assert_eq!(word(0), U256::from_be_slice(recipient.as_slice()));
+        // the array starts after the three words of the head
+        assert_eq!(word(1), U256::from(96));
+        // the bytes start after the two words of the array
+        assert_eq!(word(2), U256::from(160));
+        assert_eq!(word(3), U256::from(1));
+        assert_eq!(word(4), U256::from(1));
+        assert_eq!(word(5), U256::from(1));
+        assert_eq!(calldata[4 + 6 * 32], 0xab);
+        assert_eq!(calldata.len(), 4 + 7 * 32);
+    }
+}
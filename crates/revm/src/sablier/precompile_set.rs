@@ -0,0 +1,177 @@
+use crate::{
+    handler::register::EvmHandler,
+    primitives::{Address, HashMap, SpecId},
+    sablier::{
+        native_tokens::{self, NativeTokensContextPrecompile, NativeTokensSelectors},
+        streams::StreamsContextPrecompile,
+    },
+    ContextPrecompile, Database,
+};
+use std::{boxed::Box, sync::Arc, vec::Vec};
+
+/// A Sablier precompile and the version of its ABI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SablierPrecompile {
+    /// The Native Tokens Precompile, answering to the given selectors.
+    NativeTokens(NativeTokensSelectors),
+    /// The Streams Precompile, see [streams](crate::sablier::streams).
+    Streams,
+}
+
+impl SablierPrecompile {
+    /// Returns the precompile as it is loaded into the EVM.
+    pub fn into_context_precompile<DB: Database>(self) -> ContextPrecompile<DB> {
+        match self {
+            Self::NativeTokens(selectors) => ContextPrecompile::ContextStatefulMut(Box::new(
+                NativeTokensContextPrecompile::new(selectors),
+            )),
+            Self::Streams => {
+                ContextPrecompile::ContextStatefulMut(Box::new(StreamsContextPrecompile))
+            }
+        }
+    }
+}
+
+/// Registry of the Sablier precompiles installed at each hardfork.
+///
+/// A precompile is installed at an address from a hardfork on, replacing whatever an earlier
+/// hardfork installed at that address. This lets a chain revise an ABI, e.g. by serving a new
+/// version of the Native Tokens Precompile at `0x7060000000000000000000000000000000000002`, while replaying
+/// old blocks with the precompiles they were executed with.
+///
+/// The set is installed with [`EvmBuilder::with_sablier_precompiles`](crate::EvmBuilder::with_sablier_precompiles).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SablierPrecompileSet {
+    /// The hardfork, address and precompile of every installation, or `None` for removals.
+    installations: Vec<(SpecId, Address, Option<SablierPrecompile>)>,
+}
+
+impl Default for SablierPrecompileSet {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+impl SablierPrecompileSet {
+    /// Creates a set without precompiles.
+    pub fn new() -> Self {
+        Self {
+            installations: Vec::new(),
+        }
+    }
+
+    /// Creates the set loaded by the mainnet handler: the first version of the Native Tokens
+    /// Precompile at [`native_tokens::ADDRESS`] in every hardfork.
+    pub fn mainnet() -> Self {
+        Self::new().with_precompile(
+            SpecId::FRONTIER,
+            native_tokens::ADDRESS,
+            SablierPrecompile::NativeTokens(NativeTokensSelectors::V1),
+        )
+    }
+
+    /// Installs the precompile at the address from the hardfork on.
+    pub fn with_precompile(
+        mut self,
+        spec_id: SpecId,
+        address: Address,
+        precompile: SablierPrecompile,
+    ) -> Self {
+        self.installations
+            .push((spec_id, address, Some(precompile)));
+        self
+    }
+
+    /// Removes the precompile at the address from the hardfork on.
+    pub fn without_precompile(mut self, spec_id: SpecId, address: Address) -> Self {
+        self.installations.push((spec_id, address, None));
+        self
+    }
+
+    /// Returns the precompiles installed at the hardfork.
+    pub fn active(&self, spec_id: SpecId) -> HashMap<Address, SablierPrecompile> {
+        let mut installations: Vec<_> = self
+            .installations
+            .iter()
+            .filter(|(since, ..)| SpecId::enabled(spec_id, *since))
+            .collect();
+        // later hardforks override earlier ones, the sort is stable for the same hardfork
+        installations.sort_by_key(|(since, ..)| *since);
+
+        let mut active = HashMap::new();
+        for (_, address, precompile) in installations {
+            match precompile {
+                Some(precompile) => active.insert(*address, *precompile),
+                None => active.remove(address),
+            };
+        }
+        active
+    }
+
+    /// Returns the precompiles installed at the hardfork, ready to be loaded into the EVM.
+    pub fn precompiles<DB: Database>(
+        &self,
+        spec_id: SpecId,
+    ) -> impl Iterator<Item = (Address, ContextPrecompile<DB>)> {
+        self.active(spec_id)
+            .into_iter()
+            .map(|(address, precompile)| (address, precompile.into_context_precompile()))
+    }
+
+    /// Makes the handler load the precompiles of this set instead of the
+    /// [mainnet](Self::mainnet) ones.
+    pub fn register<EXT, DB: Database>(&self, handler: &mut EvmHandler<'_, EXT, DB>) {
+        let spec_id = handler.cfg.spec_id;
+        let load_precompiles = handler.pre_execution.load_precompiles.clone();
+        let set = self.clone();
+        handler.pre_execution.load_precompiles = Arc::new(move || {
+            let mut precompiles = load_precompiles();
+            for address in Self::mainnet().active(spec_id).keys() {
+                precompiles.remove(address);
+            }
+            precompiles.extend(set.precompiles(spec_id));
+            precompiles
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sablier::u64_to_prefixed_address;
+
+    const V2: NativeTokensSelectors = NativeTokensSelectors {
+        transfer: 0xa9059cbb,
+        ..NativeTokensSelectors::V1
+    };
+
+    #[test]
+    fn later_hardforks_override_earlier_ones() {
+        let v2_address = u64_to_prefixed_address(2);
+        let set = SablierPrecompileSet::mainnet()
+            .with_precompile(
+                SpecId::PRAGUE,
+                v2_address,
+                SablierPrecompile::NativeTokens(V2),
+            )
+            .without_precompile(SpecId::PRAGUE, native_tokens::ADDRESS);
+
+        let cancun = set.active(SpecId::CANCUN);
+        assert_eq!(cancun.len(), 1);
+        assert_eq!(
+            cancun[&native_tokens::ADDRESS],
+            SablierPrecompile::NativeTokens(NativeTokensSelectors::V1)
+        );
+
+        let prague = set.active(SpecId::PRAGUE);
+        assert_eq!(prague.len(), 1);
+        assert_eq!(prague[&v2_address], SablierPrecompile::NativeTokens(V2));
+    }
+
+    #[test]
+    fn empty_set_has_no_precompiles() {
+        assert!(SablierPrecompileSet::new()
+            .active(SpecId::LATEST)
+            .is_empty());
+    }
+}
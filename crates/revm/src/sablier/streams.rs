@@ -0,0 +1,479 @@
+//! The Streams Precompile, which streams Native Tokens from a sender to a recipient linearly over
+//! time, so that the core of the Sablier protocol is offered natively by the VM.
+//!
+//! A stream is created by calling `createStream` with the streamed tokens as the call value. The
+//! precompile holds the tokens in escrow, and `withdraw` sends the recipient the tokens streamed
+//! so far. The streams are kept in the storage of the precompile, laid out like the storage of
+//! this Solidity contract:
+//!
+//! ```solidity
+//! contract Streams {
+//!     struct Stream {
+//!         address sender;
+//!         address recipient;
+//!         uint256 tokenId;
+//!         uint256 amount;
+//!         uint256 startTime;
+//!         uint256 endTime;
+//!         uint256 withdrawnAmount;
+//!     }
+//!
+//!     // slot 0, the ID of the last created stream
+//!     uint256 lastStreamId;
+//!     // slot 1
+//!     mapping(uint256 streamId => Stream) streams;
+//! }
+//! ```
+use crate::{
+    interpreter::{gas, CallInputs, CallScheme},
+    precompile::{Error, PrecompileResult, ResultInfo, ResultOrNewCall},
+    primitives::{ruint::aliases::U512, utilities::bytes_parsing::*, Address, TokenTransfer, U256},
+    sablier::utilities::{
+        charge, consume_address, consume_u256, ensure_consumed, mapping_slot, sload, sstore,
+    },
+    ContextStatefulPrecompileMut, Database, InnerEvmContext,
+};
+
+pub const ADDRESS: Address = crate::sablier::u64_to_prefixed_address(3);
+
+/// The gas cost of every call, before the cost of its storage accesses.
+pub const BASE_GAS_COST: u64 = 15;
+
+/// The gas cost of reading a storage slot of the precompile.
+pub const SLOT_READ_GAS_COST: u64 = gas::COLD_SLOAD_COST;
+
+/// The gas cost of writing a new storage slot of the precompile.
+pub const SLOT_SET_GAS_COST: u64 = gas::SSTORE_SET;
+
+/// The gas cost of updating a storage slot of the precompile.
+pub const SLOT_UPDATE_GAS_COST: u64 = gas::SSTORE_RESET;
+
+// The function selector of `createStream(address recipient, uint256 startTime, uint256 endTime)`
+pub const CREATE_STREAM_SELECTOR: u32 = 0xcd9e3fe8;
+
+// The function selector of `withdrawableAmountOf(uint256 streamId)`
+pub const WITHDRAWABLE_AMOUNT_OF_SELECTOR: u32 = 0xd975dfed;
+
+// The function selector of `withdraw(uint256 streamId)`
+pub const WITHDRAW_SELECTOR: u32 = 0x2e1a7d4d;
+
+/// The slot of `lastStreamId`.
+const LAST_STREAM_ID_SLOT: U256 = U256::ZERO;
+
+/// The slot of the `streams` mapping.
+const STREAMS_SLOT: U256 = U256::from_limbs([1, 0, 0, 0]);
+
+/// The number of storage slots of a stream.
+const STREAM_SLOTS: u64 = 7;
+
+/// The offset of the slot of `withdrawnAmount` from the first slot of a stream.
+const WITHDRAWN_AMOUNT_OFFSET: u64 = 6;
+
+/// A stream of tokens, as it is stored by the precompile.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stream {
+    pub sender: Address,
+    pub recipient: Address,
+    pub token_id: U256,
+    /// The amount of tokens streamed over the whole stream.
+    pub amount: U256,
+    pub start_time: U256,
+    pub end_time: U256,
+    /// The amount of tokens already withdrawn by the recipient.
+    pub withdrawn_amount: U256,
+}
+
+impl Stream {
+    /// Returns the amount of tokens streamed at the timestamp, growing linearly from zero at the
+    /// start time to the whole amount at the end time.
+    pub fn streamed_amount(&self, timestamp: U256) -> U256 {
+        if timestamp <= self.start_time {
+            return U256::ZERO;
+        }
+        if timestamp >= self.end_time {
+            return self.amount;
+        }
+        let elapsed = timestamp - self.start_time;
+        let duration = self.end_time - self.start_time;
+        // the product can't overflow 512 bits, and the quotient is less than the amount
+        let streamed: U512 = self.amount.widening_mul(elapsed) / U512::from(duration);
+        streamed.to()
+    }
+
+    /// Returns the amount of tokens streamed at the timestamp and not withdrawn yet.
+    pub fn withdrawable_amount(&self, timestamp: U256) -> U256 {
+        self.streamed_amount(timestamp) - self.withdrawn_amount
+    }
+
+    /// Returns the slot of the first field of the stream, `streams[stream_id]`.
+    fn slot(stream_id: U256) -> U256 {
+        mapping_slot(STREAMS_SLOT, stream_id)
+    }
+
+    /// Returns the fields of the stream, in the order they are stored.
+    fn fields(&self) -> [U256; STREAM_SLOTS as usize] {
+        [
+            self.sender.into_word().into(),
+            self.recipient.into_word().into(),
+            self.token_id,
+            self.amount,
+            self.start_time,
+            self.end_time,
+            self.withdrawn_amount,
+        ]
+    }
+
+    fn from_fields(fields: [U256; STREAM_SLOTS as usize]) -> Self {
+        let address = |field: U256| Address::from_word(field.into());
+        Self {
+            sender: address(fields[0]),
+            recipient: address(fields[1]),
+            token_id: fields[2],
+            amount: fields[3],
+            start_time: fields[4],
+            end_time: fields[5],
+            withdrawn_amount: fields[6],
+        }
+    }
+}
+
+/// The Context Stateful Precompile that implements the streaming of Native Tokens.
+///
+/// It isn't installed by the mainnet handler. A chain offering streaming installs it with
+/// [SablierPrecompileSet::with_precompile](crate::sablier::SablierPrecompileSet::with_precompile).
+#[derive(Clone, Debug, Default)]
+pub struct StreamsContextPrecompile;
+
+impl<DB: Database> ContextStatefulPrecompileMut<DB> for StreamsContextPrecompile {
+    fn call_mut(
+        &mut self,
+        inputs: &CallInputs,
+        gas_limit: u64,
+        evmctx: &mut InnerEvmContext<DB>,
+    ) -> PrecompileResult {
+        let gas_used = BASE_GAS_COST;
+        if gas_used > gas_limit {
+            return Err(Error::OutOfGas);
+        }
+
+        let mut input = inputs.input.clone();
+        let input = &mut input;
+        let function_selector = consume_u32_from(input).map_err(|_| Error::InvalidInput)?;
+
+        // Only the deposit of a stream is kept by the precompile, the tokens sent with any other
+        // call would be stranded.
+        if function_selector != CREATE_STREAM_SELECTOR && inputs.transfers_value() {
+            return Err(Error::InvalidInput);
+        }
+
+        match function_selector {
+            CREATE_STREAM_SELECTOR => {
+                let recipient = consume_address(input)?;
+                let start_time = consume_u256(input)?;
+                let end_time = consume_u256(input)?;
+                ensure_consumed(input)?;
+                create_stream(
+                    evmctx, inputs, gas_used, gas_limit, recipient, start_time, end_time,
+                )
+            }
+            WITHDRAWABLE_AMOUNT_OF_SELECTOR => {
+                let stream_id = consume_u256(input)?;
+                ensure_consumed(input)?;
+                withdrawable_amount_of(evmctx, gas_used, gas_limit, stream_id)
+            }
+            WITHDRAW_SELECTOR => {
+                let stream_id = consume_u256(input)?;
+                ensure_consumed(input)?;
+                withdraw(evmctx, inputs, gas_used, gas_limit, stream_id)
+            }
+            _ => Err(Error::InvalidInput),
+        }
+    }
+
+    fn accepts_token_transfers(&self) -> bool {
+        true
+    }
+}
+
+/// Makes sure that the call changes the state of the precompile itself.
+///
+/// The deposit of a stream is transferred to the target of the call, which is the precompile only
+/// when it is called with `CALL`.
+fn ensure_state_changing_call(inputs: &CallInputs) -> Result<(), Error> {
+    if inputs.is_static {
+        return Err(Error::AttemptedStateChangeDuringStaticCall);
+    }
+    if inputs.scheme != CallScheme::Call {
+        return Err(Error::UnauthorizedCaller);
+    }
+    Ok(())
+}
+
+/// Reads the stream, failing if it doesn't exist.
+fn read_stream<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    stream_id: U256,
+) -> Result<Stream, Error> {
+    let slot = Stream::slot(stream_id);
+    let mut fields = [U256::ZERO; STREAM_SLOTS as usize];
+    for (offset, field) in fields.iter_mut().enumerate() {
+        *field = sload(evmctx, ADDRESS, slot + U256::from(offset))?;
+    }
+    let stream = Stream::from_fields(fields);
+    // every stream has a recipient
+    if stream.recipient.is_zero() {
+        return Err(Error::InvalidInput);
+    }
+    Ok(stream)
+}
+
+fn create_stream<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    inputs: &CallInputs,
+    mut gas_used: u64,
+    gas_limit: u64,
+    recipient: Address,
+    start_time: U256,
+    end_time: U256,
+) -> PrecompileResult {
+    ensure_state_changing_call(inputs)?;
+    if recipient.is_zero() || start_time >= end_time {
+        return Err(Error::InvalidInput);
+    }
+
+    // The deposit is the only token sent with the call
+    let [TokenTransfer { id, amount }] = inputs.values.transferred() else {
+        return Err(Error::InvalidInput);
+    };
+    if amount.is_zero() {
+        return Err(Error::InvalidInput);
+    }
+
+    charge(
+        &mut gas_used,
+        gas_limit,
+        SLOT_READ_GAS_COST + SLOT_UPDATE_GAS_COST + STREAM_SLOTS * SLOT_SET_GAS_COST,
+    )?;
+
+    let stream_id = sload(evmctx, ADDRESS, LAST_STREAM_ID_SLOT)? + U256::from(1);
+    sstore(evmctx, ADDRESS, LAST_STREAM_ID_SLOT, stream_id)?;
+    let stream = Stream {
+        sender: inputs.caller,
+        recipient,
+        token_id: *id,
+        amount: *amount,
+        start_time,
+        end_time,
+        withdrawn_amount: U256::ZERO,
+    };
+    let slot = Stream::slot(stream_id);
+    for (offset, field) in stream.fields().into_iter().enumerate() {
+        sstore(evmctx, ADDRESS, slot + U256::from(offset), field)?;
+    }
+
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: stream_id.to_be_bytes::<{ U256::BYTES }>().into(),
+    }))
+}
+
+fn withdrawable_amount_of<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    mut gas_used: u64,
+    gas_limit: u64,
+    stream_id: U256,
+) -> PrecompileResult {
+    charge(&mut gas_used, gas_limit, STREAM_SLOTS * SLOT_READ_GAS_COST)?;
+
+    let stream = read_stream(evmctx, stream_id)?;
+    let withdrawable = stream.withdrawable_amount(evmctx.env.block.timestamp);
+
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: withdrawable.to_be_bytes::<{ U256::BYTES }>().into(),
+    }))
+}
+
+/// Sends the recipient of the stream the tokens streamed and not withdrawn yet.
+///
+/// Anyone can make the withdrawal, as the tokens can only go to the recipient.
+fn withdraw<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    inputs: &CallInputs,
+    mut gas_used: u64,
+    gas_limit: u64,
+    stream_id: U256,
+) -> PrecompileResult {
+    ensure_state_changing_call(inputs)?;
+    charge(
+        &mut gas_used,
+        gas_limit,
+        STREAM_SLOTS * SLOT_READ_GAS_COST + SLOT_UPDATE_GAS_COST,
+    )?;
+
+    let mut stream = read_stream(evmctx, stream_id)?;
+    let withdrawable = stream.withdrawable_amount(evmctx.env.block.timestamp);
+    if !withdrawable.is_zero() {
+        stream.withdrawn_amount += withdrawable;
+        sstore(
+            evmctx,
+            ADDRESS,
+            Stream::slot(stream_id) + U256::from(WITHDRAWN_AMOUNT_OFFSET),
+            stream.withdrawn_amount,
+        )?;
+        let transfer = TokenTransfer {
+            id: stream.token_id,
+            amount: withdrawable,
+        };
+        if !matches!(
+            evmctx.transfer(&ADDRESS, &stream.recipient, &[transfer]),
+            Ok(None)
+        ) {
+            return Err(Error::Other(String::from("Withdrawal failed")));
+        }
+    }
+
+    Ok(ResultOrNewCall::Result(ResultInfo {
+        gas_used,
+        returned_bytes: withdrawable.to_be_bytes::<{ U256::BYTES }>().into(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryDB,
+        primitives::{
+            address, init_balances, AccountInfo, Bytes, ExecutionResult, Output, SpecId,
+            TransactTo, BASE_TOKEN_ID,
+        },
+        sablier::{SablierPrecompile, SablierPrecompileSet},
+        Evm,
+    };
+    use std::vec::Vec;
+
+    fn calldata(selector: u32, args: &[U256]) -> Bytes {
+        let mut data = selector.to_be_bytes().to_vec();
+        for arg in args {
+            data.extend_from_slice(&arg.to_be_bytes::<32>());
+        }
+        data.into()
+    }
+
+    fn output(result: ExecutionResult) -> U256 {
+        match result {
+            ExecutionResult::Success {
+                output: Output::Call(output),
+                ..
+            } => U256::from_be_slice(&output),
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn streams_tokens_linearly() {
+        let sender = address!("00000000000000000000000000000000000000aa");
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(7);
+        let mut db = InMemoryDB::default();
+        let mut balances = init_balances(U256::from(1_000_000_000));
+        balances.insert(token_id, U256::from(1000));
+        db.insert_account_info(
+            sender,
+            AccountInfo {
+                balances,
+                ..Default::default()
+            },
+        );
+        db.register_token(token_id);
+
+        let set = SablierPrecompileSet::mainnet().with_precompile(
+            SpecId::FRONTIER,
+            ADDRESS,
+            SablierPrecompile::Streams,
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_sablier_precompiles(set)
+            .modify_tx_env(|tx| {
+                tx.caller = sender;
+                tx.transact_to = TransactTo::Call(ADDRESS);
+                tx.gas_limit = 1_000_000;
+            })
+            .build();
+        let transact = |evm: &mut Evm<'_, (), InMemoryDB>,
+                        timestamp: u64,
+                        data: Bytes,
+                        transfers: Vec<TokenTransfer>| {
+            evm.block_mut().timestamp = U256::from(timestamp);
+            evm.tx_mut().data = data;
+            evm.tx_mut().transferred_tokens = transfers;
+            evm.tx_mut().nonce = None;
+            evm.transact_commit().unwrap()
+        };
+
+        // 1000 tokens streamed from 100 to 200
+        let create = calldata(
+            CREATE_STREAM_SELECTOR,
+            &[
+                recipient.into_word().into(),
+                U256::from(100),
+                U256::from(200),
+            ],
+        );
+        let deposit = vec![TokenTransfer {
+            id: token_id,
+            amount: U256::from(1000),
+        }];
+        let stream_id = output(transact(&mut evm, 50, create, deposit));
+        assert_eq!(stream_id, U256::from(1));
+        let balance = |evm: &mut Evm<'_, (), InMemoryDB>, address| {
+            evm.db_mut()
+                .accounts
+                .get(&address)
+                .map(|account| account.info.get_balance(token_id))
+                .unwrap_or_default()
+        };
+        assert_eq!(balance(&mut evm, ADDRESS), U256::from(1000));
+
+        let withdrawable = calldata(WITHDRAWABLE_AMOUNT_OF_SELECTOR, &[stream_id]);
+        let withdraw = calldata(WITHDRAW_SELECTOR, &[stream_id]);
+        assert_eq!(
+            output(transact(&mut evm, 125, withdrawable.clone(), vec![])),
+            U256::from(250)
+        );
+        assert_eq!(
+            output(transact(&mut evm, 150, withdraw.clone(), vec![])),
+            U256::from(500)
+        );
+        assert_eq!(balance(&mut evm, recipient), U256::from(500));
+        // calldata with bytes left over is rejected
+        let mut padded = withdrawable.to_vec();
+        padded.push(0);
+        assert!(!transact(&mut evm, 150, padded.into(), vec![]).is_success());
+        // tokens sent with a withdrawal would be stranded
+        let stray = vec![TokenTransfer {
+            id: BASE_TOKEN_ID,
+            amount: U256::from(1),
+        }];
+        assert!(!transact(&mut evm, 150, withdraw.clone(), stray).is_success());
+        assert_eq!(
+            output(transact(&mut evm, 150, withdrawable.clone(), vec![])),
+            U256::ZERO
+        );
+
+        // the rest is withdrawn after the end of the stream
+        assert_eq!(
+            output(transact(&mut evm, 300, withdraw, vec![])),
+            U256::from(500)
+        );
+        assert_eq!(balance(&mut evm, recipient), U256::from(1000));
+        assert_eq!(balance(&mut evm, ADDRESS), U256::ZERO);
+        // the precompile keeps its storage once it holds no tokens
+        assert_eq!(
+            output(transact(&mut evm, 300, withdrawable, vec![])),
+            U256::ZERO
+        );
+    }
+}
@@ -1,14 +1,34 @@
 #[cfg(test)]
 mod test {
     use crate::{
+        inspector_handle_register,
+        interpreter::{CallInputs, CallOutcome, CallValues, InstructionResult, Interpreter},
+        precompile::{Error as PrecompileError, PrecompileResult, ResultInfo, ResultOrNewCall},
         primitives::{
             address, bytes, keccak256, token_id_address, AccountInfo, Address, Bytecode, Bytes,
-            SpecId, TokenBalances, TokenTransfer, TransactTo, B256, BASE_TOKEN_ID, U256,
+            SpecId, TokenBalances, TokenIdDerivation, TokenPermit, TokenTransfer, TransactTo, B256,
+            BASE_TOKEN_ID, U256,
+        },
+        sablier::native_tokens::{
+            permit_domain, AbiMode, ForwardedCalldata, NativeTokenCall,
+            NativeTokensContextPrecompile, NativeTokensSelectors,
+            ADDRESS as NATIVE_TOKENS_PRECOMPILE_ADDRESS, ALLOWANCE_SELECTOR,
+            ALL_TOKEN_IDS_SELECTOR, BASE_GAS_COST, CALL_VALUE_AT_SELECTOR,
+            CALL_VALUE_COUNT_SELECTOR, COLD_BALANCE_GAS_COST, ECRECOVER_GAS_COST,
+            GET_CALL_VALUES_SELECTOR, MAX_CALLDATA_SIZE, MAX_TOKEN_TRANSFERS, MINT_SELECTOR,
+            MULTICALL_SELECTOR, NONCES_SELECTOR, PERMIT_SELECTOR, SLOT_READ_GAS_COST,
+            SLOT_WRITE_GAS_COST, TOKEN_ID_GAS_COST, TOKEN_TRANSFER_GAS_COST,
+            TRANSFER_AND_CALL_SELECTOR, TRANSFER_FROM_SELECTOR,
+            TRANSFER_MULTIPLE_AND_CALL_SELECTOR, TRANSFER_MULTIPLE_SELECTOR, TRANSFER_SELECTOR,
         },
-        sablier::native_tokens::{ADDRESS as NATIVE_TOKENS_PRECOMPILE_ADDRESS, BALANCEOF_SELECTOR},
-        Evm, InMemoryDB,
+        sablier::native_tokens_abi::{encode_call, AbiArg},
+        test_utils::precompile::{
+            call_inputs, contract_account, create_precompile_context, delegatecall_inputs,
+            expect_new_call, expect_result, MOCK_DELEGATOR,
+        },
+        ContextStatefulPrecompileMut, Database, Evm, EvmContext, InMemoryDB, Inspector,
     };
-    use revm_interpreter::Host;
+    use revm_interpreter::{opcode, Host};
     use revm_precompile::HashMap;
 
     /// The Bytecode of the SRF20 Mock contract, transacted to when testing the minting and burning Precompile functionalities.
@@ -66,15 +86,11 @@ mod test {
                 tx.caller = caller_eoa;
                 tx.transact_to = TransactTo::Call(NATIVE_TOKENS_PRECOMPILE_ADDRESS);
 
-                // Encode the Tx Data, as follows: the balanceOf() function selector + address + token_id
-                let mut data = BALANCEOF_SELECTOR.to_be_bytes().to_vec();
-
-                let caller_address_evm_word = caller_eoa.into_word();
-                data.append(caller_address_evm_word.to_vec().as_mut());
-
-                data.append(BASE_TOKEN_ID.to_be_bytes_vec().as_mut());
-
-                tx.data = Bytes::from(data);
+                tx.data = NativeTokenCall::BalanceOf {
+                    account: caller_eoa,
+                    token_id: BASE_TOKEN_ID,
+                }
+                .encode(&NativeTokensSelectors::V1);
             })
             .with_spec_id(SpecId::LATEST)
             .build();
@@ -202,12 +218,66 @@ mod test {
         assert!(execution_result.is_success());
 
         // Assert that the tokens have been successfully minted to the intended recipient
-        let minted_token_id = token_id_address(SRF20_MOCK_ADDRESS, U256::ZERO);
+        let minted_token_id = TokenIdDerivation::V1.token_id(SRF20_MOCK_ADDRESS, U256::ZERO);
         let caller_minted_token_balance =
             evm.context.balance(minted_token_id, caller_eoa).unwrap().0;
         assert_eq!(caller_minted_token_balance, amount_to_mint);
     }
 
+    #[test]
+    fn simulate_previews_minted_tokens() {
+        let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e9");
+        let amount_to_mint = U256::from(1000);
+
+        let mut evm = Evm::builder()
+            .with_db(InMemoryDB::default())
+            .modify_db(|db| {
+                db.insert_account_info(caller_eoa, AccountInfo::default());
+                db.insert_account_info(
+                    SRF20_MOCK_ADDRESS,
+                    AccountInfo {
+                        code: Some(Bytecode::new_raw(SRF20_MOCK_BYTECODE.clone())),
+                        ..Default::default()
+                    },
+                );
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = caller_eoa;
+                tx.transact_to = TransactTo::Call(SRF20_MOCK_ADDRESS);
+                // the selector of "mint(address, uint256)"
+                let mut data = bytes!("40c10f19").to_vec();
+                data.extend_from_slice(caller_eoa.into_word().as_slice());
+                data.extend_from_slice(&amount_to_mint.to_be_bytes::<32>());
+                tx.data = Bytes::from(data);
+            })
+            .build();
+
+        let simulation = evm.simulate().unwrap();
+        assert!(simulation.result_and_state.result.is_success());
+        let minted_token_id = TokenIdDerivation::V1.token_id(SRF20_MOCK_ADDRESS, U256::ZERO);
+        assert_eq!(simulation.minted_token_ids, vec![minted_token_id]);
+        assert_eq!(simulation.balance_deltas.len(), 1);
+        let delta = simulation
+            .balance_delta(caller_eoa, minted_token_id)
+            .unwrap();
+        assert_eq!(delta.received(), amount_to_mint);
+        assert_eq!(delta.spent(), U256::ZERO);
+
+        // nothing was committed, so the token is minted for the first time again
+        assert!(!evm.db().token_ids.contains(&minted_token_id));
+        assert_eq!(evm.simulate().unwrap(), simulation);
+        evm.transact_commit().unwrap();
+        let simulation = evm.simulate().unwrap();
+        assert!(simulation.minted_token_ids.is_empty());
+        assert_eq!(
+            simulation
+                .balance_delta(caller_eoa, minted_token_id)
+                .unwrap()
+                .after,
+            amount_to_mint * U256::from(2)
+        );
+    }
+
     #[test]
     /// Test the "burn" Precompile functionality, as follows:
     ///    - an EOA address calls the burn() function of the SRF20 Contract
@@ -736,17 +806,12 @@ mod test {
                 tx.caller = caller_eoa;
                 tx.transact_to = TransactTo::Call(NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS);
 
-                // Encode the Tx Data
-                let mut data = bytes!("095bcdb6").to_vec(); // the selector of "transfer(address recipient, uint256 tokenID, uint256 amount)"
-
-                let recipient_address_evm_word = tx.caller.into_word();
-                data.append(recipient_address_evm_word.to_vec().as_mut());
-
-                data.append(token_id.to_be_bytes_vec().as_mut());
-
-                data.append(transfer_amount.to_be_bytes_vec().as_mut());
-
-                tx.data = Bytes::from(data);
+                tx.data = NativeTokenCall::Transfer {
+                    recipient: caller_eoa,
+                    token_id,
+                    amount: transfer_amount,
+                }
+                .encode(&NativeTokensSelectors::V1);
             })
             .with_spec_id(SpecId::LATEST)
             .build();
@@ -820,46 +885,15 @@ mod test {
                 tx.caller = caller_eoa;
                 tx.transact_to = TransactTo::Call(NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS);
 
-                // Encode the Tx Data
-                // tx.data structure:
-                // 0 - recipient's address
-                // 1/32 - token_ids_offset
-                // 2/64 - amounts_offset
-                // 3/96 - token_ids_len
-                // 4/128 - token_ids
-                // TBD/TBD - transfer_amounts_len
-                // TBD/TBD - transfer_amounts
-
-                let mut data = bytes!("99583417").to_vec(); // the selector of "transferMultiple(address to, uint256[] calldata tokenIDs, uint256[] calldata amounts)"
-
-                let recipient_address_evm_word = tx.caller.into_word();
-                data.append(recipient_address_evm_word.to_vec().as_mut());
-
-                let token_ids_offset = U256::from(96);
-                data.append(token_ids_offset.to_be_bytes_vec().as_mut());
-
-                let token_ids_len = U256::from_be_slice(token_ids.len().to_be_bytes().as_slice());
-
-                let evm_word_size = U256::from(32);
-                let amounts_offset =
-                    token_ids_offset + ((U256::from(1) + token_ids_len) * evm_word_size);
-                data.append(amounts_offset.to_be_bytes_vec().as_mut());
-
-                data.append(token_ids_len.to_be_bytes_vec().as_mut());
-
-                for token_id in token_ids.iter() {
-                    data.append(token_id.to_be_bytes_vec().as_mut());
-                }
-
-                let transfer_amounts_len =
-                    U256::from_be_slice(transfer_amounts.len().to_be_bytes().as_slice());
-                data.append(transfer_amounts_len.to_be_bytes_vec().as_mut());
-
-                for transfer_amount in transfer_amounts.iter() {
-                    data.append(transfer_amount.to_be_bytes_vec().as_mut());
+                tx.data = NativeTokenCall::TransferMultiple {
+                    recipient: caller_eoa,
+                    transfers: token_ids
+                        .iter()
+                        .zip(transfer_amounts)
+                        .map(|(id, amount)| TokenTransfer { id: *id, amount })
+                        .collect(),
                 }
-
-                tx.data = Bytes::from(data);
+                .encode(&NativeTokensSelectors::V1);
             })
             .with_spec_id(SpecId::LATEST)
             .build();
@@ -896,7 +930,7 @@ mod test {
     fn token_transfer_and_call_naive_transferrer_native_library_precompile() {
         let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
         let token_id = U256::from(5); // Random id
-        let caller_balance = U256::from(10);
+        let transferrer_balance = U256::from(10);
         let transfer_amount = U256::from(4);
         let fee_amount = U256::from(1);
 
@@ -908,7 +942,7 @@ mod test {
                 db.token_ids.push(token_id);
 
                 let caller_info = AccountInfo {
-                    balances: HashMap::from([(token_id, caller_balance)]),
+                    balances: HashMap::default(),
                     code_hash: B256::default(),
                     code: None,
                     nonce: 0,
@@ -917,7 +951,7 @@ mod test {
 
                 let token_transferrer_bytecode = &NAIVE_TOKEN_TRANSFERRER_MOCK_BYTECODE;
                 let token_transferrer_info = AccountInfo {
-                    balances: HashMap::default(),
+                    balances: HashMap::from([(token_id, transferrer_balance)]),
                     code_hash: keccak256(token_transferrer_bytecode.clone()),
                     code: Some(Bytecode::new_raw(token_transferrer_bytecode.clone())),
                     nonce: 1,
@@ -943,49 +977,23 @@ mod test {
                 tx.caller = caller_eoa;
                 tx.transact_to = TransactTo::Call(NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS);
 
-                // tx.data structure:
-                // 0 - recipient-and-callee's address
-                // 1/32 - token id
-                // 2/64 - transfer amount
-                // 3/96 - calldata offset (== 128)
-                // 4/128 - calldata byte length
-                // TBD/TBD - calldata bytes
-
-                // Encode the Tx Data
-                let mut data = bytes!("d1c673e9").to_vec(); // the selector of "transferAndCall(address recipientAndCallee, uint256 tokenID, uint256 amount, bytes calldata data)"
-
-                let recipient_and_callee_address_evm_word =
-                    CONTRACT_TO_TRANSFER_AND_CALL_TO_ADDRESS.into_word();
-                data.append(recipient_and_callee_address_evm_word.to_vec().as_mut());
-
-                data.append(token_id.to_be_bytes_vec().as_mut());
-
-                data.append(transfer_amount.to_be_bytes_vec().as_mut());
-
-                let calldata_offset = U256::from(128);
-                data.append(calldata_offset.to_be_bytes_vec().as_mut());
-
-                let mut inner_data: Vec<u8> = Vec::new();
-                let mut evm_word = [0; 32];
-
-                evm_word[28..].copy_from_slice(bytes!("ffb4fc75").to_vec().as_slice()); // the selector of "transferTokenForAFee(address recipient, uint256 tokenID, uint256 amount, uint256 fee)"
-                inner_data.append(evm_word.to_vec().as_mut());
-
-                let recipient_address_evm_word = end_recipient_eoa.into_word();
-                inner_data.append(recipient_address_evm_word.to_vec().as_mut());
-
-                inner_data.append(token_id.to_be_bytes_vec().as_mut());
-
-                inner_data.append(transfer_amount.to_be_bytes_vec().as_mut());
-
-                inner_data.append(fee_amount.to_be_bytes_vec().as_mut());
-
-                let calldata_byte_length = U256::from(inner_data.len());
-                data.append(calldata_byte_length.to_be_bytes_vec().as_mut());
-
-                data.append(inner_data.as_mut());
-
-                tx.data = Bytes::from(data);
+                // the selector of "transferTokenForAFee(address recipient, uint256 tokenID, uint256 amount, uint256 fee)"
+                let data = encode_call(
+                    0xffb4fc75,
+                    &[
+                        AbiArg::Address(end_recipient_eoa),
+                        AbiArg::Uint(token_id),
+                        AbiArg::Uint(transfer_amount),
+                        AbiArg::Uint(fee_amount),
+                    ],
+                );
+                tx.data = NativeTokenCall::TransferAndCall {
+                    recipient_and_callee: CONTRACT_TO_TRANSFER_AND_CALL_TO_ADDRESS,
+                    token_id,
+                    amount: transfer_amount,
+                    data,
+                }
+                .encode(&NativeTokensSelectors::V1);
             })
             .with_spec_id(SpecId::LATEST)
             .build();
@@ -996,12 +1004,23 @@ mod test {
         let execution_result = tx_result.unwrap();
         assert!(execution_result.is_success());
 
-        // Assert that the tokens have been successfully transferred from the sender to the recipient
+        // Assert that the tokens have been successfully transferred from the delegating contract to the recipient
         let recipient_token_balance = evm.context.balance(token_id, end_recipient_eoa).unwrap().0;
         assert_eq!(recipient_token_balance, transfer_amount - fee_amount);
 
+        let transferrer_token_balance = evm
+            .context
+            .balance(token_id, NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS)
+            .unwrap()
+            .0;
+        assert_eq!(
+            transferrer_token_balance,
+            transferrer_balance - transfer_amount
+        );
+
+        // Assert that the caller of the delegating contract has not been debited
         let caller_token_balance = evm.context.balance(token_id, caller_eoa).unwrap().0;
-        assert_eq!(caller_token_balance, caller_balance - transfer_amount);
+        assert_eq!(caller_token_balance, U256::ZERO);
 
         // Assert that the "transfer fee" has been successfully transferred to the contract
         let mock_contract_token_balance = evm
@@ -1022,7 +1041,7 @@ mod test {
         let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
         let token1_id = U256::from(5); // Random id
         let token2_id = U256::from(6); // Random id
-        let caller_balance = U256::from(10);
+        let transferrer_balance = U256::from(10);
         let transfer_amount = U256::from(4);
         let fee_amount = U256::from(1);
 
@@ -1034,10 +1053,7 @@ mod test {
                 db.token_ids.push(token1_id);
 
                 let caller_info = AccountInfo {
-                    balances: HashMap::from([
-                        (token1_id, caller_balance),
-                        (token2_id, caller_balance),
-                    ]),
+                    balances: HashMap::default(),
                     code_hash: B256::default(),
                     code: None,
                     nonce: 0,
@@ -1046,7 +1062,10 @@ mod test {
 
                 let token_transferrer_bytecode = &NAIVE_TOKEN_TRANSFERRER_MOCK_BYTECODE;
                 let token_transferrer_info = AccountInfo {
-                    balances: HashMap::default(),
+                    balances: HashMap::from([
+                        (token1_id, transferrer_balance),
+                        (token2_id, transferrer_balance),
+                    ]),
                     code_hash: keccak256(token_transferrer_bytecode.clone()),
                     code: Some(Bytecode::new_raw(token_transferrer_bytecode.clone())),
                     nonce: 1,
@@ -1072,90 +1091,33 @@ mod test {
                 tx.caller = caller_eoa;
                 tx.transact_to = TransactTo::Call(NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS);
 
-                // tx.data structure:
-                // 0 - recipient-and-callee's address
-                // 1/32 - token ids offset (== 128)
-                // 2/64 - transfer amounts offset (== 224)
-                // 3/96 - calldata offset (== 320)
-                // 4/128 - token ids len
-                // 5/160 - token ids::token1_id
-                // 6/192 - token ids::token2_id
-                // 7/224 - transfer amounts len
-                // 8/256 - transfer amounts::transfer_amount (1)
-                // 9/288 - transfer amounts::transfer_amount (2)
-                // 10/320 - calldata byte length
-                // TBD/TBD - calldata bytes
-
-                // Encode the Tx Data
-                let mut data = bytes!("822bbe4c").to_vec(); // the selector of `transferMultipleAndCall(address recipientAndCallee, uint256[] calldata tokenIDs, uint256[] calldata amounts, bytes calldata data)`
-                let recipient_and_callee_address_evm_word =
-                    CONTRACT_TO_TRANSFER_AND_CALL_TO_ADDRESS.into_word();
-                data.append(recipient_and_callee_address_evm_word.to_vec().as_mut());
-
-                let token_ids_offset = U256::from(128);
-                data.append(token_ids_offset.to_be_bytes_vec().as_mut());
-
-                let transfer_amounts_offset = U256::from(224);
-                data.append(transfer_amounts_offset.to_be_bytes_vec().as_mut());
-
-                let calldata_offset = U256::from(320);
-                data.append(calldata_offset.to_be_bytes_vec().as_mut());
-
-                let token_ids_len = U256::from(2);
-                data.append(token_ids_len.to_be_bytes_vec().as_mut());
-                data.append(token1_id.to_be_bytes_vec().as_mut());
-                data.append(token2_id.to_be_bytes_vec().as_mut());
-
-                let transfer_amounts_len = U256::from(2);
-                data.append(transfer_amounts_len.to_be_bytes_vec().as_mut());
-                data.append(transfer_amount.to_be_bytes_vec().as_mut());
-                data.append(transfer_amount.to_be_bytes_vec().as_mut());
-
-                let mut inner_data: Vec<u8> = Vec::new();
-                let mut evm_word = [0; 32];
-
-                evm_word[28..].copy_from_slice(bytes!("65066c97").to_vec().as_slice()); // the selector of "transferMultipleTokensForAFee(address recipient, uint256[] calldata tokenIDs, uint256[] calldata amounts, uint256 fee)"
-                inner_data.append(evm_word.to_vec().as_mut());
-
-                // Calldata structure:
-                // 0 - recipient's address
-                // 1/32 - token ids offset (== 128)
-                // 2/64 - transfer amounts offset (== 224)
-                // 3/96 - fee amount
-                // 4/128 - token ids len
-                // 5/160 - token ids::token1_id
-                // 6/192 - token ids::token2_id
-                // 7/224 - transfer amounts len
-                // 8/256 - transfer amounts::transfer_amount (1)
-                // 9/288 - transfer amounts::transfer_amount (2)
-
-                // Encode the Tx Data
-                let recipient_address_evm_word = end_recipient_eoa.into_word();
-                inner_data.append(recipient_address_evm_word.to_vec().as_mut());
-
-                let token_ids_offset = U256::from(128);
-                inner_data.append(token_ids_offset.to_be_bytes_vec().as_mut());
-
-                let transfer_amounts_offset = U256::from(224);
-                inner_data.append(transfer_amounts_offset.to_be_bytes_vec().as_mut());
-
-                inner_data.append(fee_amount.to_be_bytes_vec().as_mut());
-
-                let token_ids_len = U256::from(2);
-                inner_data.append(token_ids_len.to_be_bytes_vec().as_mut());
-                inner_data.append(token1_id.to_be_bytes_vec().as_mut());
-                inner_data.append(token2_id.to_be_bytes_vec().as_mut());
-
-                let transfer_amounts_len = U256::from(2);
-                inner_data.append(transfer_amounts_len.to_be_bytes_vec().as_mut());
-                inner_data.append(transfer_amount.to_be_bytes_vec().as_mut());
-                inner_data.append(transfer_amount.to_be_bytes_vec().as_mut());
-
-                let calldata_byte_length = U256::from(inner_data.len());
-                data.append(calldata_byte_length.to_be_bytes_vec().as_mut());
-                data.append(inner_data.as_mut());
-
-                tx.data = Bytes::from(data);
+                let transfers = vec![
+                    TokenTransfer {
+                        id: token1_id,
+                        amount: transfer_amount,
+                    },
+                    TokenTransfer {
+                        id: token2_id,
+                        amount: transfer_amount,
+                    },
+                ];
+                let [token_ids, amounts] = AbiArg::token_transfers(&transfers);
+                // the selector of "transferMultipleTokensForAFee(address recipient, uint256[] calldata tokenIDs, uint256[] calldata amounts, uint256 fee)"
+                let data = encode_call(
+                    0x65066c97,
+                    &[
+                        AbiArg::Address(end_recipient_eoa),
+                        token_ids,
+                        amounts,
+                        AbiArg::Uint(fee_amount),
+                    ],
+                );
+                tx.data = NativeTokenCall::TransferMultipleAndCall {
+                    recipient_and_callee: CONTRACT_TO_TRANSFER_AND_CALL_TO_ADDRESS,
+                    transfers,
+                    data,
+                }
+                .encode(&NativeTokensSelectors::V1);
             })
             .with_spec_id(SpecId::LATEST)
             .build();
@@ -1166,12 +1128,22 @@ mod test {
         let execution_result = tx_result.unwrap();
         assert!(execution_result.is_success());
 
-        // Assert that the first token has been successfully transferred from the sender to the recipient
+        // Assert that the first token has been successfully transferred from the delegating contract to the recipient
         let recipient_token1_balance = evm.context.balance(token1_id, end_recipient_eoa).unwrap().0;
         assert_eq!(recipient_token1_balance, transfer_amount - fee_amount);
 
+        let transferrer_token1_balance = evm
+            .context
+            .balance(token1_id, NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS)
+            .unwrap()
+            .0;
+        assert_eq!(
+            transferrer_token1_balance,
+            transferrer_balance - transfer_amount
+        );
+
         let caller_token1_balance = evm.context.balance(token1_id, caller_eoa).unwrap().0;
-        assert_eq!(caller_token1_balance, caller_balance - transfer_amount);
+        assert_eq!(caller_token1_balance, U256::ZERO);
 
         let fee_retainer_token1_balance = evm
             .context
@@ -1180,12 +1152,22 @@ mod test {
             .0;
         assert_eq!(fee_retainer_token1_balance, fee_amount);
 
-        // Assert that the second token has been successfully transferred from the sender to the recipient
+        // Assert that the second token has been successfully transferred from the delegating contract to the recipient
         let recipient_token2_balance = evm.context.balance(token2_id, end_recipient_eoa).unwrap().0;
         assert_eq!(recipient_token2_balance, transfer_amount - fee_amount);
 
+        let transferrer_token2_balance = evm
+            .context
+            .balance(token2_id, NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS)
+            .unwrap()
+            .0;
+        assert_eq!(
+            transferrer_token2_balance,
+            transferrer_balance - transfer_amount
+        );
+
         let caller_token2_balance = evm.context.balance(token2_id, caller_eoa).unwrap().0;
-        assert_eq!(caller_token2_balance, caller_balance - transfer_amount);
+        assert_eq!(caller_token2_balance, U256::ZERO);
 
         let fee_retainer_token2_balance = evm
             .context
@@ -1194,4 +1176,1549 @@ mod test {
             .0;
         assert_eq!(fee_retainer_token2_balance, fee_amount);
     }
+
+    /// Builds an EVM whose transaction has the delegator call `transferAndCall` of the precompile
+    /// with the callee, which returns the word 0x2a.
+    fn transfer_and_call_evm<EXT>(
+        external: EXT,
+        delegator: Address,
+        callee: Address,
+    ) -> Evm<'static, EXT, InMemoryDB> {
+        let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
+        // Copies the calldata to memory, delegatecalls the precompile with it, writing the output
+        // to memory offset 0, and returns the first word of memory
+        let mut delegator_code = vec![
+            opcode::CALLDATASIZE,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::CALLDATACOPY,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::CALLDATASIZE,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH20,
+        ];
+        delegator_code.extend_from_slice(NATIVE_TOKENS_PRECOMPILE_ADDRESS.as_slice());
+        delegator_code.extend_from_slice(&[
+            opcode::GAS,
+            opcode::DELEGATECALL,
+            opcode::POP,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+
+        // Returns 0x2a
+        let callee_code = vec![
+            opcode::PUSH1,
+            0x2a,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ];
+
+        Evm::builder()
+            .with_db(InMemoryDB::default())
+            .with_external_context(external)
+            .modify_db(|db| {
+                for (address, code) in [(delegator, delegator_code), (callee, callee_code)] {
+                    let bytecode = Bytecode::new_raw(Bytes::from(code));
+                    let info = AccountInfo {
+                        balances: HashMap::default(),
+                        code_hash: bytecode.hash_slow(),
+                        code: Some(bytecode),
+                        nonce: 1,
+                    };
+                    db.insert_account_info(address, info);
+                }
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = caller_eoa;
+                tx.transact_to = TransactTo::Call(delegator);
+
+                // a call to a zero function selector
+                tx.data = NativeTokenCall::TransferAndCall {
+                    recipient_and_callee: callee,
+                    token_id: BASE_TOKEN_ID,
+                    amount: U256::ZERO,
+                    data: Bytes::from_static(&[0; 4]),
+                }
+                .encode(&NativeTokensSelectors::V1);
+            })
+            .with_spec_id(SpecId::LATEST)
+            .build()
+    }
+
+    #[test]
+    fn token_transfer_and_call_returns_callee_output() {
+        let delegator = address!("5fdcca53617f4d2b9134b29090c87d01058e27d1");
+        let callee = address!("5fdcca53617f4d2b9134b29090c87d01058e27c1");
+        let returned_word = U256::from(0x2a);
+        let mut evm = transfer_and_call_evm((), delegator, callee);
+
+        let execution_result = evm.transact_commit().unwrap();
+        assert!(execution_result.is_success());
+
+        // The callee's output has been written to the delegatecall's output memory
+        let output = execution_result.output().unwrap();
+        assert_eq!(output.as_ref(), returned_word.to_be_bytes_vec().as_slice());
+    }
+
+    /// Records the calls started and ended in order.
+    #[derive(Default)]
+    struct CallRecorder {
+        calls: Vec<(&'static str, Address)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for CallRecorder {
+        fn call(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            inputs: &mut CallInputs,
+        ) -> Option<CallOutcome> {
+            self.calls.push(("call", inputs.target_address));
+            None
+        }
+
+        fn call_end(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            inputs: &CallInputs,
+            outcome: CallOutcome,
+        ) -> CallOutcome {
+            self.calls.push(("call_end", inputs.target_address));
+            outcome
+        }
+    }
+
+    #[test]
+    fn inspector_sees_call_spawned_by_precompile() {
+        let delegator = address!("5fdcca53617f4d2b9134b29090c87d01058e27d1");
+        let callee = address!("5fdcca53617f4d2b9134b29090c87d01058e27c1");
+        let mut evm = transfer_and_call_evm(CallRecorder::default(), delegator, callee)
+            .modify()
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let execution_result = evm.transact_commit().unwrap();
+        assert!(execution_result.is_success());
+
+        // the call to the callee is nested in the delegatecall of the precompile, which runs
+        // with the delegator as target
+        assert_eq!(
+            evm.context.external.calls,
+            vec![
+                ("call", delegator),
+                ("call", delegator),
+                ("call", callee),
+                ("call_end", callee),
+                ("call_end", delegator),
+                ("call_end", delegator),
+            ]
+        );
+    }
+
+    #[test]
+    fn token_transfer_via_callcode_is_rejected() {
+        let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
+        let library_user = address!("5fdcca53617f4d2b9134b29090c87d01058e27d1");
+        let recipient_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27a5");
+        let token_id = U256::from(5); // Random id
+        let library_user_balance = U256::from(10);
+
+        // Copies the calldata to memory, callcodes the precompile with it, and returns the
+        // success flag of the callcode
+        let mut library_user_code = vec![
+            opcode::CALLDATASIZE,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::CALLDATACOPY,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::CALLDATASIZE,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH1,
+            0x00,
+            opcode::PUSH20,
+        ];
+        library_user_code.extend_from_slice(NATIVE_TOKENS_PRECOMPILE_ADDRESS.as_slice());
+        library_user_code.extend_from_slice(&[
+            opcode::GAS,
+            opcode::CALLCODE,
+            opcode::PUSH1,
+            0x00,
+            opcode::MSTORE,
+            opcode::PUSH1,
+            0x20,
+            opcode::PUSH1,
+            0x00,
+            opcode::RETURN,
+        ]);
+
+        let mut evm = Evm::builder()
+            .with_db(InMemoryDB::default())
+            .modify_db(|db| {
+                db.token_ids.push(token_id);
+
+                let bytecode = Bytecode::new_raw(Bytes::from(library_user_code));
+                let library_user_info = AccountInfo {
+                    balances: HashMap::from([(token_id, library_user_balance)]),
+                    code_hash: bytecode.hash_slow(),
+                    code: Some(bytecode),
+                    nonce: 1,
+                };
+                db.insert_account_info(library_user, library_user_info);
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = caller_eoa;
+                tx.transact_to = TransactTo::Call(library_user);
+
+                tx.data = NativeTokenCall::Transfer {
+                    recipient: recipient_eoa,
+                    token_id,
+                    amount: library_user_balance,
+                }
+                .encode(&NativeTokensSelectors::V1);
+            })
+            .with_spec_id(SpecId::LATEST)
+            .build();
+
+        let execution_result = evm.transact_commit().unwrap();
+        assert!(execution_result.is_success());
+
+        // The precompile call has failed, since it has not been delegatecalled
+        let output = execution_result.output().unwrap();
+        assert_eq!(output.as_ref(), U256::ZERO.to_be_bytes_vec().as_slice());
+
+        let library_user_token_balance = evm.context.balance(token_id, library_user).unwrap().0;
+        assert_eq!(library_user_token_balance, library_user_balance);
+
+        let recipient_token_balance = evm.context.balance(token_id, recipient_eoa).unwrap().0;
+        assert_eq!(recipient_token_balance, U256::ZERO);
+    }
+
+    /// Records the native token calls reported to the inspector, with the contract making them.
+    #[derive(Default)]
+    struct NativeTokenCallRecorder(Vec<(Address, NativeTokenCall)>);
+
+    impl<DB: Database> Inspector<DB> for NativeTokenCallRecorder {
+        fn native_token_call(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            inputs: &CallInputs,
+            call: &NativeTokenCall,
+        ) {
+            self.0.push((inputs.target_address, call.clone()));
+        }
+    }
+
+    #[test]
+    fn inspector_sees_decoded_token_transfer() {
+        let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
+        let token_id = U256::from(5); // Random token id
+        let transfer_amount = U256::from(4);
+
+        let mut evm = Evm::builder()
+            .with_db(InMemoryDB::default())
+            .with_external_context(NativeTokenCallRecorder::default())
+            .modify_db(|db| {
+                db.token_ids.push(token_id);
+
+                let token_transferrer_bytecode = &NAIVE_TOKEN_TRANSFERRER_MOCK_BYTECODE;
+                let token_transferrer_info = AccountInfo {
+                    balances: HashMap::from([(token_id, transfer_amount)]),
+                    code_hash: keccak256(token_transferrer_bytecode.clone()),
+                    code: Some(Bytecode::new_raw(token_transferrer_bytecode.clone())),
+                    nonce: 1,
+                };
+                db.insert_account_info(
+                    NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS,
+                    token_transferrer_info,
+                );
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = caller_eoa;
+                tx.transact_to = TransactTo::Call(NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS);
+
+                tx.data = NativeTokenCall::Transfer {
+                    recipient: caller_eoa,
+                    token_id,
+                    amount: transfer_amount,
+                }
+                .encode(&NativeTokensSelectors::V1);
+            })
+            .with_spec_id(SpecId::LATEST)
+            .append_handler_register(inspector_handle_register)
+            .build();
+
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.context.external.0,
+            vec![(
+                NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS,
+                NativeTokenCall::Transfer {
+                    recipient: caller_eoa,
+                    token_id,
+                    amount: transfer_amount,
+                }
+            )]
+        );
+    }
+
+    /// Records the tokens sent to each frame and the registered tokens when the frame starts.
+    #[derive(Default)]
+    struct TokenValuesRecorder {
+        frames: Vec<(Vec<TokenTransfer>, Vec<U256>)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for TokenValuesRecorder {
+        fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+            let registered = Host::registered_token_ids(&mut context.inner).unwrap();
+            self.frames
+                .push((interp.incoming_token_values().to_vec(), registered));
+        }
+    }
+
+    #[test]
+    fn inspector_tells_incoming_values_from_registered_tokens() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let receiver = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(5);
+        let transfer = TokenTransfer {
+            id: token_id,
+            amount: U256::from(3),
+        };
+        let mut db = InMemoryDB::default();
+        db.token_ids.extend([token_id, U256::from(6)]);
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: HashMap::from([
+                    (BASE_TOKEN_ID, U256::from(1_000_000)),
+                    (token_id, U256::from(10)),
+                ]),
+                ..Default::default()
+            },
+        );
+        db.insert_account_info(
+            receiver,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from_static(&[opcode::STOP]))),
+                ..Default::default()
+            },
+        );
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_external_context(TokenValuesRecorder::default())
+            .append_handler_register(inspector_handle_register)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Call(receiver);
+                tx.gas_limit = 100_000;
+                tx.transferred_tokens = vec![transfer.clone()];
+            })
+            .build();
+        assert!(evm.transact().unwrap().result.is_success());
+
+        // the frame received one of the three registered tokens
+        assert_eq!(
+            evm.context.external.frames,
+            vec![(vec![transfer], vec![BASE_TOKEN_ID, token_id, U256::from(6)])]
+        );
+    }
+
+    /// Blocks the transfers of a token and records the transfers it is asked about.
+    struct TokenTransferPolicy {
+        blocked_token_id: U256,
+        transfers: Vec<(Address, Address, U256, U256)>,
+    }
+
+    impl<DB: Database> Inspector<DB> for TokenTransferPolicy {
+        fn token_transfer(
+            &mut self,
+            _context: &mut EvmContext<DB>,
+            from: Address,
+            to: Address,
+            token_id: U256,
+            amount: U256,
+        ) -> Option<InstructionResult> {
+            self.transfers.push((from, to, token_id, amount));
+            (token_id == self.blocked_token_id).then_some(InstructionResult::Revert)
+        }
+    }
+
+    #[test]
+    fn inspector_blocks_token_transfer() {
+        let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
+        let transfer_amount = U256::from(4);
+
+        for (token_id, blocked) in [(U256::from(5), true), (U256::from(6), false)] {
+            let mut evm = Evm::builder()
+                .with_db(InMemoryDB::default())
+                .with_external_context(TokenTransferPolicy {
+                    blocked_token_id: U256::from(5),
+                    transfers: Vec::new(),
+                })
+                .modify_db(|db| {
+                    db.token_ids.push(token_id);
+
+                    let token_transferrer_bytecode = &NAIVE_TOKEN_TRANSFERRER_MOCK_BYTECODE;
+                    let token_transferrer_info = AccountInfo {
+                        balances: HashMap::from([(token_id, transfer_amount)]),
+                        code_hash: keccak256(token_transferrer_bytecode.clone()),
+                        code: Some(Bytecode::new_raw(token_transferrer_bytecode.clone())),
+                        nonce: 1,
+                    };
+                    db.insert_account_info(
+                        NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS,
+                        token_transferrer_info,
+                    );
+                })
+                .modify_tx_env(|tx| {
+                    tx.caller = caller_eoa;
+                    tx.transact_to = TransactTo::Call(NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS);
+
+                    tx.data = NativeTokenCall::Transfer {
+                        recipient: caller_eoa,
+                        token_id,
+                        amount: transfer_amount,
+                    }
+                    .encode(&NativeTokensSelectors::V1);
+                })
+                .with_spec_id(SpecId::LATEST)
+                .append_handler_register(inspector_handle_register)
+                .build();
+
+            evm.transact_commit().unwrap();
+            assert_eq!(
+                evm.context.external.transfers,
+                vec![(
+                    NAIVE_TOKEN_TRANSFERRER_MOCK_ADDRESS,
+                    caller_eoa,
+                    token_id,
+                    transfer_amount
+                )]
+            );
+
+            let recipient_balance = evm.context.balance(token_id, caller_eoa).unwrap().0;
+            let expected = if blocked { U256::ZERO } else { transfer_amount };
+            assert_eq!(recipient_balance, expected);
+        }
+    }
+
+    #[test]
+    fn decode_native_token_calls() {
+        let recipient = address!("5fdcca53617f4d2b9134b29090c87d01058e27a5");
+        let selectors = NativeTokensSelectors::V1;
+        let encode = |selector: u32, words: &[U256]| {
+            let mut data = selector.to_be_bytes().to_vec();
+            data.append(recipient.into_word().to_vec().as_mut());
+            for word in words {
+                data.append(word.to_be_bytes_vec().as_mut());
+            }
+            Bytes::from(data)
+        };
+
+        // transferMultiple(recipient, [1, 2], [10, 20])
+        let words = [96, 192, 2, 1, 2, 2, 10, 20].map(U256::from);
+        assert_eq!(
+            NativeTokenCall::decode(&selectors, &encode(TRANSFER_MULTIPLE_SELECTOR, &words)),
+            Ok(NativeTokenCall::TransferMultiple {
+                recipient,
+                transfers: vec![
+                    TokenTransfer {
+                        id: U256::from(1),
+                        amount: U256::from(10),
+                    },
+                    TokenTransfer {
+                        id: U256::from(2),
+                        amount: U256::from(20),
+                    },
+                ],
+            })
+        );
+
+        // the token IDs have to be unique
+        let words = [96, 192, 2, 1, 1, 2, 10, 20].map(U256::from);
+        assert!(
+            NativeTokenCall::decode(&selectors, &encode(TRANSFER_MULTIPLE_SELECTOR, &words))
+                .is_err()
+        );
+
+        // the input can't have trailing bytes
+        let words = [1, 2, 3].map(U256::from);
+        assert!(NativeTokenCall::decode(&selectors, &encode(TRANSFER_SELECTOR, &words)).is_err());
+
+        // unknown selectors are rejected
+        assert!(NativeTokenCall::decode(&selectors, &encode(0xdeadbeef, &[])).is_err());
+    }
+
+    #[test]
+    fn decode_checks_or_follows_the_offsets() {
+        let recipient = address!("5fdcca53617f4d2b9134b29090c87d01058e27a5");
+        let sequential = NativeTokensSelectors::V1;
+        let lenient = NativeTokensSelectors {
+            abi_mode: AbiMode::Lenient,
+            ..NativeTokensSelectors::V1
+        };
+        let encode = |words: &[u64]| {
+            let mut data = TRANSFER_MULTIPLE_SELECTOR.to_be_bytes().to_vec();
+            data.append(recipient.into_word().to_vec().as_mut());
+            for word in words {
+                data.append(U256::from(*word).to_be_bytes_vec().as_mut());
+            }
+            Bytes::from(data)
+        };
+        let expected = Ok(NativeTokenCall::TransferMultiple {
+            recipient,
+            transfers: vec![TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(10),
+            }],
+        });
+
+        // transferMultiple(recipient, [1], [10]), encoded by Solidity
+        let canonical = encode(&[96, 160, 1, 1, 1, 10]);
+        assert_eq!(NativeTokenCall::decode(&sequential, &canonical), expected);
+        assert_eq!(NativeTokenCall::decode(&lenient, &canonical), expected);
+
+        // the same call, with the amounts placed before the token IDs
+        let swapped = encode(&[160, 96, 1, 10, 1, 1]);
+        assert!(NativeTokenCall::decode(&sequential, &swapped).is_err());
+        assert_eq!(NativeTokenCall::decode(&lenient, &swapped), expected);
+
+        // the same call, with both arrays at the same offset and a trailing word
+        let shared = encode(&[96, 96, 1, 1, 0]);
+        let shared_expected = Ok(NativeTokenCall::TransferMultiple {
+            recipient,
+            transfers: vec![TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(1),
+            }],
+        });
+        assert!(NativeTokenCall::decode(&sequential, &shared).is_err());
+        assert_eq!(NativeTokenCall::decode(&lenient, &shared), shared_expected);
+
+        // offsets relative to the wrong position used to be ignored
+        let ill_formed = encode(&[64, 128, 1, 1, 1, 10]);
+        assert!(NativeTokenCall::decode(&sequential, &ill_formed).is_err());
+        assert!(NativeTokenCall::decode(&lenient, &ill_formed).is_err());
+
+        // offsets past the calldata are rejected
+        for words in [[96, 1024, 1, 1, 1, 10], [u64::MAX, 160, 1, 1, 1, 10]] {
+            assert!(NativeTokenCall::decode(&sequential, &encode(&words)).is_err());
+            assert!(NativeTokenCall::decode(&lenient, &encode(&words)).is_err());
+        }
+    }
+
+    #[test]
+    fn precompile_transfers_delegator_tokens() {
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(5);
+        let mut context = create_precompile_context(
+            [(
+                MOCK_DELEGATOR,
+                contract_account(HashMap::from([(token_id, U256::from(10))])),
+            )],
+            [token_id],
+        );
+        let inputs = delegatecall_inputs(
+            NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+            TRANSFER_SELECTOR,
+            &[
+                AbiArg::Address(recipient),
+                AbiArg::Uint(token_id),
+                AbiArg::Uint(U256::from(4)),
+            ],
+        );
+
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        let result = expect_result(precompile.call_mut(&inputs, u64::MAX, &mut context));
+        assert_eq!(result.gas_used, BASE_GAS_COST);
+        assert!(result.returned_bytes.is_empty());
+        assert_eq!(
+            context.balance(token_id, MOCK_DELEGATOR).unwrap().0,
+            U256::from(6)
+        );
+        assert_eq!(
+            context.balance(token_id, recipient).unwrap().0,
+            U256::from(4)
+        );
+    }
+
+    #[test]
+    fn precompile_transfer_multiple_and_call_makes_call() {
+        let callee = address!("00000000000000000000000000000000000000cc");
+        let transfers = vec![
+            TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(2),
+            },
+            TokenTransfer {
+                id: U256::from(3),
+                amount: U256::from(4),
+            },
+        ];
+        // the selector of the callee's function, as a word
+        let mut data = [0; 32];
+        data[28..].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        let mut context = create_precompile_context(
+            [(callee, contract_account(TokenBalances::new()))],
+            [U256::from(1), U256::from(3)],
+        );
+        let [token_ids, amounts] = AbiArg::token_transfers(&transfers);
+        let inputs = delegatecall_inputs(
+            NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+            TRANSFER_MULTIPLE_AND_CALL_SELECTOR,
+            &[
+                AbiArg::Address(callee),
+                token_ids,
+                amounts,
+                AbiArg::Bytes(Bytes::copy_from_slice(&data)),
+            ],
+        );
+
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        let call = expect_new_call(precompile.call_mut(&inputs, u64::MAX, &mut context));
+        assert_eq!(call.target_address, callee);
+        assert_eq!(call.token_transfers, transfers);
+        assert_eq!(call.input_data.as_ref(), &data[28..]);
+        assert_eq!(call.gas_used, BASE_GAS_COST + 2 * TOKEN_TRANSFER_GAS_COST);
+    }
+
+    #[test]
+    fn precompile_charges_every_token_transfer() {
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let transfers: Vec<TokenTransfer> = (1..=3)
+            .map(|id| TokenTransfer {
+                id: U256::from(id),
+                amount: U256::from(1),
+            })
+            .collect();
+        let balances = transfers
+            .iter()
+            .map(|transfer| (transfer.id, U256::from(10)))
+            .collect();
+        let mut context = create_precompile_context(
+            [(MOCK_DELEGATOR, contract_account(balances))],
+            transfers.iter().map(|transfer| transfer.id),
+        );
+        let inputs = CallInputs {
+            input: NativeTokenCall::TransferMultiple {
+                recipient,
+                transfers,
+            }
+            .encode(&NativeTokensSelectors::V1),
+            ..delegatecall_inputs(NATIVE_TOKENS_PRECOMPILE_ADDRESS, 0, &[])
+        };
+        let gas_used = BASE_GAS_COST + 3 * TOKEN_TRANSFER_GAS_COST;
+
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        assert_eq!(
+            precompile
+                .call_mut(&inputs, gas_used - 1, &mut context)
+                .err(),
+            Some(PrecompileError::OutOfGas)
+        );
+        assert_eq!(
+            context.balance(U256::from(1), recipient).unwrap().0,
+            U256::ZERO
+        );
+
+        let result = expect_result(precompile.call_mut(&inputs, gas_used, &mut context));
+        assert_eq!(result.gas_used, gas_used);
+        assert_eq!(
+            context.balance(U256::from(1), recipient).unwrap().0,
+            U256::from(1)
+        );
+    }
+
+    #[test]
+    fn decode_checks_the_forwarded_calldata() {
+        let callee = address!("00000000000000000000000000000000000000cc");
+        let transfer_and_call = |data: &[u8]| {
+            encode_call(
+                TRANSFER_AND_CALL_SELECTOR,
+                &[
+                    AbiArg::Address(callee),
+                    AbiArg::Uint(U256::from(1)),
+                    AbiArg::Uint(U256::from(2)),
+                    AbiArg::Bytes(Bytes::copy_from_slice(data)),
+                ],
+            )
+        };
+        let forwarded = |call| match call {
+            Ok(NativeTokenCall::TransferAndCall { data, .. }) => Ok(data),
+            Ok(call) => panic!("decoded {call:?}"),
+            Err(error) => Err(error),
+        };
+
+        // data shorter than the 28-byte prefix is ill-formed
+        let selector_word = NativeTokensSelectors::V1;
+        assert_eq!(
+            forwarded(NativeTokenCall::decode(
+                &selector_word,
+                &transfer_and_call(&[0; 27])
+            )),
+            Err(PrecompileError::InvalidInput)
+        );
+
+        // raw calldata of any length, not only a selector followed by words, is forwarded as
+        // it is
+        let raw = NativeTokensSelectors {
+            forwarded_calldata: ForwardedCalldata::Raw,
+            ..NativeTokensSelectors::V1
+        };
+        let data: Vec<u8> = (1..=4 + 3 * 32 + 5).collect();
+        assert_eq!(
+            forwarded(NativeTokenCall::decode(&raw, &transfer_and_call(&data))),
+            Ok(Bytes::from(data.clone()))
+        );
+        assert_eq!(
+            forwarded(NativeTokenCall::decode(&raw, &transfer_and_call(&[]))),
+            Ok(Bytes::new())
+        );
+
+        // the padding has to be made of zeros
+        let mut calldata = transfer_and_call(&data).to_vec();
+        *calldata.last_mut().unwrap() = 1;
+        assert_eq!(
+            forwarded(NativeTokenCall::decode(&raw, &Bytes::from(calldata))),
+            Err(PrecompileError::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn decode_rejects_adversarial_lengths() {
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let selectors = NativeTokensSelectors::V1;
+        let transfer_multiple = |count: usize| {
+            NativeTokenCall::TransferMultiple {
+                recipient,
+                transfers: (0..count)
+                    .map(|id| TokenTransfer {
+                        id: U256::from(id),
+                        amount: U256::from(1),
+                    })
+                    .collect(),
+            }
+            .encode(&selectors)
+        };
+
+        assert!(
+            NativeTokenCall::decode(&selectors, &transfer_multiple(MAX_TOKEN_TRANSFERS)).is_ok()
+        );
+        assert_eq!(
+            NativeTokenCall::decode(&selectors, &transfer_multiple(MAX_TOKEN_TRANSFERS + 1)),
+            Err(PrecompileError::InvalidInput)
+        );
+
+        // lengths that the calldata can't back are rejected before anything is allocated
+        for len in [U256::from(2), U256::from(usize::MAX), U256::MAX] {
+            let mut calldata = transfer_multiple(1).to_vec();
+            // the length of the token IDs follows the three words of the head
+            calldata[4 + 3 * 32..4 + 4 * 32].copy_from_slice(&len.to_be_bytes::<32>());
+            assert_eq!(
+                NativeTokenCall::decode(&selectors, &Bytes::from(calldata)),
+                Err(PrecompileError::InvalidInput)
+            );
+        }
+
+        let transfer_and_call = |data_size: usize| {
+            NativeTokenCall::TransferAndCall {
+                recipient_and_callee: recipient,
+                token_id: U256::from(1),
+                amount: U256::from(1),
+                data: Bytes::from(vec![0; data_size]),
+            }
+            .encode(&selectors)
+        };
+        // a selector followed by words, as large as allowed
+        let max_size = MAX_CALLDATA_SIZE - (MAX_CALLDATA_SIZE - 4) % 32;
+        assert!(NativeTokenCall::decode(&selectors, &transfer_and_call(max_size)).is_ok());
+        assert_eq!(
+            NativeTokenCall::decode(&selectors, &transfer_and_call(max_size + 32)),
+            Err(PrecompileError::InvalidInput)
+        );
+        let mut calldata = transfer_and_call(4).to_vec();
+        // the size of the calldata follows the four words of the head
+        calldata[4 + 4 * 32..4 + 5 * 32].copy_from_slice(&U256::MAX.to_be_bytes::<32>());
+        assert_eq!(
+            NativeTokenCall::decode(&selectors, &Bytes::from(calldata)),
+            Err(PrecompileError::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn precompile_classifies_the_delegating_frame() {
+        let eoa = address!("00000000000000000000000000000000000000aa");
+        let sub_id = U256::from(1);
+        let mut context = create_precompile_context([(eoa, AccountInfo::default())], []);
+        assert_eq!(context.is_contract(MOCK_DELEGATOR), Some(true));
+        assert_eq!(context.is_contract(eoa), Some(false));
+
+        // the frame of the delegating contract can mint, even if an EOA sent the transaction
+        let mut inputs = delegatecall_inputs(
+            NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+            MINT_SELECTOR,
+            &[
+                AbiArg::Uint(sub_id),
+                AbiArg::Address(eoa),
+                AbiArg::Uint(U256::from(3)),
+            ],
+        );
+        inputs.caller = eoa;
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        expect_result(precompile.call_mut(&inputs, u64::MAX, &mut context));
+        assert_eq!(
+            context
+                .balance(token_id_address(MOCK_DELEGATOR, sub_id), eoa)
+                .unwrap()
+                .0,
+            U256::from(3)
+        );
+
+        // a frame running on the account of an EOA can't
+        inputs.target_address = eoa;
+        inputs.caller_bytecode_address = eoa;
+        assert!(matches!(
+            precompile.call_mut(&inputs, u64::MAX, &mut context),
+            Err(PrecompileError::UnauthorizedCaller)
+        ));
+    }
+
+    #[test]
+    fn precompile_rejects_plain_calls() {
+        let mut context = create_precompile_context([], []);
+        let inputs = call_inputs(
+            NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+            MOCK_DELEGATOR,
+            TRANSFER_SELECTOR,
+            &[
+                AbiArg::Address(MOCK_DELEGATOR),
+                AbiArg::Uint(BASE_TOKEN_ID),
+                AbiArg::Uint(U256::from(1)),
+            ],
+        );
+
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        assert_eq!(
+            precompile.call_mut(&inputs, u64::MAX, &mut context).err(),
+            Some(PrecompileError::UnauthorizedCaller)
+        );
+    }
+
+    #[test]
+    fn precompile_routes_registered_selectors() {
+        /// Returns the number of tokens, charging 5 gas.
+        fn token_count(
+            host: &mut dyn Host,
+            _inputs: &CallInputs,
+            gas_used: u64,
+            _gas_limit: u64,
+        ) -> PrecompileResult {
+            Ok(ResultOrNewCall::Result(ResultInfo {
+                gas_used: gas_used + 5,
+                returned_bytes: U256::from(host.registered_token_ids().unwrap().len())
+                    .to_be_bytes_vec()
+                    .into(),
+            }))
+        }
+
+        let custom_selector = 0x12345678;
+        let mut context = create_precompile_context([], [U256::from(1), U256::from(3)]);
+        let tokens = U256::from(context.registered_token_ids().unwrap().len());
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1)
+            .with_selector(custom_selector, token_count);
+
+        let decoded = |precompile: &NativeTokensContextPrecompile, inputs: &CallInputs| {
+            ContextStatefulPrecompileMut::<InMemoryDB>::native_token_call(precompile, inputs)
+        };
+
+        let inputs = delegatecall_inputs(NATIVE_TOKENS_PRECOMPILE_ADDRESS, custom_selector, &[]);
+        let result = expect_result(precompile.call_mut(&inputs, u64::MAX, &mut context));
+        assert_eq!(result.gas_used, BASE_GAS_COST + 5);
+        assert_eq!(U256::from_be_slice(&result.returned_bytes), tokens);
+        assert_eq!(decoded(&precompile, &inputs), None);
+
+        // the built-in functions are still served, unless their selector is registered
+        let inputs = delegatecall_inputs(
+            NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+            CALL_VALUE_COUNT_SELECTOR,
+            &[],
+        );
+        assert_eq!(
+            decoded(&precompile, &inputs),
+            Some(NativeTokenCall::CallValueCount)
+        );
+        assert!(precompile
+            .register_selector(CALL_VALUE_COUNT_SELECTOR, token_count)
+            .is_none());
+        let result = expect_result(precompile.call_mut(&inputs, u64::MAX, &mut context));
+        assert_eq!(result.gas_used, BASE_GAS_COST + 5);
+        assert_eq!(decoded(&precompile, &inputs), None);
+    }
+
+    #[test]
+    fn precompile_reads_call_values_one_by_one() {
+        let values = vec![
+            TokenTransfer {
+                id: U256::from(1),
+                amount: U256::from(2),
+            },
+            // zero amounts aren't call values
+            TokenTransfer {
+                id: U256::from(3),
+                amount: U256::ZERO,
+            },
+            TokenTransfer {
+                id: U256::from(4),
+                amount: U256::from(5),
+            },
+        ];
+        let mut context = create_precompile_context([], []);
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        let mut call = |selector, args: &[AbiArg]| {
+            let mut inputs = delegatecall_inputs(NATIVE_TOKENS_PRECOMPILE_ADDRESS, selector, args);
+            inputs.values = CallValues::Apparent(values.as_slice().into());
+            precompile.call_mut(&inputs, u64::MAX, &mut context)
+        };
+        let words =
+            |bytes: Bytes| -> Vec<U256> { bytes.chunks(32).map(U256::from_be_slice).collect() };
+
+        let count = expect_result(call(CALL_VALUE_COUNT_SELECTOR, &[]));
+        assert_eq!(words(count.returned_bytes), vec![U256::from(2)]);
+        let first = expect_result(call(CALL_VALUE_AT_SELECTOR, &[AbiArg::Uint(U256::ZERO)]));
+        assert_eq!(
+            words(first.returned_bytes),
+            vec![U256::from(1), U256::from(2)]
+        );
+        let second = expect_result(call(CALL_VALUE_AT_SELECTOR, &[AbiArg::Uint(U256::from(1))]));
+        assert_eq!(
+            words(second.returned_bytes),
+            vec![U256::from(4), U256::from(5)]
+        );
+        for index in [U256::from(2), U256::MAX] {
+            assert_eq!(
+                call(CALL_VALUE_AT_SELECTOR, &[AbiArg::Uint(index)]).err(),
+                Some(PrecompileError::InvalidInput)
+            );
+        }
+    }
+
+    #[test]
+    fn precompile_pages_through_token_ids() {
+        let mut context = create_precompile_context([], (1..=5).map(U256::from));
+        // the base token is registered first
+        let token_ids = context.registered_token_ids().unwrap().to_vec();
+        assert_eq!(
+            token_ids,
+            [BASE_TOKEN_ID]
+                .into_iter()
+                .chain((1..=5).map(U256::from))
+                .collect::<Vec<_>>()
+        );
+
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        let mut page = |offset: U256, limit: u64| {
+            let inputs = delegatecall_inputs(
+                NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+                ALL_TOKEN_IDS_SELECTOR,
+                &[AbiArg::Uint(offset), AbiArg::Uint(U256::from(limit))],
+            );
+            let result = expect_result(precompile.call_mut(&inputs, u64::MAX, &mut context));
+            let words: Vec<U256> = result
+                .returned_bytes
+                .chunks(32)
+                .map(U256::from_be_slice)
+                .collect();
+            // the array offset and length precede the token IDs
+            assert_eq!(words[..2], [U256::from(32), U256::from(words.len() - 2)]);
+            assert_eq!(
+                result.gas_used,
+                BASE_GAS_COST + TOKEN_ID_GAS_COST * (words.len() - 2) as u64
+            );
+            words[2..].to_vec()
+        };
+
+        assert_eq!(page(U256::ZERO, 4), token_ids[..4]);
+        assert_eq!(page(U256::from(4), 4), token_ids[4..]);
+        assert!(page(U256::from(6), 4).is_empty());
+        assert!(page(U256::MAX, 4).is_empty());
+        assert_eq!(page(U256::ZERO, u64::MAX), token_ids);
+    }
+
+    /// Returns code that calls the target with the opcode and the calldata, and returns the
+    /// output of the call.
+    fn forwarder_code(call_opcode: u8, target: Address, calldata: &[u8]) -> Bytecode {
+        let mut code = Vec::new();
+        for (offset, byte) in calldata.iter().enumerate() {
+            code.extend([
+                opcode::PUSH1,
+                *byte,
+                opcode::PUSH1,
+                offset as u8,
+                opcode::MSTORE8,
+            ]);
+        }
+        // no output is copied, the calldata is at the start of the memory
+        code.extend([opcode::PUSH1, 0, opcode::PUSH1, 0]);
+        code.extend([opcode::PUSH1, calldata.len() as u8, opcode::PUSH1, 0]);
+        if call_opcode == opcode::CALL {
+            // without value
+            code.extend([opcode::PUSH1, 0]);
+        }
+        code.push(opcode::PUSH20);
+        code.extend_from_slice(target.as_slice());
+        code.extend([opcode::GAS, call_opcode, opcode::POP]);
+        code.extend([opcode::RETURNDATASIZE, opcode::PUSH1, 0, opcode::PUSH1, 0]);
+        code.extend([
+            opcode::RETURNDATACOPY,
+            opcode::RETURNDATASIZE,
+            opcode::PUSH1,
+            0,
+        ]);
+        code.push(opcode::RETURN);
+        Bytecode::new_raw(code.into())
+    }
+
+    /// Returns the output of `getCallValues()` for the values.
+    fn encode_call_values(values: &[TokenTransfer]) -> Vec<u8> {
+        let len = values.len();
+        let mut words = vec![U256::from(64), U256::from(96 + 32 * len), U256::from(len)];
+        words.extend(values.iter().map(|value| value.id));
+        words.push(U256::from(len));
+        words.extend(values.iter().map(|value| value.amount));
+        words
+            .iter()
+            .flat_map(|word| word.to_be_bytes::<32>())
+            .collect()
+    }
+
+    #[test]
+    fn call_values_follow_delegatecall_chains() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let outer = address!("00000000000000000000000000000000000000bb");
+        let inner = address!("00000000000000000000000000000000000000cc");
+        let reader = address!("00000000000000000000000000000000000000dd");
+        let token_id = U256::from(5);
+        let transfers = vec![
+            TokenTransfer {
+                id: BASE_TOKEN_ID,
+                amount: U256::from(7),
+            },
+            TokenTransfer {
+                id: token_id,
+                amount: U256::from(4),
+            },
+            TokenTransfer {
+                id: U256::from(6),
+                amount: U256::ZERO,
+            },
+        ];
+
+        let transact = |target: Address| {
+            let mut db = InMemoryDB::default();
+            db.token_ids.extend([token_id, U256::from(6)]);
+            db.insert_account_info(
+                caller,
+                AccountInfo {
+                    balances: HashMap::from([
+                        (BASE_TOKEN_ID, U256::from(10)),
+                        (token_id, U256::from(10)),
+                    ]),
+                    ..Default::default()
+                },
+            );
+            // the reader delegatecalls the precompile, the inner contract delegatecalls the
+            // reader, and the outer contract delegatecalls the inner one or calls the reader
+            let selector = GET_CALL_VALUES_SELECTOR.to_be_bytes();
+            for (address, code) in [
+                (
+                    reader,
+                    forwarder_code(
+                        opcode::DELEGATECALL,
+                        NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+                        &selector,
+                    ),
+                ),
+                (inner, forwarder_code(opcode::DELEGATECALL, reader, &[])),
+                (outer, forwarder_code(opcode::DELEGATECALL, inner, &[])),
+            ] {
+                db.insert_account_info(
+                    address,
+                    AccountInfo {
+                        code: Some(code),
+                        ..Default::default()
+                    },
+                );
+            }
+            let calling_reader = address!("00000000000000000000000000000000000000ee");
+            db.insert_account_info(
+                calling_reader,
+                AccountInfo {
+                    code: Some(forwarder_code(opcode::CALL, reader, &[])),
+                    ..Default::default()
+                },
+            );
+
+            let mut evm = Evm::builder()
+                .with_db(db)
+                .modify_tx_env(|tx| {
+                    tx.caller = caller;
+                    tx.transact_to = TransactTo::Call(match target {
+                        Address::ZERO => calling_reader,
+                        target => target,
+                    });
+                    tx.transferred_tokens = transfers.clone();
+                    tx.gas_limit = 1_000_000;
+                })
+                .build();
+            let result = evm.transact().unwrap().result;
+            assert!(result.is_success(), "{result:?}");
+            result.into_output().unwrap()
+        };
+
+        // zero amounts are left out
+        let expected = encode_call_values(&transfers[..2]);
+        assert_eq!(transact(reader), expected);
+        assert_eq!(transact(outer), expected);
+        // a call without value has no call values
+        assert_eq!(transact(Address::ZERO), encode_call_values(&[]));
+    }
+
+    #[test]
+    fn delegated_mint_is_rejected_by_config() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let proxy = address!("00000000000000000000000000000000000000bb");
+        let library = address!("00000000000000000000000000000000000000cc");
+        let minter = address!("00000000000000000000000000000000000000dd");
+        let sub_id = U256::from(1);
+        let amount = U256::from(100);
+        let mut mint_call = MINT_SELECTOR.to_be_bytes().to_vec();
+        mint_call.extend(sub_id.to_be_bytes::<32>());
+        mint_call.extend(caller.into_word());
+        mint_call.extend(amount.to_be_bytes::<32>());
+
+        // the library and the minter delegatecall the precompile to mint to the caller, the
+        // proxy delegatecalls the library
+        let transact = |target: Address, reject_delegated_mint_burn: bool| {
+            let mut db = InMemoryDB::default();
+            db.insert_account_info(caller, AccountInfo::default());
+            let library_code = forwarder_code(
+                opcode::DELEGATECALL,
+                NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+                &mint_call,
+            );
+            for (address, code) in [
+                (library, library_code.clone()),
+                (minter, library_code),
+                (proxy, forwarder_code(opcode::DELEGATECALL, library, &[])),
+            ] {
+                db.insert_account_info(
+                    address,
+                    AccountInfo {
+                        code: Some(code),
+                        ..Default::default()
+                    },
+                );
+            }
+
+            let mut evm = Evm::builder()
+                .with_db(db)
+                .modify_cfg_env(|cfg| cfg.reject_delegated_mint_burn = reject_delegated_mint_burn)
+                .modify_tx_env(|tx| {
+                    tx.caller = caller;
+                    tx.transact_to = TransactTo::Call(target);
+                    tx.gas_limit = 1_000_000;
+                })
+                .build();
+            let result_and_state = evm.transact().unwrap();
+            assert!(result_and_state.result.is_success());
+            result_and_state.state.accounts[&caller]
+                .info
+                .get_balance(TokenIdDerivation::V1.token_id(target, sub_id))
+        };
+
+        // the library's code mints the tokens of the proxy
+        assert_eq!(transact(proxy, false), amount);
+        assert_eq!(transact(proxy, true), U256::ZERO);
+        // a contract's own code can still mint its tokens
+        assert_eq!(transact(minter, true), amount);
+    }
+
+    #[test]
+    fn constructor_reads_call_values() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let value = U256::from(7);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: HashMap::from([(BASE_TOKEN_ID, U256::from(10))]),
+                ..Default::default()
+            },
+        );
+
+        // the init code returns the output of `callValueAt(0)` as the code of the contract
+        let calldata = [CALL_VALUE_AT_SELECTOR.to_be_bytes().as_slice(), &[0; 32]].concat();
+        let init_code = forwarder_code(
+            opcode::DELEGATECALL,
+            NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+            &calldata,
+        );
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.caller = caller;
+                tx.transact_to = TransactTo::Create;
+                tx.data = init_code.original_bytes();
+                tx.transferred_tokens = vec![TokenTransfer {
+                    id: BASE_TOKEN_ID,
+                    amount: value,
+                }];
+                tx.gas_limit = 1_000_000;
+            })
+            .build();
+        let result = evm.transact().unwrap().result;
+        assert!(result.is_success(), "{result:?}");
+
+        let output = result.output().unwrap();
+        assert_eq!(output.len(), 64);
+        assert_eq!(U256::from_be_slice(&output[..32]), BASE_TOKEN_ID);
+        assert_eq!(U256::from_be_slice(&output[32..]), value);
+    }
+
+    #[test]
+    fn permit_sets_an_allowance_spent_by_transfer_from() {
+        let key = k256::ecdsa::SigningKey::from_slice(&[0x11; 32]).unwrap();
+        let public_key = key.verifying_key().to_encoded_point(false);
+        let owner = Address::from_slice(&keccak256(&public_key.as_bytes()[1..])[12..]);
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let relayer = address!("00000000000000000000000000000000000000cc");
+        let token_id = U256::from(5);
+        let mut context = create_precompile_context(
+            [(
+                owner,
+                AccountInfo {
+                    balances: HashMap::from([(token_id, U256::from(10))]),
+                    ..Default::default()
+                },
+            )],
+            [token_id],
+        );
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        let chain_id = context.env.cfg.chain_id;
+
+        let sign = |permit: &TokenPermit| {
+            let hash = permit_domain(chain_id).signing_hash(permit);
+            let (signature, recovery_id) = key.sign_prehash_recoverable(hash.as_slice()).unwrap();
+            let mut signature = signature.to_bytes().to_vec();
+            signature.push(27 + recovery_id.to_byte());
+            Bytes::from(signature)
+        };
+        let permit_inputs = |permit: &TokenPermit, signature: Bytes| {
+            call_inputs(
+                NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+                relayer,
+                PERMIT_SELECTOR,
+                &[
+                    AbiArg::Address(permit.owner),
+                    AbiArg::Address(permit.spender),
+                    AbiArg::Uint(permit.token_id),
+                    AbiArg::Uint(permit.amount),
+                    AbiArg::Uint(permit.deadline),
+                    AbiArg::Bytes(signature),
+                ],
+            )
+        };
+        let view = |precompile: &mut NativeTokensContextPrecompile,
+                    context: &mut _,
+                    selector: u32,
+                    args: &[AbiArg]| {
+            let inputs = call_inputs(NATIVE_TOKENS_PRECOMPILE_ADDRESS, relayer, selector, args);
+            let result = expect_result(precompile.call_mut(&inputs, u64::MAX, context));
+            assert_eq!(result.gas_used, BASE_GAS_COST + SLOT_READ_GAS_COST);
+            U256::from_be_slice(&result.returned_bytes)
+        };
+        let allowance_args = [
+            AbiArg::Address(owner),
+            AbiArg::Address(MOCK_DELEGATOR),
+            AbiArg::Uint(token_id),
+        ];
+
+        let permit = TokenPermit {
+            owner,
+            spender: MOCK_DELEGATOR,
+            token_id,
+            amount: U256::from(6),
+            nonce: U256::ZERO,
+            deadline: U256::MAX,
+        };
+        let signature = sign(&permit);
+        let result = expect_result(precompile.call_mut(
+            &permit_inputs(&permit, signature.clone()),
+            u64::MAX,
+            &mut context,
+        ));
+        assert_eq!(
+            result.gas_used,
+            BASE_GAS_COST + ECRECOVER_GAS_COST + SLOT_READ_GAS_COST + 2 * SLOT_WRITE_GAS_COST
+        );
+        assert_eq!(
+            view(
+                &mut precompile,
+                &mut context,
+                ALLOWANCE_SELECTOR,
+                &allowance_args
+            ),
+            U256::from(6)
+        );
+        assert_eq!(
+            view(
+                &mut precompile,
+                &mut context,
+                NONCES_SELECTOR,
+                &[AbiArg::Address(owner)]
+            ),
+            U256::from(1)
+        );
+
+        // the signature can't be replayed, and it doesn't sign another permit
+        assert!(precompile
+            .call_mut(
+                &permit_inputs(&permit, signature.clone()),
+                u64::MAX,
+                &mut context
+            )
+            .is_err());
+        let other_permit = TokenPermit {
+            amount: U256::from(7),
+            nonce: U256::from(1),
+            ..permit
+        };
+        assert!(precompile
+            .call_mut(
+                &permit_inputs(&other_permit, signature),
+                u64::MAX,
+                &mut context
+            )
+            .is_err());
+        // nor can an expired permit be used
+        context.env.block.timestamp = U256::from(10);
+        let expired_permit = TokenPermit {
+            deadline: U256::from(9),
+            ..other_permit
+        };
+        assert!(precompile
+            .call_mut(
+                &permit_inputs(&expired_permit, sign(&expired_permit)),
+                u64::MAX,
+                &mut context
+            )
+            .is_err());
+
+        // the spender transfers the tokens of the owner, up to the allowance
+        let transfer_from = |amount: u64| {
+            delegatecall_inputs(
+                NATIVE_TOKENS_PRECOMPILE_ADDRESS,
+                TRANSFER_FROM_SELECTOR,
+                &[
+                    AbiArg::Address(owner),
+                    AbiArg::Address(recipient),
+                    AbiArg::Uint(token_id),
+                    AbiArg::Uint(U256::from(amount)),
+                ],
+            )
+        };
+        let result = expect_result(precompile.call_mut(&transfer_from(4), u64::MAX, &mut context));
+        assert_eq!(
+            result.gas_used,
+            BASE_GAS_COST + SLOT_READ_GAS_COST + SLOT_WRITE_GAS_COST
+        );
+        assert_eq!(context.balance(token_id, owner).unwrap().0, U256::from(6));
+        assert_eq!(
+            context.balance(token_id, recipient).unwrap().0,
+            U256::from(4)
+        );
+        assert_eq!(
+            view(
+                &mut precompile,
+                &mut context,
+                ALLOWANCE_SELECTOR,
+                &allowance_args
+            ),
+            U256::from(2)
+        );
+        assert!(precompile
+            .call_mut(&transfer_from(3), u64::MAX, &mut context)
+            .is_err());
+
+        // the allowance is spent by its spender only
+        let mut inputs = transfer_from(1);
+        inputs.caller_bytecode_address = relayer;
+        inputs.target_address = relayer;
+        assert!(precompile
+            .call_mut(&inputs, u64::MAX, &mut context)
+            .is_err());
+    }
+
+    #[test]
+    fn multicall_pays_the_base_gas_cost_per_call() {
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(5);
+        let mut context = create_precompile_context(
+            [(
+                MOCK_DELEGATOR,
+                contract_account(HashMap::from([(token_id, U256::from(10))])),
+            )],
+            [token_id],
+        );
+        let mut precompile = NativeTokensContextPrecompile::new(NativeTokensSelectors::V1);
+        let transfer = NativeTokenCall::Transfer {
+            recipient,
+            token_id,
+            amount: U256::from(4),
+        };
+        let multicall = |calls: Vec<NativeTokenCall>| {
+            let mut inputs =
+                delegatecall_inputs(NATIVE_TOKENS_PRECOMPILE_ADDRESS, MULTICALL_SELECTOR, &[]);
+            inputs.input = NativeTokenCall::Multicall { calls }.encode(&NativeTokensSelectors::V1);
+            inputs
+        };
+
+        let inputs = multicall(vec![
+            transfer.clone(),
+            transfer,
+            NativeTokenCall::BalanceOf {
+                account: recipient,
+                token_id,
+            },
+        ]);
+        // the base cost is charged for the multicall and for each of its calls
+        let result = expect_result(precompile.call_mut(&inputs, u64::MAX, &mut context));
+        assert_eq!(result.gas_used, 4 * BASE_GAS_COST + COLD_BALANCE_GAS_COST);
+        assert_eq!(
+            context.balance(token_id, recipient).unwrap().0,
+            U256::from(8)
+        );
+        // `bytes[]` of the two empty outputs and the balance
+        let words: Vec<U256> = result
+            .returned_bytes
+            .chunks(32)
+            .map(U256::from_be_slice)
+            .collect();
+        assert_eq!(words, [32, 3, 96, 128, 160, 0, 0, 32, 8].map(U256::from));
+
+        // a batch of calls costing nothing more than the base cost runs out of gas
+        let inputs = multicall(vec![NativeTokenCall::CallValueCount; 2]);
+        assert!(matches!(
+            precompile.call_mut(&inputs, 3 * BASE_GAS_COST - 1, &mut context),
+            Err(PrecompileError::OutOfGas)
+        ));
+        let result = expect_result(precompile.call_mut(&inputs, 3 * BASE_GAS_COST, &mut context));
+        assert_eq!(result.gas_used, 3 * BASE_GAS_COST);
+
+        // the batched calls can't make calls of their own
+        let inputs = multicall(vec![NativeTokenCall::TransferAndCall {
+            recipient_and_callee: recipient,
+            token_id,
+            amount: U256::from(1),
+            data: Bytes::new(),
+        }]);
+        assert!(matches!(
+            precompile.call_mut(&inputs, u64::MAX, &mut context),
+            Err(PrecompileError::InvalidInput)
+        ));
+    }
+
+    #[test]
+    fn multicall_is_all_or_nothing() {
+        let caller_eoa = address!("5fdcca53617f4d2b9134b29090c87d01058e27e0");
+        let batcher = address!("00000000000000000000000000000000000000cc");
+        let recipient = address!("00000000000000000000000000000000000000bb");
+        let token_id = U256::from(5);
+
+        // delegatecalls the precompile with the calldata, ignoring the result
+        let mut code = vec![opcode::CALLDATASIZE, opcode::PUSH0, opcode::PUSH0];
+        code.extend([opcode::CALLDATACOPY, opcode::PUSH0, opcode::PUSH0]);
+        code.extend([opcode::CALLDATASIZE, opcode::PUSH0, opcode::PUSH20]);
+        code.extend_from_slice(NATIVE_TOKENS_PRECOMPILE_ADDRESS.as_slice());
+        code.extend([opcode::GAS, opcode::DELEGATECALL, opcode::STOP]);
+        let code = Bytecode::new_raw(code.into());
+
+        let mut evm = Evm::builder()
+            .with_db(InMemoryDB::default())
+            .modify_db(|db| {
+                db.token_ids.push(token_id);
+                db.insert_account_info(caller_eoa, AccountInfo::default());
+                db.insert_account_info(
+                    batcher,
+                    AccountInfo::new(
+                        HashMap::from([(token_id, U256::from(10))]),
+                        1,
+                        code.hash_slow(),
+                        code,
+                    ),
+                );
+            })
+            .modify_tx_env(|tx| {
+                tx.caller = caller_eoa;
+                tx.transact_to = TransactTo::Call(batcher);
+            })
+            .with_spec_id(SpecId::LATEST)
+            .build();
+        let multicall = |amounts: [u64; 2]| {
+            NativeTokenCall::Multicall {
+                calls: amounts
+                    .map(|amount| NativeTokenCall::Transfer {
+                        recipient,
+                        token_id,
+                        amount: U256::from(amount),
+                    })
+                    .to_vec(),
+            }
+            .encode(&NativeTokensSelectors::V1)
+        };
+
+        // the second transfer fails, so the first one is reverted too
+        evm.tx_mut().data = multicall([4, 7]);
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.context.balance(token_id, recipient).unwrap().0,
+            U256::ZERO
+        );
+        assert_eq!(
+            evm.context.balance(token_id, batcher).unwrap().0,
+            U256::from(10)
+        );
+
+        evm.tx_mut().data = multicall([4, 6]);
+        assert!(evm.transact_commit().unwrap().is_success());
+        assert_eq!(
+            evm.context.balance(token_id, recipient).unwrap().0,
+            U256::from(10)
+        );
+        assert_eq!(
+            evm.context.balance(token_id, batcher).unwrap().0,
+            U256::ZERO
+        );
+    }
 }
@@ -0,0 +1,77 @@
+//! Helpers shared by the Sablier precompiles, to read their input, charge their gas and access
+//! their storage.
+use crate::{
+    precompile::Error,
+    primitives::{keccak256, utilities::bytes_parsing::*, Address, Bytes, U256},
+    Database, InnerEvmContext,
+};
+use std::string::String;
+
+pub(crate) fn consume_address(input: &mut Bytes) -> Result<Address, Error> {
+    consume_address_from(input).map_err(|_| Error::InvalidInput)
+}
+
+pub(crate) fn consume_u256(input: &mut Bytes) -> Result<U256, Error> {
+    consume_u256_from(input).map_err(|_| Error::InvalidInput)
+}
+
+/// Fails if bytes are left in the input after its arguments were decoded.
+pub(crate) fn ensure_consumed(input: &Bytes) -> Result<(), Error> {
+    if !input.is_empty() {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+/// Adds the cost to the gas used, failing if the gas limit is exceeded.
+pub(crate) fn charge(gas_used: &mut u64, gas_limit: u64, cost: u64) -> Result<(), Error> {
+    *gas_used += cost;
+    if *gas_used > gas_limit {
+        return Err(Error::OutOfGas);
+    }
+    Ok(())
+}
+
+/// Returns the slot of `mapping[key]`, for the mapping at `slot` of the storage.
+pub(crate) fn mapping_slot(slot: U256, key: U256) -> U256 {
+    let mut preimage = [0; 64];
+    preimage[..32].copy_from_slice(&key.to_be_bytes::<32>());
+    preimage[32..].copy_from_slice(&slot.to_be_bytes::<32>());
+    keccak256(preimage).into()
+}
+
+/// Reads a slot of the storage of the precompile at `address`.
+pub(crate) fn sload<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    address: Address,
+    slot: U256,
+) -> Result<U256, Error> {
+    evmctx
+        .load_account(address)
+        .map_err(|_| Error::Other(String::from("Storage read failed")))?;
+    evmctx
+        .sload(address, slot)
+        .map(|(value, _)| value)
+        .map_err(|_| Error::Other(String::from("Storage read failed")))
+}
+
+/// Writes a slot of the storage of the precompile at `address`.
+pub(crate) fn sstore<DB: Database>(
+    evmctx: &mut InnerEvmContext<DB>,
+    address: Address,
+    slot: U256,
+    value: U256,
+) -> Result<(), Error> {
+    evmctx
+        .load_account(address)
+        .map_err(|_| Error::Other(String::from("Storage write failed")))?;
+    // Like a deployed contract, the precompile has a nonce, so that it isn't cleared as an
+    // empty account with its storage.
+    if evmctx.journaled_state.account(address).info.nonce == 0 {
+        evmctx.journaled_state.inc_nonce(address);
+    }
+    evmctx
+        .sstore(address, slot, value)
+        .map(|_| ())
+        .map_err(|_| Error::Other(String::from("Storage write failed")))
+}
@@ -0,0 +1,280 @@
+//! Counters and histograms of the execution, emitted through the [metrics] facade.
+//!
+//! The metrics are recorded by the recorder installed by the application, e.g. a Prometheus
+//! exporter. Without a recorder, they are discarded.
+//!
+//! | Name | Kind | Labels |
+//! |------|------|--------|
+//! | `sabvm_transactions_total` | counter | `outcome`: `success`, `revert` or `halt` |
+//! | `sabvm_gas_used` | histogram | |
+//! | `sabvm_token_transfers_total` | counter | |
+//! | `sabvm_call_depth` | histogram | |
+//! | `sabvm_precompile_calls_total` | counter | `address`, `selector` |
+//! | `sabvm_db_read_seconds` | histogram | `method` |
+use crate::{
+    handler::register::EvmHandler,
+    primitives::{AccountInfo, Address, Bytecode, EvmState, B256, U256},
+    Database, DatabaseCommit, JournalEntry,
+};
+use ::metrics::{counter, histogram};
+use std::{format, string::ToString, sync::Arc, time::Instant, vec::Vec};
+
+/// Makes the handler emit the metrics of the executed transactions and of their calls.
+pub fn register_metrics<EXT, DB: Database>(handler: &mut EvmHandler<'_, EXT, DB>) {
+    let call = handler.execution.call.clone();
+    handler.execution.call = Arc::new(move |ctx, inputs| {
+        histogram!("sabvm_call_depth").record(ctx.evm.journaled_state.depth() as f64);
+        if ctx.evm.precompiles.contains(&inputs.bytecode_address) {
+            let selector = inputs
+                .input
+                .get(..4)
+                .map(|selector| format!("0x{}", crate::primitives::hex::encode(selector)))
+                .unwrap_or_default();
+            counter!(
+                "sabvm_precompile_calls_total",
+                "address" => inputs.bytecode_address.to_string(),
+                "selector" => selector,
+            )
+            .increment(1);
+        }
+        call(ctx, inputs)
+    });
+
+    let output = handler.post_execution.output.clone();
+    handler.post_execution.output = Arc::new(move |ctx, result| {
+        // the journal is reset when the output is made, and it no longer holds the entries of
+        // the reverted calls.
+        let transfers = ctx
+            .evm
+            .journaled_state
+            .journal
+            .iter()
+            .flatten()
+            .filter(|entry| {
+                matches!(entry, JournalEntry::BalanceTransfer { amount, .. } if !amount.is_zero())
+            })
+            .count();
+        let result_and_state = output(ctx, result)?;
+        let outcome = result_and_state.result.kind().as_str();
+        counter!("sabvm_transactions_total", "outcome" => outcome).increment(1);
+        histogram!("sabvm_gas_used").record(result_and_state.result.gas_used() as f64);
+        counter!("sabvm_token_transfers_total").increment(transfers as u64);
+        Ok(result_and_state)
+    });
+}
+
+/// A database wrapper that records the latency of the reads made to the wrapped database.
+#[derive(Debug, Default)]
+pub struct MetricsDB<DB> {
+    db: DB,
+}
+
+impl<DB> MetricsDB<DB> {
+    /// Wraps the database.
+    pub fn new(db: DB) -> Self {
+        Self { db }
+    }
+
+    /// Returns the wrapped database.
+    pub fn inner(&self) -> &DB {
+        &self.db
+    }
+
+    /// Consumes the wrapper, returning the wrapped database.
+    pub fn into_inner(self) -> DB {
+        self.db
+    }
+}
+
+/// Runs the read, recording its latency under the method.
+fn timed<T>(method: &'static str, read: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let value = read();
+    histogram!("sabvm_db_read_seconds", "method" => method).record(start.elapsed().as_secs_f64());
+    value
+}
+
+impl<DB: Database> Database for MetricsDB<DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        timed("basic", || self.db.basic(address))
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        timed("code_by_hash", || self.db.code_by_hash(code_hash))
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        timed("storage", || self.db.storage(address, index))
+    }
+
+    fn block_hash(&mut self, number: U256) -> Result<B256, Self::Error> {
+        timed("block_hash", || self.db.block_hash(number))
+    }
+
+    fn get_token_ids(&self) -> Result<Vec<U256>, Self::Error> {
+        timed("get_token_ids", || self.db.get_token_ids())
+    }
+
+    fn is_token_id_valid(&self, token_id: U256) -> Result<bool, Self::Error> {
+        timed("is_token_id_valid", || self.db.is_token_id_valid(token_id))
+    }
+}
+
+impl<DB: DatabaseCommit> DatabaseCommit for MetricsDB<DB> {
+    fn commit(&mut self, changes: EvmState) {
+        self.db.commit(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::InMemoryDB,
+        primitives::{address, init_balances, Bytes, TokenTransfer, TransactTo, TxEnv},
+        Evm,
+    };
+    use ::metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+    use std::{string::String, sync::Mutex};
+
+    type Records = Arc<Mutex<Vec<(String, f64)>>>;
+
+    /// Records every increment and observation as `name{label=value,...}` and its value.
+    #[derive(Default)]
+    struct TestRecorder {
+        records: Records,
+    }
+
+    struct Handle {
+        key: String,
+        records: Records,
+    }
+
+    impl CounterFn for Handle {
+        fn increment(&self, value: u64) {
+            self.records
+                .lock()
+                .unwrap()
+                .push((self.key.clone(), value as f64));
+        }
+
+        fn absolute(&self, _value: u64) {}
+    }
+
+    impl HistogramFn for Handle {
+        fn record(&self, value: f64) {
+            self.records.lock().unwrap().push((self.key.clone(), value));
+        }
+    }
+
+    impl TestRecorder {
+        fn handle(&self, key: &Key) -> Arc<Handle> {
+            let labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            Arc::new(Handle {
+                key: format!("{}{{{}}}", key.name(), labels.join(",")),
+                records: self.records.clone(),
+            })
+        }
+
+        fn sum(&self, key: &str) -> f64 {
+            let records = self.records.lock().unwrap();
+            records
+                .iter()
+                .filter(|(k, _)| k == key)
+                .map(|(_, v)| v)
+                .sum()
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.handle(key))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.handle(key))
+        }
+    }
+
+    #[test]
+    fn emits_execution_metrics() {
+        let caller = address!("00000000000000000000000000000000000000aa");
+        let receiver = address!("00000000000000000000000000000000000000bb");
+        let identity = address!("0000000000000000000000000000000000000004");
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            caller,
+            AccountInfo {
+                balances: init_balances(U256::from(1_000_000)),
+                ..Default::default()
+            },
+        );
+        let transfer = TxEnv {
+            caller,
+            transact_to: TransactTo::Call(receiver),
+            gas_limit: 100_000,
+            transferred_tokens: vec![TokenTransfer {
+                id: crate::primitives::BASE_TOKEN_ID,
+                amount: U256::from(10),
+            }],
+            ..Default::default()
+        };
+        let precompile_call = TxEnv {
+            caller,
+            transact_to: TransactTo::Call(identity),
+            gas_limit: 100_000,
+            data: Bytes::from_static(&[0x12, 0x34, 0x56, 0x78, 0x9a]),
+            ..Default::default()
+        };
+
+        let recorder = TestRecorder::default();
+        ::metrics::with_local_recorder(&recorder, || {
+            let mut evm = Evm::builder()
+                .with_db(MetricsDB::new(db))
+                .with_metrics()
+                .build();
+            for tx in [transfer, precompile_call] {
+                *evm.tx_mut() = tx;
+                evm.transact_commit().unwrap();
+            }
+        });
+
+        assert_eq!(
+            recorder.sum("sabvm_transactions_total{outcome=success}"),
+            2.0
+        );
+        assert_eq!(
+            recorder.sum("sabvm_gas_used{}"),
+            (21_000 + 21_000 + 5 * 16 + 18) as f64
+        );
+        assert_eq!(recorder.sum("sabvm_token_transfers_total{}"), 1.0);
+        assert_eq!(
+            recorder.sum(&format!(
+                "sabvm_precompile_calls_total{{address={identity},selector=0x12345678}}"
+            )),
+            1.0
+        );
+        assert!(recorder
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(key, _)| key == "sabvm_db_read_seconds{method=basic}"));
+    }
+}
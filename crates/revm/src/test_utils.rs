@@ -1,2 +1,6 @@
+#[cfg(feature = "serde-json")]
+pub mod fixture;
+pub mod precompile;
+
 #[doc(hidden)]
 pub use crate::context::evm_context::test_utils::*;
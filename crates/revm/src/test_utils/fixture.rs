@@ -0,0 +1,565 @@
+//! Declarative state tests of the SabVM features, read from JSON.
+//!
+//! A fixture file maps the names of the tests to [Fixture]s: the pre-state with the balances of
+//! every Native Token and the registered token IDs, a transaction transferring Native Tokens,
+//! and the expected result and post-state. Only what is listed in `expect` and `post` is checked:
+//!
+//! ```json
+//! {
+//!   "transfer of a native token": {
+//!     "spec": "Cancun",
+//!     "token_ids": ["0x7"],
+//!     "pre": {
+//!       "0x00000000000000000000000000000000000000aa": {
+//!         "balances": { "0x0": "1000000", "0x7": "10" }
+//!       }
+//!     },
+//!     "transaction": {
+//!       "caller": "0x00000000000000000000000000000000000000aa",
+//!       "to": "0x00000000000000000000000000000000000000bb",
+//!       "transferred_tokens": [{ "id": "0x7", "amount": "4" }]
+//!     },
+//!     "expect": { "status": "success", "gas_used": 21000 },
+//!     "post": {
+//!       "0x00000000000000000000000000000000000000aa": { "balances": { "0x7": "6" } },
+//!       "0x00000000000000000000000000000000000000bb": { "balances": { "0x7": "4" } }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! The fixtures are run by [FixtureSuite::assert_passes], e.g. for every file of a directory.
+use crate::{
+    db::{DatabaseCommit, InMemoryDB},
+    primitives::{
+        Address, Bytecode, Bytes, ExecutionResult, SpecId, TokenTransfer, TransactTo,
+        BASE_TOKEN_ID, U256,
+    },
+    Evm,
+};
+use core::fmt;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// The fixtures of a file, by name.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FixtureSuite(pub BTreeMap<String, Fixture>);
+
+impl FixtureSuite {
+    /// Reads the fixtures from JSON.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Runs the fixtures, returning the failed ones with why they failed.
+    pub fn failures(&self) -> Vec<(&str, FixtureError)> {
+        self.0
+            .iter()
+            .filter_map(|(name, fixture)| Some((name.as_str(), fixture.run().err()?)))
+            .collect()
+    }
+
+    /// Runs the fixtures, panicking with every failure if any fixture fails.
+    #[track_caller]
+    pub fn assert_passes(&self) {
+        let failures = self.failures();
+        if !failures.is_empty() {
+            let failures: Vec<String> = failures
+                .iter()
+                .map(|(name, error)| format!("{name}: {error}"))
+                .collect();
+            panic!("failed fixtures:\n{}", failures.join("\n"));
+        }
+    }
+}
+
+/// A state test: a transaction run on a pre-state, and what is expected of it.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    /// The hardfork, e.g. `"Cancun"`, the latest one by default.
+    #[serde(default)]
+    pub spec: Option<String>,
+    #[serde(default)]
+    pub block: FixtureBlock,
+    /// The registered tokens, in addition to the base token and the tokens held in `pre`.
+    #[serde(default)]
+    pub token_ids: Vec<U256>,
+    /// The accounts, by address.
+    #[serde(default)]
+    pub pre: BTreeMap<Address, FixtureAccount>,
+    pub transaction: FixtureTransaction,
+    #[serde(default)]
+    pub expect: FixtureExpectation,
+    /// The expected accounts after the transaction, by address.
+    #[serde(default)]
+    pub post: BTreeMap<Address, ExpectedAccount>,
+}
+
+/// The block of the transaction, whose unset fields are the default ones.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureBlock {
+    pub number: Option<U256>,
+    pub timestamp: Option<U256>,
+    pub coinbase: Option<Address>,
+    pub gas_limit: Option<U256>,
+    pub basefee: Option<U256>,
+}
+
+/// An account of the pre-state.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureAccount {
+    /// The balances, by token ID, including the base token.
+    #[serde(default)]
+    pub balances: BTreeMap<U256, U256>,
+    /// The nonce, 1 by default for a contract and 0 otherwise.
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    #[serde(default)]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// The transaction.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureTransaction {
+    pub caller: Address,
+    /// The called account, or `None` to create a contract with `data` as initcode.
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub data: Bytes,
+    #[serde(default = "default_gas_limit")]
+    pub gas_limit: u64,
+    #[serde(default)]
+    pub gas_price: U256,
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    #[serde(default)]
+    pub transferred_tokens: Vec<TokenTransfer>,
+}
+
+fn default_gas_limit() -> u64 {
+    30_000_000
+}
+
+/// The expected result of the transaction.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureExpectation {
+    /// A part of the error message of the transaction, which is expected to be invalid.
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub status: Option<ExpectedStatus>,
+    /// Why the execution stopped or halted, e.g. `"Stop"` or `"OutOfFunds"`.
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// The returned or revert data.
+    #[serde(default)]
+    pub output: Option<Bytes>,
+    #[serde(default)]
+    pub gas_used: Option<u64>,
+    /// All the registered token IDs, in order of registration.
+    #[serde(default)]
+    pub token_ids: Option<Vec<U256>>,
+}
+
+/// The expected status of the execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpectedStatus {
+    Success,
+    Revert,
+    Halt,
+}
+
+/// An account of the post-state, whose unlisted balances and slots aren't checked.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedAccount {
+    /// The balances, by token ID.
+    #[serde(default)]
+    pub balances: BTreeMap<U256, U256>,
+    #[serde(default)]
+    pub nonce: Option<u64>,
+    #[serde(default)]
+    pub code: Option<Bytes>,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Why a [Fixture] failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FixtureError {
+    /// The transaction is invalid, while it is expected to be executed.
+    UnexpectedError(String),
+    /// The transaction is executed, while it is expected to be invalid.
+    MissingError {
+        expected: String,
+    },
+    ErrorMismatch {
+        expected: String,
+        got: String,
+    },
+    Status {
+        expected: ExpectedStatus,
+        got: ExpectedStatus,
+    },
+    Reason {
+        expected: String,
+        got: String,
+    },
+    Output {
+        expected: Bytes,
+        got: Bytes,
+    },
+    GasUsed {
+        expected: u64,
+        got: u64,
+    },
+    TokenIds {
+        expected: Vec<U256>,
+        got: Vec<U256>,
+    },
+    Balance {
+        address: Address,
+        token_id: U256,
+        expected: U256,
+        got: U256,
+    },
+    Nonce {
+        address: Address,
+        expected: u64,
+        got: u64,
+    },
+    Code {
+        address: Address,
+        expected: Bytes,
+        got: Bytes,
+    },
+    Storage {
+        address: Address,
+        slot: U256,
+        expected: U256,
+        got: U256,
+    },
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedError(error) => write!(f, "unexpected error: {error}"),
+            Self::MissingError { expected } => write!(f, "expected error: {expected}"),
+            Self::ErrorMismatch { expected, got } => {
+                write!(f, "expected error {expected:?}, got {got:?}")
+            }
+            Self::Status { expected, got } => write!(f, "expected {expected:?}, got {got:?}"),
+            Self::Reason { expected, got } => {
+                write!(f, "expected reason {expected}, got {got}")
+            }
+            Self::Output { expected, got } => write!(f, "expected output {expected}, got {got}"),
+            Self::GasUsed { expected, got } => {
+                write!(f, "expected {expected} gas used, got {got}")
+            }
+            Self::TokenIds { expected, got } => {
+                write!(f, "expected token IDs {expected:?}, got {got:?}")
+            }
+            Self::Balance {
+                address,
+                token_id,
+                expected,
+                got,
+            } => write!(
+                f,
+                "expected a balance of {expected} of token {token_id} for {address}, got {got}"
+            ),
+            Self::Nonce {
+                address,
+                expected,
+                got,
+            } => write!(f, "expected nonce {expected} for {address}, got {got}"),
+            Self::Code {
+                address,
+                expected,
+                got,
+            } => write!(f, "expected code {expected} for {address}, got {got}"),
+            Self::Storage {
+                address,
+                slot,
+                expected,
+                got,
+            } => write!(
+                f,
+                "expected {expected} at slot {slot} of {address}, got {got}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FixtureError {}
+
+impl Fixture {
+    /// Returns the database holding the pre-state.
+    pub fn db(&self) -> InMemoryDB {
+        let mut db = InMemoryDB::default();
+        db.register_token(BASE_TOKEN_ID);
+        for token_id in &self.token_ids {
+            db.register_token(*token_id);
+        }
+        for (address, account) in &self.pre {
+            for (token_id, amount) in &account.balances {
+                let Ok(()) = db.fund(*address, *token_id, *amount);
+            }
+            if !account.code.is_empty() {
+                let Ok(()) = db.deploy(*address, Bytecode::new_raw(account.code.clone()));
+            }
+            let Ok(db_account) = db.load_account(*address);
+            if let Some(nonce) = account.nonce {
+                db_account.info.nonce = nonce;
+            }
+            for (slot, value) in &account.storage {
+                let Ok(()) = db.insert_account_storage(*address, *slot, *value);
+            }
+        }
+        db
+    }
+
+    /// Runs the transaction on the pre-state, and checks its result and the post-state.
+    pub fn run(&self) -> Result<(), FixtureError> {
+        let mut evm = Evm::builder()
+            .with_db(self.db())
+            .with_spec_id(self.spec.as_deref().map_or(SpecId::LATEST, SpecId::from))
+            .modify_block_env(|block| {
+                let fixture = &self.block;
+                block.number = fixture.number.unwrap_or(block.number);
+                block.timestamp = fixture.timestamp.unwrap_or(block.timestamp);
+                block.coinbase = fixture.coinbase.unwrap_or(block.coinbase);
+                block.gas_limit = fixture.gas_limit.unwrap_or(block.gas_limit);
+                block.basefee = fixture.basefee.unwrap_or(block.basefee);
+            })
+            .modify_tx_env(|tx| {
+                let fixture = &self.transaction;
+                tx.caller = fixture.caller;
+                tx.transact_to = fixture.to.map_or(TransactTo::Create, TransactTo::Call);
+                tx.data = fixture.data.clone();
+                tx.gas_limit = fixture.gas_limit;
+                tx.gas_price = fixture.gas_price;
+                tx.nonce = fixture.nonce;
+                tx.transferred_tokens = fixture.transferred_tokens.clone();
+            })
+            .build();
+
+        match (evm.transact(), &self.expect.error) {
+            (Ok(result_and_state), None) => {
+                self.check_result(&result_and_state.result)?;
+                evm.db_mut().commit(result_and_state.state);
+            }
+            (Ok(_), Some(expected)) => {
+                return Err(FixtureError::MissingError {
+                    expected: expected.clone(),
+                })
+            }
+            (Err(error), None) => return Err(FixtureError::UnexpectedError(error.to_string())),
+            (Err(error), Some(expected)) => {
+                let got = error.to_string();
+                if !got.contains(expected.as_str()) {
+                    return Err(FixtureError::ErrorMismatch {
+                        expected: expected.clone(),
+                        got,
+                    });
+                }
+            }
+        }
+        self.check_post_state(evm.db())
+    }
+
+    fn check_result(&self, result: &ExecutionResult) -> Result<(), FixtureError> {
+        let expect = &self.expect;
+        let (status, reason) = match result {
+            ExecutionResult::Success { reason, .. } => {
+                (ExpectedStatus::Success, Some(format!("{reason:?}")))
+            }
+            ExecutionResult::Revert { .. } => (ExpectedStatus::Revert, None),
+            ExecutionResult::Halt { reason, .. } => {
+                (ExpectedStatus::Halt, Some(format!("{reason:?}")))
+            }
+        };
+        if let Some(expected) = expect.status {
+            if expected != status {
+                return Err(FixtureError::Status {
+                    expected,
+                    got: status,
+                });
+            }
+        }
+        if let Some(expected) = &expect.reason {
+            let got = reason.unwrap_or_default();
+            if *expected != got {
+                return Err(FixtureError::Reason {
+                    expected: expected.clone(),
+                    got,
+                });
+            }
+        }
+        if let Some(expected) = &expect.output {
+            let got = result.output().cloned().unwrap_or_default();
+            if *expected != got {
+                return Err(FixtureError::Output {
+                    expected: expected.clone(),
+                    got,
+                });
+            }
+        }
+        if let Some(expected) = expect.gas_used {
+            if expected != result.gas_used() {
+                return Err(FixtureError::GasUsed {
+                    expected,
+                    got: result.gas_used(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_post_state(&self, db: &InMemoryDB) -> Result<(), FixtureError> {
+        if let Some(expected) = &self.expect.token_ids {
+            if *expected != db.token_ids {
+                return Err(FixtureError::TokenIds {
+                    expected: expected.clone(),
+                    got: db.token_ids.clone(),
+                });
+            }
+        }
+        for (address, expected) in &self.post {
+            let account = db.accounts.get(address);
+            let info = account
+                .map(|account| account.info.clone())
+                .unwrap_or_default();
+            for (token_id, expected) in &expected.balances {
+                let got = info.get_balance(*token_id);
+                if *expected != got {
+                    return Err(FixtureError::Balance {
+                        address: *address,
+                        token_id: *token_id,
+                        expected: *expected,
+                        got,
+                    });
+                }
+            }
+            if let Some(expected) = expected.nonce {
+                if expected != info.nonce {
+                    return Err(FixtureError::Nonce {
+                        address: *address,
+                        expected,
+                        got: info.nonce,
+                    });
+                }
+            }
+            if let Some(expected) = &expected.code {
+                let got = info
+                    .code
+                    .map(|code| code.original_bytes())
+                    .unwrap_or_default();
+                if *expected != got {
+                    return Err(FixtureError::Code {
+                        address: *address,
+                        expected: expected.clone(),
+                        got,
+                    });
+                }
+            }
+            for (slot, expected) in &expected.storage {
+                let got = account
+                    .and_then(|account| account.storage.get(slot).copied())
+                    .unwrap_or_default();
+                if *expected != got {
+                    return Err(FixtureError::Storage {
+                        address: *address,
+                        slot: *slot,
+                        expected: *expected,
+                        got,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn runs_fixture_files() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+        let mut files = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let json = fs::read_to_string(&path).unwrap();
+            let suite = FixtureSuite::from_json(&json)
+                .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+            suite.assert_passes();
+            files += 1;
+        }
+        assert!(files > 0);
+    }
+
+    #[test]
+    fn reports_failures() {
+        let json = r#"{
+            "wrong balance": {
+                "pre": {
+                    "0x00000000000000000000000000000000000000aa": { "balances": { "0x0": 1000 } }
+                },
+                "transaction": {
+                    "caller": "0x00000000000000000000000000000000000000aa",
+                    "to": "0x00000000000000000000000000000000000000bb",
+                    "transferred_tokens": [{ "id": "0x0", "amount": "100" }]
+                },
+                "post": {
+                    "0x00000000000000000000000000000000000000bb": { "balances": { "0x0": 99 } }
+                }
+            },
+            "missing error": {
+                "transaction": { "caller": "0x00000000000000000000000000000000000000aa" },
+                "expect": { "error": "lack of funds" }
+            }
+        }"#;
+        let suite = FixtureSuite::from_json(json).unwrap();
+        let recipient = Address::with_last_byte(0xbb);
+        assert_eq!(
+            suite.failures(),
+            vec![
+                (
+                    "missing error",
+                    FixtureError::MissingError {
+                        expected: "lack of funds".into()
+                    }
+                ),
+                (
+                    "wrong balance",
+                    FixtureError::Balance {
+                        address: recipient,
+                        token_id: BASE_TOKEN_ID,
+                        expected: U256::from(99),
+                        got: U256::from(100),
+                    }
+                ),
+            ]
+        );
+        assert!(FixtureSuite::from_json(r#"{"a": {"transaction": {}}}"#).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Helpers for unit tests of context precompiles.
+//!
+//! A precompile is tested by calling it directly with [CallInputs] made by
+//! [delegatecall_inputs] or [call_inputs], on the context made by [create_precompile_context],
+//! and checking its result with [expect_result] or [expect_new_call].
+
+use super::create_cache_db_evm_context;
+use crate::{
+    db::{CacheDB, EmptyDB},
+    interpreter::{CallInputs, CallScheme, CallValues},
+    precompile::{PrecompileResult, PrimitiveCallInfo, ResultInfo, ResultOrNewCall},
+    primitives::{
+        address, AccountInfo, Address, Bytecode, Bytes, Env, TokenBalances, TokenTransfers, U256,
+    },
+    sablier::native_tokens_abi::{encode_call, AbiArg},
+    InnerEvmContext,
+};
+use std::boxed::Box;
+
+/// Mock address of the contract that delegatecalls the precompile.
+pub const MOCK_DELEGATOR: Address = address!("00000000000000000000000000000000000000de");
+
+/// Creates `CallInputs` that delegatecall the precompile from the [MOCK_DELEGATOR], with the
+/// ABI-encoded arguments.
+pub fn delegatecall_inputs(precompile: Address, selector: u32, args: &[AbiArg]) -> CallInputs {
+    CallInputs {
+        input: encode_call(selector, args),
+        gas_limit: u64::MAX,
+        bytecode_address: precompile,
+        target_address: MOCK_DELEGATOR,
+        caller: super::MOCK_CALLER,
+        caller_bytecode_address: MOCK_DELEGATOR,
+        values: CallValues::Apparent(TokenTransfers::new()),
+        scheme: CallScheme::DelegateCall,
+        is_eof: false,
+        is_static: false,
+        return_memory_offset: 0..0,
+    }
+}
+
+/// Creates `CallInputs` that call the precompile from the caller, with the ABI-encoded
+/// arguments.
+pub fn call_inputs(
+    precompile: Address,
+    caller: Address,
+    selector: u32,
+    args: &[AbiArg],
+) -> CallInputs {
+    CallInputs {
+        input: encode_call(selector, args),
+        gas_limit: u64::MAX,
+        bytecode_address: precompile,
+        target_address: precompile,
+        caller,
+        caller_bytecode_address: caller,
+        values: CallValues::Transfer(TokenTransfers::new()),
+        scheme: CallScheme::Call,
+        is_eof: false,
+        is_static: false,
+        return_memory_offset: 0..0,
+    }
+}
+
+/// Returns the info of a contract with the balances.
+pub fn contract_account(balances: TokenBalances) -> AccountInfo {
+    // a contract that stops
+    let code = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+    AccountInfo::new(balances, 1, code.hash_slow(), code)
+}
+
+/// Creates a context with a cache db backend holding the accounts and supporting the token IDs.
+///
+/// The [MOCK_DELEGATOR] is a [contract](contract_account) without balances unless it is one of
+/// the accounts.
+pub fn create_precompile_context(
+    accounts: impl IntoIterator<Item = (Address, AccountInfo)>,
+    token_ids: impl IntoIterator<Item = U256>,
+) -> InnerEvmContext<CacheDB<EmptyDB>> {
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(MOCK_DELEGATOR, contract_account(TokenBalances::new()));
+    for (address, info) in accounts {
+        db.insert_account_info(address, info);
+    }
+    for token_id in token_ids {
+        if !db.token_ids.contains(&token_id) {
+            db.token_ids.push(token_id);
+        }
+    }
+    create_cache_db_evm_context(Box::<Env>::default(), db).inner
+}
+
+/// Returns the result of the precompile, panicking if it failed or made a call.
+#[track_caller]
+pub fn expect_result(result: PrecompileResult) -> ResultInfo {
+    match result {
+        Ok(ResultOrNewCall::Result(info)) => info,
+        Ok(ResultOrNewCall::Call(call)) => {
+            panic!("expected a result, the precompile made {call:?}")
+        }
+        Err(error) => panic!("expected a result, the precompile failed with {error:?}"),
+    }
+}
+
+/// Returns the call made by the precompile, panicking if it failed or returned a result.
+#[track_caller]
+pub fn expect_new_call(result: PrecompileResult) -> PrimitiveCallInfo {
+    match result {
+        Ok(ResultOrNewCall::Call(call)) => call,
+        Ok(ResultOrNewCall::Result(info)) => {
+            panic!("expected a call, the precompile returned {info:?}")
+        }
+        Err(error) => panic!("expected a call, the precompile failed with {error:?}"),
+    }
+}